@@ -0,0 +1,44 @@
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! `cargo bench --bench key_aggregation`, prints the time per key of aggregating committees of
+//! growing size with the key list hashed once, which stays about the same as aggregation is then
+//! linear in the number of keys.
+//! With `--features parallel` the coefficients are hashed and the key summed on all cores.
+
+extern crate multi_party_eddsa;
+
+use std::time::Instant;
+
+use multi_party_eddsa::protocols::aggsig::AggregatedKey;
+use multi_party_eddsa::protocols::ExpandedKeyPair;
+
+const ITERATIONS: u32 = 10;
+
+fn main() {
+    for &n in &[100usize, 200, 400, 800] {
+        let pks: Vec<_> = (0..n)
+            .map(|i| {
+                let mut secret = [0u8; 32];
+                secret[..8].copy_from_slice(&(i as u64).to_le_bytes());
+                ExpandedKeyPair::create_from_private_key(secret).public_key
+            })
+            .collect();
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            AggregatedKey::new_hashed_key_list(&pks);
+        }
+        let per_key = start.elapsed() / ITERATIONS / n as u32;
+        println!("key aggregation n = {:4}: {:?} per key", n, per_key);
+    }
+}
@@ -0,0 +1,185 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! `cargo bench --bench signing`, prints the time per call of the hot paths of aggsig signing:
+//! key aggregation for committees of growing size, nonce generation, partial signing, combining
+//! the partial signatures and verification. `BENCH_ITERATIONS` overrides the number of calls
+//! timed per line, e.g. to compare two builds more precisely.
+
+extern crate multi_party_eddsa;
+
+use std::env;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use multi_party_eddsa::protocols::aggsig::{self, AggregatedKey, KeyAgg};
+use multi_party_eddsa::protocols::{musig2, ExpandedKeyPair, Signature};
+
+const DEFAULT_ITERATIONS: u32 = 100;
+const MESSAGE: &[u8] = b"benchmark";
+
+fn iterations() -> u32 {
+    env::var("BENCH_ITERATIONS")
+        .ok()
+        .and_then(|iterations| iterations.parse().ok())
+        .filter(|iterations| *iterations > 0)
+        .unwrap_or(DEFAULT_ITERATIONS)
+}
+
+fn report(name: &str, elapsed: Duration, iterations: u32) {
+    println!("{:40} {:?} per call", name, elapsed / iterations);
+}
+
+// times `iterations` calls of `f`, after one warm up call
+fn bench<T>(name: &str, iterations: u32, mut f: impl FnMut() -> T) {
+    black_box(f());
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(f());
+    }
+    report(name, start.elapsed(), iterations);
+}
+
+fn keypairs(n: usize) -> Vec<ExpandedKeyPair> {
+    (0..n)
+        .map(|i| {
+            let mut secret = [0u8; 32];
+            secret[..8].copy_from_slice(&(i as u64).to_le_bytes());
+            ExpandedKeyPair::create_from_private_key(secret)
+        })
+        .collect()
+}
+
+fn main() {
+    let iterations = iterations();
+
+    for &n in &[2usize, 3, 5, 10, 20, 50, 100] {
+        let pks: Vec<_> = keypairs(n).into_iter().map(|k| k.public_key).collect();
+        bench(&format!("key_aggregation_n n = {}", n), iterations, || {
+            KeyAgg::key_aggregation_n(&pks, 0)
+        });
+    }
+
+    let keys = keypairs(3);
+    let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+    let key_agg = KeyAgg::key_aggregation_n(&pks, 0);
+    bench("aggsig nonce and commitment", iterations, || {
+        aggsig::create_ephemeral_key_and_commit(&keys[0], MESSAGE).unwrap()
+    });
+    bench("musig2 nonces", iterations, || {
+        musig2::generate_partial_nonces(&keys[0], Some(MESSAGE))
+    });
+
+    // the nonces are consumed by signing, so they're made before the clock starts
+    let (ephemeral_keys, reveals): (Vec<_>, Vec<_>) = (0..iterations)
+        .map(|_| {
+            let (ephemeral_key, _, reveal) =
+                aggsig::create_ephemeral_key_and_commit(&keys[0], MESSAGE).unwrap();
+            (ephemeral_key, reveal)
+        })
+        .unzip();
+    let R_tot = aggsig::get_R_tot(reveals.iter().take(3).map(|reveal| &reveal.R)).unwrap();
+    let start = Instant::now();
+    for ephemeral_key in ephemeral_keys {
+        black_box(aggsig::partial_sign(
+            ephemeral_key,
+            &keys[0],
+            &key_agg.hash,
+            &R_tot,
+            &key_agg.apk,
+            MESSAGE,
+        ));
+    }
+    report("partial_sign", start.elapsed(), iterations);
+
+    // a complete 3 party signature for the rest
+    let (ephemeral_keys, reveals): (Vec<_>, Vec<_>) = keys
+        .iter()
+        .map(|keys| {
+            let (ephemeral_key, _, reveal) =
+                aggsig::create_ephemeral_key_and_commit(keys, MESSAGE).unwrap();
+            (ephemeral_key, reveal)
+        })
+        .unzip();
+    let R_tot = aggsig::get_R_tot(reveals.iter().map(|reveal| &reveal.R)).unwrap();
+    let partial_sigs: Vec<_> = ephemeral_keys
+        .into_iter()
+        .zip(&keys)
+        .enumerate()
+        .map(|(i, (ephemeral_key, keys))| {
+            let key_agg = KeyAgg::key_aggregation_n(&pks, i);
+            aggsig::partial_sign(
+                ephemeral_key,
+                keys,
+                &key_agg.hash,
+                &R_tot,
+                &key_agg.apk,
+                MESSAGE,
+            )
+        })
+        .collect();
+    bench("verify_partial_sig", iterations, || {
+        aggsig::verify_partial_sig(
+            &partial_sigs[0],
+            MESSAGE,
+            &key_agg.hash,
+            &reveals[0].R,
+            &pks[0],
+            &key_agg.apk,
+        )
+        .unwrap()
+    });
+    let aggregated_key = AggregatedKey::new(&pks);
+    let prepared = aggregated_key.prepare(&pks).unwrap();
+    bench("PreparedApk::verify_partial_sig", iterations, || {
+        prepared
+            .verify_partial_sig(0, &partial_sigs[0], MESSAGE, &reveals[0].R)
+            .unwrap()
+    });
+    for &n in &[3usize, 10, 100] {
+        // the same parts over and over, adding them costs the same
+        let parts: Vec<_> = partial_sigs.iter().cycle().take(n).cloned().collect();
+        bench(
+            &format!("add_signature_parts n = {}", n),
+            iterations,
+            || aggsig::add_signature_parts(&parts).unwrap(),
+        );
+    }
+
+    let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+    let bytes = signature.to_bytes();
+    bench("Signature::verify", iterations, || {
+        signature.verify(MESSAGE, &key_agg.apk).unwrap()
+    });
+    bench("Signature::verify_prepared", iterations, || {
+        signature.verify_prepared(MESSAGE, prepared.apk()).unwrap()
+    });
+    bench("Signature::from_bytes and verify", iterations, || {
+        Signature::from_bytes(&bytes)
+            .unwrap()
+            .verify(MESSAGE, &key_agg.apk)
+            .unwrap()
+    });
+    let signatures = vec![signature.clone(); 64];
+    let messages = vec![MESSAGE; 64];
+    let apks = vec![key_agg.apk.clone(); 64];
+    let start = Instant::now();
+    for _ in 0..iterations {
+        Signature::batch_verify(&messages, &signatures, &apks).unwrap();
+    }
+    report(
+        "batch_verify, per signature",
+        start.elapsed() / 64,
+        iterations,
+    );
+}
@@ -0,0 +1,68 @@
+#![no_main]
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! Untrusted partial signatures into `add_signature_parts` and `verify_partial_sig`.
+//!
+//! The input is the number of partial signatures (one byte), their 64 byte encodings, the
+//! coefficient `a`, the signer's `R` and public key and the aggregated key (32 bytes each decoded
+//! as leniently as curv does) and the message.
+
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use libfuzzer_sys::fuzz_target;
+use multi_party_eddsa::protocols::aggsig::{add_signature_parts, verify_partial_sig};
+use multi_party_eddsa::protocols::Signature;
+
+fuzz_target!(|data: &[u8]| {
+    let (n, data) = match data.split_first() {
+        Some((n, data)) => (usize::from(n % 8) + 1, data),
+        None => return,
+    };
+    if data.len() < n * 64 + 4 * 32 {
+        return;
+    }
+    let (sigs, data) = data.split_at(n * 64);
+    let sigs: Vec<_> = match sigs.chunks(64).map(Signature::from_bytes).collect() {
+        Ok(sigs) => sigs,
+        Err(_) => return,
+    };
+    let (a, data) = data.split_at(32);
+    let (partial_R, data) = data.split_at(32);
+    let (partial_public_key, data) = data.split_at(32);
+    let (agg_pubkey, message) = data.split_at(32);
+
+    if let Ok(signature) = add_signature_parts(&sigs) {
+        assert_eq!(signature.R, sigs[0].R);
+    }
+    let (a, partial_R, partial_public_key, agg_pubkey) = match (
+        Scalar::<Ed25519>::from_bytes(a),
+        Point::<Ed25519>::from_bytes(partial_R),
+        Point::<Ed25519>::from_bytes(partial_public_key),
+        Point::<Ed25519>::from_bytes(agg_pubkey),
+    ) {
+        (Ok(a), Ok(partial_R), Ok(partial_public_key), Ok(agg_pubkey)) => {
+            (a, partial_R, partial_public_key, agg_pubkey)
+        }
+        _ => return,
+    };
+    for sig in &sigs {
+        let _ = verify_partial_sig(
+            sig,
+            message,
+            &a,
+            &partial_R,
+            &partial_public_key,
+            &agg_pubkey,
+        );
+    }
+});
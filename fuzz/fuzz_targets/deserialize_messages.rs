@@ -0,0 +1,59 @@
+#![no_main]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! Untrusted bytes into the JSON and the binary decoding of the aggsig round messages and of
+//! signatures. Anything that decodes has to encode again and decode to the same value.
+
+use std::convert::TryFrom;
+
+use libfuzzer_sys::fuzz_target;
+use multi_party_eddsa::protocols::aggsig::{
+    verify_commitment, SignFirstMsg, SignSecondMsg, SIGN_FIRST_MSG_SIZE, SIGN_SECOND_MSG_SIZE,
+};
+use multi_party_eddsa::protocols::Signature;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(msg) = serde_json::from_slice::<SignFirstMsg>(data) {
+        // a commitment of more than 64 bytes is an error, not a panic
+        if let Ok(bytes) = msg.to_bytes() {
+            assert_eq!(SignFirstMsg::from_bytes(&bytes), msg);
+        }
+    }
+    if let Ok(msg) = serde_json::from_slice::<SignSecondMsg>(data) {
+        if let Ok(bytes) = msg.to_bytes() {
+            assert_eq!(SignSecondMsg::from_bytes(&bytes).unwrap(), msg);
+        }
+        let _ = verify_commitment(
+            &SignFirstMsg {
+                commitment: msg.blind_factor.clone(),
+            },
+            &msg,
+        );
+    }
+    if let Ok(signature) = serde_json::from_slice::<Signature>(data) {
+        let _ = signature.to_bytes();
+    }
+
+    if let Ok(bytes) = <[u8; SIGN_FIRST_MSG_SIZE]>::try_from(data) {
+        let msg = SignFirstMsg::from_bytes(&bytes);
+        assert_eq!(msg.to_bytes().unwrap(), bytes);
+    }
+    if let Ok(bytes) = <[u8; SIGN_SECOND_MSG_SIZE]>::try_from(data) {
+        if let Ok(msg) = SignSecondMsg::from_bytes(&bytes) {
+            assert_eq!(msg.to_bytes().unwrap(), bytes);
+        }
+    }
+    if let Ok(signature) = Signature::from_bytes(data) {
+        assert_eq!(&signature.to_bytes()[..], data);
+    }
+});
@@ -0,0 +1,169 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! `arbitrary::Arbitrary` for keys, the aggsig messages and signatures, so fuzz targets of code
+//! built on this crate (e.g. an orchestrator routing `Msg<AggSigMessage>`) can take them as input.
+//!
+//! The values are what a deserializer can hand out, not what an honest party sends: a point is
+//! the identity once in 16 times, scalars can be unreduced and commitments and blind factors
+//! longer than their 64 and 32 byte encodings. So most of the messages and signatures don't
+//! verify, see `multi_party_eddsa::proptest` for strategies of messages that do.
+
+use curv::arithmetic::Converter;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use curv::BigInt;
+
+use arbitrary_crate::{Arbitrary, Result, Unstructured};
+
+use protocols::aggsig::{AggSigMessage, SignFirstMsg, SignSecondMsg};
+use protocols::rounds::Msg;
+use protocols::{ExpandedKeyPair, Signature};
+
+fn scalar(u: &mut Unstructured) -> Result<Scalar<Ed25519>> {
+    let bytes: [u8; 32] = u.arbitrary()?;
+    if u.int_in_range(0..=15u8)? == 0 {
+        // unreduced, as `Scalar::from_bytes` of received bytes can be
+        if let Ok(scalar) = Scalar::from_bytes(&bytes) {
+            return Ok(scalar);
+        }
+    }
+    Ok(Scalar::from_bigint(&BigInt::from_bytes(&bytes)))
+}
+
+fn point(u: &mut Unstructured) -> Result<Point<Ed25519>> {
+    if u.int_in_range(0..=15u8)? == 0 {
+        return Ok(Point::zero());
+    }
+    Ok(Point::generator() * scalar(u)?)
+}
+
+// `BigInt` of up to `max_len` big endian bytes, fewer if the input runs out
+fn big_int(u: &mut Unstructured, max_len: usize) -> Result<BigInt> {
+    let len = u.int_in_range(0..=max_len.min(u.len()))?;
+    Ok(BigInt::from_bytes(u.bytes(len)?))
+}
+
+impl<'a> Arbitrary<'a> for ExpandedKeyPair {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(ExpandedKeyPair::create_from_private_key(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Signature {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Signature {
+            R: point(u)?,
+            s: scalar(u)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for SignFirstMsg {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(SignFirstMsg {
+            commitment: big_int(u, 65)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for SignSecondMsg {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(SignSecondMsg {
+            R: point(u)?,
+            blind_factor: big_int(u, 33)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for AggSigMessage {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=2u8)? {
+            0 => AggSigMessage::Commitment(u.arbitrary()?),
+            1 => AggSigMessage::Reveal(u.arbitrary()?),
+            _ => AggSigMessage::PartialSignature(u.arbitrary()?),
+        })
+    }
+}
+
+impl<'a, B: Arbitrary<'a>> Arbitrary<'a> for Msg<B> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Msg {
+            sender: u.arbitrary()?,
+            receiver: u.arbitrary()?,
+            body: u.arbitrary()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary_crate::Unstructured;
+    use rand::Rng;
+    use serde_json;
+
+    use protocols::aggsig::{self, AggSigMessage, SignSecondMsg};
+    use protocols::rounds::Msg;
+    use protocols::tests::deterministic_fast_rand;
+    use protocols::{ExpandedKeyPair, Signature};
+    use Error;
+
+    #[test]
+    fn test_arbitrary_messages() {
+        let mut rng = deterministic_fast_rand("test_arbitrary_messages", None);
+        let (mut identities, mut variants) = (0, [false; 3]);
+        for _ in 0..200 {
+            let bytes: Vec<u8> = (0..512).map(|_| rng.gen()).collect();
+            let mut u = Unstructured::new(&bytes);
+            let keys: ExpandedKeyPair = u.arbitrary().unwrap();
+            let signature: Signature = u.arbitrary().unwrap();
+            let reveal: SignSecondMsg = u.arbitrary().unwrap();
+            let msg: Msg<AggSigMessage> = u.arbitrary().unwrap();
+
+            let json = serde_json::to_string(&signature).unwrap();
+            assert_eq!(serde_json::from_str::<Signature>(&json).unwrap(), signature);
+            let json = serde_json::to_string(&msg).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Msg<AggSigMessage>>(&json).unwrap(),
+                msg
+            );
+            assert!(signature.verify(b"arbitrary", &keys.public_key).is_err());
+            if reveal.R.is_zero() {
+                identities += 1;
+                let (_, first_msg, _) =
+                    aggsig::create_ephemeral_key_and_commit_rng(&keys, b"arbitrary", &mut rng)
+                        .unwrap();
+                assert_eq!(
+                    aggsig::verify_commitment(&first_msg, &reveal),
+                    Err(Error::InvalidPoint)
+                );
+            }
+            variants[match msg.body {
+                AggSigMessage::Commitment(_) => 0,
+                AggSigMessage::Reveal(_) => 1,
+                AggSigMessage::PartialSignature(_) => 2,
+            }] = true;
+        }
+        assert!(identities > 0);
+        assert_eq!(variants, [true; 3]);
+
+        // too few bytes give zeros or an error, never a panic
+        for len in 0..64 {
+            let bytes = vec![0xff; len];
+            let _ = Unstructured::new(&bytes).arbitrary::<Msg<AggSigMessage>>();
+        }
+    }
+}
@@ -0,0 +1,77 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! RFC4648 base32 without padding, as in Stellar strkeys and onion addresses. Both are a whole
+//! number of 5 byte groups, so there is never any padding to leave out.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+pub fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = buffer << 8 | u16::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(char::from(ALPHABET[usize::from(buffer >> bits & 0x1f)]));
+        }
+    }
+    if bits > 0 {
+        out.push(char::from(
+            ALPHABET[usize::from(buffer << (5 - bits) & 0x1f)],
+        ));
+    }
+    out
+}
+
+pub fn decode(string: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(string.len() * 5 / 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for c in string.bytes() {
+        let value = ALPHABET.iter().position(|a| *a == c)? as u16;
+        buffer = buffer << 5 | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    // only zero padding bits, so every string has a single decoding
+    if buffer & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base32() {
+        assert_eq!(encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(decode("MZXW6YTBOI").unwrap(), b"foobar");
+        assert_eq!(decode("MZXW6YTBOJ"), None);
+        assert_eq!(decode("mzxw6ytboi"), None);
+    }
+}
@@ -0,0 +1,87 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Bitcoin's base58, Solana and NEAR encode keys and signatures with it and multibase's `z`
+//! prefix stands for it.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+pub fn encode(bytes: &[u8]) -> String {
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    // base 58 digits, least significant first
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+    for byte in &bytes[zeros..] {
+        let mut carry = u32::from(*byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    core::iter::repeat_n('1', zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|d| char::from(ALPHABET[usize::from(*d)])),
+        )
+        .collect()
+}
+
+pub fn decode(string: &str) -> Option<Vec<u8>> {
+    let zeros = string.bytes().take_while(|c| *c == b'1').count();
+    // base 256 digits, least significant first
+    let mut bytes: Vec<u8> = Vec::with_capacity(string.len());
+    for c in string.bytes().skip(zeros) {
+        let mut carry = ALPHABET.iter().position(|a| *a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    bytes.extend(core::iter::repeat_n(0, zeros));
+    bytes.reverse();
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base58() {
+        assert_eq!(encode(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(decode("StV1DL6CwTryKyV").unwrap(), b"hello world");
+        // the system program
+        assert_eq!(encode(&[0; 32]), "11111111111111111111111111111111");
+        assert_eq!(decode("11111111111111111111111111111111").unwrap(), [0; 32]);
+        assert_eq!(encode(&[]), "");
+        assert_eq!(decode("0OIl"), None);
+    }
+}
@@ -0,0 +1,257 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! `multi-party-eddsa-rpc`, the `multi_party_eddsa::rpc` co-signer served over HTTP with
+//! tiny_http and over WebSocket with tungstenite, built with `--features rpc`.
+//!
+//! ```text
+//! MPE_RPC_TOKEN=<token> multi-party-eddsa-rpc 127.0.0.1:8545 https://wallet.example
+//! ```
+//!
+//! JSON-RPC requests are POSTed, or sent one per text message after a WebSocket upgrade of the
+//! same address. Anyone who can reach the server can sign with its keys, so every request needs
+//! `Authorization: Bearer <token>`, and WebSocket upgrades from browsers one of the allowed
+//! origins, so a web page can't drive the co-signer with the browser's credentials. Only listen
+//! on localhost or behind a TLS proxy, the token is sent in the clear otherwise.
+//!
+//! Requests are served by `WORKERS` threads, a WebSocket connection keeps one of them busy for as
+//! long as it's open.
+
+extern crate multi_party_eddsa;
+extern crate tiny_http;
+extern crate tungstenite;
+
+use std::env;
+use std::io::Read;
+use std::process;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use multi_party_eddsa::rpc::CoSigner;
+use tiny_http::{Header, Method, ReadWrite, Request, Response, ResponseBox, Server, StatusCode};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::frame::coding::CloseCode;
+use tungstenite::protocol::{CloseFrame, Role, WebSocketConfig};
+use tungstenite::{Message, WebSocket};
+
+const USAGE: &str =
+    "usage: MPE_RPC_TOKEN=<token> multi-party-eddsa-rpc <address> [<allowed-origin>...]";
+
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+const WORKERS: usize = 8;
+
+// who may send requests
+struct Access {
+    token: String,
+    // the `Origin`s WebSocket upgrades are accepted from
+    allowed_origins: Vec<String>,
+}
+
+impl Access {
+    // `Authorization: Bearer <token>`, compared in constant time
+    fn is_authorized(&self, authorization: Option<&str>) -> bool {
+        match authorization.and_then(|authorization| authorization.split_once(' ')) {
+            Some((scheme, credentials)) if scheme.eq_ignore_ascii_case("bearer") => {
+                eq_constant_time(credentials.trim().as_bytes(), self.token.as_bytes())
+            }
+            _ => false,
+        }
+    }
+
+    // browsers always send an `Origin`, other clients only need the token
+    fn is_allowed_origin(&self, origin: Option<&str>) -> bool {
+        match origin {
+            Some(origin) => self.allowed_origins.iter().any(|allowed| allowed == origin),
+            None => true,
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let token = env::var("MPE_RPC_TOKEN").unwrap_or_default();
+    if args.is_empty() || token.is_empty() {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+    let server = Arc::new(Server::http(&args[0]).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }));
+    let access = Arc::new(Access {
+        token,
+        allowed_origins: args[1..].to_vec(),
+    });
+    let cosigner = Arc::new(Mutex::new(CoSigner::new()));
+    let workers: Vec<_> = (0..WORKERS)
+        .map(|_| {
+            let (server, access, cosigner) = (server.clone(), access.clone(), cosigner.clone());
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    serve(request, &access, &cosigner);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
+
+fn serve(mut request: Request, access: &Access, cosigner: &Mutex<CoSigner>) {
+    if !access.is_authorized(header(&request, "Authorization")) {
+        let response = empty(401).with_header(header_line("WWW-Authenticate: Bearer"));
+        let _ = request.respond(response);
+        return;
+    }
+    if let Some(key) = websocket_key(&request) {
+        if !access.is_allowed_origin(header(&request, "Origin")) {
+            let _ = request.respond(empty(403));
+            return;
+        }
+        // `upgrade` adds the `Upgrade` and `Connection` headers
+        let response = empty(101).with_header(header_line(&format!(
+            "Sec-WebSocket-Accept: {}",
+            derive_accept_key(key.as_bytes())
+        )));
+        let stream = request.upgrade("websocket", response);
+        return serve_websocket(stream, cosigner);
+    }
+    if *request.method() != Method::Post {
+        let _ = request.respond(empty(405));
+        return;
+    }
+    let mut body = String::new();
+    let read = request
+        .as_reader()
+        .take(MAX_BODY_SIZE as u64 + 1)
+        .read_to_string(&mut body);
+    let response = if read.is_err() {
+        empty(400)
+    } else if body.len() > MAX_BODY_SIZE {
+        empty(413)
+    } else {
+        match lock(cosigner).handle(&body) {
+            Some(response) => Response::from_string(response)
+                .with_header(header_line("Content-Type: application/json"))
+                .boxed(),
+            // only notifications
+            None => empty(204),
+        }
+    };
+    let _ = request.respond(response);
+}
+
+fn serve_websocket(stream: Box<dyn ReadWrite + Send>, cosigner: &Mutex<CoSigner>) {
+    let config = WebSocketConfig {
+        max_message_size: Some(MAX_BODY_SIZE),
+        max_frame_size: Some(MAX_BODY_SIZE),
+        ..WebSocketConfig::default()
+    };
+    let mut websocket = WebSocket::from_raw_socket(stream, Role::Server, Some(config));
+    loop {
+        // tungstenite answers pings and closes itself
+        let request = match websocket.read() {
+            Ok(Message::Text(request)) => request,
+            Ok(Message::Binary(_)) => {
+                let _ = websocket.close(Some(CloseFrame {
+                    code: CloseCode::Unsupported,
+                    reason: "JSON-RPC requests are text messages".into(),
+                }));
+                return;
+            }
+            Ok(_) => continue,
+            Err(_) => return,
+        };
+        let response = lock(cosigner).handle(&request);
+        if let Some(response) = response {
+            if websocket.send(Message::Text(response)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+// the `Sec-WebSocket-Key` of an upgrade request
+fn websocket_key(request: &Request) -> Option<String> {
+    let upgrade = header(request, "Upgrade")?;
+    if *request.method() != Method::Get || !upgrade.eq_ignore_ascii_case("websocket") {
+        return None;
+    }
+    header(request, "Sec-WebSocket-Key").map(String::from)
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+fn header_line(line: &str) -> Header {
+    line.parse().expect("a valid header line")
+}
+
+fn empty(status: u16) -> ResponseBox {
+    Response::empty(StatusCode(status)).boxed()
+}
+
+fn lock(cosigner: &Mutex<CoSigner>) -> MutexGuard<'_, CoSigner> {
+    cosigner.lock().expect("a request panicked")
+}
+
+// only leaks the length of `a`
+fn eq_constant_time(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn access() -> Access {
+        Access {
+            token: String::from("secret"),
+            allowed_origins: vec![String::from("https://wallet.example")],
+        }
+    }
+
+    #[test]
+    fn test_authorization() {
+        let access = access();
+        for authorization in &[
+            None,
+            Some("Bearer other"),
+            Some("Basic secret"),
+            Some("Bearer secret2"),
+        ] {
+            assert!(!access.is_authorized(*authorization));
+        }
+        assert!(access.is_authorized(Some("Bearer secret")));
+        assert!(access.is_authorized(Some("bearer  secret")));
+    }
+
+    #[test]
+    fn test_websocket_origin() {
+        let access = access();
+        assert!(!access.is_allowed_origin(Some("https://evil.example")));
+        assert!(access.is_allowed_origin(Some("https://wallet.example")));
+        assert!(access.is_allowed_origin(None));
+    }
+}
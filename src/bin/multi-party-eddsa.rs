@@ -0,0 +1,379 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! `multi-party-eddsa`, a command line co-signer for aggsig, built with `--features cli`.
+//!
+//! Every subcommand reads and writes files: keys and signatures as the serde JSON of the crate's
+//! types and the round messages as `wire::Envelope`s, so they can be passed around by hand or
+//! by a script to run or debug a signing session. A session of n signers is
+//!
+//! ```text
+//! multi-party-eddsa keygen keys.json pk.json                       # every signer
+//! multi-party-eddsa agg-keys <index> key_agg.json pk_1.json ... pk_n.json
+//! multi-party-eddsa sign-round1 keys.json msg state.json commitment.json
+//! multi-party-eddsa sign-round2 state.json reveal.json             # after all commitments
+//! multi-party-eddsa sign-round3 keys.json key_agg.json msg state.json partial.json \
+//!     commitment_1.json reveal_1.json ... commitment_n.json reveal_n.json
+//! multi-party-eddsa aggregate signature.json partial_1.json ... partial_n.json
+//! multi-party-eddsa verify key_agg.json msg signature.json
+//! ```
+//!
+//! The signers' files are given in the same order everywhere, `<index>` is the signer's place
+//! in it. `state.json` holds the secret nonce and is deleted by `sign-round3`, so it can't be
+//! used for a second signature.
+
+extern crate multi_party_eddsa;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate curv;
+extern crate hex;
+extern crate serde_json;
+extern crate sha2;
+
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::process;
+
+use curv::elliptic::curves::{Ed25519, Point};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha512};
+
+use multi_party_eddsa::protocols::aggsig::{self, EphemeralKey, KeyAgg, SignSecondMsg};
+use multi_party_eddsa::protocols::wire::{Envelope, Message};
+use multi_party_eddsa::protocols::{ExpandedKeyPair, Signature};
+
+const USAGE: &str = "usage:
+    multi-party-eddsa keygen <keys-out> <public-key-out>
+    multi-party-eddsa agg-keys <party-index> <key-agg-out> <public-key>...
+    multi-party-eddsa sign-round1 <keys> <message> <state-out> <commitment-out>
+    multi-party-eddsa sign-round2 <state> <reveal-out>
+    multi-party-eddsa sign-round3 <keys> <key-agg> <message> <state> <partial-signature-out> \
+(<commitment> <reveal>)...
+    multi-party-eddsa aggregate <signature-out> <partial-signature>...
+    multi-party-eddsa verify <key-agg or public-key> <message> <signature>";
+
+// the secret state between the rounds of one signer
+#[derive(Serialize, Deserialize)]
+struct SigningState {
+    ephemeral_key: EphemeralKey,
+    sign_second_msg: SignSecondMsg,
+    // the SHA-512 of the message round 1 committed to, round 3 has to sign the same one
+    message_hash: String,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(output) => println!("{}", output),
+        Err(CliError::Usage) => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+        Err(CliError::Failed(error)) => {
+            eprintln!("multi-party-eddsa: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum CliError {
+    Usage,
+    Failed(String),
+}
+
+fn failed(context: &str, error: impl Display) -> CliError {
+    CliError::Failed(format!("{}: {}", context, error))
+}
+
+// runs a subcommand and returns what it prints
+fn run(args: &[String]) -> Result<String, CliError> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args[..] {
+        ["keygen", keys_out, public_key_out] => {
+            let keys = ExpandedKeyPair::create();
+            write_json(keys_out, &keys)?;
+            write_json(public_key_out, &keys.public_key)?;
+            to_json(&keys.public_key)
+        }
+        ["agg-keys", party_index, key_agg_out, ref public_keys @ ..] if !public_keys.is_empty() => {
+            let party_index: usize = party_index.parse().map_err(|e| failed("party index", e))?;
+            if party_index >= public_keys.len() {
+                return Err(CliError::Failed(format!(
+                    "party index {} out of range for {} keys",
+                    party_index,
+                    public_keys.len()
+                )));
+            }
+            let public_keys = public_keys
+                .iter()
+                .map(|path| read_json(path))
+                .collect::<Result<Vec<Point<Ed25519>>, _>>()?;
+            let key_agg = KeyAgg::key_aggregation_n(&public_keys, party_index);
+            write_json(key_agg_out, &key_agg)?;
+            to_json(&key_agg.apk)
+        }
+        ["sign-round1", keys, message, state_out, commitment_out] => {
+            let keys: ExpandedKeyPair = read_json(keys)?;
+            let message = read(message)?;
+            let (ephemeral_key, sign_first_msg, sign_second_msg) =
+                aggsig::create_ephemeral_key_and_commit(&keys, &message)
+                    .map_err(|e| failed("round 1", e))?;
+            write_json(
+                state_out,
+                &SigningState {
+                    ephemeral_key,
+                    sign_second_msg,
+                    message_hash: hex::encode(Sha512::digest(&message)),
+                },
+            )?;
+            write_json(
+                commitment_out,
+                &Envelope::new(Message::AggSigFirst(sign_first_msg)),
+            )?;
+            Ok(format!("wrote {}", commitment_out))
+        }
+        ["sign-round2", state, reveal_out] => {
+            let state: SigningState = read_json(state)?;
+            write_json(
+                reveal_out,
+                &Envelope::new(Message::AggSigSecond(state.sign_second_msg)),
+            )?;
+            Ok(format!("wrote {}", reveal_out))
+        }
+        ["sign-round3", keys, key_agg, message, state_path, partial_out, ref messages @ ..]
+            if !messages.is_empty() && messages.len() % 2 == 0 =>
+        {
+            let keys: ExpandedKeyPair = read_json(keys)?;
+            let key_agg: KeyAgg = read_json(key_agg)?;
+            let message = read(message)?;
+            let state: SigningState = read_json(state_path)?;
+            if hex::encode(Sha512::digest(&message)) != state.message_hash {
+                return Err(CliError::Failed(String::from(
+                    "the message isn't the one of round 1",
+                )));
+            }
+            let mut Rs = Vec::new();
+            for (i, pair) in messages.chunks(2).enumerate() {
+                let commitment = match read_envelope(pair[0])? {
+                    Message::AggSigFirst(commitment) => commitment,
+                    _ => return Err(CliError::Failed(format!("{} isn't a commitment", pair[0]))),
+                };
+                let reveal = match read_envelope(pair[1])? {
+                    Message::AggSigSecond(reveal) => reveal,
+                    _ => return Err(CliError::Failed(format!("{} isn't a reveal", pair[1]))),
+                };
+                aggsig::verify_commitment(&commitment, &reveal)
+                    .map_err(|e| failed(&format!("signer {}", i + 1), e))?;
+                Rs.push(reveal.R);
+            }
+            if !Rs.contains(&state.sign_second_msg.R) {
+                return Err(CliError::Failed(String::from(
+                    "our own reveal isn't among the signers'",
+                )));
+            }
+            let R_tot = aggsig::get_R_tot(&Rs)
+                .ok_or_else(|| CliError::Failed(String::from("the nonces sum to the identity")))?;
+            // the nonce is used, remove it before anything is signed with it
+            fs::remove_file(state_path).map_err(|e| failed(state_path, e))?;
+            let partial_sig = aggsig::partial_sign(
+                state.ephemeral_key,
+                &keys,
+                &key_agg.hash,
+                &R_tot,
+                &key_agg.apk,
+                &message,
+            );
+            write_json(
+                partial_out,
+                &Envelope::new(Message::AggSigPartialSignature(partial_sig)),
+            )?;
+            Ok(format!("wrote {}", partial_out))
+        }
+        ["aggregate", signature_out, ref partial_sigs @ ..] if !partial_sigs.is_empty() => {
+            let partial_sigs = partial_sigs
+                .iter()
+                .map(|path| match read_envelope(path)? {
+                    Message::AggSigPartialSignature(partial_sig) => Ok(partial_sig),
+                    _ => Err(CliError::Failed(format!(
+                        "{} isn't a partial signature",
+                        path
+                    ))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let signature =
+                aggsig::add_signature_parts(&partial_sigs).map_err(|e| failed("aggregate", e))?;
+            write_json(signature_out, &signature)?;
+            Ok(hex::encode(&signature.to_bytes()[..]))
+        }
+        ["verify", public_key, message, signature] => {
+            let public_key = read_public_key(public_key)?;
+            let message = read(message)?;
+            let signature: Signature = read_json(signature)?;
+            signature
+                .verify(&message, &public_key)
+                .map_err(|e| failed("verify", e))?;
+            Ok(String::from("signature ok"))
+        }
+        _ => Err(CliError::Usage),
+    }
+}
+
+// a key aggregation file or a public key file
+fn read_public_key(path: &str) -> Result<Point<Ed25519>, CliError> {
+    let value: serde_json::Value = read_json(path)?;
+    if let Ok(key_agg) = serde_json::from_value::<KeyAgg>(value.clone()) {
+        return Ok(key_agg.apk);
+    }
+    serde_json::from_value(value).map_err(|e| failed(path, e))
+}
+
+fn read_envelope(path: &str) -> Result<Message, CliError> {
+    Ok(read_json::<Envelope>(path)?.into_message())
+}
+
+fn read(path: &str) -> Result<Vec<u8>, CliError> {
+    fs::read(path).map_err(|e| failed(path, e))
+}
+
+fn read_json<T: DeserializeOwned>(path: &str) -> Result<T, CliError> {
+    serde_json::from_slice(&read(path)?).map_err(|e| failed(path, e))
+}
+
+fn write_json<T: Serialize>(path: &str, value: &T) -> Result<(), CliError> {
+    fs::write(path, to_json(value)?).map_err(|e| failed(path, e))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, CliError> {
+    serde_json::to_string(value).map_err(|e| failed("encoding", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_args(args: &[&str]) -> Result<String, CliError> {
+        run(&args
+            .iter()
+            .map(|arg| String::from(*arg))
+            .collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_signing_session() {
+        let dir = env::temp_dir().join(format!("multi-party-eddsa-cli-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        fs::write(file("msg"), b"signed from the command line").unwrap();
+        let n = 3;
+
+        for i in 0..n {
+            run_args(&[
+                "keygen",
+                &file(&format!("keys_{}", i)),
+                &file(&format!("pk_{}", i)),
+            ])
+            .unwrap();
+        }
+        let pks: Vec<_> = (0..n).map(|i| file(&format!("pk_{}", i))).collect();
+        let mut apks = Vec::new();
+        for i in 0..n {
+            let mut args = vec![
+                String::from("agg-keys"),
+                i.to_string(),
+                file(&format!("key_agg_{}", i)),
+            ];
+            args.extend(pks.iter().cloned());
+            apks.push(run(&args).unwrap());
+        }
+        assert!(apks.iter().all(|apk| *apk == apks[0]));
+
+        for i in 0..n {
+            run_args(&[
+                "sign-round1",
+                &file(&format!("keys_{}", i)),
+                &file("msg"),
+                &file(&format!("state_{}", i)),
+                &file(&format!("commitment_{}", i)),
+            ])
+            .unwrap();
+            run_args(&[
+                "sign-round2",
+                &file(&format!("state_{}", i)),
+                &file(&format!("reveal_{}", i)),
+            ])
+            .unwrap();
+        }
+        let round2: Vec<_> = (0..n)
+            .flat_map(|i| {
+                vec![
+                    file(&format!("commitment_{}", i)),
+                    file(&format!("reveal_{}", i)),
+                ]
+            })
+            .collect();
+        for i in 0..n {
+            let mut args = vec![
+                String::from("sign-round3"),
+                file(&format!("keys_{}", i)),
+                file(&format!("key_agg_{}", i)),
+                file("msg"),
+                file(&format!("state_{}", i)),
+                file(&format!("partial_{}", i)),
+            ];
+            args.extend(round2.iter().cloned());
+            // the message has to be the one of round 1
+            if i == 0 {
+                let mut wrong_message = args.clone();
+                wrong_message[3] = file("pk_0");
+                assert!(matches!(run(&wrong_message), Err(CliError::Failed(_))));
+            }
+            run(&args).unwrap();
+            // the nonce is gone
+            assert!(matches!(run(&args), Err(CliError::Failed(_))));
+        }
+
+        let mut args = vec![String::from("aggregate"), file("signature")];
+        args.extend((0..n).map(|i| file(&format!("partial_{}", i))));
+        let signature = run(&args).unwrap();
+        assert_eq!(signature.len(), 128);
+        assert_eq!(
+            run_args(&[
+                "verify",
+                &file("key_agg_0"),
+                &file("msg"),
+                &file("signature")
+            ])
+            .unwrap(),
+            "signature ok"
+        );
+        assert!(matches!(
+            run_args(&["verify", &file("pk_0"), &file("msg"), &file("signature")]),
+            Err(CliError::Failed(_))
+        ));
+        // not a partial signature
+        assert!(matches!(
+            run_args(&["aggregate", &file("signature"), &file("reveal_0")]),
+            Err(CliError::Failed(_))
+        ));
+        assert_eq!(run_args(&["sign-round2"]), Err(CliError::Usage));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,28 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Helpers for using aggregated keys and signatures on specific chains, each behind the feature
+//! of the same name.
+
+#[cfg(feature = "near")]
+pub mod near;
+#[cfg(feature = "solana")]
+pub mod solana;
+#[cfg(feature = "stellar")]
+pub mod stellar;
+#[cfg(feature = "tendermint")]
+pub mod tendermint;
@@ -0,0 +1,194 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! NEAR keys and transactions for aggregated keys.
+//!
+//! NEAR writes an Ed25519 public key as `ed25519:<base58>` and signs a transaction by signing
+//! the SHA-256 hash of its Borsh serialization. The co-signers run aggsig with
+//! `transaction_hash` as the message and `signed_transaction` wraps the transaction and the
+//! aggregated signature into the Borsh serialized `SignedTransaction` that is sent to the RPC
+//! (base64 encoded, by `broadcast_tx_commit`).
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use curv::elliptic::curves::{Ed25519, Point};
+use sha2::{Digest, Sha256};
+
+use base58;
+use protocols::{decode_point, Signature};
+use Error::{self, InvalidKey, InvalidMessage, InvalidSig};
+
+const KEY_PREFIX: &str = "ed25519:";
+// the `KeyType` tag of ed25519 public keys and signatures in Borsh
+const ED25519_KEY_TYPE: u8 = 0;
+
+/// `public_key` as `ed25519:<base58>`.
+pub fn public_key_to_string(public_key: &Point<Ed25519>) -> String {
+    format!(
+        "{}{}",
+        KEY_PREFIX,
+        base58::encode(&public_key.to_bytes(true))
+    )
+}
+
+/// Parses `public_key_to_string`, fails with `InvalidKey` for anything but a canonically
+/// encoded ed25519 key.
+pub fn public_key_from_string(public_key: &str) -> Result<Point<Ed25519>, Error> {
+    let encoded = public_key.strip_prefix(KEY_PREFIX).ok_or(InvalidKey)?;
+    let bytes = base58::decode(encoded).ok_or(InvalidKey)?;
+    decode_point(&bytes).ok_or(InvalidKey)
+}
+
+/// `signature` as `ed25519:<base58>`, as the RPC shows signatures.
+pub fn signature_to_string(signature: &Signature) -> String {
+    format!("{}{}", KEY_PREFIX, base58::encode(&signature.to_bytes()))
+}
+
+/// The message to sign for the Borsh serialized `transaction`.
+pub fn transaction_hash(transaction: &[u8]) -> [u8; 32] {
+    Sha256::digest(transaction).into()
+}
+
+/// The key `transaction` is signed with, the second field after the signer account id. Fails with
+/// `InvalidMessage` if `transaction` is too short and with `InvalidKey` if the key isn't an
+/// ed25519 one.
+pub fn transaction_public_key(transaction: &[u8]) -> Result<Point<Ed25519>, Error> {
+    let (signer_id_len, rest) = transaction.split_at(4.min(transaction.len()));
+    let signer_id_len = <[u8; 4]>::try_from(signer_id_len).map_err(|_| InvalidMessage)?;
+    let signer_id_len =
+        usize::try_from(u32::from_le_bytes(signer_id_len)).map_err(|_| InvalidMessage)?;
+    let public_key = rest
+        .get(signer_id_len..signer_id_len + 33)
+        .ok_or(InvalidMessage)?;
+    if public_key[0] != ED25519_KEY_TYPE {
+        return Err(InvalidKey);
+    }
+    decode_point(&public_key[1..]).ok_or(InvalidKey)
+}
+
+/// The Borsh serialized `SignedTransaction { transaction, signature }`. Fails with `InvalidKey`
+/// or `InvalidMessage` like `transaction_public_key` and with `InvalidSig` if `signature`
+/// doesn't verify under the transaction's key.
+pub fn signed_transaction(transaction: &[u8], signature: &Signature) -> Result<Vec<u8>, Error> {
+    let public_key = transaction_public_key(transaction)?;
+    signature
+        .verify(&transaction_hash(transaction), &public_key)
+        .map_err(|_| InvalidSig)?;
+    let mut signed = Vec::with_capacity(transaction.len() + 65);
+    signed.extend_from_slice(transaction);
+    signed.push(ED25519_KEY_TYPE);
+    signed.extend_from_slice(&signature.to_bytes());
+    Ok(signed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::MultiUnzip;
+    use protocols::aggsig::{self, AggregatedKey};
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use protocols::ExpandedKeyPair;
+
+    // a transfer of one yoctoNEAR from `signer_id` signed by `public_key`
+    fn transfer(signer_id: &str, public_key: &Point<Ed25519>) -> Vec<u8> {
+        let mut transaction = Vec::new();
+        let string = |transaction: &mut Vec<u8>, s: &str| {
+            transaction.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            transaction.extend_from_slice(s.as_bytes());
+        };
+        string(&mut transaction, signer_id);
+        transaction.push(ED25519_KEY_TYPE);
+        transaction.extend_from_slice(&public_key.to_bytes(true));
+        transaction.extend_from_slice(&7u64.to_le_bytes());
+        string(&mut transaction, "receiver.near");
+        transaction.extend_from_slice(&[9u8; 32]);
+        // one action, `Transfer { deposit: 1u128 }`
+        transaction.extend_from_slice(&1u32.to_le_bytes());
+        transaction.push(3);
+        transaction.extend_from_slice(&1u128.to_le_bytes());
+        transaction
+    }
+
+    #[test]
+    fn test_public_key_strings() {
+        let mut rng = deterministic_fast_rand("test_near_public_key_strings", None);
+        let keys = ExpandedKeyPair::create_rng(&mut rng);
+        let string = public_key_to_string(&keys.public_key);
+        assert!(string.starts_with("ed25519:"));
+        assert_eq!(public_key_from_string(&string).unwrap(), keys.public_key);
+        assert_eq!(
+            public_key_from_string(&string["ed25519:".len()..]),
+            Err(InvalidKey)
+        );
+        assert_eq!(
+            public_key_from_string(&string.replace("ed25519", "secp256k1")),
+            Err(InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_signed_transaction() {
+        let mut rng = deterministic_fast_rand("test_near_signed_transaction", None);
+        let signers: Vec<_> = (0..2)
+            .map(|_| ExpandedKeyPair::create_rng(&mut rng))
+            .collect();
+        let pks: Vec<_> = signers.iter().map(|k| k.public_key.clone()).collect();
+        let agg_key = AggregatedKey::new(&pks);
+        let transaction = transfer("multisig.near", &agg_key.apk);
+        assert_eq!(transaction_public_key(&transaction).unwrap(), agg_key.apk);
+        let hash = transaction_hash(&transaction);
+
+        let (ephemeral_keys, first_msgs, second_msgs): (Vec<_>, Vec<_>, Vec<_>) = signers
+            .iter()
+            .map(|k| aggsig::create_ephemeral_key_and_commit_rng(k, &hash, &mut rng).unwrap())
+            .multiunzip();
+        let R_tot = aggsig::get_R_tot(second_msgs.iter().map(|msg| &msg.R)).unwrap();
+        let partial_sigs: Vec<_> = ephemeral_keys
+            .into_iter()
+            .zip(&signers)
+            .zip(&agg_key.coefficients)
+            .map(|((eph, k), a)| aggsig::partial_sign(eph, k, a, &R_tot, &agg_key.apk, &hash))
+            .collect();
+        let signature = agg_key
+            .aggregate_and_verify(&partial_sigs, &first_msgs, &second_msgs, &pks, &hash)
+            .unwrap();
+        assert!(verify_dalek(&agg_key.apk, &signature, &hash));
+
+        let signed = signed_transaction(&transaction, &signature).unwrap();
+        assert_eq!(&signed[..transaction.len()], &transaction[..]);
+        assert_eq!(signed[transaction.len()], ED25519_KEY_TYPE);
+        assert_eq!(&signed[transaction.len() + 1..], &signature.to_bytes()[..]);
+        assert!(signature_to_string(&signature).starts_with("ed25519:"));
+
+        // signed by another key
+        let other = transfer("multisig.near", &pks[0]);
+        assert_eq!(signed_transaction(&other, &signature), Err(InvalidSig));
+        let mut secp256k1_key = transaction.clone();
+        secp256k1_key["multisig.near".len() + 4] = 1;
+        assert_eq!(
+            signed_transaction(&secp256k1_key, &signature),
+            Err(InvalidKey)
+        );
+        assert_eq!(
+            signed_transaction(&transaction[..20], &signature),
+            Err(InvalidMessage)
+        );
+        assert_eq!(transaction_public_key(&[1, 0]), Err(InvalidMessage));
+    }
+}
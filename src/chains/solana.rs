@@ -0,0 +1,290 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Solana addresses and transactions for aggregated keys.
+//!
+//! A Solana address is the base58 encoding of an Ed25519 public key and a transaction is the
+//! list of its signatures (a compact-u16 length, then 64 bytes each) followed by the message
+//! they sign. The required signers are the first `num_required_signatures` account keys of the
+//! message, an aggregated key signs like any other one: the co-signers run aggsig on the
+//! serialized message and the signature goes into the slot of the key.
+//! Both legacy and versioned (v0) messages can be signed.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use curv::elliptic::curves::{Ed25519, Point};
+use rand::{thread_rng, CryptoRng, RngCore};
+
+use base58;
+use local;
+use protocols::aggsig::AggregatedKey;
+use protocols::{decode_point, ExpandedKeyPair, Signature};
+use Error::{self, InvalidKey, InvalidMessage};
+
+// the first byte of a versioned message has the top bit set and the version below it
+const VERSION_PREFIX: u8 = 0x80;
+const PUBLIC_KEY_SIZE: usize = 32;
+const SIGNATURE_SIZE: usize = 64;
+
+/// The base58 address of `public_key`.
+pub fn address(public_key: &Point<Ed25519>) -> String {
+    base58::encode(&public_key.to_bytes(true))
+}
+
+/// Parses an address, fails with `InvalidKey` for anything but a canonically encoded point.
+pub fn public_key_from_address(address: &str) -> Result<Point<Ed25519>, Error> {
+    let bytes = base58::decode(address).ok_or(InvalidKey)?;
+    decode_point(&bytes).ok_or(InvalidKey)
+}
+
+/// The base58 encoding Solana shows transaction signatures (ids) in.
+pub fn signature_to_base58(signature: &Signature) -> String {
+    base58::encode(&signature.to_bytes())
+}
+
+/// The wire format of a transaction, `signatures` in the order of the required signers.
+pub fn serialize_transaction(signatures: &[Signature], message: &[u8]) -> Vec<u8> {
+    let mut transaction = Vec::with_capacity(3 + signatures.len() * SIGNATURE_SIZE + message.len());
+    write_compact_u16(&mut transaction, signatures.len());
+    for signature in signatures {
+        transaction.extend_from_slice(&signature.to_bytes());
+    }
+    transaction.extend_from_slice(message);
+    transaction
+}
+
+/// The transaction with `signature` by `public_key` in its slot and the other signatures zero,
+/// as for a partially signed transaction. Fails with `InvalidMessage` if `message` is
+/// malformed and with `InvalidKey` if `public_key` isn't one of its required signers.
+pub fn transaction_with_signature(
+    message: &[u8],
+    public_key: &Point<Ed25519>,
+    signature: &Signature,
+) -> Result<Vec<u8>, Error> {
+    let (slot, num_signers) = signer_slot(message, public_key)?;
+    let mut transaction = Vec::with_capacity(3 + num_signers * SIGNATURE_SIZE + message.len());
+    write_compact_u16(&mut transaction, num_signers);
+    for i in 0..num_signers {
+        if i == slot {
+            transaction.extend_from_slice(&signature.to_bytes());
+        } else {
+            transaction.extend_from_slice(&[0u8; SIGNATURE_SIZE]);
+        }
+    }
+    transaction.extend_from_slice(message);
+    Ok(transaction)
+}
+
+pub fn sign_transaction(message: &[u8], signers: &[ExpandedKeyPair]) -> Result<Vec<u8>, Error> {
+    sign_transaction_rng(message, signers, &mut thread_rng())
+}
+
+/// Runs the aggsig rounds for all of `signers` on `message` and returns the transaction signed
+/// by their aggregated key, for co-signers whose keys are all at hand (e.g. tests or a single
+/// custody host). Fails like `transaction_with_signature` or `AggregatedKey::aggregate_and_verify`.
+pub fn sign_transaction_rng(
+    message: &[u8],
+    signers: &[ExpandedKeyPair],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<Vec<u8>, Error> {
+    if signers.is_empty() {
+        return Err(InvalidKey);
+    }
+    let pks: Vec<_> = signers.iter().map(|keys| keys.public_key.clone()).collect();
+    let agg_key = AggregatedKey::new(&pks);
+    // fail before any nonce is made
+    signer_slot(message, &agg_key.apk)?;
+    let signature = local::sign_rng(message, signers, &agg_key, rng)?;
+    transaction_with_signature(message, &agg_key.apk, &signature)
+}
+
+// the index of `public_key` among the required signers of `message` and their number
+fn signer_slot(message: &[u8], public_key: &Point<Ed25519>) -> Result<(usize, usize), Error> {
+    let signers = required_signers(message)?;
+    let pk = public_key.to_bytes(true);
+    let slot = signers
+        .iter()
+        .position(|signer| **signer == *pk)
+        .ok_or(InvalidKey)?;
+    Ok((slot, signers.len()))
+}
+
+// the account keys that have to sign `message`
+fn required_signers(message: &[u8]) -> Result<Vec<&[u8]>, Error> {
+    let mut rest = message;
+    let first = *rest.first().ok_or(InvalidMessage)?;
+    if first & VERSION_PREFIX != 0 {
+        // only v0 is defined
+        if first != VERSION_PREFIX {
+            return Err(InvalidMessage);
+        }
+        rest = &rest[1..];
+    }
+    // the header is the number of required signatures, readonly signed and readonly unsigned
+    // accounts
+    if rest.len() < 3 {
+        return Err(InvalidMessage);
+    }
+    let num_required_signatures = usize::from(rest[0]);
+    rest = &rest[3..];
+    let (num_keys, rest) = read_compact_u16(rest)?;
+    if num_required_signatures > num_keys || rest.len() < num_keys * PUBLIC_KEY_SIZE {
+        return Err(InvalidMessage);
+    }
+    Ok(rest[..num_required_signatures * PUBLIC_KEY_SIZE]
+        .chunks(PUBLIC_KEY_SIZE)
+        .collect())
+}
+
+// `n` as 7 bit groups, least significant first, with the top bit set on all but the last
+fn write_compact_u16(out: &mut Vec<u8>, mut n: usize) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_compact_u16(bytes: &[u8]) -> Result<(usize, &[u8]), Error> {
+    let mut n = 0usize;
+    for (i, byte) in bytes.iter().enumerate().take(3) {
+        n |= usize::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            if n > usize::from(u16::MAX) {
+                return Err(InvalidMessage);
+            }
+            return Ok((n, &bytes[i + 1..]));
+        }
+    }
+    Err(InvalidMessage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use rand::Rng;
+
+    // a message with the header, `signers` then one readonly unsigned key, a blockhash and no
+    // instructions
+    fn message(version_prefix: bool, signers: &[&Point<Ed25519>], blockhash: [u8; 32]) -> Vec<u8> {
+        let mut message = Vec::new();
+        if version_prefix {
+            message.push(VERSION_PREFIX);
+        }
+        message.extend_from_slice(&[signers.len() as u8, 0, 1]);
+        write_compact_u16(&mut message, signers.len() + 1);
+        for signer in signers {
+            message.extend_from_slice(&signer.to_bytes(true));
+        }
+        message.extend_from_slice(&[0u8; 32]);
+        message.extend_from_slice(&blockhash);
+        message.push(0);
+        if version_prefix {
+            // no address table lookups
+            message.push(0);
+        }
+        message
+    }
+
+    #[test]
+    fn test_address() {
+        let mut rng = deterministic_fast_rand("test_solana_address", None);
+        let keys = ExpandedKeyPair::create_rng(&mut rng);
+        let address = address(&keys.public_key);
+        assert_eq!(public_key_from_address(&address).unwrap(), keys.public_key);
+        assert_eq!(public_key_from_address("1111"), Err(InvalidKey));
+    }
+
+    #[test]
+    fn test_compact_u16() {
+        for (n, encoding) in &[
+            (0usize, &[0x00u8][..]),
+            (0x7f, &[0x7f]),
+            (0x80, &[0x80, 0x01]),
+            (0x3fff, &[0xff, 0x7f]),
+            (0x4000, &[0x80, 0x80, 0x01]),
+            (0xffff, &[0xff, 0xff, 0x03]),
+        ] {
+            let mut out = Vec::new();
+            write_compact_u16(&mut out, *n);
+            assert_eq!(&out[..], *encoding);
+            assert_eq!(read_compact_u16(encoding).unwrap(), (*n, &[][..]));
+        }
+        assert_eq!(read_compact_u16(&[0x80, 0x80, 0x04]), Err(InvalidMessage));
+        assert_eq!(read_compact_u16(&[0x80, 0x80, 0x80]), Err(InvalidMessage));
+    }
+
+    #[test]
+    fn test_sign_transaction() {
+        let mut rng = deterministic_fast_rand("test_solana_sign_transaction", None);
+        let signers: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_rng(&mut rng))
+            .collect();
+        let pks: Vec<_> = signers.iter().map(|k| k.public_key.clone()).collect();
+        let apk = AggregatedKey::new(&pks).apk;
+        let fee_payer = ExpandedKeyPair::create_rng(&mut rng).public_key;
+
+        for version_prefix in &[false, true] {
+            let message = message(*version_prefix, &[&fee_payer, &apk], rng.gen());
+            let transaction = sign_transaction_rng(&message, &signers, &mut rng).unwrap();
+            assert_eq!(transaction[0], 2);
+            assert_eq!(&transaction[1..65], &[0u8; 64][..]);
+            assert_eq!(&transaction[129..], &message[..]);
+            let signature = Signature::from_bytes(&transaction[65..129]).unwrap();
+            assert!(verify_dalek(&apk, &signature, &message));
+            assert_eq!(
+                serialize_transaction(std::slice::from_ref(&signature), &message)[1..65],
+                transaction[65..129]
+            );
+            assert_eq!(
+                base58::decode(&signature_to_base58(&signature)).unwrap(),
+                &transaction[65..129]
+            );
+        }
+
+        // not a required signer
+        let message = message(false, &[&fee_payer], rng.gen());
+        assert_eq!(
+            sign_transaction_rng(&message, &signers, &mut rng),
+            Err(InvalidKey)
+        );
+        assert_eq!(
+            sign_transaction_rng(&[], &signers, &mut rng),
+            Err(InvalidMessage)
+        );
+        let mut v1 = message.clone();
+        v1.insert(0, VERSION_PREFIX | 1);
+        assert_eq!(
+            sign_transaction_rng(&v1, &signers, &mut rng),
+            Err(InvalidMessage)
+        );
+        assert_eq!(
+            sign_transaction_rng(&message[..40], &signers, &mut rng),
+            Err(InvalidMessage)
+        );
+        assert_eq!(
+            sign_transaction_rng(&message, &[], &mut rng),
+            Err(InvalidKey)
+        );
+    }
+}
@@ -0,0 +1,284 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Stellar accounts and signatures for aggregated keys.
+//!
+//! An account is the strkey of its Ed25519 public key, a `G...` string: base32 of a version
+//! byte, the key and a CRC16 of both. A transaction is signed by signing the SHA-256 hash of the
+//! network id, the envelope type and the XDR encoded transaction, and the signature goes into
+//! the envelope as a `DecoratedSignature`, prefixed with the last four bytes of the key as a
+//! hint. An aggregated key can be a signer of a multisig account like any other key, with the
+//! weight the account gives it.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use curv::elliptic::curves::{Ed25519, Point};
+use rand::{thread_rng, CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+
+use base32;
+use local;
+use protocols::aggsig::AggregatedKey;
+use protocols::{decode_point, ExpandedKeyPair, Signature};
+use Error::{self, InvalidKey, InvalidSig};
+
+pub const PUBLIC_NETWORK: &str = "Public Global Stellar Network ; September 2015";
+pub const TEST_NETWORK: &str = "Test SDF Network ; September 2015";
+
+// the strkey version byte of ed25519 public keys, `6 << 3`, what makes them start with `G`
+const ACCOUNT_ID_VERSION: u8 = 6 << 3;
+// the version byte, the key and the checksum
+const STRKEY_SIZE: usize = 35;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EnvelopeType {
+    Transaction,
+    FeeBump,
+}
+
+impl EnvelopeType {
+    fn xdr(self) -> [u8; 4] {
+        match self {
+            EnvelopeType::Transaction => 2u32.to_be_bytes(),
+            EnvelopeType::FeeBump => 5u32.to_be_bytes(),
+        }
+    }
+}
+
+/// The `G...` strkey of `public_key`.
+pub fn address(public_key: &Point<Ed25519>) -> String {
+    encode_strkey(&public_key.to_bytes(true))
+}
+
+/// Parses a `G...` strkey, fails with `InvalidKey` for a bad checksum or anything but a
+/// canonically encoded point.
+pub fn public_key_from_address(address: &str) -> Result<Point<Ed25519>, Error> {
+    let bytes = base32::decode(address).ok_or(InvalidKey)?;
+    if bytes.len() != STRKEY_SIZE || bytes[0] != ACCOUNT_ID_VERSION {
+        return Err(InvalidKey);
+    }
+    let (versioned_key, checksum) = bytes.split_at(STRKEY_SIZE - 2);
+    if crc16_xmodem(versioned_key).to_le_bytes() != *checksum {
+        return Err(InvalidKey);
+    }
+    decode_point(&versioned_key[1..]).ok_or(InvalidKey)
+}
+
+/// The network id, SHA-256 of the network passphrase (e.g. `PUBLIC_NETWORK`).
+pub fn network_id(network_passphrase: &str) -> [u8; 32] {
+    Sha256::digest(network_passphrase.as_bytes()).into()
+}
+
+/// The hash to sign for the XDR encoded `transaction` (a `Transaction` or a
+/// `FeeBumpTransaction`, as `envelope_type` says) on the network with `network_passphrase`.
+pub fn transaction_hash(
+    network_passphrase: &str,
+    envelope_type: EnvelopeType,
+    transaction: &[u8],
+) -> [u8; 32] {
+    Sha256::new()
+        .chain(network_id(network_passphrase))
+        .chain(envelope_type.xdr())
+        .chain(transaction)
+        .finalize()
+        .into()
+}
+
+/// The last four bytes of the public key, the hint of its signatures.
+pub fn signature_hint(public_key: &Point<Ed25519>) -> [u8; 4] {
+    let mut hint = [0u8; 4];
+    hint.copy_from_slice(&public_key.to_bytes(true)[28..]);
+    hint
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DecoratedSignature {
+    pub hint: [u8; 4],
+    pub signature: Signature,
+}
+
+impl DecoratedSignature {
+    /// `signature` of `transaction_hash` by `public_key` with its hint. Fails with `InvalidSig`
+    /// if it doesn't verify.
+    pub fn new(
+        public_key: &Point<Ed25519>,
+        transaction_hash: &[u8; 32],
+        signature: Signature,
+    ) -> Result<DecoratedSignature, Error> {
+        signature
+            .verify(transaction_hash, public_key)
+            .map_err(|_| InvalidSig)?;
+        Ok(DecoratedSignature {
+            hint: signature_hint(public_key),
+            signature,
+        })
+    }
+
+    /// The XDR encoding, the hint and the signature as `opaque<64>` (length prefixed).
+    pub fn to_xdr(&self) -> [u8; 72] {
+        let mut xdr = [0u8; 72];
+        xdr[..4].copy_from_slice(&self.hint);
+        xdr[4..8].copy_from_slice(&64u32.to_be_bytes());
+        xdr[8..].copy_from_slice(&self.signature.to_bytes());
+        xdr
+    }
+}
+
+pub fn sign_transaction(
+    network_passphrase: &str,
+    envelope_type: EnvelopeType,
+    transaction: &[u8],
+    signers: &[ExpandedKeyPair],
+) -> Result<DecoratedSignature, Error> {
+    sign_transaction_rng(
+        network_passphrase,
+        envelope_type,
+        transaction,
+        signers,
+        &mut thread_rng(),
+    )
+}
+
+/// Runs the aggsig rounds for all of `signers` on the hash of `transaction` and returns the
+/// decorated signature of their aggregated key, for co-signers whose keys are all at hand.
+/// Fails with `InvalidKey` without signers, otherwise like `AggregatedKey::aggregate_and_verify`.
+pub fn sign_transaction_rng(
+    network_passphrase: &str,
+    envelope_type: EnvelopeType,
+    transaction: &[u8],
+    signers: &[ExpandedKeyPair],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<DecoratedSignature, Error> {
+    if signers.is_empty() {
+        return Err(InvalidKey);
+    }
+    let pks: Vec<_> = signers.iter().map(|keys| keys.public_key.clone()).collect();
+    let agg_key = AggregatedKey::new(&pks);
+    let hash = transaction_hash(network_passphrase, envelope_type, transaction);
+    let signature = local::sign_rng(&hash, signers, &agg_key, rng)?;
+    DecoratedSignature::new(&agg_key.apk, &hash, signature)
+}
+
+fn encode_strkey(key: &[u8]) -> String {
+    let mut bytes = Vec::with_capacity(STRKEY_SIZE);
+    bytes.push(ACCOUNT_ID_VERSION);
+    bytes.extend_from_slice(key);
+    let checksum = crc16_xmodem(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+    base32::encode(&bytes)
+}
+
+// CRC-16/XMODEM, polynomial 0x1021 and initial value 0
+fn crc16_xmodem(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0u16, |crc, byte| {
+        (0..8).fold(crc ^ (u16::from(*byte) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                crc << 1 ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+
+    #[test]
+    fn test_strkey() {
+        // the all zero account id
+        assert_eq!(
+            encode_strkey(&[0; 32]),
+            "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF"
+        );
+        assert_eq!(crc16_xmodem(b"123456789"), 0x31c3);
+
+        let mut rng = deterministic_fast_rand("test_stellar_strkey", None);
+        let keys = ExpandedKeyPair::create_rng(&mut rng);
+        let address = address(&keys.public_key);
+        assert!(address.starts_with('G'));
+        assert_eq!(address.len(), 56);
+        assert_eq!(public_key_from_address(&address).unwrap(), keys.public_key);
+        // a flipped character breaks the checksum
+        let mut bad_checksum = address.into_bytes();
+        bad_checksum[10] = if bad_checksum[10] == b'A' { b'B' } else { b'A' };
+        assert_eq!(
+            public_key_from_address(&String::from_utf8(bad_checksum).unwrap()),
+            Err(InvalidKey)
+        );
+        // a secret seed, `S...`, isn't an account
+        assert_eq!(
+            public_key_from_address("SAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"),
+            Err(InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_sign_transaction() {
+        let mut rng = deterministic_fast_rand("test_stellar_sign_transaction", None);
+        let signers: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_rng(&mut rng))
+            .collect();
+        let pks: Vec<_> = signers.iter().map(|k| k.public_key.clone()).collect();
+        let apk = AggregatedKey::new(&pks).apk;
+        let transaction = b"an XDR encoded transaction";
+
+        let decorated = sign_transaction_rng(
+            TEST_NETWORK,
+            EnvelopeType::Transaction,
+            transaction,
+            &signers,
+            &mut rng,
+        )
+        .unwrap();
+        let hash = transaction_hash(TEST_NETWORK, EnvelopeType::Transaction, transaction);
+        assert!(verify_dalek(&apk, &decorated.signature, &hash));
+        assert_eq!(decorated.hint, apk.to_bytes(true)[28..]);
+        let xdr = decorated.to_xdr();
+        assert_eq!(&xdr[..4], &decorated.hint[..]);
+        assert_eq!(&xdr[4..8], &[0, 0, 0, 64]);
+        assert_eq!(&xdr[8..], &decorated.signature.to_bytes()[..]);
+
+        // the hash depends on the network and the envelope type
+        assert_ne!(
+            hash,
+            transaction_hash(PUBLIC_NETWORK, EnvelopeType::Transaction, transaction)
+        );
+        assert_ne!(
+            hash,
+            transaction_hash(TEST_NETWORK, EnvelopeType::FeeBump, transaction)
+        );
+        let public_hash = transaction_hash(PUBLIC_NETWORK, EnvelopeType::Transaction, transaction);
+        assert_eq!(
+            DecoratedSignature::new(&apk, &public_hash, decorated.signature),
+            Err(InvalidSig)
+        );
+        assert_eq!(
+            sign_transaction_rng(
+                TEST_NETWORK,
+                EnvelopeType::Transaction,
+                transaction,
+                &[],
+                &mut rng
+            ),
+            Err(InvalidKey)
+        );
+    }
+}
@@ -0,0 +1,506 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Tendermint/CometBFT votes signed by an aggregated key, with double-sign protection.
+//!
+//! `Vote::sign_bytes` is the length delimited protobuf `CanonicalVote` a validator signs.
+//! `VoteSigner` signs prevotes and precommits with whatever protocol the caller runs on those
+//! bytes (aggsig, threshold signing, ...) and refuses to sign anything that could be a double
+//! sign: a vote for a lower height, round or step than the last one it signed, or another vote
+//! for the same height, round and step. The last signed state goes to a `SignStateStore` before
+//! the signing starts, so a crash can cost a vote but never produce a conflicting one.
+//! Asked again for the vote it signed last (also with another timestamp, as CometBFT does after a
+//! restart) it returns that vote and signature instead of signing again.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::path::PathBuf;
+
+use protocols::Signature;
+use Error;
+
+/// The `SignedMsgType` of a vote.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum VoteType {
+    Prevote,
+    Precommit,
+}
+
+impl VoteType {
+    fn msg_type(self) -> u64 {
+        match self {
+            VoteType::Prevote => 1,
+            VoteType::Precommit => 2,
+        }
+    }
+
+    // the privval step, proposals are step 1
+    fn step(self) -> u8 {
+        match self {
+            VoteType::Prevote => 2,
+            VoteType::Precommit => 3,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct BlockId {
+    pub hash: Vec<u8>,
+    pub part_set_total: u32,
+    pub part_set_hash: Vec<u8>,
+}
+
+/// `google.protobuf.Timestamp`, seconds since the unix epoch and nanoseconds.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Timestamp {
+    pub seconds: i64,
+    pub nanos: i32,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Vote {
+    pub vote_type: VoteType,
+    pub height: i64,
+    pub round: i64,
+    /// `None` for a vote for nil.
+    pub block_id: Option<BlockId>,
+    pub timestamp: Timestamp,
+}
+
+impl Vote {
+    /// The varint length prefixed protobuf encoding of the `CanonicalVote` on `chain_id`, fields
+    /// with default values are left out as proto3 does.
+    pub fn sign_bytes(&self, chain_id: &str) -> Vec<u8> {
+        let mut vote = Vec::new();
+        write_varint_field(&mut vote, 1, self.vote_type.msg_type());
+        write_fixed64_field(&mut vote, 2, self.height);
+        write_fixed64_field(&mut vote, 3, self.round);
+        if let Some(block_id) = &self.block_id {
+            let mut part_set_header = Vec::new();
+            write_varint_field(&mut part_set_header, 1, u64::from(block_id.part_set_total));
+            write_bytes_field(&mut part_set_header, 2, &block_id.part_set_hash);
+            let mut canonical_block_id = Vec::new();
+            write_bytes_field(&mut canonical_block_id, 1, &block_id.hash);
+            // the part set header isn't nullable, it's there even if empty
+            write_message_field(&mut canonical_block_id, 2, &part_set_header);
+            write_message_field(&mut vote, 4, &canonical_block_id);
+        }
+        let mut timestamp = Vec::new();
+        write_varint_field(&mut timestamp, 1, self.timestamp.seconds as u64);
+        // int32 is sign extended to 64 bits on the wire
+        write_varint_field(&mut timestamp, 2, i64::from(self.timestamp.nanos) as u64);
+        write_message_field(&mut vote, 5, &timestamp);
+        write_bytes_field(&mut vote, 6, chain_id.as_bytes());
+
+        let mut sign_bytes = Vec::with_capacity(vote.len() + 2);
+        write_varint(&mut sign_bytes, vote.len() as u64);
+        sign_bytes.extend_from_slice(&vote);
+        sign_bytes
+    }
+
+    fn hrs(&self) -> (i64, i64, u8) {
+        (self.height, self.round, self.vote_type.step())
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SignedVote {
+    pub vote: Vote,
+    pub signature: Signature,
+}
+
+/// The last vote `VoteSigner` started signing, with its signature once it has one.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LastSignState {
+    pub vote: Vote,
+    pub signature: Option<Signature>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum VoteError {
+    HeightRegression,
+    RoundRegression,
+    StepRegression,
+    /// Another vote for the height, round and step of the last one.
+    ConflictingVote,
+    /// The signing protocol failed, the vote is recorded as the last one anyway.
+    Sign(Error),
+    Storage(ErrorKind),
+}
+
+impl fmt::Display for VoteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VoteError::Sign(e) => write!(f, "signing failed: {}", e),
+            VoteError::Storage(kind) => write!(f, "sign state storage failed: {:?}", kind),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl std::error::Error for VoteError {}
+
+impl From<io::Error> for VoteError {
+    fn from(e: io::Error) -> VoteError {
+        VoteError::Storage(e.kind())
+    }
+}
+
+pub trait SignStateStore {
+    fn load(&mut self) -> Result<Option<LastSignState>, VoteError>;
+
+    /// Has to return only once `state` is durable.
+    fn store(&mut self, state: &LastSignState) -> Result<(), VoteError>;
+}
+
+/// A store keeping the state in memory only, e.g. for tests or to wrap with another store.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore(pub Option<LastSignState>);
+
+impl SignStateStore for MemoryStore {
+    fn load(&mut self) -> Result<Option<LastSignState>, VoteError> {
+        Ok(self.0.clone())
+    }
+
+    fn store(&mut self, state: &LastSignState) -> Result<(), VoteError> {
+        self.0 = Some(state.clone());
+        Ok(())
+    }
+}
+
+/// The state as JSON in a file, replaced atomically by writing and syncing a temporary file
+/// next to it and renaming that. A missing file is no state yet.
+#[derive(Clone, Debug)]
+pub struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>) -> FileStore {
+        FileStore { path: path.into() }
+    }
+}
+
+impl SignStateStore for FileStore {
+    fn load(&mut self) -> Result<Option<LastSignState>, VoteError> {
+        match fs::read(&self.path) {
+            Ok(json) => serde_json::from_slice(&json)
+                .map(Some)
+                .map_err(|_| VoteError::Storage(ErrorKind::InvalidData)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn store(&mut self, state: &LastSignState) -> Result<(), VoteError> {
+        let json =
+            serde_json::to_vec(state).map_err(|_| VoteError::Storage(ErrorKind::InvalidData))?;
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&json)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+pub struct VoteSigner<S: SignStateStore> {
+    chain_id: String,
+    store: S,
+    last: Option<LastSignState>,
+}
+
+impl<S: SignStateStore> VoteSigner<S> {
+    /// Starts from the state in `store`.
+    pub fn new(chain_id: &str, mut store: S) -> Result<VoteSigner<S>, VoteError> {
+        let last = store.load()?;
+        Ok(VoteSigner {
+            chain_id: String::from(chain_id),
+            store,
+            last,
+        })
+    }
+
+    pub fn last_sign_state(&self) -> Option<&LastSignState> {
+        self.last.as_ref()
+    }
+
+    /// Signs `vote` by running `sign` on its sign bytes, unless that could be a double sign.
+    /// The returned vote is `vote` or, if only its timestamp differs from the last signed one,
+    /// that one with its signature.
+    pub fn sign_vote(
+        &mut self,
+        vote: &Vote,
+        sign: impl FnOnce(&[u8]) -> Result<Signature, Error>,
+    ) -> Result<SignedVote, VoteError> {
+        if let Some(last) = &self.last {
+            let (height, round, step) = last.vote.hrs();
+            if vote.height < height {
+                return Err(VoteError::HeightRegression);
+            }
+            if vote.height == height && vote.round < round {
+                return Err(VoteError::RoundRegression);
+            }
+            if vote.hrs() < (height, round, step) {
+                return Err(VoteError::StepRegression);
+            }
+            if vote.hrs() == (height, round, step) {
+                let same_but_timestamp = Vote {
+                    timestamp: last.vote.timestamp,
+                    ..vote.clone()
+                };
+                match &last.signature {
+                    Some(signature) if same_but_timestamp == last.vote => {
+                        return Ok(SignedVote {
+                            vote: last.vote.clone(),
+                            signature: signature.clone(),
+                        });
+                    }
+                    // signing the same bytes again is safe
+                    None if *vote == last.vote => {}
+                    _ => return Err(VoteError::ConflictingVote),
+                }
+            }
+        }
+
+        let mut state = LastSignState {
+            vote: vote.clone(),
+            signature: None,
+        };
+        self.store.store(&state)?;
+        self.last = Some(state.clone());
+        let signature = sign(&vote.sign_bytes(&self.chain_id)).map_err(VoteError::Sign)?;
+        state.signature = Some(signature.clone());
+        self.store.store(&state)?;
+        self.last = Some(state);
+        Ok(SignedVote {
+            vote: vote.clone(),
+            signature,
+        })
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+// the field tag of `field` with the wire type
+fn write_key(out: &mut Vec<u8>, field: u64, wire_type: u64) {
+    write_varint(out, field << 3 | wire_type);
+}
+
+fn write_varint_field(out: &mut Vec<u8>, field: u64, n: u64) {
+    if n != 0 {
+        write_key(out, field, 0);
+        write_varint(out, n);
+    }
+}
+
+fn write_fixed64_field(out: &mut Vec<u8>, field: u64, n: i64) {
+    if n != 0 {
+        write_key(out, field, 1);
+        out.extend_from_slice(&n.to_le_bytes());
+    }
+}
+
+fn write_bytes_field(out: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    if !bytes.is_empty() {
+        write_message_field(out, field, bytes);
+    }
+}
+
+fn write_message_field(out: &mut Vec<u8>, field: u64, message: &[u8]) {
+    write_key(out, field, 2);
+    write_varint(out, message.len() as u64);
+    out.extend_from_slice(message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocols::aggsig;
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use protocols::ExpandedKeyPair;
+
+    // Go's zero time, 0001-01-01T00:00:00Z
+    const GO_ZERO_TIME: Timestamp = Timestamp {
+        seconds: -62135596800,
+        nanos: 0,
+    };
+    const GO_ZERO_TIME_BYTES: [u8; 13] = [
+        0x2a, 0xb, 0x8, 0x80, 0x92, 0xb8, 0xc3, 0x98, 0xfe, 0xff, 0xff, 0xff, 0x1,
+    ];
+
+    fn vote(vote_type: VoteType, height: i64, round: i64) -> Vote {
+        Vote {
+            vote_type,
+            height,
+            round,
+            block_id: None,
+            timestamp: GO_ZERO_TIME,
+        }
+    }
+
+    #[test]
+    fn test_sign_bytes() {
+        // the vectors of CometBFT's TestVoteSignBytesTestVectors
+        let mut precommit = vec![
+            0x21, 0x8, 0x2, 0x11, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x19, 0x1, 0x0, 0x0, 0x0,
+            0x0, 0x0, 0x0, 0x0,
+        ];
+        precommit.extend_from_slice(&GO_ZERO_TIME_BYTES);
+        assert_eq!(vote(VoteType::Precommit, 1, 1).sign_bytes(""), precommit);
+        let mut prevote = precommit.clone();
+        prevote[2] = 0x1;
+        assert_eq!(vote(VoteType::Prevote, 1, 1).sign_bytes(""), prevote);
+
+        let mut with_chain_id = precommit.clone();
+        with_chain_id[0] += 15;
+        with_chain_id.extend_from_slice(&[0x32, 0xd]);
+        with_chain_id.extend_from_slice(b"test_chain_id");
+        assert_eq!(
+            vote(VoteType::Precommit, 1, 1).sign_bytes("test_chain_id"),
+            with_chain_id
+        );
+
+        let mut for_block = vote(VoteType::Prevote, 1, 0);
+        for_block.block_id = Some(BlockId {
+            hash: vec![0xaa; 2],
+            part_set_total: 1,
+            part_set_hash: vec![0xbb; 2],
+        });
+        for_block.timestamp = Timestamp {
+            seconds: 1,
+            nanos: 2,
+        };
+        assert_eq!(
+            for_block.sign_bytes(""),
+            vec![
+                0x1f, 0x8, 0x1, 0x11, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x22, 0xc, 0xa, 0x2,
+                0xaa, 0xaa, 0x12, 0x6, 0x8, 0x1, 0x12, 0x2, 0xbb, 0xbb, 0x2a, 0x4, 0x8, 0x1, 0x10,
+                0x2
+            ]
+        );
+    }
+
+    #[test]
+    fn test_double_sign_protection() {
+        let mut rng = deterministic_fast_rand("test_tendermint_double_sign", None);
+        let keys = ExpandedKeyPair::create_rng(&mut rng);
+        let sign = |bytes: &[u8]| Ok(aggsig::sign_single(bytes, &keys));
+        let mut signer = VoteSigner::new("chain", MemoryStore::default()).unwrap();
+
+        let prevote = vote(VoteType::Prevote, 10, 0);
+        let signed = signer.sign_vote(&prevote, sign).unwrap();
+        assert_eq!(signed.vote, prevote);
+        assert!(verify_dalek(
+            &keys.public_key,
+            &signed.signature,
+            &prevote.sign_bytes("chain")
+        ));
+
+        // the same vote with a later timestamp gets the signed one back
+        let mut later = prevote.clone();
+        later.timestamp.seconds += 5;
+        assert_eq!(signer.sign_vote(&later, sign).unwrap(), signed);
+        // a vote for a block in the same round is a double sign
+        let mut for_block = prevote.clone();
+        for_block.block_id = Some(BlockId {
+            hash: vec![1; 32],
+            part_set_total: 1,
+            part_set_hash: vec![2; 32],
+        });
+        assert_eq!(
+            signer.sign_vote(&for_block, sign),
+            Err(VoteError::ConflictingVote)
+        );
+
+        let precommit = vote(VoteType::Precommit, 10, 1);
+        assert!(signer.sign_vote(&precommit, sign).is_ok());
+        assert_eq!(
+            signer.sign_vote(&vote(VoteType::Prevote, 10, 1), sign),
+            Err(VoteError::StepRegression)
+        );
+        assert_eq!(
+            signer.sign_vote(&vote(VoteType::Precommit, 10, 0), sign),
+            Err(VoteError::RoundRegression)
+        );
+        assert_eq!(
+            signer.sign_vote(&vote(VoteType::Precommit, 9, 5), sign),
+            Err(VoteError::HeightRegression)
+        );
+
+        // a failed signing still counts as signed, only the same vote can be retried
+        let next = vote(VoteType::Prevote, 11, 0);
+        assert_eq!(
+            signer.sign_vote(&next, |_| Err(Error::InvalidSig)),
+            Err(VoteError::Sign(Error::InvalidSig))
+        );
+        let mut next_later = next.clone();
+        next_later.timestamp.seconds += 1;
+        assert_eq!(
+            signer.sign_vote(&next_later, sign),
+            Err(VoteError::ConflictingVote)
+        );
+        assert!(signer.sign_vote(&next, sign).is_ok());
+    }
+
+    #[test]
+    fn test_file_store() {
+        let mut rng = deterministic_fast_rand("test_tendermint_file_store", None);
+        let keys = ExpandedKeyPair::create_rng(&mut rng);
+        let sign = |bytes: &[u8]| Ok(aggsig::sign_single(bytes, &keys));
+        let path = std::env::temp_dir().join(format!(
+            "multi-party-eddsa-sign-state-{}.json",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        let mut signer = VoteSigner::new("chain", FileStore::new(&path)).unwrap();
+        assert!(signer.last_sign_state().is_none());
+        let signed = signer
+            .sign_vote(&vote(VoteType::Precommit, 3, 0), sign)
+            .unwrap();
+        drop(signer);
+
+        // a restarted signer remembers the vote
+        let mut signer = VoteSigner::new("chain", FileStore::new(&path)).unwrap();
+        assert_eq!(
+            signer.last_sign_state().unwrap().signature,
+            Some(signed.signature)
+        );
+        assert_eq!(
+            signer.sign_vote(&vote(VoteType::Prevote, 3, 0), sign),
+            Err(VoteError::StepRegression)
+        );
+
+        fs::write(&path, b"not json").unwrap();
+        assert_eq!(
+            VoteSigner::new("chain", FileStore::new(&path)).err(),
+            Some(VoteError::Storage(ErrorKind::InvalidData))
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,485 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! COSE_Sign1 (RFC 9052) messages with the EdDSA algorithm (RFC 9053), and `OKP` COSE_Keys of
+//! public keys as in WebAuthn credentials.
+//!
+//! The co-signers sign the `Sig_structure` of the payload, `CoseSign1::sign` runs the signing
+//! protocol on it through a closure. Messages are written tagged with the deterministic CBOR
+//! encoding, both tagged and untagged messages are read. Decoding fails with `InvalidMessage`
+//! for malformed CBOR and with `InvalidSig` for another algorithm or critical headers, which
+//! aren't understood here.
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use curv::elliptic::curves::{Ed25519, Point};
+
+use protocols::{decode_point, Signature};
+use Error::{self, InvalidKey, InvalidMessage, InvalidSig};
+
+// the major types of RFC 8949
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+const NULL: u8 = 0xf6;
+// nesting limit when skipping unknown header values
+const MAX_DEPTH: usize = 16;
+
+const COSE_SIGN1_TAG: u64 = 18;
+const ALG: i64 = 1;
+const CRIT: i64 = 2;
+const KID: i64 = 4;
+const EDDSA: i64 = -8;
+const KTY: i64 = 1;
+const KTY_OKP: i64 = 1;
+const KEY_ALG: i64 = 3;
+const CRV: i64 = -1;
+const CRV_ED25519: i64 = 6;
+const X: i64 = -2;
+const D: i64 = -4;
+
+/// A COSE_Sign1 whose protected header has the EdDSA algorithm.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CoseSign1 {
+    /// The serialized protected header, `{1: -8}` for the messages signed here.
+    pub protected: Vec<u8>,
+    /// The `kid` of the unprotected header.
+    pub kid: Option<Vec<u8>>,
+    /// `None` for a detached payload.
+    pub payload: Option<Vec<u8>>,
+    pub signature: Signature,
+}
+
+impl CoseSign1 {
+    /// Signs `payload` and `external_aad` with `public_key`, `sign` runs the signing protocol on
+    /// the `sig_structure`. Fails with `InvalidSig` if the signature of `sign` doesn't verify.
+    pub fn sign(
+        payload: &[u8],
+        external_aad: &[u8],
+        kid: Option<&[u8]>,
+        public_key: &Point<Ed25519>,
+        sign: impl FnOnce(&[u8]) -> Result<Signature, Error>,
+    ) -> Result<CoseSign1, Error> {
+        let mut protected = Vec::new();
+        write_head(&mut protected, MAP, 1);
+        write_int(&mut protected, ALG);
+        write_int(&mut protected, EDDSA);
+        let to_sign = sig_structure(&protected, external_aad, payload);
+        let signature = sign(&to_sign)?;
+        signature
+            .verify(&to_sign, public_key)
+            .map_err(|_| InvalidSig)?;
+        Ok(CoseSign1 {
+            protected,
+            kid: kid.map(Vec::from),
+            payload: Some(Vec::from(payload)),
+            signature,
+        })
+    }
+
+    /// Checks the signature under `public_key`, with `detached_payload` if the payload isn't
+    /// in the message. Fails with `InvalidMessage` if the payload is given twice or not at all.
+    pub fn verify(
+        &self,
+        external_aad: &[u8],
+        detached_payload: Option<&[u8]>,
+        public_key: &Point<Ed25519>,
+    ) -> Result<(), Error> {
+        let payload = match (&self.payload, detached_payload) {
+            (Some(payload), None) => payload.as_slice(),
+            (None, Some(payload)) => payload,
+            _ => return Err(InvalidMessage),
+        };
+        check_protected(&self.protected)?;
+        self.signature
+            .verify(
+                &sig_structure(&self.protected, external_aad, payload),
+                public_key,
+            )
+            .map_err(|_| InvalidSig)
+    }
+
+    /// The tagged COSE_Sign1.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_head(&mut out, TAG, COSE_SIGN1_TAG);
+        write_head(&mut out, ARRAY, 4);
+        write_head(&mut out, BYTES, self.protected.len() as u64);
+        out.extend_from_slice(&self.protected);
+        match &self.kid {
+            Some(kid) => {
+                write_head(&mut out, MAP, 1);
+                write_int(&mut out, KID);
+                write_head(&mut out, BYTES, kid.len() as u64);
+                out.extend_from_slice(kid);
+            }
+            None => write_head(&mut out, MAP, 0),
+        }
+        match &self.payload {
+            Some(payload) => {
+                write_head(&mut out, BYTES, payload.len() as u64);
+                out.extend_from_slice(payload);
+            }
+            None => out.push(NULL),
+        }
+        write_head(&mut out, BYTES, 64);
+        out.extend_from_slice(&self.signature.to_bytes());
+        out
+    }
+
+    pub fn from_cbor(cbor: &[u8]) -> Result<CoseSign1, Error> {
+        let mut decoder = Decoder(cbor);
+        let mut head = decoder.head()?;
+        if head == (TAG, COSE_SIGN1_TAG) {
+            head = decoder.head()?;
+        }
+        if head != (ARRAY, 4) {
+            return Err(InvalidMessage);
+        }
+        let protected = Vec::from(decoder.bytes()?);
+        check_protected(&protected)?;
+        let mut kid = None;
+        let (major, len) = decoder.head()?;
+        if major != MAP {
+            return Err(InvalidMessage);
+        }
+        for _ in 0..len {
+            if decoder.int()? == KID {
+                kid = Some(Vec::from(decoder.bytes()?));
+            } else {
+                decoder.skip(0)?;
+            }
+        }
+        let payload = if decoder.0.first() == Some(&NULL) {
+            decoder.0 = &decoder.0[1..];
+            None
+        } else {
+            Some(Vec::from(decoder.bytes()?))
+        };
+        let signature = Signature::from_bytes(decoder.bytes()?).map_err(|_| InvalidSig)?;
+        if !decoder.0.is_empty() {
+            return Err(InvalidMessage);
+        }
+        Ok(CoseSign1 {
+            protected,
+            kid,
+            payload,
+            signature,
+        })
+    }
+}
+
+/// The `Sig_structure` for `Signature1`, the bytes the co-signers sign.
+pub fn sig_structure(protected: &[u8], external_aad: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_head(&mut out, ARRAY, 4);
+    write_head(&mut out, TEXT, 10);
+    out.extend_from_slice(b"Signature1");
+    for bytes in [protected, external_aad, payload] {
+        write_head(&mut out, BYTES, bytes.len() as u64);
+        out.extend_from_slice(bytes);
+    }
+    out
+}
+
+/// The `{1: 1, 3: -8, -1: 6, -2: x}` COSE_Key of `public_key`.
+pub fn public_key_to_cose_key(public_key: &Point<Ed25519>) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_head(&mut out, MAP, 4);
+    for (label, value) in [(KTY, KTY_OKP), (KEY_ALG, EDDSA), (CRV, CRV_ED25519)] {
+        write_int(&mut out, label);
+        write_int(&mut out, value);
+    }
+    write_int(&mut out, X);
+    write_head(&mut out, BYTES, 32);
+    out.extend_from_slice(&public_key.to_bytes(true));
+    out
+}
+
+/// Parses an Ed25519 `OKP` COSE_Key, its algorithm has to be EdDSA if it's given and other
+/// parameters (`kid`, key ops, ...) are ignored. Fails with `InvalidKey` for anything else,
+/// including private keys.
+pub fn public_key_from_cose_key(cbor: &[u8]) -> Result<Point<Ed25519>, Error> {
+    let mut decoder = Decoder(cbor);
+    let (major, len) = decoder.head().map_err(|_| InvalidKey)?;
+    if major != MAP {
+        return Err(InvalidKey);
+    }
+    let (mut kty, mut crv, mut x) = (None, None, None);
+    for _ in 0..len {
+        match decoder.int().map_err(|_| InvalidKey)? {
+            KTY => kty = Some(decoder.int().map_err(|_| InvalidKey)?),
+            KEY_ALG if decoder.int().map_err(|_| InvalidKey)? != EDDSA => return Err(InvalidKey),
+            CRV => crv = Some(decoder.int().map_err(|_| InvalidKey)?),
+            X => x = Some(decoder.bytes().map_err(|_| InvalidKey)?),
+            D => return Err(InvalidKey),
+            KEY_ALG => {}
+            _ => decoder.skip(0).map_err(|_| InvalidKey)?,
+        }
+    }
+    if !decoder.0.is_empty() || kty != Some(KTY_OKP) || crv != Some(CRV_ED25519) {
+        return Err(InvalidKey);
+    }
+    decode_point(x.ok_or(InvalidKey)?).ok_or(InvalidKey)
+}
+
+// the protected header has to be a map with the EdDSA algorithm and without critical headers
+fn check_protected(protected: &[u8]) -> Result<(), Error> {
+    let mut decoder = Decoder(protected);
+    let (major, len) = decoder.head()?;
+    if major != MAP {
+        return Err(InvalidMessage);
+    }
+    let mut alg = None;
+    for _ in 0..len {
+        match decoder.int()? {
+            ALG => alg = Some(decoder.int()?),
+            CRIT => return Err(InvalidSig),
+            _ => decoder.skip(0)?,
+        }
+    }
+    if !decoder.0.is_empty() {
+        return Err(InvalidMessage);
+    }
+    if alg != Some(EDDSA) {
+        return Err(InvalidSig);
+    }
+    Ok(())
+}
+
+// the shortest head for `value`
+fn write_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    if value < 24 {
+        out.push(major | value as u8);
+    } else if value <= u64::from(u8::MAX) {
+        out.extend_from_slice(&[major | 24, value as u8]);
+    } else if value <= u64::from(u16::MAX) {
+        out.push(major | 25);
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u64::from(u32::MAX) {
+        out.push(major | 26);
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn write_int(out: &mut Vec<u8>, value: i64) {
+    if value < 0 {
+        // -1 - n, which is at most i64::MAX
+        write_head(out, NEGATIVE, !value as u64);
+    } else {
+        write_head(out, UNSIGNED, value as u64);
+    }
+}
+
+/// Reads definite length CBOR items.
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: u64) -> Result<&'a [u8], Error> {
+        let len = usize::try_from(len).map_err(|_| InvalidMessage)?;
+        if self.0.len() < len {
+            return Err(InvalidMessage);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn head(&mut self) -> Result<(u8, u64), Error> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        let value = match info {
+            0..=23 => u64::from(info),
+            24 => u64::from(self.take(1)?[0]),
+            25 => self.take(2)?.iter().fold(0, |n, b| n << 8 | u64::from(*b)),
+            26 => self.take(4)?.iter().fold(0, |n, b| n << 8 | u64::from(*b)),
+            27 => self.take(8)?.iter().fold(0, |n, b| n << 8 | u64::from(*b)),
+            // reserved or indefinite length
+            _ => return Err(InvalidMessage),
+        };
+        Ok((major, value))
+    }
+
+    fn int(&mut self) -> Result<i64, Error> {
+        match self.head()? {
+            (UNSIGNED, n) => i64::try_from(n).map_err(|_| InvalidMessage),
+            (NEGATIVE, n) => i64::try_from(n).map(|n| -1 - n).map_err(|_| InvalidMessage),
+            _ => Err(InvalidMessage),
+        }
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], Error> {
+        match self.head()? {
+            (BYTES, len) => self.take(len),
+            _ => Err(InvalidMessage),
+        }
+    }
+
+    fn skip(&mut self, depth: usize) -> Result<(), Error> {
+        if depth > MAX_DEPTH {
+            return Err(InvalidMessage);
+        }
+        match self.head()? {
+            (UNSIGNED, _) | (NEGATIVE, _) | (SIMPLE, _) => Ok(()),
+            (BYTES, len) | (TEXT, len) => self.take(len).map(|_| ()),
+            (ARRAY, len) => (0..len).try_for_each(|_| self.skip(depth + 1)),
+            (MAP, len) => (0..len).try_for_each(|_| {
+                self.skip(depth + 1)?;
+                self.skip(depth + 1)
+            }),
+            (TAG, _) => self.skip(depth + 1),
+            _ => Err(InvalidMessage),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use local;
+    use protocols::aggsig::{self, AggregatedKey};
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use protocols::ExpandedKeyPair;
+
+    // the key of RFC 8032 test 1, the messages were made with Python's `cryptography`:
+    // payload "This is the content." with kid "11" and detached with external aad "aad"
+    const SEED: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+    const SIGN1: &str = "d28443a10127a10442313154546869732069732074686520636f6e74656e742e58406354488f9f290e36cd80e23762e664a5cb03e4267c66a8cffaef7c66d89a40bf2cbb8222432a08e5ee410d8b540c6931d26fb6af673f7e2100655d8bae765c04";
+    const DETACHED: &str = "d28443a10127a0f65840ef315797bdc1f059450330b98f3b7e00fb42ff0f40303fd2e0b7eae49ab7008a41fd771af6a9d3a0fa5550de428cacc80254d5aaf5b3351e5ce621d95ab21809";
+    const PAYLOAD: &[u8] = b"This is the content.";
+
+    fn keypair() -> ExpandedKeyPair {
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&hex::decode(SEED).unwrap());
+        ExpandedKeyPair::from_seed_bytes(seed)
+    }
+
+    #[test]
+    fn test_cose_sign1_vectors() {
+        let keypair = keypair();
+        let public_key = &keypair.public_key;
+        let bytes = hex::decode(SIGN1).unwrap();
+        let message = CoseSign1::from_cbor(&bytes).unwrap();
+        assert_eq!(message.kid.as_deref(), Some(&b"11"[..]));
+        assert_eq!(message.payload.as_deref(), Some(PAYLOAD));
+        assert!(message.verify(b"", None, public_key).is_ok());
+        assert_eq!(message.to_cbor(), bytes);
+        // untagged
+        assert_eq!(CoseSign1::from_cbor(&bytes[1..]).unwrap(), message);
+
+        let detached = CoseSign1::from_cbor(&hex::decode(DETACHED).unwrap()).unwrap();
+        assert_eq!(detached.payload, None);
+        assert!(detached.verify(b"aad", Some(PAYLOAD), public_key).is_ok());
+        assert_eq!(
+            detached.verify(b"", Some(PAYLOAD), public_key),
+            Err(InvalidSig)
+        );
+        assert_eq!(
+            detached.verify(b"aad", None, public_key),
+            Err(InvalidMessage)
+        );
+        assert_eq!(
+            message.verify(b"", Some(PAYLOAD), public_key),
+            Err(InvalidMessage)
+        );
+
+        let signed = CoseSign1::sign(PAYLOAD, b"", Some(b"11"), public_key, |to_sign| {
+            Ok(aggsig::sign_single(to_sign, &keypair))
+        })
+        .unwrap();
+        assert_eq!(signed.to_cbor()[..34], bytes[..34]);
+        assert!(signed.verify(b"", None, public_key).is_ok());
+    }
+
+    #[test]
+    fn test_cose_sign1_aggregated_key() {
+        let mut rng = deterministic_fast_rand("test_cose_sign1_aggregated_key", None);
+        let signers = [
+            ExpandedKeyPair::create_rng(&mut rng),
+            ExpandedKeyPair::create_rng(&mut rng),
+        ];
+        let agg_key =
+            AggregatedKey::new(&[signers[0].public_key.clone(), signers[1].public_key.clone()]);
+        let message = CoseSign1::sign(b"telemetry", b"device 7", None, &agg_key.apk, |to_sign| {
+            local::sign_rng(to_sign, &signers, &agg_key, &mut rng)
+        })
+        .unwrap();
+        assert!(verify_dalek(
+            &agg_key.apk,
+            &message.signature,
+            &sig_structure(&message.protected, b"device 7", b"telemetry")
+        ));
+        let decoded = CoseSign1::from_cbor(&message.to_cbor()).unwrap();
+        assert!(decoded.verify(b"device 7", None, &agg_key.apk).is_ok());
+        assert_eq!(
+            CoseSign1::sign(b"telemetry", b"", None, &agg_key.apk, |to_sign| Ok(
+                aggsig::sign_single(to_sign, &signers[0])
+            )),
+            Err(InvalidSig)
+        );
+
+        let cose_key = public_key_to_cose_key(&agg_key.apk);
+        assert_eq!(
+            cose_key[..10],
+            [0xa4, 0x01, 0x01, 0x03, 0x27, 0x20, 0x06, 0x21, 0x58, 0x20]
+        );
+        assert_eq!(public_key_from_cose_key(&cose_key).unwrap(), agg_key.apk);
+    }
+
+    #[test]
+    fn test_cose_reject_invalid() {
+        let bytes = hex::decode(SIGN1).unwrap();
+        // ES256 (-7) instead of EdDSA
+        let mut es256 = bytes.clone();
+        es256[5] = 0x26;
+        assert_eq!(CoseSign1::from_cbor(&es256), Err(InvalidSig));
+        // {1: -8, 2: [1]}, a critical header
+        let mut crit = hex::decode("d28445a2012702810101").unwrap();
+        crit.extend_from_slice(&bytes[6..]);
+        assert_eq!(CoseSign1::from_cbor(&crit), Err(InvalidSig));
+        assert_eq!(
+            CoseSign1::from_cbor(&bytes[..bytes.len() - 1]),
+            Err(InvalidMessage)
+        );
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(CoseSign1::from_cbor(&trailing), Err(InvalidMessage));
+        // an indefinite length payload
+        let mut indefinite = bytes;
+        indefinite[11] = 0x5f;
+        assert_eq!(CoseSign1::from_cbor(&indefinite), Err(InvalidMessage));
+
+        let mut cose_key = public_key_to_cose_key(&keypair().public_key);
+        // X25519
+        cose_key[6] = 0x04;
+        assert_eq!(public_key_from_cose_key(&cose_key), Err(InvalidKey));
+        let mut private_key = public_key_to_cose_key(&keypair().public_key);
+        private_key[0] = 0xa5;
+        private_key.extend_from_slice(&[0x23, 0x41, 0x00]);
+        assert_eq!(public_key_from_cose_key(&private_key), Err(InvalidKey));
+    }
+}
@@ -0,0 +1,129 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Conversions between the keys and signatures of this crate and those of `ed25519-dalek`.
+//!
+//! A dalek `SecretKey` (or `Keypair`) becomes an `ExpandedKeyPair` with the same public key, so
+//! an existing key can join an aggregation, and an `ExpandedKeyPair` becomes a dalek
+//! `ExpandedSecretKey`. Public keys are curv points, which can't implement the conversion
+//! traits here, so they are converted with `to_dalek_public_key` and `from_dalek_public_key`.
+//! Signatures convert both ways.
+
+use core::convert::TryFrom;
+
+use curv::elliptic::curves::{Ed25519, Point};
+use ed25519_dalek;
+
+use protocols::{decode_point, ExpandedKeyPair, Signature};
+use Error::{self, InvalidKey};
+use VerifyError;
+
+impl<'a> From<&'a ed25519_dalek::SecretKey> for ExpandedKeyPair {
+    fn from(secret: &'a ed25519_dalek::SecretKey) -> ExpandedKeyPair {
+        ExpandedKeyPair::create_from_private_key(*secret.as_bytes())
+    }
+}
+
+impl<'a> From<&'a ed25519_dalek::Keypair> for ExpandedKeyPair {
+    fn from(keypair: &'a ed25519_dalek::Keypair) -> ExpandedKeyPair {
+        ExpandedKeyPair::from(&keypair.secret)
+    }
+}
+
+/// The scalar and the prefix are reduced mod l here, so the key signs for the same public key
+/// as the original, but its nonces differ from those of the dalek key of the same seed.
+impl<'a> From<&'a ExpandedKeyPair> for ed25519_dalek::ExpandedSecretKey {
+    fn from(keypair: &'a ExpandedKeyPair) -> ed25519_dalek::ExpandedSecretKey {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&keypair.expanded_private_key.private_key.to_bytes());
+        bytes[32..].copy_from_slice(&keypair.expanded_private_key.prefix.to_bytes());
+        ed25519_dalek::ExpandedSecretKey::from_bytes(&bytes)
+            .expect("expanded secret keys are 64 bytes, so can't fail")
+    }
+}
+
+/// Fails with `InvalidKey` for the identity, which dalek doesn't accept as a public key.
+pub fn to_dalek_public_key(public_key: &Point<Ed25519>) -> Result<ed25519_dalek::PublicKey, Error> {
+    if public_key.is_zero() {
+        return Err(InvalidKey);
+    }
+    ed25519_dalek::PublicKey::from_bytes(&public_key.to_bytes(true)).map_err(|_| InvalidKey)
+}
+
+pub fn from_dalek_public_key(
+    public_key: &ed25519_dalek::PublicKey,
+) -> Result<Point<Ed25519>, Error> {
+    decode_point(public_key.as_bytes()).ok_or(InvalidKey)
+}
+
+impl<'a> From<&'a Signature> for ed25519_dalek::Signature {
+    fn from(signature: &'a Signature) -> ed25519_dalek::Signature {
+        ed25519_dalek::Signature::from_bytes(&signature.to_bytes())
+            .expect("s is reduced, so the encoding is canonical")
+    }
+}
+
+/// Fails for the signatures `Signature::from_bytes` rejects, e.g. with a non canonical `s`.
+impl<'a> TryFrom<&'a ed25519_dalek::Signature> for Signature {
+    type Error = VerifyError;
+
+    fn try_from(signature: &'a ed25519_dalek::Signature) -> Result<Signature, VerifyError> {
+        Signature::from_bytes(&signature.to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::Verifier;
+    use protocols::aggsig;
+    use protocols::tests::deterministic_fast_rand;
+    use rand::Rng;
+
+    #[test]
+    fn test_dalek_conversions() {
+        let mut rng = deterministic_fast_rand("test_dalek_conversions", None);
+        let message = b"dalek";
+        let secret: [u8; 32] = rng.gen();
+
+        let dalek_secret = ed25519_dalek::SecretKey::from_bytes(&secret).unwrap();
+        let dalek_public = ed25519_dalek::PublicKey::from(&dalek_secret);
+        let keypair = ExpandedKeyPair::from(&dalek_secret);
+        assert_eq!(
+            from_dalek_public_key(&dalek_public).unwrap(),
+            keypair.public_key
+        );
+        assert_eq!(
+            to_dalek_public_key(&keypair.public_key).unwrap().to_bytes(),
+            dalek_public.to_bytes()
+        );
+        assert_eq!(to_dalek_public_key(&Point::zero()).unwrap_err(), InvalidKey);
+
+        // our signatures verify with dalek and the other way around
+        let signature = aggsig::sign_single(message, &keypair);
+        let dalek_signature = ed25519_dalek::Signature::from(&signature);
+        dalek_public.verify(message, &dalek_signature).unwrap();
+        assert_eq!(Signature::try_from(&dalek_signature).unwrap(), signature);
+
+        let expanded = ed25519_dalek::ExpandedSecretKey::from(&keypair);
+        let dalek_signature = expanded.sign(message, &dalek_public);
+        Signature::try_from(&dalek_signature)
+            .unwrap()
+            .verify(message, &keypair.public_key)
+            .unwrap();
+    }
+}
@@ -0,0 +1,310 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! did:key identifiers and multibase/multicodec encodings of public keys, and signature
+//! verification with the verification methods of DID documents.
+//!
+//! A key is encoded as the multicodec `ed25519-pub` (the varint `0xed 0x01`) followed by the
+//! 32 byte key, in multibase base58btc (`z`), so the did:key of every Ed25519 key starts with
+//! `did:key:z6Mk`. An aggregated key is encoded like any other key.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use curv::elliptic::curves::{Ed25519, Point};
+use serde_json::{json, Value};
+
+use base58;
+use protocols::{decode_point, Signature};
+use Error::{self, InvalidKey, InvalidSig};
+
+const MULTICODEC_ED25519_PUB: [u8; 2] = [0xed, 0x01];
+const MULTIBASE_BASE58BTC: char = 'z';
+const DID_KEY_PREFIX: &str = "did:key:";
+// the verification relationships a did:key document references its key from
+const DID_KEY_RELATIONSHIPS: [&str; 4] = [
+    "authentication",
+    "assertionMethod",
+    "capabilityInvocation",
+    "capabilityDelegation",
+];
+
+pub fn public_key_to_multicodec(public_key: &Point<Ed25519>) -> Vec<u8> {
+    let mut bytes = MULTICODEC_ED25519_PUB.to_vec();
+    bytes.extend_from_slice(&public_key.to_bytes(true));
+    bytes
+}
+
+pub fn public_key_from_multicodec(bytes: &[u8]) -> Result<Point<Ed25519>, Error> {
+    let key = bytes
+        .strip_prefix(&MULTICODEC_ED25519_PUB[..])
+        .ok_or(InvalidKey)?;
+    decode_point(key).ok_or(InvalidKey)
+}
+
+/// The `z6Mk...` multibase of the multicodec key.
+pub fn public_key_to_multibase(public_key: &Point<Ed25519>) -> String {
+    let mut multibase = String::new();
+    multibase.push(MULTIBASE_BASE58BTC);
+    multibase.push_str(&base58::encode(&public_key_to_multicodec(public_key)));
+    multibase
+}
+
+/// Only base58btc is accepted, the encoding of `publicKeyMultibase` and did:key.
+pub fn public_key_from_multibase(multibase: &str) -> Result<Point<Ed25519>, Error> {
+    let encoded = multibase
+        .strip_prefix(MULTIBASE_BASE58BTC)
+        .ok_or(InvalidKey)?;
+    public_key_from_multicodec(&base58::decode(encoded).ok_or(InvalidKey)?)
+}
+
+pub fn public_key_to_did_key(public_key: &Point<Ed25519>) -> String {
+    format!("{}{}", DID_KEY_PREFIX, public_key_to_multibase(public_key))
+}
+
+/// Accepts a did:key and the id of its verification method, whose fragment is the key again.
+pub fn public_key_from_did_key(did: &str) -> Result<Point<Ed25519>, Error> {
+    let multibase = did.strip_prefix(DID_KEY_PREFIX).ok_or(InvalidKey)?;
+    let (multibase, fragment) = match multibase.split_once('#') {
+        Some((multibase, fragment)) => (multibase, Some(fragment)),
+        None => (multibase, None),
+    };
+    if fragment.is_some_and(|fragment| fragment != multibase) {
+        return Err(InvalidKey);
+    }
+    public_key_from_multibase(multibase)
+}
+
+/// The DID document a did:key resolves to, with the key as an `Ed25519VerificationKey2020`
+/// referenced from all the verification relationships.
+pub fn did_key_document(public_key: &Point<Ed25519>) -> Value {
+    let did = public_key_to_did_key(public_key);
+    let multibase = public_key_to_multibase(public_key);
+    let id = format!("{}#{}", did, multibase);
+    let mut document = json!({
+        "@context": [
+            "https://www.w3.org/ns/did/v1",
+            "https://w3id.org/security/suites/ed25519-2020/v1"
+        ],
+        "id": did,
+        "verificationMethod": [{
+            "id": id,
+            "type": "Ed25519VerificationKey2020",
+            "controller": did,
+            "publicKeyMultibase": multibase
+        }]
+    });
+    for relationship in &DID_KEY_RELATIONSHIPS {
+        document[*relationship] = json!([id]);
+    }
+    document
+}
+
+/// The key of the verification method `id` (absolute or relative to the document, as in
+/// `#key-1`) of `document`, listed in `verificationMethod` or embedded in a verification
+/// relationship. Keys are read from `publicKeyMultibase` (`Ed25519VerificationKey2020`,
+/// `Multikey`) or `publicKeyBase58` (`Ed25519VerificationKey2018`).
+/// Fails with `InvalidKey` if there's no such method or it doesn't have an Ed25519 key.
+pub fn verification_method_key(document: &Value, id: &str) -> Result<Point<Ed25519>, Error> {
+    let id = absolute_id(document, id);
+    let embedded = DID_KEY_RELATIONSHIPS
+        .iter()
+        .chain(&["keyAgreement"])
+        .filter_map(|relationship| document[*relationship].as_array());
+    let method = document["verificationMethod"]
+        .as_array()
+        .into_iter()
+        .chain(embedded)
+        .flatten()
+        .find(|method| method["id"].as_str().map(|m| absolute_id(document, m)) == Some(id.clone()))
+        .ok_or(InvalidKey)?;
+    if let Some(multibase) = method["publicKeyMultibase"].as_str() {
+        public_key_from_multibase(multibase)
+    } else if let Some(encoded) = method["publicKeyBase58"].as_str() {
+        decode_point(&base58::decode(encoded).ok_or(InvalidKey)?).ok_or(InvalidKey)
+    } else {
+        Err(InvalidKey)
+    }
+}
+
+/// Checks `signature` of `message` under the verification method `id` of `document`, which
+/// has to be referenced from or embedded in `relationship` (e.g. `assertionMethod`) if it is
+/// given. Fails like `verification_method_key`, with `InvalidKey` if the method isn't in
+/// `relationship` and with `InvalidSig` if the signature doesn't verify.
+pub fn verify_with_document(
+    document: &Value,
+    id: &str,
+    relationship: Option<&str>,
+    message: &[u8],
+    signature: &Signature,
+) -> Result<(), Error> {
+    let public_key = verification_method_key(document, id)?;
+    if let Some(relationship) = relationship {
+        let absolute = absolute_id(document, id);
+        let referenced = document[relationship]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.as_str().or_else(|| entry["id"].as_str()))
+            .any(|entry| absolute_id(document, entry) == absolute);
+        if !referenced {
+            return Err(InvalidKey);
+        }
+    }
+    signature
+        .verify(message, &public_key)
+        .map_err(|_| InvalidSig)
+}
+
+// the id with the document's id in front of a relative `#fragment`
+fn absolute_id(document: &Value, id: &str) -> String {
+    match document["id"].as_str() {
+        Some(did) if id.starts_with('#') => format!("{}{}", did, id),
+        _ => String::from(id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use local;
+    use protocols::aggsig::{self, AggregatedKey};
+    use protocols::tests::deterministic_fast_rand;
+    use protocols::ExpandedKeyPair;
+
+    // the public key of RFC 8032 test 1
+    const PUBLIC_KEY: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+    const DID_KEY: &str = "did:key:z6MktwupdmLXVVqTzCw4i46r4uGyosGXRnR3XjN4Zq7oMMsw";
+
+    #[test]
+    fn test_did_key() {
+        let public_key = decode_point(&hex::decode(PUBLIC_KEY).unwrap()).unwrap();
+        assert_eq!(public_key_to_did_key(&public_key), DID_KEY);
+        assert_eq!(public_key_from_did_key(DID_KEY).unwrap(), public_key);
+        let method_id = format!("{}#{}", DID_KEY, &DID_KEY[8..]);
+        assert_eq!(public_key_from_did_key(&method_id).unwrap(), public_key);
+        let multicodec = public_key_to_multicodec(&public_key);
+        assert_eq!(multicodec[..2], [0xed, 0x01]);
+        assert_eq!(public_key_from_multicodec(&multicodec).unwrap(), public_key);
+
+        assert_eq!(
+            public_key_from_did_key(&format!("{}#key-1", DID_KEY)),
+            Err(InvalidKey)
+        );
+        assert_eq!(public_key_from_did_key(&DID_KEY[1..]), Err(InvalidKey));
+        // base58btc only
+        assert_eq!(
+            public_key_from_multibase(&DID_KEY[8..].replacen('z', "u", 1)),
+            Err(InvalidKey)
+        );
+        // x25519-pub
+        let mut x25519 = multicodec;
+        x25519[0] = 0xec;
+        assert_eq!(public_key_from_multicodec(&x25519), Err(InvalidKey));
+    }
+
+    #[test]
+    fn test_did_document_verification() {
+        let mut rng = deterministic_fast_rand("test_did_document_verification", None);
+        let signers = [
+            ExpandedKeyPair::create_rng(&mut rng),
+            ExpandedKeyPair::create_rng(&mut rng),
+        ];
+        let agg_key =
+            AggregatedKey::new(&[signers[0].public_key.clone(), signers[1].public_key.clone()]);
+        let message = b"credential";
+        let signature = local::sign_rng(message, &signers, &agg_key, &mut rng).unwrap();
+
+        let did = public_key_to_did_key(&agg_key.apk);
+        let document = did_key_document(&agg_key.apk);
+        let method_id = document["verificationMethod"][0]["id"].as_str().unwrap();
+        assert_eq!(document["id"], did);
+        assert_eq!(public_key_from_did_key(method_id).unwrap(), agg_key.apk);
+        assert!(verify_with_document(
+            &document,
+            method_id,
+            Some("assertionMethod"),
+            message,
+            &signature
+        )
+        .is_ok());
+        assert_eq!(
+            verify_with_document(&document, method_id, None, b"other", &signature),
+            Err(InvalidSig)
+        );
+        assert_eq!(
+            verify_with_document(
+                &document,
+                method_id,
+                Some("keyAgreement"),
+                message,
+                &signature
+            ),
+            Err(InvalidKey)
+        );
+
+        // a did:web document with a relative id, an embedded method and a 2018 key
+        let single = &signers[0];
+        let single_signature = aggsig::sign_single(message, single);
+        let document = json!({
+            "id": "did:web:example.com",
+            "verificationMethod": [{
+                "id": "#aggregated",
+                "type": "Multikey",
+                "controller": "did:web:example.com",
+                "publicKeyMultibase": public_key_to_multibase(&agg_key.apk)
+            }],
+            "assertionMethod": ["did:web:example.com#aggregated"],
+            "authentication": [{
+                "id": "did:web:example.com#single",
+                "type": "Ed25519VerificationKey2018",
+                "controller": "did:web:example.com",
+                "publicKeyBase58": base58::encode(&single.public_key.to_bytes(true))
+            }]
+        });
+        assert!(verify_with_document(
+            &document,
+            "did:web:example.com#aggregated",
+            Some("assertionMethod"),
+            message,
+            &signature
+        )
+        .is_ok());
+        assert!(verify_with_document(
+            &document,
+            "#single",
+            Some("authentication"),
+            message,
+            &single_signature
+        )
+        .is_ok());
+        assert_eq!(
+            verify_with_document(
+                &document,
+                "#single",
+                Some("assertionMethod"),
+                message,
+                &single_signature
+            ),
+            Err(InvalidKey)
+        );
+        assert_eq!(
+            verification_method_key(&document, "#missing"),
+            Err(InvalidKey)
+        );
+    }
+}
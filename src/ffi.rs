@@ -0,0 +1,494 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! C ABI for aggsig, so wallets written in other languages can link against the crate directly.
+//!
+//! Secret state lives behind opaque handles that are created by `mpe_*_create` and released with
+//! the matching `mpe_*_free`. Everything else crosses the boundary as fixed-size byte buffers:
+//! points and private keys are 32 bytes, signatures are 64 bytes (`R || s` as in RFC8032),
+//! commitments are 64 bytes and blind factors 32 bytes (big endian).
+//! Every function returns `MPE_OK` or one of the negative `MPE_ERR_*` codes.
+//!
+//! The flow mirrors `protocols::aggsig`: `mpe_keypair_create` and `mpe_key_agg_create`, then
+//! round 1 `mpe_ephemeral_key_create` (broadcast the commitment), round 2 broadcast `R` and the
+//! blind factor and check everyone else's with `mpe_verify_commitment`, round 3
+//! `mpe_partial_sign` and finally `mpe_add_signature_parts` and `mpe_verify`.
+//! `mpe_partial_sign` consumes the ephemeral key, so it can only be used for one signature.
+//!
+//! Build a static library with `cargo rustc --lib --release --features ffi --crate-type staticlib`.
+
+use core::slice;
+
+use curv::elliptic::curves::{Ed25519, Point};
+
+use protocols::aggsig::{
+    self, EphemeralKey, KeyAgg, SignFirstMsg, SignSecondMsg, SIGN_FIRST_MSG_SIZE,
+    SIGN_SECOND_MSG_SIZE,
+};
+use protocols::{decode_point, ExpandedKeyPair, Signature};
+use Error;
+
+pub const MPE_OK: i32 = 0;
+pub const MPE_ERR_NULL_POINTER: i32 = -1;
+// a buffer doesn't hold a canonical encoding, or a count is zero or out of range
+pub const MPE_ERR_INVALID_INPUT: i32 = -2;
+pub const MPE_ERR_INVALID_COMMITMENT: i32 = -3;
+pub const MPE_ERR_INVALID_SIGNATURE: i32 = -4;
+pub const MPE_ERR_INTERNAL: i32 = -5;
+
+pub const MPE_POINT_SIZE: usize = 32;
+pub const MPE_SIGNATURE_SIZE: usize = 64;
+pub const MPE_COMMITMENT_SIZE: usize = 64;
+pub const MPE_BLIND_FACTOR_SIZE: usize = 32;
+
+/// Opaque handle to an `ExpandedKeyPair`.
+pub struct MpeKeyPair(ExpandedKeyPair);
+/// Opaque handle to a `KeyAgg`.
+pub struct MpeKeyAgg(KeyAgg);
+/// Opaque handle to the secret nonce of round 1.
+pub struct MpeEphemeralKey(EphemeralKey);
+
+fn error_code(e: Error) -> i32 {
+    match e {
+        Error::InvalidCom => MPE_ERR_INVALID_COMMITMENT,
+        Error::InvalidSig | Error::InvalidPartialSignature { .. } => MPE_ERR_INVALID_SIGNATURE,
+        Error::InvalidPoint => MPE_ERR_INVALID_INPUT,
+        _ => MPE_ERR_INTERNAL,
+    }
+}
+
+macro_rules! try_ffi {
+    ($e:expr) => {
+        match $e {
+            Ok(v) => v,
+            Err(code) => return code,
+        }
+    };
+}
+
+unsafe fn input<'a>(ptr: *const u8, len: usize) -> Result<&'a [u8], i32> {
+    if len == 0 {
+        // a null pointer is fine for empty messages
+        Ok(&[])
+    } else if ptr.is_null() {
+        Err(MPE_ERR_NULL_POINTER)
+    } else {
+        Ok(slice::from_raw_parts(ptr, len))
+    }
+}
+
+unsafe fn output<'a>(ptr: *mut u8, len: usize) -> Result<&'a mut [u8], i32> {
+    if ptr.is_null() {
+        Err(MPE_ERR_NULL_POINTER)
+    } else {
+        Ok(slice::from_raw_parts_mut(ptr, len))
+    }
+}
+
+unsafe fn handle<'a, T>(ptr: *const T) -> Result<&'a T, i32> {
+    ptr.as_ref().ok_or(MPE_ERR_NULL_POINTER)
+}
+
+// `n` elements of `size` bytes each, back to back
+unsafe fn input_array<'a>(ptr: *const u8, n: usize, size: usize) -> Result<&'a [u8], i32> {
+    if n == 0 {
+        return Err(MPE_ERR_INVALID_INPUT);
+    }
+    input(ptr, n.checked_mul(size).ok_or(MPE_ERR_INVALID_INPUT)?)
+}
+
+unsafe fn points(ptr: *const u8, n: usize) -> Result<Vec<Point<Ed25519>>, i32> {
+    input_array(ptr, n, MPE_POINT_SIZE)?
+        .chunks(MPE_POINT_SIZE)
+        .map(|bytes| decode_point(bytes).ok_or(MPE_ERR_INVALID_INPUT))
+        .collect()
+}
+
+/// Creates a key pair from a fresh random private key.
+///
+/// # Safety
+/// `out_keypair` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn mpe_keypair_create(out_keypair: *mut *mut MpeKeyPair) -> i32 {
+    if out_keypair.is_null() {
+        return MPE_ERR_NULL_POINTER;
+    }
+    *out_keypair = Box::into_raw(Box::new(MpeKeyPair(ExpandedKeyPair::create())));
+    MPE_OK
+}
+
+/// Creates a key pair from a 32 byte Ed25519 private key.
+///
+/// # Safety
+/// `private_key` must point to 32 readable bytes and `out_keypair` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn mpe_keypair_from_private_key(
+    private_key: *const u8,
+    out_keypair: *mut *mut MpeKeyPair,
+) -> i32 {
+    let private_key = try_ffi!(input(private_key, 32));
+    if out_keypair.is_null() {
+        return MPE_ERR_NULL_POINTER;
+    }
+    let mut secret = [0u8; 32];
+    secret.copy_from_slice(private_key);
+    *out_keypair = Box::into_raw(Box::new(MpeKeyPair(
+        ExpandedKeyPair::create_from_private_key(secret),
+    )));
+    MPE_OK
+}
+
+/// Writes the 32 byte public key of `keypair` to `out_public_key`.
+///
+/// # Safety
+/// `keypair` must come from `mpe_keypair_create` and `out_public_key` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mpe_keypair_public_key(
+    keypair: *const MpeKeyPair,
+    out_public_key: *mut u8,
+) -> i32 {
+    let keypair = try_ffi!(handle(keypair));
+    let out = try_ffi!(output(out_public_key, MPE_POINT_SIZE));
+    out.copy_from_slice(&keypair.0.public_key.to_bytes(true));
+    MPE_OK
+}
+
+/// Releases (and zeroizes) a key pair, `keypair` may be null.
+///
+/// # Safety
+/// `keypair` must come from `mpe_keypair_create` and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn mpe_keypair_free(keypair: *mut MpeKeyPair) {
+    if !keypair.is_null() {
+        drop(Box::from_raw(keypair));
+    }
+}
+
+/// Aggregates the `n` public keys of all signers, `public_keys` holds them back to back, in the
+/// same order for everyone. `party_index` is our position in that list.
+/// Fails with `MPE_ERR_INVALID_INPUT` if it's out of range or a key is the identity.
+///
+/// # Safety
+/// `public_keys` must point to `n * 32` readable bytes and `out_key_agg` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn mpe_key_agg_create(
+    public_keys: *const u8,
+    n: usize,
+    party_index: usize,
+    out_key_agg: *mut *mut MpeKeyAgg,
+) -> i32 {
+    let public_keys = try_ffi!(points(public_keys, n));
+    if out_key_agg.is_null() {
+        return MPE_ERR_NULL_POINTER;
+    }
+    let key_agg = try_ffi!(KeyAgg::key_aggregation_n_checked(&public_keys, party_index)
+        .map_err(|_| MPE_ERR_INVALID_INPUT));
+    *out_key_agg = Box::into_raw(Box::new(MpeKeyAgg(key_agg)));
+    MPE_OK
+}
+
+/// Writes the 32 byte aggregated public key to `out_public_key`.
+///
+/// # Safety
+/// `key_agg` must come from `mpe_key_agg_create` and `out_public_key` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mpe_key_agg_public_key(
+    key_agg: *const MpeKeyAgg,
+    out_public_key: *mut u8,
+) -> i32 {
+    let key_agg = try_ffi!(handle(key_agg));
+    let out = try_ffi!(output(out_public_key, MPE_POINT_SIZE));
+    out.copy_from_slice(&key_agg.0.apk.to_bytes(true));
+    MPE_OK
+}
+
+/// # Safety
+/// `key_agg` must come from `mpe_key_agg_create` and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn mpe_key_agg_free(key_agg: *mut MpeKeyAgg) {
+    if !key_agg.is_null() {
+        drop(Box::from_raw(key_agg));
+    }
+}
+
+/// Round 1, creates the ephemeral key and writes the commitment to broadcast now, and the `R`
+/// and blind factor to broadcast in round 2.
+///
+/// # Safety
+/// `message` must point to `message_len` readable bytes, the outputs must point to 64, 32 and 32
+/// writable bytes and `out_ephemeral_key` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn mpe_ephemeral_key_create(
+    keypair: *const MpeKeyPair,
+    message: *const u8,
+    message_len: usize,
+    out_ephemeral_key: *mut *mut MpeEphemeralKey,
+    out_commitment: *mut u8,
+    out_R: *mut u8,
+    out_blind_factor: *mut u8,
+) -> i32 {
+    let keypair = try_ffi!(handle(keypair));
+    let message = try_ffi!(input(message, message_len));
+    let out_commitment = try_ffi!(output(out_commitment, MPE_COMMITMENT_SIZE));
+    let out_R = try_ffi!(output(out_R, MPE_POINT_SIZE));
+    let out_blind_factor = try_ffi!(output(out_blind_factor, MPE_BLIND_FACTOR_SIZE));
+    if out_ephemeral_key.is_null() {
+        return MPE_ERR_NULL_POINTER;
+    }
+    let (ephemeral_key, sign_first_msg, sign_second_msg) =
+        try_ffi!(aggsig::create_ephemeral_key_and_commit(&keypair.0, message).map_err(error_code));
+    let first_bytes = try_ffi!(sign_first_msg.to_bytes().map_err(error_code));
+    let second_bytes = try_ffi!(sign_second_msg.to_bytes().map_err(error_code));
+    out_commitment.copy_from_slice(&first_bytes);
+    out_R.copy_from_slice(&second_bytes[..MPE_POINT_SIZE]);
+    out_blind_factor.copy_from_slice(&second_bytes[MPE_POINT_SIZE..]);
+    *out_ephemeral_key = Box::into_raw(Box::new(MpeEphemeralKey(ephemeral_key)));
+    MPE_OK
+}
+
+/// Round 2, checks that another party's revealed `R` matches its round 1 commitment.
+/// Returns `MPE_ERR_INVALID_COMMITMENT` if it doesn't.
+///
+/// # Safety
+/// `commitment`, `R` and `blind_factor` must point to 64, 32 and 32 readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mpe_verify_commitment(
+    commitment: *const u8,
+    R: *const u8,
+    blind_factor: *const u8,
+) -> i32 {
+    let commitment = try_ffi!(input(commitment, MPE_COMMITMENT_SIZE));
+    let R = try_ffi!(input(R, MPE_POINT_SIZE));
+    let blind_factor = try_ffi!(input(blind_factor, MPE_BLIND_FACTOR_SIZE));
+    let mut first_bytes = [0u8; SIGN_FIRST_MSG_SIZE];
+    first_bytes.copy_from_slice(commitment);
+    let mut second_bytes = [0u8; SIGN_SECOND_MSG_SIZE];
+    second_bytes[..MPE_POINT_SIZE].copy_from_slice(R);
+    second_bytes[MPE_POINT_SIZE..].copy_from_slice(blind_factor);
+    let sign_first_msg = SignFirstMsg::from_bytes(&first_bytes);
+    let sign_second_msg = try_ffi!(SignSecondMsg::from_bytes(&second_bytes).map_err(error_code));
+    try_ffi!(aggsig::verify_commitment(&sign_first_msg, &sign_second_msg).map_err(error_code));
+    MPE_OK
+}
+
+/// Round 3, writes our 64 byte partial signature to `out_partial_sig`.
+/// `Rs` holds the `n` revealed `R` of every signer (including ours) back to back.
+/// `ephemeral_key` is released by the call, also when it fails, it must not be used or freed
+/// afterwards.
+///
+/// # Safety
+/// The handles must come from their `mpe_*_create` functions, `Rs` must point to `n * 32`
+/// readable bytes, `message` to `message_len` readable bytes and `out_partial_sig` to 64 writable
+/// bytes.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn mpe_partial_sign(
+    ephemeral_key: *mut MpeEphemeralKey,
+    keypair: *const MpeKeyPair,
+    key_agg: *const MpeKeyAgg,
+    Rs: *const u8,
+    n: usize,
+    message: *const u8,
+    message_len: usize,
+    out_partial_sig: *mut u8,
+) -> i32 {
+    if ephemeral_key.is_null() {
+        return MPE_ERR_NULL_POINTER;
+    }
+    let ephemeral_key = Box::from_raw(ephemeral_key);
+    let keypair = try_ffi!(handle(keypair));
+    let key_agg = try_ffi!(handle(key_agg));
+    let Rs = try_ffi!(points(Rs, n));
+    let message = try_ffi!(input(message, message_len));
+    let out = try_ffi!(output(out_partial_sig, MPE_SIGNATURE_SIZE));
+    let partial_sig = aggsig::partial_sign(
+        ephemeral_key.0,
+        &keypair.0,
+        &key_agg.0.hash,
+        &try_ffi!(aggsig::get_R_tot(&Rs).ok_or(MPE_ERR_INVALID_INPUT)),
+        &key_agg.0.apk,
+        message,
+    );
+    out.copy_from_slice(&partial_sig.to_bytes());
+    MPE_OK
+}
+
+/// Releases (and zeroizes) an ephemeral key, `ephemeral_key` may be null.
+///
+/// # Safety
+/// `ephemeral_key` must come from `mpe_ephemeral_key_create` and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn mpe_ephemeral_key_free(ephemeral_key: *mut MpeEphemeralKey) {
+    if !ephemeral_key.is_null() {
+        drop(Box::from_raw(ephemeral_key));
+    }
+}
+
+/// Combines the `n` partial signatures, stored back to back in `partial_sigs`, into the 64 byte
+/// signature `out_signature`.
+///
+/// # Safety
+/// `partial_sigs` must point to `n * 64` readable bytes and `out_signature` to 64 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mpe_add_signature_parts(
+    partial_sigs: *const u8,
+    n: usize,
+    out_signature: *mut u8,
+) -> i32 {
+    let partial_sigs = try_ffi!(input_array(partial_sigs, n, MPE_SIGNATURE_SIZE));
+    let out = try_ffi!(output(out_signature, MPE_SIGNATURE_SIZE));
+    let partial_sigs = try_ffi!(partial_sigs
+        .chunks(MPE_SIGNATURE_SIZE)
+        .map(|bytes| Signature::from_bytes(bytes).map_err(|_| MPE_ERR_INVALID_INPUT))
+        .collect::<Result<Vec<_>, _>>());
+    let signature = try_ffi!(aggsig::add_signature_parts(&partial_sigs).map_err(error_code));
+    out.copy_from_slice(&signature.to_bytes());
+    MPE_OK
+}
+
+/// Verifies a 64 byte signature over `message` under the 32 byte `public_key`.
+/// Returns `MPE_ERR_INVALID_SIGNATURE` if it doesn't verify.
+///
+/// # Safety
+/// `signature`, `message` and `public_key` must point to 64, `message_len` and 32 readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mpe_verify(
+    signature: *const u8,
+    message: *const u8,
+    message_len: usize,
+    public_key: *const u8,
+) -> i32 {
+    let signature = try_ffi!(input(signature, MPE_SIGNATURE_SIZE));
+    let message = try_ffi!(input(message, message_len));
+    let public_key = try_ffi!(input(public_key, MPE_POINT_SIZE));
+    match Signature::verify_bytes(signature, message, public_key) {
+        Ok(()) => MPE_OK,
+        Err(_) => MPE_ERR_INVALID_SIGNATURE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ptr;
+
+    #[test]
+    fn test_ffi_two_party_signing() {
+        let message = b"ffi";
+        unsafe {
+            let mut keypairs = [ptr::null_mut(); 2];
+            let mut public_keys = [0u8; 2 * MPE_POINT_SIZE];
+            for (i, keypair) in keypairs.iter_mut().enumerate() {
+                assert_eq!(mpe_keypair_create(keypair), MPE_OK);
+                let out = public_keys[i * MPE_POINT_SIZE..].as_mut_ptr();
+                assert_eq!(mpe_keypair_public_key(*keypair, out), MPE_OK);
+            }
+
+            let mut key_aggs = [ptr::null_mut(); 2];
+            for (i, key_agg) in key_aggs.iter_mut().enumerate() {
+                assert_eq!(
+                    mpe_key_agg_create(public_keys.as_ptr(), 2, i, key_agg),
+                    MPE_OK
+                );
+            }
+            let mut apk = [0u8; MPE_POINT_SIZE];
+            assert_eq!(
+                mpe_key_agg_public_key(key_aggs[0], apk.as_mut_ptr()),
+                MPE_OK
+            );
+
+            let mut ephemeral_keys = [ptr::null_mut(); 2];
+            let mut commitments = [[0u8; MPE_COMMITMENT_SIZE]; 2];
+            let mut Rs = [0u8; 2 * MPE_POINT_SIZE];
+            let mut blind_factors = [[0u8; MPE_BLIND_FACTOR_SIZE]; 2];
+            for i in 0..2 {
+                assert_eq!(
+                    mpe_ephemeral_key_create(
+                        keypairs[i],
+                        message.as_ptr(),
+                        message.len(),
+                        &mut ephemeral_keys[i],
+                        commitments[i].as_mut_ptr(),
+                        Rs[i * MPE_POINT_SIZE..].as_mut_ptr(),
+                        blind_factors[i].as_mut_ptr(),
+                    ),
+                    MPE_OK
+                );
+            }
+            for i in 0..2 {
+                let R = Rs[i * MPE_POINT_SIZE..].as_ptr();
+                assert_eq!(
+                    mpe_verify_commitment(commitments[i].as_ptr(), R, blind_factors[i].as_ptr()),
+                    MPE_OK
+                );
+                assert_eq!(
+                    mpe_verify_commitment(
+                        commitments[i].as_ptr(),
+                        R,
+                        blind_factors[1 - i].as_ptr()
+                    ),
+                    MPE_ERR_INVALID_COMMITMENT
+                );
+            }
+
+            let mut partial_sigs = [0u8; 2 * MPE_SIGNATURE_SIZE];
+            for i in 0..2 {
+                assert_eq!(
+                    mpe_partial_sign(
+                        ephemeral_keys[i],
+                        keypairs[i],
+                        key_aggs[i],
+                        Rs.as_ptr(),
+                        2,
+                        message.as_ptr(),
+                        message.len(),
+                        partial_sigs[i * MPE_SIGNATURE_SIZE..].as_mut_ptr(),
+                    ),
+                    MPE_OK
+                );
+            }
+            let mut signature = [0u8; MPE_SIGNATURE_SIZE];
+            assert_eq!(
+                mpe_add_signature_parts(partial_sigs.as_ptr(), 2, signature.as_mut_ptr()),
+                MPE_OK
+            );
+            assert_eq!(
+                mpe_verify(
+                    signature.as_ptr(),
+                    message.as_ptr(),
+                    message.len(),
+                    apk.as_ptr()
+                ),
+                MPE_OK
+            );
+            assert_eq!(
+                mpe_verify(signature.as_ptr(), message.as_ptr(), 2, apk.as_ptr()),
+                MPE_ERR_INVALID_SIGNATURE
+            );
+            assert_eq!(
+                mpe_verify(signature.as_ptr(), message.as_ptr(), 2, ptr::null()),
+                MPE_ERR_NULL_POINTER
+            );
+
+            // the ephemeral keys were consumed by mpe_partial_sign
+            for i in 0..2 {
+                mpe_key_agg_free(key_aggs[i]);
+                mpe_keypair_free(keypairs[i]);
+            }
+        }
+    }
+}
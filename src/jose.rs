@@ -0,0 +1,309 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! EdDSA JSON Web Signatures (RFC 7515, RFC 8037) in the compact serialization and JWTs
+//! (RFC 7519) signed with them, plus `OKP` JSON Web Keys of public keys.
+//!
+//! The signing input is `BASE64URL(header) || '.' || BASE64URL(payload)` without padding,
+//! `sign` runs the signing protocol on it, so the key can be a single key, an aggregated key
+//! or the key of a threshold sharing. Verification only accepts `"alg": "EdDSA"` headers and
+//! fails with `InvalidMessage` for malformed tokens and with `InvalidSig` for tokens that
+//! don't verify.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use base64ct::{Base64UrlUnpadded, Encoding};
+use curv::elliptic::curves::{Ed25519, Point};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use protocols::{decode_point, Signature};
+use Error::{self, InvalidKey, InvalidMessage, InvalidSig};
+
+const ALGORITHM: &str = "EdDSA";
+
+/// The protected header fields besides `alg`.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Header {
+    pub typ: Option<String>,
+    pub kid: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawHeader {
+    alg: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    typ: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+    // critical extensions, none are understood here
+    #[serde(default, skip_serializing)]
+    crit: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Jwk {
+    kty: String,
+    crv: String,
+    x: String,
+}
+
+/// The signing input of `payload` under `header`.
+pub fn signing_input(header: &Header, payload: &[u8]) -> Result<String, Error> {
+    let header = serde_json::to_vec(&RawHeader {
+        alg: String::from(ALGORITHM),
+        typ: header.typ.clone(),
+        kid: header.kid.clone(),
+        crit: None,
+    })
+    .map_err(|_| InvalidMessage)?;
+    Ok(format!(
+        "{}.{}",
+        Base64UrlUnpadded::encode_string(&header),
+        Base64UrlUnpadded::encode_string(payload)
+    ))
+}
+
+/// The compact JWS of `payload` by `public_key`, `sign` runs the signing protocol on the
+/// `signing_input`. Fails with `InvalidSig` if the signature of `sign` doesn't verify.
+pub fn sign(
+    header: &Header,
+    payload: &[u8],
+    public_key: &Point<Ed25519>,
+    sign: impl FnOnce(&[u8]) -> Result<Signature, Error>,
+) -> Result<String, Error> {
+    let input = signing_input(header, payload)?;
+    let signature = sign(input.as_bytes())?;
+    signature
+        .verify(input.as_bytes(), public_key)
+        .map_err(|_| InvalidSig)?;
+    Ok(format!(
+        "{}.{}",
+        input,
+        Base64UrlUnpadded::encode_string(&signature.to_bytes())
+    ))
+}
+
+/// Checks a compact JWS under `public_key` and returns its header and payload.
+/// Headers with critical extensions are rejected, as none are understood.
+pub fn verify(jws: &str, public_key: &Point<Ed25519>) -> Result<(Header, Vec<u8>), Error> {
+    let mut parts = jws.split('.');
+    let (header, payload, signature) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(header), Some(payload), Some(signature)) if parts.next().is_none() => {
+            (header, payload, signature)
+        }
+        _ => return Err(InvalidMessage),
+    };
+    // the signing input as received, not re-encoded
+    let input_len = header.len() + 1 + payload.len();
+    let header_json = Base64UrlUnpadded::decode_vec(header).map_err(|_| InvalidMessage)?;
+    let header: RawHeader = serde_json::from_slice(&header_json).map_err(|_| InvalidMessage)?;
+    if header.alg != ALGORITHM || header.crit.is_some() {
+        return Err(InvalidSig);
+    }
+    let payload = Base64UrlUnpadded::decode_vec(payload).map_err(|_| InvalidMessage)?;
+    let signature = Base64UrlUnpadded::decode_vec(signature).map_err(|_| InvalidMessage)?;
+    let signature = Signature::from_bytes(&signature).map_err(|_| InvalidSig)?;
+    signature
+        .verify(&jws.as_bytes()[..input_len], public_key)
+        .map_err(|_| InvalidSig)?;
+    Ok((
+        Header {
+            typ: header.typ,
+            kid: header.kid,
+        },
+        payload,
+    ))
+}
+
+/// A JWT of `claims` with `"typ": "JWT"` and `kid`, signed like `sign`.
+pub fn sign_jwt(
+    claims: &impl Serialize,
+    kid: Option<&str>,
+    public_key: &Point<Ed25519>,
+    sign: impl FnOnce(&[u8]) -> Result<Signature, Error>,
+) -> Result<String, Error> {
+    let header = Header {
+        typ: Some(String::from("JWT")),
+        kid: kid.map(String::from),
+    };
+    let claims = serde_json::to_vec(claims).map_err(|_| InvalidMessage)?;
+    self::sign(&header, &claims, public_key, sign)
+}
+
+/// Checks a JWT like `verify` and returns its claims, validating them (`exp`, `aud`, ...) is
+/// left to the caller.
+pub fn verify_jwt<T: DeserializeOwned>(jwt: &str, public_key: &Point<Ed25519>) -> Result<T, Error> {
+    let (_, claims) = verify(jwt, public_key)?;
+    serde_json::from_slice(&claims).map_err(|_| InvalidMessage)
+}
+
+/// The `{"kty":"OKP","crv":"Ed25519","x":...}` JWK of `public_key`.
+pub fn public_key_to_jwk(public_key: &Point<Ed25519>) -> String {
+    serde_json::to_string(&Jwk {
+        kty: String::from("OKP"),
+        crv: String::from("Ed25519"),
+        x: Base64UrlUnpadded::encode_string(&public_key.to_bytes(true)),
+    })
+    .expect("a JWK serializes")
+}
+
+/// Parses an Ed25519 `OKP` JWK, other members (`kid`, `use`, ...) are ignored.
+/// Fails with `InvalidKey` for anything else, including private keys.
+pub fn public_key_from_jwk(jwk: &str) -> Result<Point<Ed25519>, Error> {
+    let value: serde_json::Value = serde_json::from_str(jwk).map_err(|_| InvalidKey)?;
+    if value.get("d").is_some() {
+        return Err(InvalidKey);
+    }
+    let jwk: Jwk = serde_json::from_value(value).map_err(|_| InvalidKey)?;
+    if jwk.kty != "OKP" || jwk.crv != "Ed25519" {
+        return Err(InvalidKey);
+    }
+    let x = Base64UrlUnpadded::decode_vec(&jwk.x).map_err(|_| InvalidKey)?;
+    decode_point(&x).ok_or(InvalidKey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use local;
+    use protocols::aggsig::{self, AggregatedKey};
+    use protocols::frost;
+    use protocols::keygen::{self, Parameters};
+    use protocols::tests::deterministic_fast_rand;
+    use protocols::ExpandedKeyPair;
+
+    // RFC 8037 appendix A
+    const JWK: &str =
+        r#"{"kty":"OKP","crv":"Ed25519","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}"#;
+    const SEED: &str = "nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A";
+    const JWS: &str = "eyJhbGciOiJFZERTQSJ9.RXhhbXBsZSBvZiBFZDI1NTE5IHNpZ25pbmc.hgyY0il_MGCjP0JzlnLWG1PPOt7-09PGcvMg3AIbQR6dWbhijcNR4ki4iylGjg5BhVsPt9g7sVvpAr_MuM0KAg";
+
+    #[test]
+    fn test_jose_rfc8037() {
+        let public_key = public_key_from_jwk(JWK).unwrap();
+        assert_eq!(public_key_to_jwk(&public_key), JWK);
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&Base64UrlUnpadded::decode_vec(SEED).unwrap());
+        let keypair = ExpandedKeyPair::from_seed_bytes(seed);
+        assert_eq!(keypair.public_key, public_key);
+
+        let (header, payload) = verify(JWS, &public_key).unwrap();
+        assert_eq!(header, Header::default());
+        assert_eq!(payload, b"Example of Ed25519 signing");
+        assert_eq!(
+            signing_input(&header, &payload).unwrap(),
+            JWS[..JWS.rfind('.').unwrap()]
+        );
+        let jws = sign(&header, &payload, &public_key, |input| {
+            Ok(aggsig::sign_single(input, &keypair))
+        })
+        .unwrap();
+        assert_eq!(verify(&jws, &public_key).unwrap().1, payload);
+
+        let other = ExpandedKeyPair::create_from_private_key([1; 32]).public_key;
+        assert_eq!(verify(JWS, &other), Err(InvalidSig));
+        // the payload isn't re-encoded, a padded one doesn't verify
+        assert_eq!(
+            verify(&JWS.replacen(".RXhh", ".RXhh=", 1), &public_key),
+            Err(InvalidMessage)
+        );
+        assert_eq!(verify(&JWS[..100], &public_key), Err(InvalidMessage));
+        assert_eq!(
+            verify(&format!("{}.", JWS), &public_key),
+            Err(InvalidMessage)
+        );
+        // {"alg":"none"}
+        let none = format!("eyJhbGciOiJub25lIn0{}", &JWS[JWS.find('.').unwrap()..]);
+        assert_eq!(verify(&none, &public_key), Err(InvalidSig));
+        // {"alg":"EdDSA","crit":["exp"]}
+        let crit = format!(
+            "{}.{}",
+            Base64UrlUnpadded::encode_string(br#"{"alg":"EdDSA","crit":["exp"]}"#),
+            &JWS[JWS.find('.').unwrap() + 1..]
+        );
+        assert_eq!(verify(&crit, &public_key), Err(InvalidSig));
+
+        let private_jwk = JWK.replace("\"x\"", &format!("\"d\":\"{}\",\"x\"", SEED));
+        assert_eq!(public_key_from_jwk(&private_jwk), Err(InvalidKey));
+        assert_eq!(
+            public_key_from_jwk(&JWK.replace("Ed25519", "X25519")),
+            Err(InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_jwt_aggregated_and_threshold_keys() {
+        let mut rng = deterministic_fast_rand("test_jwt_aggregated_and_threshold_keys", None);
+        let claims = serde_json::json!({"sub": "1234567890", "iat": 1516239022});
+
+        let signers = [
+            ExpandedKeyPair::create_rng(&mut rng),
+            ExpandedKeyPair::create_rng(&mut rng),
+        ];
+        let agg_key =
+            AggregatedKey::new(&[signers[0].public_key.clone(), signers[1].public_key.clone()]);
+        let jwt = sign_jwt(&claims, Some("aggregated"), &agg_key.apk, |input| {
+            local::sign_rng(input, &signers, &agg_key, &mut rng)
+        })
+        .unwrap();
+        let (header, _) = verify(&jwt, &agg_key.apk).unwrap();
+        assert_eq!(header.typ.as_deref(), Some("JWT"));
+        assert_eq!(header.kid.as_deref(), Some("aggregated"));
+        assert_eq!(
+            verify_jwt::<serde_json::Value>(&jwt, &agg_key.apk).unwrap(),
+            claims
+        );
+        // a session signing with only one of the co-signers
+        assert_eq!(
+            sign_jwt(&claims, None, &agg_key.apk, |input| Ok(
+                aggsig::sign_single(input, &signers[0])
+            )),
+            Err(InvalidSig)
+        );
+
+        // 2-of-3 FROST
+        let keypair = ExpandedKeyPair::create_rng(&mut rng);
+        let shares = keygen::deal_rng(
+            &Parameters {
+                threshold: 1,
+                share_count: 3,
+            },
+            &keypair,
+            &mut rng,
+        )
+        .unwrap();
+        let jwt = sign_jwt(&claims, None, &keypair.public_key, |input| {
+            let (nonces, commitments): (Vec<_>, Vec<_>) = [&shares[0], &shares[2]]
+                .iter()
+                .map(|keys| frost::preprocess_rng(keys, &mut rng))
+                .unzip();
+            let partial_sigs = nonces
+                .into_iter()
+                .zip([&shares[0], &shares[2]])
+                .map(|(nonces, keys)| frost::partial_sign(keys, nonces, &commitments, input))
+                .collect::<Result<Vec<_>, _>>()?;
+            frost::aggregate(&shares[0], &commitments, &partial_sigs, input)
+        })
+        .unwrap();
+        assert_eq!(
+            verify_jwt::<serde_json::Value>(&jwt, &keypair.public_key).unwrap(),
+            claims
+        );
+    }
+}
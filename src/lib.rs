@@ -14,38 +14,199 @@
     @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
 */
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate core;
 extern crate curv;
+extern crate curve25519_dalek;
 
 extern crate hex;
+extern crate hmac;
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate rand;
+#[cfg(feature = "std")]
 extern crate serde_json;
 extern crate sha2;
+extern crate zeroize;
 
-#[cfg(test)]
+#[cfg(feature = "async")]
+extern crate futures_core;
+#[cfg(feature = "async")]
+extern crate futures_sink;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary as arbitrary_crate;
+#[cfg(feature = "proptest")]
+extern crate proptest as proptest_crate;
+
+#[cfg(any(
+    feature = "jose",
+    feature = "minisign",
+    feature = "openpgp",
+    feature = "openssh",
+    feature = "pkcs8"
+))]
+extern crate base64ct;
+#[cfg(feature = "minisign")]
+extern crate blake2;
+#[cfg(any(test, feature = "dalek"))]
 extern crate ed25519_dalek;
+#[cfg(feature = "ed448")]
+extern crate ed448_goldilocks;
 #[cfg(test)]
 extern crate itertools;
-#[cfg(test)]
-extern crate rand_xoshiro;
+#[cfg(feature = "transcript")]
+extern crate merlin;
+#[cfg(feature = "pkcs8")]
+extern crate pkcs8 as pkcs8_der;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(feature = "openpgp")]
+extern crate sha1;
+#[cfg(any(test, feature = "ed448", feature = "tor"))]
+extern crate sha3;
+#[cfg(feature = "noise")]
+extern crate snow;
+#[cfg(feature = "tracing")]
+extern crate tracing;
 
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(any(feature = "stellar", feature = "tor"))]
+mod base32;
+#[cfg(any(feature = "did", feature = "near", feature = "solana"))]
+mod base58;
+#[cfg(any(
+    feature = "near",
+    feature = "solana",
+    feature = "stellar",
+    feature = "tendermint"
+))]
+pub mod chains;
+#[cfg(feature = "cose")]
+pub mod cose;
+#[cfg(feature = "dalek")]
+pub mod dalek;
+#[cfg(feature = "did")]
+pub mod did;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "jose")]
+pub mod jose;
+#[cfg(any(
+    feature = "solana",
+    feature = "stellar",
+    all(
+        test,
+        any(
+            feature = "cose",
+            feature = "did",
+            feature = "jose",
+            feature = "minisign",
+            feature = "openpgp",
+            feature = "openssh",
+            feature = "x509"
+        )
+    )
+))]
+mod local;
+#[cfg(feature = "minisign")]
+pub mod minisign;
+#[cfg(feature = "noise")]
+pub mod noise;
+#[cfg(feature = "openpgp")]
+pub mod openpgp;
+#[cfg(feature = "openssh")]
+pub mod openssh;
+#[cfg(feature = "pkcs8")]
+pub mod pkcs8;
+#[cfg(feature = "proptest")]
+pub mod proptest;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
 pub mod protocols;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "tor")]
+pub mod tor;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "x509")]
+pub mod x509;
 
+/// Errors of the multi-party protocols.
+/// `party` is the index of the party whose message failed verification, so the caller can exclude it.
 #[derive(Copy, PartialEq, Eq, Clone, Debug)]
 pub enum Error {
     InvalidKey,
     InvalidSS,
     InvalidCom,
     InvalidSig,
+    InvalidContext,
+    // the point is zero where a nonzero point is required
+    InvalidPoint,
+    // messages are missing, duplicated or don't match the protocol parameters
+    RoundMismatch,
+    // non-hardened derivation was asked for a hardened index
+    InvalidDerivationIndex,
+    // an encrypted message failed authentication or couldn't be decoded
+    InvalidMessage,
+    InvalidCommitment { party: u16 },
+    InvalidProof { party: u16 },
+    InvalidShare { party: u16 },
+    InvalidPartialSignature { party: u16 },
 }
 
-use std::fmt;
+use core::fmt;
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", &self)
+        match self {
+            Error::InvalidKey => write!(f, "invalid key"),
+            Error::InvalidSS => write!(f, "invalid secret share"),
+            Error::InvalidCom => write!(f, "invalid commitment"),
+            Error::InvalidSig => write!(f, "invalid signature"),
+            Error::InvalidContext => write!(f, "context is longer than 255 bytes"),
+            Error::InvalidPoint => write!(f, "invalid point"),
+            Error::RoundMismatch => write!(f, "messages don't match the protocol round"),
+            Error::InvalidDerivationIndex => write!(f, "index can't be derived non-hardened"),
+            Error::InvalidMessage => write!(f, "invalid message"),
+            Error::InvalidCommitment { party } => {
+                write!(f, "invalid commitment from party {}", party)
+            }
+            Error::InvalidProof { party } => write!(f, "invalid proof from party {}", party),
+            Error::InvalidShare { party } => write!(f, "invalid secret share from party {}", party),
+            Error::InvalidPartialSignature { party } => {
+                write!(f, "invalid partial signature from party {}", party)
+            }
+        }
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for Error {}
+
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum VerifyError {
+    InvalidPublicKey,
+    InvalidR,
+    NonCanonicalS,
+    InvalidSignature,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for VerifyError {}
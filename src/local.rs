@@ -0,0 +1,56 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! All the aggsig rounds in one call, for co-signers whose keys are all at hand.
+
+use alloc::vec::Vec;
+
+use rand::{CryptoRng, RngCore};
+
+use protocols::aggsig::{self, AggregatedKey};
+use protocols::{ExpandedKeyPair, Signature};
+use Error::{self, InvalidKey};
+
+/// The signature of `message` under `agg_key`, the aggregation of the keys of `signers`.
+pub fn sign_rng(
+    message: &[u8],
+    signers: &[ExpandedKeyPair],
+    agg_key: &AggregatedKey,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<Signature, Error> {
+    let pks: Vec<_> = signers.iter().map(|keys| keys.public_key.clone()).collect();
+    let mut ephemeral_keys = Vec::with_capacity(signers.len());
+    let mut first_msgs = Vec::with_capacity(signers.len());
+    let mut second_msgs = Vec::with_capacity(signers.len());
+    for keys in signers {
+        let (ephemeral_key, first_msg, second_msg) =
+            aggsig::create_ephemeral_key_and_commit_rng(keys, message, rng)?;
+        ephemeral_keys.push(ephemeral_key);
+        first_msgs.push(first_msg);
+        second_msgs.push(second_msg);
+    }
+    let R_tot = aggsig::get_R_tot(second_msgs.iter().map(|msg| &msg.R)).ok_or(InvalidKey)?;
+    let partial_sigs: Vec<_> = ephemeral_keys
+        .into_iter()
+        .zip(signers)
+        .zip(&agg_key.coefficients)
+        .map(|((ephemeral_key, keys), a)| {
+            aggsig::partial_sign(ephemeral_key, keys, a, &R_tot, &agg_key.apk, message)
+        })
+        .collect();
+    agg_key.aggregate_and_verify(&partial_sigs, &first_msgs, &second_msgs, &pks, message)
+}
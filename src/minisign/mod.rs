@@ -0,0 +1,454 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! minisign public keys, secret keys and signatures, and OpenBSD signify ones in `signify`, so
+//! files signed by an aggregated or threshold key verify with `minisign -V` and `signify -V`.
+//!
+//! Both tools name a key with a random 8 byte key id, which is in the public key and in every
+//! signature. To move an existing signing key to a committee without touching the public key
+//! downstream verifies with, `read_secret_key` imports it with its key id and the key is dealt to
+//! the co-signers with `keygen::deal`.
+//!
+//! A minisign signature is two Ed25519 signatures, one of the BLAKE2b-512 hash of the file and
+//! one of that signature followed by the trusted comment, so the co-signers sign twice.
+
+pub mod signify;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use base64ct::{Base64, Encoding};
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Blake2b512, Digest};
+use curv::elliptic::curves::{Ed25519, Point};
+use rand::{thread_rng, CryptoRng, Rng, RngCore};
+
+use protocols::{decode_point, ExpandedKeyPair, Signature};
+use Error::{self, InvalidKey, InvalidMessage, InvalidSig};
+
+pub const KEY_ID_SIZE: usize = 8;
+
+const ALGORITHM: &[u8; 2] = b"Ed";
+const PREHASHED_ALGORITHM: &[u8; 2] = b"ED";
+const CHECKSUM_ALGORITHM: &[u8; 2] = b"B2";
+const UNTRUSTED_COMMENT: &str = "untrusted comment: ";
+const TRUSTED_COMMENT: &str = "trusted comment: ";
+const PUBLIC_KEY_SIZE: usize = 2 + KEY_ID_SIZE + 32;
+const SIGNATURE_SIZE: usize = 2 + KEY_ID_SIZE + 64;
+// algorithm, kdf, checksum algorithm, salt, ops and memory limits, key id, key, checksum
+const SECRET_KEY_SIZE: usize = 2 + 2 + 2 + 32 + 8 + 8 + KEY_ID_SIZE + 64 + 32;
+
+/// An Ed25519 key with the key id signatures name it by, the same for minisign and signify.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PublicKey {
+    pub key_id: [u8; KEY_ID_SIZE],
+    pub public_key: Point<Ed25519>,
+}
+
+impl PublicKey {
+    pub fn new(public_key: Point<Ed25519>) -> PublicKey {
+        PublicKey::new_rng(public_key, &mut thread_rng())
+    }
+
+    /// `public_key` with a key id drawn from `rng`, e.g. for a freshly aggregated key.
+    pub fn new_rng(public_key: Point<Ed25519>, rng: &mut (impl CryptoRng + RngCore)) -> PublicKey {
+        PublicKey {
+            key_id: rng.gen(),
+            public_key,
+        }
+    }
+
+    /// The key id as minisign prints it, e.g. `E7620F1842B4E81F`.
+    pub fn key_id_hex(&self) -> String {
+        format!("{:016X}", u64::from_le_bytes(self.key_id))
+    }
+
+    /// The contents of a `minisign.pub` file.
+    pub fn to_minisign(&self) -> String {
+        key_file(
+            &format!("minisign public key {}", self.key_id_hex()),
+            &self.to_bytes(),
+        )
+    }
+
+    /// Reads a `minisign.pub` file or its base64 line alone, as given to `minisign -P`.
+    /// Fails with `InvalidKey` for anything else.
+    pub fn from_minisign(file: &str) -> Result<PublicKey, Error> {
+        let lines = read_lines(file)?;
+        match lines[..] {
+            [line] if !line.starts_with(UNTRUSTED_COMMENT) => {
+                PublicKey::from_bytes(&decode(line, InvalidKey)?)
+            }
+            [comment, line] if comment.starts_with(UNTRUSTED_COMMENT) => {
+                PublicKey::from_bytes(&decode(line, InvalidKey)?)
+            }
+            _ => Err(InvalidKey),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PUBLIC_KEY_SIZE);
+        bytes.extend_from_slice(ALGORITHM);
+        bytes.extend_from_slice(&self.key_id);
+        bytes.extend_from_slice(&self.public_key.to_bytes(true));
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<PublicKey, Error> {
+        if bytes.len() != PUBLIC_KEY_SIZE || bytes[..2] != ALGORITHM[..] {
+            return Err(InvalidKey);
+        }
+        Ok(PublicKey {
+            key_id: key_id(&bytes[2..]),
+            public_key: decode_point(&bytes[2 + KEY_ID_SIZE..]).ok_or(InvalidKey)?,
+        })
+    }
+}
+
+/// Reads an unencrypted `minisign.key` (`minisign -G -W`) and returns its public key, key id
+/// included, and the key pair to deal to the co-signers. Fails with `InvalidKey` for an
+/// encrypted or malformed key or a wrong checksum.
+pub fn read_secret_key(file: &str) -> Result<(PublicKey, ExpandedKeyPair), Error> {
+    let bytes = match read_lines(file)?[..] {
+        [comment, line] if comment.starts_with(UNTRUSTED_COMMENT) => decode(line, InvalidKey)?,
+        _ => return Err(InvalidKey),
+    };
+    if bytes.len() != SECRET_KEY_SIZE
+        || bytes[..2] != ALGORITHM[..]
+        || bytes[2..4] != [0, 0]
+        || bytes[4..6] != CHECKSUM_ALGORITHM[..]
+    {
+        return Err(InvalidKey);
+    }
+    let key = &bytes[54..];
+    let (key_id_and_secret, checksum) = key.split_at(KEY_ID_SIZE + 64);
+    let expected = Blake2b::<U32>::new()
+        .chain_update(ALGORITHM)
+        .chain_update(key_id_and_secret)
+        .finalize();
+    if checksum != &expected[..] {
+        return Err(InvalidKey);
+    }
+    let keypair = keypair_from_secret_key(&key_id_and_secret[KEY_ID_SIZE..])?;
+    Ok((
+        PublicKey {
+            key_id: key_id(key_id_and_secret),
+            public_key: keypair.public_key.clone(),
+        },
+        keypair,
+    ))
+}
+
+/// The minisign signature file of `message`, with `trusted_comment` (e.g. `timestamp:...`)
+/// signed along. `sign` runs the signing protocol twice, first on the BLAKE2b-512 hash of
+/// `message` and then on that signature followed by the trusted comment.
+/// Fails with `InvalidMessage` for a trusted comment of more than one line and with
+/// `InvalidSig` if a signature of `sign` doesn't verify.
+pub fn sign(
+    public_key: &PublicKey,
+    message: &[u8],
+    trusted_comment: &str,
+    mut sign: impl FnMut(&[u8]) -> Result<Signature, Error>,
+) -> Result<String, Error> {
+    if trusted_comment.contains(['\n', '\r']) {
+        return Err(InvalidMessage);
+    }
+    let hash = Blake2b512::digest(message);
+    let signature = sign(&hash)?;
+    signature
+        .verify(&hash, &public_key.public_key)
+        .map_err(|_| InvalidSig)?;
+    let global_data = global_data(&signature, trusted_comment);
+    let global_signature = sign(&global_data)?;
+    global_signature
+        .verify(&global_data, &public_key.public_key)
+        .map_err(|_| InvalidSig)?;
+
+    let mut file = key_file(
+        "signature from minisign secret key",
+        &signature_bytes(PREHASHED_ALGORITHM, &public_key.key_id, &signature),
+    );
+    file.push_str(TRUSTED_COMMENT);
+    file.push_str(trusted_comment);
+    file.push('\n');
+    file.push_str(&Base64::encode_string(&global_signature.to_bytes()));
+    file.push('\n');
+    Ok(file)
+}
+
+/// Checks a minisign signature file of `message`, prehashed or legacy, and returns its trusted
+/// comment. Fails with `InvalidKey` for a malformed file or a signature by another key id and
+/// with `InvalidSig` if either signature doesn't verify.
+pub fn verify(public_key: &PublicKey, file: &str, message: &[u8]) -> Result<String, Error> {
+    let (signature_line, trusted_comment, global_line) = match read_lines(file)?[..] {
+        [comment, signature, trusted_comment, global_signature]
+            if comment.starts_with(UNTRUSTED_COMMENT) =>
+        {
+            (
+                signature,
+                trusted_comment
+                    .strip_prefix(TRUSTED_COMMENT)
+                    .ok_or(InvalidKey)?,
+                global_signature,
+            )
+        }
+        _ => return Err(InvalidKey),
+    };
+    let (algorithm, signature) = read_signature(public_key, &decode(signature_line, InvalidKey)?)?;
+    let verified = match &algorithm {
+        PREHASHED_ALGORITHM => {
+            signature.verify(&Blake2b512::digest(message), &public_key.public_key)
+        }
+        _ => signature.verify(message, &public_key.public_key),
+    };
+    verified.map_err(|_| InvalidSig)?;
+    Signature::from_bytes(&decode(global_line, InvalidKey)?)
+        .map_err(|_| InvalidSig)?
+        .verify(
+            &global_data(&signature, trusted_comment),
+            &public_key.public_key,
+        )
+        .map_err(|_| InvalidSig)?;
+    Ok(String::from(trusted_comment))
+}
+
+fn global_data(signature: &Signature, trusted_comment: &str) -> Vec<u8> {
+    let mut data = signature.to_bytes().to_vec();
+    data.extend_from_slice(trusted_comment.as_bytes());
+    data
+}
+
+// an untrusted comment line and a base64 line
+fn key_file(untrusted_comment: &str, bytes: &[u8]) -> String {
+    format!(
+        "{}{}\n{}\n",
+        UNTRUSTED_COMMENT,
+        untrusted_comment,
+        Base64::encode_string(bytes)
+    )
+}
+
+fn read_lines(file: &str) -> Result<Vec<&str>, Error> {
+    let lines: Vec<_> = file.lines().collect();
+    if lines.is_empty() {
+        return Err(InvalidKey);
+    }
+    Ok(lines)
+}
+
+fn decode(line: &str, error: Error) -> Result<Vec<u8>, Error> {
+    Base64::decode_vec(line.trim_end()).map_err(|_| error)
+}
+
+fn key_id(bytes: &[u8]) -> [u8; KEY_ID_SIZE] {
+    let mut key_id = [0u8; KEY_ID_SIZE];
+    key_id.copy_from_slice(&bytes[..KEY_ID_SIZE]);
+    key_id
+}
+
+fn signature_bytes(
+    algorithm: &[u8; 2],
+    key_id: &[u8; KEY_ID_SIZE],
+    signature: &Signature,
+) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(SIGNATURE_SIZE);
+    bytes.extend_from_slice(algorithm);
+    bytes.extend_from_slice(key_id);
+    bytes.extend_from_slice(&signature.to_bytes());
+    bytes
+}
+
+// the algorithm and signature of an `Ed` or `ED` signature line by `public_key`
+fn read_signature(public_key: &PublicKey, bytes: &[u8]) -> Result<([u8; 2], Signature), Error> {
+    if bytes.len() != SIGNATURE_SIZE
+        || (bytes[..2] != ALGORITHM[..] && bytes[..2] != PREHASHED_ALGORITHM[..])
+        || bytes[2..2 + KEY_ID_SIZE] != public_key.key_id
+    {
+        return Err(InvalidKey);
+    }
+    let signature = Signature::from_bytes(&bytes[2 + KEY_ID_SIZE..]).map_err(|_| InvalidSig)?;
+    Ok(([bytes[0], bytes[1]], signature))
+}
+
+// the 64 byte secret key of both tools, the seed followed by the public key
+fn keypair_from_secret_key(secret_key: &[u8]) -> Result<ExpandedKeyPair, Error> {
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&secret_key[..32]);
+    let keypair = ExpandedKeyPair::from_seed_bytes(seed);
+    if keypair.public_key.to_bytes(true)[..] != secret_key[32..] {
+        return Err(InvalidKey);
+    }
+    Ok(keypair)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use local;
+    use protocols::aggsig::{self, AggregatedKey};
+    use protocols::frost;
+    use protocols::keygen::{self, Parameters};
+    use protocols::tests::deterministic_fast_rand;
+
+    // the key of RFC 8032 test 1 with key id 0123456789abcdef, written with Python
+    pub const PUBLIC_KEY: &str = "untrusted comment: minisign public key EFCDAB8967452301
+RWQBI0VniavN79damAGCsQq31Uv+08lkBzoO4XLz2qYjJa8CGmj3B1Ea
+";
+    const SECRET_KEY: &str = "untrusted comment: minisign secret key
+RWQAAEIyAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAASNFZ4mrze+dYbGd7/1aYLqESvSS7CzEREnFaXsyaRlwO6wDHK5/YNdamAGCsQq31Uv+08lkBzoO4XLz2qYjJa8CGmj3B1EaY4lxIL3lgW2PUFFO8HPooQm2oChsSS/Fr9YNHxjqNO4=
+";
+    pub const MESSAGE: &[u8] = b"release v1.0\n";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQBI0VniavN7/cYjrMRU4QXELhHeoqdJJFs/G10JdWRJpcooehi2feDJtdWjLBh0fOaeiHDSrclydn3SogLWEs4ObrSekna4AI=
+trusted comment: timestamp:1700000000\tfile:release.tar.gz\thashed
+Y75fxKbrv6Q0I6xYNB3dij9G/BF00LC1RTubu3d7XLWN+tjG1bcufP/f/hwvgBtFtPTBnAjOrIz69xUJwHCsDQ==
+";
+    const LEGACY_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RWQBI0VniavN75ruksot4zmeOTUGDo1PF42nWKxVpn6AZrjBAf1/7KCRYmVkUJd6rDyUlhkPe4HZ1Ai60XofeDGSSWhbzyUcTgc=
+trusted comment: timestamp:1700000000\tfile:release.tar.gz
+egYsy1gDEP12EezCET5I+AaFteBb3J5mqaKTL8cIFQWo9zHsMfH5pr0VWLFzQFbuJpWB58AIpzDkiAu4jj8dCw==
+";
+
+    #[test]
+    fn test_keys() {
+        let public_key = PublicKey::from_minisign(PUBLIC_KEY).unwrap();
+        assert_eq!(public_key.key_id_hex(), "EFCDAB8967452301");
+        assert_eq!(public_key.to_minisign(), PUBLIC_KEY);
+        assert_eq!(
+            PublicKey::from_minisign(PUBLIC_KEY.lines().nth(1).unwrap()).unwrap(),
+            public_key
+        );
+        let (secret_public_key, keypair) = read_secret_key(SECRET_KEY).unwrap();
+        assert_eq!(secret_public_key, public_key);
+        assert_eq!(keypair.public_key, public_key.public_key);
+
+        // the key minisign releases are signed with
+        let releases =
+            PublicKey::from_minisign("RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3")
+                .unwrap();
+        assert_eq!(releases.key_id_hex(), "E7620F1842B4E81F");
+
+        assert_eq!(
+            PublicKey::from_minisign(&PUBLIC_KEY.replace("RWQB", "RUQB")),
+            Err(InvalidKey)
+        );
+        assert_eq!(PublicKey::from_minisign(""), Err(InvalidKey));
+        // encrypted with scrypt
+        assert_eq!(
+            read_secret_key(&SECRET_KEY.replace("RWQAAEIy", "RWRTY0Iy")).err(),
+            Some(InvalidKey)
+        );
+        // a wrong checksum
+        assert_eq!(
+            read_secret_key(&SECRET_KEY.replace("NO4=", "NO8=")).err(),
+            Some(InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_signatures() {
+        let public_key = PublicKey::from_minisign(PUBLIC_KEY).unwrap();
+        assert_eq!(
+            verify(&public_key, SIGNATURE, MESSAGE).unwrap(),
+            "timestamp:1700000000\tfile:release.tar.gz\thashed"
+        );
+        assert_eq!(
+            verify(&public_key, LEGACY_SIGNATURE, MESSAGE).unwrap(),
+            "timestamp:1700000000\tfile:release.tar.gz"
+        );
+        assert_eq!(
+            verify(&public_key, SIGNATURE, b"release v1.1\n"),
+            Err(InvalidSig)
+        );
+        // an edited trusted comment
+        assert_eq!(
+            verify(
+                &public_key,
+                &SIGNATURE.replace("1700000000", "1800000000"),
+                MESSAGE
+            ),
+            Err(InvalidSig)
+        );
+        let mut other_key = public_key.clone();
+        other_key.key_id[0] ^= 1;
+        assert_eq!(verify(&other_key, SIGNATURE, MESSAGE), Err(InvalidKey));
+
+        let (_, keypair) = read_secret_key(SECRET_KEY).unwrap();
+        let file = sign(&public_key, MESSAGE, "timestamp:1700000000", |data| {
+            Ok(aggsig::sign_single(data, &keypair))
+        })
+        .unwrap();
+        assert_eq!(
+            verify(&public_key, &file, MESSAGE).unwrap(),
+            "timestamp:1700000000"
+        );
+        // the same key id and algorithm
+        assert_eq!(file[..67], SIGNATURE[..67]);
+        assert_eq!(
+            sign(&public_key, MESSAGE, "two\nlines", |data| Ok(
+                aggsig::sign_single(data, &keypair)
+            )),
+            Err(InvalidMessage)
+        );
+    }
+
+    #[test]
+    fn test_committee_signing() {
+        let mut rng = deterministic_fast_rand("test_minisign_committee_signing", None);
+
+        // the released key dealt to a 2-of-3 committee
+        let (public_key, keypair) = read_secret_key(SECRET_KEY).unwrap();
+        let shares = keygen::deal_rng(
+            &Parameters {
+                threshold: 1,
+                share_count: 3,
+            },
+            &keypair,
+            &mut rng,
+        )
+        .unwrap();
+        let file = sign(&public_key, MESSAGE, "timestamp:1700000000", |data| {
+            let (nonces, commitments): (Vec<_>, Vec<_>) = [&shares[1], &shares[2]]
+                .iter()
+                .map(|keys| frost::preprocess_rng(keys, &mut rng))
+                .unzip();
+            let partial_sigs = nonces
+                .into_iter()
+                .zip([&shares[1], &shares[2]])
+                .map(|(nonces, keys)| frost::partial_sign(keys, nonces, &commitments, data))
+                .collect::<Result<Vec<_>, _>>()?;
+            frost::aggregate(&shares[1], &commitments, &partial_sigs, data)
+        })
+        .unwrap();
+        assert!(verify(&public_key, &file, MESSAGE).is_ok());
+
+        // a new aggregated key
+        let signers = [
+            ExpandedKeyPair::create_rng(&mut rng),
+            ExpandedKeyPair::create_rng(&mut rng),
+        ];
+        let agg_key =
+            AggregatedKey::new(&[signers[0].public_key.clone(), signers[1].public_key.clone()]);
+        let public_key = PublicKey::new_rng(agg_key.apk.clone(), &mut rng);
+        let public_key = PublicKey::from_minisign(&public_key.to_minisign()).unwrap();
+        let file = sign(&public_key, MESSAGE, "aggregated", |data| {
+            local::sign_rng(data, &signers, &agg_key, &mut rng)
+        })
+        .unwrap();
+        assert_eq!(verify(&public_key, &file, MESSAGE).unwrap(), "aggregated");
+    }
+}
@@ -0,0 +1,181 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! OpenBSD signify public keys, secret keys and signatures, as checked by `signify -V`.
+//!
+//! A signify signature is a plain Ed25519 signature of the file, so `sign` runs the signing
+//! protocol once on the message itself.
+
+use alloc::string::String;
+
+use sha2::{Digest, Sha512};
+
+use super::{
+    decode, key_file, key_id, keypair_from_secret_key, read_lines, signature_bytes, PublicKey,
+    ALGORITHM, KEY_ID_SIZE, SIGNATURE_SIZE, UNTRUSTED_COMMENT,
+};
+use protocols::{ExpandedKeyPair, Signature};
+use Error::{self, InvalidKey, InvalidSig};
+
+const KDF_ALGORITHM: &[u8; 2] = b"BK";
+// algorithm, kdf, kdf rounds, salt, checksum, key id, key
+const SECRET_KEY_SIZE: usize = 2 + 2 + 4 + 16 + 8 + KEY_ID_SIZE + 64;
+
+impl PublicKey {
+    /// The contents of a signify `.pub` file.
+    pub fn to_signify(&self) -> String {
+        key_file("signify public key", &self.to_bytes())
+    }
+
+    /// Reads a signify `.pub` file, fails with `InvalidKey` for anything else.
+    pub fn from_signify(file: &str) -> Result<PublicKey, Error> {
+        match read_lines(file)?[..] {
+            [comment, line] if comment.starts_with(UNTRUSTED_COMMENT) => {
+                PublicKey::from_bytes(&decode(line, InvalidKey)?)
+            }
+            _ => Err(InvalidKey),
+        }
+    }
+}
+
+/// Reads a signify `.sec` file made without a passphrase (`signify -G -n`) and returns its
+/// public key, key id included, and the key pair to deal to the co-signers. Fails with
+/// `InvalidKey` for an encrypted or malformed key or a wrong checksum.
+pub fn read_secret_key(file: &str) -> Result<(PublicKey, ExpandedKeyPair), Error> {
+    let bytes = match read_lines(file)?[..] {
+        [comment, line] if comment.starts_with(UNTRUSTED_COMMENT) => decode(line, InvalidKey)?,
+        _ => return Err(InvalidKey),
+    };
+    if bytes.len() != SECRET_KEY_SIZE
+        || bytes[..2] != ALGORITHM[..]
+        || bytes[2..4] != KDF_ALGORITHM[..]
+        || bytes[4..8] != [0; 4]
+    {
+        return Err(InvalidKey);
+    }
+    let (checksum, key_id_and_secret) = bytes[24..].split_at(8);
+    let secret_key = &key_id_and_secret[KEY_ID_SIZE..];
+    if checksum != &Sha512::digest(secret_key)[..8] {
+        return Err(InvalidKey);
+    }
+    let keypair = keypair_from_secret_key(secret_key)?;
+    Ok((
+        PublicKey {
+            key_id: key_id(key_id_and_secret),
+            public_key: keypair.public_key.clone(),
+        },
+        keypair,
+    ))
+}
+
+/// The signify signature file of `message`, whose untrusted comment names `public_key_file`
+/// (e.g. `openbsd-75-base.pub`) as signify does. `sign` runs the signing protocol on `message`.
+/// Fails with `InvalidSig` if the signature of `sign` doesn't verify.
+pub fn sign(
+    public_key: &PublicKey,
+    public_key_file: &str,
+    message: &[u8],
+    sign: impl FnOnce(&[u8]) -> Result<Signature, Error>,
+) -> Result<String, Error> {
+    let signature = sign(message)?;
+    signature
+        .verify(message, &public_key.public_key)
+        .map_err(|_| InvalidSig)?;
+    Ok(key_file(
+        &format!("verify with {}", public_key_file),
+        &signature_bytes(ALGORITHM, &public_key.key_id, &signature),
+    ))
+}
+
+/// Checks a signify signature file of `message`. Fails with `InvalidKey` for a malformed file or
+/// a signature by another key id and with `InvalidSig` if it doesn't verify.
+pub fn verify(public_key: &PublicKey, file: &str, message: &[u8]) -> Result<(), Error> {
+    let bytes = match read_lines(file)?[..] {
+        [comment, line] if comment.starts_with(UNTRUSTED_COMMENT) => decode(line, InvalidKey)?,
+        _ => return Err(InvalidKey),
+    };
+    if bytes.len() != SIGNATURE_SIZE
+        || bytes[..2] != ALGORITHM[..]
+        || bytes[2..2 + KEY_ID_SIZE] != public_key.key_id
+    {
+        return Err(InvalidKey);
+    }
+    Signature::from_bytes(&bytes[2 + KEY_ID_SIZE..])
+        .map_err(|_| InvalidSig)?
+        .verify(message, &public_key.public_key)
+        .map_err(|_| InvalidSig)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minisign::tests::{MESSAGE, PUBLIC_KEY as MINISIGN_PUBLIC_KEY};
+    use protocols::aggsig;
+
+    // the minisign test key as a signify key, written with Python
+    const PUBLIC_KEY: &str = "untrusted comment: signify public key
+RWQBI0VniavN79damAGCsQq31Uv+08lkBzoO4XLz2qYjJa8CGmj3B1Ea
+";
+    const SECRET_KEY: &str = "untrusted comment: signify secret key
+RWRCSwAAAAAAAAAAAAAAAAAAAAAAAAAASulvySzYtIABI0VniavN751hsZ3v/VpguoRK9JLsLMREScVpezJpGXA7rAMcrn9g11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=
+";
+    const SIGNATURE: &str = "untrusted comment: verify with release.pub
+RWQBI0VniavN75ruksot4zmeOTUGDo1PF42nWKxVpn6AZrjBAf1/7KCRYmVkUJd6rDyUlhkPe4HZ1Ai60XofeDGSSWhbzyUcTgc=
+";
+
+    #[test]
+    fn test_signify() {
+        let public_key = PublicKey::from_signify(PUBLIC_KEY).unwrap();
+        assert_eq!(public_key.to_signify(), PUBLIC_KEY);
+        assert_eq!(
+            public_key,
+            PublicKey::from_minisign(MINISIGN_PUBLIC_KEY).unwrap()
+        );
+        let (secret_public_key, keypair) = read_secret_key(SECRET_KEY).unwrap();
+        assert_eq!(secret_public_key, public_key);
+
+        assert!(verify(&public_key, SIGNATURE, MESSAGE).is_ok());
+        assert_eq!(verify(&public_key, SIGNATURE, b"release"), Err(InvalidSig));
+        let file = sign(&public_key, "release.pub", MESSAGE, |message| {
+            Ok(aggsig::sign_single(message, &keypair))
+        })
+        .unwrap();
+        assert!(verify(&public_key, &file, MESSAGE).is_ok());
+        // the same key id
+        assert_eq!(file[..56], SIGNATURE[..56]);
+        // a session signing something else
+        assert_eq!(
+            sign(&public_key, "release.pub", MESSAGE, |_| Ok(
+                aggsig::sign_single(b"release", &keypair)
+            )),
+            Err(InvalidSig)
+        );
+
+        let mut other_key = public_key.clone();
+        other_key.key_id[7] ^= 1;
+        assert_eq!(verify(&other_key, SIGNATURE, MESSAGE), Err(InvalidKey));
+        // encrypted with bcrypt_pbkdf
+        assert_eq!(
+            read_secret_key(&SECRET_KEY.replace("RWRCSwAAAAAA", "RWRCSwAAACoA")).err(),
+            Some(InvalidKey)
+        );
+        assert_eq!(
+            read_secret_key(&SECRET_KEY.replace("RWRCSw", "RWRCTA")).err(),
+            Some(InvalidKey)
+        );
+    }
+}
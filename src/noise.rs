@@ -0,0 +1,332 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Authenticated and encrypted channels between co-signers, using the [Noise](https://noiseprotocol.org/noise.html)
+//! XX or IK handshake (`Noise_XX_25519_ChaChaPoly_SHA256`, `Noise_IK_25519_ChaChaPoly_SHA256`).
+//!
+//! Every party is identified by its long-term Ed25519 public key, the Noise static keys are
+//! the X25519 keys converted from it (as libsodium's `crypto_sign_ed25519_pk_to_curve25519`),
+//! so a channel can only be finished with one of the expected co-signers.
+//! Use XX if the initiator doesn't know who it talks to yet, IK saves a message when it does.
+//!
+//! Handshake: the initiator sends the first message, then the parties take turns with
+//! `write_message`/`read_message` until `is_finished`, and `into_channel` returns the `Channel`
+//! for the protocol messages. Only an `ExpandedKeyPair` that has its seed
+//! (see `ExpandedKeyPair::to_seed_bytes`) has a static key.
+
+use alloc::vec::Vec;
+
+use curv::elliptic::curves::{Ed25519, Point};
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json;
+use sha2::{Digest, Sha512};
+use snow::{Builder, HandshakeState, TransportState};
+use zeroize::Zeroize;
+
+use protocols::ExpandedKeyPair;
+use Error::{self, InvalidKey, InvalidMessage, RoundMismatch};
+
+/// The largest Noise message, including the 16 byte authentication tag.
+const MAX_MESSAGE_LEN: usize = 65535;
+const TAG_LEN: usize = 16;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// Three messages, the initiator learns who it talks to in the second.
+    XX,
+    /// Two messages, the initiator has to know the responder's public key.
+    IK,
+}
+
+impl Pattern {
+    fn params(self) -> snow::params::NoiseParams {
+        let name = match self {
+            Pattern::XX => "Noise_XX_25519_ChaChaPoly_SHA256",
+            Pattern::IK => "Noise_IK_25519_ChaChaPoly_SHA256",
+        };
+        name.parse().expect("the pattern names are valid")
+    }
+}
+
+/// The X25519 private key of the same secret scalar as the Ed25519 key.
+fn x25519_private_key(keys: &ExpandedKeyPair) -> Result<[u8; 32], Error> {
+    let mut seed = keys.to_seed_bytes().ok_or(InvalidKey)?;
+    let mut h = Sha512::new().chain(seed).finalize();
+    seed.zeroize();
+    let mut private_key = [0u8; 32];
+    private_key.copy_from_slice(&h[..32]);
+    h.as_mut_slice().zeroize();
+    Ok(private_key)
+}
+
+/// The Montgomery u-coordinate `(1 + y) / (1 - y)` of an Ed25519 public key.
+pub fn x25519_public_key(public_key: &Point<Ed25519>) -> Result<[u8; 32], Error> {
+    if public_key.is_zero() {
+        return Err(InvalidKey);
+    }
+    let point = CompressedEdwardsY::from_slice(&public_key.to_bytes(true))
+        .decompress()
+        .ok_or(InvalidKey)?;
+    Ok(point.to_montgomery().to_bytes())
+}
+
+/// A Noise handshake with one of the `peers`.
+pub struct Handshake {
+    state: HandshakeState,
+    peers: Vec<(Point<Ed25519>, [u8; 32])>,
+    peer: Option<Point<Ed25519>>,
+}
+
+impl Handshake {
+    /// Starts a handshake with `peer`, `prologue` (e.g. a session id) has to be the same on both sides.
+    pub fn initiator(
+        keys: &ExpandedKeyPair,
+        pattern: Pattern,
+        peer: &Point<Ed25519>,
+        prologue: &[u8],
+    ) -> Result<Handshake, Error> {
+        Self::new(keys, pattern, true, core::slice::from_ref(peer), prologue)
+    }
+
+    /// Answers a handshake from any of `peers`.
+    pub fn responder(
+        keys: &ExpandedKeyPair,
+        pattern: Pattern,
+        peers: &[Point<Ed25519>],
+        prologue: &[u8],
+    ) -> Result<Handshake, Error> {
+        Self::new(keys, pattern, false, peers, prologue)
+    }
+
+    fn new(
+        keys: &ExpandedKeyPair,
+        pattern: Pattern,
+        initiator: bool,
+        peers: &[Point<Ed25519>],
+        prologue: &[u8],
+    ) -> Result<Handshake, Error> {
+        let peers = peers
+            .iter()
+            .map(|peer| Ok((peer.clone(), x25519_public_key(peer)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        if peers.is_empty() {
+            return Err(InvalidKey);
+        }
+        let mut private_key = x25519_private_key(keys)?;
+        let builder = Builder::new(pattern.params())
+            .local_private_key(&private_key)
+            .prologue(prologue);
+        let state = if !initiator {
+            builder.build_responder()
+        } else if pattern == Pattern::IK {
+            builder.remote_public_key(&peers[0].1).build_initiator()
+        } else {
+            builder.build_initiator()
+        };
+        private_key.zeroize();
+        Ok(Handshake {
+            state: state.map_err(|_| InvalidKey)?,
+            peers,
+            peer: None,
+        })
+    }
+
+    /// The next handshake message to send to the peer.
+    pub fn write_message(&mut self) -> Result<Vec<u8>, Error> {
+        let mut message = vec![0u8; MAX_MESSAGE_LEN];
+        let len = self
+            .state
+            .write_message(&[], &mut message)
+            .map_err(|_| RoundMismatch)?;
+        message.truncate(len);
+        Ok(message)
+    }
+
+    /// Fails with `InvalidKey` as soon as the peer turns out not to be one of the expected ones.
+    pub fn read_message(&mut self, message: &[u8]) -> Result<(), Error> {
+        if self.state.is_handshake_finished() {
+            return Err(RoundMismatch);
+        }
+        let mut payload = vec![0u8; MAX_MESSAGE_LEN];
+        self.state
+            .read_message(message, &mut payload)
+            .map_err(|_| InvalidMessage)?;
+        if self.peer.is_none() {
+            if let Some(remote) = self.state.get_remote_static() {
+                let peer = self
+                    .peers
+                    .iter()
+                    .find(|(_, key)| &key[..] == remote)
+                    .ok_or(InvalidKey)?;
+                self.peer = Some(peer.0.clone());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.is_handshake_finished()
+    }
+
+    pub fn into_channel(self) -> Result<Channel, Error> {
+        let peer = self.peer.ok_or(RoundMismatch)?;
+        let transport = self
+            .state
+            .into_transport_mode()
+            .map_err(|_| RoundMismatch)?;
+        Ok(Channel { transport, peer })
+    }
+}
+
+/// A finished handshake, messages have to be read in the order they were written.
+pub struct Channel {
+    transport: TransportState,
+    peer: Point<Ed25519>,
+}
+
+impl Channel {
+    /// The Ed25519 public key of the other side.
+    pub fn peer(&self) -> &Point<Ed25519> {
+        &self.peer
+    }
+
+    /// `plaintext` can be at most 65519 bytes.
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        if plaintext.len() + TAG_LEN > MAX_MESSAGE_LEN {
+            return Err(InvalidMessage);
+        }
+        let mut message = vec![0u8; plaintext.len() + TAG_LEN];
+        let len = self
+            .transport
+            .write_message(plaintext, &mut message)
+            .map_err(|_| InvalidMessage)?;
+        message.truncate(len);
+        Ok(message)
+    }
+
+    /// Fails with `InvalidMessage` if the message was changed, replayed or reordered.
+    pub fn decrypt(&mut self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        if message.len() < TAG_LEN || message.len() > MAX_MESSAGE_LEN {
+            return Err(InvalidMessage);
+        }
+        let mut plaintext = vec![0u8; message.len()];
+        let len = self
+            .transport
+            .read_message(message, &mut plaintext)
+            .map_err(|_| InvalidMessage)?;
+        plaintext.truncate(len);
+        Ok(plaintext)
+    }
+
+    /// Encrypts the JSON encoding of a protocol message.
+    pub fn send<T: Serialize>(&mut self, msg: &T) -> Result<Vec<u8>, Error> {
+        let json = serde_json::to_vec(msg).map_err(|_| InvalidMessage)?;
+        self.encrypt(&json)
+    }
+
+    pub fn receive<T: DeserializeOwned>(&mut self, message: &[u8]) -> Result<T, Error> {
+        let json = self.decrypt(message)?;
+        serde_json::from_slice(&json).map_err(|_| InvalidMessage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocols::aggsig::{self, SignFirstMsg};
+
+    fn handshake(initiator: &mut Handshake, responder: &mut Handshake) -> Result<(), Error> {
+        let mut sender = initiator;
+        let mut receiver = responder;
+        while !sender.is_finished() || !receiver.is_finished() {
+            let message = sender.write_message()?;
+            receiver.read_message(&message)?;
+            core::mem::swap(&mut sender, &mut receiver);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel() {
+        for pattern in [Pattern::XX, Pattern::IK] {
+            let alice = ExpandedKeyPair::create();
+            let bob = ExpandedKeyPair::create();
+            let carol = ExpandedKeyPair::create();
+            let mut initiator =
+                Handshake::initiator(&alice, pattern, &bob.public_key, b"session").unwrap();
+            let peers = [carol.public_key.clone(), alice.public_key.clone()];
+            let mut responder = Handshake::responder(&bob, pattern, &peers, b"session").unwrap();
+            handshake(&mut initiator, &mut responder).unwrap();
+            let mut alice_channel = initiator.into_channel().unwrap();
+            let mut bob_channel = responder.into_channel().unwrap();
+            assert_eq!(alice_channel.peer(), &bob.public_key);
+            assert_eq!(bob_channel.peer(), &alice.public_key);
+
+            let (_, first_msg, _) =
+                aggsig::create_ephemeral_key_and_commit(&alice, b"message").unwrap();
+            let message = alice_channel.send(&first_msg).unwrap();
+            let received: SignFirstMsg = bob_channel.receive(&message).unwrap();
+            assert_eq!(received.commitment, first_msg.commitment);
+
+            let reply = bob_channel.encrypt(b"reply").unwrap();
+            assert_eq!(alice_channel.decrypt(&reply).unwrap(), b"reply");
+
+            let mut tampered = alice_channel.encrypt(b"pay alice").unwrap();
+            tampered[0] ^= 1;
+            assert_eq!(bob_channel.decrypt(&tampered), Err(InvalidMessage));
+        }
+    }
+
+    #[test]
+    fn test_unexpected_peer() {
+        for pattern in [Pattern::XX, Pattern::IK] {
+            let alice = ExpandedKeyPair::create();
+            let bob = ExpandedKeyPair::create();
+            let mallory = ExpandedKeyPair::create();
+            let mut initiator =
+                Handshake::initiator(&mallory, pattern, &bob.public_key, b"session").unwrap();
+            let mut responder = Handshake::responder(
+                &bob,
+                pattern,
+                std::slice::from_ref(&alice.public_key),
+                b"session",
+            )
+            .unwrap();
+            assert_eq!(
+                handshake(&mut initiator, &mut responder).err(),
+                Some(InvalidKey)
+            );
+        }
+    }
+
+    #[test]
+    fn test_x25519_public_key() {
+        // RFC 8032 test vector 1 and its X25519 public key from libsodium
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(
+            &hex::decode("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60")
+                .unwrap(),
+        );
+        let keys = ExpandedKeyPair::create_from_private_key(seed);
+        assert_eq!(
+            hex::encode(x25519_public_key(&keys.public_key).unwrap()),
+            "d85e07ec22b0ad881537c2f44d662d1a143cf830c57aca4305d85c7a90f6b62e"
+        );
+    }
+}
@@ -0,0 +1,267 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! An ssh-agent (draft-miller-ssh-agent) which lists aggregated keys and answers sign requests
+//! by running a multi-party signing session, so `ssh`, `git` and anything else talking to
+//! `SSH_AUTH_SOCK` can use an aggregated key without any co-signer holding its private key.
+//!
+//! Only listing the identities and signing are supported, every other request (adding keys,
+//! locking, extensions) is answered with `SSH_AGENT_FAILURE`. `Agent::serve` answers the
+//! requests of one connection, e.g. a `UnixStream` accepted on the listener bound at the
+//! socket path.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+use curv::elliptic::curves::{Ed25519, Point};
+
+use super::{
+    public_key_blob, read_public_key_blob, signature_blob, write_string, write_u32, Reader,
+};
+use protocols::Signature;
+use Error::{self, InvalidKey, InvalidMessage, InvalidSig};
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH2_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH2_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH2_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH2_AGENT_SIGN_RESPONSE: u8 = 14;
+// OpenSSH's AGENT_MAX_LEN
+const MAX_MESSAGE_SIZE: usize = 256 * 1024;
+
+/// Signs for the agent, e.g. by running the aggsig rounds with the other co-signers.
+pub trait SigningSession {
+    /// The signature of `data` under `public_key`, one of the agent's identities.
+    fn sign(&mut self, public_key: &Point<Ed25519>, data: &[u8]) -> Result<Signature, Error>;
+}
+
+impl<F: FnMut(&Point<Ed25519>, &[u8]) -> Result<Signature, Error>> SigningSession for F {
+    fn sign(&mut self, public_key: &Point<Ed25519>, data: &[u8]) -> Result<Signature, Error> {
+        self(public_key, data)
+    }
+}
+
+pub struct Agent<S: SigningSession> {
+    identities: Vec<(Point<Ed25519>, String)>,
+    session: S,
+}
+
+impl<S: SigningSession> Agent<S> {
+    /// An agent without identities.
+    pub fn new(session: S) -> Agent<S> {
+        Agent {
+            identities: Vec::new(),
+            session,
+        }
+    }
+
+    /// Lists `public_key` with `comment` and signs for it.
+    pub fn add_identity(&mut self, public_key: Point<Ed25519>, comment: &str) {
+        self.identities.push((public_key, String::from(comment)));
+    }
+
+    /// The response to a request, both without their length prefix.
+    pub fn handle(&mut self, request: &[u8]) -> Vec<u8> {
+        self.respond(request)
+            .unwrap_or_else(|_| alloc::vec![SSH_AGENT_FAILURE])
+    }
+
+    /// Answers the requests read from `stream` until it's closed.
+    /// Fails with `InvalidData` for a request longer than 256 KiB, as OpenSSH's agent does.
+    pub fn serve(&mut self, stream: &mut (impl Read + Write)) -> io::Result<()> {
+        loop {
+            let mut len = [0u8; 4];
+            match stream.read_exact(&mut len) {
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                result => result?,
+            }
+            let len = usize::try_from(u32::from_be_bytes(len))
+                .ok()
+                .filter(|len| *len <= MAX_MESSAGE_SIZE)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?;
+            let mut request = alloc::vec![0u8; len];
+            stream.read_exact(&mut request)?;
+            let mut response = Vec::new();
+            write_string(&mut response, &self.handle(&request));
+            stream.write_all(&response)?;
+            stream.flush()?;
+        }
+    }
+
+    fn respond(&mut self, request: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut reader = Reader(request);
+        match reader.u8()? {
+            SSH2_AGENTC_REQUEST_IDENTITIES => {
+                reader.finish()?;
+                let mut response = alloc::vec![SSH2_AGENT_IDENTITIES_ANSWER];
+                write_u32(
+                    &mut response,
+                    u32::try_from(self.identities.len()).map_err(|_| InvalidKey)?,
+                );
+                for (public_key, comment) in &self.identities {
+                    write_string(&mut response, &public_key_blob(public_key));
+                    write_string(&mut response, comment.as_bytes());
+                }
+                Ok(response)
+            }
+            SSH2_AGENTC_SIGN_REQUEST => {
+                let public_key = read_public_key_blob(&mut reader)?;
+                let data = reader.string()?;
+                // the flags only select RSA signature algorithms
+                let _flags = reader.u32()?;
+                reader.finish()?;
+                if !self.identities.iter().any(|(pk, _)| *pk == public_key) {
+                    return Err(InvalidKey);
+                }
+                let signature = self.session.sign(&public_key, data)?;
+                signature
+                    .verify(data, &public_key)
+                    .map_err(|_| InvalidSig)?;
+                let mut response = alloc::vec![SSH2_AGENT_SIGN_RESPONSE];
+                write_string(&mut response, &signature_blob(&signature));
+                Ok(response)
+            }
+            _ => Err(InvalidMessage),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use local;
+    use openssh::{read_signature_blob, sshsig};
+    use protocols::aggsig::{self, AggregatedKey};
+    use protocols::tests::deterministic_fast_rand;
+    use protocols::ExpandedKeyPair;
+
+    // a connection whose requests are read from `input` and responses written to `output`
+    struct Connection {
+        input: io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Connection {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Connection {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn sign_request(public_key: &Point<Ed25519>, data: &[u8]) -> Vec<u8> {
+        let mut request = alloc::vec![SSH2_AGENTC_SIGN_REQUEST];
+        write_string(&mut request, &public_key_blob(public_key));
+        write_string(&mut request, data);
+        write_u32(&mut request, 0);
+        request
+    }
+
+    #[test]
+    fn test_agent() {
+        let mut rng = deterministic_fast_rand("test_agent", None);
+        let signers = [
+            ExpandedKeyPair::create_rng(&mut rng),
+            ExpandedKeyPair::create_rng(&mut rng),
+        ];
+        let agg_key =
+            AggregatedKey::new(&[signers[0].public_key.clone(), signers[1].public_key.clone()]);
+        let apk = agg_key.apk.clone();
+        let mut agent = Agent::new(|_: &Point<Ed25519>, data: &[u8]| {
+            local::sign_rng(data, &signers, &agg_key, &mut rng)
+        });
+        agent.add_identity(apk.clone(), "aggregated");
+
+        let mut expected = alloc::vec![SSH2_AGENT_IDENTITIES_ANSWER];
+        write_u32(&mut expected, 1);
+        write_string(&mut expected, &public_key_blob(&apk));
+        write_string(&mut expected, b"aggregated");
+        assert_eq!(agent.handle(&[SSH2_AGENTC_REQUEST_IDENTITIES]), expected);
+
+        // what ssh-keygen -Y sign asks the agent for
+        let data = sshsig::signed_data("git", b"commit");
+        let response = agent.handle(&sign_request(&apk, &data));
+        assert_eq!(response[0], SSH2_AGENT_SIGN_RESPONSE);
+        let mut reader = Reader(&response[1..]);
+        let signature = read_signature_blob(&mut reader).unwrap();
+        reader.finish().unwrap();
+        let armored = sshsig::to_armored(&apk, "git", &signature);
+        assert_eq!(sshsig::verify(&armored, "git", b"commit").unwrap(), apk);
+
+        // not an identity, a truncated request and an unsupported one
+        assert_eq!(
+            agent.handle(&sign_request(&signers[0].public_key, &data)),
+            [SSH_AGENT_FAILURE]
+        );
+        assert_eq!(
+            agent.handle(&sign_request(&apk, &data)[..40]),
+            [SSH_AGENT_FAILURE]
+        );
+        // SSH_AGENTC_REMOVE_ALL_IDENTITIES
+        assert_eq!(agent.handle(&[19]), [SSH_AGENT_FAILURE]);
+        assert_eq!(agent.handle(&[]), [SSH_AGENT_FAILURE]);
+    }
+
+    #[test]
+    fn test_agent_serve() {
+        let keypair = ExpandedKeyPair::create_from_private_key([7; 32]);
+        let mut agent =
+            Agent::new(|_: &Point<Ed25519>, data: &[u8]| Ok(aggsig::sign_single(data, &keypair)));
+        agent.add_identity(keypair.public_key.clone(), "");
+        // a session signing something else
+        let mut failing = Agent::new(|_: &Point<Ed25519>, _: &[u8]| {
+            Ok(aggsig::sign_single(b"something else", &keypair))
+        });
+        failing.add_identity(keypair.public_key.clone(), "");
+        assert_eq!(
+            failing.handle(&sign_request(&keypair.public_key, b"data")),
+            [SSH_AGENT_FAILURE]
+        );
+
+        let mut input = Vec::new();
+        write_string(&mut input, &[SSH2_AGENTC_REQUEST_IDENTITIES]);
+        write_string(&mut input, &sign_request(&keypair.public_key, b"data"));
+        let mut connection = Connection {
+            input: io::Cursor::new(input),
+            output: Vec::new(),
+        };
+        agent.serve(&mut connection).unwrap();
+        let mut responses = Reader(&connection.output);
+        assert_eq!(responses.string().unwrap()[0], SSH2_AGENT_IDENTITIES_ANSWER);
+        assert_eq!(responses.string().unwrap()[0], SSH2_AGENT_SIGN_RESPONSE);
+        responses.finish().unwrap();
+
+        let mut too_long = Connection {
+            input: io::Cursor::new(alloc::vec![0, 4, 0, 1]),
+            output: Vec::new(),
+        };
+        assert_eq!(
+            agent.serve(&mut too_long).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,374 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! OpenSSH certificates (PROTOCOL.certkeys) of Ed25519 keys, signed by a CA key that can be an
+//! aggregated key, so issuing a certificate takes the co-signers of the CA.
+//!
+//! A `SignedCertificate` is written as a `ssh-ed25519-cert-v01@openssh.com` line, the format of
+//! `ssh-keygen -s`'s `-cert.pub` files. The validity, principals and options are checked by
+//! `sshd` and `ssh`, not here.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use base64ct::{Base64, Encoding};
+use curv::elliptic::curves::{Ed25519, Point};
+use rand::{thread_rng, CryptoRng, Rng, RngCore};
+
+use super::{
+    public_key_blob, read_public_key_blob, read_signature_blob, signature_blob, write_string,
+    write_u32, write_u64, Reader,
+};
+use protocols::{decode_point, Signature};
+use Error::{self, InvalidKey, InvalidSig};
+
+const CERT_KEY_TYPE: &str = "ssh-ed25519-cert-v01@openssh.com";
+// the extensions `ssh-keygen -s` gives user certificates
+const DEFAULT_USER_EXTENSIONS: &[&str] = &[
+    "permit-X11-forwarding",
+    "permit-agent-forwarding",
+    "permit-port-forwarding",
+    "permit-pty",
+    "permit-user-rc",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CertType {
+    User = 1,
+    Host = 2,
+}
+
+/// The fields of a certificate, times are seconds since the epoch.
+/// Options and extensions are `(name, value)` pairs with an empty value for flags
+/// (e.g. `("force-command", "ls")` or `("permit-pty", "")`), they are written sorted by name.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Certificate {
+    pub nonce: Vec<u8>,
+    pub public_key: Point<Ed25519>,
+    pub serial: u64,
+    pub cert_type: CertType,
+    pub key_id: String,
+    /// No principals means any principal.
+    pub valid_principals: Vec<String>,
+    pub valid_after: u64,
+    pub valid_before: u64,
+    pub critical_options: Vec<(String, String)>,
+    pub extensions: Vec<(String, String)>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SignedCertificate {
+    pub certificate: Certificate,
+    pub ca_key: Point<Ed25519>,
+    pub signature: Signature,
+}
+
+impl Certificate {
+    pub fn new(public_key: Point<Ed25519>, cert_type: CertType, key_id: &str) -> Certificate {
+        Certificate::new_rng(public_key, cert_type, key_id, &mut thread_rng())
+    }
+
+    /// A certificate valid forever for any principal, with serial 0 and the extensions
+    /// `ssh-keygen -s` adds by default, `rng` is used for the nonce.
+    pub fn new_rng(
+        public_key: Point<Ed25519>,
+        cert_type: CertType,
+        key_id: &str,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Certificate {
+        let extensions = match cert_type {
+            CertType::User => DEFAULT_USER_EXTENSIONS
+                .iter()
+                .map(|name| (String::from(*name), String::new()))
+                .collect(),
+            CertType::Host => Vec::new(),
+        };
+        Certificate {
+            nonce: rng.gen::<[u8; 32]>().to_vec(),
+            public_key,
+            serial: 0,
+            cert_type,
+            key_id: String::from(key_id),
+            valid_principals: Vec::new(),
+            valid_after: 0,
+            valid_before: u64::MAX,
+            critical_options: Vec::new(),
+            extensions,
+        }
+    }
+
+    /// The bytes `ca_key` signs.
+    pub fn signed_data(&self, ca_key: &Point<Ed25519>) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_string(&mut data, CERT_KEY_TYPE.as_bytes());
+        write_string(&mut data, &self.nonce);
+        write_string(&mut data, &self.public_key.to_bytes(true));
+        write_u64(&mut data, self.serial);
+        write_u32(&mut data, self.cert_type as u32);
+        write_string(&mut data, self.key_id.as_bytes());
+        let mut principals = Vec::new();
+        for principal in &self.valid_principals {
+            write_string(&mut principals, principal.as_bytes());
+        }
+        write_string(&mut data, &principals);
+        write_u64(&mut data, self.valid_after);
+        write_u64(&mut data, self.valid_before);
+        write_string(&mut data, &options_blob(&self.critical_options));
+        write_string(&mut data, &options_blob(&self.extensions));
+        // reserved
+        write_string(&mut data, b"");
+        write_string(&mut data, &public_key_blob(ca_key));
+        data
+    }
+
+    /// Signs the certificate with `ca_key`, `sign` runs the signing protocol on the
+    /// `signed_data`. Fails with `InvalidSig` if the signature of `sign` doesn't verify.
+    pub fn sign(
+        self,
+        ca_key: &Point<Ed25519>,
+        sign: impl FnOnce(&[u8]) -> Result<Signature, Error>,
+    ) -> Result<SignedCertificate, Error> {
+        let signature = sign(&self.signed_data(ca_key))?;
+        let signed = SignedCertificate {
+            certificate: self,
+            ca_key: ca_key.clone(),
+            signature,
+        };
+        signed.verify()?;
+        Ok(signed)
+    }
+}
+
+impl SignedCertificate {
+    pub fn verify(&self) -> Result<(), Error> {
+        self.signature
+            .verify(&self.certificate.signed_data(&self.ca_key), &self.ca_key)
+            .map_err(|_| InvalidSig)
+    }
+
+    pub fn to_openssh(&self, comment: &str) -> String {
+        let mut blob = self.certificate.signed_data(&self.ca_key);
+        write_string(&mut blob, &signature_blob(&self.signature));
+        let blob = Base64::encode_string(&blob);
+        if comment.is_empty() {
+            format!("{} {}", CERT_KEY_TYPE, blob)
+        } else {
+            format!("{} {} {}", CERT_KEY_TYPE, blob, comment)
+        }
+    }
+
+    /// Parses a certificate line, the comment is ignored. Fails with `InvalidKey` for anything
+    /// but a well formed Ed25519 certificate signed by an Ed25519 CA and with `InvalidSig` if
+    /// the CA signature doesn't verify.
+    pub fn from_openssh(line: &str) -> Result<SignedCertificate, Error> {
+        let mut fields = line.split_whitespace();
+        if fields.next() != Some(CERT_KEY_TYPE) {
+            return Err(InvalidKey);
+        }
+        let blob = Base64::decode_vec(fields.next().ok_or(InvalidKey)?).map_err(|_| InvalidKey)?;
+        let mut reader = Reader(&blob);
+        if reader.string()? != CERT_KEY_TYPE.as_bytes() {
+            return Err(InvalidKey);
+        }
+        let nonce = reader.string()?.to_vec();
+        let public_key = decode_point(reader.string()?).ok_or(InvalidKey)?;
+        let serial = reader.u64()?;
+        let cert_type = match reader.u32()? {
+            1 => CertType::User,
+            2 => CertType::Host,
+            _ => return Err(InvalidKey),
+        };
+        let key_id = String::from(reader.str()?);
+        let mut principals = Reader(reader.string()?);
+        let mut valid_principals = Vec::new();
+        while !principals.0.is_empty() {
+            valid_principals.push(String::from(principals.str()?));
+        }
+        let valid_after = reader.u64()?;
+        let valid_before = reader.u64()?;
+        let critical_options = read_options(reader.string()?)?;
+        let extensions = read_options(reader.string()?)?;
+        let _reserved = reader.string()?;
+        let ca_key = read_public_key_blob(&mut reader)?;
+        let signature = read_signature_blob(&mut reader)?;
+        reader.finish()?;
+
+        let signed = SignedCertificate {
+            certificate: Certificate {
+                nonce,
+                public_key,
+                serial,
+                cert_type,
+                key_id,
+                valid_principals,
+                valid_after,
+                valid_before,
+                critical_options,
+                extensions,
+            },
+            ca_key,
+            signature,
+        };
+        signed.verify()?;
+        Ok(signed)
+    }
+}
+
+// a non-empty value is held in a string within the data string
+fn options_blob(options: &[(String, String)]) -> Vec<u8> {
+    let mut sorted: Vec<_> = options.iter().collect();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut blob = Vec::new();
+    for (name, value) in sorted {
+        write_string(&mut blob, name.as_bytes());
+        let mut data = Vec::new();
+        if !value.is_empty() {
+            write_string(&mut data, value.as_bytes());
+        }
+        write_string(&mut blob, &data);
+    }
+    blob
+}
+
+fn read_options(blob: &[u8]) -> Result<Vec<(String, String)>, Error> {
+    let mut reader = Reader(blob);
+    let mut options = Vec::new();
+    while !reader.0.is_empty() {
+        let name = String::from(reader.str()?);
+        let mut data = Reader(reader.string()?);
+        let value = if data.0.is_empty() {
+            String::new()
+        } else {
+            let value = String::from(data.str()?);
+            data.finish()?;
+            value
+        };
+        options.push((name, value));
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use local;
+    use openssh::public_key_from_openssh;
+    use openssh::tests::PRIVATE_KEY;
+    use protocols::aggsig::{self, AggregatedKey};
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use protocols::ExpandedKeyPair;
+
+    const USER_KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIMYNjGfR2YdWj4agQb9zGBBij22e/QFkkWZUUKtQtBut user";
+    // ssh-keygen -s <PRIVATE_KEY> -I alice@example -n alice,root
+    //     -V 20240101000000:20250101000000 -z 42 -O force-command=ls <USER_KEY>
+    const CERTIFICATE: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIDyChdWraSw5JKf7hTsRGY/7LkOMfCrohvdFO2tNzDd4AAAAIMYNjGfR2YdWj4agQb9zGBBij22e/QFkkWZUUKtQtButAAAAAAAAACoAAAABAAAADWFsaWNlQGV4YW1wbGUAAAARAAAABWFsaWNlAAAABHJvb3QAAAAAZZIAgAAAAABndIWAAAAAGwAAAA1mb3JjZS1jb21tYW5kAAAABgAAAAJscwAAAIIAAAAVcGVybWl0LVgxMS1mb3J3YXJkaW5nAAAAAAAAABdwZXJtaXQtYWdlbnQtZm9yd2FyZGluZwAAAAAAAAAWcGVybWl0LXBvcnQtZm9yd2FyZGluZwAAAAAAAAAKcGVybWl0LXB0eQAAAAAAAAAOcGVybWl0LXVzZXItcmMAAAAAAAAAAAAAADMAAAALc3NoLWVkMjU1MTkAAAAgLKivDLAxBAH0ZHpNXQVyQJqd+TAzBEqv72iV0jWMPMgAAABTAAAAC3NzaC1lZDI1NTE5AAAAQACgoC/UFrA7MyhikdJIrR0aIw36g6+ewTkSrnWjVl0pZMJ96fyAhMkrE/ZxTpr+T/MR6W9LUYCPHcmM8JjGpwI= user";
+
+    #[test]
+    fn test_certificate_from_ssh_keygen() {
+        let ca = ExpandedKeyPair::from_openssh(PRIVATE_KEY).unwrap();
+        let signed = SignedCertificate::from_openssh(CERTIFICATE).unwrap();
+        assert_eq!(signed.ca_key, ca.public_key);
+        let certificate = &signed.certificate;
+        assert_eq!(
+            certificate.public_key,
+            public_key_from_openssh(USER_KEY).unwrap()
+        );
+        assert_eq!(certificate.serial, 42);
+        assert_eq!(certificate.cert_type, CertType::User);
+        assert_eq!(certificate.key_id, "alice@example");
+        assert_eq!(certificate.valid_principals, ["alice", "root"]);
+        assert_eq!(certificate.valid_after, 1704067200);
+        assert_eq!(certificate.valid_before, 1735689600);
+        assert_eq!(
+            certificate.critical_options,
+            [(String::from("force-command"), String::from("ls"))]
+        );
+        assert_eq!(signed.to_openssh("user"), CERTIFICATE);
+
+        // the same fields signed here
+        let mut rng = deterministic_fast_rand("test_certificate_from_ssh_keygen", None);
+        let mut resigned = Certificate::new_rng(
+            certificate.public_key.clone(),
+            CertType::User,
+            "alice@example",
+            &mut rng,
+        );
+        assert_eq!(resigned.extensions, certificate.extensions);
+        resigned.nonce = certificate.nonce.clone();
+        resigned.serial = 42;
+        resigned.valid_principals = certificate.valid_principals.clone();
+        resigned.valid_after = certificate.valid_after;
+        resigned.valid_before = certificate.valid_before;
+        resigned.critical_options =
+            alloc::vec![(String::from("force-command"), String::from("ls"))];
+        let resigned = resigned
+            .sign(&ca.public_key, |data| Ok(aggsig::sign_single(data, &ca)))
+            .unwrap();
+        assert_eq!(resigned.certificate, signed.certificate);
+        assert_eq!(
+            SignedCertificate::from_openssh(&resigned.to_openssh("user")).unwrap(),
+            resigned
+        );
+    }
+
+    #[test]
+    fn test_certificate_aggregated_ca() {
+        let mut rng = deterministic_fast_rand("test_certificate_aggregated_ca", None);
+        let host = ExpandedKeyPair::create_rng(&mut rng).public_key;
+        let signers = [
+            ExpandedKeyPair::create_rng(&mut rng),
+            ExpandedKeyPair::create_rng(&mut rng),
+        ];
+        let agg_key =
+            AggregatedKey::new(&[signers[0].public_key.clone(), signers[1].public_key.clone()]);
+        let ca_key = agg_key.apk.clone();
+        let certificate = Certificate::new_rng(host, CertType::Host, "host", &mut rng);
+        assert!(certificate.extensions.is_empty());
+
+        // a session signing with only one of the co-signers
+        assert_eq!(
+            certificate
+                .clone()
+                .sign(&ca_key, |data| Ok(aggsig::sign_single(data, &signers[0])))
+                .err(),
+            Some(InvalidSig)
+        );
+        let signed = certificate
+            .clone()
+            .sign(&ca_key, |data| {
+                local::sign_rng(data, &signers, &agg_key, &mut rng)
+            })
+            .unwrap();
+        let data = certificate.signed_data(&ca_key);
+        assert!(verify_dalek(&ca_key, &signed.signature, &data));
+        let line = signed.to_openssh("");
+        assert_eq!(SignedCertificate::from_openssh(&line).unwrap(), signed);
+
+        let mut forged = signed;
+        forged.certificate.valid_principals = alloc::vec![String::from("root")];
+        assert_eq!(
+            SignedCertificate::from_openssh(&forged.to_openssh("")).err(),
+            Some(InvalidSig)
+        );
+        assert_eq!(
+            SignedCertificate::from_openssh(&line.replacen("ssh-ed25519-cert", "ssh-rsa-cert", 1))
+                .err(),
+            Some(InvalidKey)
+        );
+    }
+}
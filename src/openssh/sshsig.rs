@@ -0,0 +1,177 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! `sshsig` signatures (PROTOCOL.sshsig) as made and checked by `ssh-keygen -Y sign` and
+//! `ssh-keygen -Y verify`, e.g. for git commits or files signed with an aggregated key.
+//!
+//! The co-signers sign the `signed_data` of a message, which binds the namespace
+//! (`git`, `file`, ...) and the SHA-512 hash of the message, with any of the protocols.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use base64ct::{Base64, Encoding};
+use curv::elliptic::curves::{Ed25519, Point};
+use sha2::{Digest, Sha256, Sha512};
+
+use super::{
+    armor, dearmor, public_key_blob, read_public_key_blob, read_signature_blob, signature_blob,
+    write_string, write_u32, Reader,
+};
+use protocols::Signature;
+use Error::{self, InvalidContext, InvalidKey, InvalidSig};
+
+const MAGIC: &[u8] = b"SSHSIG";
+const VERSION: u32 = 1;
+const BEGIN: &str = "-----BEGIN SSH SIGNATURE-----";
+const END: &str = "-----END SSH SIGNATURE-----";
+
+/// The bytes to sign for `message` in `namespace`.
+pub fn signed_data(namespace: &str, message: &[u8]) -> Vec<u8> {
+    signed_data_with_hash(namespace, "sha512", &Sha512::digest(message))
+}
+
+/// The armored signature of `message` in `namespace` by `public_key`, `sign` runs the signing
+/// protocol on the `signed_data`.
+/// Fails with `InvalidContext` for an empty namespace, which `ssh-keygen` rejects, and with
+/// `InvalidSig` if the signature of `sign` doesn't verify.
+pub fn sign(
+    public_key: &Point<Ed25519>,
+    namespace: &str,
+    message: &[u8],
+    sign: impl FnOnce(&[u8]) -> Result<Signature, Error>,
+) -> Result<String, Error> {
+    if namespace.is_empty() {
+        return Err(InvalidContext);
+    }
+    let data = signed_data(namespace, message);
+    let signature = sign(&data)?;
+    signature
+        .verify(&data, public_key)
+        .map_err(|_| InvalidSig)?;
+    Ok(to_armored(public_key, namespace, &signature))
+}
+
+/// The armored signature for a `signature` of the `signed_data` of a message in `namespace`.
+pub fn to_armored(public_key: &Point<Ed25519>, namespace: &str, signature: &Signature) -> String {
+    let mut blob = MAGIC.to_vec();
+    write_u32(&mut blob, VERSION);
+    write_string(&mut blob, &public_key_blob(public_key));
+    write_string(&mut blob, namespace.as_bytes());
+    write_string(&mut blob, b"");
+    write_string(&mut blob, b"sha512");
+    write_string(&mut blob, &signature_blob(signature));
+    armor(&Base64::encode_string(&blob), BEGIN, END)
+}
+
+/// Checks an armored signature of `message` in `namespace` and returns the public key it was
+/// made with, which the caller still has to trust (as `ssh-keygen -Y verify` does with
+/// `allowed_signers`). Fails with `InvalidKey` for a malformed signature, `InvalidContext` for
+/// another namespace and `InvalidSig` if it doesn't verify.
+pub fn verify(armored: &str, namespace: &str, message: &[u8]) -> Result<Point<Ed25519>, Error> {
+    let blob = Base64::decode_vec(&dearmor(armored, BEGIN, END)?).map_err(|_| InvalidKey)?;
+    let mut reader = Reader(blob.strip_prefix(MAGIC).ok_or(InvalidKey)?);
+    if reader.u32()? != VERSION {
+        return Err(InvalidKey);
+    }
+    let public_key = read_public_key_blob(&mut reader)?;
+    if reader.string()? != namespace.as_bytes() {
+        return Err(InvalidContext);
+    }
+    let _reserved = reader.string()?;
+    let hash_algorithm = reader.str()?;
+    let hash = match hash_algorithm {
+        "sha512" => Sha512::digest(message).to_vec(),
+        "sha256" => Sha256::digest(message).to_vec(),
+        _ => return Err(InvalidKey),
+    };
+    let signature = read_signature_blob(&mut reader)?;
+    reader.finish()?;
+    signature
+        .verify(
+            &signed_data_with_hash(namespace, hash_algorithm, &hash),
+            &public_key,
+        )
+        .map_err(|_| InvalidSig)?;
+    Ok(public_key)
+}
+
+fn signed_data_with_hash(namespace: &str, hash_algorithm: &str, hash: &[u8]) -> Vec<u8> {
+    let mut data = MAGIC.to_vec();
+    write_string(&mut data, namespace.as_bytes());
+    write_string(&mut data, b"");
+    write_string(&mut data, hash_algorithm.as_bytes());
+    write_string(&mut data, hash);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssh::tests::PRIVATE_KEY;
+    use protocols::aggsig;
+    use protocols::ExpandedKeyPair;
+
+    // printf 'hello sshsig\n' > msg; ssh-keygen -Y sign -f <PRIVATE_KEY> -n file msg
+    const SIGNATURE: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgLKivDLAxBAH0ZHpNXQVyQJqd+T
+AzBEqv72iV0jWMPMgAAAAEZmlsZQAAAAAAAAAGc2hhNTEyAAAAUwAAAAtzc2gtZWQyNTUx
+OQAAAEBFEmk1eCqfVkSJ5NiWV3xnHcaYG2pIB4pD+2IPMAddmQJuFHmDBJgXJ4BJnnmhJh
+DMiDt1QgRNHmGqux80wmsB
+-----END SSH SIGNATURE-----
+";
+    const MESSAGE: &[u8] = b"hello sshsig\n";
+
+    #[test]
+    fn test_sshsig() {
+        let keypair = ExpandedKeyPair::from_openssh(PRIVATE_KEY).unwrap();
+        assert_eq!(
+            verify(SIGNATURE, "file", MESSAGE).unwrap(),
+            keypair.public_key
+        );
+        let armored = sign(&keypair.public_key, "file", MESSAGE, |data| {
+            Ok(aggsig::sign_single(data, &keypair))
+        })
+        .unwrap();
+        assert_eq!(
+            verify(&armored, "file", MESSAGE).unwrap(),
+            keypair.public_key
+        );
+        // the same blob up to the signature
+        assert_eq!(armored[..175], SIGNATURE[..175]);
+
+        assert_eq!(verify(SIGNATURE, "git", MESSAGE), Err(InvalidContext));
+        assert_eq!(verify(SIGNATURE, "file", b"hello"), Err(InvalidSig));
+        assert_eq!(
+            verify(&SIGNATURE.replace("U1NIU0lH", "U1NIU0lI"), "file", MESSAGE),
+            Err(InvalidKey)
+        );
+        assert_eq!(
+            sign(&keypair.public_key, "", MESSAGE, |data| Ok(
+                aggsig::sign_single(data, &keypair)
+            )),
+            Err(InvalidContext)
+        );
+        // a session signing something else
+        assert_eq!(
+            sign(&keypair.public_key, "file", MESSAGE, |_| Ok(
+                aggsig::sign_single(MESSAGE, &keypair)
+            )),
+            Err(InvalidSig)
+        );
+    }
+}
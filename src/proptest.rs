@@ -0,0 +1,219 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! [proptest](https://proptest-rs.github.io/proptest) strategies for keys, the aggsig messages
+//! and signatures, to property test code built on this crate (e.g. the routing of an
+//! orchestrator) with values that are valid and with ones that have to be rejected.
+//!
+//! `signed_message`, `commitment` and `msg` give values that verify and are well addressed,
+//! `forged_message`, `malformed_signature_bytes`, `mismatched_commitment` and `misaddressed_msg`
+//! give ones that are guaranteed not to, whatever they shrink to.
+
+use alloc::vec::Vec;
+
+use curv::arithmetic::Converter;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use curv::BigInt;
+use proptest_crate::collection::vec;
+use proptest_crate::option;
+use proptest_crate::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use protocols::aggsig::{self, AggSigMessage, SignFirstMsg, SignSecondMsg};
+use protocols::rounds::Msg;
+use protocols::{ExpandedKeyPair, Signature};
+
+pub fn scalar() -> impl Strategy<Value = Scalar<Ed25519>> {
+    any::<[u8; 32]>().prop_map(|bytes| Scalar::from_bigint(&BigInt::from_bytes(&bytes)))
+}
+
+/// A multiple of the generator, the identity only for the zero scalar.
+pub fn point() -> impl Strategy<Value = Point<Ed25519>> {
+    scalar().prop_map(|scalar| Point::generator() * scalar)
+}
+
+pub fn keypair() -> impl Strategy<Value = ExpandedKeyPair> {
+    any::<[u8; 32]>().prop_map(ExpandedKeyPair::create_from_private_key)
+}
+
+/// Up to 255 bytes.
+pub fn message() -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<u8>(), 0..256)
+}
+
+/// A public key, a message and a signature of it that verifies under the key.
+pub fn signed_message() -> impl Strategy<Value = (Point<Ed25519>, Vec<u8>, Signature)> {
+    (keypair(), message()).prop_map(|(keys, message)| {
+        let signature = aggsig::sign_single(&message, &keys);
+        (keys.public_key, message, signature)
+    })
+}
+
+/// `signed_message` with the `s`, the `R`, the message or the key changed, so that the signature
+/// doesn't verify.
+pub fn forged_message() -> impl Strategy<Value = (Point<Ed25519>, Vec<u8>, Signature)> {
+    (signed_message(), 0..4u8).prop_map(|((public_key, mut message, mut signature), forgery)| {
+        let mut public_key = public_key;
+        match forgery {
+            0 => signature.s = &signature.s + Scalar::from(1),
+            1 => signature.R = &signature.R + Point::generator(),
+            2 => message.push(0),
+            _ => public_key = &public_key + Point::generator(),
+        }
+        (public_key, message, signature)
+    })
+}
+
+/// Encodings `Signature::from_bytes` rejects: of another length than 64 bytes, with a non
+/// canonical `s` (`s + l`) or with an `R` whose `y` coordinate isn't reduced mod `p`.
+pub fn malformed_signature_bytes() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        vec(any::<u8>(), 0..64),
+        vec(any::<u8>(), 65..96),
+        signed_message().prop_map(|(_, _, signature)| {
+            let s_plus_l = signature.s.to_bigint() + Scalar::<Ed25519>::group_order();
+            let mut bytes = signature.to_bytes();
+            // l < 2^253, so `s + l` fits
+            let mut s = s_plus_l.to_bytes();
+            s.reverse();
+            bytes[32..].iter_mut().for_each(|b| *b = 0);
+            bytes[32..32 + s.len()].copy_from_slice(&s);
+            bytes.to_vec()
+        }),
+        (signed_message(), 0..19u8, any::<bool>()).prop_map(|((_, _, signature), k, sign)| {
+            // y = p + k, with p = 2^255 - 19
+            let mut bytes = signature.to_bytes();
+            bytes[..32].iter_mut().for_each(|b| *b = 0xff);
+            bytes[0] = 0xed + k;
+            bytes[31] = if sign { 0xff } else { 0x7f };
+            bytes.to_vec()
+        }),
+    ]
+}
+
+/// A round 1 commitment and the round 2 reveal that opens it, of a fresh nonce.
+pub fn commitment() -> impl Strategy<Value = (SignFirstMsg, SignSecondMsg)> {
+    (keypair(), message(), any::<[u8; 32]>()).prop_map(|(keys, message, seed)| {
+        let (_, first_msg, second_msg) = aggsig::create_ephemeral_key_and_commit_rng(
+            &keys,
+            &message,
+            &mut StdRng::from_seed(seed),
+        )
+        .expect("the default domain has no context");
+        (first_msg, second_msg)
+    })
+}
+
+/// `commitment` with a reveal that doesn't open it: another blind factor, another `R` or the
+/// identity as `R`.
+pub fn mismatched_commitment() -> impl Strategy<Value = (SignFirstMsg, SignSecondMsg)> {
+    (commitment(), 0..3u8).prop_map(|((first_msg, mut second_msg), mismatch)| {
+        match mismatch {
+            0 => second_msg.blind_factor = &second_msg.blind_factor + BigInt::from(1),
+            1 => second_msg.R = &second_msg.R + Point::generator(),
+            _ => second_msg.R = Point::zero(),
+        }
+        (first_msg, second_msg)
+    })
+}
+
+/// Any of the aggsig round messages, as parties of different sessions send them.
+pub fn aggsig_message() -> impl Strategy<Value = AggSigMessage> {
+    prop_oneof![
+        commitment().prop_map(|(first_msg, _)| AggSigMessage::Commitment(first_msg)),
+        commitment().prop_map(|(_, second_msg)| AggSigMessage::Reveal(second_msg)),
+        signed_message().prop_map(|(_, _, signature)| AggSigMessage::PartialSignature(signature)),
+    ]
+}
+
+/// `body` broadcast or sent to one party, by and to parties of a session of `parties` parties.
+pub fn msg<S: Strategy>(body: S, parties: u16) -> impl Strategy<Value = Msg<S::Value>> {
+    (1..=parties, option::of(1..=parties), body).prop_map(|(sender, receiver, body)| Msg {
+        sender,
+        receiver,
+        body,
+    })
+}
+
+/// `body` from or to a party that isn't in a session of `parties` (less than `u16::MAX`) parties.
+pub fn misaddressed_msg<S: Strategy>(
+    body: S,
+    parties: u16,
+) -> impl Strategy<Value = Msg<S::Value>> {
+    let outside = || prop_oneof![Just(0), parties + 1..=u16::MAX];
+    let addresses = prop_oneof![
+        (outside(), option::of(1..=parties)),
+        (1..=parties, outside().prop_map(Some)),
+    ];
+    (addresses, body).prop_map(|((sender, receiver), body)| Msg {
+        sender,
+        receiver,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest_crate::prelude::*;
+
+    use super::{
+        aggsig_message, commitment, forged_message, malformed_signature_bytes, misaddressed_msg,
+        mismatched_commitment, msg, signed_message,
+    };
+    use protocols::aggsig::{self, AggSigMessage};
+    use protocols::Signature;
+
+    proptest! {
+        #[test]
+        fn test_signed_messages_verify((public_key, message, signature) in signed_message()) {
+            prop_assert!(signature.verify(&message, &public_key).is_ok());
+            let bytes = signature.to_bytes();
+            prop_assert_eq!(Signature::from_bytes(&bytes).unwrap(), signature);
+        }
+
+        #[test]
+        fn test_forged_messages_fail((public_key, message, signature) in forged_message()) {
+            prop_assert!(signature.verify(&message, &public_key).is_err());
+        }
+
+        #[test]
+        fn test_malformed_signature_bytes_fail(bytes in malformed_signature_bytes()) {
+            prop_assert!(Signature::from_bytes(&bytes).is_err());
+        }
+
+        #[test]
+        fn test_commitments((first_msg, second_msg) in commitment(),
+                            (bad_first_msg, bad_second_msg) in mismatched_commitment()) {
+            prop_assert!(aggsig::verify_commitment(&first_msg, &second_msg).is_ok());
+            prop_assert!(aggsig::verify_commitment(&bad_first_msg, &bad_second_msg).is_err());
+        }
+
+        #[test]
+        fn test_msgs(good in msg(aggsig_message(), 5), bad in misaddressed_msg(aggsig_message(), 5)) {
+            prop_assert!((1..=5).contains(&good.sender));
+            prop_assert!(good.receiver.is_none_or(|receiver| (1..=5).contains(&receiver)));
+            let in_session = |party: u16| (1..=5).contains(&party);
+            prop_assert!(
+                !in_session(bad.sender) || bad.receiver.is_some_and(|receiver| !in_session(receiver))
+            );
+            if let AggSigMessage::PartialSignature(signature) = good.body {
+                prop_assert!(Signature::from_bytes(&signature.to_bytes()).is_ok());
+            }
+        }
+    }
+}
@@ -0,0 +1,1160 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Protobuf encodings of the protocol messages
+//!
+//! The types here are the messages of `proto/multi_party_eddsa.proto` laid out as prost generates
+//! them: `Vec<u8>` for `bytes`, `Option` for message fields and the `oneof` of `Envelope` as the
+//! `envelope::Message` enum. Parties in other languages generate their types from the same schema.
+//! `encode_to_vec` and `decode` implement the protobuf binary encoding, fields with their default
+//! value aren't written and unknown fields are skipped when decoding.
+//!
+//! `From` converts a protocol message to its protobuf message and `TryFrom` back. The conversion
+//! fails with `InvalidPoint` if a point isn't a canonical encoding, and with `InvalidMessage` for
+//! fields of the wrong size, a non canonical scalar, a party index above `u16::MAX` or an envelope
+//! with another version than `WIRE_VERSION`.
+
+use alloc::vec::Vec;
+use core::convert::{TryFrom, TryInto};
+
+use curv::arithmetic::Converter;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use curv::BigInt;
+
+use protocols::aggsig::SIGN_FIRST_MSG_SIZE;
+use protocols::musig2::NUMBER_OF_NONCES;
+use protocols::schnorr::{scalar_from_bytes_le, scalar_to_bytes_le};
+use protocols::wire::{self, WIRE_VERSION};
+use protocols::{self, aggsig, decode_point, frost, keygen, musig2, refresh};
+use Error::{self, InvalidMessage, InvalidPoint};
+
+// the wire types of the protobuf encoding, groups aren't supported
+const VARINT: u8 = 0;
+const FIXED64: u8 = 1;
+const LENGTH_DELIMITED: u8 = 2;
+const FIXED32: u8 = 5;
+
+// the fields of a message, what prost's `Message` derive generates
+trait Fields: Default {
+    fn encode_fields(&self, buf: &mut Vec<u8>);
+
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error>;
+}
+
+fn encode<M: Fields>(message: &M) -> Vec<u8> {
+    let mut buf = Vec::new();
+    message.encode_fields(&mut buf);
+    buf
+}
+
+fn decode<M: Fields>(bytes: &[u8]) -> Result<M, Error> {
+    let mut message = M::default();
+    merge(&mut message, bytes)?;
+    Ok(message)
+}
+
+// a field that appears again overwrites a scalar, appends to a repeated field and is merged into
+// a message field
+fn merge<M: Fields>(message: &mut M, bytes: &[u8]) -> Result<(), Error> {
+    let mut reader = Reader { bytes };
+    while !reader.bytes.is_empty() {
+        let key = reader.varint()?;
+        let field = u32::try_from(key >> 3).map_err(|_| InvalidMessage)?;
+        if field == 0 {
+            return Err(InvalidMessage);
+        }
+        // the low 3 bits
+        message.merge_field(field, (key & 7) as u8, &mut reader)?;
+    }
+    Ok(())
+}
+
+macro_rules! impl_message {
+    ($($name:ident),*) => {$(
+        impl $name {
+            /// The protobuf encoding of the message.
+            pub fn encode_to_vec(&self) -> Vec<u8> {
+                encode(self)
+            }
+
+            /// Fails with `InvalidMessage` if `bytes` isn't the protobuf encoding of a message.
+            pub fn decode(bytes: &[u8]) -> Result<$name, Error> {
+                decode(bytes)
+            }
+        }
+    )*};
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if len > self.bytes.len() {
+            return Err(InvalidMessage);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn varint(&mut self) -> Result<u64, Error> {
+        let mut value = 0u64;
+        for i in 0..10 {
+            let byte = self.take(1)?[0];
+            // the 10th byte holds the top bit
+            if i == 9 && byte > 1 {
+                return Err(InvalidMessage);
+            }
+            value |= u64::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(InvalidMessage)
+    }
+
+    fn bytes(&mut self, wire_type: u8) -> Result<&'a [u8], Error> {
+        if wire_type != LENGTH_DELIMITED {
+            return Err(InvalidMessage);
+        }
+        let len = usize::try_from(self.varint()?).map_err(|_| InvalidMessage)?;
+        self.take(len)
+    }
+
+    // truncated to the low 32 bits, like every protobuf implementation does
+    fn uint32(&mut self, wire_type: u8) -> Result<u32, Error> {
+        if wire_type != VARINT {
+            return Err(InvalidMessage);
+        }
+        Ok(self.varint()? as u32)
+    }
+
+    fn skip(&mut self, wire_type: u8) -> Result<(), Error> {
+        match wire_type {
+            VARINT => self.varint().map(drop),
+            FIXED64 => self.take(8).map(drop),
+            LENGTH_DELIMITED => self.bytes(wire_type).map(drop),
+            FIXED32 => self.take(4).map(drop),
+            _ => Err(InvalidMessage),
+        }
+    }
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn put_key(buf: &mut Vec<u8>, field: u32, wire_type: u8) {
+    put_varint(buf, u64::from(field) << 3 | u64::from(wire_type));
+}
+
+fn put_length_delimited(buf: &mut Vec<u8>, field: u32, value: &[u8]) {
+    put_key(buf, field, LENGTH_DELIMITED);
+    put_varint(buf, value.len() as u64);
+    buf.extend_from_slice(value);
+}
+
+// proto3 doesn't write scalar fields with their default value
+fn put_uint32(buf: &mut Vec<u8>, field: u32, value: u32) {
+    if value != 0 {
+        put_key(buf, field, VARINT);
+        put_varint(buf, value.into());
+    }
+}
+
+fn put_bytes(buf: &mut Vec<u8>, field: u32, value: &[u8]) {
+    if !value.is_empty() {
+        put_length_delimited(buf, field, value);
+    }
+}
+
+fn put_repeated_bytes(buf: &mut Vec<u8>, field: u32, values: &[Vec<u8>]) {
+    for value in values {
+        put_length_delimited(buf, field, value);
+    }
+}
+
+fn put_message<M: Fields>(buf: &mut Vec<u8>, field: u32, message: &M) {
+    put_length_delimited(buf, field, &encode(message));
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct SignFirstMsg {
+    pub commitment: Vec<u8>,
+}
+
+impl Fields for SignFirstMsg {
+    fn encode_fields(&self, buf: &mut Vec<u8>) {
+        put_bytes(buf, 1, &self.commitment);
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error> {
+        match field {
+            1 => self.commitment = reader.bytes(wire_type)?.to_vec(),
+            _ => reader.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct SignSecondMsg {
+    pub r: Vec<u8>,
+    pub blind_factor: Vec<u8>,
+}
+
+impl Fields for SignSecondMsg {
+    fn encode_fields(&self, buf: &mut Vec<u8>) {
+        put_bytes(buf, 1, &self.r);
+        put_bytes(buf, 2, &self.blind_factor);
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error> {
+        match field {
+            1 => self.r = reader.bytes(wire_type)?.to_vec(),
+            2 => self.blind_factor = reader.bytes(wire_type)?.to_vec(),
+            _ => reader.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Signature {
+    pub r: Vec<u8>,
+    pub s: Vec<u8>,
+}
+
+impl Fields for Signature {
+    fn encode_fields(&self, buf: &mut Vec<u8>) {
+        put_bytes(buf, 1, &self.r);
+        put_bytes(buf, 2, &self.s);
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error> {
+        match field {
+            1 => self.r = reader.bytes(wire_type)?.to_vec(),
+            2 => self.s = reader.bytes(wire_type)?.to_vec(),
+            _ => reader.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Musig2Nonces {
+    pub r: Vec<Vec<u8>>,
+}
+
+impl Fields for Musig2Nonces {
+    fn encode_fields(&self, buf: &mut Vec<u8>) {
+        put_repeated_bytes(buf, 1, &self.r);
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error> {
+        match field {
+            1 => self.r.push(reader.bytes(wire_type)?.to_vec()),
+            _ => reader.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Musig2PartialSignature {
+    pub r: Vec<u8>,
+    pub partial_s: Vec<u8>,
+}
+
+impl Fields for Musig2PartialSignature {
+    fn encode_fields(&self, buf: &mut Vec<u8>) {
+        put_bytes(buf, 1, &self.r);
+        put_bytes(buf, 2, &self.partial_s);
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error> {
+        match field {
+            1 => self.r = reader.bytes(wire_type)?.to_vec(),
+            2 => self.partial_s = reader.bytes(wire_type)?.to_vec(),
+            _ => reader.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ProofOfKnowledge {
+    pub r: Vec<u8>,
+    pub mu: Vec<u8>,
+}
+
+impl Fields for ProofOfKnowledge {
+    fn encode_fields(&self, buf: &mut Vec<u8>) {
+        put_bytes(buf, 1, &self.r);
+        put_bytes(buf, 2, &self.mu);
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error> {
+        match field {
+            1 => self.r = reader.bytes(wire_type)?.to_vec(),
+            2 => self.mu = reader.bytes(wire_type)?.to_vec(),
+            _ => reader.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct KeyGenBroadcast1 {
+    pub party_index: u32,
+    pub vss: Vec<Vec<u8>>,
+    pub proof: Option<ProofOfKnowledge>,
+}
+
+impl Fields for KeyGenBroadcast1 {
+    fn encode_fields(&self, buf: &mut Vec<u8>) {
+        put_uint32(buf, 1, self.party_index);
+        put_repeated_bytes(buf, 2, &self.vss);
+        if let Some(proof) = &self.proof {
+            put_message(buf, 3, proof);
+        }
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error> {
+        match field {
+            1 => self.party_index = reader.uint32(wire_type)?,
+            2 => self.vss.push(reader.bytes(wire_type)?.to_vec()),
+            3 => merge(
+                self.proof.get_or_insert_with(Default::default),
+                reader.bytes(wire_type)?,
+            )?,
+            _ => reader.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct KeyGenMessage2 {
+    pub sender_index: u32,
+    pub receiver_index: u32,
+    pub secret_share: Vec<u8>,
+}
+
+impl Fields for KeyGenMessage2 {
+    fn encode_fields(&self, buf: &mut Vec<u8>) {
+        put_uint32(buf, 1, self.sender_index);
+        put_uint32(buf, 2, self.receiver_index);
+        put_bytes(buf, 3, &self.secret_share);
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error> {
+        match field {
+            1 => self.sender_index = reader.uint32(wire_type)?,
+            2 => self.receiver_index = reader.uint32(wire_type)?,
+            3 => self.secret_share = reader.bytes(wire_type)?.to_vec(),
+            _ => reader.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct RefreshBroadcast1 {
+    pub party_index: u32,
+    pub vss: Vec<Vec<u8>>,
+}
+
+impl Fields for RefreshBroadcast1 {
+    fn encode_fields(&self, buf: &mut Vec<u8>) {
+        put_uint32(buf, 1, self.party_index);
+        put_repeated_bytes(buf, 2, &self.vss);
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error> {
+        match field {
+            1 => self.party_index = reader.uint32(wire_type)?,
+            2 => self.vss.push(reader.bytes(wire_type)?.to_vec()),
+            _ => reader.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct RefreshMessage2 {
+    pub sender_index: u32,
+    pub receiver_index: u32,
+    pub secret_share: Vec<u8>,
+}
+
+impl Fields for RefreshMessage2 {
+    fn encode_fields(&self, buf: &mut Vec<u8>) {
+        put_uint32(buf, 1, self.sender_index);
+        put_uint32(buf, 2, self.receiver_index);
+        put_bytes(buf, 3, &self.secret_share);
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error> {
+        match field {
+            1 => self.sender_index = reader.uint32(wire_type)?,
+            2 => self.receiver_index = reader.uint32(wire_type)?,
+            3 => self.secret_share = reader.bytes(wire_type)?.to_vec(),
+            _ => reader.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct FrostCommitment {
+    pub party_index: u32,
+    pub d: Vec<u8>,
+    pub e: Vec<u8>,
+}
+
+impl Fields for FrostCommitment {
+    fn encode_fields(&self, buf: &mut Vec<u8>) {
+        put_uint32(buf, 1, self.party_index);
+        put_bytes(buf, 2, &self.d);
+        put_bytes(buf, 3, &self.e);
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error> {
+        match field {
+            1 => self.party_index = reader.uint32(wire_type)?,
+            2 => self.d = reader.bytes(wire_type)?.to_vec(),
+            3 => self.e = reader.bytes(wire_type)?.to_vec(),
+            _ => reader.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct FrostPartialSignature {
+    pub party_index: u32,
+    pub z: Vec<u8>,
+}
+
+impl Fields for FrostPartialSignature {
+    fn encode_fields(&self, buf: &mut Vec<u8>) {
+        put_uint32(buf, 1, self.party_index);
+        put_bytes(buf, 2, &self.z);
+    }
+
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error> {
+        match field {
+            1 => self.party_index = reader.uint32(wire_type)?,
+            2 => self.z = reader.bytes(wire_type)?.to_vec(),
+            _ => reader.skip(wire_type)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Envelope {
+    pub version: u32,
+    pub message: Option<envelope::Message>,
+}
+
+pub mod envelope {
+    /// The `message` oneof of `Envelope`.
+    #[derive(Clone, PartialEq, Eq, Debug)]
+    pub enum Message {
+        AggSigFirst(super::SignFirstMsg),
+        AggSigSecond(super::SignSecondMsg),
+        AggSigPartialSignature(super::Signature),
+        Musig2Nonces(super::Musig2Nonces),
+        Musig2PartialSignature(super::Musig2PartialSignature),
+        KeyGenBroadcast1(super::KeyGenBroadcast1),
+        KeyGenMessage2(super::KeyGenMessage2),
+        RefreshBroadcast1(super::RefreshBroadcast1),
+        RefreshMessage2(super::RefreshMessage2),
+        FrostCommitment(super::FrostCommitment),
+        FrostPartialSignature(super::FrostPartialSignature),
+    }
+}
+
+impl Fields for Envelope {
+    fn encode_fields(&self, buf: &mut Vec<u8>) {
+        use self::envelope::Message::*;
+
+        put_uint32(buf, 1, self.version);
+        match &self.message {
+            Some(AggSigFirst(message)) => put_message(buf, 2, message),
+            Some(AggSigSecond(message)) => put_message(buf, 3, message),
+            Some(AggSigPartialSignature(message)) => put_message(buf, 4, message),
+            Some(Musig2Nonces(message)) => put_message(buf, 5, message),
+            Some(Musig2PartialSignature(message)) => put_message(buf, 6, message),
+            Some(KeyGenBroadcast1(message)) => put_message(buf, 7, message),
+            Some(KeyGenMessage2(message)) => put_message(buf, 8, message),
+            Some(RefreshBroadcast1(message)) => put_message(buf, 9, message),
+            Some(RefreshMessage2(message)) => put_message(buf, 10, message),
+            Some(FrostCommitment(message)) => put_message(buf, 11, message),
+            Some(FrostPartialSignature(message)) => put_message(buf, 12, message),
+            None => {}
+        }
+    }
+
+    // a later member of the oneof replaces an earlier one
+    fn merge_field(&mut self, field: u32, wire_type: u8, reader: &mut Reader) -> Result<(), Error> {
+        use self::envelope::Message::*;
+
+        let message = match field {
+            1 => {
+                self.version = reader.uint32(wire_type)?;
+                return Ok(());
+            }
+            2..=12 => reader.bytes(wire_type)?,
+            _ => return reader.skip(wire_type),
+        };
+        self.message = Some(match field {
+            2 => AggSigFirst(decode(message)?),
+            3 => AggSigSecond(decode(message)?),
+            4 => AggSigPartialSignature(decode(message)?),
+            5 => Musig2Nonces(decode(message)?),
+            6 => Musig2PartialSignature(decode(message)?),
+            7 => KeyGenBroadcast1(decode(message)?),
+            8 => KeyGenMessage2(decode(message)?),
+            9 => RefreshBroadcast1(decode(message)?),
+            10 => RefreshMessage2(decode(message)?),
+            11 => FrostCommitment(decode(message)?),
+            _ => FrostPartialSignature(decode(message)?),
+        });
+        Ok(())
+    }
+}
+
+impl_message!(
+    SignFirstMsg,
+    SignSecondMsg,
+    Signature,
+    Musig2Nonces,
+    Musig2PartialSignature,
+    ProofOfKnowledge,
+    KeyGenBroadcast1,
+    KeyGenMessage2,
+    RefreshBroadcast1,
+    RefreshMessage2,
+    FrostCommitment,
+    FrostPartialSignature,
+    Envelope
+);
+
+fn point_bytes(point: &Point<Ed25519>) -> Vec<u8> {
+    point.to_bytes(true).to_vec()
+}
+
+fn scalar_bytes(scalar: &Scalar<Ed25519>) -> Vec<u8> {
+    scalar_to_bytes_le(scalar).to_vec()
+}
+
+// big endian, left padded to `size` bytes
+fn padded_bytes(n: &BigInt, size: usize) -> Vec<u8> {
+    let bytes = n.to_bytes();
+    let mut padded = alloc::vec![0u8; size.saturating_sub(bytes.len())];
+    padded.extend_from_slice(&bytes);
+    padded
+}
+
+fn to_point(bytes: &[u8]) -> Result<Point<Ed25519>, Error> {
+    if bytes.len() != 32 {
+        return Err(InvalidMessage);
+    }
+    decode_point(bytes).ok_or(InvalidPoint)
+}
+
+fn to_points(encodings: &[Vec<u8>]) -> Result<Vec<Point<Ed25519>>, Error> {
+    encodings.iter().map(|bytes| to_point(bytes)).collect()
+}
+
+fn to_scalar(bytes: &[u8]) -> Result<Scalar<Ed25519>, Error> {
+    let bytes = <&[u8; 32]>::try_from(bytes).map_err(|_| InvalidMessage)?;
+    scalar_from_bytes_le(bytes).ok_or(InvalidMessage)
+}
+
+fn to_bigint(bytes: &[u8], size: usize) -> Result<BigInt, Error> {
+    if bytes.len() > size {
+        return Err(InvalidMessage);
+    }
+    Ok(BigInt::from_bytes(bytes))
+}
+
+fn to_index(index: u32) -> Result<u16, Error> {
+    u16::try_from(index).map_err(|_| InvalidMessage)
+}
+
+impl From<&aggsig::SignFirstMsg> for SignFirstMsg {
+    fn from(message: &aggsig::SignFirstMsg) -> SignFirstMsg {
+        SignFirstMsg {
+            commitment: padded_bytes(&message.commitment, SIGN_FIRST_MSG_SIZE),
+        }
+    }
+}
+
+impl TryFrom<SignFirstMsg> for aggsig::SignFirstMsg {
+    type Error = Error;
+
+    fn try_from(message: SignFirstMsg) -> Result<aggsig::SignFirstMsg, Error> {
+        Ok(aggsig::SignFirstMsg {
+            commitment: to_bigint(&message.commitment, SIGN_FIRST_MSG_SIZE)?,
+        })
+    }
+}
+
+impl From<&aggsig::SignSecondMsg> for SignSecondMsg {
+    fn from(message: &aggsig::SignSecondMsg) -> SignSecondMsg {
+        SignSecondMsg {
+            r: point_bytes(&message.R),
+            blind_factor: padded_bytes(&message.blind_factor, 32),
+        }
+    }
+}
+
+impl TryFrom<SignSecondMsg> for aggsig::SignSecondMsg {
+    type Error = Error;
+
+    fn try_from(message: SignSecondMsg) -> Result<aggsig::SignSecondMsg, Error> {
+        Ok(aggsig::SignSecondMsg {
+            R: to_point(&message.r)?,
+            blind_factor: to_bigint(&message.blind_factor, 32)?,
+        })
+    }
+}
+
+impl From<&protocols::Signature> for Signature {
+    fn from(signature: &protocols::Signature) -> Signature {
+        Signature {
+            r: point_bytes(&signature.R),
+            s: scalar_bytes(&signature.s),
+        }
+    }
+}
+
+impl TryFrom<Signature> for protocols::Signature {
+    type Error = Error;
+
+    fn try_from(signature: Signature) -> Result<protocols::Signature, Error> {
+        Ok(protocols::Signature {
+            R: to_point(&signature.r)?,
+            s: to_scalar(&signature.s)?,
+        })
+    }
+}
+
+impl From<&musig2::PublicPartialNonces> for Musig2Nonces {
+    fn from(nonces: &musig2::PublicPartialNonces) -> Musig2Nonces {
+        Musig2Nonces {
+            r: nonces.R.iter().map(point_bytes).collect(),
+        }
+    }
+}
+
+impl TryFrom<Musig2Nonces> for musig2::PublicPartialNonces {
+    type Error = Error;
+
+    fn try_from(nonces: Musig2Nonces) -> Result<musig2::PublicPartialNonces, Error> {
+        let R = <[Point<Ed25519>; NUMBER_OF_NONCES]>::try_from(to_points(&nonces.r)?)
+            .map_err(|_| InvalidMessage)?;
+        Ok(musig2::PublicPartialNonces { R })
+    }
+}
+
+impl From<&musig2::PartialSignature> for Musig2PartialSignature {
+    fn from(signature: &musig2::PartialSignature) -> Musig2PartialSignature {
+        Musig2PartialSignature {
+            r: point_bytes(&signature.R),
+            partial_s: scalar_bytes(&signature.my_partial_s),
+        }
+    }
+}
+
+impl TryFrom<Musig2PartialSignature> for musig2::PartialSignature {
+    type Error = Error;
+
+    fn try_from(signature: Musig2PartialSignature) -> Result<musig2::PartialSignature, Error> {
+        Ok(musig2::PartialSignature {
+            R: to_point(&signature.r)?,
+            my_partial_s: to_scalar(&signature.partial_s)?,
+        })
+    }
+}
+
+impl From<&keygen::ProofOfKnowledge> for ProofOfKnowledge {
+    fn from(proof: &keygen::ProofOfKnowledge) -> ProofOfKnowledge {
+        ProofOfKnowledge {
+            r: point_bytes(&proof.R),
+            mu: scalar_bytes(&proof.mu),
+        }
+    }
+}
+
+impl TryFrom<ProofOfKnowledge> for keygen::ProofOfKnowledge {
+    type Error = Error;
+
+    fn try_from(proof: ProofOfKnowledge) -> Result<keygen::ProofOfKnowledge, Error> {
+        Ok(keygen::ProofOfKnowledge {
+            R: to_point(&proof.r)?,
+            mu: to_scalar(&proof.mu)?,
+        })
+    }
+}
+
+impl From<&keygen::KeyGenBroadcastMessage1> for KeyGenBroadcast1 {
+    fn from(message: &keygen::KeyGenBroadcastMessage1) -> KeyGenBroadcast1 {
+        KeyGenBroadcast1 {
+            party_index: message.party_index.into(),
+            vss: message.vss.commitments.iter().map(point_bytes).collect(),
+            proof: Some((&message.proof).into()),
+        }
+    }
+}
+
+impl TryFrom<KeyGenBroadcast1> for keygen::KeyGenBroadcastMessage1 {
+    type Error = Error;
+
+    fn try_from(message: KeyGenBroadcast1) -> Result<keygen::KeyGenBroadcastMessage1, Error> {
+        Ok(keygen::KeyGenBroadcastMessage1 {
+            party_index: to_index(message.party_index)?,
+            vss: keygen::VerificationVector {
+                commitments: to_points(&message.vss)?,
+            },
+            proof: message.proof.ok_or(InvalidMessage)?.try_into()?,
+        })
+    }
+}
+
+impl From<&keygen::KeyGenMessage2> for KeyGenMessage2 {
+    fn from(message: &keygen::KeyGenMessage2) -> KeyGenMessage2 {
+        KeyGenMessage2 {
+            sender_index: message.sender_index.into(),
+            receiver_index: message.receiver_index.into(),
+            secret_share: scalar_bytes(&message.secret_share),
+        }
+    }
+}
+
+impl TryFrom<KeyGenMessage2> for keygen::KeyGenMessage2 {
+    type Error = Error;
+
+    fn try_from(message: KeyGenMessage2) -> Result<keygen::KeyGenMessage2, Error> {
+        Ok(keygen::KeyGenMessage2 {
+            sender_index: to_index(message.sender_index)?,
+            receiver_index: to_index(message.receiver_index)?,
+            secret_share: to_scalar(&message.secret_share)?,
+        })
+    }
+}
+
+impl From<&refresh::RefreshBroadcastMessage1> for RefreshBroadcast1 {
+    fn from(message: &refresh::RefreshBroadcastMessage1) -> RefreshBroadcast1 {
+        RefreshBroadcast1 {
+            party_index: message.party_index.into(),
+            vss: message.vss.commitments.iter().map(point_bytes).collect(),
+        }
+    }
+}
+
+impl TryFrom<RefreshBroadcast1> for refresh::RefreshBroadcastMessage1 {
+    type Error = Error;
+
+    fn try_from(message: RefreshBroadcast1) -> Result<refresh::RefreshBroadcastMessage1, Error> {
+        Ok(refresh::RefreshBroadcastMessage1 {
+            party_index: to_index(message.party_index)?,
+            vss: keygen::VerificationVector {
+                commitments: to_points(&message.vss)?,
+            },
+        })
+    }
+}
+
+impl From<&refresh::RefreshMessage2> for RefreshMessage2 {
+    fn from(message: &refresh::RefreshMessage2) -> RefreshMessage2 {
+        RefreshMessage2 {
+            sender_index: message.sender_index.into(),
+            receiver_index: message.receiver_index.into(),
+            secret_share: scalar_bytes(&message.secret_share),
+        }
+    }
+}
+
+impl TryFrom<RefreshMessage2> for refresh::RefreshMessage2 {
+    type Error = Error;
+
+    fn try_from(message: RefreshMessage2) -> Result<refresh::RefreshMessage2, Error> {
+        Ok(refresh::RefreshMessage2 {
+            sender_index: to_index(message.sender_index)?,
+            receiver_index: to_index(message.receiver_index)?,
+            secret_share: to_scalar(&message.secret_share)?,
+        })
+    }
+}
+
+impl From<&frost::SigningCommitment> for FrostCommitment {
+    fn from(commitment: &frost::SigningCommitment) -> FrostCommitment {
+        FrostCommitment {
+            party_index: commitment.party_index.into(),
+            d: point_bytes(&commitment.D),
+            e: point_bytes(&commitment.E),
+        }
+    }
+}
+
+impl TryFrom<FrostCommitment> for frost::SigningCommitment {
+    type Error = Error;
+
+    fn try_from(commitment: FrostCommitment) -> Result<frost::SigningCommitment, Error> {
+        Ok(frost::SigningCommitment {
+            party_index: to_index(commitment.party_index)?,
+            D: to_point(&commitment.d)?,
+            E: to_point(&commitment.e)?,
+        })
+    }
+}
+
+impl From<&frost::PartialSignature> for FrostPartialSignature {
+    fn from(signature: &frost::PartialSignature) -> FrostPartialSignature {
+        FrostPartialSignature {
+            party_index: signature.party_index.into(),
+            z: scalar_bytes(&signature.z),
+        }
+    }
+}
+
+impl TryFrom<FrostPartialSignature> for frost::PartialSignature {
+    type Error = Error;
+
+    fn try_from(signature: FrostPartialSignature) -> Result<frost::PartialSignature, Error> {
+        Ok(frost::PartialSignature {
+            party_index: to_index(signature.party_index)?,
+            z: to_scalar(&signature.z)?,
+        })
+    }
+}
+
+impl From<&wire::Message> for envelope::Message {
+    fn from(message: &wire::Message) -> envelope::Message {
+        use self::envelope::Message::*;
+
+        match message {
+            wire::Message::AggSigFirst(message) => AggSigFirst(message.into()),
+            wire::Message::AggSigSecond(message) => AggSigSecond(message.into()),
+            wire::Message::AggSigPartialSignature(message) => {
+                AggSigPartialSignature(message.into())
+            }
+            wire::Message::Musig2Nonces(message) => Musig2Nonces(message.into()),
+            wire::Message::Musig2PartialSignature(message) => {
+                Musig2PartialSignature(message.into())
+            }
+            wire::Message::KeyGenBroadcast1(message) => KeyGenBroadcast1(message.into()),
+            wire::Message::KeyGenMessage2(message) => KeyGenMessage2(message.into()),
+            wire::Message::RefreshBroadcast1(message) => RefreshBroadcast1(message.into()),
+            wire::Message::RefreshMessage2(message) => RefreshMessage2(message.into()),
+            wire::Message::FrostCommitment(message) => FrostCommitment(message.into()),
+            wire::Message::FrostPartialSignature(message) => FrostPartialSignature(message.into()),
+        }
+    }
+}
+
+impl TryFrom<envelope::Message> for wire::Message {
+    type Error = Error;
+
+    fn try_from(message: envelope::Message) -> Result<wire::Message, Error> {
+        use self::envelope::Message::*;
+
+        Ok(match message {
+            AggSigFirst(message) => wire::Message::AggSigFirst(message.try_into()?),
+            AggSigSecond(message) => wire::Message::AggSigSecond(message.try_into()?),
+            AggSigPartialSignature(message) => {
+                wire::Message::AggSigPartialSignature(message.try_into()?)
+            }
+            Musig2Nonces(message) => wire::Message::Musig2Nonces(message.try_into()?),
+            Musig2PartialSignature(message) => {
+                wire::Message::Musig2PartialSignature(message.try_into()?)
+            }
+            KeyGenBroadcast1(message) => wire::Message::KeyGenBroadcast1(message.try_into()?),
+            KeyGenMessage2(message) => wire::Message::KeyGenMessage2(message.try_into()?),
+            RefreshBroadcast1(message) => wire::Message::RefreshBroadcast1(message.try_into()?),
+            RefreshMessage2(message) => wire::Message::RefreshMessage2(message.try_into()?),
+            FrostCommitment(message) => wire::Message::FrostCommitment(message.try_into()?),
+            FrostPartialSignature(message) => {
+                wire::Message::FrostPartialSignature(message.try_into()?)
+            }
+        })
+    }
+}
+
+impl From<&wire::Envelope> for Envelope {
+    fn from(envelope: &wire::Envelope) -> Envelope {
+        Envelope {
+            version: envelope.version().into(),
+            message: Some((&envelope.message).into()),
+        }
+    }
+}
+
+impl TryFrom<Envelope> for wire::Envelope {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<wire::Envelope, Error> {
+        if envelope.version != u32::from(WIRE_VERSION) {
+            return Err(InvalidMessage);
+        }
+        let message = envelope.message.ok_or(InvalidMessage)?;
+        Ok(wire::Envelope::new(message.try_into()?))
+    }
+}
+
+/// The protobuf encoding of `envelope`.
+pub fn encode_envelope(envelope: &wire::Envelope) -> Vec<u8> {
+    Envelope::from(envelope).encode_to_vec()
+}
+
+/// Decodes and converts a protobuf encoded `Envelope`.
+pub fn decode_envelope(bytes: &[u8]) -> Result<wire::Envelope, Error> {
+    Envelope::decode(bytes)?.try_into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocols::keygen::{Keys, Parameters};
+    use protocols::refresh::RefreshKeys;
+    use protocols::tests::deterministic_fast_rand;
+    use protocols::ExpandedKeyPair;
+    use rand::Rng;
+
+    fn messages() -> Vec<wire::Message> {
+        let mut rng = deterministic_fast_rand("test_protobuf_messages", None);
+        let keypair = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let (_, first_msg, second_msg) =
+            aggsig::create_ephemeral_key_and_commit_rng(&keypair, b"protobuf", &mut rng).unwrap();
+        let s = Scalar::from_bigint(&BigInt::from_bytes(&rng.gen::<[u8; 32]>()));
+        let signature = protocols::Signature {
+            R: Point::generator() * &s,
+            s: s.clone(),
+        };
+        let (_, nonces) = musig2::generate_partial_nonces_rng(&keypair, None, &mut rng);
+        let musig2_signature = musig2::PartialSignature {
+            R: nonces.R[0].clone(),
+            my_partial_s: s.clone(),
+        };
+
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let keys: Vec<_> = (1..=3)
+            .map(|i| Keys::phase1_create_rng(&params, i, &mut rng).unwrap())
+            .collect();
+        let broadcasts: Vec<_> = keys
+            .iter()
+            .map(|keys| keys.phase1_broadcast_rng(b"protobuf", &mut rng))
+            .collect();
+        let shares = keys[0]
+            .phase1_verify_proofs_phase2_distribute(&params, b"protobuf", &broadcasts)
+            .unwrap();
+        let refresh_keys: Vec<_> = (1..=3)
+            .map(|i| RefreshKeys::phase1_create_rng(&params, i, &mut rng))
+            .collect();
+        let refresh_broadcasts: Vec<_> = refresh_keys
+            .iter()
+            .map(RefreshKeys::phase1_broadcast)
+            .collect();
+        let refresh_shares = refresh_keys[0]
+            .phase1_verify_phase2_distribute(&params, &refresh_broadcasts)
+            .unwrap();
+        let shared_keys = keygen::deal_rng(&params, &keypair, &mut rng).unwrap();
+        let (_, commitment) = frost::preprocess_rng(&shared_keys[1], &mut rng);
+
+        vec![
+            wire::Message::AggSigFirst(first_msg),
+            wire::Message::AggSigSecond(second_msg),
+            wire::Message::AggSigPartialSignature(signature),
+            wire::Message::Musig2Nonces(nonces),
+            wire::Message::Musig2PartialSignature(musig2_signature),
+            wire::Message::KeyGenBroadcast1(broadcasts[2].clone()),
+            wire::Message::KeyGenMessage2(shares[1].clone()),
+            wire::Message::RefreshBroadcast1(refresh_broadcasts[1].clone()),
+            wire::Message::RefreshMessage2(refresh_shares[2].clone()),
+            wire::Message::FrostCommitment(commitment),
+            wire::Message::FrostPartialSignature(frost::PartialSignature {
+                party_index: 2,
+                z: s,
+            }),
+        ]
+    }
+
+    #[test]
+    fn test_envelope_roundtrip() {
+        for message in messages() {
+            let envelope = wire::Envelope::new(message);
+            let bytes = encode_envelope(&envelope);
+            assert_eq!(decode_envelope(&bytes).unwrap(), envelope);
+            // the encoding is the same as from a fresh decode
+            assert_eq!(Envelope::decode(&bytes).unwrap().encode_to_vec(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_encoding() {
+        let signature = FrostPartialSignature::from(&frost::PartialSignature {
+            party_index: 3,
+            z: Scalar::from(1),
+        });
+        let mut expected = alloc::vec![0x08, 0x03, 0x12, 0x20, 0x01];
+        expected.extend_from_slice(&[0; 31]);
+        assert_eq!(signature.encode_to_vec(), expected);
+
+        let envelope = Envelope {
+            version: 2,
+            message: Some(envelope::Message::FrostPartialSignature(signature)),
+        };
+        // field 12, 36 bytes long
+        let mut expected_envelope = alloc::vec![0x08, 0x02, 0x62, 0x24];
+        expected_envelope.extend_from_slice(&expected);
+        assert_eq!(envelope.encode_to_vec(), expected_envelope);
+
+        // defaults aren't written
+        assert!(FrostPartialSignature::default().encode_to_vec().is_empty());
+        assert_eq!(
+            FrostPartialSignature::decode(&[]).unwrap(),
+            FrostPartialSignature::default()
+        );
+    }
+
+    #[test]
+    fn test_decode_unknown_and_repeated_fields() {
+        let signature = FrostPartialSignature {
+            party_index: 300,
+            z: alloc::vec![1, 2, 3],
+        };
+        let mut bytes = signature.encode_to_vec();
+        assert_eq!(&bytes[..3], &[0x08, 0xac, 0x02]);
+        // a varint, fixed64, length delimited and fixed32 field the decoder doesn't know
+        bytes.extend_from_slice(&[0x78, 0x96, 0x01]);
+        bytes.extend_from_slice(&[0x81, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&[0x8a, 0x01, 0x02, 0xaa, 0xbb]);
+        bytes.extend_from_slice(&[0x95, 0x01, 0, 0, 0, 0]);
+        assert_eq!(FrostPartialSignature::decode(&bytes).unwrap(), signature);
+
+        // the last value of a scalar field wins
+        bytes.extend_from_slice(&[0x08, 0x07]);
+        assert_eq!(
+            FrostPartialSignature::decode(&bytes).unwrap().party_index,
+            7
+        );
+
+        let nonces = Musig2Nonces {
+            r: alloc::vec![alloc::vec![], alloc::vec![5]],
+        };
+        assert_eq!(
+            Musig2Nonces::decode(&nonces.encode_to_vec()).unwrap(),
+            nonces
+        );
+    }
+
+    #[test]
+    fn test_decode_malformed() {
+        let bytes = encode_envelope(&wire::Envelope::new(messages().remove(6)));
+        // cut inside a field, the first 2 bytes are the version alone
+        for len in (1..bytes.len()).filter(|len| *len != 2) {
+            assert_eq!(Envelope::decode(&bytes[..len]), Err(InvalidMessage));
+        }
+        // field 0, a group, a bytes field as varint and an overlong varint
+        assert_eq!(Envelope::decode(&[0x00, 0x01]), Err(InvalidMessage));
+        assert_eq!(Envelope::decode(&[0x0b]), Err(InvalidMessage));
+        assert_eq!(Envelope::decode(&[0x10, 0x01]), Err(InvalidMessage));
+        assert_eq!(
+            Envelope::decode(&[0x08, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]),
+            Err(InvalidMessage)
+        );
+        assert_eq!(
+            Envelope::decode(&[
+                0x08, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01
+            ]),
+            Err(InvalidMessage)
+        );
+    }
+
+    #[test]
+    fn test_convert_invalid() {
+        let mut envelope = Envelope::from(&wire::Envelope::new(messages().remove(9)));
+        assert!(wire::Envelope::try_from(envelope.clone()).is_ok());
+
+        envelope.version = 1;
+        assert_eq!(
+            wire::Envelope::try_from(envelope.clone()),
+            Err(InvalidMessage)
+        );
+        assert_eq!(
+            wire::Envelope::try_from(Envelope {
+                version: u32::from(WIRE_VERSION),
+                message: None
+            }),
+            Err(InvalidMessage)
+        );
+
+        let commitment = match envelope.message.clone() {
+            Some(envelope::Message::FrostCommitment(commitment)) => commitment,
+            _ => unreachable!(),
+        };
+        let mut bad = commitment.clone();
+        bad.d = alloc::vec![0xff; 32];
+        assert_eq!(frost::SigningCommitment::try_from(bad), Err(InvalidPoint));
+        let mut bad = commitment.clone();
+        bad.e.pop();
+        assert_eq!(frost::SigningCommitment::try_from(bad), Err(InvalidMessage));
+        let mut bad = commitment;
+        bad.party_index = u32::from(u16::MAX) + 1;
+        assert_eq!(frost::SigningCommitment::try_from(bad), Err(InvalidMessage));
+
+        // s has to be below the group order
+        let signature = Signature {
+            r: point_bytes(&Point::generator().to_point()),
+            s: alloc::vec![0xff; 32],
+        };
+        assert_eq!(
+            protocols::Signature::try_from(signature),
+            Err(InvalidMessage)
+        );
+        assert_eq!(
+            musig2::PublicPartialNonces::try_from(Musig2Nonces {
+                r: alloc::vec![point_bytes(&Point::generator().to_point())]
+            }),
+            Err(InvalidMessage)
+        );
+        assert_eq!(
+            keygen::KeyGenBroadcastMessage1::try_from(KeyGenBroadcast1::default()),
+            Err(InvalidMessage)
+        );
+        assert_eq!(
+            aggsig::SignFirstMsg::try_from(SignFirstMsg {
+                commitment: alloc::vec![1; 65]
+            }),
+            Err(InvalidMessage)
+        );
+    }
+}
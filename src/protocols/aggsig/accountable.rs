@@ -0,0 +1,94 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Aggregated signatures that record which keys of a known roster signed.
+//!
+//! The signers aggregate only their own keys (in roster order), the verifier rebuilds that
+//! aggregation from the roster and the bitmap, so a valid `AccountableSignature` shows exactly
+//! which keys authorized the message.
+
+use alloc::vec::Vec;
+
+use curv::elliptic::curves::{Ed25519, Point};
+use protocols::aggsig::AggregatedKey;
+use protocols::Signature;
+use Error::{self, InvalidKey, InvalidSig, RoundMismatch};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AccountableSignature {
+    pub signature: Signature,
+    /// Bit `i % 8` of byte `i / 8` is set if the i-th key of the roster signed.
+    pub signers: Vec<u8>,
+    pub apk: Point<Ed25519>,
+}
+
+impl AccountableSignature {
+    /// `signature` is the aggregated signature of the roster keys at the (0-based) indices
+    /// `signers`, which aggregated the keys `signer_keys` returns.
+    pub fn new(
+        signature: Signature,
+        roster: &[Point<Ed25519>],
+        signers: &[usize],
+    ) -> Result<AccountableSignature, Error> {
+        let mut bitmap = alloc::vec![0u8; roster.len().div_ceil(8)];
+        for &i in signers {
+            if i >= roster.len() || bitmap[i / 8] & (1 << (i % 8)) != 0 {
+                return Err(RoundMismatch);
+            }
+            bitmap[i / 8] |= 1 << (i % 8);
+        }
+        let accountable = AccountableSignature {
+            signature,
+            signers: bitmap,
+            apk: Point::zero(),
+        };
+        let apk = AggregatedKey::new(&accountable.signer_keys(roster)?).apk;
+        Ok(AccountableSignature { apk, ..accountable })
+    }
+
+    /// The keys of the roster that signed, in roster order.
+    /// Fails with `RoundMismatch` if the bitmap doesn't fit the roster or nobody signed.
+    pub fn signer_keys(&self, roster: &[Point<Ed25519>]) -> Result<Vec<Point<Ed25519>>, Error> {
+        let in_bitmap = |i: usize| self.signers[i / 8] & (1 << (i % 8)) != 0;
+        if self.signers.len() != roster.len().div_ceil(8)
+            || (roster.len()..self.signers.len() * 8).any(in_bitmap)
+        {
+            return Err(RoundMismatch);
+        }
+        let keys: Vec<_> = roster
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| in_bitmap(i))
+            .map(|(_, pk)| pk.clone())
+            .collect();
+        if keys.is_empty() {
+            return Err(RoundMismatch);
+        }
+        Ok(keys)
+    }
+
+    /// Recomputes the aggregated key of the signers from `roster` and checks the signature
+    /// against it, fails with `InvalidKey` if `apk` isn't that aggregated key.
+    pub fn verify(&self, message: &[u8], roster: &[Point<Ed25519>]) -> Result<(), Error> {
+        if AggregatedKey::new(&self.signer_keys(roster)?).apk != self.apk {
+            return Err(InvalidKey);
+        }
+        self.signature
+            .verify(message, &self.apk)
+            .map_err(|_| InvalidSig)
+    }
+}
@@ -0,0 +1,256 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Borrowed views of the round messages, for coordinators that route and check the messages of
+//! many sessions.
+//!
+//! `SignFirstMsgRef` and `SignSecondMsgRef` only check the length of a received buffer and point
+//! into it, the `SIGN_FIRST_MSG_SIZE` and `SIGN_SECOND_MSG_SIZE` byte encodings of `to_bytes`.
+//! `verify_commitment` and its variants hash the bytes as they are, so a commitment is opened
+//! without allocating a `BigInt` or decoding `R` into a curv point. `to_owned` parses a view
+//! into the owned message once it's needed for signing.
+//!
+//! With serde they are (de)serialized as bytes, which formats such as bincode borrow from the
+//! input buffer. Formats that can't borrow bytes, e.g. JSON, fail to deserialize them and need
+//! the owned messages.
+
+use core::convert::TryFrom;
+use core::fmt;
+
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use curve25519_dalek::traits::IsIdentity;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::{Serialize, Serializer};
+use sha2::digest::{consts::U64, Digest};
+use sha2::Sha512;
+
+use super::{
+    check_commitment, domain_hasher_with_digest, DomainConfig, SignFirstMsg, SignSecondMsg,
+    SIGN_FIRST_MSG_SIZE, SIGN_SECOND_MSG_SIZE,
+};
+use Error::{self, InvalidCom, InvalidMessage, InvalidPoint};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SignFirstMsgRef<'a> {
+    bytes: &'a [u8; SIGN_FIRST_MSG_SIZE],
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SignSecondMsgRef<'a> {
+    bytes: &'a [u8; SIGN_SECOND_MSG_SIZE],
+}
+
+impl<'a> SignFirstMsgRef<'a> {
+    /// Fails with `InvalidMessage` unless `bytes` is `SIGN_FIRST_MSG_SIZE` long.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<SignFirstMsgRef<'a>, Error> {
+        Ok(SignFirstMsgRef {
+            bytes: <&[u8; SIGN_FIRST_MSG_SIZE]>::try_from(bytes).map_err(|_| InvalidMessage)?,
+        })
+    }
+
+    /// The commitment as 64 big endian bytes.
+    pub fn commitment(&self) -> &'a [u8; SIGN_FIRST_MSG_SIZE] {
+        self.bytes
+    }
+
+    pub fn to_owned(&self) -> SignFirstMsg {
+        SignFirstMsg::from_bytes(self.bytes)
+    }
+}
+
+impl<'a> SignSecondMsgRef<'a> {
+    /// Fails with `InvalidMessage` unless `bytes` is `SIGN_SECOND_MSG_SIZE` long, `R` is only
+    /// checked by `to_owned` and the commitment checks.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<SignSecondMsgRef<'a>, Error> {
+        Ok(SignSecondMsgRef {
+            bytes: <&[u8; SIGN_SECOND_MSG_SIZE]>::try_from(bytes).map_err(|_| InvalidMessage)?,
+        })
+    }
+
+    /// The compressed encoding of `R`.
+    pub fn R(&self) -> &'a [u8; 32] {
+        <&[u8; 32]>::try_from(&self.bytes[..32]).expect("32 bytes")
+    }
+
+    /// The blind factor as 32 big endian bytes.
+    pub fn blind_factor(&self) -> &'a [u8; 32] {
+        <&[u8; 32]>::try_from(&self.bytes[32..]).expect("32 bytes")
+    }
+
+    /// Fails with `InvalidPoint` if `R` isn't a canonical point encoding.
+    pub fn to_owned(&self) -> Result<SignSecondMsg, Error> {
+        SignSecondMsg::from_bytes(self.bytes)
+    }
+}
+
+impl<'a> From<&'a [u8; SIGN_FIRST_MSG_SIZE]> for SignFirstMsgRef<'a> {
+    fn from(bytes: &'a [u8; SIGN_FIRST_MSG_SIZE]) -> SignFirstMsgRef<'a> {
+        SignFirstMsgRef { bytes }
+    }
+}
+
+impl<'a> From<&'a [u8; SIGN_SECOND_MSG_SIZE]> for SignSecondMsgRef<'a> {
+    fn from(bytes: &'a [u8; SIGN_SECOND_MSG_SIZE]) -> SignSecondMsgRef<'a> {
+        SignSecondMsgRef { bytes }
+    }
+}
+
+/// `aggsig::verify_commitment` of the borrowed messages.
+/// Fails with `InvalidPoint` if `R` isn't the canonical encoding of a point other than the
+/// identity. Unlike `SignSecondMsg::from_bytes` it doesn't check that `R` is in the prime order
+/// subgroup, that is left to `to_owned`.
+pub fn verify_commitment(
+    sign_first_msg: SignFirstMsgRef,
+    sign_second_msg: SignSecondMsgRef,
+) -> Result<(), Error> {
+    check_commitment_ref::<Sha512>(
+        sign_first_msg,
+        sign_second_msg,
+        None,
+        &DomainConfig::default(),
+    )
+}
+
+/// `aggsig::verify_commitment_with_sid` of the borrowed messages.
+pub fn verify_commitment_with_sid(
+    sign_first_msg: SignFirstMsgRef,
+    sign_second_msg: SignSecondMsgRef,
+    sid: &[u8],
+) -> Result<(), Error> {
+    check_commitment_ref::<Sha512>(
+        sign_first_msg,
+        sign_second_msg,
+        Some(sid),
+        &DomainConfig::default(),
+    )
+}
+
+/// `aggsig::verify_commitment_with_domain` of the borrowed messages. A `legacy_commitment`
+/// domain commits to the y coordinate of `R`, so the messages are parsed with `to_owned`.
+pub fn verify_commitment_with_domain(
+    sign_first_msg: SignFirstMsgRef,
+    sign_second_msg: SignSecondMsgRef,
+    domain: &DomainConfig,
+) -> Result<(), Error> {
+    if domain.legacy_commitment {
+        return check_commitment(
+            &sign_first_msg.to_owned(),
+            &sign_second_msg.to_owned()?,
+            None,
+            domain,
+        );
+    }
+    check_commitment_ref::<Sha512>(sign_first_msg, sign_second_msg, None, domain)
+}
+
+/// `aggsig::verify_commitment_with_digest` of the borrowed messages.
+pub fn verify_commitment_with_digest<D: Digest<OutputSize = U64> + Clone>(
+    sign_first_msg: SignFirstMsgRef,
+    sign_second_msg: SignSecondMsgRef,
+) -> Result<(), Error> {
+    check_commitment_ref::<D>(
+        sign_first_msg,
+        sign_second_msg,
+        None,
+        &DomainConfig::default(),
+    )
+}
+
+// `check_commitment_with_digest` on the encodings. The commitment hashes the minimal big endian
+// encodings of the committed value and the blind factor, i.e. without their leading zeros, and
+// `SignFirstMsg::to_bytes` pads the digest back to its full 64 bytes.
+fn check_commitment_ref<D: Digest<OutputSize = U64> + Clone>(
+    sign_first_msg: SignFirstMsgRef,
+    sign_second_msg: SignSecondMsgRef,
+    sid: Option<&[u8]>,
+    domain: &DomainConfig,
+) -> Result<(), Error> {
+    let R = sign_second_msg.R();
+    match CompressedEdwardsY(*R).decompress() {
+        Some(point) if point.compress().as_bytes() == R && !point.is_identity() => {}
+        _ => return Err(InvalidPoint),
+    }
+    let tagged;
+    let value = match sid {
+        None if domain.commitment.is_empty() => strip_zeros(R),
+        sid => {
+            let sid = sid.unwrap_or_default();
+            tagged = domain_hasher_with_digest::<D>(&domain.commitment, 7)
+                .chain((sid.len() as u64).to_be_bytes())
+                .chain(sid)
+                .chain(strip_zeros(R))
+                .finalize();
+            strip_zeros(&tagged)
+        }
+    };
+    let commitment = D::new()
+        .chain(value)
+        .chain(strip_zeros(sign_second_msg.blind_factor()))
+        .finalize();
+    if commitment.as_slice() == &sign_first_msg.commitment()[..] {
+        Ok(())
+    } else {
+        Err(InvalidCom)
+    }
+}
+
+fn strip_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+impl<'a> Serialize for SignFirstMsgRef<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.bytes)
+    }
+}
+
+impl<'a> Serialize for SignSecondMsgRef<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.bytes)
+    }
+}
+
+struct BorrowedBytesVisitor<const N: usize>;
+
+impl<'de, const N: usize> Visitor<'de> for BorrowedBytesVisitor<N> {
+    type Value = &'de [u8; N];
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} bytes borrowed from the input", N)
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, bytes: &'de [u8]) -> Result<&'de [u8; N], E> {
+        <&[u8; N]>::try_from(bytes).map_err(|_| E::invalid_length(bytes.len(), &self))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for SignFirstMsgRef<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<SignFirstMsgRef<'a>, D::Error> {
+        let bytes = deserializer.deserialize_bytes(BorrowedBytesVisitor::<SIGN_FIRST_MSG_SIZE>)?;
+        Ok(SignFirstMsgRef { bytes })
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for SignSecondMsgRef<'a> {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<SignSecondMsgRef<'a>, D::Error> {
+        let bytes = deserializer.deserialize_bytes(BorrowedBytesVisitor::<SIGN_SECOND_MSG_SIZE>)?;
+        Ok(SignSecondMsgRef { bytes })
+    }
+}
@@ -0,0 +1,95 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Non-hardened (public) derivation of child keys from an aggregated public key.
+//!
+//! This follows the public derivation of BIP32-Ed25519: the child key is
+//! `apk + 8 * Z_L * G`, with `Z_L` the first 28 bytes of
+//! `HMAC-SHA512(chain_code, 0x02 || apk || index)`. Everyone who knows the aggregated key and
+//! the chain code derives the same child, so there is no new key generation per address.
+//! The secret key of the child is the aggregated secret plus `tweak`, so the parties sign with
+//! `ChildKey::apk` in place of the aggregated key and `tweak_signature` adds the rest.
+//!
+//! The tweak is public, anyone who knows the chain code can link the children to the parent key.
+
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use protocols::{hmac_sha512, Signature, HARDENED};
+use Error::{self, InvalidDerivationIndex, InvalidKey};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ChildKey {
+    /// The public key the child signatures verify against.
+    pub apk: Point<Ed25519>,
+    /// Sum of the tweaks from the root to this child.
+    pub tweak: Scalar<Ed25519>,
+    pub chain_code: [u8; 32],
+}
+
+impl ChildKey {
+    /// The root of the derivation tree below the aggregated key `apk`, all parties have to use
+    /// the same `chain_code` (e.g. agreed on together with the public keys).
+    pub fn root(apk: &Point<Ed25519>, chain_code: [u8; 32]) -> ChildKey {
+        ChildKey {
+            apk: apk.clone(),
+            tweak: Scalar::zero(),
+            chain_code,
+        }
+    }
+
+    /// Derives the child at `index`, which has to be below `HARDENED`.
+    pub fn derive(&self, index: u32) -> Result<ChildKey, Error> {
+        if index >= HARDENED {
+            return Err(InvalidDerivationIndex);
+        }
+        let A = self.apk.to_bytes(true);
+        let index = index.to_le_bytes();
+        let z = hmac_sha512(&self.chain_code, &[&[2], &A, &index]);
+        let c = hmac_sha512(&self.chain_code, &[&[3], &A, &index]);
+
+        let mut z_l = [0u8; 32];
+        z_l[..28].copy_from_slice(&z[..28]);
+        let t = Scalar::from(8)
+            * Scalar::from_bytes(&z_l).expect("z_l is the right length, so can't fail");
+        let apk = &self.apk + Point::generator() * &t;
+        if apk.is_zero() {
+            return Err(InvalidKey);
+        }
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&c[32..]);
+        Ok(ChildKey {
+            apk,
+            tweak: &self.tweak + t,
+            chain_code,
+        })
+    }
+
+    /// Derives every index of `path` in turn.
+    pub fn derive_path(&self, path: &[u32]) -> Result<ChildKey, Error> {
+        path.iter()
+            .try_fold(self.clone(), |key, &index| key.derive(index))
+    }
+
+    /// Turns the aggregate of partial signatures made with `self.apk` as the aggregated key into
+    /// a signature of `message` under `self.apk`.
+    pub fn tweak_signature(&self, signature: &Signature, message: &[u8]) -> Signature {
+        let k = Signature::k(&signature.R, &self.apk, message);
+        Signature {
+            R: signature.R.clone(),
+            s: &signature.s + k * &self.tweak,
+        }
+    }
+}
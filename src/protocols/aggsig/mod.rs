@@ -20,6 +20,10 @@
 //! See https://tools.ietf.org/html/rfc8032
 
 use super::ExpandedKeyPair;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Seek, SeekFrom, Write};
 
 pub use curv::arithmetic::traits::Samplable;
 use curv::cryptographic_primitives::commitments::hash_commitment::HashCommitment;
@@ -29,46 +33,601 @@ use curv::BigInt;
 
 pub use curv::arithmetic::traits::Converter;
 use curv::cryptographic_primitives::commitments::traits::Commitment;
-use protocols::{ProofError, Signature};
-use rand::{thread_rng, Rng};
-use sha2::{digest::Digest, Sha512};
+#[cfg(not(feature = "parallel"))]
+use protocols::multiscalar_mul;
+#[cfg(feature = "parallel")]
+use protocols::par_multiscalar_mul;
+use protocols::{
+    base_mul, check_point, decode_point, dom2, verification_equation, CryptoRngCore, Signature,
+};
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::{CryptoRng, Rng, RngCore};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use sha2::digest::{consts::U64, Digest, Output};
+use sha2::Sha512;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+use Error::{
+    self, InvalidCom, InvalidCommitment, InvalidContext, InvalidKey, InvalidPartialSignature,
+    InvalidPoint, InvalidSig, RoundMismatch,
+};
+
+pub mod accountable;
+pub mod borrowed;
+pub mod derivation;
+pub mod nonce_pool;
+pub mod pop;
+pub mod prepared;
+pub mod session;
+pub mod signer;
+#[cfg(feature = "std")]
+pub mod state_machine;
+#[cfg(feature = "transcript")]
+pub mod transcript;
+#[cfg(feature = "std")]
+pub mod vectors;
+pub use self::accountable::AccountableSignature;
+pub use self::borrowed::{SignFirstMsgRef, SignSecondMsgRef};
+pub use self::derivation::ChildKey;
+pub use self::nonce_pool::{partial_sign_with_pool, MemoryNonceStore, NoncePool, NonceStore};
+pub use self::pop::ProofOfPossession;
+pub use self::prepared::PreparedApk;
+pub use self::session::{AwaitingCommitments, AwaitingReveals, ReadyToSign};
+#[cfg(feature = "std")]
+pub use self::signer::MemorySigner;
+pub use self::signer::{
+    create_ephemeral_key_and_commit_with_signer, partial_sign_with_signer, NonceHandle,
+    PartialSigner,
+};
+#[cfg(feature = "std")]
+pub use self::state_machine::{AggSigMessage, AggSigStateMachine};
+
+/// Application chosen domain separation tags for the coefficient hashes of the key aggregation,
+/// the nonce derivation and the round 1 commitments. Nothing made under one `DomainConfig`
+/// (aggregated keys, nonces, commitments) is valid under another, so independent deployments
+/// can't cross-verify each other's artifacts.
+/// The default has empty tags, which are the hashes of the original protocol, but commits to the
+/// compressed encoding of `R` (see `legacy_commitment`).
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct DomainConfig {
+    pub key_aggregation: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub commitment: Vec<u8>,
+    /// Commit to the y coordinate of `R` instead of its compressed encoding, as earlier versions
+    /// of this crate did, to sign together with them. The y coordinate leaves the sign of x open, so a
+    /// party can still choose between `R` and its negation after seeing the other commitments.
+    #[serde(default)]
+    pub legacy_commitment: bool,
+    /// Hash the coefficient of `pk` as `H(1 || L || pk)`, with `L` the hash of all the public
+    /// keys, instead of `H(1 || pk || pk_1 || ... || pk_n)`, so the key list is hashed once
+    /// rather than for every key, e.g. for committees of hundreds of keys. The same keys give
+    /// another aggregated key than without it, so it's only for new key sets.
+    #[serde(default)]
+    pub hashed_key_list: bool,
+}
+
+impl DomainConfig {
+    /// Uses `application` (e.g. `b"com.example.wallet/v1"`) as all three tags.
+    pub fn new(application: &[u8]) -> DomainConfig {
+        DomainConfig {
+            key_aggregation: application.to_vec(),
+            nonce: application.to_vec(),
+            commitment: application.to_vec(),
+            legacy_commitment: false,
+            hashed_key_list: false,
+        }
+    }
+
+    /// The default tags with the commitments of earlier versions, see `legacy_commitment`.
+    pub fn legacy() -> DomainConfig {
+        DomainConfig {
+            legacy_commitment: true,
+            ..DomainConfig::default()
+        }
+    }
+}
+
+// a hasher for the domain separated hash with the prefix byte `prefix`, an empty `tag` is the
+// untagged hash `H(prefix || ...)`, otherwise it's `H(13 || len(tag) || tag || prefix || ...)`
+fn domain_hasher(tag: &[u8], prefix: u8) -> Sha512 {
+    domain_hasher_with_digest(tag, prefix)
+}
+
+fn domain_hasher_with_digest<D: Digest>(tag: &[u8], prefix: u8) -> D {
+    if tag.is_empty() {
+        return D::new().chain([prefix]);
+    }
+    D::new()
+        .chain([13])
+        .chain((tag.len() as u64).to_be_bytes())
+        .chain(tag)
+        .chain([prefix])
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct KeyAgg {
     pub apk: Point<Ed25519>,
     pub hash: Scalar<Ed25519>,
+    /// The number of keys aggregated into `apk`, zero for a `KeyAgg` serialized before it was
+    /// recorded.
+    #[serde(default)]
+    pub party_count: usize,
 }
 
 impl KeyAgg {
+    /// Doesn't check the keys, see `key_aggregation_n_checked` for keys of untrusted parties.
+    /// Every coefficient hashes all the keys, so this is quadratic in the number of keys, which
+    /// can't change without changing every aggregated key; committees of hundreds of keys use
+    /// `key_aggregation_n_hashed_key_list`.
     pub fn key_aggregation_n(pks: &[Point<Ed25519>], party_index: usize) -> KeyAgg {
-        let mut my_hash = Scalar::zero();
-        let mut sum = Point::zero();
-        pks.iter().enumerate().for_each(|(index, pk)| {
-            let mut hasher = Sha512::new().chain(&[1]).chain(&*pk.to_bytes(true));
-            for pk in pks {
-                hasher.update(&*pk.to_bytes(true));
-            }
-            let hash = hasher.result_scalar();
-            let a_i = pk * &hash;
-            if index == party_index {
-                my_hash = hash;
-            }
-            sum = &sum + a_i
+        AggregatedKey::new(pks).key_agg(party_index)
+    }
+
+    /// `key_aggregation_n` failing with `InvalidKey` if a key is the identity or there is no
+    /// party with `party_index`.
+    pub fn key_aggregation_n_checked(
+        pks: &[Point<Ed25519>],
+        party_index: usize,
+    ) -> Result<KeyAgg, Error> {
+        if party_index >= pks.len() {
+            return Err(InvalidKey);
+        }
+        Ok(AggregatedKey::new_checked(pks)?.key_agg(party_index))
+    }
+
+    /// `key_aggregation_n` with the coefficients hashed under `domain.key_aggregation`.
+    pub fn key_aggregation_n_with_domain(
+        pks: &[Point<Ed25519>],
+        party_index: usize,
+        domain: &DomainConfig,
+    ) -> KeyAgg {
+        AggregatedKey::new_with_domain(pks, domain).key_agg(party_index)
+    }
+
+    /// `key_aggregation_n` hashing the key list once, see `DomainConfig::hashed_key_list`.
+    pub fn key_aggregation_n_hashed_key_list(pks: &[Point<Ed25519>], party_index: usize) -> KeyAgg {
+        AggregatedKey::new_hashed_key_list(pks).key_agg(party_index)
+    }
+
+    /// `key_aggregation_n` with the weights of `AggregatedKey::new_weighted`.
+    pub fn key_aggregation_n_weighted(
+        pks: &[Point<Ed25519>],
+        weights: &[u64],
+        party_index: usize,
+    ) -> Result<KeyAgg, Error> {
+        Ok(AggregatedKey::new_weighted(pks, weights)?.key_agg(party_index))
+    }
+
+    /// Tweaks the aggregated key to `apk + tweak * G`, every one of the `party_count` signers
+    /// has to tweak with the same `tweak` and sign with `partial_sign_tweaked`.
+    /// Fails with `RoundMismatch` if `party_count` is zero.
+    pub fn tweak(&self, tweak: &Scalar<Ed25519>) -> Result<TweakedKeyAgg, Error> {
+        let n = u64::try_from(self.party_count).map_err(|_| RoundMismatch)?;
+        let n_inv = Scalar::from(n).invert().ok_or(RoundMismatch)?;
+        let apk = &self.apk + Point::generator() * tweak;
+        if apk.is_zero() {
+            return Err(InvalidKey);
+        }
+        Ok(TweakedKeyAgg {
+            apk,
+            hash: self.hash.clone(),
+            adjustment: tweak * n_inv,
+            party_count: self.party_count,
+        })
+    }
+}
+
+/// A `KeyAgg` with the aggregated key tweaked by `KeyAgg::tweak`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TweakedKeyAgg {
+    pub apk: Point<Ed25519>,
+    pub hash: Scalar<Ed25519>,
+    /// The share of the tweak each party adds to its partial signature, `tweak / party_count`.
+    pub adjustment: Scalar<Ed25519>,
+    #[serde(default)]
+    pub party_count: usize,
+}
+
+/// The result of the key aggregation for all parties at once, so it is computed once and can
+/// be shared.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct AggregatedKey {
+    pub apk: Point<Ed25519>,
+    /// `coefficients[i]` is the `KeyAgg::hash` of the party with the i-th public key.
+    pub coefficients: Vec<Scalar<Ed25519>>,
+    /// SHA-512 of the public keys in order, identifies the key set.
+    pub pks_hash: BigInt,
+    /// SHA-512 of the weights in order, if the key was aggregated with `new_weighted`.
+    #[serde(default)]
+    pub weights_hash: Option<BigInt>,
+}
+
+impl AggregatedKey {
+    /// The coefficient of `pk` is `H(1 || pk || pk_1 || ... || pk_n)`, quadratic in the number
+    /// of keys, for large committees see `new_hashed_key_list`. Doesn't check the keys, see
+    /// `new_checked`.
+    pub fn new(pks: &[Point<Ed25519>]) -> AggregatedKey {
+        Self::aggregate(pks, None, &DomainConfig::default())
+    }
+
+    /// `new` failing with `InvalidKey` if there are no keys or one of them is the identity.
+    pub fn new_checked(pks: &[Point<Ed25519>]) -> Result<AggregatedKey, Error> {
+        if pks.is_empty() || pks.iter().any(|pk| check_point(pk).is_err()) {
+            return Err(InvalidKey);
+        }
+        Ok(Self::new(pks))
+    }
+
+    /// `new` with the coefficients hashed under `domain.key_aggregation`, the same keys give a
+    /// different aggregated key for every tag.
+    pub fn new_with_domain(pks: &[Point<Ed25519>], domain: &DomainConfig) -> AggregatedKey {
+        Self::aggregate(pks, None, domain)
+    }
+
+    /// `new` with the coefficient of `pk` `H(1 || L || pk)`, with `L` the hash of all the public
+    /// keys, so the key list is only hashed once, see `DomainConfig::hashed_key_list`.
+    pub fn new_hashed_key_list(pks: &[Point<Ed25519>]) -> AggregatedKey {
+        let domain = DomainConfig {
+            hashed_key_list: true,
+            ..DomainConfig::default()
+        };
+        Self::aggregate(pks, None, &domain)
+    }
+
+    /// Aggregation where the party with `pks[i]` has the weight `weights[i]`, its coefficient
+    /// is `weights[i] * H(1 || L || W || pk)` with `W` the hash of the weights, the key list is
+    /// always hashed once.
+    /// Fails with `RoundMismatch` if there isn't one weight per key and with `InvalidKey` if a
+    /// weight is zero or a key is the identity.
+    pub fn new_weighted(pks: &[Point<Ed25519>], weights: &[u64]) -> Result<AggregatedKey, Error> {
+        if pks.len() != weights.len() {
+            return Err(RoundMismatch);
+        }
+        if weights.contains(&0) || pks.iter().any(|pk| check_point(pk).is_err()) {
+            return Err(InvalidKey);
+        }
+        Ok(Self::aggregate(
+            pks,
+            Some(weights),
+            &DomainConfig::default(),
+        ))
+    }
+
+    /// Checks that this key was aggregated from `pks` with `weights`, so a party can't be given
+    /// an aggregated key with different weights than it agreed to.
+    pub fn verify_weights(&self, pks: &[Point<Ed25519>], weights: &[u64]) -> Result<(), Error> {
+        if Self::new_weighted(pks, weights)? == *self {
+            Ok(())
+        } else {
+            Err(InvalidKey)
+        }
+    }
+
+    fn aggregate(
+        pks: &[Point<Ed25519>],
+        weights: Option<&[u64]>,
+        domain: &DomainConfig,
+    ) -> AggregatedKey {
+        let pks_hash = hash_public_keys(pks);
+        let mut prefix = domain_hasher(&domain.key_aggregation, 1).chain(pks_hash);
+        let weights_hash = weights.map(|weights| {
+            let weights_hash = weights
+                .iter()
+                .fold(Sha512::new(), |hasher, w| hasher.chain(w.to_le_bytes()))
+                .finalize();
+            prefix.update(weights_hash);
+            BigInt::from_bytes(&weights_hash)
         });
+        let prefix = &prefix;
+        let hashed_key_list = domain.hashed_key_list || weights.is_some();
+        let coefficient = |i: usize| {
+            let hash = if hashed_key_list {
+                prefix
+                    .clone()
+                    .chain(&*pks[i].to_bytes(true))
+                    .result_scalar()
+            } else {
+                pks.iter()
+                    .fold(
+                        domain_hasher(&domain.key_aggregation, 1).chain(&*pks[i].to_bytes(true)),
+                        |hasher, pk| hasher.chain(&*pk.to_bytes(true)),
+                    )
+                    .result_scalar()
+            };
+            match weights {
+                Some(weights) => hash * Scalar::from(weights[i]),
+                None => hash,
+            }
+        };
+        // for committees of hundreds of keys, hash on all cores and share the doublings of
+        // `sum(coefficients[i] * pks[i])` between the keys
+        #[cfg(feature = "parallel")]
+        let coefficients: Vec<_> = (0..pks.len()).into_par_iter().map(coefficient).collect();
+        #[cfg(not(feature = "parallel"))]
+        let coefficients: Vec<_> = (0..pks.len()).map(coefficient).collect();
+        #[cfg(feature = "parallel")]
+        let apk = par_multiscalar_mul(&coefficients, pks);
+        #[cfg(not(feature = "parallel"))]
+        let apk = multiscalar_mul(&coefficients, pks);
+        AggregatedKey {
+            apk,
+            coefficients,
+            pks_hash: BigInt::from_bytes(&pks_hash),
+            weights_hash,
+        }
+    }
 
+    /// The `KeyAgg` of the party with the public key `pks[party_index]`, its hash is zero if
+    /// there is no such party.
+    pub fn key_agg(&self, party_index: usize) -> KeyAgg {
         KeyAgg {
-            apk: sum,
-            hash: my_hash,
+            apk: self.apk.clone(),
+            hash: self
+                .coefficients
+                .get(party_index)
+                .cloned()
+                .unwrap_or_else(Scalar::zero),
+            party_count: self.coefficients.len(),
         }
     }
+
+    /// `add_signature_parts` after checking every partial signature: `partial_sigs[i]` has to
+    /// verify under `public_keys[i]` with its partial nonce `partial_Rs[i]`, and its `R` has to be
+    /// the sum of all partial nonces. Fails with `InvalidPartialSignature { party: i + 1 }` for the
+    /// first one that doesn't and with `RoundMismatch` if the lengths don't match the key set.
+    pub fn add_verified_signature_parts(
+        &self,
+        partial_sigs: &[Signature],
+        message: &[u8],
+        partial_Rs: &[Point<Ed25519>],
+        public_keys: &[Point<Ed25519>],
+    ) -> Result<Signature, Error> {
+        self.add_checked_signature_parts(partial_sigs, partial_Rs, public_keys, |i, sig, R, pk| {
+            self.verify_partial_sig(i, sig, message, R, pk)
+        })
+    }
+
+    /// `add_verified_signature_parts` for the Ed25519ph partial signatures of `partial_sign_ph`.
+    pub fn add_verified_signature_parts_ph(
+        &self,
+        partial_sigs: &[Signature],
+        prehashed_message: &[u8; 64],
+        partial_Rs: &[Point<Ed25519>],
+        public_keys: &[Point<Ed25519>],
+    ) -> Result<Signature, Error> {
+        self.add_checked_signature_parts(partial_sigs, partial_Rs, public_keys, |i, sig, R, pk| {
+            self.verify_partial_sig_ph(i, sig, prehashed_message, R, pk)
+        })
+    }
+
+    /// `add_verified_signature_parts` for the partial signatures of `partial_sign_tweaked` under
+    /// `tweaked`, the `KeyAgg::tweak` of one of the parties of this key.
+    pub fn add_verified_signature_parts_tweaked(
+        &self,
+        tweaked: &TweakedKeyAgg,
+        partial_sigs: &[Signature],
+        message: &[u8],
+        partial_Rs: &[Point<Ed25519>],
+        public_keys: &[Point<Ed25519>],
+    ) -> Result<Signature, Error> {
+        if tweaked.party_count != self.coefficients.len() {
+            return Err(RoundMismatch);
+        }
+        self.add_checked_signature_parts(partial_sigs, partial_Rs, public_keys, |i, sig, R, pk| {
+            self.verify_partial_sig_tweaked(i, tweaked, sig, message, R, pk)
+        })
+    }
+
+    fn add_checked_signature_parts(
+        &self,
+        partial_sigs: &[Signature],
+        partial_Rs: &[Point<Ed25519>],
+        public_keys: &[Point<Ed25519>],
+        verify: impl Fn(usize, &Signature, &Point<Ed25519>, &Point<Ed25519>) -> Result<(), Error> + Sync,
+    ) -> Result<Signature, Error> {
+        let n = self.coefficients.len();
+        if n == 0 || partial_sigs.len() != n || partial_Rs.len() != n || public_keys.len() != n {
+            return Err(RoundMismatch);
+        }
+        let R_tot = get_R_tot(partial_Rs).ok_or(RoundMismatch)?;
+        let faulty = |&i: &usize| {
+            let (sig, R, pk) = (&partial_sigs[i], &partial_Rs[i], &public_keys[i]);
+            sig.R != R_tot || check_point(R).is_err() || verify(i, sig, R, pk).is_err()
+        };
+        // `find_first` reports the lowest faulty party too, as the sequential search does
+        #[cfg(feature = "parallel")]
+        let faulty = (0..n).into_par_iter().find_first(faulty);
+        #[cfg(not(feature = "parallel"))]
+        let faulty = (0..n).find(faulty);
+        if let Some(i) = faulty {
+            return Err(InvalidPartialSignature {
+                party: party_number(i),
+            });
+        }
+        add_signature_parts(partial_sigs)
+    }
+
+    /// Everything after the last round in one call: opens the commitments of round 1 with the
+    /// reveals of round 2, checks and sums the partial signatures and verifies the result under
+    /// `apk`. The messages of party `i` are `sign_first_msgs[i]`, `sign_second_msgs[i]` and
+    /// `partial_sigs[i]`, its public key is `public_keys[i]`.
+    /// Fails with `InvalidCommitment { party: i + 1 }` for the first party whose reveal doesn't
+    /// match its commitment, otherwise like `add_verified_signature_parts`.
+    pub fn aggregate_and_verify(
+        &self,
+        partial_sigs: &[Signature],
+        sign_first_msgs: &[SignFirstMsg],
+        sign_second_msgs: &[SignSecondMsg],
+        public_keys: &[Point<Ed25519>],
+        message: &[u8],
+    ) -> Result<Signature, Error> {
+        if sign_first_msgs.len() != self.coefficients.len() {
+            return Err(RoundMismatch);
+        }
+        let partial_Rs =
+            verify_commitments(sign_first_msgs, sign_second_msgs).map_err(|faulty| {
+                InvalidCommitment {
+                    party: party_number(faulty[0]),
+                }
+            })?;
+        let signature =
+            self.add_verified_signature_parts(partial_sigs, message, &partial_Rs, public_keys)?;
+        signature
+            .verify(message, &self.apk)
+            .map_err(|_| InvalidSig)?;
+        Ok(signature)
+    }
+
+    /// `verify_partial_sig` for the party with the public key `pks[party_index]`.
+    pub fn verify_partial_sig(
+        &self,
+        party_index: usize,
+        sig: &Signature,
+        message: &[u8],
+        partial_R: &Point<Ed25519>,
+        partial_public_key: &Point<Ed25519>,
+    ) -> Result<(), Error> {
+        let a = self.coefficients.get(party_index).ok_or(RoundMismatch)?;
+        verify_partial_sig(sig, message, a, partial_R, partial_public_key, &self.apk)
+    }
+
+    /// `verify_partial_sig_tweaked` with the coefficient of the party with `party_index`.
+    pub fn verify_partial_sig_tweaked(
+        &self,
+        party_index: usize,
+        tweaked: &TweakedKeyAgg,
+        sig: &Signature,
+        message: &[u8],
+        partial_R: &Point<Ed25519>,
+        partial_public_key: &Point<Ed25519>,
+    ) -> Result<(), Error> {
+        let a = self.coefficients.get(party_index).ok_or(RoundMismatch)?;
+        verify_partial_sig_tweaked(sig, message, a, partial_R, partial_public_key, tweaked)
+    }
+
+    /// `verify_partial_sig_ph` with the coefficient of the party with `party_index`.
+    pub fn verify_partial_sig_ph(
+        &self,
+        party_index: usize,
+        sig: &Signature,
+        prehashed_message: &[u8; 64],
+        partial_R: &Point<Ed25519>,
+        partial_public_key: &Point<Ed25519>,
+    ) -> Result<(), Error> {
+        let a = self.coefficients.get(party_index).ok_or(RoundMismatch)?;
+        verify_partial_sig_ph(
+            sig,
+            prehashed_message,
+            a,
+            partial_R,
+            partial_public_key,
+            &self.apk,
+        )
+    }
+}
+
+// SHA-512 of the public keys in order, `AggregatedKey::pks_hash`
+fn hash_public_keys(pks: &[Point<Ed25519>]) -> Output<Sha512> {
+    pks.iter()
+        .fold(Sha512::new(), |hasher, pk| {
+            hasher.chain(&*pk.to_bytes(true))
+        })
+        .finalize()
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// A list of public keys that can be aggregated, none of them the identity and no key twice.
+#[derive(Clone, PartialEq, Debug)]
+pub struct KeySet {
+    pks: Vec<Point<Ed25519>>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct KeySetBuilder {
+    pks: Vec<Point<Ed25519>>,
+    sort: bool,
+}
+
+impl KeySet {
+    pub fn builder() -> KeySetBuilder {
+        KeySetBuilder::default()
+    }
+
+    pub fn public_keys(&self) -> &[Point<Ed25519>] {
+        &self.pks
+    }
+
+    /// The index to use in `KeyAgg::key_aggregation_n` and `AggregatedKey::key_agg`.
+    pub fn index_of(&self, pk: &Point<Ed25519>) -> Option<usize> {
+        self.pks.iter().position(|x| x == pk)
+    }
+
+    pub fn aggregate(&self) -> AggregatedKey {
+        AggregatedKey::new(&self.pks)
+    }
+}
+
+impl KeySetBuilder {
+    pub fn key(mut self, pk: Point<Ed25519>) -> Self {
+        self.pks.push(pk);
+        self
+    }
+
+    pub fn extend<I: IntoIterator<Item = Point<Ed25519>>>(mut self, pks: I) -> Self {
+        self.pks.extend(pks);
+        self
+    }
+
+    /// Orders the keys by their encoding, so the aggregated key doesn't depend on the order
+    /// in which they were added.
+    pub fn sorted(mut self) -> Self {
+        self.sort = true;
+        self
+    }
+
+    /// Fails with `InvalidKey` if there are no keys, one of them is the identity or a key was
+    /// added twice.
+    pub fn build(mut self) -> Result<KeySet, Error> {
+        if self.pks.is_empty() || self.pks.iter().any(|pk| check_point(pk).is_err()) {
+            return Err(InvalidKey);
+        }
+        let mut encodings: Vec<_> = self
+            .pks
+            .iter()
+            .map(|pk| pk.to_bytes(true).to_vec())
+            .collect();
+        encodings.sort_unstable();
+        if encodings.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(InvalidKey);
+        }
+        if self.sort {
+            self.pks.sort_by_cached_key(|pk| pk.to_bytes(true).to_vec());
+        }
+        Ok(KeySet { pks: self.pks })
+    }
+}
+
+/// The secret nonce `r` of a signer and its `R`, a one-shot token: the `partial_sign` functions
+/// consume it, and it can't be cloned, so signing twice with the same nonce doesn't compile.
+/// Serializing it (e.g. to keep it across a restart) gives that guarantee up, as any copy does.
+/// The same goes for a `PartialSigner`, which keeps its nonces itself: its `NonceHandle` can't be
+/// cloned either, but a made up handle for the same `R` is only refused at runtime, by the
+/// signer (`MemorySigner` fails with `InvalidCom`).
+#[derive(Debug, Serialize, Deserialize)]
 pub struct EphemeralKey {
-    pub r: Scalar<Ed25519>,
+    pub(crate) r: Scalar<Ed25519>,
     pub R: Point<Ed25519>,
 }
 
+impl Zeroize for EphemeralKey {
+    fn zeroize(&mut self) {
+        self.r = Scalar::zero();
+        self.R = Point::zero();
+    }
+}
+
+impl ZeroizeOnDrop for EphemeralKey {}
+
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct SignFirstMsg {
     pub commitment: BigInt,
@@ -80,42 +639,446 @@ pub struct SignSecondMsg {
     pub blind_factor: BigInt,
 }
 
+pub const SIGN_FIRST_MSG_SIZE: usize = 64;
+pub const SIGN_SECOND_MSG_SIZE: usize = 64;
+
+impl SignFirstMsg {
+    /// The commitment is a SHA-512 output, encoded as 64 big endian bytes.
+    /// Fails with `InvalidCom` if it doesn't fit, which a valid commitment always does.
+    pub fn to_bytes(&self) -> Result<[u8; SIGN_FIRST_MSG_SIZE], Error> {
+        let mut bytes = [0u8; SIGN_FIRST_MSG_SIZE];
+        write_be_padded(&self.commitment, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8; SIGN_FIRST_MSG_SIZE]) -> SignFirstMsg {
+        SignFirstMsg {
+            commitment: BigInt::from_bytes(bytes),
+        }
+    }
+}
+
+impl SignSecondMsg {
+    /// `R` in its 32 byte compressed form followed by the 32 big endian bytes of the blind factor.
+    /// Fails with `InvalidCom` if the blind factor doesn't fit.
+    pub fn to_bytes(&self) -> Result<[u8; SIGN_SECOND_MSG_SIZE], Error> {
+        let mut bytes = [0u8; SIGN_SECOND_MSG_SIZE];
+        bytes[..32].copy_from_slice(&self.R.to_bytes(true));
+        write_be_padded(&self.blind_factor, &mut bytes[32..])?;
+        Ok(bytes)
+    }
+
+    /// Fails with `InvalidPoint` if `R` isn't a canonical point encoding.
+    pub fn from_bytes(bytes: &[u8; SIGN_SECOND_MSG_SIZE]) -> Result<SignSecondMsg, Error> {
+        Ok(SignSecondMsg {
+            R: decode_point(&bytes[..32]).ok_or(InvalidPoint)?,
+            blind_factor: BigInt::from_bytes(&bytes[32..]),
+        })
+    }
+}
+
+// left pads the big endian encoding of `n` to the size of `out`
+fn write_be_padded(n: &BigInt, out: &mut [u8]) -> Result<(), Error> {
+    let bytes = n.to_bytes();
+    if bytes.len() > out.len() {
+        return Err(InvalidCom);
+    }
+    let (padding, value) = out.split_at_mut(out.len() - bytes.len());
+    padding.iter_mut().for_each(|b| *b = 0);
+    value.copy_from_slice(&bytes);
+    Ok(())
+}
+
+#[cfg(feature = "std")]
 pub fn create_ephemeral_key_and_commit(
     keys: &ExpandedKeyPair,
     message: &[u8],
-) -> (EphemeralKey, SignFirstMsg, SignSecondMsg) {
+) -> Result<(EphemeralKey, SignFirstMsg, SignSecondMsg), Error> {
     create_ephemeral_key_and_commit_rng(keys, message, &mut thread_rng())
 }
 
 pub fn create_ephemeral_key_and_commit_rng(
     keys: &ExpandedKeyPair,
     message: &[u8],
-    rng: &mut impl Rng,
-) -> (EphemeralKey, SignFirstMsg, SignSecondMsg) {
-    // here we deviate from the spec, by introducing  non-deterministic element (random number)
-    // to the nonce
-    let r = Sha512::new()
-        .chain(&[2])
-        .chain(&*keys.expanded_private_key.prefix.to_bytes())
-        .chain(message)
-        .chain(rng.gen::<[u8; 32]>())
-        .result_scalar();
-    let R = Point::generator() * &r;
-    let (commitment, blind_factor) =
-        HashCommitment::<Sha512>::create_commitment(&R.y_coord().unwrap());
-    (
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<(EphemeralKey, SignFirstMsg, SignSecondMsg), Error> {
+    ephemeral_key_and_commit(
+        keys,
+        message,
+        None,
+        &DomainConfig::default(),
+        NonceMode::Hedged(rng),
+    )
+}
+
+/// `create_ephemeral_key_and_commit` with the commitment hashed with `D` instead of SHA-512,
+/// e.g. `sha3::Sha3_512`, it has to be verified with `verify_commitment_with_digest::<D>`.
+#[cfg(feature = "std")]
+pub fn create_ephemeral_key_and_commit_with_digest<D: Digest<OutputSize = U64> + Clone>(
+    keys: &ExpandedKeyPair,
+    message: &[u8],
+) -> Result<(EphemeralKey, SignFirstMsg, SignSecondMsg), Error> {
+    create_ephemeral_key_and_commit_with_digest_rng::<D>(keys, message, &mut thread_rng())
+}
+
+pub fn create_ephemeral_key_and_commit_with_digest_rng<D: Digest<OutputSize = U64> + Clone>(
+    keys: &ExpandedKeyPair,
+    message: &[u8],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<(EphemeralKey, SignFirstMsg, SignSecondMsg), Error> {
+    ephemeral_key_and_commit_with_digest::<D>(
+        keys,
+        message,
+        None,
+        &DomainConfig::default(),
+        NonceMode::Hedged(rng),
+    )
+}
+
+/// Like `create_ephemeral_key_and_commit`, but the commitment is bound to the session id `sid`,
+/// so it can't be replayed into another session with the same signers. All signers have to agree
+/// on `sid` (e.g. a counter or a random value chosen by the coordinator) and verify the
+/// commitments with `verify_commitment_with_sid`.
+/// The challenge is left as in RFC8032, so the signature still verifies as a standard Ed25519 one.
+#[cfg(feature = "std")]
+pub fn create_ephemeral_key_and_commit_with_sid(
+    keys: &ExpandedKeyPair,
+    message: &[u8],
+    sid: &[u8],
+) -> Result<(EphemeralKey, SignFirstMsg, SignSecondMsg), Error> {
+    create_ephemeral_key_and_commit_with_sid_rng(keys, message, sid, &mut thread_rng())
+}
+
+pub fn create_ephemeral_key_and_commit_with_sid_rng(
+    keys: &ExpandedKeyPair,
+    message: &[u8],
+    sid: &[u8],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<(EphemeralKey, SignFirstMsg, SignSecondMsg), Error> {
+    ephemeral_key_and_commit(
+        keys,
+        message,
+        Some(sid),
+        &DomainConfig::default(),
+        NonceMode::Hedged(rng),
+    )
+}
+
+/// Like `create_ephemeral_key_and_commit`, but the nonce is derived under `domain.nonce` and the
+/// commitment made under `domain.commitment`, it has to be verified with
+/// `verify_commitment_with_domain`.
+#[cfg(feature = "std")]
+pub fn create_ephemeral_key_and_commit_with_domain(
+    keys: &ExpandedKeyPair,
+    message: &[u8],
+    domain: &DomainConfig,
+) -> Result<(EphemeralKey, SignFirstMsg, SignSecondMsg), Error> {
+    create_ephemeral_key_and_commit_with_domain_rng(keys, message, domain, &mut thread_rng())
+}
+
+pub fn create_ephemeral_key_and_commit_with_domain_rng(
+    keys: &ExpandedKeyPair,
+    message: &[u8],
+    domain: &DomainConfig,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<(EphemeralKey, SignFirstMsg, SignSecondMsg), Error> {
+    ephemeral_key_and_commit(keys, message, None, domain, NonceMode::Hedged(rng))
+}
+
+/// How the nonce of `create_ephemeral_key_and_commit_with_mode` is derived from the prefix of the
+/// private key and the message.
+/// There is no purely deterministic mode as in RFC8032: a cheating co-signer could start two
+/// sessions over the same message with different `R`s of its own, our nonce would be the same in
+/// both while the challenge differs, and the two partial signatures give away the private key.
+/// Every mode mixes in input that is unique to the session.
+pub enum NonceMode<'a> {
+    /// Additionally mixes in 32 bytes from `rng`, so a faulty or predictable rng alone doesn't
+    /// leak the key. This is what `create_ephemeral_key_and_commit_rng` does.
+    /// The blind factor of the commitment is drawn from `rng` as well, in the other modes it comes
+    /// from the operating system.
+    Hedged(&'a mut dyn CryptoRngCore),
+    /// Additionally mixes in `counter`, which the caller must never reuse with the same key, for
+    /// devices without a good rng that can keep a monotonic counter. A reused counter is as bad
+    /// as a deterministic nonce.
+    CounterBased(u64),
+}
+
+/// `create_ephemeral_key_and_commit` with an explicit choice of nonce derivation, see `NonceMode`.
+pub fn create_ephemeral_key_and_commit_with_mode(
+    keys: &ExpandedKeyPair,
+    message: &[u8],
+    mode: NonceMode,
+) -> Result<(EphemeralKey, SignFirstMsg, SignSecondMsg), Error> {
+    ephemeral_key_and_commit(keys, message, None, &DomainConfig::default(), mode)
+}
+
+// mixes in fresh randomness if `rng` is given, otherwise `counter`
+fn derive_nonce(
+    keys: &ExpandedKeyPair,
+    message: &[u8],
+    sid: Option<&[u8]>,
+    rng: Option<&mut dyn CryptoRngCore>,
+    counter: u64,
+    domain: &[u8],
+) -> Scalar<Ed25519> {
+    let prefix = keys.expanded_private_key.prefix.to_bytes();
+    if let Some(rng) = rng {
+        // here we deviate from the spec, by introducing  non-deterministic element (random number)
+        // to the nonce
+        let mut hasher = domain_hasher(domain, 2).chain(&*prefix).chain(message);
+        if let Some(sid) = sid {
+            hasher = hasher.chain(sid);
+        }
+        return hasher.chain(rng.gen::<[u8; 32]>()).result_scalar();
+    }
+    // length prefixed, so that no two inputs can collide
+    let mut hasher = domain_hasher(domain, 10)
+        .chain(&*prefix)
+        .chain((message.len() as u64).to_be_bytes())
+        .chain(message);
+    if let Some(sid) = sid {
+        hasher = hasher.chain((sid.len() as u64).to_be_bytes()).chain(sid);
+    }
+    hasher.chain(counter.to_be_bytes()).result_scalar()
+}
+
+fn ephemeral_key_and_commit(
+    keys: &ExpandedKeyPair,
+    message: &[u8],
+    sid: Option<&[u8]>,
+    domain: &DomainConfig,
+    mode: NonceMode,
+) -> Result<(EphemeralKey, SignFirstMsg, SignSecondMsg), Error> {
+    ephemeral_key_and_commit_with_digest::<Sha512>(keys, message, sid, domain, mode)
+}
+
+// the commitment's hash is `D`, the nonce is always derived with SHA-512
+fn ephemeral_key_and_commit_with_digest<D: Digest<OutputSize = U64> + Clone>(
+    keys: &ExpandedKeyPair,
+    message: &[u8],
+    sid: Option<&[u8]>,
+    domain: &DomainConfig,
+    mode: NonceMode,
+) -> Result<(EphemeralKey, SignFirstMsg, SignSecondMsg), Error> {
+    let nonce_tag = &domain.nonce;
+    let (r, blind_factor) = match mode {
+        NonceMode::Hedged(rng) => {
+            let r = derive_nonce(keys, message, sid, Some(&mut *rng), 0, nonce_tag);
+            (r, Some(BigInt::from_bytes(&rng.gen::<[u8; 32]>())))
+        }
+        NonceMode::CounterBased(counter) => (
+            derive_nonce(keys, message, sid, None, counter, nonce_tag),
+            None,
+        ),
+    };
+    let R = base_mul(&r);
+    let value = committed_value::<D>(&R, sid, domain)?;
+    let (commitment, blind_factor) = match blind_factor {
+        Some(blind_factor) => (
+            HashCommitment::<D>::create_commitment_with_user_defined_randomness(
+                &value,
+                &blind_factor,
+            ),
+            blind_factor,
+        ),
+        None => HashCommitment::<D>::create_commitment(&value),
+    };
+    Ok((
         EphemeralKey { r, R: R.clone() },
         SignFirstMsg { commitment },
         SignSecondMsg { R, blind_factor },
+    ))
+}
+
+// the value committed to in round 1: the compressed encoding of R (its y coordinate with
+// `legacy_commitment`), hashed together with the session id if there is one or the commitment
+// tag isn't empty (without a session id, then as an empty one)
+fn committed_value<D: Digest + Clone>(
+    R: &Point<Ed25519>,
+    sid: Option<&[u8]>,
+    domain: &DomainConfig,
+) -> Result<BigInt, Error> {
+    let value = if domain.legacy_commitment {
+        R.y_coord().ok_or(InvalidPoint)?
+    } else {
+        BigInt::from_bytes(&R.to_bytes(true))
+    };
+    Ok(match sid {
+        None if domain.commitment.is_empty() => value,
+        sid => {
+            let sid = sid.unwrap_or_default();
+            domain_hasher_with_digest::<D>(&domain.commitment, 7)
+                .chain((sid.len() as u64).to_be_bytes())
+                .chain(sid)
+                .chain_bigint(&value)
+                .result_bigint()
+        }
+    })
+}
+
+/// Checks that the `R` revealed in `sign_second_msg` is the one committed to in `sign_first_msg`.
+/// Fails with `InvalidPoint` if `R` is the identity, see `check_point`.
+pub fn verify_commitment(
+    sign_first_msg: &SignFirstMsg,
+    sign_second_msg: &SignSecondMsg,
+) -> Result<(), Error> {
+    check_commitment(
+        sign_first_msg,
+        sign_second_msg,
+        None,
+        &DomainConfig::default(),
     )
 }
-pub fn get_R_tot(Rs: &[Point<Ed25519>]) -> Point<Ed25519> {
-    let first = Rs[0].clone();
-    Rs[1..].iter().fold(first, |acc, Ri| acc + Ri)
+
+/// `verify_commitment` for commitments created with `create_ephemeral_key_and_commit_with_sid`,
+/// fails with `InvalidCom` if the commitment was made for another session.
+pub fn verify_commitment_with_sid(
+    sign_first_msg: &SignFirstMsg,
+    sign_second_msg: &SignSecondMsg,
+    sid: &[u8],
+) -> Result<(), Error> {
+    check_commitment(
+        sign_first_msg,
+        sign_second_msg,
+        Some(sid),
+        &DomainConfig::default(),
+    )
+}
+
+/// `verify_commitment` for commitments created with `create_ephemeral_key_and_commit_with_domain`,
+/// fails with `InvalidCom` if the commitment was made under another `domain.commitment`.
+pub fn verify_commitment_with_domain(
+    sign_first_msg: &SignFirstMsg,
+    sign_second_msg: &SignSecondMsg,
+    domain: &DomainConfig,
+) -> Result<(), Error> {
+    check_commitment(sign_first_msg, sign_second_msg, None, domain)
 }
 
+/// `verify_commitment` for commitments created with `create_ephemeral_key_and_commit_with_digest`.
+pub fn verify_commitment_with_digest<D: Digest<OutputSize = U64> + Clone>(
+    sign_first_msg: &SignFirstMsg,
+    sign_second_msg: &SignSecondMsg,
+) -> Result<(), Error> {
+    check_commitment_with_digest::<D>(
+        sign_first_msg,
+        sign_second_msg,
+        None,
+        &DomainConfig::default(),
+    )
+}
+
+/// Checks every opening `sign_second_msgs[i]` against the commitment `sign_first_msgs[i]` and
+/// returns the verified `R` of every party, or the indices of the parties whose openings failed.
+/// If the lengths differ, the parties with only one of the two messages failed as well.
+pub fn verify_commitments(
+    sign_first_msgs: &[SignFirstMsg],
+    sign_second_msgs: &[SignSecondMsg],
+) -> Result<Vec<Point<Ed25519>>, Vec<usize>> {
+    open_commitments(sign_first_msgs, sign_second_msgs, None)
+}
+
+fn open_commitments(
+    sign_first_msgs: &[SignFirstMsg],
+    sign_second_msgs: &[SignSecondMsg],
+    sid: Option<&[u8]>,
+) -> Result<Vec<Point<Ed25519>>, Vec<usize>> {
+    let n = sign_first_msgs.len().max(sign_second_msgs.len());
+    let faulty: Vec<_> = (0..n)
+        .filter(
+            |&i| match (sign_first_msgs.get(i), sign_second_msgs.get(i)) {
+                (Some(first), Some(second)) => {
+                    check_commitment(first, second, sid, &DomainConfig::default()).is_err()
+                }
+                _ => true,
+            },
+        )
+        .collect();
+    if faulty.is_empty() {
+        Ok(sign_second_msgs.iter().map(|msg| msg.R.clone()).collect())
+    } else {
+        Err(faulty)
+    }
+}
+
+fn check_commitment(
+    sign_first_msg: &SignFirstMsg,
+    sign_second_msg: &SignSecondMsg,
+    sid: Option<&[u8]>,
+    domain: &DomainConfig,
+) -> Result<(), Error> {
+    check_commitment_with_digest::<Sha512>(sign_first_msg, sign_second_msg, sid, domain)
+}
+
+fn check_commitment_with_digest<D: Digest<OutputSize = U64> + Clone>(
+    sign_first_msg: &SignFirstMsg,
+    sign_second_msg: &SignSecondMsg,
+    sid: Option<&[u8]>,
+    domain: &DomainConfig,
+) -> Result<(), Error> {
+    check_point(&sign_second_msg.R)?;
+    let commitment = HashCommitment::<D>::create_commitment_with_user_defined_randomness(
+        &committed_value::<D>(&sign_second_msg.R, sid, domain)?,
+        &sign_second_msg.blind_factor,
+    );
+    if commitment == sign_first_msg.commitment {
+        Ok(())
+    } else {
+        Err(InvalidCom)
+    }
+}
+
+/// The sum of the partial `R`, `None` if there are none.
+pub fn get_R_tot<'a>(Rs: impl IntoIterator<Item = &'a Point<Ed25519>>) -> Option<Point<Ed25519>> {
+    let mut R_tot = RTotAccumulator::new();
+    Rs.into_iter().for_each(|R| R_tot.add(R));
+    R_tot.R_tot()
+}
+
+/// `get_R_tot` for partial `R` that arrive one at a time.
+#[derive(Clone, Debug)]
+pub struct RTotAccumulator {
+    sum: Point<Ed25519>,
+    count: usize,
+}
+
+impl RTotAccumulator {
+    pub fn new() -> RTotAccumulator {
+        RTotAccumulator {
+            sum: Point::zero(),
+            count: 0,
+        }
+    }
+
+    pub fn add(&mut self, R: &Point<Ed25519>) {
+        self.sum = &self.sum + R;
+        self.count += 1;
+    }
+
+    /// The number of `R` added so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The sum of the `R` added so far, `None` if there are none.
+    pub fn R_tot(&self) -> Option<Point<Ed25519>> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.sum.clone())
+        }
+    }
+}
+
+impl Default for RTotAccumulator {
+    fn default() -> RTotAccumulator {
+        RTotAccumulator::new()
+    }
+}
+
+/// The partial signature with our nonce, `ephemeral_key` is consumed.
 pub fn partial_sign(
-    r: &Scalar<Ed25519>,
+    ephemeral_key: EphemeralKey,
     keys: &ExpandedKeyPair,
     a: &Scalar<Ed25519>,
     R_tot: &Point<Ed25519>,
@@ -123,21 +1086,169 @@ pub fn partial_sign(
     msg: &[u8],
 ) -> Signature {
     let k = Signature::k(R_tot, agg_pubkey, msg);
+    sign_with_challenge(ephemeral_key, k, keys, a, R_tot)
+}
+
+/// `partial_sign` with the challenge hashed with `D` instead of SHA-512, the signature verifies
+/// with `Signature::verify_with_digest::<D>`.
+pub fn partial_sign_with_digest<D: Digest<OutputSize = U64>>(
+    ephemeral_key: EphemeralKey,
+    keys: &ExpandedKeyPair,
+    a: &Scalar<Ed25519>,
+    R_tot: &Point<Ed25519>,
+    agg_pubkey: &Point<Ed25519>,
+    msg: &[u8],
+) -> Signature {
+    let k = Signature::k_with_digest::<D>(R_tot, agg_pubkey, msg);
+    sign_with_challenge(ephemeral_key, k, keys, a, R_tot)
+}
+
+/// `partial_sign` of the message `msg` reads to its end, without buffering it. The signature
+/// verifies with `Signature::verifier` fed the same message.
+#[cfg(feature = "std")]
+pub fn partial_sign_reader(
+    ephemeral_key: EphemeralKey,
+    keys: &ExpandedKeyPair,
+    a: &Scalar<Ed25519>,
+    R_tot: &Point<Ed25519>,
+    agg_pubkey: &Point<Ed25519>,
+    mut msg: impl Read,
+) -> io::Result<Signature> {
+    let mut hasher = Signature::challenge_hasher(R_tot, agg_pubkey);
+    io::copy(&mut msg, &mut hasher)?;
+    let k = Signature::k_from_hasher(hasher);
+    Ok(sign_with_challenge(ephemeral_key, k, keys, a, R_tot))
+}
+
+/// Ed25519ctx version of `partial_sign`, `context` is at most 255 bytes.
+pub fn partial_sign_ctx(
+    ephemeral_key: EphemeralKey,
+    keys: &ExpandedKeyPair,
+    a: &Scalar<Ed25519>,
+    R_tot: &Point<Ed25519>,
+    agg_pubkey: &Point<Ed25519>,
+    msg: &[u8],
+    context: &[u8],
+) -> Result<Signature, Error> {
+    let k = Signature::k_ctx(R_tot, agg_pubkey, msg, context).ok_or(InvalidContext)?;
+    Ok(sign_with_challenge(ephemeral_key, k, keys, a, R_tot))
+}
+
+/// Ed25519ph version of `partial_sign`, `prehashed_message` is SHA-512 of the message.
+/// The nonce can be created by passing `prehashed_message` to `create_ephemeral_key_and_commit`.
+pub fn partial_sign_ph(
+    ephemeral_key: EphemeralKey,
+    keys: &ExpandedKeyPair,
+    a: &Scalar<Ed25519>,
+    R_tot: &Point<Ed25519>,
+    agg_pubkey: &Point<Ed25519>,
+    prehashed_message: &[u8; 64],
+) -> Signature {
+    let k = Signature::k_ph(R_tot, agg_pubkey, prehashed_message);
+    sign_with_challenge(ephemeral_key, k, keys, a, R_tot)
+}
+
+/// `partial_sign` for the tweaked aggregated key `key_agg.apk`, the partial signatures add up
+/// with `add_signature_parts` to a signature under it.
+pub fn partial_sign_tweaked(
+    ephemeral_key: EphemeralKey,
+    keys: &ExpandedKeyPair,
+    key_agg: &TweakedKeyAgg,
+    R_tot: &Point<Ed25519>,
+    msg: &[u8],
+) -> Signature {
+    let sig = partial_sign(ephemeral_key, keys, &key_agg.hash, R_tot, &key_agg.apk, msg);
+    let k = Signature::k(R_tot, &key_agg.apk, msg);
+    Signature {
+        s: sig.s + k * &key_agg.adjustment,
+        R: sig.R,
+    }
+}
+
+/// Adaptor version of `partial_sign`: the partial signatures add up to a pre-signature that only
+/// becomes a valid signature once the discrete log `t` of `adaptor_point` is added to it with
+/// `adapt`, and publishing the adapted signature reveals `t` through `extract_secret`.
+/// All signers have to use the same `adaptor_point`.
+pub fn partial_sign_adaptor(
+    ephemeral_key: EphemeralKey,
+    keys: &ExpandedKeyPair,
+    a: &Scalar<Ed25519>,
+    R_tot: &Point<Ed25519>,
+    adaptor_point: &Point<Ed25519>,
+    agg_pubkey: &Point<Ed25519>,
+    msg: &[u8],
+) -> Signature {
+    // the challenge commits to the final R, which includes the adaptor point
+    partial_sign(
+        ephemeral_key,
+        keys,
+        a,
+        &(R_tot + adaptor_point),
+        agg_pubkey,
+        msg,
+    )
+}
+
+// `r + k * a * x`, the nonce is dropped (and so zeroized) on return
+fn sign_with_challenge(
+    ephemeral_key: EphemeralKey,
+    k: Scalar<Ed25519>,
+    keys: &ExpandedKeyPair,
+    a: &Scalar<Ed25519>,
+    R_tot: &Point<Ed25519>,
+) -> Signature {
+    // the intermediates are moved into the next operation, so curv zeroizes them as they are dropped
     let k_mul_sk = k * &keys.expanded_private_key.private_key;
     let k_mul_sk_mul_ai = k_mul_sk * a;
-    let s = r + k_mul_sk_mul_ai;
+    let s = &ephemeral_key.r + k_mul_sk_mul_ai;
     Signature {
         R: R_tot.clone(),
         s,
     }
 }
 
+/// Checks that `pre_signature` (the sum of the `partial_sign_adaptor` outputs) becomes a valid
+/// signature over `message` once adapted with the discrete log of `adaptor_point`.
+pub fn verify_pre_signature(
+    pre_signature: &Signature,
+    message: &[u8],
+    adaptor_point: &Point<Ed25519>,
+    agg_pubkey: &Point<Ed25519>,
+) -> Result<(), Error> {
+    let k = Signature::k(&pre_signature.R, agg_pubkey, message);
+    if &pre_signature.s * Point::generator() + adaptor_point == &pre_signature.R + agg_pubkey * k {
+        Ok(())
+    } else {
+        Err(InvalidSig)
+    }
+}
+
+/// Completes a pre-signature with the adaptor secret `t`.
+pub fn adapt(pre_signature: &Signature, t: &Scalar<Ed25519>) -> Signature {
+    Signature {
+        R: pre_signature.R.clone(),
+        s: &pre_signature.s + t,
+    }
+}
+
+/// Recovers the adaptor secret from a pre-signature and the signature adapted from it.
+/// Fails with `InvalidSig` if `signature` wasn't adapted from `pre_signature`.
+pub fn extract_secret(
+    pre_signature: &Signature,
+    signature: &Signature,
+) -> Result<Scalar<Ed25519>, Error> {
+    if pre_signature.R != signature.R {
+        return Err(InvalidSig);
+    }
+    Ok(&signature.s - &pre_signature.s)
+}
+
 pub fn sign_single(message: &[u8], keys: &ExpandedKeyPair) -> Signature {
     let r = Sha512::new()
         .chain(&*keys.expanded_private_key.prefix.to_bytes())
         .chain(message)
         .result_scalar();
-    let R = &r * Point::generator();
+    let R = base_mul(&r);
     let k = Signature::k(&R, &keys.public_key, message);
 
     let k_mul_sk = k * &keys.expanded_private_key.private_key;
@@ -145,17 +1256,119 @@ pub fn sign_single(message: &[u8], keys: &ExpandedKeyPair) -> Signature {
     Signature { R, s }
 }
 
-pub fn add_signature_parts(sigs: &[Signature]) -> Signature {
-    //test equality of group elements:
-    assert!(sigs[1..].iter().all(|x| x.R == sigs[0].R));
-    //sum s part of the signature:
+/// `sign_single` of the message `msg` reads from where it is to its end, without buffering it.
+/// Ed25519 hashes the message into the nonce and into the challenge, so it is read twice, seeking
+/// back in between. Fails with `InvalidData` if the second read gives a different message, as
+/// a nonce of one message with the challenge of another would leak the private key.
+/// `sign_single_ph` reads it once.
+#[cfg(feature = "std")]
+pub fn sign_single_reader<M: Read + Seek>(
+    msg: &mut M,
+    keys: &ExpandedKeyPair,
+) -> io::Result<Signature> {
+    let start = msg.stream_position()?;
+    let mut nonce_hasher = Sha512::new().chain(&*keys.expanded_private_key.prefix.to_bytes());
+    let mut message_hasher = Sha512::new();
+    io::copy(msg, &mut Tee(&mut nonce_hasher, &mut message_hasher))?;
+    let r = nonce_hasher.result_scalar();
+    let R = base_mul(&r);
 
-    let s1 = sigs[0].s.clone();
-    let sum = sigs[1..].iter().fold(s1, |acc, si| acc + &si.s);
-    Signature {
-        s: sum,
-        R: sigs[0].R.clone(),
+    msg.seek(SeekFrom::Start(start))?;
+    let mut challenge_hasher = Signature::challenge_hasher(&R, &keys.public_key);
+    let mut reread_hasher = Sha512::new();
+    io::copy(msg, &mut Tee(&mut challenge_hasher, &mut reread_hasher))?;
+    if reread_hasher.finalize() != message_hasher.finalize() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the message changed between the two reads",
+        ));
     }
+    let k = Signature::k_from_hasher(challenge_hasher);
+
+    let k_mul_sk = k * &keys.expanded_private_key.private_key;
+    let s = r + k_mul_sk;
+    Ok(Signature { R, s })
+}
+
+// writes to both hashers
+#[cfg(feature = "std")]
+struct Tee<'a>(&'a mut Sha512, &'a mut Sha512);
+
+#[cfg(feature = "std")]
+impl<'a> Write for Tee<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        self.1.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Ed25519ctx signing, applications sharing a key can use different contexts to make sure
+/// a signature for one of them is never valid for another. `context` is at most 255 bytes.
+pub fn sign_single_ctx(
+    message: &[u8],
+    context: &[u8],
+    keys: &ExpandedKeyPair,
+) -> Result<Signature, Error> {
+    let r = Sha512::new()
+        .chain(dom2(0, context).ok_or(InvalidContext)?)
+        .chain(&*keys.expanded_private_key.prefix.to_bytes())
+        .chain(message)
+        .result_scalar();
+    let R = base_mul(&r);
+    let k = Signature::k_ctx(&R, &keys.public_key, message, context).ok_or(InvalidContext)?;
+
+    let k_mul_sk = k * &keys.expanded_private_key.private_key;
+    let s = r + k_mul_sk;
+    Ok(Signature { R, s })
+}
+
+/// Ed25519ph signing (RFC8032 section 5.1.6), `prehashed_message` is SHA-512 of the message,
+/// so the message itself can be streamed into the hasher.
+pub fn sign_single_ph(prehashed_message: &[u8; 64], keys: &ExpandedKeyPair) -> Signature {
+    let r = Sha512::new()
+        .chain(dom2(1, &[]).expect("the context is empty"))
+        .chain(&*keys.expanded_private_key.prefix.to_bytes())
+        .chain(prehashed_message)
+        .result_scalar();
+    let R = base_mul(&r);
+    let k = Signature::k_ph(&R, &keys.public_key, prehashed_message);
+
+    let k_mul_sk = k * &keys.expanded_private_key.private_key;
+    let s = r + k_mul_sk;
+    Signature { R, s }
+}
+
+/// Fails with `InvalidSig` if `sigs` is empty and with `InvalidPartialSignature { party: i + 1 }`
+/// if the i-th signature has another `R` than the first one. The partial signatures themselves
+/// aren't checked, see `AggregatedKey::add_verified_signature_parts` for that.
+pub fn add_signature_parts<'a>(
+    sigs: impl IntoIterator<Item = &'a Signature>,
+) -> Result<Signature, Error> {
+    let mut sigs = sigs.into_iter();
+    let first = sigs.next().ok_or(InvalidSig)?;
+    sigs.zip(1..).try_fold(first.clone(), |acc, (si, i)| {
+        //test equality of group elements:
+        if si.R != acc.R {
+            return Err(InvalidPartialSignature {
+                party: party_number(i),
+            });
+        }
+        //sum s part of the signature:
+        Ok(Signature {
+            s: acc.s + &si.s,
+            R: acc.R,
+        })
+    })
+}
+
+// the 1-based party number of index `i`, saturating for more than `u16::MAX` parties
+fn party_number(i: usize) -> u16 {
+    u16::try_from(i + 1).unwrap_or(u16::MAX)
 }
 
 pub fn verify_partial_sig(
@@ -165,20 +1378,151 @@ pub fn verify_partial_sig(
     partial_R: &Point<Ed25519>,
     partial_public_key: &Point<Ed25519>,
     agg_pubkey: &Point<Ed25519>,
-) -> Result<(), ProofError> {
+) -> Result<(), Error> {
     let k = Signature::k(&sig.R, agg_pubkey, message);
-    let A = partial_public_key;
+    verify_partial_sig_with_challenge(sig, k, a, partial_R, partial_public_key)
+}
 
-    let kA = A * k * a;
+/// `verify_partial_sig` for a partial signature of `partial_sign_tweaked`, `a` is the coefficient
+/// of the signer and `key_agg` the tweaked key of any of the parties.
+pub fn verify_partial_sig_tweaked(
+    sig: &Signature,
+    message: &[u8],
+    a: &Scalar<Ed25519>,
+    partial_R: &Point<Ed25519>,
+    partial_public_key: &Point<Ed25519>,
+    key_agg: &TweakedKeyAgg,
+) -> Result<(), Error> {
+    let k = Signature::k(&sig.R, &key_agg.apk, message);
+    // the signer added `k * adjustment` to its share of the untweaked signature
+    let untweaked = Signature {
+        R: sig.R.clone(),
+        s: &sig.s - &k * &key_agg.adjustment,
+    };
+    verify_partial_sig_with_challenge(&untweaked, k, a, partial_R, partial_public_key)
+}
 
-    let R_plus_kA = kA + partial_R;
-    let sG = &sig.s * Point::generator();
+/// `verify_partial_sig` for a partial signature of `partial_sign_ph`.
+pub fn verify_partial_sig_ph(
+    sig: &Signature,
+    prehashed_message: &[u8; 64],
+    a: &Scalar<Ed25519>,
+    partial_R: &Point<Ed25519>,
+    partial_public_key: &Point<Ed25519>,
+    agg_pubkey: &Point<Ed25519>,
+) -> Result<(), Error> {
+    let k = Signature::k_ph(&sig.R, agg_pubkey, prehashed_message);
+    verify_partial_sig_with_challenge(sig, k, a, partial_R, partial_public_key)
+}
 
-    if R_plus_kA == sG {
+fn verify_partial_sig_with_challenge(
+    sig: &Signature,
+    k: Scalar<Ed25519>,
+    a: &Scalar<Ed25519>,
+    partial_R: &Point<Ed25519>,
+    partial_public_key: &Point<Ed25519>,
+) -> Result<(), Error> {
+    if verification_equation(&sig.s, partial_R, &(k * a), partial_public_key, false) {
         Ok(())
     } else {
-        Err(ProofError)
+        Err(InvalidSig)
+    }
+}
+
+/// The abort phase of a failed signing session: checks every party's commitment opening and, if
+/// the session got to round 3, its partial signature against its coefficient and partial `R`.
+/// Returns the indices (into `public_keys`) of the parties that cheated, the messages of party `i`
+/// are `sign_first_msgs[i]`, `sign_second_msgs[i]` and `partial_sigs[i]`.
+/// `partial_sigs` is empty if the session aborted before round 3, `sid` is the session id the
+/// commitments were made with, if any.
+pub fn identify_faulty_parties(
+    public_keys: &[Point<Ed25519>],
+    sign_first_msgs: &[SignFirstMsg],
+    sign_second_msgs: &[SignSecondMsg],
+    partial_sigs: &[Signature],
+    message: &[u8],
+    sid: Option<&[u8]>,
+) -> Result<Vec<usize>, Error> {
+    find_faulty_parties(
+        public_keys,
+        sign_first_msgs,
+        sign_second_msgs,
+        partial_sigs,
+        message,
+        sid,
+        None,
+    )
+}
+
+/// `identify_faulty_parties` for a session signing with `partial_sign_tweaked` under the
+/// aggregated key of `public_keys` tweaked by `tweak`.
+pub fn identify_faulty_parties_tweaked(
+    public_keys: &[Point<Ed25519>],
+    sign_first_msgs: &[SignFirstMsg],
+    sign_second_msgs: &[SignSecondMsg],
+    partial_sigs: &[Signature],
+    message: &[u8],
+    sid: Option<&[u8]>,
+    tweak: &Scalar<Ed25519>,
+) -> Result<Vec<usize>, Error> {
+    find_faulty_parties(
+        public_keys,
+        sign_first_msgs,
+        sign_second_msgs,
+        partial_sigs,
+        message,
+        sid,
+        Some(tweak),
+    )
+}
+
+fn find_faulty_parties(
+    public_keys: &[Point<Ed25519>],
+    sign_first_msgs: &[SignFirstMsg],
+    sign_second_msgs: &[SignSecondMsg],
+    partial_sigs: &[Signature],
+    message: &[u8],
+    sid: Option<&[u8]>,
+    tweak: Option<&Scalar<Ed25519>>,
+) -> Result<Vec<usize>, Error> {
+    let n = public_keys.len();
+    if n == 0
+        || sign_first_msgs.len() != n
+        || sign_second_msgs.len() != n
+        || !(partial_sigs.is_empty() || partial_sigs.len() == n)
+    {
+        return Err(RoundMismatch);
+    }
+    let Rs = match open_commitments(sign_first_msgs, sign_second_msgs, sid) {
+        Ok(Rs) => Rs,
+        Err(faulty) => return Ok(faulty),
+    };
+    // partial signatures can only be checked against valid openings
+    if partial_sigs.is_empty() {
+        return Ok(Vec::new());
     }
+
+    let R_tot = get_R_tot(&Rs).ok_or(RoundMismatch)?;
+    let aggregated_key = AggregatedKey::new(public_keys);
+    let tweaked = match tweak {
+        Some(tweak) => Some(aggregated_key.key_agg(0).tweak(tweak)?),
+        None => None,
+    };
+    let faulty = |&i: &usize| {
+        let (sig, R, pk) = (&partial_sigs[i], &Rs[i], &public_keys[i]);
+        let verified = match &tweaked {
+            Some(tweaked) => {
+                aggregated_key.verify_partial_sig_tweaked(i, tweaked, sig, message, R, pk)
+            }
+            None => aggregated_key.verify_partial_sig(i, sig, message, R, pk),
+        };
+        sig.R != R_tot || verified.is_err()
+    };
+    #[cfg(feature = "parallel")]
+    let faulty = (0..n).into_par_iter().filter(faulty).collect();
+    #[cfg(not(feature = "parallel"))]
+    let faulty = (0..n).filter(faulty).collect();
+    Ok(faulty)
 }
 
 mod test;
@@ -0,0 +1,154 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Pre-generated aggsig nonces that are signed with at most once.
+//!
+//! Signing twice with the same nonce over different challenges reveals the private key. The pool
+//! hands every `EphemeralKey` out once and removes it, and before signing with it marks its `R`
+//! as used in a `NonceStore`. The store is meant to be persistent (a file, a database), so a
+//! signer restored from a backup or restarted with an old copy of its pool refuses to sign with
+//! a nonce it already used, instead of leaking its key.
+//! The nonces don't depend on the message (it isn't known yet), they are derived from the prefix
+//! and fresh randomness as in `NonceMode::Hedged`.
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::{CryptoRng, RngCore};
+
+use super::{
+    ephemeral_key_and_commit, partial_sign, DomainConfig, EphemeralKey, NonceMode, SignFirstMsg,
+    SignSecondMsg,
+};
+use protocols::{ExpandedKeyPair, Signature};
+use Error::{self, InvalidCom};
+
+/// Markers of the nonces that were signed with, by the encoding of their `R`.
+pub trait NonceStore {
+    /// Marks `R` as used. Returns `false` if it already was, or if the marker couldn't be
+    /// persisted, in both cases the nonce must not be signed with.
+    fn mark_used(&mut self, R: &[u8; 32]) -> bool;
+}
+
+/// A `NonceStore` that only lives as long as the process, for tests and short lived signers.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryNonceStore {
+    used: BTreeSet<[u8; 32]>,
+}
+
+impl NonceStore for MemoryNonceStore {
+    fn mark_used(&mut self, R: &[u8; 32]) -> bool {
+        self.used.insert(*R)
+    }
+}
+
+/// The nonces we published commitments for and haven't signed with yet.
+pub struct NoncePool<S: NonceStore> {
+    nonces: Vec<EphemeralKey>,
+    store: S,
+}
+
+impl<S: NonceStore> NoncePool<S> {
+    pub fn new(store: S) -> NoncePool<S> {
+        NoncePool::restore(store, Vec::new())
+    }
+
+    /// A pool with the unused nonces saved with `nonces` before a restart.
+    /// Nonces the store already marked are refused when signed with.
+    pub fn restore(store: S, nonces: Vec<EphemeralKey>) -> NoncePool<S> {
+        NoncePool { nonces, store }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn generate(
+        &mut self,
+        keys: &ExpandedKeyPair,
+        count: usize,
+    ) -> Result<Vec<(SignFirstMsg, SignSecondMsg)>, Error> {
+        self.generate_rng(keys, count, &mut thread_rng())
+    }
+
+    /// Adds `count` fresh nonces to the pool and returns their round 1 and round 2 messages,
+    /// `SignFirstMsg` to be published ahead of the signing sessions.
+    pub fn generate_rng(
+        &mut self,
+        keys: &ExpandedKeyPair,
+        count: usize,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<Vec<(SignFirstMsg, SignSecondMsg)>, Error> {
+        (0..count)
+            .map(|_| {
+                let (ephemeral_key, first, second) = ephemeral_key_and_commit(
+                    keys,
+                    &[],
+                    None,
+                    &DomainConfig::default(),
+                    NonceMode::Hedged(&mut *rng),
+                )?;
+                self.nonces.push(ephemeral_key);
+                Ok((first, second))
+            })
+            .collect()
+    }
+
+    /// Removes and returns the nonce of `R`, `None` if it was already taken or isn't ours.
+    pub fn take(&mut self, R: &Point<Ed25519>) -> Option<EphemeralKey> {
+        let position = self.nonces.iter().position(|nonce| nonce.R == *R)?;
+        Some(self.nonces.swap_remove(position))
+    }
+
+    /// The nonces still in the pool, to be serialized (encrypted) for `restore`.
+    pub fn nonces(&self) -> &[EphemeralKey] {
+        &self.nonces
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn len(&self) -> usize {
+        self.nonces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nonces.is_empty()
+    }
+}
+
+/// `partial_sign` with the nonce of `R`, taken out of `pool` and marked as used in its store.
+/// Fails with `InvalidCom` if the nonce isn't in the pool or the store refuses to mark it, e.g.
+/// because it was already signed with.
+pub fn partial_sign_with_pool<S: NonceStore>(
+    pool: &mut NoncePool<S>,
+    R: &Point<Ed25519>,
+    keys: &ExpandedKeyPair,
+    a: &Scalar<Ed25519>,
+    R_tot: &Point<Ed25519>,
+    agg_pubkey: &Point<Ed25519>,
+    msg: &[u8],
+) -> Result<Signature, Error> {
+    let nonce = pool.take(R).ok_or(InvalidCom)?;
+    let mut encoded = [0u8; 32];
+    encoded.copy_from_slice(&nonce.R.to_bytes(true));
+    if !pool.store.mark_used(&encoded) {
+        return Err(InvalidCom);
+    }
+    Ok(partial_sign(nonce, keys, a, R_tot, agg_pubkey, msg))
+}
@@ -0,0 +1,107 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Key aggregation with proofs of possession instead of per-key coefficients.
+//!
+//! Every party proves that it knows the private key of its public key, which rules out rogue-key
+//! attacks, so the aggregated key can be the plain sum of the public keys. That saves hashing
+//! every key on aggregation and the coefficient multiplications, but all proofs have to be
+//! checked before the key is used, so it fits closed signer sets whose keys are registered once.
+//! The signing rounds are the same, with the `KeyAgg` of `AggregatedKey::new_with_pop`.
+
+use alloc::vec::Vec;
+
+use curv::arithmetic::Converter;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use curv::BigInt;
+
+use super::{hash_public_keys, sign_single_ctx, AggregatedKey, KeyAgg};
+use protocols::{ExpandedKeyPair, Signature};
+use Error::{self, InvalidKey, InvalidProof};
+
+// Ed25519ctx context of the proofs, so they can't be mistaken for a signature of anything else
+const POP_CONTEXT: &[u8] = b"multi-party-eddsa proof of possession";
+
+/// An Ed25519ctx signature of `public_key` by its own private key.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ProofOfPossession {
+    pub public_key: Point<Ed25519>,
+    pub signature: Signature,
+}
+
+impl ProofOfPossession {
+    pub fn prove(keys: &ExpandedKeyPair) -> ProofOfPossession {
+        let signature = sign_single_ctx(&keys.public_key.to_bytes(true), POP_CONTEXT, keys)
+            .expect("the context is shorter than 255 bytes");
+        ProofOfPossession {
+            public_key: keys.public_key.clone(),
+            signature,
+        }
+    }
+
+    pub fn verify(&self) -> Result<(), Error> {
+        self.signature
+            .verify_ctx(
+                &self.public_key.to_bytes(true),
+                POP_CONTEXT,
+                &self.public_key,
+            )
+            .map_err(|_| InvalidKey)
+    }
+}
+
+impl AggregatedKey {
+    /// `apk` is the sum of the proven public keys and every coefficient is one.
+    /// Fails with `InvalidProof { party: i + 1 }` if `proofs[i]` doesn't verify and with
+    /// `InvalidKey` for no keys, more than `u16::MAX` keys or a key given twice.
+    pub fn new_with_pop(proofs: &[ProofOfPossession]) -> Result<AggregatedKey, Error> {
+        if proofs.is_empty() || proofs.len() > usize::from(u16::MAX) {
+            return Err(InvalidKey);
+        }
+        for (i, proof) in proofs.iter().enumerate() {
+            // `i < u16::MAX`, so it fits
+            proof.verify().map_err(|_| InvalidProof {
+                party: i as u16 + 1,
+            })?;
+        }
+        let pks: Vec<_> = proofs
+            .iter()
+            .map(|proof| proof.public_key.clone())
+            .collect();
+        let mut encodings: Vec<_> = pks.iter().map(|pk| pk.to_bytes(true).to_vec()).collect();
+        encodings.sort_unstable();
+        if encodings.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(InvalidKey);
+        }
+        Ok(AggregatedKey {
+            apk: pks.iter().fold(Point::zero(), |sum, pk| sum + pk),
+            coefficients: alloc::vec![Scalar::from(1); pks.len()],
+            pks_hash: BigInt::from_bytes(&hash_public_keys(&pks)),
+            weights_hash: None,
+        })
+    }
+}
+
+impl KeyAgg {
+    /// `key_aggregation_n` for `AggregatedKey::new_with_pop`.
+    pub fn key_aggregation_n_with_pop(
+        proofs: &[ProofOfPossession],
+        party_index: usize,
+    ) -> Result<KeyAgg, Error> {
+        Ok(AggregatedKey::new_with_pop(proofs)?.key_agg(party_index))
+    }
+}
@@ -0,0 +1,93 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! An aggregated key prepared for verifying many sessions under it, e.g. of a validator set that
+//! signs every block.
+//!
+//! `PreparedApk` keeps the prepared `apk` and, for every party, a table of multiples of its public
+//! key times its coefficient, so checking a partial signature or the aggregated signature is one
+//! precomputed multi-scalar multiplication instead of two or three curv multiplications, and the
+//! keys aren't encoded again for every challenge.
+
+use alloc::vec::Vec;
+
+use curv::elliptic::curves::{Ed25519, Point};
+use sha2::Sha512;
+
+use super::AggregatedKey;
+use protocols::{encode_point, PreparedPublicKey, Signature};
+use Error::{self, InvalidKey, InvalidSig, RoundMismatch};
+use VerifyError;
+
+#[derive(Debug)]
+pub struct PreparedApk {
+    apk: PreparedPublicKey,
+    // `partial_keys[i]` is `coefficients[i] * public_keys[i]`
+    partial_keys: Vec<PreparedPublicKey>,
+}
+
+impl AggregatedKey {
+    /// Prepares this key and the keys `public_keys` it was aggregated from, in the same order.
+    /// Fails with `RoundMismatch` if there isn't one public key per coefficient and with
+    /// `InvalidKey` if the aggregated key or a public key is the identity.
+    pub fn prepare(&self, public_keys: &[Point<Ed25519>]) -> Result<PreparedApk, Error> {
+        if public_keys.len() != self.coefficients.len() {
+            return Err(RoundMismatch);
+        }
+        let apk = PreparedPublicKey::new(&self.apk).map_err(|_| InvalidKey)?;
+        let partial_keys = public_keys
+            .iter()
+            .zip(&self.coefficients)
+            .map(|(pk, a)| PreparedPublicKey::new(&(pk * a)).map_err(|_| InvalidKey))
+            .collect::<Result<_, _>>()?;
+        Ok(PreparedApk { apk, partial_keys })
+    }
+}
+
+impl PreparedApk {
+    pub fn apk(&self) -> &PreparedPublicKey {
+        &self.apk
+    }
+
+    /// `AggregatedKey::verify_partial_sig` of the party with the public key
+    /// `public_keys[party_index]` of `AggregatedKey::prepare`.
+    pub fn verify_partial_sig(
+        &self,
+        party_index: usize,
+        sig: &Signature,
+        message: &[u8],
+        partial_R: &Point<Ed25519>,
+    ) -> Result<(), Error> {
+        let partial_key = self.partial_keys.get(party_index).ok_or(RoundMismatch)?;
+        let k = Signature::k_dom_encoded::<Sha512>(
+            &[],
+            &encode_point(&sig.R),
+            &self.apk.encoding,
+            message,
+        );
+        if partial_key.check(&encode_point(partial_R), &k, &sig.s) {
+            Ok(())
+        } else {
+            Err(InvalidSig)
+        }
+    }
+
+    /// `Signature::verify` of the aggregated signature under `apk`.
+    pub fn verify(&self, sig: &Signature, message: &[u8]) -> Result<(), VerifyError> {
+        sig.verify_prepared(message, &self.apk)
+    }
+}
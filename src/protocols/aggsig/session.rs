@@ -0,0 +1,190 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! The aggsig rounds as types: `AwaitingCommitments -> AwaitingReveals -> ReadyToSign`.
+//!
+//! Every transition consumes the previous state, and only `ReadyToSign` can sign, which it only
+//! becomes once the commitments of all signers were received and every reveal was checked
+//! against its commitment. Signing before that, or signing twice, doesn't compile.
+//! `AggSigStateMachine` runs the same rounds for message driven code (e.g. `rounds::Driver`).
+//!
+//! The messages of all signers are passed in the order of the public keys, ours included, which
+//! is checked against our own.
+
+use alloc::vec::Vec;
+
+use curv::elliptic::curves::{Ed25519, Point};
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::{CryptoRng, RngCore};
+
+use super::{
+    check_commitment, ephemeral_key_and_commit, get_R_tot, partial_sign, party_number,
+    DomainConfig, EphemeralKey, KeyAgg, NonceMode, SignFirstMsg, SignSecondMsg,
+};
+use protocols::{check_point, ExpandedKeyPair, Signature};
+use Error::{self, InvalidCom, InvalidCommitment, InvalidKey, RoundMismatch};
+
+/// Round 1: our commitment was created and has to be broadcast.
+pub struct AwaitingCommitments<'a> {
+    keys: &'a ExpandedKeyPair,
+    key_agg: KeyAgg,
+    party_index: usize,
+    parties: usize,
+    message: Vec<u8>,
+    ephemeral_key: EphemeralKey,
+    commitment: SignFirstMsg,
+    reveal: SignSecondMsg,
+}
+
+/// Round 2: all commitments are in, our reveal has to be broadcast.
+pub struct AwaitingReveals<'a> {
+    round1: AwaitingCommitments<'a>,
+    commitments: Vec<SignFirstMsg>,
+}
+
+/// Round 3: every reveal matched its commitment.
+pub struct ReadyToSign<'a> {
+    keys: &'a ExpandedKeyPair,
+    key_agg: KeyAgg,
+    message: Vec<u8>,
+    ephemeral_key: EphemeralKey,
+    R_tot: Point<Ed25519>,
+}
+
+impl<'a> AwaitingCommitments<'a> {
+    /// Starts signing `message` as the signer with `public_keys[party_index]`, returns our
+    /// commitment. Fails with `InvalidKey` if that isn't the public key of `keys` or one of the
+    /// public keys is the identity, and with `RoundMismatch` for more than `u16::MAX` signers.
+    #[cfg(feature = "std")]
+    pub fn new(
+        keys: &'a ExpandedKeyPair,
+        public_keys: &[Point<Ed25519>],
+        party_index: usize,
+        message: &[u8],
+    ) -> Result<(AwaitingCommitments<'a>, SignFirstMsg), Error> {
+        Self::new_rng(keys, public_keys, party_index, message, &mut thread_rng())
+    }
+
+    pub fn new_rng(
+        keys: &'a ExpandedKeyPair,
+        public_keys: &[Point<Ed25519>],
+        party_index: usize,
+        message: &[u8],
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<(AwaitingCommitments<'a>, SignFirstMsg), Error> {
+        // the signers are blamed by their `u16` party number
+        if public_keys.len() > usize::from(u16::MAX) {
+            return Err(RoundMismatch);
+        }
+        if public_keys.get(party_index) != Some(&keys.public_key)
+            || public_keys.iter().any(|pk| check_point(pk).is_err())
+        {
+            return Err(InvalidKey);
+        }
+        let (ephemeral_key, commitment, reveal) = ephemeral_key_and_commit(
+            keys,
+            message,
+            None,
+            &DomainConfig::default(),
+            NonceMode::Hedged(rng),
+        )?;
+        let state = AwaitingCommitments {
+            keys,
+            key_agg: KeyAgg::key_aggregation_n(public_keys, party_index),
+            party_index,
+            parties: public_keys.len(),
+            message: message.to_vec(),
+            ephemeral_key,
+            commitment: commitment.clone(),
+            reveal,
+        };
+        Ok((state, commitment))
+    }
+
+    /// Takes the commitments of all signers and returns our reveal.
+    /// Fails with `RoundMismatch` for a commitment too many or too few and with `InvalidCom` if
+    /// ours isn't at our index.
+    pub fn receive_commitments(
+        self,
+        commitments: Vec<SignFirstMsg>,
+    ) -> Result<(AwaitingReveals<'a>, SignSecondMsg), Error> {
+        if commitments.len() != self.parties {
+            return Err(RoundMismatch);
+        }
+        if commitments[self.party_index] != self.commitment {
+            return Err(InvalidCom);
+        }
+        let reveal = self.reveal.clone();
+        Ok((
+            AwaitingReveals {
+                round1: self,
+                commitments,
+            },
+            reveal,
+        ))
+    }
+}
+
+impl<'a> AwaitingReveals<'a> {
+    /// Takes the reveals of all signers and checks them against their commitments.
+    /// Fails with `RoundMismatch` for a reveal too many or too few and with
+    /// `InvalidCommitment { party: j + 1 }` if `reveals[j]` doesn't open `commitments[j]`.
+    pub fn receive_reveals(self, reveals: &[SignSecondMsg]) -> Result<ReadyToSign<'a>, Error> {
+        if reveals.len() != self.commitments.len() {
+            return Err(RoundMismatch);
+        }
+        for (j, (commitment, reveal)) in self.commitments.iter().zip(reveals).enumerate() {
+            check_commitment(commitment, reveal, None, &DomainConfig::default()).map_err(|_| {
+                InvalidCommitment {
+                    party: party_number(j),
+                }
+            })?;
+        }
+        let Rs: Vec<_> = reveals.iter().map(|reveal| reveal.R.clone()).collect();
+        let round1 = self.round1;
+        Ok(ReadyToSign {
+            keys: round1.keys,
+            key_agg: round1.key_agg,
+            message: round1.message,
+            ephemeral_key: round1.ephemeral_key,
+            R_tot: get_R_tot(&Rs).ok_or(RoundMismatch)?,
+        })
+    }
+}
+
+impl<'a> ReadyToSign<'a> {
+    pub fn R_tot(&self) -> &Point<Ed25519> {
+        &self.R_tot
+    }
+
+    pub fn key_agg(&self) -> &KeyAgg {
+        &self.key_agg
+    }
+
+    /// Our partial signature, to be combined with the others' by `add_signature_parts`.
+    pub fn partial_sign(self) -> Signature {
+        partial_sign(
+            self.ephemeral_key,
+            self.keys,
+            &self.key_agg.hash,
+            &self.R_tot,
+            &self.key_agg.apk,
+            &self.message,
+        )
+    }
+}
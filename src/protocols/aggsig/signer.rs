@@ -0,0 +1,208 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! The secret key operations of an aggsig co-signer behind a trait, so a share can live in an
+//! HSM, a hardware wallet or a remote enclave instead of in memory (`MemorySigner`).
+//!
+//! A signer only has to do round 1 (`PartialSigner::commit`) and round 3
+//! (`PartialSigner::partial_sign`), everything else only needs public values.
+//! `create_ephemeral_key_and_commit_with_signer` and `partial_sign_with_signer` run these
+//! rounds and check the signer's output, so a faulty device is noticed before its messages
+//! are sent.
+
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::sync::{Mutex, PoisonError};
+
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::{check_commitment, verify_partial_sig, DomainConfig, SignFirstMsg, SignSecondMsg};
+#[cfg(feature = "std")]
+use super::{ephemeral_key_and_commit, partial_sign, EphemeralKey, NonceMode};
+#[cfg(feature = "std")]
+use protocols::ExpandedKeyPair;
+use protocols::Signature;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use Error::{self, InvalidCom, InvalidSig};
+
+/// A nonce created by `PartialSigner::commit`, handed back to the same signer for
+/// `PartialSigner::partial_sign`. It can't be cloned, so it's signed with at most once.
+/// `secret` is whatever the signer needs to find the secret nonce again, e.g. the encrypted
+/// nonce or an id of it for a device that keeps it, never the secret nonce itself.
+pub struct NonceHandle {
+    pub R: Point<Ed25519>,
+    secret: Vec<u8>,
+}
+
+impl NonceHandle {
+    pub fn new(R: Point<Ed25519>, secret: Vec<u8>) -> NonceHandle {
+        NonceHandle { R, secret }
+    }
+
+    pub fn secret(&self) -> &[u8] {
+        &self.secret
+    }
+}
+
+impl fmt::Debug for NonceHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NonceHandle")
+            .field("R", &self.R)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Zeroize for NonceHandle {
+    fn zeroize(&mut self) {
+        self.R = Point::zero();
+        self.secret.zeroize();
+    }
+}
+
+impl Drop for NonceHandle {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for NonceHandle {}
+
+pub trait PartialSigner {
+    fn public_key(&self) -> Point<Ed25519>;
+
+    /// Round 1: a fresh nonce for signing `message` and the commitment to its `R`.
+    fn commit(&self, message: &[u8]) -> Result<(NonceHandle, SignFirstMsg, SignSecondMsg), Error>;
+
+    /// Round 3: the partial signature with `nonce`, as `aggsig::partial_sign` computes it.
+    /// The nonce is consumed, a signer must never sign twice with the same nonce.
+    fn partial_sign(
+        &self,
+        nonce: NonceHandle,
+        a: &Scalar<Ed25519>,
+        R_tot: &Point<Ed25519>,
+        agg_pubkey: &Point<Ed25519>,
+        message: &[u8],
+    ) -> Result<Signature, Error>;
+}
+
+/// The in-memory signer. It keeps its secret nonces until it signs with them, so its
+/// `NonceHandle`s carry no secret and a nonce can't be signed with twice.
+#[cfg(feature = "std")]
+pub struct MemorySigner {
+    keys: ExpandedKeyPair,
+    nonces: Mutex<Vec<EphemeralKey>>,
+}
+
+#[cfg(feature = "std")]
+impl MemorySigner {
+    pub fn new(keys: ExpandedKeyPair) -> MemorySigner {
+        MemorySigner {
+            keys,
+            nonces: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn keys(&self) -> &ExpandedKeyPair {
+        &self.keys
+    }
+}
+
+#[cfg(feature = "std")]
+impl PartialSigner for MemorySigner {
+    fn public_key(&self) -> Point<Ed25519> {
+        self.keys.public_key.clone()
+    }
+
+    fn commit(&self, message: &[u8]) -> Result<(NonceHandle, SignFirstMsg, SignSecondMsg), Error> {
+        let (ephemeral_key, first, second) = ephemeral_key_and_commit(
+            &self.keys,
+            message,
+            None,
+            &DomainConfig::default(),
+            NonceMode::Hedged(&mut thread_rng()),
+        )?;
+        let nonce = NonceHandle::new(ephemeral_key.R.clone(), Vec::new());
+        self.nonces
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(ephemeral_key);
+        Ok((nonce, first, second))
+    }
+
+    /// Fails with `InvalidCom` if the nonce isn't one of ours or was already signed with.
+    fn partial_sign(
+        &self,
+        nonce: NonceHandle,
+        a: &Scalar<Ed25519>,
+        R_tot: &Point<Ed25519>,
+        agg_pubkey: &Point<Ed25519>,
+        message: &[u8],
+    ) -> Result<Signature, Error> {
+        let ephemeral_key = {
+            let mut nonces = self.nonces.lock().unwrap_or_else(PoisonError::into_inner);
+            let position = nonces
+                .iter()
+                .position(|ephemeral_key| ephemeral_key.R == nonce.R)
+                .ok_or(InvalidCom)?;
+            nonces.swap_remove(position)
+        };
+        Ok(partial_sign(
+            ephemeral_key,
+            &self.keys,
+            a,
+            R_tot,
+            agg_pubkey,
+            message,
+        ))
+    }
+}
+
+/// `create_ephemeral_key_and_commit` with `signer`, fails with `InvalidCom` if the signer's
+/// commitment doesn't open to its `R`.
+pub fn create_ephemeral_key_and_commit_with_signer(
+    signer: &dyn PartialSigner,
+    message: &[u8],
+) -> Result<(NonceHandle, SignFirstMsg, SignSecondMsg), Error> {
+    let (nonce, first, second) = signer.commit(message)?;
+    check_commitment(&first, &second, None, &DomainConfig::default())?;
+    if second.R != nonce.R {
+        return Err(InvalidCom);
+    }
+    Ok((nonce, first, second))
+}
+
+/// `partial_sign` with `signer`, fails with `InvalidSig` if the signer's partial signature
+/// doesn't verify.
+pub fn partial_sign_with_signer(
+    signer: &dyn PartialSigner,
+    nonce: NonceHandle,
+    a: &Scalar<Ed25519>,
+    R_tot: &Point<Ed25519>,
+    agg_pubkey: &Point<Ed25519>,
+    message: &[u8],
+) -> Result<Signature, Error> {
+    let R = nonce.R.clone();
+    let signature = signer.partial_sign(nonce, a, R_tot, agg_pubkey, message)?;
+    if signature.R != *R_tot {
+        return Err(InvalidSig);
+    }
+    verify_partial_sig(&signature, message, a, &R, &signer.public_key(), agg_pubkey)?;
+    Ok(signature)
+}
@@ -0,0 +1,339 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! aggsig as a `StateMachine`
+//!
+//! Round 1 broadcasts the commitment to our `R`, round 2 reveals it once everyone has committed,
+//! round 3 checks all openings and broadcasts our partial signature, and finishing adds up the
+//! partial signatures and checks the result against the aggregated key. If that fails, the party
+//! with an invalid partial signature is blamed (see `aggsig::identify_faulty_parties`).
+//! If `proceed` fails the session is aborted, a new state machine (with fresh nonces) is needed
+//! to try again.
+
+use std::convert::TryFrom;
+use std::mem;
+
+use curv::elliptic::curves::{Ed25519, Point};
+use rand::thread_rng;
+
+use protocols::aggsig::{
+    self, DomainConfig, EphemeralKey, KeyAgg, NonceMode, SignFirstMsg, SignSecondMsg,
+};
+use protocols::rounds::{Msg, StateMachine};
+use protocols::{check_point, ExpandedKeyPair, Signature};
+use Error::{
+    self, InvalidCommitment, InvalidKey, InvalidPartialSignature, InvalidSig, RoundMismatch,
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AggSigMessage {
+    Commitment(SignFirstMsg),
+    Reveal(SignSecondMsg),
+    PartialSignature(Signature),
+}
+
+enum Round {
+    Round0,
+    Round1 {
+        ephemeral_key: EphemeralKey,
+        reveal: SignSecondMsg,
+    },
+    Round2 {
+        ephemeral_key: EphemeralKey,
+    },
+    Round3,
+    Finished(Option<Signature>),
+    // `proceed` failed in this round, the session has to be restarted
+    Aborted(u16),
+}
+
+pub struct AggSigStateMachine {
+    keys: ExpandedKeyPair,
+    key_agg: KeyAgg,
+    public_keys: Vec<Point<Ed25519>>,
+    party_index: u16,
+    parties: u16,
+    message: Vec<u8>,
+    sid: Option<Vec<u8>>,
+    round: Round,
+    queue: Vec<Msg<AggSigMessage>>,
+    commitments: Vec<Option<SignFirstMsg>>,
+    reveals: Vec<Option<SignSecondMsg>>,
+    partial_sigs: Vec<Option<Signature>>,
+}
+
+impl AggSigStateMachine {
+    /// Party `i` has the public key `public_keys[i - 1]`, which has to be `keys.public_key` for
+    /// our own `party_index`. Fails with `InvalidKey` if one of them is the identity.
+    pub fn new(
+        keys: ExpandedKeyPair,
+        public_keys: &[Point<Ed25519>],
+        party_index: u16,
+        message: &[u8],
+    ) -> Result<AggSigStateMachine, Error> {
+        let parties = u16::try_from(public_keys.len()).map_err(|_| RoundMismatch)?;
+        if party_index == 0 || party_index > parties {
+            return Err(RoundMismatch);
+        }
+        let i = usize::from(party_index - 1);
+        if public_keys[i] != keys.public_key
+            || public_keys.iter().any(|pk| check_point(pk).is_err())
+        {
+            return Err(InvalidKey);
+        }
+        let n = public_keys.len();
+        Ok(AggSigStateMachine {
+            key_agg: KeyAgg::key_aggregation_n(public_keys, i),
+            keys,
+            public_keys: public_keys.to_vec(),
+            party_index,
+            parties,
+            message: message.to_vec(),
+            sid: None,
+            round: Round::Round0,
+            queue: Vec::new(),
+            commitments: vec![None; n],
+            reveals: vec![None; n],
+            partial_sigs: vec![None; n],
+        })
+    }
+
+    /// Binds the commitments to the session id `sid`, which all parties have to agree on,
+    /// see `aggsig::create_ephemeral_key_and_commit_with_sid`.
+    pub fn new_with_sid(
+        keys: ExpandedKeyPair,
+        public_keys: &[Point<Ed25519>],
+        party_index: u16,
+        message: &[u8],
+        sid: &[u8],
+    ) -> Result<AggSigStateMachine, Error> {
+        let mut state_machine = Self::new(keys, public_keys, party_index, message)?;
+        state_machine.sid = Some(sid.to_vec());
+        Ok(state_machine)
+    }
+
+    /// The aggregated public key the signature will verify under.
+    pub fn aggregated_public_key(&self) -> &Point<Ed25519> {
+        &self.key_agg.apk
+    }
+
+    fn broadcast(&mut self, body: AggSigMessage) {
+        self.queue.push(Msg {
+            sender: self.party_index,
+            receiver: None,
+            body,
+        });
+    }
+
+    // finds the party whose partial signature broke the aggregated one
+    fn blame(&self, partial_sigs: &[Signature]) -> Error {
+        let sign_first_msgs: Option<Vec<_>> = self.commitments.iter().cloned().collect();
+        let sign_second_msgs: Option<Vec<_>> = self.reveals.iter().cloned().collect();
+        let faulty = match (sign_first_msgs, sign_second_msgs) {
+            (Some(sign_first_msgs), Some(sign_second_msgs)) => aggsig::identify_faulty_parties(
+                &self.public_keys,
+                &sign_first_msgs,
+                &sign_second_msgs,
+                partial_sigs,
+                &self.message,
+                self.sid.as_deref(),
+            ),
+            _ => Err(RoundMismatch),
+        };
+        match faulty.as_ref().map(|faulty| faulty.first()) {
+            // `i < parties`, so it fits
+            Ok(Some(&i)) => InvalidPartialSignature {
+                party: i as u16 + 1,
+            },
+            _ => InvalidSig,
+        }
+    }
+
+    fn own_slot(&self) -> usize {
+        usize::from(self.party_index - 1)
+    }
+}
+
+// stores `value` for `sender`, a second message of the same kind from the same party is rejected
+fn store<T>(slots: &mut [Option<T>], sender: u16, value: T) -> Result<(), Error> {
+    let slot = &mut slots[usize::from(sender - 1)];
+    if slot.is_some() {
+        return Err(RoundMismatch);
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+fn all_received<T>(slots: &[Option<T>]) -> bool {
+    slots.iter().all(Option::is_some)
+}
+
+impl StateMachine for AggSigStateMachine {
+    type MessageBody = AggSigMessage;
+    type Output = Signature;
+    type Err = Error;
+
+    fn handle_incoming(&mut self, msg: Msg<AggSigMessage>) -> Result<(), Error> {
+        if msg.sender == 0
+            || msg.sender > self.parties
+            || msg.sender == self.party_index
+            || msg.receiver.is_some()
+        {
+            return Err(RoundMismatch);
+        }
+        match msg.body {
+            AggSigMessage::Commitment(commitment) => {
+                store(&mut self.commitments, msg.sender, commitment)
+            }
+            AggSigMessage::Reveal(reveal) => store(&mut self.reveals, msg.sender, reveal),
+            AggSigMessage::PartialSignature(partial_sig) => {
+                store(&mut self.partial_sigs, msg.sender, partial_sig)
+            }
+        }
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<AggSigMessage>> {
+        &mut self.queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        match self.round {
+            Round::Round0 => true,
+            Round::Round1 { .. } => all_received(&self.commitments),
+            Round::Round2 { .. } => all_received(&self.reveals),
+            Round::Round3 => all_received(&self.partial_sigs),
+            Round::Finished(_) | Round::Aborted(_) => false,
+        }
+    }
+
+    fn proceed(&mut self) -> Result<(), Error> {
+        if !self.wants_to_proceed() {
+            return Err(RoundMismatch);
+        }
+        let own = self.own_slot();
+        let aborted = Round::Aborted(self.current_round());
+        self.round = match mem::replace(&mut self.round, aborted) {
+            Round::Round0 => {
+                let (ephemeral_key, commitment, reveal) = aggsig::ephemeral_key_and_commit(
+                    &self.keys,
+                    &self.message,
+                    self.sid.as_deref(),
+                    &DomainConfig::default(),
+                    NonceMode::Hedged(&mut thread_rng()),
+                )?;
+                self.commitments[own] = Some(commitment.clone());
+                self.broadcast(AggSigMessage::Commitment(commitment));
+                Round::Round1 {
+                    ephemeral_key,
+                    reveal,
+                }
+            }
+            Round::Round1 {
+                ephemeral_key,
+                reveal,
+            } => {
+                self.reveals[own] = Some(reveal.clone());
+                self.broadcast(AggSigMessage::Reveal(reveal));
+                Round::Round2 { ephemeral_key }
+            }
+            Round::Round2 { ephemeral_key } => {
+                let mut Rs = Vec::with_capacity(self.reveals.len());
+                for (j, (commitment, reveal)) in
+                    self.commitments.iter().zip(&self.reveals).enumerate()
+                {
+                    let (commitment, reveal) = match (commitment, reveal) {
+                        (Some(commitment), Some(reveal)) => (commitment, reveal),
+                        _ => return Err(RoundMismatch),
+                    };
+                    if aggsig::check_commitment(
+                        commitment,
+                        reveal,
+                        self.sid.as_deref(),
+                        &DomainConfig::default(),
+                    )
+                    .is_err()
+                    {
+                        // `j < parties`, so it fits
+                        return Err(InvalidCommitment {
+                            party: j as u16 + 1,
+                        });
+                    }
+                    Rs.push(reveal.R.clone());
+                }
+                let partial_sig = aggsig::partial_sign(
+                    ephemeral_key,
+                    &self.keys,
+                    &self.key_agg.hash,
+                    &aggsig::get_R_tot(&Rs).ok_or(RoundMismatch)?,
+                    &self.key_agg.apk,
+                    &self.message,
+                );
+                self.partial_sigs[own] = Some(partial_sig.clone());
+                self.broadcast(AggSigMessage::PartialSignature(partial_sig));
+                Round::Round3
+            }
+            Round::Round3 => {
+                let partial_sigs = self
+                    .partial_sigs
+                    .iter()
+                    .cloned()
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or(RoundMismatch)?;
+                let signature = aggsig::add_signature_parts(&partial_sigs)
+                    .ok()
+                    .filter(|sig| sig.verify(&self.message, &self.key_agg.apk).is_ok());
+                match signature {
+                    Some(signature) => Round::Finished(Some(signature)),
+                    None => return Err(self.blame(&partial_sigs)),
+                }
+            }
+            // wants_to_proceed is false in these
+            round @ Round::Finished(_) | round @ Round::Aborted(_) => round,
+        };
+        Ok(())
+    }
+
+    fn current_round(&self) -> u16 {
+        match self.round {
+            Round::Round0 => 0,
+            Round::Round1 { .. } => 1,
+            Round::Round2 { .. } => 2,
+            Round::Round3 => 3,
+            Round::Finished(_) => 4,
+            Round::Aborted(round) => round,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.round, Round::Finished(_))
+    }
+
+    fn pick_output(&mut self) -> Option<Signature> {
+        match &mut self.round {
+            Round::Finished(output) => output.take(),
+            _ => None,
+        }
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.party_index
+    }
+
+    fn parties(&self) -> u16 {
+        self.parties
+    }
+}
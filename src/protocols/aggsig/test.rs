@@ -16,7 +16,10 @@
 
 #[cfg(test)]
 mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
     use std::convert::TryInto;
+    use std::io::{self, Cursor, Read, Seek, SeekFrom};
 
     use curv::cryptographic_primitives::commitments::{
         hash_commitment::HashCommitment, traits::Commitment,
@@ -25,15 +28,26 @@ mod tests {
     use curv::{arithmetic::Converter, BigInt};
     use hex::decode;
     use itertools::{izip, MultiUnzip};
-    use rand::{Rng, RngCore};
-    use sha2::Sha512;
+    use rand::{CryptoRng, Rng, RngCore};
+    use serde_json;
+    use sha2::{Digest, Sha512};
+    use sha3::Sha3_512;
 
     use protocols::tests::deterministic_fast_rand;
     use protocols::{
-        aggsig::{self, KeyAgg},
+        aggsig::vectors::AggSigVector,
+        aggsig::{
+            self, AccountableSignature, AggSigMessage, AggSigStateMachine, AggregatedKey,
+            AwaitingCommitments, ChildKey, DomainConfig, EphemeralKey, KeyAgg, KeySet,
+            MemoryNonceStore, MemorySigner, NonceHandle, NonceMode, NoncePool, PartialSigner,
+            ProofOfPossession, RTotAccumulator, SignFirstMsg, SignFirstMsgRef, SignSecondMsg,
+            SignSecondMsgRef,
+        },
+        rounds::{Msg, StateMachine},
         tests::verify_dalek,
-        ExpandedKeyPair, Signature,
+        ExpandedKeyPair, Signature, HARDENED,
     };
+    use {Error, VerifyError};
 
     #[test]
     fn test_ed25519_generate_keypair_from_seed() {
@@ -109,8 +123,9 @@ mod tests {
                     .iter()
                     .map(|keypair| {
                         let (ephemeral, sign_first, sign_second) =
-                            aggsig::create_ephemeral_key_and_commit_rng(keypair, msg, &mut rng);
-                        (ephemeral.R, ephemeral.r, sign_first, sign_second)
+                            aggsig::create_ephemeral_key_and_commit_rng(keypair, msg, &mut rng)
+                                .unwrap();
+                        (ephemeral.R.clone(), ephemeral, sign_first, sign_second)
                     })
                     .multiunzip();
                 // Send first first msg, wait to recieve everyone else's and then send second msg.
@@ -127,16 +142,16 @@ mod tests {
                         ));
                     });
                 // Each party aggregates the Rs to get the aggregate R
-                let agg_R = aggsig::get_R_tot(&Rs);
+                let agg_R = aggsig::get_R_tot(&Rs).unwrap();
 
                 // keypairs
-                let partial_sigs: Vec<_> = izip!(keypairs.iter(), rs.iter(), agg_keys.iter())
+                let partial_sigs: Vec<_> = izip!(keypairs.iter(), rs, agg_keys.iter())
                     .map(|(keypair, r, aggkey)| {
                         aggsig::partial_sign(r, keypair, &aggkey.hash, &agg_R, &aggkey.apk, msg)
                     })
                     .collect();
 
-                let signature = aggsig::add_signature_parts(&partial_sigs);
+                let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
                 assert!(verify_dalek(&agg_keys[0].apk, &signature, msg));
             }
         }
@@ -158,7 +173,7 @@ mod tests {
         }
     }
 
-    fn test_multiparty_signing_for_two_parties_internal(rng: &mut impl Rng) {
+    fn test_multiparty_signing_for_two_parties_internal(rng: &mut (impl CryptoRng + RngCore)) {
         let message: [u8; 4] = [79, 77, 69, 82];
 
         // round 0: generate signing keys
@@ -167,9 +182,9 @@ mod tests {
 
         // round 1: send commitments to ephemeral public keys
         let (party1_ephemeral_key, party1_sign_first_message, party1_sign_second_message) =
-            aggsig::create_ephemeral_key_and_commit_rng(&party1_key, &message, rng);
+            aggsig::create_ephemeral_key_and_commit_rng(&party1_key, &message, rng).unwrap();
         let (party2_ephemeral_key, party2_sign_first_message, party2_sign_second_message) =
-            aggsig::create_ephemeral_key_and_commit_rng(&party2_key, &message, rng);
+            aggsig::create_ephemeral_key_and_commit_rng(&party2_key, &message, rng).unwrap();
 
         let party1_commitment = &party1_sign_first_message.commitment;
         let party2_commitment = &party2_sign_first_message.commitment;
@@ -185,6 +200,14 @@ mod tests {
             &party1_sign_second_message.blind_factor,
             party1_commitment
         ));
+        assert!(
+            aggsig::verify_commitment(&party1_sign_first_message, &party1_sign_second_message)
+                .is_ok()
+        );
+        assert_eq!(
+            aggsig::verify_commitment(&party1_sign_first_message, &party2_sign_second_message),
+            Err(Error::InvalidCom)
+        );
 
         // compute apk:
         let pks = [party1_key.public_key.clone(), party2_key.public_key.clone()];
@@ -192,11 +215,14 @@ mod tests {
         let party2_key_agg = KeyAgg::key_aggregation_n(&pks, 1);
         assert_eq!(party1_key_agg.apk, party2_key_agg.apk);
         // compute R' = sum(Ri):
-        let Ri = [party1_ephemeral_key.R, party2_ephemeral_key.R];
+        let Ri = [
+            party1_ephemeral_key.R.clone(),
+            party2_ephemeral_key.R.clone(),
+        ];
         // each party i should run this:
-        let R_tot = aggsig::get_R_tot(&Ri);
+        let R_tot = aggsig::get_R_tot(&Ri).unwrap();
         let s1 = aggsig::partial_sign(
-            &party1_ephemeral_key.r,
+            party1_ephemeral_key,
             &party1_key,
             &party1_key_agg.hash,
             &R_tot,
@@ -204,7 +230,7 @@ mod tests {
             &message,
         );
         let s2 = aggsig::partial_sign(
-            &party2_ephemeral_key.r,
+            party2_ephemeral_key,
             &party2_key,
             &party2_key_agg.hash,
             &R_tot,
@@ -213,7 +239,7 @@ mod tests {
         );
 
         let s = [s1, s2];
-        let signature = aggsig::add_signature_parts(&s);
+        let signature = aggsig::add_signature_parts(&s).unwrap();
 
         // verify:
         assert!(signature.verify(&message, &party1_key_agg.apk).is_ok())
@@ -227,7 +253,7 @@ mod tests {
         }
     }
 
-    fn test_multiparty_signing_for_three_parties_internal(rng: &mut impl Rng) {
+    fn test_multiparty_signing_for_three_parties_internal(rng: &mut (impl CryptoRng + RngCore)) {
         let message: [u8; 4] = [79, 77, 69, 82];
 
         // round 0: generate signing keys
@@ -237,11 +263,11 @@ mod tests {
 
         // round 1: send commitments to ephemeral public keys
         let (party1_ephemeral_key, party1_sign_first_message, party1_sign_second_message) =
-            aggsig::create_ephemeral_key_and_commit_rng(&party1_key, &message, rng);
+            aggsig::create_ephemeral_key_and_commit_rng(&party1_key, &message, rng).unwrap();
         let (party2_ephemeral_key, party2_sign_first_message, party2_sign_second_message) =
-            aggsig::create_ephemeral_key_and_commit_rng(&party2_key, &message, rng);
+            aggsig::create_ephemeral_key_and_commit_rng(&party2_key, &message, rng).unwrap();
         let (party3_ephemeral_key, party3_sign_first_message, party3_sign_second_message) =
-            aggsig::create_ephemeral_key_and_commit_rng(&party3_key, &message, rng);
+            aggsig::create_ephemeral_key_and_commit_rng(&party3_key, &message, rng).unwrap();
 
         let party1_commitment = &party1_sign_first_message.commitment;
         let party2_commitment = &party2_sign_first_message.commitment;
@@ -277,14 +303,14 @@ mod tests {
         assert_eq!(party1_key_agg.apk, party3_key_agg.apk);
         // compute R' = sum(Ri):
         let Ri = [
-            party1_ephemeral_key.R,
-            party2_ephemeral_key.R,
-            party3_ephemeral_key.R,
+            party1_ephemeral_key.R.clone(),
+            party2_ephemeral_key.R.clone(),
+            party3_ephemeral_key.R.clone(),
         ];
         // each party i should run this:
-        let R_tot = aggsig::get_R_tot(&Ri);
+        let R_tot = aggsig::get_R_tot(&Ri).unwrap();
         let s1 = aggsig::partial_sign(
-            &party1_ephemeral_key.r,
+            party1_ephemeral_key,
             &party1_key,
             &party1_key_agg.hash,
             &R_tot,
@@ -292,7 +318,7 @@ mod tests {
             &message,
         );
         let s2 = aggsig::partial_sign(
-            &party2_ephemeral_key.r,
+            party2_ephemeral_key,
             &party2_key,
             &party2_key_agg.hash,
             &R_tot,
@@ -300,7 +326,7 @@ mod tests {
             &message,
         );
         let s3 = aggsig::partial_sign(
-            &party3_ephemeral_key.r,
+            party3_ephemeral_key,
             &party3_key,
             &party3_key_agg.hash,
             &R_tot,
@@ -309,7 +335,7 @@ mod tests {
         );
 
         let s = [s1, s2, s3];
-        let signature = aggsig::add_signature_parts(&s);
+        let signature = aggsig::add_signature_parts(&s).unwrap();
 
         // verify:
         assert!(signature.verify(&message, &party1_key_agg.apk).is_ok())
@@ -345,10 +371,2216 @@ mod tests {
         assert!(sig.verify(&message, &pk).is_ok())
     }
 
+    #[test]
+    fn test_sign_single_ph_rfc8032_vector() {
+        // RFC8032 section 7.3, Ed25519ph test vector for "abc"
+        let priv_dec: [u8; 32] =
+            decode("833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let expected_pubkey =
+            decode("ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf").unwrap();
+        let expected_sig = decode(
+            "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae41\
+             31f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406",
+        )
+        .unwrap();
+        let message = decode("616263").unwrap();
+        let mut prehashed = [0u8; 64];
+        prehashed.copy_from_slice(&Sha512::digest(&message));
+
+        let keypair = ExpandedKeyPair::create_from_private_key(priv_dec);
+        assert_eq!(&*keypair.public_key.to_bytes(true), &expected_pubkey[..]);
+        assert!(Signature::verify_bytes_ph(&expected_sig, &prehashed, &expected_pubkey).is_ok());
+        assert!(Signature::verify_bytes(&expected_sig, &message, &expected_pubkey).is_err());
+
+        // the nonce derivation differs from RFC8032, so only check that our signature verifies
+        let sig = aggsig::sign_single_ph(&prehashed, &keypair);
+        assert!(sig.verify_ph(&prehashed, &keypair.public_key).is_ok());
+        // Ed25519ph signatures are domain separated from plain Ed25519 signatures
+        assert!(sig.verify(&prehashed, &keypair.public_key).is_err());
+        assert!(sig.verify(&message, &keypair.public_key).is_err());
+    }
+
+    #[test]
+    fn test_sign_msgs_bytes_roundtrip() {
+        let mut rng = deterministic_fast_rand("test_sign_msgs_bytes_roundtrip", None);
+        for _ in 0..32 {
+            let keys = ExpandedKeyPair::create_from_private_key(rng.gen());
+            let (_, sign_first_msg, sign_second_msg) =
+                aggsig::create_ephemeral_key_and_commit_rng(&keys, b"bytes", &mut rng).unwrap();
+
+            let first_bytes = sign_first_msg.to_bytes().unwrap();
+            let second_bytes = sign_second_msg.to_bytes().unwrap();
+            let first = SignFirstMsg::from_bytes(&first_bytes);
+            let second = SignSecondMsg::from_bytes(&second_bytes).unwrap();
+            assert_eq!(first, sign_first_msg);
+            assert_eq!(second, sign_second_msg);
+            assert!(aggsig::verify_commitment(&first, &second).is_ok());
+        }
+
+        let mut not_a_point = [0u8; 64];
+        not_a_point[..32].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            SignSecondMsg::from_bytes(&not_a_point),
+            Err(Error::InvalidPoint)
+        );
+        let too_long = SignFirstMsg {
+            commitment: BigInt::from(1) << 512,
+        };
+        assert_eq!(too_long.to_bytes(), Err(Error::InvalidCom));
+    }
+
+    #[test]
+    fn test_borrowed_msgs() {
+        use protocols::aggsig::borrowed;
+        use serde::de::value::{BorrowedBytesDeserializer, Error as ValueError};
+        use serde::Deserialize;
+
+        let mut rng = deterministic_fast_rand("test_borrowed_msgs", None);
+        let domain = DomainConfig::new(b"borrowed");
+        for _ in 0..16 {
+            let keys = ExpandedKeyPair::create_from_private_key(rng.gen());
+            let (_, first, second) =
+                aggsig::create_ephemeral_key_and_commit_rng(&keys, b"borrowed", &mut rng).unwrap();
+            let (first_bytes, second_bytes) =
+                (first.to_bytes().unwrap(), second.to_bytes().unwrap());
+            let first_ref = SignFirstMsgRef::from_bytes(&first_bytes).unwrap();
+            let second_ref = SignSecondMsgRef::from_bytes(&second_bytes).unwrap();
+            assert_eq!(first_ref.to_owned(), first);
+            assert_eq!(second_ref.to_owned().unwrap(), second);
+            assert_eq!(second_ref.R()[..], second.R.to_bytes(true)[..]);
+            assert!(borrowed::verify_commitment(first_ref, second_ref).is_ok());
+            assert_eq!(
+                borrowed::verify_commitment_with_sid(first_ref, second_ref, b"sid"),
+                Err(Error::InvalidCom)
+            );
+
+            let (_, first, second) = aggsig::create_ephemeral_key_and_commit_with_sid_rng(
+                &keys,
+                b"borrowed",
+                b"sid",
+                &mut rng,
+            )
+            .unwrap();
+            let (first_bytes, second_bytes) =
+                (first.to_bytes().unwrap(), second.to_bytes().unwrap());
+            assert!(borrowed::verify_commitment_with_sid(
+                (&first_bytes).into(),
+                (&second_bytes).into(),
+                b"sid"
+            )
+            .is_ok());
+
+            let (_, first, second) = aggsig::create_ephemeral_key_and_commit_with_domain_rng(
+                &keys,
+                b"borrowed",
+                &domain,
+                &mut rng,
+            )
+            .unwrap();
+            let (first_bytes, second_bytes) =
+                (first.to_bytes().unwrap(), second.to_bytes().unwrap());
+            assert!(borrowed::verify_commitment_with_domain(
+                (&first_bytes).into(),
+                (&second_bytes).into(),
+                &domain
+            )
+            .is_ok());
+            assert_eq!(
+                borrowed::verify_commitment((&first_bytes).into(), (&second_bytes).into()),
+                Err(Error::InvalidCom)
+            );
+
+            let (_, first, second) = aggsig::create_ephemeral_key_and_commit_with_domain_rng(
+                &keys,
+                b"borrowed",
+                &DomainConfig::legacy(),
+                &mut rng,
+            )
+            .unwrap();
+            let (first_bytes, second_bytes) =
+                (first.to_bytes().unwrap(), second.to_bytes().unwrap());
+            assert!(borrowed::verify_commitment_with_domain(
+                (&first_bytes).into(),
+                (&second_bytes).into(),
+                &DomainConfig::legacy()
+            )
+            .is_ok());
+
+            let (_, first, second) = aggsig::create_ephemeral_key_and_commit_with_digest_rng::<
+                Sha3_512,
+            >(&keys, b"borrowed", &mut rng)
+            .unwrap();
+            let (first_bytes, second_bytes) =
+                (first.to_bytes().unwrap(), second.to_bytes().unwrap());
+            assert!(borrowed::verify_commitment_with_digest::<Sha3_512>(
+                (&first_bytes).into(),
+                (&second_bytes).into()
+            )
+            .is_ok());
+        }
+
+        // a blind factor with leading zeros is committed to without them
+        let R = ExpandedKeyPair::create_from_private_key(rng.gen()).public_key;
+        let second = SignSecondMsg {
+            blind_factor: BigInt::from(5),
+            R: R.clone(),
+        };
+        let first = SignFirstMsg {
+            commitment: HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
+                &BigInt::from_bytes(&R.to_bytes(true)),
+                &second.blind_factor,
+            ),
+        };
+        assert!(aggsig::verify_commitment(&first, &second).is_ok());
+        let (first_bytes, mut second_bytes) =
+            (first.to_bytes().unwrap(), second.to_bytes().unwrap());
+        assert!(borrowed::verify_commitment((&first_bytes).into(), (&second_bytes).into()).is_ok());
+
+        second_bytes[63] ^= 1;
+        assert_eq!(
+            borrowed::verify_commitment((&first_bytes).into(), (&second_bytes).into()),
+            Err(Error::InvalidCom)
+        );
+        let mut identity = second_bytes;
+        identity[..32].copy_from_slice(&Point::<Ed25519>::zero().to_bytes(true));
+        assert_eq!(
+            borrowed::verify_commitment((&first_bytes).into(), (&identity).into()),
+            Err(Error::InvalidPoint)
+        );
+        assert_eq!(
+            SignFirstMsgRef::from_bytes(&first_bytes[1..]),
+            Err(Error::InvalidMessage)
+        );
+        assert_eq!(
+            SignSecondMsgRef::from_bytes(&[0u8; 65]),
+            Err(Error::InvalidMessage)
+        );
+
+        // borrowed from the input with serde, which JSON can't do
+        let first_ref = SignFirstMsgRef::deserialize(BorrowedBytesDeserializer::<ValueError>::new(
+            &first_bytes,
+        ))
+        .unwrap();
+        assert!(core::ptr::eq(first_ref.commitment(), &first_bytes));
+        assert!(
+            SignSecondMsgRef::deserialize(BorrowedBytesDeserializer::<ValueError>::new(
+                &first_bytes[..32]
+            ))
+            .is_err()
+        );
+        let json = serde_json::to_string(&first_ref).unwrap();
+        assert!(serde_json::from_str::<SignFirstMsgRef>(&json).is_err());
+    }
+
+    #[test]
+    fn test_state_machine_signing() {
+        let message = b"state machine";
+        let keys: Vec<_> = (0..3).map(|_| ExpandedKeyPair::create()).collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let mut parties: Vec<_> = keys
+            .into_iter()
+            .zip(1..)
+            .map(|(keys, i)| AggSigStateMachine::new(keys, &pks, i, message).unwrap())
+            .collect();
+
+        run_state_machines(&mut parties, |_| {}).unwrap();
+        let apk = parties[0].aggregated_public_key().clone();
+        for party in &mut parties {
+            assert!(party.is_finished());
+            let signature = party.pick_output().unwrap();
+            assert!(verify_dalek(&apk, &signature, message));
+            assert!(party.pick_output().is_none());
+        }
+    }
+
+    #[test]
+    fn test_commitment_bound_to_sid() {
+        let mut rng = deterministic_fast_rand("test_commitment_bound_to_sid", None);
+        let keys = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let (_, sign_first_msg, sign_second_msg) =
+            aggsig::create_ephemeral_key_and_commit_with_sid_rng(
+                &keys,
+                b"message",
+                b"session 1",
+                &mut rng,
+            )
+            .unwrap();
+
+        assert!(aggsig::verify_commitment_with_sid(
+            &sign_first_msg,
+            &sign_second_msg,
+            b"session 1"
+        )
+        .is_ok());
+        assert_eq!(
+            aggsig::verify_commitment_with_sid(&sign_first_msg, &sign_second_msg, b"session 2"),
+            Err(Error::InvalidCom)
+        );
+        assert_eq!(
+            aggsig::verify_commitment(&sign_first_msg, &sign_second_msg),
+            Err(Error::InvalidCom)
+        );
+
+        // signing with a session id still produces standard signatures
+        let message = b"message";
+        let keys: Vec<_> = (0..2).map(|_| ExpandedKeyPair::create()).collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let mut parties: Vec<_> = keys
+            .into_iter()
+            .zip(1..)
+            .map(|(keys, i)| {
+                AggSigStateMachine::new_with_sid(keys, &pks, i, message, b"session 1").unwrap()
+            })
+            .collect();
+        run_state_machines(&mut parties, |_| {}).unwrap();
+        let signature = parties[0].pick_output().unwrap();
+        assert!(verify_dalek(
+            parties[0].aggregated_public_key(),
+            &signature,
+            message
+        ));
+    }
+
+    #[test]
+    fn test_state_machine_reject_bad_reveal() {
+        let message = b"state machine";
+        let keys: Vec<_> = (0..3).map(|_| ExpandedKeyPair::create()).collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let mut parties: Vec<_> = keys
+            .into_iter()
+            .zip(1..)
+            .map(|(keys, i)| AggSigStateMachine::new(keys, &pks, i, message).unwrap())
+            .collect();
+
+        // party 2 reveals a different R than the one it committed to
+        let result = run_state_machines(&mut parties, |msg| {
+            if let AggSigMessage::Reveal(reveal) = &mut msg.body {
+                if msg.sender == 2 {
+                    reveal.R = &reveal.R + Point::generator();
+                }
+            }
+        });
+        assert_eq!(result, Err(Error::InvalidCommitment { party: 2 }));
+        assert!(!parties[0].is_finished());
+        assert!(!parties[0].wants_to_proceed());
+
+        let unknown_sender = Msg {
+            sender: 0,
+            receiver: None,
+            body: AggSigMessage::PartialSignature(aggsig::sign_single(
+                message,
+                &ExpandedKeyPair::create(),
+            )),
+        };
+        assert_eq!(
+            parties[0].handle_incoming(unknown_sender),
+            Err(Error::RoundMismatch)
+        );
+    }
+
+    #[test]
+    fn test_identify_faulty_parties() {
+        let mut rng = deterministic_fast_rand("test_identify_faulty_parties", None);
+        let message = b"abort";
+        let keys: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let (ephemeral_keys, sign_first_msgs, mut sign_second_msgs): (Vec<_>, Vec<_>, Vec<_>) =
+            keys.iter()
+                .map(|k| aggsig::create_ephemeral_key_and_commit_rng(k, message, &mut rng).unwrap())
+                .multiunzip();
+        let Rs: Vec<_> = sign_second_msgs.iter().map(|msg| msg.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let mut partial_sigs: Vec<_> = keys
+            .iter()
+            .zip(ephemeral_keys)
+            .enumerate()
+            .map(|(i, (k, eph))| {
+                let key_agg = KeyAgg::key_aggregation_n(&pks, i);
+                aggsig::partial_sign(eph, k, &key_agg.hash, &R_tot, &key_agg.apk, message)
+            })
+            .collect();
+        let identify = |first: &[SignFirstMsg], second: &[SignSecondMsg], sigs: &[Signature]| {
+            aggsig::identify_faulty_parties(&pks, first, second, sigs, message, None).unwrap()
+        };
+        assert!(identify(&sign_first_msgs, &sign_second_msgs, &partial_sigs).is_empty());
+
+        // party 2 sends a bad partial signature
+        partial_sigs[2].s = &partial_sigs[2].s + Scalar::from(1);
+        assert!(aggsig::add_signature_parts(&partial_sigs)
+            .unwrap()
+            .verify(message, &KeyAgg::key_aggregation_n(&pks, 0).apk)
+            .is_err());
+        assert_eq!(
+            identify(&sign_first_msgs, &sign_second_msgs, &partial_sigs),
+            vec![2]
+        );
+
+        // party 1 opens its commitment to another R
+        sign_second_msgs[1].R = &sign_second_msgs[1].R + Point::generator();
+        assert_eq!(identify(&sign_first_msgs, &sign_second_msgs, &[]), vec![1]);
+        assert_eq!(
+            aggsig::identify_faulty_parties(
+                &pks,
+                &sign_first_msgs,
+                &sign_second_msgs[..2],
+                &[],
+                message,
+                None
+            ),
+            Err(Error::RoundMismatch)
+        );
+    }
+
+    #[test]
+    fn test_verify_commitments() {
+        let mut rng = deterministic_fast_rand("test_verify_commitments", None);
+        let keys: Vec<_> = (0..4)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let (_, first_msgs, mut second_msgs): (Vec<_>, Vec<_>, Vec<_>) = keys
+            .iter()
+            .map(|k| aggsig::create_ephemeral_key_and_commit_rng(k, b"blame", &mut rng).unwrap())
+            .multiunzip();
+        let Rs: Vec<_> = second_msgs.iter().map(|msg| msg.R.clone()).collect();
+        assert_eq!(
+            aggsig::verify_commitments(&first_msgs, &second_msgs),
+            Ok(Rs)
+        );
+
+        second_msgs[1].R = &second_msgs[1].R + Point::generator();
+        second_msgs[3].blind_factor = BigInt::from(1);
+        assert_eq!(
+            aggsig::verify_commitments(&first_msgs, &second_msgs),
+            Err(vec![1, 3])
+        );
+        // party 3's opening is missing
+        assert_eq!(
+            aggsig::verify_commitments(&first_msgs, &second_msgs[..3]),
+            Err(vec![1, 3])
+        );
+    }
+
+    #[test]
+    fn test_reject_identity_points() {
+        let mut rng = deterministic_fast_rand("test_reject_identity_points", None);
+        let keys = ExpandedKeyPair::create_from_private_key(rng.gen());
+        // a correct opening of a commitment to the identity
+        let (commitment, blind_factor) = HashCommitment::<Sha512>::create_commitment(
+            &BigInt::from_bytes(&Point::<Ed25519>::zero().to_bytes(true)),
+        );
+        let identity_first_msg = SignFirstMsg { commitment };
+        let identity_second_msg = SignSecondMsg {
+            R: Point::zero(),
+            blind_factor,
+        };
+        assert_eq!(
+            aggsig::verify_commitment(&identity_first_msg, &identity_second_msg),
+            Err(Error::InvalidPoint)
+        );
+        let (_, first_msg, second_msg) =
+            aggsig::create_ephemeral_key_and_commit_rng(&keys, b"identity", &mut rng).unwrap();
+        assert_eq!(
+            aggsig::verify_commitments(
+                &[first_msg, identity_first_msg],
+                &[second_msg, identity_second_msg]
+            ),
+            Err(vec![1])
+        );
+
+        let pks = [keys.public_key.clone(), Point::zero()];
+        assert_eq!(
+            AggregatedKey::new_weighted(&pks, &[1, 1]),
+            Err(Error::InvalidKey)
+        );
+        assert_eq!(AggregatedKey::new_checked(&pks), Err(Error::InvalidKey));
+        assert_eq!(
+            KeyAgg::key_aggregation_n_checked(&pks, 0).err(),
+            Some(Error::InvalidKey)
+        );
+        assert_eq!(AggregatedKey::new_checked(&[]), Err(Error::InvalidKey));
+        assert_eq!(
+            KeyAgg::key_aggregation_n_checked(&pks[..1], 1).err(),
+            Some(Error::InvalidKey)
+        );
+        assert_eq!(
+            AggregatedKey::new_checked(&pks[..1]).unwrap(),
+            AggregatedKey::new(&pks[..1])
+        );
+        assert_eq!(
+            KeySet::builder().extend(pks.iter().cloned()).build().err(),
+            Some(Error::InvalidKey)
+        );
+        assert_eq!(
+            AwaitingCommitments::new_rng(&keys, &pks, 0, b"identity", &mut rng).err(),
+            Some(Error::InvalidKey)
+        );
+        assert_eq!(
+            AggSigStateMachine::new(keys.clone(), &pks, 1, b"identity").err(),
+            Some(Error::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_state_machine_blame_bad_partial_sig() {
+        let message = b"state machine";
+        let keys: Vec<_> = (0..3).map(|_| ExpandedKeyPair::create()).collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let mut parties: Vec<_> = keys
+            .into_iter()
+            .zip(1..)
+            .map(|(keys, i)| AggSigStateMachine::new(keys, &pks, i, message).unwrap())
+            .collect();
+
+        let result = run_state_machines(&mut parties, |msg| {
+            if let AggSigMessage::PartialSignature(partial_sig) = &mut msg.body {
+                if msg.sender == 3 {
+                    partial_sig.s = &partial_sig.s + Scalar::from(1);
+                }
+            }
+        });
+        assert_eq!(result, Err(Error::InvalidPartialSignature { party: 3 }));
+    }
+
+    // delivers every queued message to everyone else until no party can proceed,
+    // `tamper` can modify messages in flight.
+    fn run_state_machines(
+        parties: &mut [AggSigStateMachine],
+        mut tamper: impl FnMut(&mut Msg<AggSigMessage>),
+    ) -> Result<(), Error> {
+        while parties.iter().any(|party| party.wants_to_proceed()) {
+            for party in parties.iter_mut().filter(|party| party.wants_to_proceed()) {
+                party.proceed()?;
+            }
+            let messages: Vec<_> = parties
+                .iter_mut()
+                .flat_map(|party| party.message_queue().drain(..).collect::<Vec<_>>())
+                .collect();
+            for mut msg in messages {
+                tamper(&mut msg);
+                for party in parties.iter_mut().filter(|p| p.party_ind() != msg.sender) {
+                    party.handle_incoming(msg.clone())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_adaptor_signature() {
+        let mut rng = deterministic_fast_rand("test_adaptor_signature", None);
+        let message = b"atomic swap";
+        let t = Scalar::<Ed25519>::random();
+        let T = Point::generator() * &t;
+
+        let keys: Vec<_> = (0..2)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let ephemeral_keys: Vec<_> = keys
+            .iter()
+            .map(|k| {
+                aggsig::create_ephemeral_key_and_commit_rng(k, message, &mut rng)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|eph| eph.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let apk = KeyAgg::key_aggregation_n(&pks, 0).apk;
+        let partial_sigs: Vec<_> = keys
+            .iter()
+            .zip(ephemeral_keys)
+            .enumerate()
+            .map(|(i, (k, eph))| {
+                let key_agg = KeyAgg::key_aggregation_n(&pks, i);
+                aggsig::partial_sign_adaptor(
+                    eph,
+                    k,
+                    &key_agg.hash,
+                    &R_tot,
+                    &T,
+                    &key_agg.apk,
+                    message,
+                )
+            })
+            .collect();
+
+        let pre_signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+        assert!(aggsig::verify_pre_signature(&pre_signature, message, &T, &apk).is_ok());
+        assert!(aggsig::verify_pre_signature(&pre_signature, message, &R_tot, &apk).is_err());
+        assert!(pre_signature.verify(message, &apk).is_err());
+
+        let signature = aggsig::adapt(&pre_signature, &t);
+        assert!(verify_dalek(&apk, &signature, message));
+        assert_eq!(
+            aggsig::extract_secret(&pre_signature, &signature).unwrap(),
+            t
+        );
+        let other = aggsig::sign_single(message, &keys[0]);
+        assert_eq!(
+            aggsig::extract_secret(&pre_signature, &other),
+            Err(Error::InvalidSig)
+        );
+    }
+
+    #[test]
+    fn test_nonce_modes() {
+        let mut rng = deterministic_fast_rand("test_nonce_modes", None);
+        let keys = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let R = |message: &[u8], mode| {
+            aggsig::create_ephemeral_key_and_commit_with_mode(&keys, message, mode)
+                .unwrap()
+                .0
+                .R
+        };
+
+        assert_eq!(
+            R(b"message", NonceMode::CounterBased(1)),
+            R(b"message", NonceMode::CounterBased(1))
+        );
+        assert_ne!(
+            R(b"message", NonceMode::CounterBased(1)),
+            R(b"message", NonceMode::CounterBased(2))
+        );
+        assert_ne!(
+            R(b"message", NonceMode::CounterBased(1)),
+            R(b"messagf", NonceMode::CounterBased(1))
+        );
+        let mut hedged = || {
+            aggsig::create_ephemeral_key_and_commit_with_mode(
+                &keys,
+                b"message",
+                NonceMode::Hedged(&mut rng),
+            )
+            .unwrap()
+            .0
+            .R
+        };
+        assert_ne!(hedged(), hedged());
+
+        // every mode signs normally
+        for mode in [NonceMode::CounterBased(7), NonceMode::Hedged(&mut rng)] {
+            let message = b"message";
+            let (eph, _, _) =
+                aggsig::create_ephemeral_key_and_commit_with_mode(&keys, message, mode).unwrap();
+            let key_agg = KeyAgg::key_aggregation_n(std::slice::from_ref(&keys.public_key), 0);
+            let R = eph.R.clone();
+            let signature =
+                aggsig::partial_sign(eph, &keys, &key_agg.hash, &R, &key_agg.apk, message);
+            assert!(verify_dalek(&key_agg.apk, &signature, message));
+        }
+    }
+
+    #[test]
+    fn test_aggregated_key_large_committee() {
+        // more keys than one chunk of the parallel multi-scalar multiplication
+        let mut rng = deterministic_fast_rand("test_aggregated_key_large_committee", None);
+        let pks: Vec<_> = (0..300)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()).public_key)
+            .collect();
+        let aggregated_key = AggregatedKey::new(&pks);
+        let apk = pks
+            .iter()
+            .zip(&aggregated_key.coefficients)
+            .fold(Point::zero(), |sum, (pk, a)| sum + pk * a);
+        assert_eq!(aggregated_key.apk, apk);
+        assert_eq!(aggregated_key.coefficients.len(), pks.len());
+
+        let weights: Vec<u64> = (1..=300).collect();
+        let weighted = AggregatedKey::new_weighted(&pks, &weights).unwrap();
+        let apk = pks
+            .iter()
+            .zip(&weighted.coefficients)
+            .fold(Point::zero(), |sum, (pk, a)| sum + pk * a);
+        assert_eq!(weighted.apk, apk);
+    }
+
+    #[test]
+    fn test_hashed_key_list_aggregation() {
+        use curv::cryptographic_primitives::hashing::DigestExt;
+
+        let mut rng = deterministic_fast_rand("test_hashed_key_list_aggregation", None);
+        let message = b"hashed key list";
+        let keys: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        // the default coefficients are H(1 || pk || pk_1 || ... || pk_n), as they always were
+        let coefficients: Vec<_> = pks
+            .iter()
+            .map(|pk| {
+                pks.iter()
+                    .fold(
+                        Sha512::new().chain([1]).chain(&*pk.to_bytes(true)),
+                        |h, pk| h.chain(&*pk.to_bytes(true)),
+                    )
+                    .result_scalar()
+            })
+            .collect();
+        let aggregated_key = AggregatedKey::new(&pks);
+        assert_eq!(aggregated_key.coefficients, coefficients);
+        assert_eq!(
+            AggregatedKey::new_with_domain(&pks, &DomainConfig::legacy()),
+            aggregated_key
+        );
+
+        // H(1 || L || pk) with the key list hashed once
+        let pks_hash = pks
+            .iter()
+            .fold(Sha512::new(), |h, pk| h.chain(&*pk.to_bytes(true)))
+            .finalize();
+        let coefficients: Vec<_> = pks
+            .iter()
+            .map(|pk| {
+                Sha512::new()
+                    .chain([1])
+                    .chain(pks_hash)
+                    .chain(&*pk.to_bytes(true))
+                    .result_scalar()
+            })
+            .collect();
+        let hashed = AggregatedKey::new_hashed_key_list(&pks);
+        assert_eq!(hashed.coefficients, coefficients);
+        assert_ne!(hashed.apk, aggregated_key.apk);
+
+        let ephemeral_keys: Vec<_> = keys
+            .iter()
+            .map(|k| {
+                aggsig::create_ephemeral_key_and_commit_rng(k, message, &mut rng)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|e| e.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = ephemeral_keys
+            .into_iter()
+            .zip(&keys)
+            .enumerate()
+            .map(|(i, (eph, k))| {
+                let key_agg = KeyAgg::key_aggregation_n_hashed_key_list(&pks, i);
+                assert_eq!(key_agg.apk, hashed.apk);
+                aggsig::partial_sign(eph, k, &key_agg.hash, &R_tot, &key_agg.apk, message)
+            })
+            .collect();
+        let signature = hashed
+            .add_verified_signature_parts(&partial_sigs, message, &Rs, &pks)
+            .unwrap();
+        assert!(verify_dalek(&hashed.apk, &signature, message));
+
+        // configs serialized before `hashed_key_list` existed still deserialize
+        let old_json =
+            r#"{"key_aggregation":[],"nonce":[],"commitment":[],"legacy_commitment":true}"#;
+        let old_config = serde_json::from_str::<DomainConfig>(old_json).unwrap();
+        assert!(old_config.legacy_commitment && !old_config.hashed_key_list);
+    }
+
+    #[test]
+    fn test_aggregated_key() {
+        let mut rng = deterministic_fast_rand("test_aggregated_key", None);
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let keys: Vec<_> = (0..4)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let aggregated_key = AggregatedKey::new(&pks);
+        for i in 0..pks.len() {
+            let key_agg = KeyAgg::key_aggregation_n(&pks, i);
+            assert_eq!(key_agg.apk, aggregated_key.apk);
+            assert_eq!(key_agg.hash, aggregated_key.coefficients[i]);
+        }
+        let json = serde_json::to_string(&aggregated_key).unwrap();
+        assert_eq!(
+            serde_json::from_str::<AggregatedKey>(&json).unwrap(),
+            aggregated_key
+        );
+        let mut reordered = pks.clone();
+        reordered.swap(0, 1);
+        assert_ne!(
+            AggregatedKey::new(&reordered).pks_hash,
+            aggregated_key.pks_hash
+        );
+
+        // everyone's partial signature can be checked with the one aggregation
+        let ephemeral_keys: Vec<_> = keys
+            .iter()
+            .map(|k| {
+                aggsig::create_ephemeral_key_and_commit_rng(k, &message, &mut rng)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|e| e.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        for (i, (eph, k)) in ephemeral_keys.into_iter().zip(&keys).enumerate() {
+            let key_agg = aggregated_key.key_agg(i);
+            let R = eph.R.clone();
+            let sig = aggsig::partial_sign(eph, k, &key_agg.hash, &R_tot, &key_agg.apk, &message);
+            assert!(aggregated_key
+                .verify_partial_sig(i, &sig, &message, &R, &k.public_key)
+                .is_ok());
+            assert!(aggregated_key
+                .verify_partial_sig((i + 1) % 4, &sig, &message, &R, &k.public_key)
+                .is_err());
+            assert_eq!(
+                aggregated_key.verify_partial_sig(4, &sig, &message, &R, &k.public_key),
+                Err(Error::RoundMismatch)
+            );
+        }
+    }
+
+    #[test]
+    fn test_key_set() {
+        let mut rng = deterministic_fast_rand("test_key_set", None);
+        let pks: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()).public_key)
+            .collect();
+        let reversed: Vec<_> = pks.iter().rev().cloned().collect();
+
+        let key_set = KeySet::builder().extend(pks.clone()).build().unwrap();
+        assert_eq!(key_set.public_keys(), &pks[..]);
+        assert_eq!(key_set.index_of(&pks[2]), Some(2));
+        assert_eq!(key_set.aggregate(), AggregatedKey::new(&pks));
+
+        let sorted = KeySet::builder()
+            .extend(pks.clone())
+            .sorted()
+            .build()
+            .unwrap();
+        let sorted_reversed = KeySet::builder()
+            .extend(reversed.clone())
+            .sorted()
+            .build()
+            .unwrap();
+        assert_eq!(sorted, sorted_reversed);
+        assert_eq!(sorted.aggregate().apk, sorted_reversed.aggregate().apk);
+        assert_ne!(
+            AggregatedKey::new(&pks).apk,
+            AggregatedKey::new(&reversed).apk
+        );
+
+        assert_eq!(KeySet::builder().build(), Err(Error::InvalidKey));
+        assert_eq!(
+            KeySet::builder()
+                .extend(pks.clone())
+                .key(pks[1].clone())
+                .build(),
+            Err(Error::InvalidKey)
+        );
+        assert_eq!(
+            KeySet::builder().extend(pks).key(Point::zero()).build(),
+            Err(Error::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_tweaked_signing() {
+        let mut rng = deterministic_fast_rand("test_tweaked_signing", None);
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let keys: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let tweak = Scalar::random();
+        let key_aggs: Vec<_> = (0..3)
+            .map(|i| KeyAgg::key_aggregation_n(&pks, i).tweak(&tweak).unwrap())
+            .collect();
+        let apk = KeyAgg::key_aggregation_n(&pks, 0).apk;
+        assert_eq!(key_aggs[0].apk, &apk + Point::generator() * &tweak);
+        assert_eq!(key_aggs[0].party_count, 3);
+
+        let (ephemeral_keys, first_msgs, second_msgs): (Vec<_>, Vec<_>, Vec<_>) = keys
+            .iter()
+            .map(|k| aggsig::create_ephemeral_key_and_commit_rng(k, &message, &mut rng).unwrap())
+            .multiunzip();
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|e| e.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = izip!(ephemeral_keys, &keys, &key_aggs)
+            .map(|(eph, k, key_agg)| {
+                aggsig::partial_sign_tweaked(eph, k, key_agg, &R_tot, &message)
+            })
+            .collect();
+        let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+        assert!(signature.verify(&message, &key_aggs[0].apk).is_ok());
+        assert!(signature.verify(&message, &apk).is_err());
+        assert!(verify_dalek(&key_aggs[0].apk, &signature, &message));
+
+        // the honest tweaked partial signatures only verify against the tweaked key
+        let aggregated_key = AggregatedKey::new(&pks);
+        assert_eq!(
+            aggregated_key
+                .add_verified_signature_parts_tweaked(
+                    &key_aggs[1],
+                    &partial_sigs,
+                    &message,
+                    &Rs,
+                    &pks
+                )
+                .unwrap(),
+            signature
+        );
+        assert_eq!(
+            aggregated_key.add_verified_signature_parts(&partial_sigs, &message, &Rs, &pks),
+            Err(Error::InvalidPartialSignature { party: 1 })
+        );
+        let faulty = |partial_sigs: &[Signature]| {
+            aggsig::identify_faulty_parties_tweaked(
+                &pks,
+                &first_msgs,
+                &second_msgs,
+                partial_sigs,
+                &message,
+                None,
+                &tweak,
+            )
+            .unwrap()
+        };
+        assert!(faulty(&partial_sigs).is_empty());
+        let mut cheated = partial_sigs.clone();
+        cheated[2].s = &cheated[2].s + Scalar::from(1);
+        assert_eq!(faulty(&cheated), vec![2]);
+        assert_eq!(
+            aggregated_key.add_verified_signature_parts_tweaked(
+                &key_aggs[0],
+                &cheated,
+                &message,
+                &Rs,
+                &pks
+            ),
+            Err(Error::InvalidPartialSignature { party: 3 })
+        );
+
+        let mut unknown_count = KeyAgg::key_aggregation_n(&pks, 0);
+        unknown_count.party_count = 0;
+        assert_eq!(
+            unknown_count.tweak(&tweak).unwrap_err(),
+            Error::RoundMismatch
+        );
+    }
+
+    #[test]
+    fn test_weighted_signing() {
+        let mut rng = deterministic_fast_rand("test_weighted_signing", None);
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let keys: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let weights = [1, 5, 2];
+        let aggregated_key = AggregatedKey::new_weighted(&pks, &weights).unwrap();
+        assert_ne!(aggregated_key.apk, AggregatedKey::new(&pks).apk);
+        assert!(aggregated_key.verify_weights(&pks, &weights).is_ok());
+        assert_eq!(
+            aggregated_key.verify_weights(&pks, &[1, 2, 5]),
+            Err(Error::InvalidKey)
+        );
+        assert_eq!(
+            AggregatedKey::new_weighted(&pks, &[1, 0, 2]),
+            Err(Error::InvalidKey)
+        );
+        assert_eq!(
+            KeyAgg::key_aggregation_n_weighted(&pks, &[1, 2], 0).unwrap_err(),
+            Error::RoundMismatch
+        );
+
+        let ephemeral_keys: Vec<_> = keys
+            .iter()
+            .map(|k| {
+                aggsig::create_ephemeral_key_and_commit_rng(k, &message, &mut rng)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|e| e.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = ephemeral_keys
+            .into_iter()
+            .zip(&keys)
+            .enumerate()
+            .map(|(i, (eph, k))| {
+                let key_agg = KeyAgg::key_aggregation_n_weighted(&pks, &weights, i).unwrap();
+                assert_eq!(key_agg.hash, aggregated_key.coefficients[i]);
+                aggsig::partial_sign(eph, k, &key_agg.hash, &R_tot, &key_agg.apk, &message)
+            })
+            .collect();
+        let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+        assert!(verify_dalek(&aggregated_key.apk, &signature, &message));
+    }
+
+    #[test]
+    fn test_accountable_signature() {
+        let mut rng = deterministic_fast_rand("test_accountable_signature", None);
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let keys: Vec<_> = (0..10)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let roster: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let signers = [1, 4, 9];
+
+        let pks: Vec<_> = signers.iter().map(|&i| roster[i].clone()).collect();
+        let ephemeral_keys: Vec<_> = signers
+            .iter()
+            .map(|&i| {
+                aggsig::create_ephemeral_key_and_commit_rng(&keys[i], &message, &mut rng)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|e| e.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = signers
+            .iter()
+            .zip(ephemeral_keys)
+            .enumerate()
+            .map(|(j, (&i, eph))| {
+                let key_agg = KeyAgg::key_aggregation_n(&pks, j);
+                aggsig::partial_sign(eph, &keys[i], &key_agg.hash, &R_tot, &key_agg.apk, &message)
+            })
+            .collect();
+        let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+
+        let accountable = AccountableSignature::new(signature, &roster, &signers).unwrap();
+        assert_eq!(accountable.signers, vec![0b0001_0010, 0b0000_0010]);
+        assert_eq!(accountable.signer_keys(&roster).unwrap(), pks);
+        accountable.verify(&message, &roster).unwrap();
+
+        // claiming another signer set, or a roster that doesn't fit the bitmap, fails
+        let mut other = accountable.clone();
+        other.signers[0] ^= 1;
+        assert_eq!(other.verify(&message, &roster), Err(Error::InvalidKey));
+        other.apk = AggregatedKey::new(&other.signer_keys(&roster).unwrap()).apk;
+        assert_eq!(other.verify(&message, &roster), Err(Error::InvalidSig));
+        assert_eq!(
+            accountable.verify(&message, &roster[..8]),
+            Err(Error::RoundMismatch)
+        );
+        assert_eq!(
+            AccountableSignature::new(accountable.signature.clone(), &roster, &[1, 1]),
+            Err(Error::RoundMismatch)
+        );
+        assert_eq!(
+            AccountableSignature::new(accountable.signature, &roster, &[10]),
+            Err(Error::RoundMismatch)
+        );
+    }
+
+    #[test]
+    fn test_child_key_signing() {
+        let mut rng = deterministic_fast_rand("test_child_key_signing", None);
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let keys: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let key_aggs: Vec<_> = (0..3).map(|i| KeyAgg::key_aggregation_n(&pks, i)).collect();
+
+        let root = ChildKey::root(&key_aggs[0].apk, rng.gen());
+        let child = root.derive_path(&[0, 7]).unwrap();
+        assert_eq!(child, root.derive(0).unwrap().derive(7).unwrap());
+        assert_ne!(child.apk, root.derive(7).unwrap().apk);
+        assert_eq!(
+            root.derive(HARDENED).unwrap_err(),
+            Error::InvalidDerivationIndex
+        );
+
+        let ephemeral_keys: Vec<_> = keys
+            .iter()
+            .map(|k| {
+                aggsig::create_ephemeral_key_and_commit_rng(k, &message, &mut rng)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|e| e.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = izip!(ephemeral_keys, &keys, &key_aggs)
+            .map(|(eph, k, key_agg)| {
+                aggsig::partial_sign(eph, k, &key_agg.hash, &R_tot, &child.apk, &message)
+            })
+            .collect();
+        let aggregate = aggsig::add_signature_parts(&partial_sigs).unwrap();
+        assert!(aggregate.verify(&message, &child.apk).is_err());
+
+        let signature = child.tweak_signature(&aggregate, &message);
+        assert!(signature.verify(&message, &child.apk).is_ok());
+        assert!(verify_dalek(&child.apk, &signature, &message));
+    }
+
+    #[test]
+    fn test_R_tot_accumulator() {
+        assert_eq!(aggsig::get_R_tot(&[]), None);
+        assert_eq!(RTotAccumulator::new().R_tot(), None);
+        assert_eq!(
+            aggsig::add_signature_parts(&[]).err(),
+            Some(Error::InvalidSig)
+        );
+
+        let Rs: Vec<_> = (0..3)
+            .map(|_| Point::generator() * Scalar::<Ed25519>::random())
+            .collect();
+        let mut accumulator = RTotAccumulator::new();
+        for R in &Rs {
+            accumulator.add(R);
+        }
+        assert_eq!(accumulator.count(), 3);
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        assert_eq!(accumulator.R_tot(), Some(R_tot.clone()));
+        assert_eq!(R_tot, &Rs[0] + &Rs[1] + &Rs[2]);
+        // any iterator of points
+        assert_eq!(aggsig::get_R_tot(Rs.iter().take(1)), Some(Rs[0].clone()));
+    }
+
+    #[test]
+    fn test_add_signature_parts_reject_mismatched_R() {
+        let mut rng = deterministic_fast_rand("test_add_signature_parts_reject_mismatched_R", None);
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let keypair = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let sig1 = aggsig::sign_single(&message, &keypair);
+        let sig2 = aggsig::sign_single(&message[..3], &keypair);
+        assert_eq!(
+            aggsig::add_signature_parts(&[sig1.clone(), sig1.clone(), sig2]).unwrap_err(),
+            Error::InvalidPartialSignature { party: 3 }
+        );
+        assert_eq!(
+            aggsig::add_signature_parts(&[]).unwrap_err(),
+            Error::InvalidSig
+        );
+        assert_eq!(
+            aggsig::add_signature_parts(std::slice::from_ref(&sig1)).unwrap(),
+            sig1
+        );
+    }
+
+    #[test]
+    fn test_aggregate_and_verify() {
+        let mut rng = deterministic_fast_rand("test_aggregate_and_verify", None);
+        let message = b"all of round 3";
+        let keys: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let aggregated_key = AggregatedKey::new(&pks);
+        let mut ephemeral_keys = Vec::new();
+        let mut first_msgs = Vec::new();
+        let mut second_msgs = Vec::new();
+        for k in &keys {
+            let (eph, first, second) =
+                aggsig::create_ephemeral_key_and_commit_rng(k, message, &mut rng).unwrap();
+            ephemeral_keys.push(eph);
+            first_msgs.push(first);
+            second_msgs.push(second);
+        }
+        let R_tot = aggsig::get_R_tot(second_msgs.iter().map(|msg| &msg.R)).unwrap();
+        let mut partial_sigs: Vec<_> = ephemeral_keys
+            .into_iter()
+            .zip(&keys)
+            .enumerate()
+            .map(|(i, (eph, k))| {
+                let key_agg = aggregated_key.key_agg(i);
+                aggsig::partial_sign(eph, k, &key_agg.hash, &R_tot, &key_agg.apk, message)
+            })
+            .collect();
+        let signature = aggregated_key
+            .aggregate_and_verify(&partial_sigs, &first_msgs, &second_msgs, &pks, message)
+            .unwrap();
+        assert!(verify_dalek(&aggregated_key.apk, &signature, message));
+
+        let mut bad_second_msgs = second_msgs.clone();
+        bad_second_msgs[2].blind_factor = BigInt::from(1);
+        assert_eq!(
+            aggregated_key
+                .aggregate_and_verify(&partial_sigs, &first_msgs, &bad_second_msgs, &pks, message)
+                .err(),
+            Some(Error::InvalidCommitment { party: 3 })
+        );
+        assert_eq!(
+            aggregated_key
+                .aggregate_and_verify(&partial_sigs, &first_msgs[1..], &second_msgs, &pks, message)
+                .err(),
+            Some(Error::RoundMismatch)
+        );
+        partial_sigs[0].s = &partial_sigs[0].s + Scalar::from(1);
+        assert_eq!(
+            aggregated_key
+                .aggregate_and_verify(&partial_sigs, &first_msgs, &second_msgs, &pks, message)
+                .err(),
+            Some(Error::InvalidPartialSignature { party: 1 })
+        );
+    }
+
+    #[test]
+    fn test_add_verified_signature_parts() {
+        let mut rng = deterministic_fast_rand("test_add_verified_signature_parts", None);
+        let message = b"checked before summing";
+        let keys: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let aggregated_key = AggregatedKey::new(&pks);
+        let ephemeral_keys: Vec<_> = keys
+            .iter()
+            .map(|k| {
+                aggsig::create_ephemeral_key_and_commit_rng(k, message, &mut rng)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|e| e.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let mut partial_sigs: Vec<_> = ephemeral_keys
+            .into_iter()
+            .zip(&keys)
+            .enumerate()
+            .map(|(i, (eph, k))| {
+                let key_agg = aggregated_key.key_agg(i);
+                aggsig::partial_sign(eph, k, &key_agg.hash, &R_tot, &key_agg.apk, message)
+            })
+            .collect();
+        let signature = aggregated_key
+            .add_verified_signature_parts(&partial_sigs, message, &Rs, &pks)
+            .unwrap();
+        assert!(verify_dalek(&aggregated_key.apk, &signature, message));
+
+        assert_eq!(
+            aggregated_key
+                .add_verified_signature_parts(&partial_sigs[1..], message, &Rs, &pks)
+                .err(),
+            Some(Error::RoundMismatch)
+        );
+        // a bad share is caught before it ruins the aggregate, which wouldn't notice
+        partial_sigs[1].s = &partial_sigs[1].s + Scalar::from(1);
+        assert!(aggsig::add_signature_parts(&partial_sigs).is_ok());
+        assert_eq!(
+            aggregated_key
+                .add_verified_signature_parts(&partial_sigs, message, &Rs, &pks)
+                .err(),
+            Some(Error::InvalidPartialSignature { party: 2 })
+        );
+        // the first of several bad shares is blamed, also when they are checked in parallel
+        partial_sigs[2].s = &partial_sigs[2].s + Scalar::from(1);
+        assert_eq!(
+            aggregated_key
+                .add_verified_signature_parts(&partial_sigs, message, &Rs, &pks)
+                .err(),
+            Some(Error::InvalidPartialSignature { party: 2 })
+        );
+    }
+
+    #[test]
+    fn test_prepared_apk() {
+        let mut rng = deterministic_fast_rand("test_prepared_apk", None);
+        let message = b"every block";
+        let keys: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let aggregated_key = AggregatedKey::new(&pks);
+        let prepared = aggregated_key.prepare(&pks).unwrap();
+        assert_eq!(prepared.apk().public_key(), &aggregated_key.apk);
+        assert_eq!(
+            aggregated_key.prepare(&pks[1..]).unwrap_err(),
+            Error::RoundMismatch
+        );
+
+        // the same sessions verify with the prepared key as without
+        for _ in 0..2 {
+            let ephemeral_keys: Vec<_> = keys
+                .iter()
+                .map(|k| {
+                    aggsig::create_ephemeral_key_and_commit_rng(k, message, &mut rng)
+                        .unwrap()
+                        .0
+                })
+                .collect();
+            let Rs: Vec<_> = ephemeral_keys.iter().map(|e| e.R.clone()).collect();
+            let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+            let mut partial_sigs: Vec<_> = ephemeral_keys
+                .into_iter()
+                .zip(&keys)
+                .enumerate()
+                .map(|(i, (eph, k))| {
+                    let key_agg = aggregated_key.key_agg(i);
+                    aggsig::partial_sign(eph, k, &key_agg.hash, &R_tot, &key_agg.apk, message)
+                })
+                .collect();
+            for (i, (sig, R)) in partial_sigs.iter().zip(&Rs).enumerate() {
+                prepared.verify_partial_sig(i, sig, message, R).unwrap();
+                aggregated_key
+                    .verify_partial_sig(i, sig, message, R, &pks[i])
+                    .unwrap();
+                // against another party's key or nonce
+                let j = (i + 1) % pks.len();
+                assert_eq!(
+                    prepared.verify_partial_sig(j, sig, message, R).unwrap_err(),
+                    Error::InvalidSig
+                );
+                assert_eq!(
+                    prepared
+                        .verify_partial_sig(i, sig, message, &Rs[j])
+                        .unwrap_err(),
+                    Error::InvalidSig
+                );
+            }
+            assert_eq!(
+                prepared
+                    .verify_partial_sig(3, &partial_sigs[0], message, &Rs[0])
+                    .unwrap_err(),
+                Error::RoundMismatch
+            );
+            let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+            prepared.verify(&signature, message).unwrap();
+            assert!(prepared.verify(&signature, b"another block").is_err());
+
+            partial_sigs[1].s = &partial_sigs[1].s + Scalar::from(1);
+            assert_eq!(
+                prepared
+                    .verify_partial_sig(1, &partial_sigs[1], message, &Rs[1])
+                    .unwrap_err(),
+                Error::InvalidSig
+            );
+        }
+    }
+
+    #[test]
+    fn test_sign_single_reader() {
+        let keys = ExpandedKeyPair::create();
+        let message: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+        let signature = aggsig::sign_single_reader(&mut Cursor::new(&message), &keys).unwrap();
+        assert_eq!(signature, aggsig::sign_single(&message, &keys));
+
+        // from the current position on
+        let mut cursor = Cursor::new(&message);
+        cursor.set_position(10);
+        let signature = aggsig::sign_single_reader(&mut cursor, &keys).unwrap();
+        assert_eq!(signature, aggsig::sign_single(&message[10..], &keys));
+
+        // a message that changes between the reads isn't signed
+        struct Changing(Cursor<Vec<u8>>, bool);
+        impl Read for Changing {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0.read(buf)
+            }
+        }
+        impl Seek for Changing {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                if self.1 {
+                    self.0.get_mut()[0] ^= 1;
+                }
+                self.1 = true;
+                self.0.seek(pos)
+            }
+        }
+        let mut changing = Changing(Cursor::new(message), false);
+        assert_eq!(
+            aggsig::sign_single_reader(&mut changing, &keys)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_partial_sign_reader() {
+        let mut rng = deterministic_fast_rand("test_partial_sign_reader", None);
+        let message: Vec<u8> = (0..100_000).map(|_| rng.gen()).collect();
+        let keys: Vec<_> = (0..2)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let aggregated_key = AggregatedKey::new(&pks);
+        let ephemeral_keys: Vec<_> = keys
+            .iter()
+            .map(|k| {
+                aggsig::create_ephemeral_key_and_commit_rng(k, b"nonce", &mut rng)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|e| e.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = ephemeral_keys
+            .into_iter()
+            .zip(&keys)
+            .enumerate()
+            .map(|(i, (eph, k))| {
+                let key_agg = aggregated_key.key_agg(i);
+                let msg = Cursor::new(&message);
+                aggsig::partial_sign_reader(eph, k, &key_agg.hash, &R_tot, &key_agg.apk, msg)
+                    .unwrap()
+            })
+            .collect();
+        for (i, (sig, R)) in partial_sigs.iter().zip(&Rs).enumerate() {
+            aggregated_key
+                .verify_partial_sig(i, sig, &message, R, &pks[i])
+                .unwrap();
+        }
+        let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+        assert!(verify_dalek(&aggregated_key.apk, &signature, &message));
+        let mut verifier = signature.verifier(&aggregated_key.apk);
+        io::copy(&mut Cursor::new(&message), &mut verifier).unwrap();
+        verifier.finalize().unwrap();
+    }
+
+    #[test]
+    fn test_sign_single_ctx_reject_long_context() {
+        let keypair = ExpandedKeyPair::create();
+        let message: [u8; 4] = [79, 77, 69, 82];
+        let context = [0u8; 256];
+        assert_eq!(
+            aggsig::sign_single_ctx(&message, &context, &keypair).unwrap_err(),
+            Error::InvalidContext
+        );
+        let sig = aggsig::sign_single_ctx(&message, &context[..255], &keypair).unwrap();
+        assert!(sig
+            .verify_ctx(&message, &context[..255], &keypair.public_key)
+            .is_ok());
+        assert!(sig
+            .verify_ctx(&message, &context, &keypair.public_key)
+            .is_err());
+    }
+
+    #[test]
+    fn test_sign_single_ctx_rfc8032_vector() {
+        // RFC8032 section 7.2, Ed25519ctx test vector with context "foo"
+        let priv_dec: [u8; 32] =
+            decode("0305334e381af78f141cb666f6199f57bc3495335a256a95bd2a55bf546663f6")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let expected_pubkey =
+            decode("dfc9425e4f968f7f0c29f0259cf5f9aed6851c2bb4ad8bfb860cfee0ab248292").unwrap();
+        let expected_sig = decode(
+            "55a4cc2f70a54e04288c5f4cd1e45a7bb520b36292911876cada7323198dd87a\
+             8b36950b95130022907a7fb7c4e9b2d5f6cca685a587b4b21f4b888e4e7edb0d",
+        )
+        .unwrap();
+        let message = decode("f726936d19c800494e3fdaff20b276a8").unwrap();
+        let context = b"foo";
+
+        let keypair = ExpandedKeyPair::create_from_private_key(priv_dec);
+        assert_eq!(&*keypair.public_key.to_bytes(true), &expected_pubkey[..]);
+        assert!(
+            Signature::verify_bytes_ctx(&expected_sig, &message, context, &expected_pubkey).is_ok()
+        );
+        assert!(
+            Signature::verify_bytes_ctx(&expected_sig, &message, b"bar", &expected_pubkey).is_err()
+        );
+        assert!(Signature::verify_bytes(&expected_sig, &message, &expected_pubkey).is_err());
+
+        let sig = aggsig::sign_single_ctx(&message, context, &keypair).unwrap();
+        assert!(sig
+            .verify_ctx(&message, context, &keypair.public_key)
+            .is_ok());
+        assert!(sig.verify_ctx(&message, b"", &keypair.public_key).is_err());
+        assert!(sig.verify(&message, &keypair.public_key).is_err());
+    }
+
+    #[test]
+    fn test_multiparty_signing_ctx() {
+        let mut rng = deterministic_fast_rand("test_multiparty_signing_ctx", None);
+        let mut message = [0u8; 32];
+        rng.fill_bytes(&mut message);
+        let context = b"multi-party-eddsa test";
+
+        let keypairs: Vec<_> = (0..2)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let agg_keys: Vec<_> = (0..2).map(|i| KeyAgg::key_aggregation_n(&pks, i)).collect();
+        let ephemeral_keys: Vec<_> = keypairs
+            .iter()
+            .map(|keypair| {
+                aggsig::create_ephemeral_key_and_commit_rng(keypair, &message, &mut rng)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|eph| eph.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = izip!(keypairs.iter(), ephemeral_keys, agg_keys.iter())
+            .map(|(keypair, eph, agg_key)| {
+                aggsig::partial_sign_ctx(
+                    eph,
+                    keypair,
+                    &agg_key.hash,
+                    &R_tot,
+                    &agg_key.apk,
+                    &message,
+                    context,
+                )
+                .unwrap()
+            })
+            .collect();
+        let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+        assert!(signature
+            .verify_ctx(&message, context, &agg_keys[0].apk)
+            .is_ok());
+        assert!(signature.verify(&message, &agg_keys[0].apk).is_err());
+    }
+
+    #[test]
+    fn test_multiparty_signing_ph() {
+        let mut rng = deterministic_fast_rand("test_multiparty_signing_ph", None);
+        let mut message = [0u8; 1024];
+        rng.fill_bytes(&mut message);
+        // stream the message into the hasher
+        let mut prehashed = [0u8; 64];
+        prehashed.copy_from_slice(
+            &message
+                .chunks(100)
+                .fold(Sha512::new(), |hasher, chunk| hasher.chain(chunk))
+                .finalize(),
+        );
+
+        let keypairs: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let agg_keys: Vec<_> = (0..3).map(|i| KeyAgg::key_aggregation_n(&pks, i)).collect();
+        let ephemeral_keys: Vec<_> = keypairs
+            .iter()
+            .map(|keypair| {
+                aggsig::create_ephemeral_key_and_commit_rng(keypair, &prehashed, &mut rng)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|eph| eph.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = izip!(keypairs.iter(), ephemeral_keys, agg_keys.iter())
+            .map(|(keypair, eph, agg_key)| {
+                aggsig::partial_sign_ph(
+                    eph,
+                    keypair,
+                    &agg_key.hash,
+                    &R_tot,
+                    &agg_key.apk,
+                    &prehashed,
+                )
+            })
+            .collect();
+        let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+        assert!(signature.verify_ph(&prehashed, &agg_keys[0].apk).is_ok());
+        assert!(signature.verify(&message, &agg_keys[0].apk).is_err());
+
+        for (i, (sig, agg_key)) in partial_sigs.iter().zip(&agg_keys).enumerate() {
+            assert!(aggsig::verify_partial_sig_ph(
+                sig,
+                &prehashed,
+                &agg_key.hash,
+                &Rs[i],
+                &pks[i],
+                &agg_key.apk
+            )
+            .is_ok());
+            assert!(aggsig::verify_partial_sig(
+                sig,
+                &prehashed,
+                &agg_key.hash,
+                &Rs[i],
+                &pks[i],
+                &agg_key.apk
+            )
+            .is_err());
+        }
+        let aggregated_key = AggregatedKey::new(&pks);
+        assert_eq!(
+            aggregated_key
+                .add_verified_signature_parts_ph(&partial_sigs, &prehashed, &Rs, &pks)
+                .unwrap(),
+            signature
+        );
+        let mut bad_partial_sigs = partial_sigs.clone();
+        bad_partial_sigs[2].s = &bad_partial_sigs[2].s + Scalar::from(1);
+        assert_eq!(
+            aggregated_key
+                .add_verified_signature_parts_ph(&bad_partial_sigs, &prehashed, &Rs, &pks)
+                .err(),
+            Some(Error::InvalidPartialSignature { party: 3 })
+        );
+        assert_eq!(
+            aggregated_key
+                .add_verified_signature_parts(&partial_sigs, &prehashed, &Rs, &pks)
+                .err(),
+            Some(Error::InvalidPartialSignature { party: 1 })
+        );
+    }
+
+    /// Keeps its nonces to itself like a hardware signer would, `faulty` signs the wrong message.
+    struct RemoteSigner {
+        keys: ExpandedKeyPair,
+        nonces: RefCell<HashMap<Vec<u8>, EphemeralKey>>,
+        faulty: bool,
+    }
+
+    impl PartialSigner for RemoteSigner {
+        fn public_key(&self) -> Point<Ed25519> {
+            self.keys.public_key.clone()
+        }
+
+        fn commit(
+            &self,
+            message: &[u8],
+        ) -> Result<(NonceHandle, SignFirstMsg, SignSecondMsg), Error> {
+            let (eph, first, second) =
+                aggsig::create_ephemeral_key_and_commit(&self.keys, message)?;
+            let nonce = NonceHandle::new(eph.R.clone(), Vec::new());
+            self.nonces
+                .borrow_mut()
+                .insert(eph.R.to_bytes(true).to_vec(), eph);
+            Ok((nonce, first, second))
+        }
+
+        fn partial_sign(
+            &self,
+            nonce: NonceHandle,
+            a: &Scalar<Ed25519>,
+            R_tot: &Point<Ed25519>,
+            agg_pubkey: &Point<Ed25519>,
+            message: &[u8],
+        ) -> Result<Signature, Error> {
+            let eph = self
+                .nonces
+                .borrow_mut()
+                .remove(&nonce.R.to_bytes(true).to_vec())
+                .ok_or(Error::InvalidKey)?;
+            let message = if self.faulty { &b"other"[..] } else { message };
+            Ok(aggsig::partial_sign(
+                eph, &self.keys, a, R_tot, agg_pubkey, message,
+            ))
+        }
+    }
+
+    #[test]
+    fn test_partial_signer() {
+        let mut rng = deterministic_fast_rand("test_partial_signer", None);
+        let message = b"signed with a remote share";
+        for faulty in [false, true] {
+            let local = MemorySigner::new(ExpandedKeyPair::create_from_private_key(rng.gen()));
+            let remote = RemoteSigner {
+                keys: ExpandedKeyPair::create_from_private_key(rng.gen()),
+                nonces: RefCell::new(HashMap::new()),
+                faulty,
+            };
+            let signers: [&dyn PartialSigner; 2] = [&local, &remote];
+            let pks: Vec<_> = signers.iter().map(|s| s.public_key()).collect();
+            let agg_keys: Vec<_> = (0..2).map(|i| KeyAgg::key_aggregation_n(&pks, i)).collect();
+
+            let mut commitments: Vec<_> = signers
+                .iter()
+                .map(|s| aggsig::create_ephemeral_key_and_commit_with_signer(*s, message).unwrap())
+                .collect();
+            // both signers keep their nonces
+            assert!(commitments
+                .iter()
+                .all(|(nonce, _, _)| nonce.secret().is_empty()));
+            let Rs: Vec<_> = commitments.iter().map(|(n, _, _)| n.R.clone()).collect();
+            let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+            let results: Vec<_> = izip!(signers.iter(), commitments.drain(..), agg_keys.iter())
+                .map(|(s, (nonce, _, _), agg_key)| {
+                    aggsig::partial_sign_with_signer(
+                        *s,
+                        nonce,
+                        &agg_key.hash,
+                        &R_tot,
+                        &agg_key.apk,
+                        message,
+                    )
+                })
+                .collect();
+            assert!(results[0].is_ok());
+            // a handle can't be cloned, and one made up for the same nonce is refused
+            assert_eq!(
+                aggsig::partial_sign_with_signer(
+                    &local,
+                    NonceHandle::new(Rs[0].clone(), Vec::new()),
+                    &agg_keys[0].hash,
+                    &R_tot,
+                    &agg_keys[0].apk,
+                    b"another message",
+                ),
+                Err(Error::InvalidCom)
+            );
+            if faulty {
+                assert_eq!(results[1], Err(Error::InvalidSig));
+                continue;
+            }
+            let partial_sigs: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+            let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+            assert!(signature.verify(message, &agg_keys[0].apk).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_nonce_pool() {
+        let mut rng = deterministic_fast_rand("test_nonce_pool", None);
+        let keypairs: Vec<_> = (0..2)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let agg_keys: Vec<_> = (0..2).map(|i| KeyAgg::key_aggregation_n(&pks, i)).collect();
+        let mut pools: Vec<_> = (0..2)
+            .map(|_| NoncePool::new(MemoryNonceStore::default()))
+            .collect();
+        // published ahead of time
+        let published: Vec<_> = pools
+            .iter_mut()
+            .zip(&keypairs)
+            .map(|(pool, keys)| pool.generate_rng(keys, 3, &mut rng).unwrap())
+            .collect();
+        assert_eq!(pools[0].len(), 3);
+        for (first, second) in published.iter().flatten() {
+            aggsig::verify_commitment(first, second).unwrap();
+        }
+
+        let message = b"signed with a pooled nonce";
+        let Rs: Vec<_> = published.iter().map(|p| p[1].1.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        // saved before signing, as on a crash right after it
+        let saved = serde_json::to_string(pools[0].nonces()).unwrap();
+        let partial_sigs: Vec<_> = izip!(pools.iter_mut(), &Rs, &keypairs, &agg_keys)
+            .map(|(pool, R, keys, agg_key)| {
+                aggsig::partial_sign_with_pool(
+                    pool,
+                    R,
+                    keys,
+                    &agg_key.hash,
+                    &R_tot,
+                    &agg_key.apk,
+                    message,
+                )
+                .unwrap()
+            })
+            .collect();
+        let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+        assert!(verify_dalek(&agg_keys[0].apk, &signature, message));
+        assert_eq!(pools[0].len(), 2);
+
+        // the nonce is gone from the pool
+        let sign_again = |pool: &mut NoncePool<MemoryNonceStore>| {
+            aggsig::partial_sign_with_pool(
+                pool,
+                &Rs[0],
+                &keypairs[0],
+                &agg_keys[0].hash,
+                &R_tot,
+                &agg_keys[0].apk,
+                b"another message",
+            )
+        };
+        assert_eq!(sign_again(&mut pools[0]), Err(Error::InvalidCom));
+        // and a pool restored from before signing refuses it, because the store marked it
+        let mut restored = NoncePool::restore(
+            pools[0].store().clone(),
+            serde_json::from_str(&saved).unwrap(),
+        );
+        assert_eq!(restored.len(), 3);
+        assert_eq!(sign_again(&mut restored), Err(Error::InvalidCom));
+        assert_eq!(restored.len(), 2);
+    }
+
+    #[test]
+    fn test_typestate_signing() {
+        let mut rng = deterministic_fast_rand("test_typestate_signing", None);
+        let message = b"typestate";
+        let keys: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        assert!(AwaitingCommitments::new_rng(&keys[0], &pks, 1, message, &mut rng).is_err());
+        let too_many = vec![keys[0].public_key.clone(); usize::from(u16::MAX) + 1];
+        assert_eq!(
+            AwaitingCommitments::new_rng(&keys[0], &too_many, 0, message, &mut rng).err(),
+            Some(Error::RoundMismatch)
+        );
+
+        let (states, commitments): (Vec<_>, Vec<_>) = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| AwaitingCommitments::new_rng(k, &pks, i, message, &mut rng).unwrap())
+            .unzip();
+        let (states, reveals): (Vec<_>, Vec<_>) = states
+            .into_iter()
+            .map(|state| state.receive_commitments(commitments.clone()).unwrap())
+            .unzip();
+        let states: Vec<_> = states
+            .into_iter()
+            .map(|state| state.receive_reveals(&reveals).unwrap())
+            .collect();
+        let partial_sigs: Vec<_> = states
+            .into_iter()
+            .map(|state| state.partial_sign())
+            .collect();
+        let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+        assert!(verify_dalek(
+            &KeyAgg::key_aggregation_n(&pks, 0).apk,
+            &signature,
+            message
+        ));
+
+        // a missing commitment, ours swapped, and a reveal that doesn't open its commitment
+        let new_state =
+            |rng: &mut _| AwaitingCommitments::new_rng(&keys[0], &pks, 0, message, rng).unwrap();
+        let (state, _) = new_state(&mut rng);
+        assert_eq!(
+            state.receive_commitments(commitments[1..].to_vec()).err(),
+            Some(Error::RoundMismatch)
+        );
+        let (state, _) = new_state(&mut rng);
+        assert_eq!(
+            state.receive_commitments(commitments.clone()).err(),
+            Some(Error::InvalidCom)
+        );
+        let (state, own) = new_state(&mut rng);
+        let mut with_own = commitments.clone();
+        with_own[0] = own;
+        let (state, own_reveal) = state.receive_commitments(with_own).unwrap();
+        let mut bad_reveals = reveals.clone();
+        bad_reveals[0] = own_reveal;
+        bad_reveals[2].R = &bad_reveals[2].R + Point::generator();
+        assert_eq!(
+            state.receive_reveals(&bad_reveals).err(),
+            Some(Error::InvalidCommitment { party: 3 })
+        );
+    }
+
+    #[test]
+    fn test_pop_key_aggregation() {
+        let mut rng = deterministic_fast_rand("test_pop_key_aggregation", None);
+        let message = b"closed signer set";
+        let keypairs: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let proofs: Vec<_> = keypairs.iter().map(ProofOfPossession::prove).collect();
+        let agg_key = AggregatedKey::new_with_pop(&proofs).unwrap();
+        let sum = keypairs
+            .iter()
+            .fold(Point::zero(), |sum, k| sum + &k.public_key);
+        assert_eq!(agg_key.apk, sum);
+
+        let ephemeral_keys: Vec<_> = keypairs
+            .iter()
+            .map(|keypair| {
+                aggsig::create_ephemeral_key_and_commit_rng(keypair, message, &mut rng)
+                    .unwrap()
+                    .0
+            })
+            .collect();
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|eph| eph.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = keypairs
+            .iter()
+            .zip(ephemeral_keys)
+            .enumerate()
+            .map(|(i, (keypair, eph))| {
+                let key_agg = KeyAgg::key_aggregation_n_with_pop(&proofs, i).unwrap();
+                let R = eph.R.clone();
+                let partial_sig = aggsig::partial_sign(
+                    eph,
+                    keypair,
+                    &key_agg.hash,
+                    &R_tot,
+                    &key_agg.apk,
+                    message,
+                );
+                assert!(agg_key
+                    .verify_partial_sig(i, &partial_sig, message, &R, &keypair.public_key)
+                    .is_ok());
+                partial_sig
+            })
+            .collect();
+        let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+        assert!(verify_dalek(&agg_key.apk, &signature, message));
+
+        // a rogue key `pk_3 - pk_1 - pk_2` has no proof
+        let mut rogue = proofs.clone();
+        rogue[2].public_key =
+            &keypairs[2].public_key - &keypairs[0].public_key - &keypairs[1].public_key;
+        assert_eq!(
+            AggregatedKey::new_with_pop(&rogue),
+            Err(Error::InvalidProof { party: 3 })
+        );
+        // nor does a standard signature of the key
+        let mut plain = proofs.clone();
+        plain[1].signature =
+            aggsig::sign_single(&keypairs[1].public_key.to_bytes(true), &keypairs[1]);
+        assert_eq!(
+            AggregatedKey::new_with_pop(&plain),
+            Err(Error::InvalidProof { party: 2 })
+        );
+        let duplicate = [proofs[0].clone(), proofs[1].clone(), proofs[0].clone()];
+        assert_eq!(
+            AggregatedKey::new_with_pop(&duplicate),
+            Err(Error::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_signing_with_digest() {
+        let mut rng = deterministic_fast_rand("test_signing_with_digest", None);
+        let message = b"sha3 deployment";
+        let keypairs: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let aggregated_key = AggregatedKey::new(&pks);
+
+        let (ephemeral_keys, first_msgs, second_msgs): (Vec<_>, Vec<_>, Vec<_>) = keypairs
+            .iter()
+            .map(|keypair| {
+                aggsig::create_ephemeral_key_and_commit_with_digest_rng::<Sha3_512>(
+                    keypair, message, &mut rng,
+                )
+                .unwrap()
+            })
+            .multiunzip();
+        for (first_msg, second_msg) in first_msgs.iter().zip(&second_msgs) {
+            assert!(
+                aggsig::verify_commitment_with_digest::<Sha3_512>(first_msg, second_msg).is_ok()
+            );
+            assert_eq!(
+                aggsig::verify_commitment(first_msg, second_msg),
+                Err(Error::InvalidCom)
+            );
+        }
+
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|eph| eph.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = keypairs
+            .iter()
+            .zip(ephemeral_keys)
+            .enumerate()
+            .map(|(i, (keypair, eph))| {
+                let key_agg = aggregated_key.key_agg(i);
+                aggsig::partial_sign_with_digest::<Sha3_512>(
+                    eph,
+                    keypair,
+                    &key_agg.hash,
+                    &R_tot,
+                    &key_agg.apk,
+                    message,
+                )
+            })
+            .collect();
+        let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+        assert!(signature
+            .verify_with_digest::<Sha3_512>(message, &aggregated_key.apk)
+            .is_ok());
+        assert!(signature.verify(message, &aggregated_key.apk).is_err());
+
+        // SHA-512 is the default
+        let single = aggsig::sign_single(message, &keypairs[0]);
+        assert!(single
+            .verify_with_digest::<Sha512>(message, &pks[0])
+            .is_ok());
+        assert!(single
+            .verify_with_digest::<Sha3_512>(message, &pks[0])
+            .is_err());
+    }
+
+    #[test]
+    fn test_domain_config() {
+        let mut rng = deterministic_fast_rand("test_domain_config", None);
+        let message = b"deployment bound";
+        let domain = DomainConfig::new(b"com.example.wallet/v1");
+        let other_domain = DomainConfig::new(b"com.example.exchange/v1");
+
+        let keypairs: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        assert_eq!(
+            AggregatedKey::new_with_domain(&pks, &DomainConfig::default()),
+            AggregatedKey::new(&pks)
+        );
+        let agg_key = AggregatedKey::new_with_domain(&pks, &domain);
+        assert_ne!(agg_key.apk, AggregatedKey::new(&pks).apk);
+        assert_ne!(
+            agg_key.apk,
+            AggregatedKey::new_with_domain(&pks, &other_domain).apk
+        );
+
+        let (ephemeral_keys, first_msgs, second_msgs): (Vec<_>, Vec<_>, Vec<_>) = keypairs
+            .iter()
+            .map(|keypair| {
+                aggsig::create_ephemeral_key_and_commit_with_domain_rng(
+                    keypair, message, &domain, &mut rng,
+                )
+                .unwrap()
+            })
+            .multiunzip();
+        for (first_msg, second_msg) in first_msgs.iter().zip(&second_msgs) {
+            assert!(aggsig::verify_commitment_with_domain(first_msg, second_msg, &domain).is_ok());
+            assert_eq!(
+                aggsig::verify_commitment_with_domain(first_msg, second_msg, &other_domain),
+                Err(Error::InvalidCom)
+            );
+            assert_eq!(
+                aggsig::verify_commitment(first_msg, second_msg),
+                Err(Error::InvalidCom)
+            );
+        }
+
+        // commitments to the y coordinate of R, as before the compressed encoding
+        let legacy = DomainConfig::legacy();
+        let (_, legacy_first_msg, legacy_second_msg) =
+            aggsig::create_ephemeral_key_and_commit_with_domain_rng(
+                &keypairs[0],
+                message,
+                &legacy,
+                &mut rng,
+            )
+            .unwrap();
+        assert_eq!(
+            legacy_first_msg.commitment,
+            HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
+                &legacy_second_msg.R.y_coord().unwrap(),
+                &legacy_second_msg.blind_factor,
+            )
+        );
+        assert!(aggsig::verify_commitment_with_domain(
+            &legacy_first_msg,
+            &legacy_second_msg,
+            &legacy
+        )
+        .is_ok());
+        assert_eq!(
+            aggsig::verify_commitment(&legacy_first_msg, &legacy_second_msg),
+            Err(Error::InvalidCom)
+        );
+        assert_eq!(
+            aggsig::verify_commitment_with_domain(&first_msgs[0], &second_msgs[0], &legacy),
+            Err(Error::InvalidCom)
+        );
+        // configs serialized before `legacy_commitment` existed still deserialize
+        let old_json = r#"{"key_aggregation":[],"nonce":[],"commitment":[]}"#;
+        assert_eq!(
+            serde_json::from_str::<DomainConfig>(old_json).unwrap(),
+            DomainConfig::default()
+        );
+
+        let Rs: Vec<_> = ephemeral_keys.iter().map(|eph| eph.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = keypairs
+            .iter()
+            .zip(ephemeral_keys)
+            .enumerate()
+            .map(|(i, (keypair, eph))| {
+                let key_agg = KeyAgg::key_aggregation_n_with_domain(&pks, i, &domain);
+                aggsig::partial_sign(eph, keypair, &key_agg.hash, &R_tot, &key_agg.apk, message)
+            })
+            .collect();
+        let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+        // only the aggregation is domain separated, the signature is a standard one
+        assert!(verify_dalek(&agg_key.apk, &signature, message));
+    }
+
+    #[cfg(feature = "transcript")]
+    #[test]
+    fn test_multiparty_signing_transcript() {
+        use merlin::Transcript;
+
+        let mut rng = deterministic_fast_rand("test_multiparty_signing_transcript", None);
+        let message = b"bound to the transcript";
+        let mut transcript = Transcript::new(b"multi-party-eddsa test");
+        transcript.append_message(b"sid", b"session 1");
+        let mut other_session = Transcript::new(b"multi-party-eddsa test");
+        other_session.append_message(b"sid", b"session 2");
+
+        let keypairs: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let agg_keys: Vec<_> = (0..3).map(|i| KeyAgg::key_aggregation_n(&pks, i)).collect();
+        let (ephemeral_keys, first_msgs, second_msgs): (Vec<_>, Vec<_>, Vec<_>) = keypairs
+            .iter()
+            .map(|keypair| {
+                aggsig::transcript::create_ephemeral_key_and_commit_transcript_rng(
+                    keypair,
+                    message,
+                    &transcript,
+                    &mut rng,
+                )
+                .unwrap()
+            })
+            .multiunzip();
+        for (first_msg, second_msg) in first_msgs.iter().zip(&second_msgs) {
+            assert!(aggsig::transcript::verify_commitment_transcript(
+                first_msg,
+                second_msg,
+                &transcript
+            )
+            .is_ok());
+            assert_eq!(
+                aggsig::transcript::verify_commitment_transcript(
+                    first_msg,
+                    second_msg,
+                    &other_session
+                ),
+                Err(Error::InvalidCom)
+            );
+            assert!(aggsig::verify_commitment(first_msg, second_msg).is_err());
+        }
+
+        let Rs: Vec<_> = second_msgs.iter().map(|msg| msg.R.clone()).collect();
+        let R_tot = aggsig::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = izip!(keypairs.iter(), ephemeral_keys, agg_keys.iter())
+            .map(|(keypair, eph, agg_key)| {
+                aggsig::transcript::partial_sign_transcript(
+                    eph,
+                    keypair,
+                    &agg_key.hash,
+                    &R_tot,
+                    &agg_key.apk,
+                    message,
+                    &transcript,
+                )
+            })
+            .collect();
+        let signature = aggsig::add_signature_parts(&partial_sigs).unwrap();
+        let apk = &agg_keys[0].apk;
+        assert!(signature
+            .verify_transcript(message, &transcript, apk)
+            .is_ok());
+        assert!(signature
+            .verify_transcript(message, &other_session, apk)
+            .is_err());
+        assert!(signature.verify(message, apk).is_err());
+    }
+
+    #[test]
+    fn test_regression_vector() {
+        let vector = AggSigVector::generate(&[7u8; 32], 3, b"regression vector").unwrap();
+        assert_eq!(
+            AggSigVector::generate(&[7u8; 32], 3, b"regression vector").unwrap(),
+            vector
+        );
+        assert_eq!(
+            vector.aggregated_public_key,
+            "ada3cf80b8136ae4bdd073e9301dfd3951ef7cd1769758b2dea3e3a7cad97dfd"
+        );
+        assert_eq!(
+            vector.signature,
+            "afc1fc7882327bc4fa3478d15f7aa8a5be5357c0b0c83ebdbf2785655b3bd159\
+             cd04458f5b81b46216456e203aede1a3bd62ec81fef7e05c0c5e0fd9d20ff405"
+        );
+        assert!(vector.check().is_ok());
+        let apk = Point::from_bytes(&decode(&vector.aggregated_public_key).unwrap()).unwrap();
+        let signature = Signature::from_bytes(&decode(&vector.signature).unwrap()).unwrap();
+        assert!(verify_dalek(&apk, &signature, b"regression vector"));
+
+        let json = serde_json::to_string(&vector).unwrap();
+        assert_eq!(serde_json::from_str::<AggSigVector>(&json).unwrap(), vector);
+        let mut upper_case = vector.clone();
+        upper_case.signature = vector.signature.to_uppercase();
+        assert!(upper_case.check().is_ok());
+
+        let mut bad = vector.clone();
+        bad.parties[1].partial_signature = vector.parties[2].partial_signature.clone();
+        assert_eq!(bad.check(), Err(Error::InvalidSig));
+        let mut bad = vector.clone();
+        bad.parties[0].coefficient = vector.parties[1].coefficient.clone();
+        assert_eq!(bad.check(), Err(Error::InvalidKey));
+        let mut bad = vector.clone();
+        let reveal = &mut bad.parties[2].reveal;
+        let last = if reveal.ends_with('0') { "1" } else { "0" };
+        reveal.replace_range(reveal.len() - 1.., last);
+        assert_eq!(bad.check(), Err(Error::InvalidCom));
+        let mut bad = vector;
+        bad.message = "not hex".to_string();
+        assert_eq!(bad.check(), Err(Error::InvalidMessage));
+        assert_eq!(
+            AggSigVector::generate(&[7u8; 32], 0, b"").err(),
+            Some(Error::InvalidKey)
+        );
+    }
+
+    // the malformed inputs the fuzz targets in `fuzz/` look for, none of them may panic
+    #[test]
+    fn test_decode_untrusted_messages() {
+        let mut rng = deterministic_fast_rand("test_decode_untrusted_messages", None);
+        let keys = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let (_, first_msg, second_msg) =
+            aggsig::create_ephemeral_key_and_commit_rng(&keys, b"fuzz", &mut rng).unwrap();
+        let signature = aggsig::sign_single(b"fuzz", &keys);
+
+        let jsons = [
+            serde_json::to_string(&first_msg).unwrap(),
+            serde_json::to_string(&second_msg).unwrap(),
+            serde_json::to_string(&signature).unwrap(),
+        ];
+        for json in &jsons {
+            for len in 0..json.len() {
+                assert!(serde_json::from_str::<SignFirstMsg>(&json[..len]).is_err());
+                assert!(serde_json::from_str::<SignSecondMsg>(&json[..len]).is_err());
+                assert!(serde_json::from_str::<Signature>(&json[..len]).is_err());
+            }
+        }
+
+        // a blind factor or commitment longer than its encoding
+        let mut long_reveal = second_msg.clone();
+        long_reveal.blind_factor = BigInt::from_bytes(&[0xab; 40]);
+        let json = serde_json::to_string(&long_reveal).unwrap();
+        let long_reveal: SignSecondMsg = serde_json::from_str(&json).unwrap();
+        assert_eq!(long_reveal.to_bytes(), Err(Error::InvalidCom));
+        assert_eq!(
+            aggsig::verify_commitment(&first_msg, &long_reveal),
+            Err(Error::InvalidCom)
+        );
+        let long_commitment = SignFirstMsg {
+            commitment: BigInt::from_bytes(&[0xab; 65]),
+        };
+        assert_eq!(long_commitment.to_bytes(), Err(Error::InvalidCom));
+
+        let mut bytes = second_msg.to_bytes().unwrap();
+        bytes[..32].copy_from_slice(&[0xff; 32]);
+        assert_eq!(SignSecondMsg::from_bytes(&bytes), Err(Error::InvalidPoint));
+        for len in (0..=65).filter(|len| *len != 64) {
+            assert_eq!(
+                Signature::from_bytes(&vec![0u8; len]),
+                Err(VerifyError::InvalidSignature)
+            );
+        }
+        let mut high_s = signature.to_bytes();
+        high_s[63] = 0xff;
+        assert_eq!(
+            Signature::from_bytes(&high_s),
+            Err(VerifyError::NonCanonicalS)
+        );
+
+        assert_eq!(
+            aggsig::add_signature_parts(&[] as &[Signature]),
+            Err(Error::InvalidSig)
+        );
+        let mut other_R = signature.clone();
+        other_R.R = Point::generator().to_point();
+        assert_eq!(
+            aggsig::add_signature_parts(&[signature.clone(), other_R]),
+            Err(Error::InvalidPartialSignature { party: 2 })
+        );
+        let unreduced = Scalar::from_bytes(&[0xff; 32]).unwrap();
+        assert_eq!(
+            aggsig::verify_partial_sig(
+                &signature,
+                b"fuzz",
+                &unreduced,
+                &signature.R,
+                &Point::zero(),
+                &keys.public_key
+            ),
+            Err(Error::InvalidSig)
+        );
+    }
+
     pub fn test_com(r_to_test: &Point<Ed25519>, blind_factor: &BigInt, comm: &BigInt) -> bool {
         let computed_comm =
             &HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
-                &r_to_test.y_coord().unwrap(),
+                &BigInt::from_bytes(&r_to_test.to_bytes(true)),
                 blind_factor,
             );
         computed_comm == comm
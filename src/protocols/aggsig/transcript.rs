@@ -0,0 +1,120 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Aggsig with the round 1 nonce commitments and the Fiat–Shamir challenge drawn from a
+//! [merlin](https://merlin.cool) transcript instead of plain SHA-512.
+//!
+//! The caller creates the transcript with its protocol label (`Transcript::new(b"my protocol")`)
+//! and appends whatever the session is bound to (e.g. a session id or the signer set), every
+//! signer has to start from the same transcript. The functions here append the round label
+//! (`commitment` or `challenge`) and the round's values to a copy of it, so each hash is domain
+//! separated by protocol, session and round.
+//!
+//! The signatures are Schnorr signatures over Ed25519 but not RFC8032 ones, they only verify with
+//! `Signature::verify_transcript` under the same transcript.
+
+use curv::arithmetic::Converter;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use curv::BigInt;
+use merlin::Transcript;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::{CryptoRng, Rng, RngCore};
+
+use super::{
+    derive_nonce, sign_with_challenge, write_be_padded, EphemeralKey, SignFirstMsg, SignSecondMsg,
+    SIGN_FIRST_MSG_SIZE,
+};
+use protocols::{check_point, ExpandedKeyPair, Signature};
+use Error::{self, InvalidCom};
+
+#[cfg(feature = "std")]
+pub fn create_ephemeral_key_and_commit_transcript(
+    keys: &ExpandedKeyPair,
+    message: &[u8],
+    transcript: &Transcript,
+) -> Result<(EphemeralKey, SignFirstMsg, SignSecondMsg), Error> {
+    create_ephemeral_key_and_commit_transcript_rng(keys, message, transcript, &mut thread_rng())
+}
+
+pub fn create_ephemeral_key_and_commit_transcript_rng(
+    keys: &ExpandedKeyPair,
+    message: &[u8],
+    transcript: &Transcript,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<(EphemeralKey, SignFirstMsg, SignSecondMsg), Error> {
+    let r = derive_nonce(keys, message, None, Some(&mut *rng), 0, &[]);
+    let R = Point::generator() * &r;
+    let blind_factor = BigInt::from_bytes(&rng.gen::<[u8; 32]>());
+    let commitment = commitment(transcript, &R, &blind_factor)?;
+    Ok((
+        EphemeralKey { r, R: R.clone() },
+        SignFirstMsg { commitment },
+        SignSecondMsg { R, blind_factor },
+    ))
+}
+
+/// Checks that the `R` revealed in `sign_second_msg` is the one committed to in `sign_first_msg`
+/// under `transcript`, and that it isn't the identity.
+pub fn verify_commitment_transcript(
+    sign_first_msg: &SignFirstMsg,
+    sign_second_msg: &SignSecondMsg,
+    transcript: &Transcript,
+) -> Result<(), Error> {
+    check_point(&sign_second_msg.R)?;
+    let commitment = commitment(
+        transcript,
+        &sign_second_msg.R,
+        &sign_second_msg.blind_factor,
+    )?;
+    if commitment == sign_first_msg.commitment {
+        Ok(())
+    } else {
+        Err(InvalidCom)
+    }
+}
+
+/// `partial_sign` with the challenge drawn from `transcript`.
+pub fn partial_sign_transcript(
+    ephemeral_key: EphemeralKey,
+    keys: &ExpandedKeyPair,
+    a: &Scalar<Ed25519>,
+    R_tot: &Point<Ed25519>,
+    agg_pubkey: &Point<Ed25519>,
+    msg: &[u8],
+    transcript: &Transcript,
+) -> Signature {
+    let k = Signature::k_transcript(transcript, R_tot, agg_pubkey, msg);
+    sign_with_challenge(ephemeral_key, k, keys, a, R_tot)
+}
+
+// fails with `InvalidCom` for a blind factor longer than the 32 bytes it's created with
+fn commitment(
+    transcript: &Transcript,
+    R: &Point<Ed25519>,
+    blind_factor: &BigInt,
+) -> Result<BigInt, Error> {
+    let mut blind_factor_bytes = [0u8; 32];
+    write_be_padded(blind_factor, &mut blind_factor_bytes).map_err(|_| InvalidCom)?;
+    let mut transcript = transcript.clone();
+    transcript.append_message(b"round", b"commitment");
+    transcript.append_message(b"R", &R.to_bytes(true));
+    transcript.append_message(b"blind", &blind_factor_bytes);
+    let mut commitment = [0u8; SIGN_FIRST_MSG_SIZE];
+    transcript.challenge_bytes(b"commitment", &mut commitment);
+    Ok(BigInt::from_bytes(&commitment))
+}
@@ -0,0 +1,287 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Deterministic regression vectors of the whole aggsig flow
+//!
+//! `AggSigVector::generate` creates the parties' keys, aggregates them and runs the three signing
+//! rounds with all randomness drawn from a seed, so the same seed always gives the same vector.
+//! The vector is serde serializable and holds every party's private key, nonce and blind factor
+//! next to the round messages, so another implementation doesn't have to reproduce how the
+//! randomness is drawn: it can check its key aggregation, commitments and partial signatures
+//! against the vector, and `AggSigVector::check` checks a vector made by another implementation.
+//!
+//! All values are hex encoded: points compressed, scalars little endian and the messages as by
+//! their `to_bytes`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use rand::{CryptoRng, Rng, RngCore};
+use sha2::{Digest, Sha512};
+
+use super::{
+    add_signature_parts, create_ephemeral_key_and_commit_rng, get_R_tot, partial_sign,
+    verify_commitment, AggregatedKey, EphemeralKey, SignFirstMsg, SignSecondMsg,
+};
+use protocols::schnorr::{scalar_from_bytes_le, scalar_to_bytes_le};
+use protocols::{ExpandedKeyPair, Signature};
+use Error::{self, InvalidCom, InvalidKey, InvalidMessage, InvalidPoint, InvalidSig};
+
+const SEED_CONTEXT: &[u8] = b"multi-party-eddsa aggsig vectors";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartyVector {
+    /// The 32 byte RFC8032 private key.
+    pub private_key: String,
+    pub public_key: String,
+    /// The key aggregation coefficient of the party.
+    pub coefficient: String,
+    /// The party's nonce `r`.
+    pub nonce: String,
+    /// Round 1, `SignFirstMsg::to_bytes`.
+    pub commitment: String,
+    /// Round 2, `SignSecondMsg::to_bytes`: `R` and the blind factor.
+    pub reveal: String,
+    /// Round 3, `Signature::to_bytes` of the partial signature.
+    pub partial_signature: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggSigVector {
+    pub seed: String,
+    pub message: String,
+    /// In the order of the public keys in the key aggregation.
+    pub parties: Vec<PartyVector>,
+    pub aggregated_public_key: String,
+    pub signature: String,
+}
+
+impl AggSigVector {
+    /// The vector of `parties` signers signing `message`, fails with `InvalidKey` for no parties.
+    pub fn generate(seed: &[u8; 32], parties: u16, message: &[u8]) -> Result<AggSigVector, Error> {
+        if parties == 0 {
+            return Err(InvalidKey);
+        }
+        let mut rngs: Vec<_> = (1..=parties)
+            .map(|party| SeedRng::new(seed, party))
+            .collect();
+        let private_keys: Vec<[u8; 32]> = rngs.iter_mut().map(|rng| rng.gen()).collect();
+        let keys: Vec<_> = private_keys
+            .iter()
+            .map(|private_key| ExpandedKeyPair::create_from_private_key(*private_key))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|keys| keys.public_key.clone()).collect();
+        let agg_key = AggregatedKey::new(&pks);
+
+        let rounds = keys
+            .iter()
+            .zip(&mut rngs)
+            .map(|(keys, rng)| create_ephemeral_key_and_commit_rng(keys, message, rng))
+            .collect::<Result<Vec<_>, _>>()?;
+        let R_tot = get_R_tot(rounds.iter().map(|(_, _, reveal)| &reveal.R)).ok_or(InvalidPoint)?;
+        let mut party_vectors = Vec::with_capacity(keys.len());
+        let mut partial_sigs = Vec::with_capacity(keys.len());
+        for (((ephemeral_key, commitment, reveal), keys), (private_key, a)) in rounds
+            .into_iter()
+            .zip(&keys)
+            .zip(private_keys.iter().zip(&agg_key.coefficients))
+        {
+            let nonce = scalar_hex(&ephemeral_key.r);
+            let partial_sig = partial_sign(ephemeral_key, keys, a, &R_tot, &agg_key.apk, message);
+            party_vectors.push(PartyVector {
+                private_key: hex::encode(private_key),
+                public_key: point_hex(&keys.public_key),
+                coefficient: scalar_hex(a),
+                nonce,
+                commitment: hex::encode(&commitment.to_bytes()?[..]),
+                reveal: hex::encode(&reveal.to_bytes()?[..]),
+                partial_signature: hex::encode(&partial_sig.to_bytes()[..]),
+            });
+            partial_sigs.push(partial_sig);
+        }
+        let signature = add_signature_parts(&partial_sigs)?;
+        Ok(AggSigVector {
+            seed: hex::encode(seed),
+            message: hex::encode(message),
+            parties: party_vectors,
+            aggregated_public_key: point_hex(&agg_key.apk),
+            signature: hex::encode(&signature.to_bytes()[..]),
+        })
+    }
+
+    /// Recomputes everything but the randomness from the private keys, nonces and blind factors
+    /// and checks that the signature verifies. Fails with `InvalidMessage` for malformed values,
+    /// with `InvalidKey` if a public key, a coefficient or the aggregated key differs, with
+    /// `InvalidCom` if a commitment doesn't open to the party's `R` and with `InvalidSig` if a
+    /// partial signature or the signature differs.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.parties.is_empty() {
+            return Err(InvalidKey);
+        }
+        let message = from_hex(&self.message)?;
+        let keys = self
+            .parties
+            .iter()
+            .map(|party| {
+                let keys =
+                    ExpandedKeyPair::create_from_private_key(from_hex_array(&party.private_key)?);
+                expect(
+                    &party.public_key,
+                    &keys.public_key.to_bytes(true),
+                    InvalidKey,
+                )?;
+                Ok(keys)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let pks: Vec<_> = keys.iter().map(|keys| keys.public_key.clone()).collect();
+        let agg_key = AggregatedKey::new(&pks);
+        expect(
+            &self.aggregated_public_key,
+            &agg_key.apk.to_bytes(true),
+            InvalidKey,
+        )?;
+
+        let mut ephemeral_keys = Vec::with_capacity(keys.len());
+        for (party, a) in self.parties.iter().zip(&agg_key.coefficients) {
+            if from_hex(&party.coefficient)? != from_hex(&scalar_hex(a))? {
+                return Err(InvalidKey);
+            }
+            let r = scalar_from_bytes_le(&from_hex_array(&party.nonce)?).ok_or(InvalidMessage)?;
+            let commitment = SignFirstMsg::from_bytes(&from_hex_array(&party.commitment)?);
+            let reveal = SignSecondMsg::from_bytes(&from_hex_array(&party.reveal)?)?;
+            if reveal.R != Point::generator() * &r {
+                return Err(InvalidCom);
+            }
+            verify_commitment(&commitment, &reveal)?;
+            ephemeral_keys.push(EphemeralKey { r, R: reveal.R });
+        }
+        let R_tot = get_R_tot(ephemeral_keys.iter().map(|ephemeral_key| &ephemeral_key.R))
+            .ok_or(InvalidPoint)?;
+        let mut partial_sigs = Vec::with_capacity(keys.len());
+        for (((party, ephemeral_key), keys), a) in self
+            .parties
+            .iter()
+            .zip(ephemeral_keys)
+            .zip(&keys)
+            .zip(&agg_key.coefficients)
+        {
+            let partial_sig = partial_sign(ephemeral_key, keys, a, &R_tot, &agg_key.apk, &message);
+            expect(
+                &party.partial_signature,
+                &partial_sig.to_bytes(),
+                InvalidSig,
+            )?;
+            partial_sigs.push(partial_sig);
+        }
+        let signature = add_signature_parts(&partial_sigs)?;
+        expect(&self.signature, &signature.to_bytes(), InvalidSig)?;
+        Signature::from_bytes(&from_hex(&self.signature)?)
+            .map_err(|_| InvalidSig)?
+            .verify(&message, &agg_key.apk)
+            .map_err(|_| InvalidSig)
+    }
+}
+
+fn point_hex(point: &Point<Ed25519>) -> String {
+    hex::encode(&point.to_bytes(true)[..])
+}
+
+// reduced, the hashed nonces aren't necessarily below the group order
+fn scalar_hex(scalar: &Scalar<Ed25519>) -> String {
+    hex::encode(scalar_to_bytes_le(&Scalar::<Ed25519>::from_bigint(
+        &scalar.to_bigint(),
+    )))
+}
+
+fn from_hex(value: &str) -> Result<Vec<u8>, Error> {
+    hex::decode(value).map_err(|_| InvalidMessage)
+}
+
+fn from_hex_array<const N: usize>(value: &str) -> Result<[u8; N], Error> {
+    <[u8; N]>::try_from(from_hex(value)?.as_slice()).map_err(|_| InvalidMessage)
+}
+
+// compares bytes, the hex of another implementation may be upper case
+fn expect(value: &str, bytes: &[u8], error: Error) -> Result<(), Error> {
+    if from_hex(value)? == bytes {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+// SHA-512 in counter mode over the seed and the party's index, every party has its own stream
+struct SeedRng {
+    seed: [u8; 32],
+    party: u16,
+    counter: u64,
+    block: [u8; 64],
+    used: usize,
+}
+
+impl SeedRng {
+    fn new(seed: &[u8; 32], party: u16) -> SeedRng {
+        SeedRng {
+            seed: *seed,
+            party,
+            counter: 0,
+            block: [0u8; 64],
+            used: 64,
+        }
+    }
+}
+
+impl RngCore for SeedRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest {
+            if self.used == self.block.len() {
+                let block = Sha512::new()
+                    .chain(SEED_CONTEXT)
+                    .chain(self.seed)
+                    .chain(self.party.to_be_bytes())
+                    .chain(self.counter.to_be_bytes())
+                    .finalize();
+                self.block.copy_from_slice(&block);
+                self.counter += 1;
+                self.used = 0;
+            }
+            *byte = self.block[self.used];
+            self.used += 1;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for SeedRng {}
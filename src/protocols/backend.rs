@@ -0,0 +1,168 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! The group operations on the hot paths of key generation, signing and verification.
+//!
+//! By default `base_mul` and `verification_equation` use curv's arithmetic, like the rest of the
+//! crate. With the `dalek-backend` feature they run on curve25519-dalek, which multiplies many
+//! times faster, converting from and to curv's types at the boundary so the API doesn't change.
+//! The multi-scalar multiplications always use curve25519-dalek, curv has none.
+
+use curv::elliptic::curves::ed25519::Ed25519Point;
+use curv::elliptic::curves::{ECPoint, Ed25519, Point, Scalar};
+#[cfg(feature = "dalek-backend")]
+use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar as DalekScalar;
+#[cfg(feature = "parallel")]
+use curve25519_dalek::traits::Identity;
+#[cfg(feature = "dalek-backend")]
+use curve25519_dalek::traits::IsIdentity;
+use curve25519_dalek::traits::VartimeMultiscalarMul;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use zeroize::Zeroize;
+
+// points per multi-scalar multiplication of `par_multiscalar_mul`
+#[cfg(feature = "parallel")]
+const MSM_CHUNK: usize = 128;
+
+/// `s * G` in constant time, `s` can be a private key or nonce.
+#[cfg(feature = "dalek-backend")]
+pub(crate) fn base_mul(s: &Scalar<Ed25519>) -> Point<Ed25519> {
+    let mut s = to_dalek_scalar(s);
+    let P = &ED25519_BASEPOINT_TABLE * &s;
+    s.zeroize();
+    from_dalek(&P)
+}
+
+/// `s * G` in constant time, `s` can be a private key or nonce.
+#[cfg(not(feature = "dalek-backend"))]
+pub(crate) fn base_mul(s: &Scalar<Ed25519>) -> Point<Ed25519> {
+    Point::generator() * s
+}
+
+/// Whether `s * G == R + k * A`, times the cofactor 8 if `cofactored`. Variable time, only for
+/// public values.
+#[cfg(feature = "dalek-backend")]
+pub(crate) fn verification_equation(
+    s: &Scalar<Ed25519>,
+    R: &Point<Ed25519>,
+    k: &Scalar<Ed25519>,
+    A: &Point<Ed25519>,
+    cofactored: bool,
+) -> bool {
+    let A = decompress(&encode_point(A));
+    let R = decompress(&encode_point(R));
+    // `s * G - k * A`
+    let sG_minus_kA = EdwardsPoint::vartime_double_scalar_mul_basepoint(
+        &to_dalek_scalar(k),
+        &-A,
+        &to_dalek_scalar(s),
+    );
+    if cofactored {
+        (sG_minus_kA - R).mul_by_cofactor().is_identity()
+    } else {
+        sG_minus_kA == R
+    }
+}
+
+/// Whether `s * G == R + k * A`, times the cofactor 8 if `cofactored`. Variable time, only for
+/// public values.
+#[cfg(not(feature = "dalek-backend"))]
+pub(crate) fn verification_equation(
+    s: &Scalar<Ed25519>,
+    R: &Point<Ed25519>,
+    k: &Scalar<Ed25519>,
+    A: &Point<Ed25519>,
+    cofactored: bool,
+) -> bool {
+    let R_plus_kA = A * k + R;
+    let sG = s * Point::generator();
+    if cofactored {
+        ((sG - R_plus_kA) * Scalar::from(8)).is_zero()
+    } else {
+        R_plus_kA == sG
+    }
+}
+
+// `sum(scalars[i] * points[i])` with curve25519-dalek's variable time multi-scalar
+// multiplication, every addition of two curv points costs a compression and decompression, so
+// summing them is slower than converting the points once. Not constant time, only for public scalars.
+pub(crate) fn multiscalar_mul(
+    scalars: &[Scalar<Ed25519>],
+    points: &[Point<Ed25519>],
+) -> Point<Ed25519> {
+    from_dalek(&dalek_multiscalar_mul(scalars, points))
+}
+
+// `multiscalar_mul` of chunks of `MSM_CHUNK` points on all cores.
+#[cfg(feature = "parallel")]
+pub(crate) fn par_multiscalar_mul(
+    scalars: &[Scalar<Ed25519>],
+    points: &[Point<Ed25519>],
+) -> Point<Ed25519> {
+    let scalars = &scalars[..scalars.len().min(points.len())];
+    let sum = scalars
+        .par_chunks(MSM_CHUNK)
+        .enumerate()
+        .map(|(chunk, scalars)| {
+            let start = chunk * MSM_CHUNK;
+            dalek_multiscalar_mul(scalars, &points[start..start + scalars.len()])
+        })
+        .reduce(EdwardsPoint::identity, |sum, term| sum + term);
+    from_dalek(&sum)
+}
+
+fn dalek_multiscalar_mul(scalars: &[Scalar<Ed25519>], points: &[Point<Ed25519>]) -> EdwardsPoint {
+    let scalars = scalars.iter().map(to_dalek_scalar);
+    let points = points.iter().map(|P| decompress(&encode_point(P)));
+    EdwardsPoint::vartime_multiscalar_mul(scalars, points)
+}
+
+pub(crate) fn to_dalek_scalar(scalar: &Scalar<Ed25519>) -> DalekScalar {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&scalar.to_bytes());
+    let scalar = DalekScalar::from_bytes_mod_order(bytes);
+    // the scalar can be secret
+    bytes.zeroize();
+    scalar
+}
+
+pub(crate) fn encode_point(P: &Point<Ed25519>) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&P.to_bytes(true));
+    bytes
+}
+
+// the encoding of a curv point
+pub(crate) fn decompress(encoding: &[u8; 32]) -> EdwardsPoint {
+    CompressedEdwardsY(*encoding)
+        .decompress()
+        .expect("curv points are valid encodings")
+}
+
+// `P` has to be in the prime order subgroup, as every multiple and sum of curv points is.
+// `Point::from_bytes` would check that with a multiplication by the group order, which costs more
+// than the multiplication that made `P`.
+fn from_dalek(P: &EdwardsPoint) -> Point<Ed25519> {
+    let raw = Ed25519Point::deserialize(P.compress().as_bytes())
+        .expect("curve25519-dalek encodes points canonically");
+    // Safety: the point is the identity or in the prime order subgroup, all that curv requires of
+    // its points; it debug asserts that
+    unsafe { Point::from_raw_unchecked(raw) }
+}
@@ -0,0 +1,104 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Aggsig over secp256k1 with [BIP-340](https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki)
+//! signatures
+//!
+//! The `schnorr` rounds with the `Bip340` scheme: public keys and `R` are encoded as their 32 byte
+//! `x` coordinate and mean the point with an even `y`, the challenge is the `BIP0340/challenge`
+//! tagged hash and `s` is encoded big endian. The aggregated keys can be used as taproot output
+//! keys and the signatures verify with any BIP-340 verifier.
+
+#[cfg(test)]
+mod test;
+
+use curv::arithmetic::Converter;
+use curv::elliptic::curves::{Point, Scalar, Secp256k1};
+use curv::BigInt;
+use sha2::{Digest, Sha256};
+
+use protocols::schnorr::{self, scalar_from_bytes_be, scalar_to_bytes_be, Scheme};
+
+pub use protocols::schnorr::{
+    add_signature_parts, create_ephemeral_key_and_commit_rng, get_R_tot, partial_sign,
+    sign_single_rng, verify_commitment, verify_partial_sig, SignFirstMsg, SIGNATURE_SIZE,
+};
+#[cfg(feature = "std")]
+pub use protocols::schnorr::{create_ephemeral_key_and_commit, sign_single};
+
+// the compressed encoding of a point with an even `y`
+const EVEN_Y: u8 = 0x02;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Bip340;
+
+impl Scheme for Bip340 {
+    type Curve = Secp256k1;
+
+    const NAME: &'static [u8] = b"bip340";
+
+    /// `int(hash_BIP0340/challenge(bytes(R) || bytes(P) || m)) mod n`
+    fn challenge(
+        R: &Point<Secp256k1>,
+        public_key: &Point<Secp256k1>,
+        message: &[u8],
+    ) -> Scalar<Secp256k1> {
+        let tag = Sha256::digest(b"BIP0340/challenge");
+        let hash = Sha256::new()
+            .chain(tag)
+            .chain(tag)
+            .chain(Self::encode_point(R))
+            .chain(Self::encode_point(public_key))
+            .chain(message)
+            .finalize();
+        Scalar::from_bigint(&BigInt::from_bytes(&hash))
+    }
+
+    fn is_normalized(point: &Point<Secp256k1>) -> bool {
+        !point.is_zero() && point.to_bytes(true)[0] == EVEN_Y
+    }
+
+    fn encode_point(point: &Point<Secp256k1>) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        if !point.is_zero() {
+            bytes.copy_from_slice(&point.to_bytes(true)[1..]);
+        }
+        bytes
+    }
+
+    /// `lift_x`, fails if `bytes` isn't the `x` coordinate of a point.
+    fn decode_point(bytes: &[u8; 32]) -> Option<Point<Secp256k1>> {
+        let mut compressed = [EVEN_Y; 33];
+        compressed[1..].copy_from_slice(bytes);
+        Point::from_bytes(&compressed).ok()
+    }
+
+    fn encode_scalar(scalar: &Scalar<Secp256k1>) -> [u8; 32] {
+        scalar_to_bytes_be(scalar)
+    }
+
+    fn decode_scalar(bytes: &[u8; 32]) -> Option<Scalar<Secp256k1>> {
+        scalar_from_bytes_be(bytes)
+    }
+}
+
+pub type KeyPair = schnorr::KeyPair<Bip340>;
+pub type KeyAgg = schnorr::KeyAgg<Bip340>;
+pub type EphemeralKey = schnorr::EphemeralKey<Bip340>;
+pub type SignSecondMsg = schnorr::SignSecondMsg<Bip340>;
+pub type PartialSignature = schnorr::PartialSignature<Bip340>;
+pub type Signature = schnorr::Signature<Bip340>;
@@ -0,0 +1,166 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::{Point, Scalar, Secp256k1};
+    use hex::decode;
+    use itertools::MultiUnzip;
+    use rand::Rng;
+
+    use protocols::bip340::{self, Bip340, KeyAgg, KeyPair, Signature};
+    use protocols::schnorr::Scheme;
+    use protocols::tests::deterministic_fast_rand;
+    use VerifyError;
+
+    // (public key, message, signature, valid) from the BIP-340 test vectors
+    const VECTORS: &[(&str, &str, &str, bool)] = &[
+        (
+            "F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA821525F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0",
+            true,
+        ),
+        (
+            "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+            "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+            "6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE33418906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A",
+            true,
+        ),
+        // public key not on the curve
+        (
+            "EEFDEA4CDB677750A420FEE807EACF21EB9898AE79B9768766E4FAA04A2D4A34",
+            "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+            "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E17776969E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B",
+            false,
+        ),
+        // R has an odd y
+        (
+            "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+            "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+            "FFF97BD5755EEEA420453A14355235D382F6472F8568A18B2F057A14602975563CC27944640AC607CD107AE10923D9EF7A73C643E166BE5EBEAFA34B1AC553E2",
+            false,
+        ),
+        // r is the field size
+        (
+            "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+            "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F69E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B",
+            false,
+        ),
+        // s is the group order
+        (
+            "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+            "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+            "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E177769FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141",
+            false,
+        ),
+    ];
+
+    #[test]
+    fn test_bip340_vectors() {
+        for (public_key, message, signature, valid) in VECTORS {
+            let result = Signature::verify_bytes(
+                &decode(signature).unwrap(),
+                &decode(message).unwrap(),
+                &decode(public_key).unwrap(),
+            );
+            assert_eq!(result.is_ok(), *valid, "{}", signature);
+        }
+        // the first vector's secret key is 3
+        let public_key = Point::<Secp256k1>::generator() * Scalar::from(3);
+        assert_eq!(
+            Bip340::encode_point(&public_key).to_vec(),
+            decode(VECTORS[0].0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_multiparty_signing() {
+        let mut rng = deterministic_fast_rand("test_bip340_multiparty_signing", None);
+        // enough sessions that both the aggregated key and R_tot come out with an odd y
+        for _ in 0..8 {
+            let message: [u8; 32] = rng.gen();
+            let keys: Vec<_> = (0..3).map(|_| KeyPair::create_rng(&mut rng)).collect();
+            let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+            assert!(pks.iter().all(Bip340::is_normalized));
+            let key_aggs: Vec<_> = (0..3)
+                .map(|i| KeyAgg::key_aggregation_n(&pks, i).unwrap())
+                .collect();
+            let apk = key_aggs[0].apk.clone();
+            assert!(Bip340::is_normalized(&apk));
+
+            let (ephemeral_keys, first_msgs, second_msgs): (Vec<_>, Vec<_>, Vec<_>) = keys
+                .iter()
+                .map(|k| bip340::create_ephemeral_key_and_commit_rng(k, &message, &mut rng))
+                .multiunzip();
+            for (first_msg, second_msg) in first_msgs.iter().zip(&second_msgs) {
+                assert!(bip340::verify_commitment(first_msg, second_msg).is_ok());
+            }
+            let Rs: Vec<_> = second_msgs.iter().map(|msg| msg.R.clone()).collect();
+            let R_tot = bip340::get_R_tot(&Rs).unwrap();
+            let partial_sigs: Vec<_> = ephemeral_keys
+                .into_iter()
+                .zip(&keys)
+                .zip(&key_aggs)
+                .map(|((eph, k), key_agg)| {
+                    bip340::partial_sign(eph, k, &key_agg.hash, &R_tot, &apk, &message)
+                })
+                .collect();
+            for (i, sig) in partial_sigs.iter().enumerate() {
+                assert!(bip340::verify_partial_sig(
+                    sig,
+                    &message,
+                    &key_aggs[i].hash,
+                    &Rs[i],
+                    &pks[i],
+                    &apk
+                )
+                .is_ok());
+            }
+            let signature = bip340::add_signature_parts(&partial_sigs).unwrap();
+            assert!(signature.verify(&message, &apk).is_ok());
+            assert_eq!(
+                signature.verify(&message, &-&apk),
+                Err(VerifyError::InvalidPublicKey)
+            );
+            let bytes = signature.to_bytes();
+            assert_eq!(Signature::from_bytes(&bytes).unwrap(), signature);
+            assert!(Signature::verify_bytes(&bytes, &message, &Bip340::encode_point(&apk)).is_ok());
+            assert_eq!(
+                Signature::verify_bytes(&bytes, b"another message", &Bip340::encode_point(&apk)),
+                Err(VerifyError::InvalidSignature)
+            );
+        }
+    }
+
+    #[test]
+    fn test_single_signer() {
+        let mut rng = deterministic_fast_rand("test_bip340_single_signer", None);
+        for _ in 0..4 {
+            let message: [u8; 32] = rng.gen();
+            let keys = KeyPair::create_rng(&mut rng);
+            let signature = bip340::sign_single_rng(&message, &keys, &mut rng);
+            assert!(Signature::verify_bytes(
+                &signature.to_bytes(),
+                &message,
+                &Bip340::encode_point(&keys.public_key)
+            )
+            .is_ok());
+        }
+    }
+}
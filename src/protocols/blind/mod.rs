@@ -0,0 +1,174 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Blind Schnorr signatures
+//!
+//! The signer (or every signer of an aggsig key) sends a nonce `R_i = r_i * G`. The requester
+//! blinds with random `alpha`, `beta`: `R' = R + alpha * G + beta * X` for `R = sum(R_i)` and the
+//! public key `X`, and sends the blinded challenge `c = H(R', X, m) + beta`. Every signer answers
+//! with `s_i = r_i + c * a_i * x_i` and the requester unblinds `s = sum(s_i) + alpha`, so that
+//! `(R', s)` is a standard Ed25519 signature over `m` the signers can't link to the session.
+//!
+//! A single signer uses `a = 1`, an aggsig signer its `KeyAgg::hash`.
+//! A signer must not run sessions concurrently: with many open sessions the requester can forge
+//! an extra signature (the ROS attack, https://eprint.iacr.org/2020/945.pdf), so every nonce has
+//! to be answered or discarded before the next one is handed out.
+
+use curv::cryptographic_primitives::hashing::DigestExt;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::{CryptoRng, Rng, RngCore};
+use sha2::{digest::Digest, Sha512};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use protocols::{random_scalar, ExpandedKeyPair, Signature};
+use Error::{self, InvalidSig};
+
+/// The signer's secret nonce of one session.
+pub struct SignerNonce {
+    r: Scalar<Ed25519>,
+}
+
+impl Zeroize for SignerNonce {
+    fn zeroize(&mut self) {
+        self.r = Scalar::zero();
+    }
+}
+
+impl ZeroizeOnDrop for SignerNonce {}
+
+/// The requester's secret blinding of one session, needed to unblind the answer.
+pub struct BlindingFactors {
+    alpha: Scalar<Ed25519>,
+    // the blinded challenge and the values it was computed from, to check the signers' answers
+    c: Scalar<Ed25519>,
+    R: Point<Ed25519>,
+    R_blinded: Point<Ed25519>,
+    public_key: Point<Ed25519>,
+}
+
+impl Zeroize for BlindingFactors {
+    fn zeroize(&mut self) {
+        self.alpha = Scalar::zero();
+        self.c = Scalar::zero();
+        self.R = Point::zero();
+        self.R_blinded = Point::zero();
+    }
+}
+
+impl ZeroizeOnDrop for BlindingFactors {}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BlindedChallenge {
+    pub c: Scalar<Ed25519>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BlindSignature {
+    pub s: Scalar<Ed25519>,
+}
+
+/// Signer, round 1: returns the secret nonce and `R_i` to send to the requester.
+#[cfg(feature = "std")]
+pub fn create_nonce(keys: &ExpandedKeyPair) -> (SignerNonce, Point<Ed25519>) {
+    create_nonce_rng(keys, &mut thread_rng())
+}
+
+pub fn create_nonce_rng(
+    keys: &ExpandedKeyPair,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> (SignerNonce, Point<Ed25519>) {
+    // the message is unknown to the signer, so the nonce can only come from the prefix and randomness
+    let r = Sha512::new()
+        .chain([8])
+        .chain_scalar(&keys.expanded_private_key.prefix)
+        .chain(rng.gen::<[u8; 32]>())
+        .result_scalar();
+    let R = Point::generator() * &r;
+    (SignerNonce { r }, R)
+}
+
+/// Requester: blinds the challenge for `message`, `R` is the sum of all signers' nonces and
+/// `public_key` the (aggregated) key the signature will verify under.
+#[cfg(feature = "std")]
+pub fn blind(
+    R: &Point<Ed25519>,
+    public_key: &Point<Ed25519>,
+    message: &[u8],
+) -> (BlindingFactors, BlindedChallenge) {
+    blind_rng(R, public_key, message, &mut thread_rng())
+}
+
+pub fn blind_rng(
+    R: &Point<Ed25519>,
+    public_key: &Point<Ed25519>,
+    message: &[u8],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> (BlindingFactors, BlindedChallenge) {
+    let alpha = random_scalar(rng);
+    let beta = random_scalar(rng);
+    let R_blinded = R + Point::generator() * &alpha + public_key * &beta;
+    let c = Signature::k(&R_blinded, public_key, message) + beta;
+    (
+        BlindingFactors {
+            alpha,
+            c: c.clone(),
+            R: R.clone(),
+            R_blinded,
+            public_key: public_key.clone(),
+        },
+        BlindedChallenge { c },
+    )
+}
+
+/// Signer, round 2: answers the blinded challenge, consuming the nonce of the session.
+/// `a` is 1 for a single signer and the `KeyAgg::hash` of an aggsig signer.
+pub fn blind_sign(
+    nonce: SignerNonce,
+    keys: &ExpandedKeyPair,
+    a: &Scalar<Ed25519>,
+    challenge: &BlindedChallenge,
+) -> BlindSignature {
+    let c_mul_sk = &challenge.c * &keys.expanded_private_key.private_key;
+    BlindSignature {
+        s: &nonce.r + c_mul_sk * a,
+    }
+}
+
+/// Requester: adds up the signers' answers, checks them against the blinded challenge and
+/// unblinds them into a standard signature. Fails with `InvalidSig` if a signer cheated.
+pub fn unblind(
+    factors: &BlindingFactors,
+    blind_signatures: &[BlindSignature],
+) -> Result<Signature, Error> {
+    if blind_signatures.is_empty() {
+        return Err(InvalidSig);
+    }
+    let s = blind_signatures
+        .iter()
+        .fold(Scalar::zero(), |acc, sig| acc + &sig.s);
+    if &s * Point::generator() != &factors.R + &factors.public_key * &factors.c {
+        return Err(InvalidSig);
+    }
+    Ok(Signature {
+        R: factors.R_blinded.clone(),
+        s: s + &factors.alpha,
+    })
+}
+
+mod test;
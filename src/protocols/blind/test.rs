@@ -0,0 +1,83 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::Scalar;
+    use rand::Rng;
+
+    use protocols::aggsig::{self, KeyAgg};
+    use protocols::blind;
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use protocols::ExpandedKeyPair;
+    use Error;
+
+    #[test]
+    fn test_blind_sign_single_signer() {
+        let mut rng = deterministic_fast_rand("test_blind_sign_single_signer", None);
+        let message = b"token";
+        let keys = ExpandedKeyPair::create_from_private_key(rng.gen());
+
+        let (nonce, R) = blind::create_nonce_rng(&keys, &mut rng);
+        let (factors, challenge) = blind::blind_rng(&R, &keys.public_key, message, &mut rng);
+        let blind_signature = blind::blind_sign(nonce, &keys, &Scalar::from(1), &challenge);
+        let signature = blind::unblind(&factors, std::slice::from_ref(&blind_signature)).unwrap();
+
+        assert!(verify_dalek(&keys.public_key, &signature, message));
+        // the signer never saw R' or s
+        assert_ne!(signature.R, R);
+        assert_ne!(signature.s, blind_signature.s);
+
+        let mut bad = blind_signature;
+        bad.s = &bad.s + Scalar::from(1);
+        assert_eq!(blind::unblind(&factors, &[bad]), Err(Error::InvalidSig));
+    }
+
+    #[test]
+    fn test_blind_sign_aggregated_signers() {
+        let mut rng = deterministic_fast_rand("test_blind_sign_aggregated_signers", None);
+        let message = b"token";
+        let keys: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let apk = KeyAgg::key_aggregation_n(&pks, 0).apk;
+
+        let (nonces, Rs): (Vec<_>, Vec<_>) = keys
+            .iter()
+            .map(|k| blind::create_nonce_rng(k, &mut rng))
+            .unzip();
+        let (factors, challenge) =
+            blind::blind_rng(&aggsig::get_R_tot(&Rs).unwrap(), &apk, message, &mut rng);
+        let blind_signatures: Vec<_> = nonces
+            .into_iter()
+            .zip(&keys)
+            .enumerate()
+            .map(|(i, (nonce, k))| {
+                let a = KeyAgg::key_aggregation_n(&pks, i).hash;
+                blind::blind_sign(nonce, k, &a, &challenge)
+            })
+            .collect();
+        let signature = blind::unblind(&factors, &blind_signatures).unwrap();
+        assert!(verify_dalek(&apk, &signature, message));
+
+        assert_eq!(
+            blind::unblind(&factors, &blind_signatures[..2]),
+            Err(Error::InvalidSig)
+        );
+    }
+}
@@ -0,0 +1,458 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Ed448 signatures and aggsig over Ed448-Goldilocks (RFC8032 section 5.2)
+//!
+//! Keys are RFC8032 Ed448 keys (a 57 byte private key expanded with SHAKE256), `sign_single` makes
+//! the deterministic RFC8032 signatures and the aggregated signatures of the aggsig rounds verify
+//! as Ed448 signatures under the aggregated key. The challenge is
+//! `SHAKE256(dom4(0, "") || R || A || M, 114)`, Ed448 without a context.
+//!
+//! The curve arithmetic is the `ed448-goldilocks` crate, which hasn't been audited. Decoded points
+//! must be canonical, not the identity (for keys) and in the prime order subgroup, so there is no
+//! torsion component a co-signer could hide in its key or nonce. This rejects some keys and
+//! signatures other RFC8032 implementations accept.
+
+#[cfg(test)]
+mod test;
+
+use core::convert::TryFrom;
+
+use ed448_goldilocks::curve::edwards::{CompressedEdwardsY, ExtendedPoint};
+use ed448_goldilocks::Scalar;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::{CryptoRng, Rng, RngCore};
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use sha3::Shake256;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use Error::{self, InvalidCom, InvalidKey, InvalidPartialSignature, InvalidPoint, InvalidSig};
+use VerifyError;
+
+/// The size of encoded points and scalars.
+pub const PUBLIC_KEY_SIZE: usize = 57;
+pub const SIGNATURE_SIZE: usize = 2 * PUBLIC_KEY_SIZE;
+
+/// An RFC8032 Ed448 key pair.
+#[derive(Clone)]
+pub struct KeyPair {
+    pub public_key: ExtendedPoint,
+    secret_key: Scalar,
+    prefix: [u8; PUBLIC_KEY_SIZE],
+    private_key: [u8; PUBLIC_KEY_SIZE],
+}
+
+// ed448-goldilocks scalars don't zeroize themselves, so the secret is overwritten on drop
+impl Zeroize for KeyPair {
+    fn zeroize(&mut self) {
+        self.public_key = ExtendedPoint::identity();
+        self.secret_key = Scalar::zero();
+        self.prefix.zeroize();
+        self.private_key.zeroize();
+    }
+}
+
+impl Drop for KeyPair {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for KeyPair {}
+
+impl KeyPair {
+    #[cfg(feature = "std")]
+    pub fn create() -> KeyPair {
+        Self::create_rng(&mut thread_rng())
+    }
+
+    pub fn create_rng(rng: &mut (impl CryptoRng + RngCore)) -> KeyPair {
+        let mut private_key = [0u8; PUBLIC_KEY_SIZE];
+        rng.fill_bytes(&mut private_key);
+        let keys = Self::create_from_private_key(private_key);
+        private_key.zeroize();
+        keys
+    }
+
+    /// Expands the 57 byte private key: the first half of its 114 byte SHAKE256 hash is clamped
+    /// into the secret scalar, the second half is the nonce prefix.
+    pub fn create_from_private_key(mut private_key: [u8; PUBLIC_KEY_SIZE]) -> KeyPair {
+        let mut h = [0u8; SIGNATURE_SIZE];
+        shake256(&[&private_key], &mut h);
+        let mut secret_bytes = [0u8; SIGNATURE_SIZE];
+        secret_bytes[..PUBLIC_KEY_SIZE].copy_from_slice(&h[..PUBLIC_KEY_SIZE]);
+        secret_bytes[0] &= 252;
+        secret_bytes[56] = 0;
+        secret_bytes[55] |= 128;
+        // the clamped scalar is larger than the group order, reduce it
+        let secret_key = Scalar::from_bytes_mod_order_wide(&secret_bytes);
+        let mut prefix = [0u8; PUBLIC_KEY_SIZE];
+        prefix.copy_from_slice(&h[PUBLIC_KEY_SIZE..]);
+        h.zeroize();
+        secret_bytes.zeroize();
+        let keys = KeyPair {
+            public_key: secret_key * ExtendedPoint::generator(),
+            secret_key,
+            prefix,
+            private_key,
+        };
+        private_key.zeroize();
+        keys
+    }
+
+    /// The private key the pair was created from.
+    pub fn to_private_key_bytes(&self) -> [u8; PUBLIC_KEY_SIZE] {
+        self.private_key
+    }
+}
+
+/// The 57 byte encoding of `point`, `y` little endian with the sign of `x` in the top bit.
+pub fn encode_point(point: &ExtendedPoint) -> [u8; PUBLIC_KEY_SIZE] {
+    point.compress().0
+}
+
+/// `None` for anything but the canonical encoding of a point in the prime order subgroup.
+pub fn decode_point(bytes: &[u8; PUBLIC_KEY_SIZE]) -> Option<ExtendedPoint> {
+    let point = CompressedEdwardsY(*bytes).decompress()?;
+    // decompress accepts y >= p and a negative zero x
+    if point.compress().0 != *bytes || !point.is_torsion_free() {
+        return None;
+    }
+    Some(point)
+}
+
+/// The encoding of a public key, fails with `InvalidKey` for anything `decode_point` rejects
+/// and the identity.
+pub fn decode_public_key(bytes: &[u8]) -> Result<ExtendedPoint, Error> {
+    let bytes = <[u8; PUBLIC_KEY_SIZE]>::try_from(bytes).map_err(|_| InvalidKey)?;
+    match decode_point(&bytes) {
+        Some(point) if point != ExtendedPoint::identity() => Ok(point),
+        _ => Err(InvalidKey),
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct KeyAgg {
+    pub apk: ExtendedPoint,
+    pub hash: Scalar,
+}
+
+impl KeyAgg {
+    /// The aggregated key `sum(a_i * pks[i])` and our coefficient `a_i = H(L || pks[i])`, with
+    /// `L` the hash of all the public keys, for the party with `pks[party_index]`.
+    /// Fails with `InvalidKey` if there is no such party or a key is the identity or has a
+    /// torsion component.
+    pub fn key_aggregation_n(pks: &[ExtendedPoint], party_index: usize) -> Result<KeyAgg, Error> {
+        if party_index >= pks.len()
+            || pks
+                .iter()
+                .any(|pk| *pk == ExtendedPoint::identity() || !pk.is_torsion_free())
+        {
+            return Err(InvalidKey);
+        }
+        let encoded: Vec<_> = pks.iter().map(encode_point).collect();
+        let mut parts: Vec<&[u8]> = vec![&[17]];
+        parts.extend(encoded.iter().map(|pk| &pk[..]));
+        let mut prefix = [0u8; 64];
+        shake256(&parts, &mut prefix);
+        let coefficient = |pk: &[u8; PUBLIC_KEY_SIZE]| hash_to_scalar(&[&[17], &prefix, pk]);
+        let apk = pks
+            .iter()
+            .zip(&encoded)
+            .map(|(pk, encoded)| *pk * coefficient(encoded))
+            .sum();
+        Ok(KeyAgg {
+            apk,
+            hash: coefficient(&encoded[party_index]),
+        })
+    }
+}
+
+/// Our secret nonce `r` and its `R`, consumed by `partial_sign` so it can't sign twice.
+#[derive(Debug)]
+pub struct EphemeralKey {
+    r: Scalar,
+    pub R: ExtendedPoint,
+}
+
+impl Zeroize for EphemeralKey {
+    fn zeroize(&mut self) {
+        self.r = Scalar::zero();
+    }
+}
+
+impl Drop for EphemeralKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for EphemeralKey {}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SignFirstMsg {
+    pub commitment: [u8; 64],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SignSecondMsg {
+    pub R: ExtendedPoint,
+    pub blind_factor: [u8; 32],
+}
+
+impl SignSecondMsg {
+    /// The encoding `R || blind_factor`.
+    pub fn to_bytes(&self) -> [u8; PUBLIC_KEY_SIZE + 32] {
+        let mut bytes = [0u8; PUBLIC_KEY_SIZE + 32];
+        bytes[..PUBLIC_KEY_SIZE].copy_from_slice(&encode_point(&self.R));
+        bytes[PUBLIC_KEY_SIZE..].copy_from_slice(&self.blind_factor);
+        bytes
+    }
+
+    /// Fails with `InvalidPoint` if `R` isn't the encoding of a point in the prime order subgroup.
+    pub fn from_bytes(bytes: &[u8; PUBLIC_KEY_SIZE + 32]) -> Result<SignSecondMsg, Error> {
+        let mut R = [0u8; PUBLIC_KEY_SIZE];
+        R.copy_from_slice(&bytes[..PUBLIC_KEY_SIZE]);
+        let mut blind_factor = [0u8; 32];
+        blind_factor.copy_from_slice(&bytes[PUBLIC_KEY_SIZE..]);
+        Ok(SignSecondMsg {
+            R: decode_point(&R).ok_or(InvalidPoint)?,
+            blind_factor,
+        })
+    }
+}
+
+/// A share of the signature, `R` is the sum of all the nonces.
+#[derive(Clone, Copy, Debug)]
+pub struct PartialSignature {
+    pub R: ExtendedPoint,
+    pub s: Scalar,
+}
+
+#[cfg(feature = "std")]
+pub fn create_ephemeral_key_and_commit(
+    keys: &KeyPair,
+    message: &[u8],
+) -> (EphemeralKey, SignFirstMsg, SignSecondMsg) {
+    create_ephemeral_key_and_commit_rng(keys, message, &mut thread_rng())
+}
+
+/// Round 1: the nonce is hashed from the key's prefix, the message and fresh randomness, the
+/// commitment is to the encoding of `R`.
+pub fn create_ephemeral_key_and_commit_rng(
+    keys: &KeyPair,
+    message: &[u8],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> (EphemeralKey, SignFirstMsg, SignSecondMsg) {
+    let mut randomness = [0u8; PUBLIC_KEY_SIZE];
+    rng.fill_bytes(&mut randomness);
+    let r = hash_to_scalar(&[&[18], &keys.prefix, message, &randomness]);
+    randomness.zeroize();
+    let R = r * ExtendedPoint::generator();
+    let blind_factor: [u8; 32] = rng.gen();
+    (
+        EphemeralKey { r, R },
+        SignFirstMsg {
+            commitment: commitment(&R, &blind_factor),
+        },
+        SignSecondMsg { R, blind_factor },
+    )
+}
+
+/// Round 2: checks that the revealed `R` is the committed one. Fails with `InvalidPoint` for
+/// the identity or an `R` with a torsion component and with `InvalidCom` if it doesn't match.
+pub fn verify_commitment(
+    sign_first_msg: &SignFirstMsg,
+    sign_second_msg: &SignSecondMsg,
+) -> Result<(), Error> {
+    let R = &sign_second_msg.R;
+    if *R == ExtendedPoint::identity() || !R.is_torsion_free() {
+        return Err(InvalidPoint);
+    }
+    if commitment(R, &sign_second_msg.blind_factor) == sign_first_msg.commitment {
+        Ok(())
+    } else {
+        Err(InvalidCom)
+    }
+}
+
+/// The sum of the partial `R`, `None` if there are none.
+pub fn get_R_tot<'a>(Rs: impl IntoIterator<Item = &'a ExtendedPoint>) -> Option<ExtendedPoint> {
+    let mut Rs = Rs.into_iter();
+    let first = *Rs.next()?;
+    Some(Rs.fold(first, |sum, R| sum + *R))
+}
+
+/// Round 3: our partial signature `r + k * a * x`. `ephemeral_key` is consumed.
+pub fn partial_sign(
+    ephemeral_key: EphemeralKey,
+    keys: &KeyPair,
+    a: &Scalar,
+    R_tot: &ExtendedPoint,
+    agg_pubkey: &ExtendedPoint,
+    message: &[u8],
+) -> PartialSignature {
+    let k = challenge(R_tot, agg_pubkey, message);
+    PartialSignature {
+        R: *R_tot,
+        s: ephemeral_key.r + k * *a * keys.secret_key,
+    }
+}
+
+/// Checks the partial signature of the party with the coefficient `a`, public key
+/// `partial_public_key` and nonce `partial_R`.
+pub fn verify_partial_sig(
+    sig: &PartialSignature,
+    message: &[u8],
+    a: &Scalar,
+    partial_R: &ExtendedPoint,
+    partial_public_key: &ExtendedPoint,
+    agg_pubkey: &ExtendedPoint,
+) -> Result<(), Error> {
+    let k = challenge(&sig.R, agg_pubkey, message);
+    if sig.s * ExtendedPoint::generator() == *partial_R + *partial_public_key * (k * *a) {
+        Ok(())
+    } else {
+        Err(InvalidSig)
+    }
+}
+
+/// The signature under the aggregated key. Fails with `InvalidSig` if `sigs` is empty and with
+/// `InvalidPartialSignature { party: i + 1 }` if the i-th has another `R` than the first one.
+pub fn add_signature_parts<'a>(
+    sigs: impl IntoIterator<Item = &'a PartialSignature>,
+) -> Result<Signature, Error> {
+    let mut sigs = sigs.into_iter();
+    let first = sigs.next().ok_or(InvalidSig)?;
+    let s = sigs.zip(1..).try_fold(first.s, |s, (sig, i)| {
+        if sig.R != first.R {
+            return Err(InvalidPartialSignature {
+                party: u16::try_from(i + 1).unwrap_or(u16::MAX),
+            });
+        }
+        Ok(s + sig.s)
+    })?;
+    Ok(Signature { R: first.R, s })
+}
+
+/// The deterministic RFC8032 Ed448 signature, the nonce is `SHAKE256(dom4 || prefix || M)`.
+pub fn sign_single(message: &[u8], keys: &KeyPair) -> Signature {
+    let r = hash_to_scalar(&[DOM4, &keys.prefix, message]);
+    let R = r * ExtendedPoint::generator();
+    let k = challenge(&R, &keys.public_key, message);
+    Signature {
+        R,
+        s: r + k * keys.secret_key,
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Signature {
+    pub R: ExtendedPoint,
+    pub s: Scalar,
+}
+
+impl PartialEq for Signature {
+    fn eq(&self, other: &Signature) -> bool {
+        self.R == other.R && self.s == other.s
+    }
+}
+
+impl Signature {
+    /// Checks `[4]s * B == [4]R + [4]k * A`, fails with `InvalidPublicKey` for the identity or a
+    /// key with a torsion component.
+    pub fn verify(&self, message: &[u8], public_key: &ExtendedPoint) -> Result<(), VerifyError> {
+        if *public_key == ExtendedPoint::identity() || !public_key.is_torsion_free() {
+            return Err(VerifyError::InvalidPublicKey);
+        }
+        let k = challenge(&self.R, public_key, message);
+        let difference = self.s * ExtendedPoint::generator() - self.R - *public_key * k;
+        if difference.double().double() == ExtendedPoint::identity() {
+            Ok(())
+        } else {
+            Err(VerifyError::InvalidSignature)
+        }
+    }
+
+    /// The encoding `R || s`.
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_SIZE] {
+        let mut bytes = [0u8; SIGNATURE_SIZE];
+        bytes[..PUBLIC_KEY_SIZE].copy_from_slice(&encode_point(&self.R));
+        bytes[PUBLIC_KEY_SIZE..].copy_from_slice(&self.s.to_bytes_rfc_8032());
+        bytes
+    }
+
+    /// Decodes `to_bytes`, fails with `InvalidR` for an `R` `decode_point` rejects and with
+    /// `NonCanonicalS` for `s >= L`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Signature, VerifyError> {
+        if bytes.len() != SIGNATURE_SIZE {
+            return Err(VerifyError::InvalidSignature);
+        }
+        let mut R = [0u8; PUBLIC_KEY_SIZE];
+        R.copy_from_slice(&bytes[..PUBLIC_KEY_SIZE]);
+        let mut s = [0u8; PUBLIC_KEY_SIZE];
+        s.copy_from_slice(&bytes[PUBLIC_KEY_SIZE..]);
+        Ok(Signature {
+            R: decode_point(&R).ok_or(VerifyError::InvalidR)?,
+            s: Scalar::from_canonical_bytes(s).ok_or(VerifyError::NonCanonicalS)?,
+        })
+    }
+
+    /// `verify` of an encoded signature under an encoded public key.
+    pub fn verify_bytes(
+        signature: &[u8],
+        message: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), VerifyError> {
+        let public_key =
+            decode_public_key(public_key).map_err(|_| VerifyError::InvalidPublicKey)?;
+        Self::from_bytes(signature)?.verify(message, &public_key)
+    }
+}
+
+// dom4(0, ""): Ed448 without prehashing and an empty context
+const DOM4: &[u8] = b"SigEd448\x00\x00";
+
+/// The RFC8032 challenge `SHAKE256(dom4 || R || A || M, 114)` reduced mod `L`.
+fn challenge(R: &ExtendedPoint, public_key: &ExtendedPoint, message: &[u8]) -> Scalar {
+    hash_to_scalar(&[DOM4, &encode_point(R), &encode_point(public_key), message])
+}
+
+// the commitment to `R`, prefixed so it can't be mistaken for another hash
+fn commitment(R: &ExtendedPoint, blind_factor: &[u8; 32]) -> [u8; 64] {
+    let mut commitment = [0u8; 64];
+    shake256(&[&[20], &encode_point(R), blind_factor], &mut commitment);
+    commitment
+}
+
+// 114 bytes of SHAKE256 reduced mod L, so the result is uniform
+fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+    let mut hash = [0u8; SIGNATURE_SIZE];
+    shake256(parts, &mut hash);
+    let scalar = Scalar::from_bytes_mod_order_wide(&hash);
+    hash.zeroize();
+    scalar
+}
+
+fn shake256(parts: &[&[u8]], output: &mut [u8]) {
+    let mut hasher = Shake256::default();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize_xof().read(output);
+}
@@ -0,0 +1,208 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use ed448_goldilocks::curve::edwards::{CompressedEdwardsY, ExtendedPoint};
+    use itertools::MultiUnzip;
+
+    use protocols::ed448::{self, KeyAgg, KeyPair, SignSecondMsg, Signature, PUBLIC_KEY_SIZE};
+    use protocols::tests::deterministic_fast_rand;
+    use {Error, VerifyError};
+
+    fn private_key(hex: &str) -> [u8; PUBLIC_KEY_SIZE] {
+        let mut bytes = [0u8; PUBLIC_KEY_SIZE];
+        bytes.copy_from_slice(&::hex::decode(hex).unwrap());
+        bytes
+    }
+
+    #[test]
+    fn test_rfc8032_vectors() {
+        // RFC8032 section 7.4, "Blank" and "1 octet"
+        let vectors = [
+            (
+                "6c82a562cb808d10d632be89c8513ebf6c929f34ddfa8c9f63c9960ef6e348a3528c8a3fcc2f044e39a3fc5b94492f8f032e7549a20098f95b",
+                "5fd7449b59b461fd2ce787ec616ad46a1da1342485a70e1f8a0ea75d80e96778edf124769b46c7061bd6783df1e50f6cd1fa1abeafe8256180",
+                "",
+                "533a37f6bbe457251f023c0d88f976ae2dfb504a843e34d2074fd823d41a591f2b233f034f628281f2fd7a22ddd47d7828c59bd0a21bfd3980ff0d2028d4b18a9df63e006c5d1c2d345b925d8dc00b4104852db99ac5c7cdda8530a113a0f4dbb61149f05a7363268c71d95808ff2e652600",
+            ),
+            (
+                "c4eab05d357007c632f3dbb48489924d552b08fe0c353a0d4a1f00acda2c463afbea67c5e8d2877c5e3bc397a659949ef8021e954e0a12274e",
+                "43ba28f430cdff456ae531545f7ecd0ac834a55d9358c0372bfa0c6c6798c0866aea01eb00742802b8438ea4cb82169c235160627b4c3a9480",
+                "03",
+                "26b8f91727bd62897af15e41eb43c377efb9c610d48f2335cb0bd0087810f4352541b143c4b981b7e18f62de8ccdf633fc1bf037ab7cd779805e0dbcc0aae1cbcee1afb2e027df36bc04dcecbf154336c19f0af7e0a6472905e799f1953d2a0ff3348ab21aa4adafd1d234441cf807c03a00",
+            ),
+        ];
+        for (secret, public, message, signature) in vectors.iter() {
+            let keys = KeyPair::create_from_private_key(private_key(secret));
+            let public = ::hex::decode(public).unwrap();
+            let message = ::hex::decode(message).unwrap();
+            let signature = ::hex::decode(signature).unwrap();
+            assert_eq!(&ed448::encode_point(&keys.public_key)[..], &public[..]);
+            assert_eq!(keys.to_private_key_bytes(), private_key(secret));
+
+            let sig = ed448::sign_single(&message, &keys);
+            assert_eq!(&sig.to_bytes()[..], &signature[..]);
+            assert_eq!(Signature::from_bytes(&signature).unwrap(), sig);
+            assert!(Signature::verify_bytes(&signature, &message, &public).is_ok());
+            assert_eq!(
+                Signature::verify_bytes(&signature, b"another message", &public),
+                Err(VerifyError::InvalidSignature)
+            );
+        }
+    }
+
+    #[test]
+    fn test_multiparty_signing() {
+        let mut rng = deterministic_fast_rand("test_ed448_multiparty_signing", None);
+        let message = b"goldilocks";
+        let keys: Vec<_> = (0..3).map(|_| KeyPair::create_rng(&mut rng)).collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key).collect();
+        let key_aggs: Vec<_> = (0..3)
+            .map(|i| KeyAgg::key_aggregation_n(&pks, i).unwrap())
+            .collect();
+        assert!(key_aggs
+            .iter()
+            .all(|key_agg| key_agg.apk == key_aggs[0].apk));
+
+        let (ephemeral_keys, first_msgs, second_msgs): (Vec<_>, Vec<_>, Vec<_>) = keys
+            .iter()
+            .map(|k| ed448::create_ephemeral_key_and_commit_rng(k, message, &mut rng))
+            .multiunzip();
+        for (first_msg, second_msg) in first_msgs.iter().zip(&second_msgs) {
+            let received = SignSecondMsg::from_bytes(&second_msg.to_bytes()).unwrap();
+            assert!(ed448::verify_commitment(first_msg, &received).is_ok());
+        }
+        assert_eq!(
+            ed448::verify_commitment(&first_msgs[0], &second_msgs[1]),
+            Err(Error::InvalidCom)
+        );
+
+        let Rs: Vec<_> = second_msgs.iter().map(|msg| msg.R).collect();
+        let R_tot = ed448::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = ephemeral_keys
+            .into_iter()
+            .zip(&keys)
+            .zip(&key_aggs)
+            .map(|((eph, k), key_agg)| {
+                ed448::partial_sign(eph, k, &key_agg.hash, &R_tot, &key_agg.apk, message)
+            })
+            .collect();
+        for (i, sig) in partial_sigs.iter().enumerate() {
+            let key_agg = &key_aggs[i];
+            assert!(ed448::verify_partial_sig(
+                sig,
+                message,
+                &key_agg.hash,
+                &Rs[i],
+                &pks[i],
+                &key_agg.apk
+            )
+            .is_ok());
+        }
+        assert_eq!(
+            ed448::verify_partial_sig(
+                &partial_sigs[0],
+                message,
+                &key_aggs[0].hash,
+                &Rs[1],
+                &pks[0],
+                &key_aggs[0].apk
+            ),
+            Err(Error::InvalidSig)
+        );
+
+        let signature = ed448::add_signature_parts(&partial_sigs).unwrap();
+        let apk = &key_aggs[0].apk;
+        assert!(signature.verify(message, apk).is_ok());
+        let bytes = signature.to_bytes();
+        assert!(Signature::verify_bytes(&bytes, message, &ed448::encode_point(apk)).is_ok());
+        assert_eq!(
+            signature.verify(b"another message", apk),
+            Err(VerifyError::InvalidSignature)
+        );
+
+        let mut other_R = partial_sigs.clone();
+        other_R[2].R = Rs[0];
+        assert_eq!(
+            ed448::add_signature_parts(&other_R),
+            Err(Error::InvalidPartialSignature { party: 3 })
+        );
+        assert_eq!(ed448::add_signature_parts(&[]), Err(Error::InvalidSig));
+    }
+
+    #[test]
+    fn test_reject_invalid_points() {
+        let mut rng = deterministic_fast_rand("test_ed448_reject_invalid_points", None);
+        let keys = KeyPair::create_rng(&mut rng);
+        let identity = ExtendedPoint::identity();
+        assert_eq!(
+            KeyAgg::key_aggregation_n(&[keys.public_key, identity], 0).unwrap_err(),
+            Error::InvalidKey
+        );
+        assert_eq!(
+            KeyAgg::key_aggregation_n(&[keys.public_key], 1).unwrap_err(),
+            Error::InvalidKey
+        );
+        assert_eq!(
+            ed448::decode_public_key(&ed448::encode_point(&identity)).unwrap_err(),
+            Error::InvalidKey
+        );
+
+        // (0, -1), the point of order two
+        let mut order_two = [0xffu8; PUBLIC_KEY_SIZE];
+        order_two[0] = 0xfe;
+        order_two[28] = 0xfe;
+        order_two[56] = 0;
+        assert!(ed448::decode_point(&order_two).is_none());
+        let order_two = CompressedEdwardsY(order_two).decompress().unwrap();
+        let torsion = keys.public_key + order_two;
+        assert!(ed448::decode_point(&ed448::encode_point(&torsion)).is_none());
+
+        // y = 1 + p, the identity encoded with an unreduced y
+        let mut non_canonical = [0u8; PUBLIC_KEY_SIZE];
+        non_canonical[28..56].copy_from_slice(&[0xff; 28]);
+        assert!(ed448::decode_point(&non_canonical).is_none());
+
+        let signature = ed448::sign_single(b"message", &keys);
+        assert_eq!(
+            signature.verify(b"message", &torsion),
+            Err(VerifyError::InvalidPublicKey)
+        );
+        let mut bytes = signature.to_bytes();
+        bytes[..PUBLIC_KEY_SIZE].copy_from_slice(&ed448::encode_point(&order_two));
+        assert_eq!(Signature::from_bytes(&bytes), Err(VerifyError::InvalidR));
+        let mut bytes = signature.to_bytes();
+        bytes[PUBLIC_KEY_SIZE..].copy_from_slice(&[0xff; PUBLIC_KEY_SIZE]);
+        assert_eq!(
+            Signature::from_bytes(&bytes),
+            Err(VerifyError::NonCanonicalS)
+        );
+        assert_eq!(
+            Signature::from_bytes(&bytes[1..]),
+            Err(VerifyError::InvalidSignature)
+        );
+
+        let (_, first_msg, mut second_msg) =
+            ed448::create_ephemeral_key_and_commit_rng(&keys, b"message", &mut rng);
+        second_msg.R = identity;
+        assert_eq!(
+            ed448::verify_commitment(&first_msg, &second_msg),
+            Err(Error::InvalidPoint)
+        );
+    }
+}
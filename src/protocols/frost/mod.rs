@@ -0,0 +1,292 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! FROST: Flexible Round-Optimized Schnorr Threshold Signatures
+//!
+//! See https://eprint.iacr.org/2020/852.pdf
+//! Key shares come from the DKG in `protocols::keygen`, signing is the two-round protocol of Figure 3.
+//! The challenge is computed as in RFC8032 so the output is a standard Ed25519 signature.
+//! Like in `thresholdsig`, `threshold` is the degree of the sharing polynomial, meaning any
+//! `threshold + 1` parties can sign.
+//!
+//! Round 1 doesn't depend on the message, so every party can publish a batch of commitments
+//! ahead of time and keep the nonces in a `NoncePool`. Signing is then a single round: the
+//! coordinator picks one published commitment of every signer and sends the list with the
+//! message, the signers answer with `partial_sign_with_pool`.
+
+use Error::{
+    self, InvalidCom, InvalidCommitment, InvalidPartialSignature, InvalidSig, RoundMismatch,
+};
+
+use curv::cryptographic_primitives::hashing::DigestExt;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use protocols::keygen::{lagrange_coefficient, SharedKeys};
+use protocols::{check_point, Signature};
+use rand::{thread_rng, CryptoRng, Rng, RngCore};
+use sha2::{digest::Digest, Sha512};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+pub mod robust;
+pub use self::robust::RobustSigning;
+
+pub struct SigningNonces {
+    d: Scalar<Ed25519>,
+    e: Scalar<Ed25519>,
+}
+
+impl Zeroize for SigningNonces {
+    fn zeroize(&mut self) {
+        self.d = Scalar::zero();
+        self.e = Scalar::zero();
+    }
+}
+
+impl ZeroizeOnDrop for SigningNonces {}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SigningCommitment {
+    pub party_index: u16,
+    pub D: Point<Ed25519>,
+    pub E: Point<Ed25519>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PartialSignature {
+    pub party_index: u16,
+    pub z: Scalar<Ed25519>,
+}
+
+pub fn preprocess(keys: &SharedKeys) -> (SigningNonces, SigningCommitment) {
+    preprocess_rng(keys, &mut thread_rng())
+}
+
+pub fn preprocess_rng(
+    keys: &SharedKeys,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> (SigningNonces, SigningCommitment) {
+    // as in aggsig, the nonces are derived from the prefix mixed with randomness
+    let mut derive_nonce = || {
+        Sha512::new()
+            .chain([2])
+            .chain_scalar(&keys.prefix)
+            .chain(rng.gen::<[u8; 32]>())
+            .result_scalar()
+    };
+    let d = derive_nonce();
+    let e = derive_nonce();
+    let commitment = SigningCommitment {
+        party_index: keys.party_index,
+        D: Point::generator() * &d,
+        E: Point::generator() * &e,
+    };
+    (SigningNonces { d, e }, commitment)
+}
+
+/// The nonces of the commitments we published and haven't signed with yet.
+/// Taking nonces out of the pool removes them, so nonces are never used for two signatures.
+#[derive(Default)]
+pub struct NoncePool {
+    nonces: Vec<(SigningCommitment, SigningNonces)>,
+}
+
+impl NoncePool {
+    pub fn new() -> NoncePool {
+        NoncePool::default()
+    }
+
+    pub fn generate(&mut self, keys: &SharedKeys, count: usize) -> Vec<SigningCommitment> {
+        self.generate_rng(keys, count, &mut thread_rng())
+    }
+
+    /// Adds `count` fresh nonces to the pool and returns their commitments for publishing.
+    pub fn generate_rng(
+        &mut self,
+        keys: &SharedKeys,
+        count: usize,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Vec<SigningCommitment> {
+        (0..count)
+            .map(|_| {
+                let (nonces, commitment) = preprocess_rng(keys, rng);
+                self.nonces.push((commitment.clone(), nonces));
+                commitment
+            })
+            .collect()
+    }
+
+    /// Removes and returns the nonces of `commitment`, `None` if they were already used or
+    /// aren't ours.
+    pub fn take(&mut self, commitment: &SigningCommitment) -> Option<SigningNonces> {
+        let position = self
+            .nonces
+            .iter()
+            .position(|(published, _)| published == commitment)?;
+        Some(self.nonces.swap_remove(position).1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nonces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nonces.is_empty()
+    }
+}
+
+/// `partial_sign` with the nonces of our commitment in `commitments`, taken out of `pool`.
+/// Fails with `InvalidCom` if `commitments` has no commitment of ours whose nonces are still in
+/// the pool, e.g. because they were already used for another signature.
+pub fn partial_sign_with_pool(
+    keys: &SharedKeys,
+    pool: &mut NoncePool,
+    commitments: &[SigningCommitment],
+    message: &[u8],
+) -> Result<PartialSignature, Error> {
+    let commitment = commitments
+        .iter()
+        .find(|com| com.party_index == keys.party_index)
+        .ok_or(InvalidCom)?;
+    let nonces = pool.take(commitment).ok_or(InvalidCom)?;
+    partial_sign(keys, nonces, commitments, message)
+}
+
+/// `commitments` has to contain the commitments of all signers, including ours.
+/// The nonces are consumed, they must never be used for another signature.
+pub fn partial_sign(
+    keys: &SharedKeys,
+    nonces: SigningNonces,
+    commitments: &[SigningCommitment],
+    message: &[u8],
+) -> Result<PartialSignature, Error> {
+    let signers = signer_indices(commitments)?;
+    if !signers.contains(&keys.party_index) {
+        return Err(InvalidCom);
+    }
+    let rho = binding_factor(keys.party_index, commitments, message);
+    let R = group_commitment(commitments, message);
+    let c = Signature::k(&R, &keys.y, message);
+    let lambda = lagrange_coefficient(keys.party_index, &signers);
+    let z = nonces.d + nonces.e * rho + lambda * &keys.x_i * c;
+    Ok(PartialSignature {
+        party_index: keys.party_index,
+        z,
+    })
+}
+
+pub fn verify_partial_sig(
+    keys: &SharedKeys,
+    partial_sig: &PartialSignature,
+    commitments: &[SigningCommitment],
+    message: &[u8],
+) -> Result<(), Error> {
+    let signers = signer_indices(commitments)?;
+    let commitment = commitments
+        .iter()
+        .find(|com| com.party_index == partial_sig.party_index)
+        .ok_or(InvalidCom)?;
+    let rho = binding_factor(partial_sig.party_index, commitments, message);
+    let R = group_commitment(commitments, message);
+    let c = Signature::k(&R, &keys.y, message);
+    let lambda = lagrange_coefficient(partial_sig.party_index, &signers);
+    let Y_i = keys.verification_share(partial_sig.party_index);
+
+    let zG = &partial_sig.z * Point::generator();
+    let expected = &commitment.D + &commitment.E * rho + Y_i * (c * lambda);
+    if zG == expected {
+        Ok(())
+    } else {
+        Err(InvalidPartialSignature {
+            party: partial_sig.party_index,
+        })
+    }
+}
+
+/// Verifies every partial signature and combines them into an Ed25519 signature under `keys.y`.
+/// Fails with `RoundMismatch` unless there is exactly one partial signature of each of at least
+/// `threshold + 1` signers, and with `InvalidSig` if the signature doesn't verify after all.
+pub fn aggregate(
+    keys: &SharedKeys,
+    commitments: &[SigningCommitment],
+    partial_sigs: &[PartialSignature],
+    message: &[u8],
+) -> Result<Signature, Error> {
+    let signers = signer_indices(commitments)?;
+    let mut sig_indices: Vec<_> = partial_sigs.iter().map(|sig| sig.party_index).collect();
+    sig_indices.sort_unstable();
+    // the threshold is the degree of the joint polynomial
+    if signers.len() < keys.vss.commitments.len() || sig_indices != signers {
+        return Err(RoundMismatch);
+    }
+    for partial_sig in partial_sigs {
+        verify_partial_sig(keys, partial_sig, commitments, message)?;
+    }
+    let s = partial_sigs
+        .iter()
+        .fold(Scalar::zero(), |acc, partial_sig| acc + &partial_sig.z);
+    let signature = Signature {
+        R: group_commitment(commitments, message),
+        s,
+    };
+    signature.verify(message, &keys.y).map_err(|_| InvalidSig)?;
+    Ok(signature)
+}
+
+// the distinct, nonzero party indices of the signers, fails if a commitment is the identity
+fn signer_indices(commitments: &[SigningCommitment]) -> Result<Vec<u16>, Error> {
+    for com in commitments {
+        if check_point(&com.D).is_err() || check_point(&com.E).is_err() {
+            return Err(InvalidCommitment {
+                party: com.party_index,
+            });
+        }
+    }
+    let mut indices: Vec<_> = commitments.iter().map(|com| com.party_index).collect();
+    indices.sort_unstable();
+    let len_before_dedup = indices.len();
+    indices.dedup();
+    if indices.len() != len_before_dedup || indices.contains(&0) {
+        return Err(InvalidCom);
+    }
+    Ok(indices)
+}
+
+fn binding_factor(
+    party_index: u16,
+    commitments: &[SigningCommitment],
+    message: &[u8],
+) -> Scalar<Ed25519> {
+    // the commitment list is hashed in ascending party index order, so every signer hashes the same list
+    let mut sorted: Vec<_> = commitments.iter().collect();
+    sorted.sort_by_key(|com| com.party_index);
+    // the message is hashed on its own first, as H4 of RFC 9591, so the variable length input
+    // can't run into the commitment list
+    let mut hasher = Sha512::new()
+        .chain([6])
+        .chain(Sha512::digest(message))
+        .chain(party_index.to_be_bytes());
+    for com in sorted {
+        hasher.update(com.party_index.to_be_bytes());
+        hasher.update(&*com.D.to_bytes(true));
+        hasher.update(&*com.E.to_bytes(true));
+    }
+    hasher.result_scalar()
+}
+
+fn group_commitment(commitments: &[SigningCommitment], message: &[u8]) -> Point<Ed25519> {
+    commitments.iter().fold(Point::zero(), |acc, com| {
+        let rho = binding_factor(com.party_index, commitments, message);
+        acc + &com.D + &com.E * rho
+    })
+}
+
+mod test;
@@ -0,0 +1,140 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! A coordinator for signing that survives signers going offline or misbehaving.
+//!
+//! The share-holders publish commitments ahead of time (see `NoncePool`), so an attempt is a
+//! single round: the coordinator sends `RobustSigning::commitments` and the message to the
+//! signers of the attempt and collects their partial signatures. If a signer doesn't answer or
+//! answers with an invalid partial signature, `RobustSigning::replace` excludes it and starts a
+//! new attempt, keeping the other signers and filling up with share-holders that still have
+//! unused commitments. Every attempt uses fresh commitments, since the binding factors (and so
+//! the partial signatures) depend on the whole commitment list.
+
+use super::{aggregate, verify_partial_sig, PartialSignature, SigningCommitment};
+use protocols::keygen::SharedKeys;
+use protocols::Signature;
+use Error::{self, RoundMismatch};
+
+pub struct RobustSigning {
+    threshold: u16,
+    message: Vec<u8>,
+    unused: Vec<SigningCommitment>,
+    excluded: Vec<u16>,
+    attempt: usize,
+    commitments: Vec<SigningCommitment>,
+    partial_sigs: Vec<PartialSignature>,
+}
+
+impl RobustSigning {
+    /// `published` are the commitments published by the share-holders, each used at most once.
+    /// Signers are picked in the order of their first commitment in `published`.
+    pub fn new(
+        threshold: u16,
+        message: &[u8],
+        published: Vec<SigningCommitment>,
+    ) -> Result<RobustSigning, Error> {
+        let mut session = RobustSigning {
+            threshold,
+            message: message.to_vec(),
+            unused: published,
+            excluded: Vec::new(),
+            attempt: 0,
+            commitments: Vec::new(),
+            partial_sigs: Vec::new(),
+        };
+        session.next_attempt()?;
+        Ok(session)
+    }
+
+    /// The commitments of the current attempt, to be sent to its signers along with the message.
+    pub fn commitments(&self) -> &[SigningCommitment] {
+        &self.commitments
+    }
+
+    /// Starts at 1 and goes up with every `replace`.
+    pub fn attempt(&self) -> usize {
+        self.attempt
+    }
+
+    /// Collects the partial signature of a signer of attempt `attempt` and returns the signature
+    /// once all signers of the attempt answered.
+    /// Fails with `RoundMismatch` for a partial signature of an earlier attempt or another party,
+    /// and with `InvalidPartialSignature` if it doesn't verify, the signer should be replaced.
+    pub fn add_partial_sig(
+        &mut self,
+        keys: &SharedKeys,
+        attempt: usize,
+        partial_sig: PartialSignature,
+    ) -> Result<Option<Signature>, Error> {
+        let party = partial_sig.party_index;
+        if attempt != self.attempt
+            || !self.commitments.iter().any(|com| com.party_index == party)
+            || self.partial_sigs.iter().any(|sig| sig.party_index == party)
+        {
+            return Err(RoundMismatch);
+        }
+        verify_partial_sig(keys, &partial_sig, &self.commitments, &self.message)?;
+        self.partial_sigs.push(partial_sig);
+        if self.partial_sigs.len() < self.commitments.len() {
+            return Ok(None);
+        }
+        aggregate(keys, &self.commitments, &self.partial_sigs, &self.message).map(Some)
+    }
+
+    /// Excludes `party`, a signer of the current attempt, and starts a new attempt.
+    /// Fails with `RoundMismatch` if there aren't enough share-holders with unused commitments
+    /// left.
+    pub fn replace(&mut self, party: u16) -> Result<(), Error> {
+        if !self.commitments.iter().any(|com| com.party_index == party) {
+            return Err(RoundMismatch);
+        }
+        self.excluded.push(party);
+        self.next_attempt()
+    }
+
+    fn next_attempt(&mut self) -> Result<(), Error> {
+        let has_unused = |party: u16| self.unused.iter().any(|com| com.party_index == party);
+        let mut signers: Vec<u16> = Vec::new();
+        let candidates = self
+            .commitments
+            .iter()
+            .chain(self.unused.iter())
+            .map(|com| com.party_index);
+        for party in candidates {
+            if signers.len() > usize::from(self.threshold) {
+                break;
+            }
+            if !self.excluded.contains(&party) && !signers.contains(&party) && has_unused(party) {
+                signers.push(party);
+            }
+        }
+        if signers.len() != usize::from(self.threshold) + 1 {
+            return Err(RoundMismatch);
+        }
+        self.commitments = signers
+            .iter()
+            .map(|&party| {
+                let position = self
+                    .unused
+                    .iter()
+                    .position(|com| com.party_index == party)
+                    .expect("signers were picked with an unused commitment");
+                self.unused.remove(position)
+            })
+            .collect();
+        self.partial_sigs.clear();
+        self.attempt += 1;
+        Ok(())
+    }
+}
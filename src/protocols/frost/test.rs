@@ -0,0 +1,321 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::{Point, Scalar};
+    use itertools::Itertools;
+    use protocols::frost::{self, NoncePool, RobustSigning};
+    use protocols::keygen::test::tests::keygen_t_n_parties;
+    use protocols::keygen::{self, Parameters, SharedKeys};
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use protocols::{ExpandedKeyPair, Signature};
+    use rand::{CryptoRng, Rng, RngCore};
+    use Error;
+
+    #[test]
+    fn test_frost_sign_verify_dalek_n1() {
+        test_frost_sign_verify_dalek_for_all_t(1);
+    }
+    #[test]
+    fn test_frost_sign_verify_dalek_n3() {
+        test_frost_sign_verify_dalek_for_all_t(3);
+    }
+    #[test]
+    fn test_frost_sign_verify_dalek_n5() {
+        test_frost_sign_verify_dalek_for_all_t(5);
+    }
+
+    fn test_frost_sign_verify_dalek_for_all_t(n: u16) {
+        let mut rng = deterministic_fast_rand(
+            &format!("test_frost_sign_verify_dalek_for_all_t_{}", n),
+            None,
+        );
+        let mut msg = [0u8; 17];
+        for t in 0..n {
+            let params = Parameters {
+                threshold: t,
+                share_count: n,
+            };
+            let shared_keys = keygen_t_n_parties(&params, &mut rng);
+            assert!(shared_keys[1..]
+                .iter()
+                .all(|keys| keys.y == shared_keys[0].y));
+
+            for group in (1u16..=n).combinations(usize::from(t + 1)) {
+                for msg_len in 0..msg.len() {
+                    let msg = &mut msg[..msg_len];
+                    rng.fill_bytes(msg);
+                    let signers: Vec<_> = group
+                        .iter()
+                        .map(|&i| &shared_keys[usize::from(i - 1)])
+                        .collect();
+                    let signature = sign(&signers, msg, &mut rng).unwrap();
+                    assert!(verify_dalek(&shared_keys[0].y, &signature, msg));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_frost_reject_bad_partial_sig() {
+        let mut rng = deterministic_fast_rand("test_frost_reject_bad_partial_sig", None);
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let shared_keys = keygen_t_n_parties(&params, &mut rng);
+        let message: [u8; 4] = [79, 77, 69, 82];
+
+        let (nonces, commitments): (Vec<_>, Vec<_>) = shared_keys[..2]
+            .iter()
+            .map(|keys| frost::preprocess_rng(keys, &mut rng))
+            .unzip();
+        let mut partial_sigs: Vec<_> = shared_keys[..2]
+            .iter()
+            .zip(nonces)
+            .map(|(keys, nonces)| {
+                frost::partial_sign(keys, nonces, &commitments, &message).unwrap()
+            })
+            .collect();
+        partial_sigs[1].z = &partial_sigs[1].z + Scalar::from(1);
+
+        assert!(frost::verify_partial_sig(
+            &shared_keys[2],
+            &partial_sigs[0],
+            &commitments,
+            &message
+        )
+        .is_ok());
+        assert_eq!(
+            frost::aggregate(&shared_keys[2], &commitments, &partial_sigs, &message).unwrap_err(),
+            Error::InvalidPartialSignature { party: 2 }
+        );
+    }
+
+    #[test]
+    fn test_frost_reject_identity_commitment_and_too_few_signers() {
+        let mut rng = deterministic_fast_rand(
+            "test_frost_reject_identity_commitment_and_too_few_signers",
+            None,
+        );
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let shared_keys = keygen_t_n_parties(&params, &mut rng);
+        let message = b"too few";
+
+        let (nonces, mut commitments) = frost::preprocess_rng(&shared_keys[0], &mut rng);
+        let commitments_1 = [commitments.clone()];
+        let partial_sigs =
+            [frost::partial_sign(&shared_keys[0], nonces, &commitments_1, message).unwrap()];
+        assert_eq!(
+            frost::aggregate(&shared_keys[0], &commitments_1, &partial_sigs, message),
+            Err(Error::RoundMismatch)
+        );
+
+        commitments.E = Point::zero();
+        let (nonces, _) = frost::preprocess_rng(&shared_keys[0], &mut rng);
+        assert_eq!(
+            frost::partial_sign(&shared_keys[0], nonces, &[commitments], message),
+            Err(Error::InvalidCommitment { party: 1 })
+        );
+    }
+
+    #[test]
+    fn test_frost_sign_with_dealt_keys() {
+        let mut rng = deterministic_fast_rand("test_frost_sign_with_dealt_keys", None);
+        let params = Parameters {
+            threshold: 2,
+            share_count: 5,
+        };
+        let keypair = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let mut shared_keys = keygen::deal_rng(&params, &keypair, &mut rng).unwrap();
+        assert!(shared_keys.iter().all(|keys| keys.verify_share().is_ok()
+            && keys.y == keypair.public_key
+            && keys.vss == shared_keys[0].vss));
+
+        let message = b"dealt";
+        for group in [[1, 2, 3], [2, 4, 5]] {
+            let signers: Vec<_> = group
+                .iter()
+                .map(|&i| &shared_keys[usize::from(i - 1u16)])
+                .collect();
+            let signature = sign(&signers, message, &mut rng).unwrap();
+            assert!(verify_dalek(&keypair.public_key, &signature, message));
+        }
+
+        shared_keys[3].x_i = &shared_keys[3].x_i + Scalar::from(1);
+        assert!(shared_keys[3].verify_share().is_err());
+        assert!(keygen::deal_rng(
+            &Parameters {
+                threshold: 3,
+                share_count: 3,
+            },
+            &keypair,
+            &mut rng,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_frost_sign_with_nonce_pool() {
+        let mut rng = deterministic_fast_rand("test_frost_sign_with_nonce_pool", None);
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let shared_keys = keygen_t_n_parties(&params, &mut rng);
+        // preprocessing: every party publishes a batch of commitments
+        let mut pools: Vec<_> = shared_keys.iter().map(|_| NoncePool::new()).collect();
+        let published: Vec<_> = shared_keys
+            .iter()
+            .zip(pools.iter_mut())
+            .map(|(keys, pool)| pool.generate_rng(keys, 2, &mut rng))
+            .collect();
+        assert!(pools.iter().all(|pool| pool.len() == 2));
+
+        // online: one round per signature
+        let message = b"presigned";
+        for (signers, batch) in [([0, 2], 0), ([1, 2], 1)] {
+            let commitments: Vec<_> = signers
+                .iter()
+                .map(|&i| published[i][batch].clone())
+                .collect();
+            let partial_sigs: Vec<_> = signers
+                .iter()
+                .map(|&i| {
+                    frost::partial_sign_with_pool(
+                        &shared_keys[i],
+                        &mut pools[i],
+                        &commitments,
+                        message,
+                    )
+                    .unwrap()
+                })
+                .collect();
+            let signature =
+                frost::aggregate(&shared_keys[0], &commitments, &partial_sigs, message).unwrap();
+            assert!(verify_dalek(&shared_keys[0].y, &signature, message));
+
+            // the same commitments can't be signed with again
+            assert_eq!(
+                frost::partial_sign_with_pool(
+                    &shared_keys[signers[0]],
+                    &mut pools[signers[0]],
+                    &commitments,
+                    b"other message",
+                ),
+                Err(Error::InvalidCom)
+            );
+        }
+        assert_eq!(pools[2].len(), 0);
+        assert_eq!(pools[0].len(), 1);
+        // nor can another party's
+        let commitments = [published[0][1].clone(), published[1][0].clone()];
+        assert_eq!(
+            frost::partial_sign_with_pool(&shared_keys[0], &mut pools[1], &commitments, message),
+            Err(Error::InvalidCom)
+        );
+    }
+
+    #[test]
+    fn test_frost_robust_signing() {
+        let mut rng = deterministic_fast_rand("test_frost_robust_signing", None);
+        let params = Parameters {
+            threshold: 1,
+            share_count: 4,
+        };
+        let shared_keys = keygen_t_n_parties(&params, &mut rng);
+        let mut pools: Vec<_> = shared_keys.iter().map(|_| NoncePool::new()).collect();
+        let published: Vec<_> = shared_keys
+            .iter()
+            .zip(pools.iter_mut())
+            .flat_map(|(keys, pool)| pool.generate_rng(keys, 3, &mut rng))
+            .collect();
+        let message = b"robust";
+        let mut session = RobustSigning::new(params.threshold, message, published).unwrap();
+        let mut partial_sign = |session: &RobustSigning, party: u16| {
+            let i = usize::from(party - 1);
+            frost::partial_sign_with_pool(
+                &shared_keys[i],
+                &mut pools[i],
+                session.commitments(),
+                message,
+            )
+            .unwrap()
+        };
+        let signers = |session: &RobustSigning| -> Vec<u16> {
+            session
+                .commitments()
+                .iter()
+                .map(|com| com.party_index)
+                .collect()
+        };
+
+        // party 1 answers, party 2 is offline
+        assert_eq!(signers(&session), [1, 2]);
+        let stale_sig = partial_sign(&session, 1);
+        assert_eq!(
+            session.add_partial_sig(&shared_keys[0], 1, stale_sig.clone()),
+            Ok(None)
+        );
+        session.replace(2).unwrap();
+
+        // the next attempt keeps party 1, party 3 sends an invalid partial signature
+        assert_eq!(session.attempt(), 2);
+        assert_eq!(signers(&session), [1, 3]);
+        assert_eq!(
+            session.add_partial_sig(&shared_keys[0], 1, stale_sig),
+            Err(Error::RoundMismatch)
+        );
+        let mut bad_sig = partial_sign(&session, 3);
+        bad_sig.z = &bad_sig.z + Scalar::from(1);
+        assert_eq!(
+            session.add_partial_sig(&shared_keys[0], 2, bad_sig),
+            Err(Error::InvalidPartialSignature { party: 3 })
+        );
+        session.replace(3).unwrap();
+
+        // party 4 steps in, after that there's nobody left to replace it with
+        assert_eq!(signers(&session), [1, 4]);
+        let sig_1 = partial_sign(&session, 1);
+        let sig_4 = partial_sign(&session, 4);
+        assert_eq!(session.add_partial_sig(&shared_keys[0], 3, sig_4), Ok(None));
+        let signature = session
+            .add_partial_sig(&shared_keys[0], 3, sig_1)
+            .unwrap()
+            .unwrap();
+        assert!(verify_dalek(&shared_keys[0].y, &signature, message));
+        assert_eq!(session.replace(4), Err(Error::RoundMismatch));
+    }
+
+    fn sign(
+        signers: &[&SharedKeys],
+        message: &[u8],
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<Signature, Error> {
+        // round 1
+        let (nonces, commitments): (Vec<_>, Vec<_>) = signers
+            .iter()
+            .map(|keys| frost::preprocess_rng(keys, rng))
+            .unzip();
+        // round 2
+        let partial_sigs = signers
+            .iter()
+            .zip(nonces)
+            .map(|(keys, nonces)| frost::partial_sign(keys, nonces, &commitments, message))
+            .collect::<Result<Vec<_>, _>>()?;
+        frost::aggregate(signers[0], &commitments, &partial_sigs, message)
+    }
+}
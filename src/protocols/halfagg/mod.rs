@@ -0,0 +1,161 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Half-aggregation of Ed25519 signatures (Chalkias, Garillot, Kondi, Nikolaenko,
+//! https://eprint.iacr.org/2021/350.pdf)
+//!
+//! `n` signatures `(R_i, s_i)` of `n` messages under `n` keys, e.g. all those of a block, are
+//! compressed into `R_1, ..., R_n` and `s = sum(z_i * s_i)`: `32 * (n + 1)` bytes instead of
+//! `64 * n`. `z_1 = 1` and the other `z_i` are 128 bit hashes of all `(R_j, A_j, m_j)`, so the
+//! aggregate can't be rearranged, and it verifies as `s * G = sum(z_i * (R_i + k_i * A_i))`.
+//! Aggregation needs no secret and doesn't check the signatures, whoever aggregates can do it
+//! with signatures received from anyone; the aggregate is only valid if all of them are.
+
+use alloc::vec::Vec;
+
+use curv::arithmetic::Converter;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use curv::BigInt;
+use sha2::{Digest, Sha512};
+
+use protocols::{decode_point, Signature};
+use VerifyError;
+
+mod test;
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct HalfAggregate {
+    pub R: Vec<Point<Ed25519>>,
+    pub s: Scalar<Ed25519>,
+}
+
+impl HalfAggregate {
+    /// `R_1 || ... || R_n || s`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(32 * (self.R.len() + 1));
+        for R in &self.R {
+            bytes.extend_from_slice(&R.to_bytes(true));
+        }
+        bytes.extend_from_slice(&self.s.to_bytes());
+        bytes
+    }
+
+    /// Rejects non canonical encodings of the `R_i` and of `s`, like `Signature::from_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<HalfAggregate, VerifyError> {
+        if bytes.is_empty() || !bytes.len().is_multiple_of(32) {
+            return Err(VerifyError::InvalidSignature);
+        }
+        let (R_bytes, s) = bytes.split_at(bytes.len() - 32);
+        let R = R_bytes
+            .chunks(32)
+            .map(|R| decode_point(R).ok_or(VerifyError::InvalidR))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut s = s.to_vec();
+        s.reverse();
+        let s = BigInt::from_bytes(&s);
+        if s >= *Scalar::<Ed25519>::group_order() {
+            return Err(VerifyError::NonCanonicalS);
+        }
+        Ok(HalfAggregate {
+            R,
+            s: Scalar::from_bigint(&s),
+        })
+    }
+}
+
+/// Aggregates `signatures[i]` over `messages[i]` under `public_keys[i]`.
+/// Fails with `InvalidSignature` if the lengths differ.
+pub fn aggregate(
+    messages: &[&[u8]],
+    signatures: &[Signature],
+    public_keys: &[Point<Ed25519>],
+) -> Result<HalfAggregate, VerifyError> {
+    if messages.len() != signatures.len() || signatures.len() != public_keys.len() {
+        return Err(VerifyError::InvalidSignature);
+    }
+    let R: Vec<_> = signatures.iter().map(|sig| sig.R.clone()).collect();
+    let s = coefficients(messages, &R, public_keys)
+        .zip(signatures)
+        .fold(Scalar::zero(), |s, (z, sig)| s + z * &sig.s);
+    Ok(HalfAggregate { R, s })
+}
+
+/// Verifies `aggregate` for `messages[i]` under `public_keys[i]`, in the order they were
+/// aggregated in.
+pub fn verify(
+    messages: &[&[u8]],
+    public_keys: &[Point<Ed25519>],
+    aggregate: &HalfAggregate,
+) -> Result<(), VerifyError> {
+    if messages.len() != aggregate.R.len() || aggregate.R.len() != public_keys.len() {
+        return Err(VerifyError::InvalidSignature);
+    }
+    if public_keys.iter().any(|A| A.is_zero()) {
+        return Err(VerifyError::InvalidPublicKey);
+    }
+    let sum_zR_zkA = coefficients(messages, &aggregate.R, public_keys)
+        .zip(messages.iter().zip(&aggregate.R).zip(public_keys))
+        .fold(Point::zero(), |sum, (z, ((message, R), A))| {
+            let k = Signature::k(R, A, message);
+            sum + R * &z + A * (z * k)
+        });
+    if &aggregate.s * Point::generator() == sum_zR_zkA {
+        Ok(())
+    } else {
+        Err(VerifyError::InvalidSignature)
+    }
+}
+
+/// `verify` for an encoded aggregate.
+pub fn verify_bytes(
+    messages: &[&[u8]],
+    public_keys: &[Point<Ed25519>],
+    aggregate: &[u8],
+) -> Result<(), VerifyError> {
+    verify(
+        messages,
+        public_keys,
+        &HalfAggregate::from_bytes(aggregate)?,
+    )
+}
+
+// `z_1 = 1`, `z_i` the first 16 bytes of `H(15, n, (R_j, A_j, len(m_j), m_j) for all j, i)`
+fn coefficients<'a>(
+    messages: &'a [&[u8]],
+    R: &'a [Point<Ed25519>],
+    public_keys: &'a [Point<Ed25519>],
+) -> impl Iterator<Item = Scalar<Ed25519>> + 'a {
+    let hasher = messages.iter().zip(R).zip(public_keys).fold(
+        Sha512::new()
+            .chain([15])
+            .chain((messages.len() as u64).to_be_bytes()),
+        |hasher, ((message, R), A)| {
+            hasher
+                .chain(&*R.to_bytes(true))
+                .chain(&*A.to_bytes(true))
+                .chain((message.len() as u64).to_be_bytes())
+                .chain(message)
+        },
+    );
+    (0..messages.len()).map(move |i| {
+        if i == 0 {
+            return Scalar::from(1);
+        }
+        let z = hasher.clone().chain((i as u64).to_be_bytes()).finalize();
+        Scalar::from_bigint(&BigInt::from_bytes(&z[..16]))
+    })
+}
@@ -0,0 +1,90 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::Scalar;
+    use rand::{Rng, RngCore};
+
+    use protocols::aggsig;
+    use protocols::halfagg::{self, HalfAggregate};
+    use protocols::tests::deterministic_fast_rand;
+    use protocols::ExpandedKeyPair;
+    use VerifyError;
+
+    #[test]
+    fn test_half_aggregation() {
+        let mut rng = deterministic_fast_rand("test_half_aggregation", None);
+        let mut msgs = [[0u8; 32]; 9];
+        msgs.iter_mut().for_each(|msg| rng.fill_bytes(msg));
+        let msgs: Vec<&[u8]> = msgs.iter().map(|msg| &msg[..]).collect();
+        let keypairs: Vec<_> = (0..msgs.len())
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let public_keys: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let sigs: Vec<_> = msgs
+            .iter()
+            .zip(keypairs.iter())
+            .map(|(msg, keypair)| aggsig::sign_single(msg, keypair))
+            .collect();
+
+        let aggregate = halfagg::aggregate(&msgs, &sigs, &public_keys).unwrap();
+        halfagg::verify(&msgs, &public_keys, &aggregate).unwrap();
+        let bytes = aggregate.to_bytes();
+        assert_eq!(bytes.len(), 32 * (msgs.len() + 1));
+        assert_eq!(HalfAggregate::from_bytes(&bytes).unwrap(), aggregate);
+        halfagg::verify_bytes(&msgs, &public_keys, &bytes).unwrap();
+
+        // the order is bound, swapping two signers fails
+        let mut swapped_msgs = msgs.clone();
+        swapped_msgs.swap(1, 2);
+        let mut swapped_public_keys = public_keys.clone();
+        swapped_public_keys.swap(1, 2);
+        let mut swapped = aggregate.clone();
+        swapped.R.swap(1, 2);
+        assert_eq!(
+            halfagg::verify(&swapped_msgs, &swapped_public_keys, &swapped),
+            Err(VerifyError::InvalidSignature)
+        );
+        assert_eq!(
+            halfagg::verify(&msgs[1..], &public_keys, &aggregate),
+            Err(VerifyError::InvalidSignature)
+        );
+
+        // a single bad signature
+        let mut bad_sigs = sigs.clone();
+        bad_sigs[4].s = &bad_sigs[4].s + Scalar::from(1);
+        let bad = halfagg::aggregate(&msgs, &bad_sigs, &public_keys).unwrap();
+        assert_eq!(
+            halfagg::verify(&msgs, &public_keys, &bad),
+            Err(VerifyError::InvalidSignature)
+        );
+
+        // non canonical s
+        let mut bytes = bytes;
+        let len = bytes.len();
+        bytes[len - 1] |= 0xf0;
+        assert_eq!(
+            HalfAggregate::from_bytes(&bytes).err(),
+            Some(VerifyError::NonCanonicalS)
+        );
+        assert_eq!(
+            HalfAggregate::from_bytes(&bytes[1..]).err(),
+            Some(VerifyError::InvalidSignature)
+        );
+    }
+}
@@ -0,0 +1,449 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! Dealerless distributed key generation with Feldman VSS
+//!
+//! Every party shares the private key of its own `ExpandedKeyPair` with a random polynomial of
+//! degree `threshold` and broadcasts the Feldman commitments to the coefficients (its public
+//! verification vector) together with a proof of knowledge of the constant term, as in the DKG of
+//! FROST (https://eprint.iacr.org/2020/852.pdf, Figure 1).
+//! The joint secret is the sum of all constant terms; no party ever learns it, and any
+//! `threshold + 1` parties can use their shares to sign under the joint public key `y`.
+//!
+//! All parties have to agree on a session id `sid` (e.g. a random value chosen by the
+//! coordinator), it's hashed into the proofs of knowledge together with the party index, so a
+//! proof can't be replayed into another key generation or by another party.
+//!
+//! Round 1: broadcast `KeyGenBroadcastMessage1`.
+//! Round 2: verify all proofs and send `KeyGenMessage2` privately to every party.
+//! Finally every party verifies the shares it received against the senders' verification vectors.
+//!
+//! Where a trusted machine provisions the shares anyway, `deal` splits an existing
+//! `ExpandedKeyPair` instead. The dealer knows the private key, every party checks its share
+//! with `SharedKeys::verify_share` and should compare `vss` with the other parties.
+
+use Error::{
+    self, InvalidCommitment, InvalidKey, InvalidProof, InvalidSS, InvalidShare, RoundMismatch,
+};
+
+use curv::cryptographic_primitives::hashing::DigestExt;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use protocols::{random_scalar, ExpandedKeyPair};
+use rand::{thread_rng, CryptoRng, Rng, RngCore};
+use sha2::{digest::Digest, Sha512};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Parameters {
+    pub threshold: u16,   //t
+    pub share_count: u16, //n
+}
+
+// keypair.private_key is the constant term of the sharing polynomial, prefix is kept for nonce derivation.
+pub struct Keys {
+    pub keypair: ExpandedKeyPair,
+    pub party_index: u16,
+    coefficients: Vec<Scalar<Ed25519>>,
+}
+
+/// Feldman commitments `a_k * G` to the coefficients of a sharing polynomial.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VerificationVector {
+    pub commitments: Vec<Point<Ed25519>>,
+}
+
+// Schnorr proof of knowledge of the constant term of the sharing polynomial
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProofOfKnowledge {
+    pub R: Point<Ed25519>,
+    pub mu: Scalar<Ed25519>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyGenBroadcastMessage1 {
+    pub party_index: u16,
+    pub vss: VerificationVector,
+    pub proof: ProofOfKnowledge,
+}
+
+// Sent privately from `sender_index` to `receiver_index`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyGenMessage2 {
+    pub sender_index: u16,
+    pub receiver_index: u16,
+    pub secret_share: Scalar<Ed25519>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SharedKeys {
+    pub party_index: u16,
+    pub y: Point<Ed25519>,
+    pub x_i: Scalar<Ed25519>,
+    // verification vector of the joint polynomial, its constant term is y
+    pub vss: VerificationVector,
+    pub(crate) prefix: Scalar<Ed25519>,
+}
+
+impl Zeroize for Keys {
+    fn zeroize(&mut self) {
+        self.keypair.zeroize();
+        self.coefficients.clear();
+    }
+}
+
+impl ZeroizeOnDrop for Keys {}
+
+impl Zeroize for SharedKeys {
+    fn zeroize(&mut self) {
+        self.x_i = Scalar::zero();
+        self.prefix = Scalar::zero();
+    }
+}
+
+impl ZeroizeOnDrop for SharedKeys {}
+
+impl Keys {
+    /// Fails with `RoundMismatch` unless `threshold < share_count` and `party_index` is in
+    /// `1..=share_count`.
+    pub fn phase1_create(params: &Parameters, party_index: u16) -> Result<Keys, Error> {
+        Self::phase1_create_rng(params, party_index, &mut thread_rng())
+    }
+
+    pub fn phase1_create_rng(
+        params: &Parameters,
+        party_index: u16,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<Keys, Error> {
+        Self::phase1_create_from_private_key_rng(params, party_index, rng.gen(), rng)
+    }
+
+    pub fn phase1_create_from_private_key(
+        params: &Parameters,
+        party_index: u16,
+        secret: [u8; 32],
+    ) -> Result<Keys, Error> {
+        Self::phase1_create_from_private_key_rng(params, party_index, secret, &mut thread_rng())
+    }
+
+    /// `rng` is used for the other coefficients of the sharing polynomial.
+    pub fn phase1_create_from_private_key_rng(
+        params: &Parameters,
+        party_index: u16,
+        secret: [u8; 32],
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<Keys, Error> {
+        if !valid_parameters(params) || party_index == 0 || party_index > params.share_count {
+            return Err(RoundMismatch);
+        }
+        let keypair = ExpandedKeyPair::create_from_private_key(secret);
+        let coefficients = sample_polynomial(
+            keypair.expanded_private_key.private_key.clone(),
+            params.threshold,
+            rng,
+        );
+        Ok(Keys {
+            keypair,
+            party_index,
+            coefficients,
+        })
+    }
+
+    /// The proof of knowledge is bound to the session id `sid`.
+    pub fn phase1_broadcast(&self, sid: &[u8]) -> KeyGenBroadcastMessage1 {
+        self.phase1_broadcast_rng(sid, &mut thread_rng())
+    }
+
+    pub fn phase1_broadcast_rng(
+        &self,
+        sid: &[u8],
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> KeyGenBroadcastMessage1 {
+        let commitments: Vec<_> = self
+            .coefficients
+            .iter()
+            .map(|a| Point::generator() * a)
+            .collect();
+        let k = Sha512::new()
+            .chain([5])
+            .chain_scalar(&self.keypair.expanded_private_key.prefix)
+            .chain(rng.gen::<[u8; 32]>())
+            .result_scalar();
+        let R = Point::generator() * &k;
+        let c = proof_challenge(sid, self.party_index, &commitments[0], &R);
+        let mu = k + c * &self.coefficients[0];
+        KeyGenBroadcastMessage1 {
+            party_index: self.party_index,
+            vss: VerificationVector { commitments },
+            proof: ProofOfKnowledge { R, mu },
+        }
+    }
+
+    /// Verifies everyone's proof of knowledge for the session `sid` and returns the share for
+    /// every party `1..=n`.
+    pub fn phase1_verify_proofs_phase2_distribute(
+        &self,
+        params: &Parameters,
+        sid: &[u8],
+        bc1_vec: &[KeyGenBroadcastMessage1],
+    ) -> Result<Vec<KeyGenMessage2>, Error> {
+        if !valid_parameters(params) || !valid_party_indices(params, bc1_vec) {
+            return Err(RoundMismatch);
+        }
+        for bc1 in bc1_vec {
+            if bc1.vss.commitments.len() != usize::from(params.threshold) + 1 {
+                return Err(InvalidCommitment {
+                    party: bc1.party_index,
+                });
+            }
+            if !verify_proof_of_knowledge(sid, bc1.party_index, &bc1.vss.commitments[0], &bc1.proof)
+            {
+                return Err(InvalidProof {
+                    party: bc1.party_index,
+                });
+            }
+        }
+        Ok((1..=params.share_count)
+            .map(|j| KeyGenMessage2 {
+                sender_index: self.party_index,
+                receiver_index: j,
+                secret_share: evaluate_polynomial(&self.coefficients, j),
+            })
+            .collect())
+    }
+
+    /// `msg2_vec` holds the round 2 messages addressed to us, one from every party, in any order.
+    pub fn phase2_verify_shares_construct_keypair(
+        &self,
+        params: &Parameters,
+        bc1_vec: &[KeyGenBroadcastMessage1],
+        msg2_vec: &[KeyGenMessage2],
+    ) -> Result<SharedKeys, Error> {
+        if !valid_parameters(params)
+            || !valid_party_indices(params, bc1_vec)
+            || msg2_vec.len() != bc1_vec.len()
+        {
+            return Err(RoundMismatch);
+        }
+        verify_received_shares(
+            self.party_index,
+            bc1_vec.iter().map(|bc1| (bc1.party_index, &bc1.vss)),
+            msg2_vec
+                .iter()
+                .map(|msg2| (msg2.sender_index, msg2.receiver_index, &msg2.secret_share)),
+        )?;
+
+        let commitments: Vec<_> = (0..usize::from(params.threshold) + 1)
+            .map(|k| {
+                bc1_vec
+                    .iter()
+                    .fold(Point::zero(), |acc, bc1| acc + &bc1.vss.commitments[k])
+            })
+            .collect();
+        let x_i = msg2_vec
+            .iter()
+            .fold(Scalar::zero(), |acc, msg2| acc + &msg2.secret_share);
+        Ok(SharedKeys {
+            party_index: self.party_index,
+            y: commitments[0].clone(),
+            x_i,
+            vss: VerificationVector { commitments },
+            prefix: self.keypair.expanded_private_key.prefix.clone(),
+        })
+    }
+}
+
+impl VerificationVector {
+    /// `sum(a_k * index^k) * G`, the public counterpart of the share of party `index`.
+    pub fn get_point_commitment(&self, index: u16) -> Point<Ed25519> {
+        let x = Scalar::from(index);
+        self.commitments
+            .iter()
+            .rev()
+            .fold(Point::zero(), |acc, commitment| acc * &x + commitment)
+    }
+
+    /// Fails with `InvalidSS`, callers know the sender and wrap it into `InvalidShare`.
+    pub fn validate_share(&self, secret_share: &Scalar<Ed25519>, index: u16) -> Result<(), Error> {
+        self.validate_share_public(&(Point::generator() * secret_share), index)
+    }
+
+    pub fn validate_share_public(
+        &self,
+        ss_point: &Point<Ed25519>,
+        index: u16,
+    ) -> Result<(), Error> {
+        if *ss_point == self.get_point_commitment(index) {
+            Ok(())
+        } else {
+            Err(InvalidSS)
+        }
+    }
+}
+
+impl SharedKeys {
+    /// The public share `x_j * G` of party `j`, derived from the public keygen transcript.
+    pub fn verification_share(&self, party_index: u16) -> Point<Ed25519> {
+        self.vss.get_point_commitment(party_index)
+    }
+
+    /// Checks a share received from a dealer against its verification vector and `y`.
+    pub fn verify_share(&self) -> Result<(), Error> {
+        if self.vss.commitments.first() != Some(&self.y) {
+            return Err(InvalidKey);
+        }
+        self.vss.validate_share(&self.x_i, self.party_index)
+    }
+}
+
+/// Splits `keypair` into shares for the parties `1..=n`, any `threshold + 1` of them can sign
+/// under `keypair.public_key`. For a fresh key, pass `ExpandedKeyPair::create()`.
+pub fn deal(params: &Parameters, keypair: &ExpandedKeyPair) -> Result<Vec<SharedKeys>, Error> {
+    deal_rng(params, keypair, &mut thread_rng())
+}
+
+/// `rng` is used for the other coefficients of the sharing polynomial.
+pub fn deal_rng(
+    params: &Parameters,
+    keypair: &ExpandedKeyPair,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<Vec<SharedKeys>, Error> {
+    if !valid_parameters(params) {
+        return Err(RoundMismatch);
+    }
+    let mut coefficients = sample_polynomial(
+        keypair.expanded_private_key.private_key.clone(),
+        params.threshold,
+        rng,
+    );
+    let commitments: Vec<_> = coefficients
+        .iter()
+        .map(|a| Point::generator() * a)
+        .collect();
+    let shared_keys = (1..=params.share_count)
+        .map(|party_index| SharedKeys {
+            party_index,
+            y: keypair.public_key.clone(),
+            x_i: evaluate_polynomial(&coefficients, party_index),
+            vss: VerificationVector {
+                commitments: commitments.clone(),
+            },
+            // every party gets its own nonce prefix, derived from the dealt key's
+            prefix: Sha512::new()
+                .chain([11])
+                .chain_scalar(&keypair.expanded_private_key.prefix)
+                .chain(party_index.to_be_bytes())
+                .result_scalar(),
+        })
+        .collect();
+    coefficients.iter_mut().for_each(|a| *a = Scalar::zero());
+    Ok(shared_keys)
+}
+
+pub fn lagrange_coefficient(index: u16, indices: &[u16]) -> Scalar<Ed25519> {
+    let x_i = Scalar::from(index);
+    let (num, denom) = indices.iter().filter(|&&j| j != index).fold(
+        (Scalar::<Ed25519>::from(1), Scalar::<Ed25519>::from(1)),
+        |(num, denom), &j| {
+            let x_j = Scalar::from(j);
+            (num * &x_j, denom * (x_j - &x_i))
+        },
+    );
+    num * denom
+        .invert()
+        .expect("indices are distinct, so the denominator can't be zero")
+}
+
+// Every sender's verification vector has to come with exactly one valid share addressed to `party_index`.
+// `msg2_vec` was already checked to have one message per sender.
+pub(crate) fn verify_received_shares<'a>(
+    party_index: u16,
+    vss_vec: impl Iterator<Item = (u16, &'a VerificationVector)>,
+    msg2_vec: impl Iterator<Item = (u16, u16, &'a Scalar<Ed25519>)> + Clone,
+) -> Result<(), Error> {
+    for (sender_index, vss) in vss_vec {
+        let valid_share = msg2_vec.clone().any(|(sender, receiver, secret_share)| {
+            sender == sender_index
+                && receiver == party_index
+                && vss.validate_share(secret_share, party_index).is_ok()
+        });
+        if !valid_share {
+            return Err(InvalidShare {
+                party: sender_index,
+            });
+        }
+    }
+    Ok(())
+}
+
+// at least one party and fewer signers needed than there are parties
+pub(crate) fn valid_parameters(params: &Parameters) -> bool {
+    params.threshold < params.share_count
+}
+
+fn valid_party_indices(params: &Parameters, bc1_vec: &[KeyGenBroadcastMessage1]) -> bool {
+    all_parties_present(params, bc1_vec.iter().map(|bc1| bc1.party_index))
+}
+
+// every party in 1..=n has to appear exactly once
+pub(crate) fn all_parties_present(params: &Parameters, indices: impl Iterator<Item = u16>) -> bool {
+    let mut indices: Vec<_> = indices.collect();
+    indices.sort_unstable();
+    indices.into_iter().eq(1..=params.share_count)
+}
+
+// the context is the length prefixed session id and the party index
+fn proof_challenge(
+    sid: &[u8],
+    party_index: u16,
+    A: &Point<Ed25519>,
+    R: &Point<Ed25519>,
+) -> Scalar<Ed25519> {
+    Sha512::new()
+        .chain([4])
+        .chain((sid.len() as u64).to_be_bytes())
+        .chain(sid)
+        .chain(party_index.to_be_bytes())
+        .chain_point(A)
+        .chain_point(R)
+        .result_scalar()
+}
+
+fn verify_proof_of_knowledge(
+    sid: &[u8],
+    party_index: u16,
+    A: &Point<Ed25519>,
+    proof: &ProofOfKnowledge,
+) -> bool {
+    let c = proof_challenge(sid, party_index, A, &proof.R);
+    &proof.mu * Point::generator() == &proof.R + A * c
+}
+
+pub(crate) fn sample_polynomial(
+    constant_term: Scalar<Ed25519>,
+    degree: u16,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Vec<Scalar<Ed25519>> {
+    std::iter::once(constant_term)
+        .chain((0..degree).map(|_| random_scalar(rng)))
+        .collect()
+}
+
+pub(crate) fn evaluate_polynomial(coefficients: &[Scalar<Ed25519>], index: u16) -> Scalar<Ed25519> {
+    let x = Scalar::from(index);
+    coefficients
+        .iter()
+        .rev()
+        .fold(Scalar::zero(), |acc, coefficient| acc * &x + coefficient)
+}
+
+pub(crate) mod test;
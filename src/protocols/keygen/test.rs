@@ -0,0 +1,212 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+#[cfg(test)]
+pub(crate) mod tests {
+    use curv::elliptic::curves::{Point, Scalar};
+    use protocols::keygen::{lagrange_coefficient, Keys, Parameters, SharedKeys};
+    use protocols::tests::deterministic_fast_rand;
+    use rand::{CryptoRng, Rng, RngCore};
+    use Error;
+
+    const SID: &[u8] = b"keygen session";
+
+    #[test]
+    fn test_keygen_t_n() {
+        let mut rng = deterministic_fast_rand("test_keygen_t_n", None);
+        for n in 1..6 {
+            for t in 0..n {
+                let params = Parameters {
+                    threshold: t,
+                    share_count: n,
+                };
+                let shared_keys = keygen_t_n_parties(&params, &mut rng);
+                // all parties agree on y and on everyone's public share
+                for keys in &shared_keys {
+                    assert_eq!(keys.y, shared_keys[0].y);
+                    assert_eq!(keys.vss, shared_keys[0].vss);
+                    assert_eq!(
+                        Point::generator() * &keys.x_i,
+                        shared_keys[0].verification_share(keys.party_index)
+                    );
+                }
+                // any t+1 shares interpolate to the joint secret
+                let indices: Vec<_> = (1..=t + 1).collect();
+                let x = indices.iter().fold(Scalar::zero(), |acc, &i| {
+                    acc + lagrange_coefficient(i, &indices) * &shared_keys[usize::from(i - 1)].x_i
+                });
+                assert_eq!(Point::generator() * x, shared_keys[0].y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_keygen_reject_bad_proof() {
+        let mut rng = deterministic_fast_rand("test_keygen_reject_bad_proof", None);
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let parties: Vec<_> = (1..=params.share_count)
+            .map(|i| Keys::phase1_create_from_private_key(&params, i, rng.gen()).unwrap())
+            .collect();
+        let mut bc1_vec: Vec<_> = parties
+            .iter()
+            .map(|party| party.phase1_broadcast_rng(SID, &mut rng))
+            .collect();
+        // party 3 claims party 2's constant term as its own
+        bc1_vec[2].vss.commitments[0] = bc1_vec[1].vss.commitments[0].clone();
+        assert_eq!(
+            parties[0]
+                .phase1_verify_proofs_phase2_distribute(&params, SID, &bc1_vec)
+                .err(),
+            Some(Error::InvalidProof { party: 3 })
+        );
+    }
+
+    #[test]
+    fn test_keygen_reject_proof_of_other_session() {
+        let mut rng = deterministic_fast_rand("test_keygen_reject_proof_of_other_session", None);
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let parties: Vec<_> = (1..=params.share_count)
+            .map(|i| Keys::phase1_create_from_private_key(&params, i, rng.gen()).unwrap())
+            .collect();
+        let mut bc1_vec: Vec<_> = parties
+            .iter()
+            .map(|party| party.phase1_broadcast_rng(SID, &mut rng))
+            .collect();
+        // party 2 replays its broadcast of an earlier key generation
+        bc1_vec[1] = parties[1].phase1_broadcast_rng(b"earlier session", &mut rng);
+        assert_eq!(
+            parties[0]
+                .phase1_verify_proofs_phase2_distribute(&params, SID, &bc1_vec)
+                .err(),
+            Some(Error::InvalidProof { party: 2 })
+        );
+    }
+
+    #[test]
+    fn test_keygen_reject_bad_parameters() {
+        let mut rng = deterministic_fast_rand("test_keygen_reject_bad_parameters", None);
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        for party_index in [0, 4] {
+            assert!(Keys::phase1_create_rng(&params, party_index, &mut rng).is_err());
+        }
+        for (threshold, share_count) in [(0, 0), (3, 3), (4, 3)] {
+            let params = Parameters {
+                threshold,
+                share_count,
+            };
+            assert_eq!(
+                Keys::phase1_create_rng(&params, 1, &mut rng).err(),
+                Some(Error::RoundMismatch)
+            );
+        }
+    }
+
+    #[test]
+    fn test_keygen_reject_missing_party() {
+        let mut rng = deterministic_fast_rand("test_keygen_reject_missing_party", None);
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let parties: Vec<_> = (1..=params.share_count)
+            .map(|i| Keys::phase1_create_from_private_key(&params, i, rng.gen()).unwrap())
+            .collect();
+        let mut bc1_vec: Vec<_> = parties
+            .iter()
+            .map(|party| party.phase1_broadcast_rng(SID, &mut rng))
+            .collect();
+        // party 3's broadcast is replaced by a second copy of party 2's
+        bc1_vec[2] = bc1_vec[1].clone();
+        assert_eq!(
+            parties[0]
+                .phase1_verify_proofs_phase2_distribute(&params, SID, &bc1_vec)
+                .err(),
+            Some(Error::RoundMismatch)
+        );
+    }
+
+    #[test]
+    fn test_keygen_reject_bad_share() {
+        let mut rng = deterministic_fast_rand("test_keygen_reject_bad_share", None);
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let parties: Vec<_> = (1..=params.share_count)
+            .map(|i| Keys::phase1_create_from_private_key(&params, i, rng.gen()).unwrap())
+            .collect();
+        let bc1_vec: Vec<_> = parties
+            .iter()
+            .map(|party| party.phase1_broadcast_rng(SID, &mut rng))
+            .collect();
+        let mut msgs_to_party_1: Vec<_> = parties
+            .iter()
+            .map(|party| {
+                party
+                    .phase1_verify_proofs_phase2_distribute(&params, SID, &bc1_vec)
+                    .unwrap()
+                    .remove(0)
+            })
+            .collect();
+        msgs_to_party_1[2].secret_share = Scalar::random();
+        assert_eq!(
+            parties[0]
+                .phase2_verify_shares_construct_keypair(&params, &bc1_vec, &msgs_to_party_1)
+                .err(),
+            Some(Error::InvalidShare { party: 3 })
+        );
+    }
+
+    pub fn keygen_t_n_parties(
+        params: &Parameters,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Vec<SharedKeys> {
+        let parties: Vec<_> = (1..=params.share_count)
+            .map(|i| Keys::phase1_create_from_private_key(params, i, rng.gen()).unwrap())
+            .collect();
+        let bc1_vec: Vec<_> = parties
+            .iter()
+            .map(|party| party.phase1_broadcast_rng(SID, rng))
+            .collect();
+        let msg2_vecs: Vec<_> = parties
+            .iter()
+            .map(|party| {
+                party
+                    .phase1_verify_proofs_phase2_distribute(params, SID, &bc1_vec)
+                    .unwrap()
+            })
+            .collect();
+        parties
+            .iter()
+            .map(|party| {
+                let received: Vec<_> = msg2_vecs
+                    .iter()
+                    .flatten()
+                    .filter(|msg2| msg2.receiver_index == party.party_index)
+                    .cloned()
+                    .collect();
+                party
+                    .phase2_verify_shares_construct_keypair(params, &bc1_vec, &received)
+                    .unwrap()
+            })
+            .collect()
+    }
+}
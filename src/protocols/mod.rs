@@ -14,60 +14,255 @@
 
     @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
 */
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+
 use curv::arithmetic::Converter;
-use curv::cryptographic_primitives::proofs::ProofError;
 use curv::elliptic::curves::{Ed25519, Point, Scalar};
 use curv::BigInt;
-use rand::{thread_rng, Rng};
+use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint, VartimeEdwardsPrecomputation};
+use curve25519_dalek::scalar::Scalar as DalekScalar;
+use curve25519_dalek::traits::{IsIdentity, VartimePrecomputedMultiscalarMul};
+use hmac::{Hmac, Mac, NewMac};
+#[cfg(feature = "transcript")]
+use merlin::Transcript;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::{CryptoRng, Rng, RngCore};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use sha2::digest::consts::U64;
 use sha2::{Digest, Sha512};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+use {Error, VerifyError};
 
 // simple ed25519 based on rfc8032
 // reference implementation: https://ed25519.cr.yp.to/python/ed25519.py
 pub mod aggsig;
+mod backend;
+pub mod bip340;
+pub mod blind;
+#[cfg(feature = "ed448")]
+pub mod ed448;
+#[cfg(feature = "std")]
+pub mod frost;
+pub mod halfagg;
+#[cfg(feature = "std")]
+pub mod keygen;
+#[cfg(feature = "std")]
 pub mod multisig;
 pub mod musig2;
+#[cfg(feature = "std")]
+pub mod refresh;
+#[cfg(feature = "std")]
+pub mod repair;
+#[cfg(feature = "std")]
+pub mod reshare;
+pub mod ristretto;
+pub mod rounds;
+pub mod schnorr;
+pub mod simulate;
+#[cfg(feature = "transcript")]
+pub mod sr25519;
+#[cfg(feature = "std")]
 pub mod thresholdsig;
+pub mod two_party;
+pub mod vrf;
+pub mod wire;
+
+#[cfg(feature = "parallel")]
+pub(crate) use self::backend::par_multiscalar_mul;
+pub(crate) use self::backend::{base_mul, encode_point, multiscalar_mul, verification_equation};
+use self::backend::{decompress, to_dalek_scalar};
+
+/// Set on the indices of hardened children, the only kind SLIP-0010 defines for Ed25519.
+pub const HARDENED: u32 = 0x8000_0000;
+
+// signatures per multi-scalar multiplication of a parallel `Signature::batch_verify`
+#[cfg(feature = "parallel")]
+const BATCH_CHUNK: usize = 64;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ExpandedPrivateKey {
     pub prefix: Scalar<Ed25519>,
-    private_key: Scalar<Ed25519>,
+    pub(crate) private_key: Scalar<Ed25519>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ExpandedKeyPair {
     pub public_key: Point<Ed25519>,
-    expanded_private_key: ExpandedPrivateKey,
+    pub(crate) expanded_private_key: ExpandedPrivateKey,
+    // missing from pairs serialized before the seed was kept
+    #[serde(default)]
+    seed: Option<Seed>,
+}
+
+/// The 32 byte RFC8032 private key, scrubbed on drop.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Seed([u8; 32]);
+
+impl Drop for Seed {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+// curv scalars zeroize themselves on drop, so scrubbing a secret scalar field
+// is done by overwriting it, which drops the old value.
+impl Zeroize for ExpandedPrivateKey {
+    fn zeroize(&mut self) {
+        self.prefix = Scalar::zero();
+        self.private_key = Scalar::zero();
+    }
+}
+
+impl ZeroizeOnDrop for ExpandedPrivateKey {}
+
+impl Zeroize for ExpandedKeyPair {
+    fn zeroize(&mut self) {
+        self.public_key = Point::zero();
+        self.expanded_private_key.zeroize();
+        self.seed = None;
+    }
+}
+
+impl ZeroizeOnDrop for ExpandedKeyPair {}
+
+/// `CryptoRng + RngCore` as a single trait, so a caller supplied rng can be held as a trait object.
+pub trait CryptoRngCore: CryptoRng + RngCore {}
+
+impl<R: CryptoRng + RngCore + ?Sized> CryptoRngCore for R {}
+
+// 64 random bytes reduced mod l, so the result is uniform
+pub(crate) fn random_scalar(rng: &mut (impl CryptoRng + RngCore)) -> Scalar<Ed25519> {
+    let mut bytes = [0u8; 64];
+    rng.fill_bytes(&mut bytes);
+    let scalar = Scalar::from_bigint(&BigInt::from_bytes(&bytes));
+    bytes.zeroize();
+    scalar
 }
 
 impl ExpandedKeyPair {
+    #[cfg(feature = "std")]
     pub fn create() -> ExpandedKeyPair {
-        let secret = thread_rng().gen();
-        Self::create_from_private_key(secret)
+        Self::create_rng(&mut thread_rng())
+    }
+
+    pub fn create_rng(rng: &mut (impl CryptoRng + RngCore)) -> ExpandedKeyPair {
+        let mut secret: [u8; 32] = rng.gen();
+        let keypair = Self::create_from_private_key(secret);
+        secret.zeroize();
+        keypair
+    }
+
+    /// Same as `create_from_private_key`, for seeds from other Ed25519 implementations.
+    pub fn from_seed_bytes(seed: [u8; 32]) -> ExpandedKeyPair {
+        Self::create_from_private_key(seed)
+    }
+
+    /// The 32 byte private key the pair was created from, `None` if it was deserialized from
+    /// a version that didn't keep it.
+    pub fn to_seed_bytes(&self) -> Option<[u8; 32]> {
+        self.seed.as_ref().map(|seed| seed.0)
+    }
+
+    /// A pair without a seed, for expanded keys from elsewhere.
+    #[cfg(feature = "tor")]
+    pub(crate) fn from_expanded_private_key(
+        expanded_private_key: ExpandedPrivateKey,
+    ) -> ExpandedKeyPair {
+        ExpandedKeyPair {
+            public_key: base_mul(&expanded_private_key.private_key),
+            expanded_private_key,
+            seed: None,
+        }
     }
 
-    pub fn create_from_private_key(secret: [u8; 32]) -> ExpandedKeyPair {
-        let h = Sha512::new().chain(secret).finalize();
-        let mut private_key: [u8; 32] = [0u8; 32];
-        let mut prefix: [u8; 32] = [0u8; 32];
-        prefix.copy_from_slice(&h[32..64]);
-        private_key.copy_from_slice(&h[0..32]);
-        private_key[0] &= 248;
-        private_key[31] &= 63;
-        private_key[31] |= 64;
-        let private_key = Scalar::from_bytes(&private_key)
+    pub fn create_from_private_key(mut secret: [u8; 32]) -> ExpandedKeyPair {
+        let mut h = Sha512::new().chain(secret).finalize();
+        let seed = Seed(secret);
+        secret.zeroize();
+        let mut private_key_bytes: [u8; 32] = [0u8; 32];
+        let mut prefix_bytes: [u8; 32] = [0u8; 32];
+        prefix_bytes.copy_from_slice(&h[32..64]);
+        private_key_bytes.copy_from_slice(&h[0..32]);
+        private_key_bytes[0] &= 248;
+        private_key_bytes[31] &= 63;
+        private_key_bytes[31] |= 64;
+        let private_key = Scalar::from_bytes(&private_key_bytes)
             .expect("private_key is the right length, so can't fail");
         let prefix =
-            Scalar::from_bytes(&prefix).expect("prefix is the right length, so can't fail");
-        let public_key = Point::generator() * &private_key;
+            Scalar::from_bytes(&prefix_bytes).expect("prefix is the right length, so can't fail");
+        h.as_mut_slice().zeroize();
+        private_key_bytes.zeroize();
+        prefix_bytes.zeroize();
+        let public_key = base_mul(&private_key);
         ExpandedKeyPair {
             public_key,
             expanded_private_key: ExpandedPrivateKey {
                 prefix,
                 private_key,
             },
+            seed: Some(seed),
+        }
+    }
+
+    /// SLIP-0010 derivation of the key at `path` below `seed`, `m/44'/0'/1'` is `&[44, 0, 1]`.
+    /// Every index is hardened, whether or not it already has `HARDENED` set.
+    pub fn derive_hardened(seed: &[u8], path: &[u32]) -> ExpandedKeyPair {
+        let mut node = hmac_sha512(b"ed25519 seed", &[seed]);
+        for index in path {
+            let (key, chain_code) = node.split_at(32);
+            let child = hmac_sha512(chain_code, &[&[0], key, &(index | HARDENED).to_be_bytes()]);
+            node.zeroize();
+            node = child;
         }
+        let mut secret = [0u8; 32];
+        secret.copy_from_slice(&node[..32]);
+        node.zeroize();
+        Self::create_from_private_key(secret)
+    }
+}
+
+pub(crate) fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC takes keys of any length");
+    for part in data {
+        mac.update(part);
     }
+    let mut tag = mac.finalize().into_bytes();
+    let mut out = [0u8; 64];
+    out.copy_from_slice(&tag);
+    tag.as_mut_slice().zeroize();
+    out
+}
+
+/// How `Signature::verify_bytes_with` checks the encoded signature. In both modes the public
+/// key and `R` have to be canonical encodings of points.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VerifyMode {
+    /// The checks of `verify_bytes`, as chains with strict validation do: `s < L`, `R` isn't
+    /// the identity and both points are in the prime order subgroup.
+    Strict,
+    /// The checks of the original ref10 code: only the top 3 bits of `s` have to be zero and
+    /// `s` is reduced mod `L`, so `s + L` verifies too. `R` and the public key may have a torsion
+    /// component, only a public key of small order is rejected.
+    Legacy,
+}
+
+/// Which verification equation `Signature::verify_bytes_with` checks in `VerifyMode::Legacy`,
+/// chains differ in whether they multiply it by the cofactor 8. The equations only disagree on
+/// points with a torsion component, which `VerifyMode::Strict` rejects, so there the policy
+/// makes no difference.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VerificationPolicy {
+    /// `s * G == R + k * A`
+    Cofactorless,
+    /// `8 * s * G == 8 * R + 8 * k * A`
+    Cofactored,
+    /// Both equations have to hold, a signature valid under either rule.
+    Both,
 }
 
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
@@ -77,27 +272,410 @@ pub struct Signature {
 }
 
 impl Signature {
-    pub fn verify(&self, message: &[u8], public_key: &Point<Ed25519>) -> Result<(), ProofError> {
-        let k = Self::k(&self.R, public_key, message);
-        let A = public_key;
+    /// A `Verifier` of this signature under `public_key`, the message is fed to it afterwards.
+    pub fn verifier(&self, public_key: &Point<Ed25519>) -> Verifier {
+        Verifier {
+            signature: self.clone(),
+            public_key: public_key.clone(),
+            hasher: Self::challenge_hasher(&self.R, public_key),
+        }
+    }
 
-        let kA = A * k;
-        let R_plus_kA = &kA + &self.R;
-        let sG = &self.s * Point::generator();
+    /// RFC8032 verification (section 5.1.7) of a decoded signature. A decoded `s` is always
+    /// reduced, `s < L` is checked when decoding, by `Signature::from_bytes` and `verify_bytes`.
+    pub fn verify(&self, message: &[u8], public_key: &Point<Ed25519>) -> Result<(), VerifyError> {
+        self.verify_with_challenge(public_key, Self::k(&self.R, public_key, message))
+    }
 
-        if R_plus_kA == sG {
+    /// `verify` of a signature with the challenge hashed with `D` instead of SHA-512, e.g.
+    /// `sha3::Sha3_512`, as made by `aggsig::partial_sign_with_digest`. Only `D = Sha512` gives
+    /// RFC8032 signatures.
+    pub fn verify_with_digest<D: Digest<OutputSize = U64>>(
+        &self,
+        message: &[u8],
+        public_key: &Point<Ed25519>,
+    ) -> Result<(), VerifyError> {
+        self.verify_with_challenge(
+            public_key,
+            Self::k_with_digest::<D>(&self.R, public_key, message),
+        )
+    }
+
+    /// Ed25519ph verification, `prehashed_message` is SHA-512 of the message.
+    pub fn verify_ph(
+        &self,
+        prehashed_message: &[u8; 64],
+        public_key: &Point<Ed25519>,
+    ) -> Result<(), VerifyError> {
+        self.verify_with_challenge(
+            public_key,
+            Self::k_ph(&self.R, public_key, prehashed_message),
+        )
+    }
+
+    /// Ed25519ctx verification, `context` is the application's context string (at most 255 bytes).
+    pub fn verify_ctx(
+        &self,
+        message: &[u8],
+        context: &[u8],
+        public_key: &Point<Ed25519>,
+    ) -> Result<(), VerifyError> {
+        let k = Self::k_ctx(&self.R, public_key, message, context)
+            .ok_or(VerifyError::InvalidSignature)?;
+        self.verify_with_challenge(public_key, k)
+    }
+
+    /// Verification of a signature made with `aggsig::transcript`, under the same `transcript`.
+    /// These are not RFC8032 signatures, `verify` rejects them.
+    #[cfg(feature = "transcript")]
+    pub fn verify_transcript(
+        &self,
+        message: &[u8],
+        transcript: &Transcript,
+        public_key: &Point<Ed25519>,
+    ) -> Result<(), VerifyError> {
+        self.verify_with_challenge(
+            public_key,
+            Self::k_transcript(transcript, &self.R, public_key, message),
+        )
+    }
+
+    fn verify_with_challenge(
+        &self,
+        public_key: &Point<Ed25519>,
+        k: Scalar<Ed25519>,
+    ) -> Result<(), VerifyError> {
+        if public_key.is_zero() {
+            return Err(VerifyError::InvalidPublicKey);
+        }
+        if verification_equation(&self.s, &self.R, &k, public_key, false) {
             Ok(())
         } else {
-            Err(ProofError)
+            Err(VerifyError::InvalidSignature)
         }
     }
 
+    /// `verify` under a key prepared with `PreparedPublicKey::new`, which gives the same result
+    /// faster for a key that verifies many signatures.
+    pub fn verify_prepared(
+        &self,
+        message: &[u8],
+        public_key: &PreparedPublicKey,
+    ) -> Result<(), VerifyError> {
+        let R = encode_point(&self.R);
+        let k = Self::k_dom_encoded::<Sha512>(&[], &R, &public_key.encoding, message);
+        if public_key.check(&R, &k, &self.s) {
+            Ok(())
+        } else {
+            Err(VerifyError::InvalidSignature)
+        }
+    }
+
+    /// RFC8032 verification of an encoded `R || s` signature under an encoded public key.
+    /// Rejects non-canonical `s` (`s >= L`), an identity `R` and encodings of `R` and the public
+    /// key which don't decode to a valid point or are not the canonical encoding of that point.
+    pub fn verify_bytes(
+        signature: &[u8],
+        message: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), VerifyError> {
+        Self::verify_bytes_with(
+            signature,
+            message,
+            public_key,
+            VerifyMode::Strict,
+            VerificationPolicy::Cofactorless,
+        )
+    }
+
+    /// `verify_bytes` with the encoding checks of `mode`, `VerifyMode::Legacy` accepts the
+    /// signatures of implementations that don't reduce `s` and checks the verification equation
+    /// of `policy`.
+    pub fn verify_bytes_with(
+        signature: &[u8],
+        message: &[u8],
+        public_key: &[u8],
+        mode: VerifyMode,
+        policy: VerificationPolicy,
+    ) -> Result<(), VerifyError> {
+        match mode {
+            VerifyMode::Strict => {
+                let (signature, public_key) = Self::decode(signature, public_key)?;
+                signature.verify(message, &public_key)
+            }
+            VerifyMode::Legacy => Self::verify_legacy(signature, message, public_key, policy),
+        }
+    }
+
+    // `Point<Ed25519>` can't hold a point with a torsion component, so this works on
+    // curve25519-dalek points instead.
+    fn verify_legacy(
+        signature: &[u8],
+        message: &[u8],
+        public_key: &[u8],
+        policy: VerificationPolicy,
+    ) -> Result<(), VerifyError> {
+        if signature.len() != 64 {
+            return Err(VerifyError::InvalidSignature);
+        }
+        let A = decode_torsion_point(public_key)
+            .filter(|A| !A.is_small_order())
+            .ok_or(VerifyError::InvalidPublicKey)?;
+        if signature[63] & 0xe0 != 0 {
+            return Err(VerifyError::NonCanonicalS);
+        }
+        let R = decode_torsion_point(&signature[..32]).ok_or(VerifyError::InvalidR)?;
+        let mut s = [0u8; 32];
+        s.copy_from_slice(&signature[32..]);
+        let s = DalekScalar::from_bytes_mod_order(s);
+        let k = Self::k_dom_encoded::<Sha512>(&[], &signature[..32], public_key, message);
+        let sG_minus_kA =
+            EdwardsPoint::vartime_double_scalar_mul_basepoint(&to_dalek_scalar(&k), &-A, &s);
+        let cofactorless = || sG_minus_kA == R;
+        let cofactored = || (sG_minus_kA - R).mul_by_cofactor().is_identity();
+        let valid = match policy {
+            VerificationPolicy::Cofactorless => cofactorless(),
+            VerificationPolicy::Cofactored => cofactored(),
+            VerificationPolicy::Both => cofactorless() && cofactored(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(VerifyError::InvalidSignature)
+        }
+    }
+
+    /// Ed25519ph version of `verify_bytes`, `prehashed_message` is SHA-512 of the message.
+    pub fn verify_bytes_ph(
+        signature: &[u8],
+        prehashed_message: &[u8; 64],
+        public_key: &[u8],
+    ) -> Result<(), VerifyError> {
+        let (signature, public_key) = Self::decode(signature, public_key)?;
+        signature.verify_ph(prehashed_message, &public_key)
+    }
+
+    /// Ed25519ctx version of `verify_bytes`.
+    pub fn verify_bytes_ctx(
+        signature: &[u8],
+        message: &[u8],
+        context: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), VerifyError> {
+        let (signature, public_key) = Self::decode(signature, public_key)?;
+        signature.verify_ctx(message, context, &public_key)
+    }
+
+    // the checks of `VerifyMode::Strict`
+    fn decode(
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(Signature, Point<Ed25519>), VerifyError> {
+        if signature.len() != 64 {
+            return Err(VerifyError::InvalidSignature);
+        }
+        let public_key = decode_point(public_key).ok_or(VerifyError::InvalidPublicKey)?;
+        let signature = Self::from_bytes(signature)?;
+        if signature.R.is_zero() {
+            return Err(VerifyError::InvalidR);
+        }
+        Ok((signature, public_key))
+    }
+
+    /// The RFC8032 encoding `R || s` of the signature.
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&self.R.to_bytes(true));
+        bytes[32..].copy_from_slice(&self.s.to_bytes());
+        bytes
+    }
+
+    /// Decodes an RFC8032 encoded signature, rejecting non canonical encodings of `R` and `s`.
+    pub fn from_bytes(signature: &[u8]) -> Result<Signature, VerifyError> {
+        if signature.len() != 64 {
+            return Err(VerifyError::InvalidSignature);
+        }
+        let R = decode_point(&signature[..32]).ok_or(VerifyError::InvalidR)?;
+        let mut s = signature[32..].to_vec();
+        // reverse because BigInt uses BigEndian.
+        s.reverse();
+        let s = BigInt::from_bytes(&s);
+        if s >= *Scalar::<Ed25519>::group_order() {
+            return Err(VerifyError::NonCanonicalS);
+        }
+        let s = Scalar::from_bigint(&s);
+        Ok(Signature { R, s })
+    }
+
+    /// Verifies `signatures[i]` over `messages[i]` under `public_keys[i]` for all `i` at once,
+    /// by checking a random linear combination of the verification equations.
+    /// A single invalid signature makes the whole batch fail. Like `verify`, it fails with
+    /// `InvalidPublicKey` if a key is the identity, and like `verify_bytes` with `InvalidR` if an
+    /// `R` is, a forgery under the identity key would satisfy the equation with any message.
+    /// With the `parallel` feature the combination is split into chunks summed on all cores.
+    #[cfg(feature = "std")]
+    pub fn batch_verify(
+        messages: &[&[u8]],
+        signatures: &[Signature],
+        public_keys: &[Point<Ed25519>],
+    ) -> Result<(), VerifyError> {
+        Self::batch_verify_rng(messages, signatures, public_keys, &mut thread_rng())
+    }
+
+    pub fn batch_verify_rng(
+        messages: &[&[u8]],
+        signatures: &[Signature],
+        public_keys: &[Point<Ed25519>],
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<(), VerifyError> {
+        if messages.len() != signatures.len() || signatures.len() != public_keys.len() {
+            return Err(VerifyError::InvalidSignature);
+        }
+        if public_keys.iter().any(|A| check_point(A).is_err()) {
+            return Err(VerifyError::InvalidPublicKey);
+        }
+        if signatures.iter().any(|sig| check_point(&sig.R).is_err()) {
+            return Err(VerifyError::InvalidR);
+        }
+        // 128 bit random weights are enough to make a forgery pass with negligible probability
+        let zs: Vec<Scalar<Ed25519>> = signatures
+            .iter()
+            .map(|_| Scalar::from_bigint(&BigInt::from_bytes(&rng.gen::<[u8; 16]>())))
+            .collect();
+        // the terms of signatures `start..start + zs.len()`
+        let combination = |start: usize, zs: &[Scalar<Ed25519>]| {
+            let end = start + zs.len();
+            Self::batch_combination(
+                &messages[start..end],
+                &signatures[start..end],
+                &public_keys[start..end],
+                zs,
+            )
+        };
+        #[cfg(feature = "parallel")]
+        let sum = zs
+            .par_chunks(BATCH_CHUNK)
+            .enumerate()
+            .map(|(chunk, zs)| combination(chunk * BATCH_CHUNK, zs))
+            .reduce(Point::zero, |sum, term| sum + term);
+        #[cfg(not(feature = "parallel"))]
+        let sum = combination(0, &zs);
+
+        if sum.is_zero() {
+            Ok(())
+        } else {
+            Err(VerifyError::InvalidSignature)
+        }
+    }
+
+    // one multi-scalar multiplication of `-sum(z * s) * G + sum(z * R + z * k * A)`
+    fn batch_combination(
+        messages: &[&[u8]],
+        signatures: &[Signature],
+        public_keys: &[Point<Ed25519>],
+        zs: &[Scalar<Ed25519>],
+    ) -> Point<Ed25519> {
+        let mut sum_zs = Scalar::zero();
+        let mut scalars = Vec::with_capacity(2 * signatures.len() + 1);
+        let mut points = Vec::with_capacity(2 * signatures.len() + 1);
+        for (((message, sig), A), z) in messages.iter().zip(signatures).zip(public_keys).zip(zs) {
+            let k = Self::k(&sig.R, A, message);
+            sum_zs = sum_zs + z * &sig.s;
+            scalars.push(z * k);
+            points.push(A.clone());
+            scalars.push(z.clone());
+            points.push(sig.R.clone());
+        }
+        scalars.push(-sum_zs);
+        points.push(Point::generator().to_point());
+        multiscalar_mul(&scalars, &points)
+    }
+
     pub(crate) fn k(R: &Point<Ed25519>, PK: &Point<Ed25519>, message: &[u8]) -> Scalar<Ed25519> {
-        let mut k = Sha512::new()
+        Self::k_with_digest::<Sha512>(R, PK, message)
+    }
+
+    /// The challenge `H(R || A || M)` with `D` as `H`, its 64 byte output is reduced mod `L`.
+    pub(crate) fn k_with_digest<D: Digest<OutputSize = U64>>(
+        R: &Point<Ed25519>,
+        PK: &Point<Ed25519>,
+        message: &[u8],
+    ) -> Scalar<Ed25519> {
+        Self::k_dom::<D>(&[], R, PK, message)
+    }
+
+    /// The Ed25519ph challenge, `prehashed_message` is SHA-512 of the message.
+    pub(crate) fn k_ph(
+        R: &Point<Ed25519>,
+        PK: &Point<Ed25519>,
+        prehashed_message: &[u8; 64],
+    ) -> Scalar<Ed25519> {
+        Self::k_dom::<Sha512>(
+            &dom2(1, &[]).expect("the context is empty"),
+            R,
+            PK,
+            prehashed_message,
+        )
+    }
+
+    /// The Ed25519ctx challenge, signatures under different contexts are domain separated.
+    /// Returns `None` if the context is longer than 255 bytes.
+    pub(crate) fn k_ctx(
+        R: &Point<Ed25519>,
+        PK: &Point<Ed25519>,
+        message: &[u8],
+        context: &[u8],
+    ) -> Option<Scalar<Ed25519>> {
+        Some(Self::k_dom::<Sha512>(&dom2(0, context)?, R, PK, message))
+    }
+
+    /// The challenge drawn from `transcript` after the round label, `R`, the public key and the message.
+    #[cfg(feature = "transcript")]
+    pub(crate) fn k_transcript(
+        transcript: &Transcript,
+        R: &Point<Ed25519>,
+        PK: &Point<Ed25519>,
+        message: &[u8],
+    ) -> Scalar<Ed25519> {
+        let mut transcript = transcript.clone();
+        transcript.append_message(b"round", b"challenge");
+        transcript.append_message(b"R", &R.to_bytes(true));
+        transcript.append_message(b"A", &PK.to_bytes(true));
+        transcript.append_message(b"message", message);
+        let mut k = [0u8; 64];
+        transcript.challenge_bytes(b"k", &mut k);
+        k.reverse();
+        Scalar::from_bigint(&BigInt::from_bytes(&k))
+    }
+
+    fn k_dom<D: Digest<OutputSize = U64>>(
+        dom: &[u8],
+        R: &Point<Ed25519>,
+        PK: &Point<Ed25519>,
+        message: &[u8],
+    ) -> Scalar<Ed25519> {
+        Self::k_dom_encoded::<D>(dom, &R.to_bytes(true), &PK.to_bytes(true), message)
+    }
+
+    // `k_dom` of the encodings of `R` and the public key
+    fn k_dom_encoded<D: Digest<OutputSize = U64>>(
+        dom: &[u8],
+        R: &[u8],
+        PK: &[u8],
+        message: &[u8],
+    ) -> Scalar<Ed25519> {
+        Self::k_from_hasher(D::new().chain(dom).chain(R).chain(PK).chain(message))
+    }
+
+    /// SHA-512 of `R || A`, the challenge is this hash of the message.
+    pub(crate) fn challenge_hasher(R: &Point<Ed25519>, PK: &Point<Ed25519>) -> Sha512 {
+        Sha512::new()
             .chain(&*R.to_bytes(true))
             .chain(&*PK.to_bytes(true))
-            .chain(message)
-            .finalize();
+    }
+
+    pub(crate) fn k_from_hasher<D: Digest<OutputSize = U64>>(hasher: D) -> Scalar<Ed25519> {
+        let mut k = hasher.finalize();
         // reverse because BigInt uses BigEndian.
         k.reverse();
         // This will reduce it mod the group order.
@@ -105,34 +683,198 @@ impl Signature {
     }
 }
 
+/// `Signature::verify` of a message that is fed in chunks with `update`, or written with
+/// `std::io::copy`, e.g. a multi-gigabyte file that shouldn't be buffered in memory.
+pub struct Verifier {
+    signature: Signature,
+    public_key: Point<Ed25519>,
+    hasher: Sha512,
+}
+
+impl Verifier {
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.hasher.update(chunk);
+    }
+
+    /// Verifies the signature over all the chunks so far, in order.
+    pub fn finalize(self) -> Result<(), VerifyError> {
+        let k = Signature::k_from_hasher(self.hasher);
+        self.signature.verify_with_challenge(&self.public_key, k)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Write for Verifier {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Verifies `signatures[i]` over `messages[i]` under `public_keys[i]`, all different messages,
+/// e.g. for the signatures of a mempool, see `Signature::batch_verify`.
+/// Signatures of `aggsig` are over a single message under the aggregated key, verify them with
+/// `Signature::verify`.
+#[cfg(feature = "std")]
+pub fn verify_aggregate(
+    messages: &[&[u8]],
+    signatures: &[Signature],
+    public_keys: &[Point<Ed25519>],
+) -> Result<(), VerifyError> {
+    Signature::batch_verify(messages, signatures, public_keys)
+}
+
+pub fn verify_aggregate_rng(
+    messages: &[&[u8]],
+    signatures: &[Signature],
+    public_keys: &[Point<Ed25519>],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<(), VerifyError> {
+    Signature::batch_verify_rng(messages, signatures, public_keys, rng)
+}
+
+/// A public key that verifies many signatures, e.g. a validator's: its encoding, which every
+/// challenge hashes, and tables of multiples of the key and the generator, so
+/// `Signature::verify_prepared` is one precomputed multi-scalar multiplication.
+pub struct PreparedPublicKey {
+    public_key: Point<Ed25519>,
+    encoding: [u8; 32],
+    // multiples of the key and of the generator, in that order
+    table: VartimeEdwardsPrecomputation,
+}
+
+impl PreparedPublicKey {
+    /// Fails with `InvalidPublicKey` for the identity, which `Signature::verify` rejects.
+    pub fn new(public_key: &Point<Ed25519>) -> Result<PreparedPublicKey, VerifyError> {
+        if public_key.is_zero() {
+            return Err(VerifyError::InvalidPublicKey);
+        }
+        let encoding = encode_point(public_key);
+        let table =
+            VartimeEdwardsPrecomputation::new([decompress(&encoding), ED25519_BASEPOINT_POINT]);
+        Ok(PreparedPublicKey {
+            public_key: public_key.clone(),
+            encoding,
+            table,
+        })
+    }
+
+    pub fn public_key(&self) -> &Point<Ed25519> {
+        &self.public_key
+    }
+
+    // `s * G == R + k * A` with `R` encoded, as `k * A - s * G + R` is the identity
+    pub(crate) fn check(&self, R: &[u8; 32], k: &Scalar<Ed25519>, s: &Scalar<Ed25519>) -> bool {
+        self.table
+            .vartime_mixed_multiscalar_mul(
+                &[to_dalek_scalar(k), -to_dalek_scalar(s)],
+                &[DalekScalar::one()],
+                &[decompress(R)],
+            )
+            .is_identity()
+    }
+}
+
+impl fmt::Debug for PreparedPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PreparedPublicKey")
+            .field("public_key", &self.public_key)
+            .finish()
+    }
+}
+
+// dom2(phflag, context) of RFC8032 section 2, prefixed to every hash of Ed25519ph and Ed25519ctx
+// `None` if the context is longer than 255 bytes
+pub(crate) fn dom2(phflag: u8, context: &[u8]) -> Option<Vec<u8>> {
+    let context_len = u8::try_from(context.len()).ok()?;
+    let mut dom = b"SigEd25519 no Ed25519 collisions".to_vec();
+    dom.push(phflag);
+    dom.push(context_len);
+    dom.extend_from_slice(context);
+    Some(dom)
+}
+
+/// Checks a public key or nonce `R` received from another party. Fails with `InvalidPoint` for
+/// the identity, the only point of small order a `Point<Ed25519>` can be: decoding (including
+/// deserialization) already rejects every point outside the prime order subgroup, i.e. the other
+/// small order points and any point with a torsion component.
+pub fn check_point(point: &Point<Ed25519>) -> Result<(), Error> {
+    if point.is_zero() {
+        Err(Error::InvalidPoint)
+    } else {
+        Ok(())
+    }
+}
+
+// 32 byte compressed encoding, which has to be the canonical encoding of the decoded point
+pub(crate) fn decode_point(bytes: &[u8]) -> Option<Point<Ed25519>> {
+    if bytes.len() != 32 {
+        return None;
+    }
+    let point = Point::from_bytes(bytes).ok()?;
+    if *point.to_bytes(true) == *bytes {
+        Some(point)
+    } else {
+        None
+    }
+}
+
+// `decode_point` of a point which may have a torsion component
+fn decode_torsion_point(bytes: &[u8]) -> Option<EdwardsPoint> {
+    let bytes = <[u8; 32]>::try_from(bytes).ok()?;
+    let point = CompressedEdwardsY(bytes).decompress()?;
+    if point.compress().to_bytes() == bytes {
+        Some(point)
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
+    use std::io::Write;
 
-    use curv::elliptic::curves::{Ed25519, Point, Scalar};
+    use curv::elliptic::curves::ed25519::Ed25519Point;
+    use curv::elliptic::curves::{ECPoint, Ed25519, Point, Scalar};
+    use curve25519_dalek::constants::{ED25519_BASEPOINT_POINT, EIGHT_TORSION};
+    use curve25519_dalek::edwards::EdwardsPoint;
+    use curve25519_dalek::scalar::Scalar as DalekScalar;
     use ed25519_dalek::Verifier;
-    use rand::{thread_rng, Rng};
-    use rand_xoshiro::rand_core::{RngCore, SeedableRng};
-    use rand_xoshiro::Xoshiro256PlusPlus;
+    use rand::rngs::StdRng;
+    use rand::{thread_rng, CryptoRng, Rng, RngCore, SeedableRng};
 
-    use protocols::{ExpandedKeyPair, Signature};
+    use curv::arithmetic::Converter;
+    use curv::BigInt;
+    use protocols::backend::to_dalek_scalar;
+    use protocols::{
+        aggsig, base_mul, check_point, decode_point, multiscalar_mul, verification_equation,
+        verify_aggregate, verify_aggregate_rng, ExpandedKeyPair, PreparedPublicKey, Signature,
+        VerificationPolicy, VerifyMode, HARDENED,
+    };
+    use sha2::Sha512;
+    use zeroize::Zeroize;
+    use {Error, VerifyError};
 
     pub fn verify_dalek(pk: &Point<Ed25519>, sig: &Signature, msg: &[u8]) -> bool {
-        let mut sig_bytes = [0u8; 64];
-        sig_bytes[..32].copy_from_slice(&*sig.R.to_bytes(true));
-        sig_bytes[32..].copy_from_slice(&sig.s.to_bytes());
+        let sig_bytes = sig.to_bytes();
 
-        let dalek_pub = ed25519_dalek::PublicKey::from_bytes(&*pk.to_bytes(true)).unwrap();
+        let dalek_pub = ed25519_dalek::PublicKey::from_bytes(&pk.to_bytes(true)).unwrap();
         let dalek_sig = ed25519_dalek::Signature::from_bytes(&sig_bytes).unwrap();
 
         dalek_pub.verify(msg, &dalek_sig).is_ok()
     }
 
-    /// This will generate a fast deterministic rng and will print the seed,
+    /// This will generate a deterministic rng and will print the seed,
     /// if a test fails, pass in the printed seed to reproduce.
-    pub fn deterministic_fast_rand(name: &str, seed: Option<u64>) -> impl Rng {
+    /// It's a `CryptoRng`, so it can be passed to every `_rng` function.
+    pub fn deterministic_fast_rand(name: &str, seed: Option<u64>) -> impl CryptoRng + RngCore {
         let seed = seed.unwrap_or_else(|| thread_rng().gen());
         println!("{} seed: {}", name, seed);
-        Xoshiro256PlusPlus::seed_from_u64(seed)
+        StdRng::seed_from_u64(seed)
     }
 
     #[test]
@@ -181,4 +923,533 @@ pub(crate) mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_seed_bytes() {
+        let mut rng = deterministic_fast_rand("test_seed_bytes", None);
+        let seed: [u8; 32] = rng.gen();
+        let keypair = ExpandedKeyPair::from_seed_bytes(seed);
+        assert_eq!(keypair.to_seed_bytes(), Some(seed));
+        assert_eq!(
+            keypair.public_key,
+            ExpandedKeyPair::create_from_private_key(seed).public_key
+        );
+
+        // pairs serialized without the seed still load
+        let mut json: serde_json::Value = serde_json::to_value(&keypair).unwrap();
+        json.as_object_mut().unwrap().remove("seed");
+        let old: ExpandedKeyPair = serde_json::from_value(json).unwrap();
+        assert_eq!(old.public_key, keypair.public_key);
+        assert_eq!(old.to_seed_bytes(), None);
+    }
+
+    #[test]
+    fn test_zeroize_keypair() {
+        let mut keypair = ExpandedKeyPair::create();
+        assert!(!keypair.expanded_private_key.private_key.is_zero());
+        keypair.zeroize();
+        assert!(keypair.public_key.is_zero());
+        assert!(keypair.to_seed_bytes().is_none());
+        assert!(keypair.expanded_private_key.prefix.is_zero());
+        assert!(keypair.expanded_private_key.private_key.is_zero());
+    }
+
+    #[test]
+    fn test_batch_verify() {
+        let mut rng = deterministic_fast_rand("test_batch_verify", None);
+
+        let mut msgs = [[0u8; 32]; 16];
+        let keypairs: Vec<_> = (0..msgs.len())
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        msgs.iter_mut().for_each(|msg| rng.fill_bytes(msg));
+        let msgs: Vec<&[u8]> = msgs.iter().map(|msg| &msg[..]).collect();
+        let public_keys: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let mut sigs: Vec<_> = msgs
+            .iter()
+            .zip(keypairs.iter())
+            .map(|(msg, keypair)| aggsig::sign_single(msg, keypair))
+            .collect();
+
+        Signature::batch_verify(&msgs, &sigs, &public_keys).unwrap();
+        Signature::batch_verify(&[], &[], &[]).unwrap();
+        // mismatched lengths
+        assert!(Signature::batch_verify(&msgs[1..], &sigs, &public_keys).is_err());
+        // signatures checked against the wrong keys
+        let mut swapped_public_keys = public_keys.clone();
+        swapped_public_keys.swap(0, 1);
+        assert!(Signature::batch_verify(&msgs, &sigs, &swapped_public_keys).is_err());
+        // a single bad signature
+        sigs[7].s = &sigs[7].s + Scalar::from(1);
+        assert!(Signature::batch_verify(&msgs, &sigs, &public_keys).is_err());
+    }
+
+    #[test]
+    fn test_batch_verify_reject_identity() {
+        let mut rng = deterministic_fast_rand("test_batch_verify_reject_identity", None);
+        let keypair = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let msgs: [&[u8]; 2] = [b"honest", b"forged"];
+        let honest = aggsig::sign_single(msgs[0], &keypair);
+        // `s * G == R + k * A` holds for any message under the identity key
+        let r = Scalar::random();
+        let forged = Signature {
+            R: Point::generator() * &r,
+            s: r,
+        };
+        let sigs = [honest.clone(), forged.clone()];
+        let public_keys = [keypair.public_key.clone(), Point::zero()];
+        assert_eq!(
+            forged.verify(msgs[1], &public_keys[1]),
+            Err(VerifyError::InvalidPublicKey)
+        );
+        assert_eq!(
+            Signature::batch_verify(&msgs, &sigs, &public_keys),
+            Err(VerifyError::InvalidPublicKey)
+        );
+
+        let mut identity_R = honest;
+        identity_R.R = Point::zero();
+        assert_eq!(
+            Signature::batch_verify(&msgs[..1], &[identity_R], &public_keys[..1]),
+            Err(VerifyError::InvalidR)
+        );
+    }
+
+    #[test]
+    fn test_batch_verify_chunks() {
+        // more signatures than the parallel batch verification checks in one chunk
+        let mut rng = deterministic_fast_rand("test_batch_verify_chunks", None);
+        let keypairs: Vec<_> = (0..150)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let msgs: Vec<[u8; 8]> = (0..keypairs.len()).map(|_| rng.gen()).collect();
+        let msgs: Vec<&[u8]> = msgs.iter().map(|msg| &msg[..]).collect();
+        let public_keys: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let mut sigs: Vec<_> = msgs
+            .iter()
+            .zip(keypairs.iter())
+            .map(|(msg, keypair)| aggsig::sign_single(msg, keypair))
+            .collect();
+
+        Signature::batch_verify(&msgs, &sigs, &public_keys).unwrap();
+        // a bad signature in the last, partial chunk
+        sigs[140].s = &sigs[140].s + Scalar::from(1);
+        assert!(Signature::batch_verify(&msgs, &sigs, &public_keys).is_err());
+    }
+
+    #[test]
+    fn test_batch_verify_different_messages() {
+        let mut rng = deterministic_fast_rand("test_batch_verify_different_messages", None);
+        let keypairs: Vec<_> = (0..12)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let msgs: Vec<Vec<u8>> = (0..keypairs.len())
+            .map(|i| (0..i).map(|_| rng.gen()).collect())
+            .collect();
+        let msgs: Vec<&[u8]> = msgs.iter().map(|msg| &msg[..]).collect();
+        let public_keys: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let mut sigs: Vec<_> = msgs
+            .iter()
+            .zip(keypairs.iter())
+            .map(|(msg, keypair)| aggsig::sign_single(msg, keypair))
+            .collect();
+
+        Signature::batch_verify_rng(&msgs, &sigs, &public_keys, &mut rng).unwrap();
+        verify_aggregate_rng(&msgs, &sigs, &public_keys, &mut rng).unwrap();
+        verify_aggregate(&[], &[], &[]).unwrap();
+        assert_eq!(
+            Signature::batch_verify(&msgs[1..], &sigs, &public_keys),
+            Err(VerifyError::InvalidSignature)
+        );
+        assert_eq!(
+            verify_aggregate(&msgs[1..], &sigs, &public_keys),
+            Err(VerifyError::InvalidSignature)
+        );
+        let mut zero_key = public_keys.clone();
+        zero_key[3] = Point::zero();
+        assert_eq!(
+            Signature::batch_verify(&msgs, &sigs, &zero_key),
+            Err(VerifyError::InvalidPublicKey)
+        );
+        let mut swapped_msgs = msgs.clone();
+        swapped_msgs.swap(4, 5);
+        assert_eq!(
+            Signature::batch_verify(&swapped_msgs, &sigs, &public_keys),
+            Err(VerifyError::InvalidSignature)
+        );
+        sigs[11].R = &sigs[11].R + Point::generator();
+        assert_eq!(
+            Signature::batch_verify(&msgs, &sigs, &public_keys),
+            Err(VerifyError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_verifier() {
+        let keypair = ExpandedKeyPair::create();
+        let message: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let signature = aggsig::sign_single(&message, &keypair);
+
+        let mut verifier = signature.verifier(&keypair.public_key);
+        for chunk in message.chunks(999) {
+            verifier.update(chunk);
+        }
+        assert_eq!(verifier.finalize(), Ok(()));
+        let mut verifier = signature.verifier(&keypair.public_key);
+        verifier.write_all(&message).unwrap();
+        assert_eq!(verifier.finalize(), Ok(()));
+
+        let mut verifier = signature.verifier(&keypair.public_key);
+        verifier.update(&message[1..]);
+        assert_eq!(verifier.finalize(), Err(VerifyError::InvalidSignature));
+        let verifier = signature.verifier(&Point::zero());
+        assert_eq!(verifier.finalize(), Err(VerifyError::InvalidPublicKey));
+    }
+
+    #[test]
+    fn test_verify_prepared() {
+        let mut rng = deterministic_fast_rand("test_verify_prepared", None);
+        let keypair = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let public_key = PreparedPublicKey::new(&keypair.public_key).unwrap();
+        assert_eq!(public_key.public_key(), &keypair.public_key);
+        assert_eq!(
+            PreparedPublicKey::new(&Point::zero()).unwrap_err(),
+            VerifyError::InvalidPublicKey
+        );
+
+        for i in 0..8u8 {
+            let message = [i; 16];
+            let mut sig = aggsig::sign_single(&message, &keypair);
+            assert_eq!(sig.verify(&message, &keypair.public_key), Ok(()));
+            assert_eq!(sig.verify_prepared(&message, &public_key), Ok(()));
+            assert_eq!(
+                sig.verify_prepared(&message[1..], &public_key),
+                Err(VerifyError::InvalidSignature)
+            );
+            sig.s = &sig.s + Scalar::from(1);
+            assert_eq!(
+                sig.verify_prepared(&message, &public_key),
+                Err(VerifyError::InvalidSignature)
+            );
+        }
+        let other = PreparedPublicKey::new(&ExpandedKeyPair::create().public_key).unwrap();
+        let sig = aggsig::sign_single(b"prepared", &keypair);
+        assert_eq!(
+            sig.verify_prepared(b"prepared", &other),
+            Err(VerifyError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_backend() {
+        // the same results as curv's arithmetic, with either backend
+        for _ in 0..8 {
+            let s: Scalar<Ed25519> = Scalar::random();
+            let k: Scalar<Ed25519> = Scalar::random();
+            let A = Point::generator() * Scalar::random();
+            assert_eq!(base_mul(&s), Point::generator() * &s);
+            let R = Point::generator() * &s - &A * &k;
+            assert!(verification_equation(&s, &R, &k, &A, false));
+            assert!(verification_equation(&s, &R, &k, &A, true));
+            let R = R + Point::generator();
+            assert!(!verification_equation(&s, &R, &k, &A, false));
+            assert!(!verification_equation(&s, &R, &k, &A, true));
+        }
+        assert!(base_mul(&Scalar::zero()).is_zero());
+    }
+
+    #[test]
+    fn test_multiscalar_mul() {
+        let scalars: Vec<Scalar<Ed25519>> = (0..5)
+            .map(|_| Scalar::random())
+            .chain(vec![Scalar::zero(), -Scalar::from(1)])
+            .collect();
+        let points: Vec<_> = (0..scalars.len())
+            .map(|_| Point::generator() * Scalar::random())
+            .collect();
+        let expected = scalars
+            .iter()
+            .zip(&points)
+            .fold(Point::zero(), |sum, (x, P)| sum + P * x);
+        assert_eq!(multiscalar_mul(&scalars, &points), expected);
+        assert!(multiscalar_mul(&[], &[]).is_zero());
+        // the identity as a point and as the sum
+        let P = Point::generator() * Scalar::random();
+        let x = Scalar::random();
+        let cancelling = [P.clone(), P, Point::zero()];
+        assert!(multiscalar_mul(&[x.clone(), -x, Scalar::from(1)], &cancelling).is_zero());
+    }
+
+    #[test]
+    fn test_derive_hardened_slip10_vectors() {
+        // SLIP-0010 test vector 1 for ed25519
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let vectors: [(&[u32], &str); 4] = [
+            (
+                &[],
+                "a4b2856bfec510abab89753fac1ac0e1112364e7d250545963f135f2a33188ed",
+            ),
+            (
+                &[0],
+                "8c8a13df77a28f3445213a0f432fde644acaa215fc72dcdf300d5efaa85d350c",
+            ),
+            (
+                &[0, 1 | HARDENED],
+                "1932a5270f335bed617d5b935c80aedb1a35bd9fc1e31acafd5372c30f5c1187",
+            ),
+            (
+                &[0, 1, 2, 2, 1_000_000_000],
+                "3c24da049451555d51a7014a37337aa4e12d41e485abccfa46b47dfb2af54b7a",
+            ),
+        ];
+        for (path, public_key) in vectors.iter() {
+            let keypair = ExpandedKeyPair::derive_hardened(&seed, path);
+            assert_eq!(
+                hex::encode(&*keypair.public_key.to_bytes(true)),
+                *public_key
+            );
+        }
+    }
+
+    #[test]
+    fn test_signature_bytes_rfc8032_vector() {
+        // RFC8032 section 7.1, test 1
+        let public_key =
+            hex::decode("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")
+                .unwrap();
+        let sig_bytes = hex::decode(
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        )
+        .unwrap();
+        let public_key = Point::<Ed25519>::from_bytes(&public_key).unwrap();
+
+        let sig = Signature::from_bytes(&sig_bytes).unwrap();
+        sig.verify(&[], &public_key).unwrap();
+        assert_eq!(&sig.to_bytes()[..], &sig_bytes[..]);
+        assert_eq!(
+            Signature::from_bytes(&sig_bytes[..63]),
+            Err(VerifyError::InvalidSignature)
+        );
+        let mut high_s = sig_bytes;
+        high_s[63] |= 0xf0;
+        assert_eq!(
+            Signature::from_bytes(&high_s),
+            Err(VerifyError::NonCanonicalS)
+        );
+    }
+
+    #[test]
+    fn test_verify_bytes() {
+        let mut rng = deterministic_fast_rand("test_verify_bytes", None);
+
+        let mut msg = [0u8; 32];
+        rng.fill_bytes(&mut msg);
+        let keypair = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let sig = aggsig::sign_single(&msg, &keypair);
+        let public_key = keypair.public_key.to_bytes(true);
+        let mut sig_bytes = [0u8; 64];
+        sig_bytes[..32].copy_from_slice(&sig.R.to_bytes(true));
+        sig_bytes[32..].copy_from_slice(&sig.s.to_bytes());
+
+        Signature::verify_bytes(&sig_bytes, &msg, &public_key).unwrap();
+        assert_eq!(
+            Signature::verify_bytes(&sig_bytes, &msg[1..], &public_key),
+            Err(VerifyError::InvalidSignature)
+        );
+        assert_eq!(
+            Signature::verify_bytes(&sig_bytes[1..], &msg, &public_key),
+            Err(VerifyError::InvalidSignature)
+        );
+
+        // s + L is the same scalar, but a non-canonical encoding
+        let mut s_plus_l = (sig.s.to_bigint() + Scalar::<Ed25519>::group_order()).to_bytes();
+        s_plus_l.reverse();
+        s_plus_l.resize(32, 0);
+        let mut malleated = sig_bytes;
+        malleated[32..].copy_from_slice(&s_plus_l);
+        assert_eq!(
+            Signature::verify_bytes(&malleated, &msg, &public_key),
+            Err(VerifyError::NonCanonicalS)
+        );
+
+        // y >= p is a non-canonical encoding, 2^255 - 1 isn't a valid y at all.
+        let mut bad_R = sig_bytes;
+        bad_R[..32].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            Signature::verify_bytes(&bad_R, &msg, &public_key),
+            Err(VerifyError::InvalidR)
+        );
+        assert_eq!(
+            Signature::verify_bytes(&sig_bytes, &msg, &[0xff; 32]),
+            Err(VerifyError::InvalidPublicKey)
+        );
+        assert_eq!(
+            sig.verify(&msg, &Point::zero()),
+            Err(VerifyError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn test_verify_bytes_with_mode() {
+        let mut rng = deterministic_fast_rand("test_verify_bytes_with_mode", None);
+        let msg: [u8; 32] = rng.gen();
+        let keypair = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let public_key = keypair.public_key.to_bytes(true);
+        let sig_bytes = aggsig::sign_single(&msg, &keypair).to_bytes();
+        let verify = |sig_bytes: &[u8], mode| {
+            Signature::verify_bytes_with(
+                sig_bytes,
+                &msg,
+                &public_key,
+                mode,
+                VerificationPolicy::Cofactorless,
+            )
+        };
+        for mode in [VerifyMode::Strict, VerifyMode::Legacy] {
+            verify(&sig_bytes, mode).unwrap();
+        }
+
+        let s = Signature::from_bytes(&sig_bytes).unwrap().s.to_bigint();
+        let malleate = |s: BigInt| {
+            let mut s = s.to_bytes();
+            s.reverse();
+            s.resize(32, 0);
+            let mut malleated = sig_bytes;
+            malleated[32..].copy_from_slice(&s);
+            malleated
+        };
+        // s + L fits in 253 bits, s + 2L doesn't
+        let s_plus_l = malleate(&s + Scalar::<Ed25519>::group_order());
+        let s_plus_2l = malleate(&s + Scalar::<Ed25519>::group_order() * 2);
+        assert_eq!(
+            verify(&s_plus_l, VerifyMode::Strict),
+            Err(VerifyError::NonCanonicalS)
+        );
+        assert!(verify(&s_plus_l, VerifyMode::Legacy).is_ok());
+        assert_eq!(
+            verify(&s_plus_2l, VerifyMode::Legacy),
+            Err(VerifyError::NonCanonicalS)
+        );
+
+        let mut identity_R = sig_bytes;
+        identity_R[..32].copy_from_slice(&Point::<Ed25519>::zero().to_bytes(true));
+        assert_eq!(
+            verify(&identity_R, VerifyMode::Strict),
+            Err(VerifyError::InvalidR)
+        );
+        assert_eq!(
+            verify(&identity_R, VerifyMode::Legacy),
+            Err(VerifyError::InvalidSignature)
+        );
+        let mut non_canonical_R = sig_bytes;
+        non_canonical_R[..32].copy_from_slice(&[0xff; 32]);
+        for mode in [VerifyMode::Strict, VerifyMode::Legacy] {
+            assert_eq!(verify(&non_canonical_R, mode), Err(VerifyError::InvalidR));
+        }
+    }
+
+    #[test]
+    fn test_verification_policy() {
+        let mut rng = deterministic_fast_rand("test_verification_policy", None);
+        let msg: [u8; 32] = rng.gen();
+        let a = DalekScalar::from_bytes_mod_order(rng.gen());
+        let r = DalekScalar::from_bytes_mod_order(rng.gen());
+        let G = ED25519_BASEPOINT_POINT;
+        // a point of order 8
+        let T = EIGHT_TORSION[1];
+        // `s = r + k * a` with `R` and `A` as given, which may have a torsion component
+        let sign = |R: EdwardsPoint, A: EdwardsPoint| {
+            let (R, A) = (R.compress().to_bytes(), A.compress().to_bytes());
+            let k = to_dalek_scalar(&Signature::k_dom_encoded::<Sha512>(&[], &R, &A, &msg));
+            let mut sig = [0u8; 64];
+            sig[..32].copy_from_slice(&R);
+            sig[32..].copy_from_slice((r + k * a).as_bytes());
+            (sig, A)
+        };
+        let verify = |(sig, A): &([u8; 64], [u8; 32]), mode, policy| {
+            Signature::verify_bytes_with(sig, &msg, A, mode, policy)
+        };
+        let policies = [
+            VerificationPolicy::Cofactorless,
+            VerificationPolicy::Cofactored,
+            VerificationPolicy::Both,
+        ];
+
+        let honest = sign(r * G, a * G);
+        let torsion_R = sign(r * G + T, a * G);
+        let torsion_key = sign(r * G, a * G + T);
+        let small_order_key = sign(r * G, T);
+        for policy in policies {
+            for mode in [VerifyMode::Strict, VerifyMode::Legacy] {
+                verify(&honest, mode, policy).unwrap();
+                assert_eq!(
+                    verify(&small_order_key, mode, policy),
+                    Err(VerifyError::InvalidPublicKey)
+                );
+            }
+            assert_eq!(
+                verify(&torsion_R, VerifyMode::Strict, policy),
+                Err(VerifyError::InvalidR)
+            );
+            assert_eq!(
+                verify(&torsion_key, VerifyMode::Strict, policy),
+                Err(VerifyError::InvalidPublicKey)
+            );
+        }
+        // only the cofactored equation ignores the torsion component
+        assert!(verify(
+            &torsion_R,
+            VerifyMode::Legacy,
+            VerificationPolicy::Cofactored
+        )
+        .is_ok());
+        assert!(verify(
+            &torsion_key,
+            VerifyMode::Legacy,
+            VerificationPolicy::Cofactored
+        )
+        .is_ok());
+        for policy in [VerificationPolicy::Cofactorless, VerificationPolicy::Both] {
+            assert_eq!(
+                verify(&torsion_R, VerifyMode::Legacy, policy),
+                Err(VerifyError::InvalidSignature)
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_point() {
+        assert_eq!(check_point(&Point::zero()), Err(Error::InvalidPoint));
+        assert!(check_point(&Point::generator().to_point()).is_ok());
+
+        let generator_json =
+            serde_json::to_value(Point::<Ed25519>::generator().to_point()).unwrap();
+        assert_eq!(
+            serde_json::from_value::<Point<Ed25519>>(generator_json.clone()).unwrap(),
+            Point::generator().to_point()
+        );
+        // the other 7 points of small order
+        let small_order = [
+            "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000000000000000080",
+            "26e8958fc2b227b045c3f489f2ef98f0d5dfac05d3c63339b13802886d53fc05",
+            "26e8958fc2b227b045c3f489f2ef98f0d5dfac05d3c63339b13802886d53fc85",
+            "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a",
+            "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac03fa",
+        ];
+        for torsion in small_order.iter().map(|t| hex::decode(t).unwrap()) {
+            assert!(Point::<Ed25519>::from_bytes(&torsion).is_err());
+            assert!(decode_point(&torsion).is_none());
+            let mut json = generator_json.clone();
+            json["point"] = serde_json::to_value(&torsion).unwrap();
+            assert!(serde_json::from_value::<Point<Ed25519>>(json).is_err());
+            // and a point with a torsion component
+            let mixed = Ed25519Point::deserialize(&torsion)
+                .unwrap()
+                .add_point(Point::<Ed25519>::generator().as_raw());
+            assert!(Point::<Ed25519>::from_raw(mixed).is_err());
+            assert!(decode_point(&mixed.serialize_compressed()).is_none());
+        }
+    }
 }
@@ -18,14 +18,16 @@
 //!
 //See (https://pdfs.semanticscholar.org/6bf4/f9450e7a8e31c106a8670b961de4735589cf.pdf)
 
-use super::ExpandedKeyPair;
+use super::{random_scalar, ExpandedKeyPair};
 
 use curv::cryptographic_primitives::hashing::DigestExt;
 use curv::elliptic::curves::{Ed25519, Point, Scalar};
 use curv::BigInt;
 use protocols::multisig;
 
+use rand::{thread_rng, CryptoRng, RngCore};
 use sha2::{digest::Digest, Sha512};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 // I is a private key and public key keypair, X is a commitment of the form X = xG used only in key generation (see p11 in the paper)
 #[derive(Debug, Clone)]
@@ -39,15 +41,31 @@ pub struct SingleKeyPair {
     pub public_key: Point<Ed25519>,
     private_key: Scalar<Ed25519>,
 }
+impl Zeroize for Keys {
+    fn zeroize(&mut self) {
+        self.I.zeroize();
+        self.X.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Keys {}
+
+impl Zeroize for SingleKeyPair {
+    fn zeroize(&mut self) {
+        self.public_key = Point::zero();
+        self.private_key = Scalar::zero();
+    }
+}
+
+impl ZeroizeOnDrop for SingleKeyPair {}
+
 impl SingleKeyPair {
     pub fn create() -> SingleKeyPair {
-        let ec_point = Point::generator();
-        let private_key = Scalar::random();
-        let public_key = ec_point * &private_key;
-        SingleKeyPair {
-            public_key,
-            private_key,
-        }
+        Self::create_rng(&mut thread_rng())
+    }
+
+    pub fn create_rng(rng: &mut (impl CryptoRng + RngCore)) -> SingleKeyPair {
+        Self::create_from_private_key(random_scalar(rng))
     }
     pub fn create_from_private_key(private_key: Scalar<Ed25519>) -> SingleKeyPair {
         let g = Point::generator();
@@ -95,7 +113,7 @@ impl Keys {
     }
 
     pub fn broadcast(keys: Keys) -> Vec<Point<Ed25519>> {
-        return vec![keys.I.public_key, keys.X.public_key];
+        vec![keys.I.public_key, keys.X.public_key]
     }
 
     pub fn collect_and_compute_challenge(ix_vec: &[Vec<Point<Ed25519>>]) -> Scalar<Ed25519> {
@@ -139,17 +157,34 @@ pub struct EphKey {
     pub eph_key_pair: SingleKeyPair,
 }
 
+impl Zeroize for EphKey {
+    fn zeroize(&mut self) {
+        self.eph_key_pair.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for EphKey {}
+
 impl EphKey {
     //signing step 1
     pub fn gen_commit(key_gen_key_pair: &ExpandedKeyPair, message: &BigInt) -> EphKey {
+        Self::gen_commit_rng(key_gen_key_pair, message, &mut thread_rng())
+    }
+
+    pub fn gen_commit_rng(
+        key_gen_key_pair: &ExpandedKeyPair,
+        message: &BigInt,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> EphKey {
         // here we deviate from the spec, by introducing  non-deterministic element (random number)
         // to the nonce
-        let r = Sha512::new()
+        let mut r = Sha512::new()
             .chain_scalar(&key_gen_key_pair.expanded_private_key.prefix)
             .chain_bigint(message)
-            .chain_scalar(&Scalar::<Ed25519>::random())
+            .chain_scalar(&random_scalar(rng))
             .result_bigint();
         let r_fe = Scalar::from_bigint(&r);
+        r.zeroize();
         let g = Point::generator();
         let eph_key_pair = SingleKeyPair {
             public_key: g * &r_fe,
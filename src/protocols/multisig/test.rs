@@ -33,7 +33,7 @@ mod tests {
     }
 
     fn two_party_key_gen_internal() {
-        let message_vec = vec![79, 77, 69, 82];
+        let message_vec = [79, 77, 69, 82];
         let message_bn = BigInt::from_bytes(&message_vec[..]);
         let message = Sha256::new().chain_bigint(&message_bn).result_bigint();
 
@@ -1,4 +1,19 @@
 #![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
 
 //! Simple ed25519
 //!
@@ -7,11 +22,13 @@
 //! We implement the v = 2 (NUMBER_OF_NONCES) version, meaning there are 2 nonces generated by each party.
 
 use super::{ExpandedKeyPair, Signature};
+use alloc::vec::Vec;
 use curv::arithmetic::Converter;
 use curv::elliptic::curves::{Ed25519, Point, Scalar};
 use curv::BigInt;
-use protocols::Rng;
+use rand::{CryptoRng, Rng, RngCore};
 use sha2::{digest::Digest, Sha512};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 pub const NUMBER_OF_NONCES: usize = 2;
 
@@ -30,6 +47,9 @@ impl PublicKeyAgg {
         // When there are at least 2 distinct public keys, it is secure to set the musig coefficient
         // of one them to 1 - saving a scalar multiplication operation - proof in Section B of the Musig2 paper linked above.
         // We therefore find the second public key (by lexicographic order) and later set its musig coefficient to 1.
+        if public_keys.is_empty() {
+            return None;
+        }
         public_keys.sort_by(|left, right| left.to_bytes(false).cmp(&right.to_bytes(false)));
         let mut second_public_key = &public_keys[0];
         for public_key in &public_keys[1..] {
@@ -44,7 +64,7 @@ impl PublicKeyAgg {
             |(mut agg_pub_key, mut musig_coeff), public_key| {
                 let mut musig_coefficient: Scalar<Ed25519> = Scalar::from(1);
                 if public_key != second_public_key {
-                    let mut hasher = Sha512::new().chain(&[1]).chain(&*public_key.to_bytes(true));
+                    let mut hasher = Sha512::new().chain([1]).chain(&*public_key.to_bytes(true));
                     for pk in &public_keys {
                         hasher.update(&*pk.to_bytes(true));
                     }
@@ -79,6 +99,14 @@ pub struct PrivatePartialNonces {
     pub r: [Scalar<Ed25519>; NUMBER_OF_NONCES],
 }
 
+impl Zeroize for PrivatePartialNonces {
+    fn zeroize(&mut self) {
+        self.r = [(); NUMBER_OF_NONCES].map(|_| Scalar::zero());
+    }
+}
+
+impl ZeroizeOnDrop for PrivatePartialNonces {}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct PublicPartialNonces {
     pub R: [Point<Ed25519>; NUMBER_OF_NONCES],
@@ -90,32 +118,37 @@ pub struct PartialSignature {
     pub my_partial_s: Scalar<Ed25519>,
 }
 
+#[cfg(feature = "std")]
 pub fn generate_partial_nonces(
     keys: &ExpandedKeyPair,
     message: Option<&[u8]>,
 ) -> (PrivatePartialNonces, PublicPartialNonces) {
     let mut rng = rand::thread_rng();
-    generate_partial_nonces_internal(keys, message, &mut rng)
+    generate_partial_nonces_rng(keys, message, &mut rng)
 }
 
-fn generate_partial_nonces_internal(
+pub fn generate_partial_nonces_rng(
     keys: &ExpandedKeyPair,
     message: Option<&[u8]>,
-    rng: &mut impl Rng,
+    rng: &mut (impl CryptoRng + RngCore),
 ) -> (PrivatePartialNonces, PublicPartialNonces) {
     // here we deviate from the spec, by introducing  non-deterministic element (random number)
     // to the nonce, this is important for MPC implementations
     let r: [Scalar<Ed25519>; NUMBER_OF_NONCES] = [(); NUMBER_OF_NONCES].map(|_| {
         let mut hash_result = Sha512::new()
-            .chain(&[2])
+            .chain([2])
             .chain(&*keys.expanded_private_key.prefix.to_bytes())
             .chain(message.unwrap_or(&[]))
             .chain(rng.gen::<[u8; 32]>())
             .finalize();
         // reverse because BigInt uses big-endian
         hash_result.reverse();
+        let mut r_bn = BigInt::from_bytes(&hash_result);
+        hash_result.as_mut_slice().zeroize();
         // reduce modulu the group order
-        Scalar::from_bigint(&BigInt::from_bytes(&hash_result))
+        let r = Scalar::from_bigint(&r_bn);
+        r_bn.zeroize();
+        r
     });
     let R: [Point<Ed25519>; NUMBER_OF_NONCES] = r.clone().map(|scalar| Point::generator() * scalar);
     (PrivatePartialNonces { r }, PublicPartialNonces { R })
@@ -143,7 +176,7 @@ pub fn partial_sign(
 
     // Compute b as hash of nonces
     let mut hasher = Sha512::new()
-        .chain(&[3])
+        .chain([3])
         .chain(&*agg_public_key.agg_public_key.to_bytes(false));
     for nonce in &R {
         hasher.update(&*nonce.to_bytes(false));
@@ -18,7 +18,7 @@
 mod tests {
     use curv::arithmetic::Converter;
     use hex::decode;
-    use rand::{Rng, RngCore};
+    use rand::{CryptoRng, RngCore};
     use std::convert::TryInto;
 
     use protocols::tests::deterministic_fast_rand;
@@ -88,11 +88,7 @@ mod tests {
                 let (private_partial_nonces, public_partial_nonces): (Vec<_>, Vec<_>) = keypairs
                     .iter()
                     .map(|keypair| {
-                        musig2::generate_partial_nonces_internal(
-                            keypair,
-                            Option::Some(msg),
-                            &mut rng,
-                        )
+                        musig2::generate_partial_nonces_rng(keypair, Option::Some(msg), &mut rng)
                     })
                     .unzip();
                 // Send partial nonces to everyone and wait to receive everyone else's
@@ -122,7 +118,6 @@ mod tests {
 
                 // Compute signature
                 let signatures: Vec<_> = (0..signers)
-                    .into_iter()
                     .map(|index| {
                         let mut partial_sigs_without_signer = partial_sigs.clone();
                         let my_partial_sig = partial_sigs_without_signer.remove(index);
@@ -165,7 +160,7 @@ mod tests {
         }
     }
 
-    fn test_multiparty_signing_for_two_parties_internal(rng: &mut impl Rng) {
+    fn test_multiparty_signing_for_two_parties_internal(rng: &mut (impl CryptoRng + RngCore)) {
         let message: [u8; 12] = [79, 77, 69, 82, 60, 61, 100, 156, 109, 125, 3, 19];
 
         // round 0: generate signing keys generate nonces
@@ -173,9 +168,9 @@ mod tests {
         let party1_key = ExpandedKeyPair::create();
 
         let (p0_private_nonces, p0_public_nonces) =
-            musig2::generate_partial_nonces_internal(&party0_key, Option::Some(&message), rng);
+            musig2::generate_partial_nonces_rng(&party0_key, Option::Some(&message), rng);
         let (p1_private_nonces, p1_public_nonces) =
-            musig2::generate_partial_nonces_internal(&party1_key, Option::Some(&message), rng);
+            musig2::generate_partial_nonces_rng(&party1_key, Option::Some(&message), rng);
 
         // compute aggregated public key:
         let pks = vec![party0_key.public_key.clone(), party1_key.public_key.clone()];
@@ -191,7 +186,7 @@ mod tests {
         assert_eq!(party0_key_agg.agg_public_key, party1_key_agg.agg_public_key);
         // Compute partial signatures
         let s0 = musig2::partial_sign(
-            &[p1_public_nonces.R.clone()],
+            core::slice::from_ref(&p1_public_nonces.R),
             p0_private_nonces,
             p0_public_nonces.clone(),
             &party0_key_agg,
@@ -207,8 +202,10 @@ mod tests {
             &message,
         );
 
-        let signature0 = musig2::aggregate_partial_signatures(&s0, &[s1.my_partial_s.clone()]);
-        let signature1 = musig2::aggregate_partial_signatures(&s1, &[s0.my_partial_s.clone()]);
+        let signature0 =
+            musig2::aggregate_partial_signatures(&s0, core::slice::from_ref(&s1.my_partial_s));
+        let signature1 =
+            musig2::aggregate_partial_signatures(&s1, core::slice::from_ref(&s0.my_partial_s));
         assert!(s0.R == s1.R, "Different partial nonce aggregation!");
         assert!(signature0.s == signature1.s);
         // debugging asserts
@@ -0,0 +1,177 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! Proactive refresh of the shares produced by `protocols::keygen`
+//!
+//! See Herzberg et al. "Proactive Secret Sharing Or: How to Cope With Perpetual Leakage".
+//! Every party shares zero with a random polynomial of degree `threshold` and the new share is the
+//! old share plus the sum of all zero-shares received. The joint public key `y` stays the same,
+//! while shares from before the refresh can no longer be combined with shares from after it.
+//!
+//! Round 1: broadcast `RefreshBroadcastMessage1`.
+//! Round 2: verify that every verification vector shares zero and send `RefreshMessage2`
+//! privately to every party.
+//! Finally every party verifies its received shares and replaces its `SharedKeys` (the old ones
+//! should be erased).
+
+use Error::{self, InvalidCommitment, RoundMismatch};
+
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use protocols::keygen::{
+    all_parties_present, evaluate_polynomial, sample_polynomial, verify_received_shares,
+    Parameters, SharedKeys, VerificationVector,
+};
+use rand::{thread_rng, CryptoRng, RngCore};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+pub struct RefreshKeys {
+    pub party_index: u16,
+    coefficients: Vec<Scalar<Ed25519>>,
+}
+
+impl Zeroize for RefreshKeys {
+    fn zeroize(&mut self) {
+        self.coefficients.clear();
+    }
+}
+
+impl ZeroizeOnDrop for RefreshKeys {}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RefreshBroadcastMessage1 {
+    pub party_index: u16,
+    pub vss: VerificationVector,
+}
+
+// Sent privately from `sender_index` to `receiver_index`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RefreshMessage2 {
+    pub sender_index: u16,
+    pub receiver_index: u16,
+    pub secret_share: Scalar<Ed25519>,
+}
+
+impl RefreshKeys {
+    pub fn phase1_create(params: &Parameters, party_index: u16) -> RefreshKeys {
+        Self::phase1_create_rng(params, party_index, &mut thread_rng())
+    }
+
+    pub fn phase1_create_rng(
+        params: &Parameters,
+        party_index: u16,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> RefreshKeys {
+        RefreshKeys {
+            party_index,
+            coefficients: sample_polynomial(Scalar::zero(), params.threshold, rng),
+        }
+    }
+
+    pub fn phase1_broadcast(&self) -> RefreshBroadcastMessage1 {
+        let commitments = self
+            .coefficients
+            .iter()
+            .map(|a| Point::generator() * a)
+            .collect();
+        RefreshBroadcastMessage1 {
+            party_index: self.party_index,
+            vss: VerificationVector { commitments },
+        }
+    }
+
+    /// Verifies that every party shares zero and returns the share for every party `1..=n`.
+    pub fn phase1_verify_phase2_distribute(
+        &self,
+        params: &Parameters,
+        bc1_vec: &[RefreshBroadcastMessage1],
+    ) -> Result<Vec<RefreshMessage2>, Error> {
+        verify_refresh_broadcasts(params, bc1_vec)?;
+        Ok((1..=params.share_count)
+            .map(|j| RefreshMessage2 {
+                sender_index: self.party_index,
+                receiver_index: j,
+                secret_share: evaluate_polynomial(&self.coefficients, j),
+            })
+            .collect())
+    }
+
+    /// `msg2_vec` holds the round 2 messages addressed to us, one from every party, in any order.
+    /// Fails with `RoundMismatch` if `keys` were shared with another threshold than `params`.
+    pub fn phase2_verify_shares_refresh_keys(
+        &self,
+        params: &Parameters,
+        bc1_vec: &[RefreshBroadcastMessage1],
+        msg2_vec: &[RefreshMessage2],
+        keys: &SharedKeys,
+    ) -> Result<SharedKeys, Error> {
+        verify_refresh_broadcasts(params, bc1_vec)?;
+        if msg2_vec.len() != bc1_vec.len()
+            || keys.party_index != self.party_index
+            || keys.vss.commitments.len() != usize::from(params.threshold) + 1
+        {
+            return Err(RoundMismatch);
+        }
+        verify_received_shares(
+            self.party_index,
+            bc1_vec.iter().map(|bc1| (bc1.party_index, &bc1.vss)),
+            msg2_vec
+                .iter()
+                .map(|msg2| (msg2.sender_index, msg2.receiver_index, &msg2.secret_share)),
+        )?;
+
+        let commitments = keys
+            .vss
+            .commitments
+            .iter()
+            .enumerate()
+            .map(|(k, commitment)| {
+                bc1_vec
+                    .iter()
+                    .fold(commitment.clone(), |acc, bc1| acc + &bc1.vss.commitments[k])
+            })
+            .collect();
+        let x_i = msg2_vec
+            .iter()
+            .fold(keys.x_i.clone(), |acc, msg2| acc + &msg2.secret_share);
+        Ok(SharedKeys {
+            party_index: keys.party_index,
+            y: keys.y.clone(),
+            x_i,
+            vss: VerificationVector { commitments },
+            prefix: keys.prefix.clone(),
+        })
+    }
+}
+
+/// Checks the public part of a refresh transcript: every party in `1..=n` contributed exactly
+/// one verification vector of the right degree, and every one of them shares zero.
+pub fn verify_refresh_broadcasts(
+    params: &Parameters,
+    bc1_vec: &[RefreshBroadcastMessage1],
+) -> Result<(), Error> {
+    if !all_parties_present(params, bc1_vec.iter().map(|bc1| bc1.party_index)) {
+        return Err(RoundMismatch);
+    }
+    for bc1 in bc1_vec {
+        let zero_sharing = bc1.vss.commitments.len() == usize::from(params.threshold) + 1
+            && bc1.vss.commitments[0].is_zero();
+        if !zero_sharing {
+            return Err(InvalidCommitment {
+                party: bc1.party_index,
+            });
+        }
+    }
+    Ok(())
+}
+
+mod test;
@@ -0,0 +1,132 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::{Point, Scalar};
+    use protocols::keygen::test::tests::keygen_t_n_parties;
+    use protocols::keygen::{lagrange_coefficient, Parameters, SharedKeys};
+    use protocols::refresh::{self, RefreshKeys};
+    use protocols::tests::deterministic_fast_rand;
+    use Error;
+
+    #[test]
+    fn test_refresh_keeps_public_key() {
+        let mut rng = deterministic_fast_rand("test_refresh_keeps_public_key", None);
+        let params = Parameters {
+            threshold: 2,
+            share_count: 4,
+        };
+        let shared_keys = keygen_t_n_parties(&params, &mut rng);
+        let refreshed_keys = refresh_all(&params, &shared_keys).unwrap();
+
+        for (old, new) in shared_keys.iter().zip(refreshed_keys.iter()) {
+            assert_eq!(old.y, new.y);
+            assert_ne!(old.x_i, new.x_i);
+            assert_eq!(
+                Point::generator() * &new.x_i,
+                refreshed_keys[0].verification_share(new.party_index)
+            );
+        }
+        // the refreshed shares still interpolate to the same secret,
+        // but mixing old and new shares doesn't.
+        let indices = [1, 2, 3];
+        let interpolate = |shares: [&SharedKeys; 3]| {
+            let x = shares.iter().fold(Scalar::zero(), |acc, keys| {
+                acc + lagrange_coefficient(keys.party_index, &indices) * &keys.x_i
+            });
+            Point::generator() * x
+        };
+        assert_eq!(
+            interpolate([&refreshed_keys[0], &refreshed_keys[1], &refreshed_keys[2]]),
+            shared_keys[0].y
+        );
+        assert_ne!(
+            interpolate([&shared_keys[0], &refreshed_keys[1], &refreshed_keys[2]]),
+            shared_keys[0].y
+        );
+    }
+
+    #[test]
+    fn test_refresh_reject_non_zero_sharing() {
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let parties: Vec<_> = (1..=params.share_count)
+            .map(|i| RefreshKeys::phase1_create(&params, i))
+            .collect();
+        let mut bc1_vec: Vec<_> = parties
+            .iter()
+            .map(|party| party.phase1_broadcast())
+            .collect();
+        // party 2 tries to shift the joint public key
+        bc1_vec[1].vss.commitments[0] = Point::generator().to_point();
+        assert_eq!(
+            refresh::verify_refresh_broadcasts(&params, &bc1_vec),
+            Err(Error::InvalidCommitment { party: 2 })
+        );
+        assert_eq!(
+            parties[0]
+                .phase1_verify_phase2_distribute(&params, &bc1_vec)
+                .err(),
+            Some(Error::InvalidCommitment { party: 2 })
+        );
+    }
+
+    #[test]
+    fn test_refresh_reject_other_threshold() {
+        let mut rng = deterministic_fast_rand("test_refresh_reject_other_threshold", None);
+        let keygen_params = Parameters {
+            threshold: 2,
+            share_count: 4,
+        };
+        let shared_keys = keygen_t_n_parties(&keygen_params, &mut rng);
+        // keys of degree 2 refreshed with degree 1 sharings
+        let params = Parameters {
+            threshold: 1,
+            share_count: 4,
+        };
+        assert_eq!(
+            refresh_all(&params, &shared_keys).err(),
+            Some(Error::RoundMismatch)
+        );
+    }
+
+    fn refresh_all(params: &Parameters, keys: &[SharedKeys]) -> Result<Vec<SharedKeys>, Error> {
+        let parties: Vec<_> = keys
+            .iter()
+            .map(|k| RefreshKeys::phase1_create(params, k.party_index))
+            .collect();
+        let bc1_vec: Vec<_> = parties
+            .iter()
+            .map(|party| party.phase1_broadcast())
+            .collect();
+        let msg2_vecs = parties
+            .iter()
+            .map(|party| party.phase1_verify_phase2_distribute(params, &bc1_vec))
+            .collect::<Result<Vec<_>, _>>()?;
+        parties
+            .iter()
+            .zip(keys)
+            .map(|(party, keys)| {
+                let received: Vec<_> = msg2_vecs
+                    .iter()
+                    .flatten()
+                    .filter(|msg2| msg2.receiver_index == party.party_index)
+                    .cloned()
+                    .collect();
+                party.phase2_verify_shares_refresh_keys(params, &bc1_vec, &received, keys)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,260 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! Recovery of a lost share of `protocols::keygen`
+//!
+//! See Laing and Stinson "A Survey and Refinement of Repairable Threshold Schemes", the
+//! enrollment protocol for a single missing share. `threshold + 1` helpers restore the share of
+//! party `lost_index` for a new device, neither the helpers nor the new device learn anything
+//! about the joint secret or the other shares.
+//! Helper `i` splits `lambda_i * x_i` (its share weighted with the Lagrange coefficient for
+//! interpolating at `lost_index`) into random pieces, one for every helper, and commits to them.
+//! The pieces of each helper add up to its public `lambda_i * X_i`, so a helper can't contribute
+//! anything but its share.
+//!
+//! Round 1: every helper broadcasts `RepairBroadcastMessage1` and sends `RepairMessage1`
+//! privately to every helper (including itself).
+//! Round 2: every helper verifies the commitments and its pieces, and sends the sum of its
+//! pieces to the new device as `RepairMessage2`.
+//! Finally the new device verifies the sums against the commitments and the restored share
+//! against the joint verification vector.
+
+use Error::{self, InvalidCommitment, InvalidShare, RoundMismatch};
+
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use protocols::keygen::{SharedKeys, VerificationVector};
+use protocols::random_scalar;
+use rand::{thread_rng, CryptoRng, RngCore};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RepairBroadcastMessage1 {
+    pub party_index: u16,
+    pub lost_index: u16,
+    // `delta_j * G` for the piece of every helper, in ascending order of the helpers' indices
+    pub commitments: Vec<Point<Ed25519>>,
+    // the joint verification vector, the new device checks that all helpers agree on it
+    pub vss: VerificationVector,
+}
+
+// Sent privately from `sender_index` to `receiver_index`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RepairMessage1 {
+    pub sender_index: u16,
+    pub receiver_index: u16,
+    pub delta: Scalar<Ed25519>,
+}
+
+// Sent privately from `sender_index` to the new device
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RepairMessage2 {
+    pub sender_index: u16,
+    pub sigma: Scalar<Ed25519>,
+}
+
+pub fn phase1_distribute(
+    keys: &SharedKeys,
+    helpers: &[u16],
+    lost_index: u16,
+) -> Result<(RepairBroadcastMessage1, Vec<RepairMessage1>), Error> {
+    phase1_distribute_rng(keys, helpers, lost_index, &mut thread_rng())
+}
+
+/// `helpers` are the indices of the `threshold + 1` parties taking part, including ours.
+pub fn phase1_distribute_rng(
+    keys: &SharedKeys,
+    helpers: &[u16],
+    lost_index: u16,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<(RepairBroadcastMessage1, Vec<RepairMessage1>), Error> {
+    let helpers = sorted_helpers(&keys.vss, helpers.iter().copied(), lost_index)?;
+    if !helpers.contains(&keys.party_index) {
+        return Err(RoundMismatch);
+    }
+    let mut remainder = lagrange_coefficient_at(lost_index, keys.party_index, &helpers) * &keys.x_i;
+    let mut deltas: Vec<_> = helpers[1..]
+        .iter()
+        .map(|_| {
+            let delta = random_scalar(rng);
+            remainder = &remainder - &delta;
+            delta
+        })
+        .collect();
+    deltas.insert(0, remainder);
+
+    let bc1 = RepairBroadcastMessage1 {
+        party_index: keys.party_index,
+        lost_index,
+        commitments: deltas
+            .iter()
+            .map(|delta| Point::generator() * delta)
+            .collect(),
+        vss: keys.vss.clone(),
+    };
+    let msg1_vec = helpers
+        .iter()
+        .zip(deltas)
+        .map(|(&receiver_index, delta)| RepairMessage1 {
+            sender_index: keys.party_index,
+            receiver_index,
+            delta,
+        })
+        .collect();
+    Ok((bc1, msg1_vec))
+}
+
+/// `msg1_vec` holds the round 1 messages addressed to us, one from every helper, in any order.
+pub fn phase2_verify_contributions(
+    keys: &SharedKeys,
+    bc1_vec: &[RepairBroadcastMessage1],
+    msg1_vec: &[RepairMessage1],
+) -> Result<RepairMessage2, Error> {
+    let helpers = verify_repair_broadcasts(&keys.vss, bc1_vec)?;
+    let position = helpers
+        .iter()
+        .position(|&i| i == keys.party_index)
+        .ok_or(RoundMismatch)?;
+    if msg1_vec.len() != bc1_vec.len() {
+        return Err(RoundMismatch);
+    }
+    for bc1 in bc1_vec {
+        let valid_piece = msg1_vec.iter().any(|msg1| {
+            msg1.sender_index == bc1.party_index
+                && msg1.receiver_index == keys.party_index
+                && Point::generator() * &msg1.delta == bc1.commitments[position]
+        });
+        if !valid_piece {
+            return Err(InvalidShare {
+                party: bc1.party_index,
+            });
+        }
+    }
+    let sigma = msg1_vec
+        .iter()
+        .fold(Scalar::zero(), |acc, msg1| acc + &msg1.delta);
+    Ok(RepairMessage2 {
+        sender_index: keys.party_index,
+        sigma,
+    })
+}
+
+pub fn recover_share(
+    bc1_vec: &[RepairBroadcastMessage1],
+    msg2_vec: &[RepairMessage2],
+) -> Result<SharedKeys, Error> {
+    recover_share_rng(bc1_vec, msg2_vec, &mut thread_rng())
+}
+
+/// Run by the new device, `rng` is used for its nonce prefix.
+pub fn recover_share_rng(
+    bc1_vec: &[RepairBroadcastMessage1],
+    msg2_vec: &[RepairMessage2],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<SharedKeys, Error> {
+    let vss = &bc1_vec.first().ok_or(RoundMismatch)?.vss;
+    let helpers = verify_repair_broadcasts(vss, bc1_vec)?;
+    if msg2_vec.len() != helpers.len() {
+        return Err(RoundMismatch);
+    }
+    for (position, &helper) in helpers.iter().enumerate() {
+        let expected = bc1_vec
+            .iter()
+            .fold(Point::zero(), |acc, bc1| acc + &bc1.commitments[position]);
+        let valid_sum = msg2_vec.iter().any(|msg2| {
+            msg2.sender_index == helper && Point::generator() * &msg2.sigma == expected
+        });
+        if !valid_sum {
+            return Err(InvalidShare { party: helper });
+        }
+    }
+    let lost_index = bc1_vec[0].lost_index;
+    let x_i = msg2_vec
+        .iter()
+        .fold(Scalar::zero(), |acc, msg2| acc + &msg2.sigma);
+    vss.validate_share(&x_i, lost_index)?;
+    Ok(SharedKeys {
+        party_index: lost_index,
+        y: vss.commitments[0].clone(),
+        x_i,
+        vss: vss.clone(),
+        prefix: random_scalar(rng),
+    })
+}
+
+/// Checks the public part of a repair transcript: `threshold + 1` distinct helpers agreeing on
+/// the lost index and the verification vector, each of them committing to pieces that add up to
+/// its weighted public share. Returns the helpers' indices in ascending order.
+pub fn verify_repair_broadcasts(
+    vss: &VerificationVector,
+    bc1_vec: &[RepairBroadcastMessage1],
+) -> Result<Vec<u16>, Error> {
+    let lost_index = bc1_vec.first().ok_or(RoundMismatch)?.lost_index;
+    if bc1_vec.iter().any(|bc1| bc1.lost_index != lost_index) {
+        return Err(RoundMismatch);
+    }
+    let helpers = sorted_helpers(vss, bc1_vec.iter().map(|bc1| bc1.party_index), lost_index)?;
+    for bc1 in bc1_vec {
+        let lambda = lagrange_coefficient_at(lost_index, bc1.party_index, &helpers);
+        let valid = bc1.vss == *vss
+            && bc1.commitments.len() == helpers.len()
+            && bc1
+                .commitments
+                .iter()
+                .fold(Point::zero(), |acc, commitment| acc + commitment)
+                == vss.get_point_commitment(bc1.party_index) * lambda;
+        if !valid {
+            return Err(InvalidCommitment {
+                party: bc1.party_index,
+            });
+        }
+    }
+    Ok(helpers)
+}
+
+// exactly `threshold + 1` distinct nonzero helpers, the lost party not among them
+fn sorted_helpers(
+    vss: &VerificationVector,
+    helpers: impl Iterator<Item = u16>,
+    lost_index: u16,
+) -> Result<Vec<u16>, Error> {
+    let mut helpers: Vec<_> = helpers.collect();
+    helpers.sort_unstable();
+    let len_before_dedup = helpers.len();
+    helpers.dedup();
+    if lost_index == 0
+        || helpers.len() != len_before_dedup
+        || helpers.len() != vss.commitments.len()
+        || helpers.contains(&0)
+        || helpers.contains(&lost_index)
+    {
+        return Err(RoundMismatch);
+    }
+    Ok(helpers)
+}
+
+// the Lagrange coefficient of `index` for interpolating at `x` instead of zero
+fn lagrange_coefficient_at(x: u16, index: u16, indices: &[u16]) -> Scalar<Ed25519> {
+    let x = Scalar::<Ed25519>::from(x);
+    let x_i = Scalar::from(index);
+    let (num, denom) = indices.iter().filter(|&&j| j != index).fold(
+        (Scalar::<Ed25519>::from(1), Scalar::<Ed25519>::from(1)),
+        |(num, denom), &j| {
+            let x_j = Scalar::from(j);
+            (num * (&x - &x_j), denom * (&x_i - x_j))
+        },
+    );
+    num * denom
+        .invert()
+        .expect("indices are distinct, so the denominator can't be zero")
+}
+
+mod test;
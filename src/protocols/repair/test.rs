@@ -0,0 +1,131 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::{Point, Scalar};
+    use protocols::keygen::test::tests::keygen_t_n_parties;
+    use protocols::keygen::{Parameters, SharedKeys};
+    use protocols::repair::{self, RepairBroadcastMessage1, RepairMessage1, RepairMessage2};
+    use protocols::tests::deterministic_fast_rand;
+    use rand::{CryptoRng, RngCore};
+    use Error;
+
+    #[test]
+    fn test_repair_lost_share() {
+        let mut rng = deterministic_fast_rand("test_repair_lost_share", None);
+        let params = Parameters {
+            threshold: 2,
+            share_count: 5,
+        };
+        let shared_keys = keygen_t_n_parties(&params, &mut rng);
+        let helpers: Vec<_> = [1, 2, 5]
+            .iter()
+            .map(|&i| &shared_keys[usize::from(i - 1u16)])
+            .collect();
+        let (bc1_vec, msg1_vec) = repair_round1(&helpers, 4, &mut rng);
+        let msg2_vec = repair_round2(&helpers, &bc1_vec, &msg1_vec).unwrap();
+        // no helper alone knows the lost share
+        assert!(msg2_vec.iter().all(|msg2| msg2.sigma != shared_keys[3].x_i));
+
+        let recovered = repair::recover_share_rng(&bc1_vec, &msg2_vec, &mut rng).unwrap();
+        assert_eq!(recovered.party_index, 4);
+        assert_eq!(recovered.x_i, shared_keys[3].x_i);
+        assert_eq!(recovered.y, shared_keys[3].y);
+        assert_eq!(recovered.vss, shared_keys[3].vss);
+        assert_eq!(
+            Point::generator() * &recovered.x_i,
+            shared_keys[0].verification_share(4)
+        );
+    }
+
+    #[test]
+    fn test_repair_reject_bad_contribution() {
+        let mut rng = deterministic_fast_rand("test_repair_reject_bad_contribution", None);
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let shared_keys = keygen_t_n_parties(&params, &mut rng);
+        let helpers = [&shared_keys[0], &shared_keys[2]];
+        // only threshold + 1 helpers, the lost party can't help
+        assert_eq!(
+            repair::phase1_distribute_rng(&shared_keys[0], &[1], 2, &mut rng).unwrap_err(),
+            Error::RoundMismatch
+        );
+        assert_eq!(
+            repair::phase1_distribute_rng(&shared_keys[0], &[1, 2], 2, &mut rng).unwrap_err(),
+            Error::RoundMismatch
+        );
+
+        // party 3's commitments don't add up to its weighted public share
+        let (mut bc1_vec, msg1_vec) = repair_round1(&helpers, 2, &mut rng);
+        let offset = Scalar::from(1);
+        bc1_vec[1].commitments[0] = &bc1_vec[1].commitments[0] + Point::generator() * &offset;
+        assert_eq!(
+            repair_round2(&helpers, &bc1_vec, &msg1_vec).unwrap_err(),
+            Error::InvalidCommitment { party: 3 }
+        );
+
+        // party 3 sends party 1 a piece that doesn't match its commitment
+        let (bc1_vec, mut msg1_vec) = repair_round1(&helpers, 2, &mut rng);
+        let msg1 = msg1_vec
+            .iter_mut()
+            .find(|msg1| msg1.sender_index == 3 && msg1.receiver_index == 1)
+            .unwrap();
+        msg1.delta = &msg1.delta + &offset;
+        assert_eq!(
+            repair_round2(&helpers, &bc1_vec, &msg1_vec).unwrap_err(),
+            Error::InvalidShare { party: 3 }
+        );
+
+        // party 1 sends the new device a wrong sum
+        let (bc1_vec, msg1_vec) = repair_round1(&helpers, 2, &mut rng);
+        let mut msg2_vec = repair_round2(&helpers, &bc1_vec, &msg1_vec).unwrap();
+        msg2_vec[0].sigma = &msg2_vec[0].sigma + &offset;
+        assert_eq!(
+            repair::recover_share_rng(&bc1_vec, &msg2_vec, &mut rng).err(),
+            Some(Error::InvalidShare { party: 1 })
+        );
+    }
+
+    fn repair_round1(
+        helpers: &[&SharedKeys],
+        lost_index: u16,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> (Vec<RepairBroadcastMessage1>, Vec<RepairMessage1>) {
+        let indices: Vec<_> = helpers.iter().map(|keys| keys.party_index).collect();
+        let (bc1_vec, msg1_vecs): (Vec<_>, Vec<_>) = helpers
+            .iter()
+            .map(|keys| repair::phase1_distribute_rng(keys, &indices, lost_index, rng).unwrap())
+            .unzip();
+        (bc1_vec, msg1_vecs.into_iter().flatten().collect())
+    }
+
+    fn repair_round2(
+        helpers: &[&SharedKeys],
+        bc1_vec: &[RepairBroadcastMessage1],
+        msg1_vec: &[RepairMessage1],
+    ) -> Result<Vec<RepairMessage2>, Error> {
+        helpers
+            .iter()
+            .map(|keys| {
+                let received: Vec<_> = msg1_vec
+                    .iter()
+                    .filter(|msg1| msg1.receiver_index == keys.party_index)
+                    .cloned()
+                    .collect();
+                repair::phase2_verify_contributions(keys, bc1_vec, &received)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,223 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+
+//! Resharing the key of `protocols::keygen` to a new committee
+//!
+//! See Desmedt and Jajodia "Redistributing Secret Shares to New Access Structures and Its
+//! Applications". `threshold + 1` parties of the old committee each share `lambda_i * x_i` (their
+//! share weighted with its Lagrange coefficient) with a random polynomial of degree
+//! `new_params.threshold` among the `new_params.share_count` parties of the new committee.
+//! The new share of party `j` is the sum of the shares it received. The constant term of every
+//! dealing is checked against the dealer's public share `lambda_i * X_i`, so the joint public
+//! key `y` stays the same, and the new verification vector is the sum of the dealings'.
+//! The two committees may overlap, a party in both uses different `SharedKeys` in each role.
+//!
+//! Round 1: every old party broadcasts `ReshareBroadcastMessage1` and sends `ReshareMessage2`
+//! privately to every new party.
+//! Finally every new party verifies the dealings against the old verification vector and its
+//! received shares, and the old parties erase their `SharedKeys`.
+
+use Error::{self, InvalidCommitment, RoundMismatch};
+
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use protocols::keygen::{
+    evaluate_polynomial, lagrange_coefficient, sample_polynomial, valid_parameters,
+    verify_received_shares, Parameters, SharedKeys, VerificationVector,
+};
+use protocols::random_scalar;
+use rand::{thread_rng, CryptoRng, RngCore};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+pub struct ReshareKeys {
+    pub party_index: u16,
+    coefficients: Vec<Scalar<Ed25519>>,
+}
+
+impl Zeroize for ReshareKeys {
+    fn zeroize(&mut self) {
+        self.coefficients.clear();
+    }
+}
+
+impl ZeroizeOnDrop for ReshareKeys {}
+
+// `party_index` is the dealer's index in the old committee
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReshareBroadcastMessage1 {
+    pub party_index: u16,
+    pub vss: VerificationVector,
+}
+
+// Sent privately from `sender_index` in the old committee to `receiver_index` in the new one
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReshareMessage2 {
+    pub sender_index: u16,
+    pub receiver_index: u16,
+    pub secret_share: Scalar<Ed25519>,
+}
+
+impl ReshareKeys {
+    pub fn phase1_create(
+        keys: &SharedKeys,
+        old_parties: &[u16],
+        new_params: &Parameters,
+    ) -> Result<ReshareKeys, Error> {
+        Self::phase1_create_rng(keys, old_parties, new_params, &mut thread_rng())
+    }
+
+    /// `old_parties` are the indices of the `threshold + 1` old parties dealing, including ours.
+    pub fn phase1_create_rng(
+        keys: &SharedKeys,
+        old_parties: &[u16],
+        new_params: &Parameters,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<ReshareKeys, Error> {
+        let old_parties = sorted_dealers(&keys.vss, old_parties.iter().copied())?;
+        if !old_parties.contains(&keys.party_index) || !valid_parameters(new_params) {
+            return Err(RoundMismatch);
+        }
+        let constant_term = lagrange_coefficient(keys.party_index, &old_parties) * &keys.x_i;
+        Ok(ReshareKeys {
+            party_index: keys.party_index,
+            coefficients: sample_polynomial(constant_term, new_params.threshold, rng),
+        })
+    }
+
+    pub fn phase1_broadcast(&self) -> ReshareBroadcastMessage1 {
+        let commitments = self
+            .coefficients
+            .iter()
+            .map(|a| Point::generator() * a)
+            .collect();
+        ReshareBroadcastMessage1 {
+            party_index: self.party_index,
+            vss: VerificationVector { commitments },
+        }
+    }
+
+    /// Returns the share for every new party `1..=n'`.
+    pub fn phase1_distribute(&self, new_params: &Parameters) -> Vec<ReshareMessage2> {
+        (1..=new_params.share_count)
+            .map(|j| ReshareMessage2 {
+                sender_index: self.party_index,
+                receiver_index: j,
+                secret_share: evaluate_polynomial(&self.coefficients, j),
+            })
+            .collect()
+    }
+}
+
+pub fn phase2_verify_shares_construct_keypair(
+    party_index: u16,
+    new_params: &Parameters,
+    old_vss: &VerificationVector,
+    bc1_vec: &[ReshareBroadcastMessage1],
+    msg2_vec: &[ReshareMessage2],
+) -> Result<SharedKeys, Error> {
+    phase2_verify_shares_construct_keypair_rng(
+        party_index,
+        new_params,
+        old_vss,
+        bc1_vec,
+        msg2_vec,
+        &mut thread_rng(),
+    )
+}
+
+/// Run by the new party `party_index`, `old_vss` is the verification vector of the old
+/// committee (whose constant term the new party has to know to be the joint public key).
+/// `msg2_vec` holds the messages addressed to us, one from every dealer, in any order.
+/// `rng` is used for the new party's nonce prefix.
+pub fn phase2_verify_shares_construct_keypair_rng(
+    party_index: u16,
+    new_params: &Parameters,
+    old_vss: &VerificationVector,
+    bc1_vec: &[ReshareBroadcastMessage1],
+    msg2_vec: &[ReshareMessage2],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<SharedKeys, Error> {
+    verify_reshare_broadcasts(new_params, old_vss, bc1_vec)?;
+    if party_index == 0 || party_index > new_params.share_count || msg2_vec.len() != bc1_vec.len() {
+        return Err(RoundMismatch);
+    }
+    verify_received_shares(
+        party_index,
+        bc1_vec.iter().map(|bc1| (bc1.party_index, &bc1.vss)),
+        msg2_vec
+            .iter()
+            .map(|msg2| (msg2.sender_index, msg2.receiver_index, &msg2.secret_share)),
+    )?;
+
+    let commitments: Vec<_> = (0..usize::from(new_params.threshold) + 1)
+        .map(|k| {
+            bc1_vec
+                .iter()
+                .fold(Point::zero(), |acc, bc1| acc + &bc1.vss.commitments[k])
+        })
+        .collect();
+    let x_i = msg2_vec
+        .iter()
+        .fold(Scalar::zero(), |acc, msg2| acc + &msg2.secret_share);
+    Ok(SharedKeys {
+        party_index,
+        y: commitments[0].clone(),
+        x_i,
+        vss: VerificationVector { commitments },
+        prefix: random_scalar(rng),
+    })
+}
+
+/// Checks the public part of a resharing transcript: `threshold + 1` distinct old parties
+/// dealt, each with a verification vector of degree `new_params.threshold` whose constant term
+/// is its weighted public share under `old_vss`.
+pub fn verify_reshare_broadcasts(
+    new_params: &Parameters,
+    old_vss: &VerificationVector,
+    bc1_vec: &[ReshareBroadcastMessage1],
+) -> Result<(), Error> {
+    let old_parties = sorted_dealers(old_vss, bc1_vec.iter().map(|bc1| bc1.party_index))?;
+    if !valid_parameters(new_params) {
+        return Err(RoundMismatch);
+    }
+    for bc1 in bc1_vec {
+        let lambda = lagrange_coefficient(bc1.party_index, &old_parties);
+        let valid = bc1.vss.commitments.len() == usize::from(new_params.threshold) + 1
+            && bc1.vss.commitments[0] == old_vss.get_point_commitment(bc1.party_index) * lambda;
+        if !valid {
+            return Err(InvalidCommitment {
+                party: bc1.party_index,
+            });
+        }
+    }
+    Ok(())
+}
+
+// exactly `threshold + 1` distinct nonzero dealers of the old committee
+fn sorted_dealers(
+    old_vss: &VerificationVector,
+    dealers: impl Iterator<Item = u16>,
+) -> Result<Vec<u16>, Error> {
+    let mut dealers: Vec<_> = dealers.collect();
+    dealers.sort_unstable();
+    let len_before_dedup = dealers.len();
+    dealers.dedup();
+    if dealers.len() != len_before_dedup
+        || dealers.len() != old_vss.commitments.len()
+        || dealers.contains(&0)
+    {
+        return Err(RoundMismatch);
+    }
+    Ok(dealers)
+}
+
+mod test;
@@ -0,0 +1,173 @@
+#![allow(non_snake_case)]
+/*
+    Multisig eddsa
+    Copyright 2018 by Kzen Networks
+    This file is part of multi-party-eddsa library
+    (https://github.com/KZen-networks/multi-party-eddsa)
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
+*/
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::{Point, Scalar};
+    use protocols::keygen::test::tests::keygen_t_n_parties;
+    use protocols::keygen::{lagrange_coefficient, Parameters, SharedKeys};
+    use protocols::reshare::{self, ReshareBroadcastMessage1, ReshareKeys, ReshareMessage2};
+    use protocols::tests::deterministic_fast_rand;
+    use rand::{CryptoRng, RngCore};
+    use Error;
+
+    #[test]
+    fn test_reshare_keeps_public_key() {
+        let mut rng = deterministic_fast_rand("test_reshare_keeps_public_key", None);
+        let old_params = Parameters {
+            threshold: 2,
+            share_count: 4,
+        };
+        let new_params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let shared_keys = keygen_t_n_parties(&old_params, &mut rng);
+        let dealers = [&shared_keys[0], &shared_keys[2], &shared_keys[3]];
+        let (bc1_vec, msg2_vec) = deal(&dealers, &new_params, &mut rng);
+        let new_keys: Vec<_> = (1..=new_params.share_count)
+            .map(|j| {
+                receive(
+                    j,
+                    &new_params,
+                    &shared_keys[0],
+                    &bc1_vec,
+                    &msg2_vec,
+                    &mut rng,
+                )
+            })
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        for keys in &new_keys {
+            assert_eq!(keys.y, shared_keys[0].y);
+            assert_eq!(keys.vss, new_keys[0].vss);
+            assert_eq!(keys.vss.commitments.len(), 2);
+            assert_eq!(
+                Point::generator() * &keys.x_i,
+                new_keys[0].verification_share(keys.party_index)
+            );
+        }
+        // any two new shares interpolate to the joint secret
+        for indices in [[1, 2], [1, 3], [2, 3]] {
+            let x = indices.iter().fold(Scalar::zero(), |acc, &j| {
+                acc + lagrange_coefficient(j, &indices) * &new_keys[usize::from(j - 1)].x_i
+            });
+            assert_eq!(Point::generator() * x, shared_keys[0].y);
+        }
+    }
+
+    #[test]
+    fn test_reshare_reject_bad_dealing() {
+        let mut rng = deterministic_fast_rand("test_reshare_reject_bad_dealing", None);
+        let old_params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let new_params = Parameters {
+            threshold: 2,
+            share_count: 4,
+        };
+        let shared_keys = keygen_t_n_parties(&old_params, &mut rng);
+        let dealers = [&shared_keys[0], &shared_keys[1]];
+        assert!(
+            ReshareKeys::phase1_create_rng(&shared_keys[0], &[1, 2, 3], &new_params, &mut rng)
+                .is_err()
+        );
+
+        // party 2 deals something else than its share
+        let (mut bc1_vec, msg2_vec) = deal(&dealers, &new_params, &mut rng);
+        bc1_vec[1].vss.commitments[0] = &bc1_vec[1].vss.commitments[0] + Point::generator();
+        assert_eq!(
+            receive(
+                1,
+                &new_params,
+                &shared_keys[0],
+                &bc1_vec,
+                &msg2_vec,
+                &mut rng
+            )
+            .err(),
+            Some(Error::InvalidCommitment { party: 2 })
+        );
+
+        // party 1 sends new party 3 a wrong share
+        let (bc1_vec, mut msg2_vec) = deal(&dealers, &new_params, &mut rng);
+        let msg2 = msg2_vec
+            .iter_mut()
+            .find(|msg2| msg2.sender_index == 1 && msg2.receiver_index == 3)
+            .unwrap();
+        msg2.secret_share = &msg2.secret_share + Scalar::from(1);
+        assert_eq!(
+            receive(
+                3,
+                &new_params,
+                &shared_keys[0],
+                &bc1_vec,
+                &msg2_vec,
+                &mut rng
+            )
+            .err(),
+            Some(Error::InvalidShare { party: 1 })
+        );
+        assert!(receive(
+            2,
+            &new_params,
+            &shared_keys[0],
+            &bc1_vec,
+            &msg2_vec,
+            &mut rng
+        )
+        .is_ok());
+    }
+
+    fn deal(
+        dealers: &[&SharedKeys],
+        new_params: &Parameters,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> (Vec<ReshareBroadcastMessage1>, Vec<ReshareMessage2>) {
+        let indices: Vec<_> = dealers.iter().map(|keys| keys.party_index).collect();
+        let reshare_keys: Vec<_> = dealers
+            .iter()
+            .map(|keys| ReshareKeys::phase1_create_rng(keys, &indices, new_params, rng).unwrap())
+            .collect();
+        let bc1_vec = reshare_keys.iter().map(|k| k.phase1_broadcast()).collect();
+        let msg2_vec = reshare_keys
+            .iter()
+            .flat_map(|k| k.phase1_distribute(new_params))
+            .collect();
+        (bc1_vec, msg2_vec)
+    }
+
+    fn receive(
+        party_index: u16,
+        new_params: &Parameters,
+        old_keys: &SharedKeys,
+        bc1_vec: &[ReshareBroadcastMessage1],
+        msg2_vec: &[ReshareMessage2],
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Result<SharedKeys, Error> {
+        let received: Vec<_> = msg2_vec
+            .iter()
+            .filter(|msg2| msg2.receiver_index == party_index)
+            .cloned()
+            .collect();
+        reshare::phase2_verify_shares_construct_keypair_rng(
+            party_index,
+            new_params,
+            &old_keys.vss,
+            bc1_vec,
+            &received,
+            rng,
+        )
+    }
+}
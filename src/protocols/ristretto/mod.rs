@@ -0,0 +1,88 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Aggsig over the [Ristretto](https://ristretto.group) group
+//!
+//! The `schnorr` rounds with the `Ristretto255` scheme. Ristretto is a prime order group, so
+//! there are no small order points or torsion components a co-signer could hide in its key or
+//! nonce and every encoding is canonical. The signatures are Schnorr signatures `(R, s)` with
+//! `s * G == R + k * A`, not RFC8032 ones: the keys and signatures have their own encoding and
+//! only verify with `Signature::verify` here.
+
+#[cfg(test)]
+mod test;
+
+use curv::elliptic::curves::{Point, Ristretto, Scalar};
+use sha2::{Digest, Sha512};
+
+use protocols::schnorr::{self, hash_to_scalar, scalar_from_bytes_le, scalar_to_bytes_le, Scheme};
+
+pub use protocols::schnorr::{
+    add_signature_parts, create_ephemeral_key_and_commit_rng, get_R_tot, partial_sign,
+    sign_single_rng, verify_commitment, verify_partial_sig, SignFirstMsg, SIGNATURE_SIZE,
+};
+#[cfg(feature = "std")]
+pub use protocols::schnorr::{create_ephemeral_key_and_commit, sign_single};
+
+/// Ristretto with a SHA-512 challenge, `s` is encoded little endian.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Ristretto255;
+
+impl Scheme for Ristretto255 {
+    type Curve = Ristretto;
+
+    const NAME: &'static [u8] = b"ristretto255";
+
+    fn challenge(
+        R: &Point<Ristretto>,
+        public_key: &Point<Ristretto>,
+        message: &[u8],
+    ) -> Scalar<Ristretto> {
+        hash_to_scalar(
+            Sha512::new()
+                .chain([19])
+                .chain(&*R.to_bytes(true))
+                .chain(&*public_key.to_bytes(true))
+                .chain(message),
+        )
+    }
+
+    fn encode_point(point: &Point<Ristretto>) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&point.to_bytes(true));
+        bytes
+    }
+
+    fn decode_point(bytes: &[u8; 32]) -> Option<Point<Ristretto>> {
+        Point::from_bytes(bytes).ok()
+    }
+
+    fn encode_scalar(scalar: &Scalar<Ristretto>) -> [u8; 32] {
+        scalar_to_bytes_le(scalar)
+    }
+
+    fn decode_scalar(bytes: &[u8; 32]) -> Option<Scalar<Ristretto>> {
+        scalar_from_bytes_le(bytes)
+    }
+}
+
+pub type KeyPair = schnorr::KeyPair<Ristretto255>;
+pub type KeyAgg = schnorr::KeyAgg<Ristretto255>;
+pub type EphemeralKey = schnorr::EphemeralKey<Ristretto255>;
+pub type SignSecondMsg = schnorr::SignSecondMsg<Ristretto255>;
+pub type PartialSignature = schnorr::PartialSignature<Ristretto255>;
+pub type Signature = schnorr::Signature<Ristretto255>;
@@ -0,0 +1,167 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::{Point, Ristretto, Scalar};
+    use curv::BigInt;
+    use itertools::MultiUnzip;
+    use rand::Rng;
+
+    use protocols::ristretto::{self, KeyAgg, KeyPair, Signature};
+    use protocols::tests::deterministic_fast_rand;
+    use {Error, VerifyError};
+
+    #[test]
+    fn test_multiparty_signing() {
+        let mut rng = deterministic_fast_rand("test_ristretto_multiparty_signing", None);
+        let message = b"prime order";
+        let keys: Vec<_> = (0..3).map(|_| KeyPair::create_rng(&mut rng)).collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let key_aggs: Vec<_> = (0..3)
+            .map(|i| KeyAgg::key_aggregation_n(&pks, i).unwrap())
+            .collect();
+        assert!(key_aggs
+            .iter()
+            .all(|key_agg| key_agg.apk == key_aggs[0].apk));
+
+        let (ephemeral_keys, first_msgs, second_msgs): (Vec<_>, Vec<_>, Vec<_>) = keys
+            .iter()
+            .map(|k| ristretto::create_ephemeral_key_and_commit_rng(k, message, &mut rng))
+            .multiunzip();
+        for (first_msg, second_msg) in first_msgs.iter().zip(&second_msgs) {
+            assert!(ristretto::verify_commitment(first_msg, second_msg).is_ok());
+        }
+        assert_eq!(
+            ristretto::verify_commitment(&first_msgs[0], &second_msgs[1]),
+            Err(Error::InvalidCom)
+        );
+
+        let Rs: Vec<_> = second_msgs.iter().map(|msg| msg.R.clone()).collect();
+        let R_tot = ristretto::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = ephemeral_keys
+            .into_iter()
+            .zip(&keys)
+            .zip(&key_aggs)
+            .map(|((eph, k), key_agg)| {
+                ristretto::partial_sign(eph, k, &key_agg.hash, &R_tot, &key_agg.apk, message)
+            })
+            .collect();
+        for (i, sig) in partial_sigs.iter().enumerate() {
+            let key_agg = &key_aggs[i];
+            assert!(ristretto::verify_partial_sig(
+                sig,
+                message,
+                &key_agg.hash,
+                &Rs[i],
+                &pks[i],
+                &key_agg.apk
+            )
+            .is_ok());
+        }
+        let signature = ristretto::add_signature_parts(&partial_sigs).unwrap();
+        let apk = &key_aggs[0].apk;
+        assert!(signature.verify(message, apk).is_ok());
+        assert_eq!(
+            signature.verify(b"another message", apk),
+            Err(VerifyError::InvalidSignature)
+        );
+
+        let bytes = signature.to_bytes();
+        assert_eq!(Signature::from_bytes(&bytes).unwrap(), signature);
+        assert!(Signature::verify_bytes(&bytes, message, &apk.to_bytes(true)).is_ok());
+        assert_eq!(
+            Signature::verify_bytes(&bytes, message, &apk.to_bytes(true)[1..]),
+            Err(VerifyError::InvalidPublicKey)
+        );
+
+        let mut bad_partial_sig = partial_sigs[1].clone();
+        bad_partial_sig.s = &bad_partial_sig.s + Scalar::from(1);
+        assert_eq!(
+            ristretto::verify_partial_sig(
+                &bad_partial_sig,
+                message,
+                &key_aggs[1].hash,
+                &Rs[1],
+                &pks[1],
+                apk
+            ),
+            Err(Error::InvalidSig)
+        );
+        bad_partial_sig.R = Rs[0].clone();
+        assert_eq!(
+            ristretto::add_signature_parts(&[partial_sigs[0].clone(), bad_partial_sig]),
+            Err(Error::InvalidPartialSignature { party: 2 })
+        );
+    }
+
+    #[test]
+    fn test_single_signer_and_encodings() {
+        let mut rng = deterministic_fast_rand("test_ristretto_single_signer", None);
+        let message: [u8; 32] = rng.gen();
+        let keys = KeyPair::from_seed(&rng.gen());
+        let signature = ristretto::sign_single_rng(&message, &keys, &mut rng);
+        assert!(signature.verify(&message, &keys.public_key).is_ok());
+        assert_eq!(
+            signature.verify(&message, &Point::zero()),
+            Err(VerifyError::InvalidPublicKey)
+        );
+        // the same seed gives the same key
+        let seed: [u8; 32] = rng.gen();
+        assert_eq!(
+            KeyPair::from_seed(&seed).public_key,
+            KeyPair::from_seed(&seed).public_key
+        );
+
+        let bytes = signature.to_bytes();
+        let mut not_a_point = bytes;
+        not_a_point[..32].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            Signature::from_bytes(&not_a_point),
+            Err(VerifyError::InvalidR)
+        );
+        let mut high_s = bytes;
+        high_s[63] = 0xff;
+        assert_eq!(
+            Signature::from_bytes(&high_s),
+            Err(VerifyError::NonCanonicalS)
+        );
+        assert_eq!(
+            Signature::from_bytes(&bytes[1..]),
+            Err(VerifyError::InvalidSignature)
+        );
+
+        assert_eq!(
+            KeyAgg::key_aggregation_n(&[keys.public_key.clone(), Point::zero()], 0).err(),
+            Some(Error::InvalidKey)
+        );
+        assert_eq!(
+            KeyAgg::key_aggregation_n(std::slice::from_ref(&keys.public_key), 1).err(),
+            Some(Error::InvalidKey)
+        );
+        let identity_reveal = ristretto::SignSecondMsg {
+            R: Point::<Ristretto>::zero(),
+            blind_factor: BigInt::from(1),
+        };
+        let (_, first_msg, _) =
+            ristretto::create_ephemeral_key_and_commit_rng(&keys, &message, &mut rng);
+        assert_eq!(
+            ristretto::verify_commitment(&first_msg, &identity_reveal),
+            Err(Error::InvalidPoint)
+        );
+    }
+}
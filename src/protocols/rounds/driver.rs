@@ -0,0 +1,210 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Runs a `StateMachine` over asynchronous message channels
+//!
+//! `run` returns a future that sends everything the state machine queues to `outgoing`, feeds
+//! every message from `incoming` to it and proceeds whenever it can, resolving to the protocol
+//! output. It doesn't depend on a runtime, e.g. tokio channels can be used through the
+//! `Stream`/`Sink` wrappers of `tokio-stream` and `tokio-util`.
+//! `incoming` has to deliver the messages the other parties send to us, in any order.
+//! `run_with_metrics` reports the session to a `metrics::Metrics` as well.
+
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+use futures_core::Stream;
+use futures_sink::Sink;
+
+use protocols::rounds::metrics::{Metrics, NoMetrics};
+use protocols::rounds::{Msg, StateMachine};
+
+#[derive(Debug, PartialEq)]
+pub enum DriverError<E, RecvErr, SendErr> {
+    // the state machine rejected a message or failed to proceed
+    Protocol(E),
+    Receive(RecvErr),
+    Send(SendErr),
+    // `incoming` ended before the protocol finished
+    UnexpectedEof,
+    // the future was polled again after it completed
+    AlreadyFinished,
+}
+
+impl<E: fmt::Display, RecvErr: fmt::Display, SendErr: fmt::Display> fmt::Display
+    for DriverError<E, RecvErr, SendErr>
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DriverError::Protocol(e) => write!(f, "protocol error: {}", e),
+            DriverError::Receive(e) => write!(f, "failed to receive a message: {}", e),
+            DriverError::Send(e) => write!(f, "failed to send a message: {}", e),
+            DriverError::UnexpectedEof => write!(f, "incoming messages ended prematurely"),
+            DriverError::AlreadyFinished => write!(f, "the protocol already finished"),
+        }
+    }
+}
+
+// the start of a session, only whether it started without std
+#[cfg(feature = "std")]
+type Instant = std::time::Instant;
+#[cfg(not(feature = "std"))]
+type Instant = ();
+
+#[cfg(feature = "std")]
+fn now() -> Instant {
+    Instant::now()
+}
+
+#[cfg(not(feature = "std"))]
+fn now() -> Instant {}
+
+#[cfg(feature = "std")]
+fn elapsed(start: &Instant) -> Option<Duration> {
+    Some(start.elapsed())
+}
+
+#[cfg(not(feature = "std"))]
+fn elapsed(_start: &Instant) -> Option<Duration> {
+    None
+}
+
+/// The future returned by `run` and `run_with_metrics`.
+pub struct Run<SM, I, O, M = NoMetrics> {
+    state_machine: SM,
+    incoming: I,
+    outgoing: O,
+    needs_flush: bool,
+    metrics: M,
+    started: Option<Instant>,
+}
+
+// the state machine is never pinned, only `incoming` and `outgoing` are, and they are `Unpin`
+impl<SM, I, O, M> Unpin for Run<SM, I, O, M> {}
+
+pub fn run<SM, I, O, RecvErr>(state_machine: SM, incoming: I, outgoing: O) -> Run<SM, I, O>
+where
+    SM: StateMachine,
+    I: Stream<Item = Result<Msg<SM::MessageBody>, RecvErr>> + Unpin,
+    O: Sink<Msg<SM::MessageBody>> + Unpin,
+{
+    run_with_metrics(state_machine, incoming, outgoing, NoMetrics)
+}
+
+/// `run`, calling `metrics` when the session starts, a round completes, a message is rejected
+/// or a round fails, and when the protocol finishes.
+pub fn run_with_metrics<SM, I, O, M, RecvErr>(
+    state_machine: SM,
+    incoming: I,
+    outgoing: O,
+    metrics: M,
+) -> Run<SM, I, O, M>
+where
+    SM: StateMachine,
+    I: Stream<Item = Result<Msg<SM::MessageBody>, RecvErr>> + Unpin,
+    O: Sink<Msg<SM::MessageBody>> + Unpin,
+    M: Metrics,
+{
+    Run {
+        state_machine,
+        incoming,
+        outgoing,
+        needs_flush: false,
+        metrics,
+        started: None,
+    }
+}
+
+impl<SM, I, O, M> Run<SM, I, O, M> {
+    /// Gives the state machine back, e.g. to inspect it after an error.
+    pub fn into_state_machine(self) -> SM {
+        self.state_machine
+    }
+}
+
+impl<SM, I, O, M, RecvErr> Future for Run<SM, I, O, M>
+where
+    SM: StateMachine,
+    I: Stream<Item = Result<Msg<SM::MessageBody>, RecvErr>> + Unpin,
+    O: Sink<Msg<SM::MessageBody>> + Unpin,
+    M: Metrics,
+{
+    type Output = Result<SM::Output, DriverError<SM::Err, RecvErr, O::Error>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if this.started.is_none() {
+            this.started = Some(now());
+            this.metrics.session_started();
+        }
+        loop {
+            while !this.state_machine.message_queue().is_empty() {
+                match Pin::new(&mut this.outgoing).poll_ready(cx) {
+                    Poll::Ready(Ok(())) => {}
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(DriverError::Send(e))),
+                    Poll::Pending => return Poll::Pending,
+                }
+                let msg = this.state_machine.message_queue().remove(0);
+                if let Err(e) = Pin::new(&mut this.outgoing).start_send(msg) {
+                    return Poll::Ready(Err(DriverError::Send(e)));
+                }
+                this.needs_flush = true;
+            }
+            if this.needs_flush {
+                match Pin::new(&mut this.outgoing).poll_flush(cx) {
+                    Poll::Ready(Ok(())) => this.needs_flush = false,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(DriverError::Send(e))),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            if this.state_machine.is_finished() {
+                let output = this.state_machine.pick_output();
+                if output.is_some() {
+                    let elapsed = this.started.as_ref().and_then(elapsed);
+                    this.metrics.session_finished(elapsed);
+                }
+                return Poll::Ready(output.ok_or(DriverError::AlreadyFinished));
+            }
+            if this.state_machine.wants_to_proceed() {
+                let round = this.state_machine.current_round();
+                if let Err(e) = this.state_machine.proceed() {
+                    this.metrics.verification_failed(round);
+                    return Poll::Ready(Err(DriverError::Protocol(e)));
+                }
+                this.metrics.round_completed(round);
+                continue;
+            }
+
+            match Pin::new(&mut this.incoming).poll_next(cx) {
+                Poll::Ready(Some(Ok(msg))) => {
+                    if let Err(e) = this.state_machine.handle_incoming(msg) {
+                        let round = this.state_machine.current_round();
+                        this.metrics.verification_failed(round);
+                        return Poll::Ready(Err(DriverError::Protocol(e)));
+                    }
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(DriverError::Receive(e))),
+                Poll::Ready(None) => return Poll::Ready(Err(DriverError::UnexpectedEof)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
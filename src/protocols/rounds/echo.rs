@@ -0,0 +1,261 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Echo broadcast for any `StateMachine`
+//!
+//! Over point-to-point channels a party can send different "broadcast" messages to different
+//! parties, e.g. two commitments, and split the session. `EchoBroadcast` wraps a state machine
+//! and, before it completes a round with broadcasts, sends everyone a hash of all broadcasts of
+//! that round (ours included) as received, and waits for the same hash from every other party.
+//! Only if all hashes match does the wrapped state machine proceed, so all honest parties
+//! continue with identical copies of every broadcast. It costs one extra message per round.
+//! The wrapped messages are tagged with the round they were sent in, the hash is taken over
+//! their JSON encoding.
+
+use core::fmt;
+
+use serde::Serialize;
+use serde_json;
+use sha2::{Digest, Sha512};
+
+use protocols::rounds::{Msg, StateMachine};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EchoMessage<B> {
+    Inner { round: u16, body: B },
+    // hash of all broadcasts of `round`, as received by the sender
+    Echo { round: u16, digest: Vec<u8> },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EchoError<E> {
+    Inner(E),
+    // an echo from an unknown party, a second echo for the same round, or `proceed` called early
+    RoundMismatch,
+    // the echo of `party` for `round` differs from ours: either `party` or the sender of one of
+    // the round's broadcasts equivocated
+    Inconsistent { round: u16, party: u16 },
+}
+
+impl<E: fmt::Display> fmt::Display for EchoError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EchoError::Inner(e) => e.fmt(f),
+            EchoError::RoundMismatch => write!(f, "messages don't match the protocol round"),
+            EchoError::Inconsistent { round, party } => write!(
+                f,
+                "party {} received different broadcasts in round {}",
+                party, round
+            ),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for EchoError<E> {}
+
+pub struct EchoBroadcast<S: StateMachine> {
+    inner: S,
+    queue: Vec<Msg<EchoMessage<S::MessageBody>>>,
+    // (round, sender, encoded body) of every broadcast, ours included
+    broadcasts: Vec<(u16, u16, Vec<u8>)>,
+    // (round, sender, digest) of the echoes of the other parties
+    echoes: Vec<(u16, u16, Vec<u8>)>,
+    echoed_rounds: Vec<u16>,
+}
+
+impl<S> EchoBroadcast<S>
+where
+    S: StateMachine,
+    S::MessageBody: Serialize,
+{
+    pub fn new(inner: S) -> EchoBroadcast<S> {
+        let mut echo_broadcast = EchoBroadcast {
+            inner,
+            queue: Vec::new(),
+            broadcasts: Vec::new(),
+            echoes: Vec::new(),
+            echoed_rounds: Vec::new(),
+        };
+        echo_broadcast.forward_queue();
+        echo_broadcast
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    // moves the messages of the wrapped state machine to our queue
+    fn forward_queue(&mut self) {
+        let round = self.inner.current_round();
+        for msg in self.inner.message_queue().drain(..) {
+            if msg.receiver.is_none() {
+                self.broadcasts.push((round, msg.sender, encode(&msg.body)));
+            }
+            self.queue.push(Msg {
+                sender: msg.sender,
+                receiver: msg.receiver,
+                body: EchoMessage::Inner {
+                    round,
+                    body: msg.body,
+                },
+            });
+        }
+    }
+
+    fn has_broadcasts(&self, round: u16) -> bool {
+        self.broadcasts.iter().any(|(r, _, _)| *r == round)
+    }
+
+    fn digest(&self, round: u16) -> Vec<u8> {
+        let mut broadcasts: Vec<_> = self
+            .broadcasts
+            .iter()
+            .filter(|(r, _, _)| *r == round)
+            .map(|(_, sender, body)| (sender, body))
+            .collect();
+        broadcasts.sort();
+        let hasher = broadcasts.into_iter().fold(
+            Sha512::new().chain([12]).chain(round.to_be_bytes()),
+            |hasher, (sender, body)| {
+                hasher
+                    .chain(sender.to_be_bytes())
+                    .chain((body.len() as u64).to_be_bytes())
+                    .chain(body)
+            },
+        );
+        hasher.finalize().to_vec()
+    }
+}
+
+fn encode<B: Serialize>(body: &B) -> Vec<u8> {
+    serde_json::to_vec(body).expect("protocol messages can always be encoded")
+}
+
+impl<S> StateMachine for EchoBroadcast<S>
+where
+    S: StateMachine,
+    S::MessageBody: Serialize,
+{
+    type MessageBody = EchoMessage<S::MessageBody>;
+    type Output = S::Output;
+    type Err = EchoError<S::Err>;
+
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err> {
+        let Msg {
+            sender,
+            receiver,
+            body,
+        } = msg;
+        match body {
+            EchoMessage::Inner { round, body } => {
+                if receiver.is_none() {
+                    self.broadcasts.push((round, sender, encode(&body)));
+                }
+                self.inner
+                    .handle_incoming(Msg {
+                        sender,
+                        receiver,
+                        body,
+                    })
+                    .map_err(EchoError::Inner)?;
+                self.forward_queue();
+                Ok(())
+            }
+            EchoMessage::Echo { round, digest } => {
+                let duplicate = self
+                    .echoes
+                    .iter()
+                    .any(|(r, s, _)| *r == round && *s == sender);
+                if sender == 0
+                    || sender > self.parties()
+                    || sender == self.party_ind()
+                    || receiver.is_some()
+                    || duplicate
+                {
+                    return Err(EchoError::RoundMismatch);
+                }
+                self.echoes.push((round, sender, digest));
+                Ok(())
+            }
+        }
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>> {
+        &mut self.queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        if !self.inner.wants_to_proceed() {
+            return false;
+        }
+        let round = self.inner.current_round();
+        if !self.has_broadcasts(round) || !self.echoed_rounds.contains(&round) {
+            return true;
+        }
+        let received = self.echoes.iter().filter(|(r, _, _)| *r == round).count();
+        received + 1 == usize::from(self.parties())
+    }
+
+    fn proceed(&mut self) -> Result<(), Self::Err> {
+        if !self.wants_to_proceed() {
+            return Err(EchoError::RoundMismatch);
+        }
+        let round = self.inner.current_round();
+        if self.has_broadcasts(round) {
+            let digest = self.digest(round);
+            if !self.echoed_rounds.contains(&round) {
+                self.echoed_rounds.push(round);
+                self.queue.push(Msg {
+                    sender: self.party_ind(),
+                    receiver: None,
+                    body: EchoMessage::Echo { round, digest },
+                });
+                return Ok(());
+            }
+            let inconsistent = self
+                .echoes
+                .iter()
+                .find(|(r, _, echo)| *r == round && *echo != digest);
+            if let Some(&(round, party, _)) = inconsistent {
+                return Err(EchoError::Inconsistent { round, party });
+            }
+        }
+        self.inner.proceed().map_err(EchoError::Inner)?;
+        self.forward_queue();
+        Ok(())
+    }
+
+    fn current_round(&self) -> u16 {
+        self.inner.current_round()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+
+    fn pick_output(&mut self) -> Option<Self::Output> {
+        self.inner.pick_output()
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.inner.party_ind()
+    }
+
+    fn parties(&self) -> u16 {
+        self.inner.parties()
+    }
+}
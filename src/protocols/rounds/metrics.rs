@@ -0,0 +1,85 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Hooks to count and time protocol sessions, e.g. with Prometheus counters and histograms
+//!
+//! `driver::run_with_metrics` calls a `Metrics` as the state machine it drives starts, completes
+//! rounds, fails and finishes, `driver::run` uses `NoMetrics`. Every method does nothing by
+//! default, so an implementation only overrides what it records. They take `&self`, as metrics
+//! libraries update their counters through shared references, and `&M` and `Arc<M>` are
+//! `Metrics` too, so one registry can serve all concurrent sessions.
+
+use alloc::sync::Arc;
+use core::time::Duration;
+
+pub trait Metrics {
+    /// The driver was polled for the first time.
+    fn session_started(&self) {}
+
+    /// The state machine completed `round`.
+    fn round_completed(&self, _round: u16) {}
+
+    /// The state machine rejected a message or failed to proceed in `round`, e.g. on a commitment
+    /// that doesn't open or a partial signature that doesn't verify.
+    fn verification_failed(&self, _round: u16) {}
+
+    /// The protocol finished `elapsed` after the session started. `None` without the `std`
+    /// feature, which has no clock.
+    fn session_finished(&self, _elapsed: Option<Duration>) {}
+}
+
+/// Records nothing.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NoMetrics;
+
+impl Metrics for NoMetrics {}
+
+impl<M: Metrics + ?Sized> Metrics for &M {
+    fn session_started(&self) {
+        (**self).session_started()
+    }
+
+    fn round_completed(&self, round: u16) {
+        (**self).round_completed(round)
+    }
+
+    fn verification_failed(&self, round: u16) {
+        (**self).verification_failed(round)
+    }
+
+    fn session_finished(&self, elapsed: Option<Duration>) {
+        (**self).session_finished(elapsed)
+    }
+}
+
+impl<M: Metrics + ?Sized> Metrics for Arc<M> {
+    fn session_started(&self) {
+        (**self).session_started()
+    }
+
+    fn round_completed(&self, round: u16) {
+        (**self).round_completed(round)
+    }
+
+    fn verification_failed(&self, round: u16) {
+        (**self).verification_failed(round)
+    }
+
+    fn session_finished(&self, elapsed: Option<Duration>) {
+        (**self).session_finished(elapsed)
+    }
+}
@@ -0,0 +1,78 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! A common interface for running the multi-party protocols as round based state machines
+//!
+//! An executor feeds every message it receives to `handle_incoming`, calls `proceed` whenever
+//! `wants_to_proceed` returns true, and delivers everything that shows up in `message_queue` to
+//! the other parties. Once `is_finished` returns true the result can be taken with `pick_output`.
+//! Parties are numbered `1..=parties()`.
+//! With the `async` feature, `driver::run` does all of this over `Stream`/`Sink` channels.
+//! `echo::EchoBroadcast` wraps any state machine to detect parties sending different copies of a
+//! broadcast message to different parties.
+//! `metrics::Metrics` are hooks for counting sessions, rounds and failures and timing sessions.
+//! With the `tracing` feature, `traced::Traced` wraps any state machine in `tracing` spans and
+//! events for every message and round.
+
+use alloc::vec::Vec;
+
+#[cfg(feature = "async")]
+pub mod driver;
+#[cfg(feature = "std")]
+pub mod echo;
+pub mod metrics;
+#[cfg(feature = "tracing")]
+pub mod traced;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Msg<B> {
+    pub sender: u16,
+    // `None` for broadcast messages
+    pub receiver: Option<u16>,
+    pub body: B,
+}
+
+pub trait StateMachine {
+    type MessageBody;
+    type Output;
+    type Err;
+
+    /// Stores a message of any round, it's only processed once the state machine gets there.
+    fn handle_incoming(&mut self, msg: Msg<Self::MessageBody>) -> Result<(), Self::Err>;
+
+    /// Messages the state machine wants to send, the executor drains it.
+    fn message_queue(&mut self) -> &mut Vec<Msg<Self::MessageBody>>;
+
+    /// True once all messages needed to complete the current round have been received.
+    fn wants_to_proceed(&self) -> bool;
+
+    /// Completes the current round, queuing the messages of the next one.
+    fn proceed(&mut self) -> Result<(), Self::Err>;
+
+    fn current_round(&self) -> u16;
+
+    fn is_finished(&self) -> bool;
+
+    /// Takes the output of a finished protocol, `None` if it isn't finished or was already taken.
+    fn pick_output(&mut self) -> Option<Self::Output>;
+
+    fn party_ind(&self) -> u16;
+
+    fn parties(&self) -> u16;
+}
+
+mod test;
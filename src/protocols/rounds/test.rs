@@ -0,0 +1,546 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::time::Duration;
+
+    use futures_core::Stream;
+    use futures_sink::Sink;
+
+    use protocols::aggsig::{AggSigMessage, AggSigStateMachine, KeyAgg, SignFirstMsg};
+    use protocols::rounds::driver::{self, DriverError};
+    use protocols::rounds::metrics::Metrics;
+    use protocols::rounds::Msg;
+    use protocols::tests::verify_dalek;
+    use protocols::ExpandedKeyPair;
+
+    type Inboxes = Rc<RefCell<Vec<VecDeque<Msg<AggSigMessage>>>>>;
+
+    struct Incoming {
+        inboxes: Inboxes,
+        party: usize,
+    }
+
+    impl Stream for Incoming {
+        type Item = Result<Msg<AggSigMessage>, ()>;
+
+        fn poll_next(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Item>> {
+            match self.inboxes.borrow_mut()[self.party].pop_front() {
+                Some(msg) => Poll::Ready(Some(Ok(msg))),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    // broadcasts every message to all other parties
+    struct Outgoing {
+        inboxes: Inboxes,
+        party: usize,
+    }
+
+    impl Sink<Msg<AggSigMessage>> for Outgoing {
+        type Error = ();
+
+        fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(self: Pin<&mut Self>, msg: Msg<AggSigMessage>) -> Result<(), ()> {
+            for (j, inbox) in self.inboxes.borrow_mut().iter_mut().enumerate() {
+                if j != self.party {
+                    inbox.push_back(msg.clone());
+                }
+            }
+            Ok(())
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    #[derive(Default)]
+    struct Recorded {
+        started: RefCell<usize>,
+        rounds: RefCell<Vec<u16>>,
+        failures: RefCell<Vec<u16>>,
+        finished: RefCell<Vec<Option<Duration>>>,
+    }
+
+    impl Metrics for Recorded {
+        fn session_started(&self) {
+            *self.started.borrow_mut() += 1;
+        }
+
+        fn round_completed(&self, round: u16) {
+            self.rounds.borrow_mut().push(round);
+        }
+
+        fn verification_failed(&self, round: u16) {
+            self.failures.borrow_mut().push(round);
+        }
+
+        fn session_finished(&self, elapsed: Option<Duration>) {
+            self.finished.borrow_mut().push(elapsed);
+        }
+    }
+
+    #[test]
+    fn test_driver_aggsig() {
+        let message = b"driver";
+        let n = 3;
+        let keys: Vec<_> = (0..n).map(|_| ExpandedKeyPair::create()).collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let inboxes: Inboxes = Rc::new(RefCell::new(vec![VecDeque::new(); n]));
+
+        let mut runs: Vec<_> = keys
+            .into_iter()
+            .enumerate()
+            .map(|(i, keys)| {
+                let state_machine =
+                    AggSigStateMachine::new(keys, &pks, i as u16 + 1, message).unwrap();
+                let incoming = Incoming {
+                    inboxes: inboxes.clone(),
+                    party: i,
+                };
+                let outgoing = Outgoing {
+                    inboxes: inboxes.clone(),
+                    party: i,
+                };
+                driver::run(state_machine, incoming, outgoing)
+            })
+            .collect();
+        let apk = KeyAgg::key_aggregation_n(&pks, 0).apk;
+
+        // poll all parties in turns, like a single threaded executor would
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        let mut outputs: Vec<_> = (0..n).map(|_| None).collect();
+        for _ in 0..16 {
+            for (run, output) in runs.iter_mut().zip(outputs.iter_mut()) {
+                if output.is_none() {
+                    if let Poll::Ready(result) = Pin::new(run).poll(&mut cx) {
+                        *output = Some(result);
+                    }
+                }
+            }
+        }
+        for output in outputs {
+            let signature = output.expect("protocol didn't finish").unwrap();
+            assert!(verify_dalek(&apk, &signature, message));
+        }
+    }
+
+    #[test]
+    fn test_driver_metrics() {
+        let message = b"metrics";
+        let n = 3;
+        let keys: Vec<_> = (0..n).map(|_| ExpandedKeyPair::create()).collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let inboxes: Inboxes = Rc::new(RefCell::new(vec![VecDeque::new(); n]));
+        let metrics = Recorded::default();
+
+        let mut runs: Vec<_> = keys
+            .into_iter()
+            .enumerate()
+            .map(|(i, keys)| {
+                let state_machine =
+                    AggSigStateMachine::new(keys, &pks, i as u16 + 1, message).unwrap();
+                let incoming = Incoming {
+                    inboxes: inboxes.clone(),
+                    party: i,
+                };
+                let outgoing = Outgoing {
+                    inboxes: inboxes.clone(),
+                    party: i,
+                };
+                driver::run_with_metrics(state_machine, incoming, outgoing, &metrics)
+            })
+            .collect();
+
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        let mut finished = 0;
+        for _ in 0..16 {
+            for run in runs.iter_mut() {
+                if let Poll::Ready(result) = Pin::new(run).poll(&mut cx) {
+                    result.unwrap();
+                    finished += 1;
+                }
+            }
+            if finished == n {
+                break;
+            }
+        }
+        assert_eq!(finished, n);
+        assert_eq!(*metrics.started.borrow(), n);
+        let mut rounds = metrics.rounds.borrow().clone();
+        rounds.sort_unstable();
+        assert_eq!(rounds, [0, 0, 0, 1, 1, 1, 2, 2, 2, 3, 3, 3]);
+        assert!(metrics.failures.borrow().is_empty());
+        assert_eq!(metrics.finished.borrow().len(), n);
+        assert!(metrics.finished.borrow().iter().all(Option::is_some));
+
+        // a message from a party that isn't in the session fails party 1 in round 1
+        let keys = ExpandedKeyPair::create();
+        let pks = [
+            keys.public_key.clone(),
+            ExpandedKeyPair::create().public_key,
+        ];
+        let state_machine = AggSigStateMachine::new(keys, &pks, 1, message).unwrap();
+        let inboxes: Inboxes = Rc::new(RefCell::new(vec![VecDeque::new(); 2]));
+        inboxes.borrow_mut()[0].push_back(Msg {
+            sender: 3,
+            receiver: None,
+            body: AggSigMessage::Commitment(SignFirstMsg::from_bytes(&[0; 64])),
+        });
+        let incoming = Incoming {
+            inboxes: inboxes.clone(),
+            party: 0,
+        };
+        let outgoing = Outgoing { inboxes, party: 0 };
+        let metrics = Recorded::default();
+        let mut run = driver::run_with_metrics(state_machine, incoming, outgoing, &metrics);
+        match Pin::new(&mut run).poll(&mut cx) {
+            Poll::Ready(Err(DriverError::Protocol(_))) => {}
+            _ => panic!("expected a protocol error"),
+        }
+        assert_eq!(*metrics.started.borrow(), 1);
+        assert_eq!(*metrics.rounds.borrow(), [0]);
+        assert_eq!(*metrics.failures.borrow(), [1]);
+        assert!(metrics.finished.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_driver_unexpected_eof() {
+        struct Closed;
+        impl Stream for Closed {
+            type Item = Result<Msg<AggSigMessage>, ()>;
+            fn poll_next(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Item>> {
+                Poll::Ready(None)
+            }
+        }
+
+        let keys = ExpandedKeyPair::create();
+        let pks = [
+            keys.public_key.clone(),
+            ExpandedKeyPair::create().public_key,
+        ];
+        let state_machine = AggSigStateMachine::new(keys, &pks, 1, b"eof").unwrap();
+        let inboxes: Inboxes = Rc::new(RefCell::new(vec![VecDeque::new(); 2]));
+        let outgoing = Outgoing { inboxes, party: 0 };
+        let mut run = driver::run(state_machine, Closed, outgoing);
+
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        match Pin::new(&mut run).poll(&mut cx) {
+            Poll::Ready(Err(DriverError::UnexpectedEof)) => {}
+            _ => panic!("expected UnexpectedEof"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod echo_tests {
+    use protocols::aggsig::{AggSigMessage, AggSigStateMachine};
+    use protocols::rounds::echo::{EchoBroadcast, EchoError, EchoMessage};
+    use protocols::rounds::{Msg, StateMachine};
+    use protocols::simulate::{self, Fault, SimulationError};
+    use protocols::tests::verify_dalek;
+    use protocols::ExpandedKeyPair;
+    use Error;
+
+    type Party = EchoBroadcast<AggSigStateMachine>;
+
+    fn parties(keys: &[ExpandedKeyPair], message: &[u8]) -> Vec<Party> {
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        keys.iter()
+            .zip(1..)
+            .map(|(keys, i)| {
+                EchoBroadcast::new(AggSigStateMachine::new(keys.clone(), &pks, i, message).unwrap())
+            })
+            .collect()
+    }
+
+    // `tamper` can change a message for one receiver
+    fn run(
+        parties: &mut [Party],
+        mut tamper: impl FnMut(u16, &mut Msg<EchoMessage<AggSigMessage>>),
+    ) -> Result<(), EchoError<Error>> {
+        let result = simulate::run_with_faults(parties, |receiver, msg| {
+            tamper(receiver, msg);
+            Fault::Deliver
+        });
+        match result {
+            Ok(_) => Ok(()),
+            Err(SimulationError::Party { error, .. }) => Err(error),
+            Err(e) => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_echo_broadcast_signing() {
+        let message = b"echo";
+        let keys: Vec<_> = (0..3).map(|_| ExpandedKeyPair::create()).collect();
+        let mut parties = parties(&keys, message);
+        run(&mut parties, |_, _| {}).unwrap();
+        let apk = parties[0].inner().aggregated_public_key().clone();
+        for party in &mut parties {
+            let signature = party.pick_output().unwrap();
+            assert!(verify_dalek(&apk, &signature, message));
+        }
+    }
+
+    #[test]
+    fn test_echo_broadcast_detects_equivocation() {
+        let message = b"echo";
+        let keys: Vec<_> = (0..3).map(|_| ExpandedKeyPair::create()).collect();
+        let mut parties = parties(&keys, message);
+        // party 1 sends party 3 a different commitment than everyone else
+        let mut other = EchoBroadcast::new(
+            AggSigStateMachine::new(
+                keys[0].clone(),
+                &[
+                    keys[0].public_key.clone(),
+                    keys[1].public_key.clone(),
+                    keys[2].public_key.clone(),
+                ],
+                1,
+                message,
+            )
+            .unwrap(),
+        );
+        other.proceed().unwrap();
+        let other_commitment = other.message_queue().remove(0).body;
+
+        let result = run(&mut parties, |receiver, msg| {
+            let is_commitment = matches!(
+                msg.body,
+                EchoMessage::Inner {
+                    body: AggSigMessage::Commitment(_),
+                    ..
+                }
+            );
+            if msg.sender == 1 && receiver == 3 && is_commitment {
+                msg.body = other_commitment.clone();
+            }
+        });
+        assert!(
+            matches!(result, Err(EchoError::Inconsistent { round: 1, .. })),
+            "{:?}",
+            result
+        );
+        assert!(parties.iter().all(|party| party.current_round() == 1));
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod traced_tests {
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use protocols::aggsig::{AggSigMessage, AggSigStateMachine, SignFirstMsg};
+    use protocols::rounds::traced::Traced;
+    use protocols::rounds::{Msg, StateMachine};
+    use protocols::simulate::{self, Fault, SimulationError};
+    use protocols::tests::verify_dalek;
+    use protocols::ExpandedKeyPair;
+    use Error;
+
+    type Fields = Vec<(&'static str, String)>;
+
+    // records the fields of every span and event
+    #[derive(Clone, Default)]
+    struct Recorder {
+        spans: Arc<Mutex<Vec<Fields>>>,
+        events: Arc<Mutex<Vec<Fields>>>,
+    }
+
+    struct Collect<'a>(&'a mut Fields);
+
+    impl<'a> Visit for Collect<'a> {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.push((field.name(), format!("{:?}", value)));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes) -> Id {
+            let mut fields = Vec::new();
+            span.record(&mut Collect(&mut fields));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(fields);
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _: &Id, _: &Record) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut fields = Vec::new();
+            event.record(&mut Collect(&mut fields));
+            self.events.lock().unwrap().push(fields);
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    impl Recorder {
+        fn events(&self, message: &str) -> Vec<Fields> {
+            let events = self.events.lock().unwrap();
+            events
+                .iter()
+                .filter(|fields| fields.contains(&("message", message.to_string())))
+                .cloned()
+                .collect()
+        }
+    }
+
+    fn field<'a>(fields: &'a Fields, name: &str) -> &'a str {
+        &fields.iter().find(|(n, _)| *n == name).unwrap().1
+    }
+
+    fn parties(keys: &[ExpandedKeyPair], message: &[u8]) -> Vec<Traced<AggSigStateMachine>> {
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        keys.iter()
+            .zip(1..)
+            .map(|(keys, i)| {
+                let aggsig = AggSigStateMachine::new(keys.clone(), &pks, i, message).unwrap();
+                Traced::new(aggsig, "aggsig", b"session")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_traced_signing() {
+        let message = b"traced";
+        let keys: Vec<_> = (0..3).map(|_| ExpandedKeyPair::create()).collect();
+        let recorder = Recorder::default();
+        // the session spans are created by `Traced::new`
+        let (mut parties, transcript) = tracing::subscriber::with_default(recorder.clone(), || {
+            let mut parties = parties(&keys, message);
+            let transcript = simulate::run(&mut parties);
+            (parties, transcript.unwrap())
+        });
+        let apk = parties[0].inner().aggregated_public_key().clone();
+        for party in &mut parties {
+            assert!(verify_dalek(&apk, &party.pick_output().unwrap(), message));
+        }
+
+        let spans = recorder.spans.lock().unwrap().clone();
+        let sessions: Vec<_> = spans
+            .iter()
+            .filter(|fields| fields.iter().any(|(name, _)| *name == "session_id"))
+            .collect();
+        assert_eq!(sessions.len(), 3);
+        assert_eq!(field(sessions[1], "session_id"), hex::encode(b"session"));
+        assert_eq!(field(sessions[1], "party"), "2");
+        assert_eq!(field(sessions[1], "protocol"), "\"aggsig\"");
+        assert!(spans
+            .iter()
+            .any(|fields| fields == &vec![("round", "2".to_string())]));
+
+        // every delivery is received once and every message sent once
+        assert_eq!(recorder.events("received").len(), transcript.len());
+        assert_eq!(recorder.events("sent").len(), 9);
+        // 4 rounds of 3 parties
+        let proceeded = recorder.events("proceeded");
+        assert_eq!(proceeded.len(), 12);
+        let total = |events: Vec<Fields>, name| -> usize {
+            events
+                .iter()
+                .map(|fields| field(fields, name).parse::<usize>().unwrap())
+                .sum()
+        };
+        assert_eq!(
+            total(proceeded, "bytes"),
+            total(recorder.events("sent"), "size")
+        );
+        assert_eq!(recorder.events("finished").len(), 3);
+        assert!(recorder.events("aborted").is_empty());
+    }
+
+    #[test]
+    fn test_traced_abort() {
+        let message = b"traced";
+        let keys: Vec<_> = (0..3).map(|_| ExpandedKeyPair::create()).collect();
+        let recorder = Recorder::default();
+        let mut parties = parties(&keys, message);
+        let result = tracing::subscriber::with_default(recorder.clone(), || {
+            simulate::run_with_faults(&mut parties, |_, msg| {
+                if let AggSigMessage::Reveal(reveal) = &mut msg.body {
+                    if msg.sender == 2 {
+                        reveal.blind_factor = reveal.blind_factor.clone() + 1;
+                    }
+                }
+                Fault::Deliver
+            })
+        });
+        let expected = Error::InvalidCommitment { party: 2 };
+        assert_eq!(
+            result.map(|_| ()),
+            Err(SimulationError::Party {
+                party: 1,
+                error: expected,
+            })
+        );
+        let aborted = recorder.events("aborted");
+        assert_eq!(aborted.len(), 1);
+        assert_eq!(field(&aborted[0], "error"), format!("{:?}", expected));
+        assert!(recorder.events("finished").is_empty());
+
+        // a message from an unknown party is rejected
+        let rejected = tracing::subscriber::with_default(recorder.clone(), || {
+            parties[0].handle_incoming(Msg {
+                sender: 7,
+                receiver: None,
+                body: AggSigMessage::Commitment(SignFirstMsg::from_bytes(&[0; 64])),
+            })
+        });
+        assert_eq!(rejected, Err(Error::RoundMismatch));
+        assert_eq!(field(&recorder.events("rejected")[0], "sender"), "7");
+    }
+}
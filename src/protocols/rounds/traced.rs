@@ -0,0 +1,176 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! `tracing` spans and events for any `StateMachine`
+//!
+//! `Traced` runs the wrapped state machine in a `session` span with the protocol's name, the
+//! session id, the party index and the number of parties, and each call in a `round` span with
+//! the round number. It emits
+//!
+//! * `received` (debug) for every incoming message, with its sender, receiver and size,
+//! * `sent` (debug) for every message the state machine queues, with its receiver and size,
+//! * `proceeded` (info) when a round completes, with the next round and the number and total size
+//!   of the messages it queued, and `finished` (info) when the protocol is done,
+//! * `rejected` and `aborted` (warn) with the error, when a message isn't accepted or a round
+//!   fails, e.g. on a commitment that doesn't open (`InvalidCommitment { party: 3 }`).
+//!
+//! So in a stalled session the last `received` events of each party show whose messages are
+//! missing. Sizes are those of the JSON encoding, as used by `EchoBroadcast`.
+
+use core::fmt;
+
+use serde::Serialize;
+use serde_json;
+use tracing::Span;
+
+use protocols::rounds::{Msg, StateMachine};
+
+pub struct Traced<S: StateMachine> {
+    inner: S,
+    span: Span,
+    queue: Vec<Msg<S::MessageBody>>,
+}
+
+impl<S> Traced<S>
+where
+    S: StateMachine,
+    S::MessageBody: Serialize,
+    S::Err: fmt::Debug,
+{
+    /// `protocol` names the protocol in the session span (e.g. `"aggsig"`), `session_id` is
+    /// recorded hex encoded, so the spans of all parties of a session can be matched up.
+    pub fn new(inner: S, protocol: &'static str, session_id: &[u8]) -> Traced<S> {
+        let span = tracing::info_span!(
+            "session",
+            protocol,
+            session_id = %hex::encode(session_id),
+            party = inner.party_ind(),
+            parties = inner.parties()
+        );
+        let mut traced = Traced {
+            inner,
+            span,
+            queue: Vec::new(),
+        };
+        traced.forward_queue();
+        traced
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// The session span, to record the caller's own events in it.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    fn round_span(&self) -> Span {
+        tracing::info_span!(parent: &self.span, "round", round = self.inner.current_round())
+    }
+
+    // moves the messages of the wrapped state machine to our queue, returns their number and size
+    fn forward_queue(&mut self) -> (usize, usize) {
+        let (mut sent, mut bytes) = (0, 0);
+        for msg in self.inner.message_queue().drain(..) {
+            let size = encoded_len(&msg.body);
+            tracing::debug!(receiver = ?msg.receiver, size, "sent");
+            sent += 1;
+            bytes += size;
+            self.queue.push(msg);
+        }
+        (sent, bytes)
+    }
+}
+
+fn encoded_len<B: Serialize>(body: &B) -> usize {
+    serde_json::to_vec(body)
+        .expect("protocol messages can always be encoded")
+        .len()
+}
+
+impl<S> StateMachine for Traced<S>
+where
+    S: StateMachine,
+    S::MessageBody: Serialize,
+    S::Err: fmt::Debug,
+{
+    type MessageBody = S::MessageBody;
+    type Output = S::Output;
+    type Err = S::Err;
+
+    fn handle_incoming(&mut self, msg: Msg<S::MessageBody>) -> Result<(), S::Err> {
+        let span = self.round_span();
+        let _entered = span.enter();
+        let (sender, receiver, size) = (msg.sender, msg.receiver, encoded_len(&msg.body));
+        tracing::debug!(sender, receiver = ?receiver, size, "received");
+        if let Err(error) = self.inner.handle_incoming(msg) {
+            tracing::warn!(sender, error = ?error, "rejected");
+            return Err(error);
+        }
+        self.forward_queue();
+        Ok(())
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<S::MessageBody>> {
+        &mut self.queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        self.inner.wants_to_proceed()
+    }
+
+    fn proceed(&mut self) -> Result<(), S::Err> {
+        let span = self.round_span();
+        let _entered = span.enter();
+        if let Err(error) = self.inner.proceed() {
+            tracing::warn!(error = ?error, "aborted");
+            return Err(error);
+        }
+        let (sent, bytes) = self.forward_queue();
+        tracing::info!(
+            next_round = self.inner.current_round(),
+            sent,
+            bytes,
+            "proceeded"
+        );
+        if self.inner.is_finished() {
+            tracing::info!("finished");
+        }
+        Ok(())
+    }
+
+    fn current_round(&self) -> u16 {
+        self.inner.current_round()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.inner.is_finished()
+    }
+
+    fn pick_output(&mut self) -> Option<S::Output> {
+        self.inner.pick_output()
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.inner.party_ind()
+    }
+
+    fn parties(&self) -> u16 {
+        self.inner.parties()
+    }
+}
@@ -0,0 +1,542 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! The aggsig rounds over any curv curve
+//!
+//! The same three rounds as `aggsig` (commit to `R`, reveal `R`, partial signatures) and the same
+//! key aggregation, generic over a `Scheme` that picks the curve, the challenge hash and the
+//! encoding of keys and signatures:
+//! * `Rfc8032`: Ed25519, the signatures verify as RFC8032 ones.
+//! * `ristretto::Ristretto255`: the Ristretto group.
+//! * `bip340::Bip340`: secp256k1, the signatures are BIP-340 (taproot) Schnorr signatures.
+//! * `sr25519::Sr25519`: Ristretto with schnorrkel's transcripts, the signatures are Substrate
+//!   sr25519 ones (with the `transcript` feature).
+//!
+//! Schemes that only encode some of the points (BIP-340 drops the `y` coordinate and means the
+//! one with an even `y`) say so with `Scheme::is_normalized`. The aggregated key and the
+//! aggregated `R` are then negated when they aren't normalized, together with the coefficients
+//! and nonces they are made of, so every signer set ends up with an encodable key and signature.
+//!
+//! `aggsig` stays the Ed25519 implementation with all the extensions (contexts, weights,
+//! derivation, ...), this module only has the plain rounds.
+//!
+//! Ed448 isn't a `Scheme`: curv has no Ed448 curve, and its 57 byte encodings and SHAKE256
+//! challenge don't fit the trait. `protocols::ed448` (with the `ed448` feature) has the same
+//! rounds on the `ed448-goldilocks` arithmetic.
+
+#[cfg(test)]
+mod test;
+
+use core::convert::TryFrom;
+
+use curv::arithmetic::Converter;
+use curv::cryptographic_primitives::commitments::hash_commitment::HashCommitment;
+use curv::cryptographic_primitives::commitments::traits::Commitment;
+use curv::elliptic::curves::{Curve, Ed25519, Point, Scalar};
+use curv::BigInt;
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::{CryptoRng, Rng, RngCore};
+use sha2::{Digest, Sha512};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use protocols::{decode_point, Signature as Ed25519Signature};
+use Error::{self, InvalidCom, InvalidKey, InvalidPartialSignature, InvalidPoint, InvalidSig};
+use VerifyError;
+
+pub const SIGNATURE_SIZE: usize = 64;
+
+/// A Schnorr signature scheme: a curve, a challenge and 32 byte encodings of points and scalars.
+pub trait Scheme: Sized {
+    type Curve: Curve;
+
+    /// Separates the key, coefficient and nonce hashes of different schemes.
+    const NAME: &'static [u8];
+
+    /// The challenge `k` of `s * G == R + k * A`.
+    fn challenge(
+        R: &Point<Self::Curve>,
+        public_key: &Point<Self::Curve>,
+        message: &[u8],
+    ) -> Scalar<Self::Curve>;
+
+    /// The nonce `r` of the signer with `secret_key` and `public_key` for `message`, by default
+    /// SHA-512 of the secret key, the message and fresh randomness reduced mod the group order.
+    fn nonce(
+        secret_key: &Scalar<Self::Curve>,
+        _public_key: &Point<Self::Curve>,
+        message: &[u8],
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Scalar<Self::Curve> {
+        hash_to_scalar(
+            scheme_hasher::<Self>(18)
+                .chain(&*secret_key.to_bytes())
+                .chain(message)
+                .chain(rng.gen::<[u8; 32]>()),
+        )
+    }
+
+    /// Whether `point` is one `encode_point` and `decode_point` round trip, all of them by
+    /// default.
+    fn is_normalized(_point: &Point<Self::Curve>) -> bool {
+        true
+    }
+
+    fn encode_point(point: &Point<Self::Curve>) -> [u8; 32];
+
+    /// `None` for anything but the encoding of a normalized point.
+    fn decode_point(bytes: &[u8; 32]) -> Option<Point<Self::Curve>>;
+
+    fn encode_scalar(scalar: &Scalar<Self::Curve>) -> [u8; 32];
+
+    /// `None` for anything but the encoding of a reduced scalar.
+    fn decode_scalar(bytes: &[u8; 32]) -> Option<Scalar<Self::Curve>>;
+}
+
+/// Ed25519 with the RFC8032 challenge and encodings. The keys aren't RFC8032 keys (there is no
+/// seed expansion) but the aggregated keys and signatures are, any RFC8032 verifier accepts the
+/// signatures.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rfc8032;
+
+impl Scheme for Rfc8032 {
+    type Curve = Ed25519;
+
+    const NAME: &'static [u8] = b"rfc8032";
+
+    fn challenge(
+        R: &Point<Ed25519>,
+        public_key: &Point<Ed25519>,
+        message: &[u8],
+    ) -> Scalar<Ed25519> {
+        Ed25519Signature::k(R, public_key, message)
+    }
+
+    fn encode_point(point: &Point<Ed25519>) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&point.to_bytes(true));
+        bytes
+    }
+
+    fn decode_point(bytes: &[u8; 32]) -> Option<Point<Ed25519>> {
+        decode_point(bytes)
+    }
+
+    fn encode_scalar(scalar: &Scalar<Ed25519>) -> [u8; 32] {
+        scalar_to_bytes_le(scalar)
+    }
+
+    fn decode_scalar(bytes: &[u8; 32]) -> Option<Scalar<Ed25519>> {
+        scalar_from_bytes_le(bytes)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct KeyPair<S: Scheme> {
+    pub public_key: Point<S::Curve>,
+    secret_key: Scalar<S::Curve>,
+}
+
+// curv scalars zeroize themselves on drop, so the secret is scrubbed by overwriting it
+impl<S: Scheme> Zeroize for KeyPair<S> {
+    fn zeroize(&mut self) {
+        self.public_key = Point::zero();
+        self.secret_key = Scalar::zero();
+    }
+}
+
+impl<S: Scheme> ZeroizeOnDrop for KeyPair<S> {}
+
+impl<S: Scheme> KeyPair<S> {
+    #[cfg(feature = "std")]
+    pub fn create() -> KeyPair<S> {
+        Self::create_rng(&mut thread_rng())
+    }
+
+    pub fn create_rng(rng: &mut (impl CryptoRng + RngCore)) -> KeyPair<S> {
+        let mut seed: [u8; 32] = rng.gen();
+        let keys = Self::from_seed(&seed);
+        seed.zeroize();
+        keys
+    }
+
+    /// The secret key is SHA-512 of `seed` reduced mod the group order, negated if that makes
+    /// the public key normalized.
+    pub fn from_seed(seed: &[u8; 32]) -> KeyPair<S> {
+        let mut secret_key = hash_to_scalar(scheme_hasher::<S>(16).chain(seed));
+        let mut public_key = Point::generator() * &secret_key;
+        if !S::is_normalized(&public_key) {
+            secret_key = -secret_key;
+            public_key = -public_key;
+        }
+        KeyPair {
+            public_key,
+            secret_key,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct KeyAgg<S: Scheme> {
+    pub apk: Point<S::Curve>,
+    pub hash: Scalar<S::Curve>,
+}
+
+impl<S: Scheme> KeyAgg<S> {
+    /// The aggregated key `sum(a_i * pks[i])` and our coefficient `a_i = H(L || pks[i])`, with
+    /// `L` the hash of all the public keys, for the party with `pks[party_index]`. Both are
+    /// negated if the key isn't normalized.
+    /// Fails with `InvalidKey` if there is no such party or a key is the identity.
+    pub fn key_aggregation_n(
+        pks: &[Point<S::Curve>],
+        party_index: usize,
+    ) -> Result<KeyAgg<S>, Error> {
+        if party_index >= pks.len() || pks.iter().any(|pk| pk.is_zero()) {
+            return Err(InvalidKey);
+        }
+        let prefix = pks
+            .iter()
+            .fold(scheme_hasher::<S>(17), |hasher, pk| {
+                hasher.chain(&*pk.to_bytes(true))
+            })
+            .finalize();
+        let coefficient = |pk: &Point<S::Curve>| {
+            hash_to_scalar(
+                scheme_hasher::<S>(17)
+                    .chain(prefix)
+                    .chain(&*pk.to_bytes(true)),
+            )
+        };
+        let apk = pks
+            .iter()
+            .fold(Point::zero(), |apk, pk| apk + pk * coefficient(pk));
+        let hash = coefficient(&pks[party_index]);
+        if S::is_normalized(&apk) {
+            Ok(KeyAgg { apk, hash })
+        } else {
+            Ok(KeyAgg {
+                apk: -apk,
+                hash: -hash,
+            })
+        }
+    }
+}
+
+/// Our secret nonce `r` and its `R`, consumed by `partial_sign` so it can't sign twice.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct EphemeralKey<S: Scheme> {
+    r: Scalar<S::Curve>,
+    pub R: Point<S::Curve>,
+}
+
+impl<S: Scheme> Zeroize for EphemeralKey<S> {
+    fn zeroize(&mut self) {
+        self.r = Scalar::zero();
+    }
+}
+
+impl<S: Scheme> ZeroizeOnDrop for EphemeralKey<S> {}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SignFirstMsg {
+    pub commitment: BigInt,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct SignSecondMsg<S: Scheme> {
+    pub R: Point<S::Curve>,
+    pub blind_factor: BigInt,
+}
+
+/// A share of the signature, `R` is the sum of all the nonces as it is, before normalizing.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct PartialSignature<S: Scheme> {
+    pub R: Point<S::Curve>,
+    pub s: Scalar<S::Curve>,
+}
+
+#[cfg(feature = "std")]
+pub fn create_ephemeral_key_and_commit<S: Scheme>(
+    keys: &KeyPair<S>,
+    message: &[u8],
+) -> (EphemeralKey<S>, SignFirstMsg, SignSecondMsg<S>) {
+    create_ephemeral_key_and_commit_rng(keys, message, &mut thread_rng())
+}
+
+/// Round 1: the nonce is `Scheme::nonce`, the commitment is to the compressed encoding of `R`.
+pub fn create_ephemeral_key_and_commit_rng<S: Scheme>(
+    keys: &KeyPair<S>,
+    message: &[u8],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> (EphemeralKey<S>, SignFirstMsg, SignSecondMsg<S>) {
+    let r = S::nonce(&keys.secret_key, &keys.public_key, message, rng);
+    let R = Point::generator() * &r;
+    let blind_factor = BigInt::from_bytes(&rng.gen::<[u8; 32]>());
+    let commitment = HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
+        &BigInt::from_bytes(&R.to_bytes(true)),
+        &blind_factor,
+    );
+    (
+        EphemeralKey { r, R: R.clone() },
+        SignFirstMsg { commitment },
+        SignSecondMsg { R, blind_factor },
+    )
+}
+
+/// Round 2: checks that the revealed `R` is the committed one. Fails with `InvalidPoint` for
+/// the identity and with `InvalidCom` if it doesn't match.
+pub fn verify_commitment<S: Scheme>(
+    sign_first_msg: &SignFirstMsg,
+    sign_second_msg: &SignSecondMsg<S>,
+) -> Result<(), Error> {
+    if sign_second_msg.R.is_zero() {
+        return Err(InvalidPoint);
+    }
+    let commitment = HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
+        &BigInt::from_bytes(&sign_second_msg.R.to_bytes(true)),
+        &sign_second_msg.blind_factor,
+    );
+    if commitment == sign_first_msg.commitment {
+        Ok(())
+    } else {
+        Err(InvalidCom)
+    }
+}
+
+/// The sum of the partial `R`, `None` if there are none.
+pub fn get_R_tot<'a, E: Curve>(Rs: impl IntoIterator<Item = &'a Point<E>>) -> Option<Point<E>> {
+    let mut Rs = Rs.into_iter();
+    let first = Rs.next()?.clone();
+    Some(Rs.fold(first, |sum, R| sum + R))
+}
+
+/// Round 3: our partial signature `r + k * a * x`, with `r` negated if `R_tot` isn't
+/// normalized. `ephemeral_key` is consumed.
+pub fn partial_sign<S: Scheme>(
+    ephemeral_key: EphemeralKey<S>,
+    keys: &KeyPair<S>,
+    a: &Scalar<S::Curve>,
+    R_tot: &Point<S::Curve>,
+    agg_pubkey: &Point<S::Curve>,
+    message: &[u8],
+) -> PartialSignature<S> {
+    let R = normalize::<S>(R_tot);
+    let k = S::challenge(&R, agg_pubkey, message);
+    let r = if S::is_normalized(R_tot) {
+        ephemeral_key.r.clone()
+    } else {
+        -&ephemeral_key.r
+    };
+    PartialSignature {
+        R: R_tot.clone(),
+        s: r + k * a * &keys.secret_key,
+    }
+}
+
+/// Checks the partial signature of the party with the coefficient `a`, public key
+/// `partial_public_key` and nonce `partial_R`.
+pub fn verify_partial_sig<S: Scheme>(
+    sig: &PartialSignature<S>,
+    message: &[u8],
+    a: &Scalar<S::Curve>,
+    partial_R: &Point<S::Curve>,
+    partial_public_key: &Point<S::Curve>,
+    agg_pubkey: &Point<S::Curve>,
+) -> Result<(), Error> {
+    let k = S::challenge(&normalize::<S>(&sig.R), agg_pubkey, message);
+    let partial_R = if S::is_normalized(&sig.R) {
+        partial_R.clone()
+    } else {
+        -partial_R
+    };
+    if &sig.s * Point::generator() == partial_R + partial_public_key * (k * a) {
+        Ok(())
+    } else {
+        Err(InvalidSig)
+    }
+}
+
+/// The signature under the aggregated key. Fails with `InvalidSig` if `sigs` is empty and with
+/// `InvalidPartialSignature { party: i + 1 }` if the i-th has another `R` than the first one.
+pub fn add_signature_parts<'a, S: Scheme + 'a>(
+    sigs: impl IntoIterator<Item = &'a PartialSignature<S>>,
+) -> Result<Signature<S>, Error> {
+    let mut sigs = sigs.into_iter();
+    let first = sigs.next().ok_or(InvalidSig)?;
+    let s = sigs.zip(1..).try_fold(first.s.clone(), |s, (sig, i)| {
+        if sig.R != first.R {
+            return Err(InvalidPartialSignature {
+                party: u16::try_from(i + 1).unwrap_or(u16::MAX),
+            });
+        }
+        Ok(s + &sig.s)
+    })?;
+    Ok(Signature {
+        R: normalize::<S>(&first.R),
+        s,
+    })
+}
+
+#[cfg(feature = "std")]
+pub fn sign_single<S: Scheme>(message: &[u8], keys: &KeyPair<S>) -> Signature<S> {
+    sign_single_rng(message, keys, &mut thread_rng())
+}
+
+/// A signature by a single key, the same as a signing session with only `keys` and the
+/// coefficient one.
+pub fn sign_single_rng<S: Scheme>(
+    message: &[u8],
+    keys: &KeyPair<S>,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Signature<S> {
+    let (ephemeral_key, _, _) = create_ephemeral_key_and_commit_rng(keys, message, rng);
+    let R = ephemeral_key.R.clone();
+    let partial_sig = partial_sign(
+        ephemeral_key,
+        keys,
+        &Scalar::from(1),
+        &R,
+        &keys.public_key,
+        message,
+    );
+    add_signature_parts(&[partial_sig]).expect("there is one partial signature")
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Signature<S: Scheme> {
+    pub R: Point<S::Curve>,
+    pub s: Scalar<S::Curve>,
+}
+
+impl<S: Scheme> Signature<S> {
+    /// Checks `s * G == R + k * A`, fails with `InvalidPublicKey` for the identity or a key that
+    /// isn't normalized and with `InvalidR` for an `R` that isn't.
+    pub fn verify(&self, message: &[u8], public_key: &Point<S::Curve>) -> Result<(), VerifyError> {
+        if public_key.is_zero() || !S::is_normalized(public_key) {
+            return Err(VerifyError::InvalidPublicKey);
+        }
+        if !S::is_normalized(&self.R) {
+            return Err(VerifyError::InvalidR);
+        }
+        let k = S::challenge(&self.R, public_key, message);
+        if &self.s * Point::generator() == &self.R + public_key * k {
+            Ok(())
+        } else {
+            Err(VerifyError::InvalidSignature)
+        }
+    }
+
+    /// The encoding `R || s`.
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_SIZE] {
+        let mut bytes = [0u8; SIGNATURE_SIZE];
+        bytes[..32].copy_from_slice(&S::encode_point(&self.R));
+        bytes[32..].copy_from_slice(&S::encode_scalar(&self.s));
+        bytes
+    }
+
+    /// Decodes `to_bytes`, every signature has a single encoding.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Signature<S>, VerifyError> {
+        if bytes.len() != SIGNATURE_SIZE {
+            return Err(VerifyError::InvalidSignature);
+        }
+        let R = S::decode_point(&to_array(&bytes[..32])).ok_or(VerifyError::InvalidR)?;
+        let s = S::decode_scalar(&to_array(&bytes[32..])).ok_or(VerifyError::NonCanonicalS)?;
+        Ok(Signature { R, s })
+    }
+
+    /// `verify` of an encoded signature under an encoded public key.
+    pub fn verify_bytes(
+        signature: &[u8],
+        message: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), VerifyError> {
+        if public_key.len() != 32 {
+            return Err(VerifyError::InvalidPublicKey);
+        }
+        let public_key =
+            S::decode_point(&to_array(public_key)).ok_or(VerifyError::InvalidPublicKey)?;
+        Self::from_bytes(signature)?.verify(message, &public_key)
+    }
+}
+
+/// The 64 byte output reduced mod the group order, so the result is uniform.
+pub(crate) fn hash_to_scalar<E: Curve>(hasher: Sha512) -> Scalar<E> {
+    let mut hash = hasher.finalize();
+    // reverse because BigInt uses BigEndian.
+    hash.reverse();
+    let scalar = Scalar::from_bigint(&BigInt::from_bytes(&hash));
+    hash.as_mut_slice().zeroize();
+    scalar
+}
+
+/// The big endian encoding of `scalar`, for curves with a 32 byte group order.
+pub(crate) fn scalar_to_bytes_be<E: Curve>(scalar: &Scalar<E>) -> [u8; 32] {
+    let bytes = scalar.to_bigint().to_bytes();
+    let mut padded = [0u8; 32];
+    padded[32 - bytes.len()..].copy_from_slice(&bytes);
+    padded
+}
+
+pub(crate) fn scalar_from_bytes_be<E: Curve>(bytes: &[u8; 32]) -> Option<Scalar<E>> {
+    let s = BigInt::from_bytes(bytes);
+    if s < *Scalar::<E>::group_order() {
+        Some(Scalar::from_bigint(&s))
+    } else {
+        None
+    }
+}
+
+pub(crate) fn scalar_to_bytes_le<E: Curve>(scalar: &Scalar<E>) -> [u8; 32] {
+    let mut bytes = scalar_to_bytes_be(scalar);
+    bytes.reverse();
+    bytes
+}
+
+pub(crate) fn scalar_from_bytes_le<E: Curve>(bytes: &[u8; 32]) -> Option<Scalar<E>> {
+    let mut bytes = *bytes;
+    bytes.reverse();
+    scalar_from_bytes_be(&bytes)
+}
+
+// the prefix byte then the scheme name, so no two schemes or rounds share a hash
+fn scheme_hasher<S: Scheme>(prefix: u8) -> Sha512 {
+    // the names are short constants
+    Sha512::new()
+        .chain([prefix, S::NAME.len() as u8])
+        .chain(S::NAME)
+}
+
+fn normalize<S: Scheme>(point: &Point<S::Curve>) -> Point<S::Curve> {
+    if S::is_normalized(point) {
+        point.clone()
+    } else {
+        -point
+    }
+}
+
+// `bytes` is 32 bytes long
+fn to_array(bytes: &[u8]) -> [u8; 32] {
+    let mut array = [0u8; 32];
+    array.copy_from_slice(bytes);
+    array
+}
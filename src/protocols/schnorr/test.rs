@@ -0,0 +1,94 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use itertools::MultiUnzip;
+    use rand::Rng;
+
+    use protocols::schnorr::{self, KeyAgg, KeyPair, Rfc8032, Scheme, Signature};
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use protocols::Signature as Ed25519Signature;
+
+    #[test]
+    fn test_rfc8032_multiparty_signing() {
+        let mut rng = deterministic_fast_rand("test_rfc8032_multiparty_signing", None);
+        let message: [u8; 32] = rng.gen();
+        let keys: Vec<KeyPair<Rfc8032>> = (0..3).map(|_| KeyPair::create_rng(&mut rng)).collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let key_aggs: Vec<_> = (0..3)
+            .map(|i| KeyAgg::<Rfc8032>::key_aggregation_n(&pks, i).unwrap())
+            .collect();
+        let apk = key_aggs[0].apk.clone();
+
+        let (ephemeral_keys, first_msgs, second_msgs): (Vec<_>, Vec<_>, Vec<_>) = keys
+            .iter()
+            .map(|k| schnorr::create_ephemeral_key_and_commit_rng(k, &message, &mut rng))
+            .multiunzip();
+        for (first_msg, second_msg) in first_msgs.iter().zip(&second_msgs) {
+            assert!(schnorr::verify_commitment(first_msg, second_msg).is_ok());
+        }
+        let Rs: Vec<_> = second_msgs.iter().map(|msg| msg.R.clone()).collect();
+        let R_tot = schnorr::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = ephemeral_keys
+            .into_iter()
+            .zip(&keys)
+            .zip(&key_aggs)
+            .map(|((eph, k), key_agg)| {
+                schnorr::partial_sign(eph, k, &key_agg.hash, &R_tot, &apk, &message)
+            })
+            .collect();
+        for (i, sig) in partial_sigs.iter().enumerate() {
+            assert!(schnorr::verify_partial_sig(
+                sig,
+                &message,
+                &key_aggs[i].hash,
+                &Rs[i],
+                &pks[i],
+                &apk
+            )
+            .is_ok());
+        }
+        let signature = schnorr::add_signature_parts(&partial_sigs).unwrap();
+        assert!(signature.verify(&message, &apk).is_ok());
+
+        let bytes = signature.to_bytes();
+        assert_eq!(Signature::<Rfc8032>::from_bytes(&bytes).unwrap(), signature);
+        let rfc8032_signature = Ed25519Signature::from_bytes(&bytes).unwrap();
+        assert!(rfc8032_signature.verify(&message, &apk).is_ok());
+        assert!(verify_dalek(&apk, &rfc8032_signature, &message));
+        assert!(
+            Signature::<Rfc8032>::verify_bytes(&bytes, &message, &Rfc8032::encode_point(&apk))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_rfc8032_single_signer() {
+        let mut rng = deterministic_fast_rand("test_rfc8032_single_signer", None);
+        let message: [u8; 32] = rng.gen();
+        let keys = KeyPair::<Rfc8032>::from_seed(&rng.gen());
+        let signature = schnorr::sign_single_rng(&message, &keys, &mut rng);
+        let rfc8032_signature = Ed25519Signature::from_bytes(&signature.to_bytes()).unwrap();
+        assert!(verify_dalek(&keys.public_key, &rfc8032_signature, &message));
+        assert!(!verify_dalek(
+            &keys.public_key,
+            &rfc8032_signature,
+            b"another message"
+        ));
+    }
+}
@@ -0,0 +1,197 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Runs n `StateMachine` parties in memory, for integration tests of the protocols
+//!
+//! `run` proceeds every party that can and routes the messages they queue to their receivers,
+//! broadcasts to everyone but the sender, until no party can proceed anymore. Every delivery is
+//! recorded in the returned `Transcript`. `run_with_faults` passes each message to a closure on its
+//! way to every receiver, which can change it or drop it, e.g. to test that a protocol detects
+//! a cheating party or to simulate a party going offline. The outputs are picked from the parties
+//! afterwards, with `pick_output`.
+//!
+//! Messages are delivered in the order they were queued, sender by sender, so the simulation is
+//! deterministic as long as the parties are.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use protocols::rounds::{Msg, StateMachine};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SimulationError<E> {
+    // `party` rejected a message or failed to proceed
+    Party { party: u16, error: E },
+    // no party can proceed but the `unfinished` ones haven't finished, e.g. a message was dropped
+    Stalled { unfinished: Vec<u16> },
+    // a message was addressed to a party that doesn't exist or to its sender
+    UnknownReceiver { sender: u16, receiver: u16 },
+    // the parties aren't numbered `1..=parties.len()` in order
+    PartyMismatch,
+}
+
+impl<E: fmt::Display> fmt::Display for SimulationError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimulationError::Party { party, error } => write!(f, "party {}: {}", party, error),
+            SimulationError::Stalled { unfinished } => {
+                write!(
+                    f,
+                    "the protocol stalled, parties {:?} didn't finish",
+                    unfinished
+                )
+            }
+            SimulationError::UnknownReceiver { sender, receiver } => write!(
+                f,
+                "party {} sent a message to unknown party {}",
+                sender, receiver
+            ),
+            SimulationError::PartyMismatch => {
+                write!(f, "the parties aren't numbered 1 to n in order")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug + fmt::Display> std::error::Error for SimulationError<E> {}
+
+/// What `run_with_faults` does with a message on its way to one receiver.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    Deliver,
+    Drop,
+}
+
+/// A message on its way to `receiver`, as it was delivered.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delivery<B> {
+    /// The sender's `current_round` when the message was routed.
+    pub round: u16,
+    pub receiver: u16,
+    pub msg: Msg<B>,
+    pub dropped: bool,
+}
+
+pub type Transcript<B> = Vec<Delivery<B>>;
+
+/// `run_with_faults` delivering every message unchanged.
+pub fn run<S>(parties: &mut [S]) -> Result<Transcript<S::MessageBody>, SimulationError<S::Err>>
+where
+    S: StateMachine,
+    S::MessageBody: Clone,
+{
+    run_with_faults(parties, |_, _| Fault::Deliver)
+}
+
+/// Runs `parties`, numbered `1..=parties.len()`, passing every message and its receiver to
+/// `faults` before delivering it. Stops at the first error of a party. On success every party
+/// has finished and the transcript holds every delivery in order.
+pub fn run_with_faults<S>(
+    parties: &mut [S],
+    mut faults: impl FnMut(u16, &mut Msg<S::MessageBody>) -> Fault,
+) -> Result<Transcript<S::MessageBody>, SimulationError<S::Err>>
+where
+    S: StateMachine,
+    S::MessageBody: Clone,
+{
+    let n = parties.len();
+    if parties.iter().enumerate().any(|(i, party)| {
+        usize::from(party.party_ind()) != i + 1 || usize::from(party.parties()) != n
+    }) {
+        return Err(SimulationError::PartyMismatch);
+    }
+    let mut transcript = Vec::new();
+    loop {
+        deliver(parties, &mut faults, &mut transcript)?;
+        let mut proceeded = false;
+        for party in parties.iter_mut().filter(|party| party.wants_to_proceed()) {
+            let party_ind = party.party_ind();
+            party.proceed().map_err(|error| SimulationError::Party {
+                party: party_ind,
+                error,
+            })?;
+            proceeded = true;
+        }
+        if !proceeded {
+            break;
+        }
+    }
+    let unfinished: Vec<_> = parties
+        .iter()
+        .filter(|party| !party.is_finished())
+        .map(|party| party.party_ind())
+        .collect();
+    if unfinished.is_empty() {
+        Ok(transcript)
+    } else {
+        Err(SimulationError::Stalled { unfinished })
+    }
+}
+
+// routes the queued messages of all parties
+fn deliver<S>(
+    parties: &mut [S],
+    faults: &mut impl FnMut(u16, &mut Msg<S::MessageBody>) -> Fault,
+    transcript: &mut Transcript<S::MessageBody>,
+) -> Result<(), SimulationError<S::Err>>
+where
+    S: StateMachine,
+    S::MessageBody: Clone,
+{
+    let mut queued = Vec::new();
+    for party in parties.iter_mut() {
+        let round = party.current_round();
+        queued.extend(party.message_queue().drain(..).map(|msg| (round, msg)));
+    }
+    // the parties are numbered by their position, which `run_with_faults` checked
+    let n = parties.len() as u16;
+    for (round, msg) in queued {
+        let receivers = match msg.receiver {
+            Some(receiver) if receiver == 0 || receiver > n || receiver == msg.sender => {
+                return Err(SimulationError::UnknownReceiver {
+                    sender: msg.sender,
+                    receiver,
+                })
+            }
+            Some(receiver) => receiver..=receiver,
+            None => 1..=n,
+        };
+        for receiver in receivers.filter(|receiver| *receiver != msg.sender) {
+            let mut msg = msg.clone();
+            let dropped = faults(receiver, &mut msg) == Fault::Drop;
+            if !dropped {
+                parties[usize::from(receiver) - 1]
+                    .handle_incoming(msg.clone())
+                    .map_err(|error| SimulationError::Party {
+                        party: receiver,
+                        error,
+                    })?;
+            }
+            transcript.push(Delivery {
+                round,
+                receiver,
+                msg,
+                dropped,
+            });
+        }
+    }
+    Ok(())
+}
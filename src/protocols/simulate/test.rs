@@ -0,0 +1,109 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use curv::BigInt;
+
+    use protocols::aggsig::{AggSigMessage, AggSigStateMachine};
+    use protocols::rounds::StateMachine;
+    use protocols::simulate::{self, Fault, SimulationError};
+    use protocols::tests::verify_dalek;
+    use protocols::ExpandedKeyPair;
+    use Error::InvalidCommitment;
+
+    fn parties(n: usize, message: &[u8]) -> Vec<AggSigStateMachine> {
+        let keys: Vec<_> = (0..n).map(|_| ExpandedKeyPair::create()).collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        keys.into_iter()
+            .zip(1..)
+            .map(|(keys, i)| AggSigStateMachine::new(keys, &pks, i, message).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_run_aggsig() {
+        let message = b"simulate";
+        let mut parties = parties(3, message);
+        let transcript = simulate::run(&mut parties).unwrap();
+
+        // a broadcast to 2 receivers from each of the 3 parties in each of the 3 rounds
+        assert_eq!(transcript.len(), 18);
+        for (i, delivery) in transcript.iter().enumerate() {
+            assert_eq!(usize::from(delivery.round), i / 6 + 1);
+            assert_ne!(delivery.receiver, delivery.msg.sender);
+            assert!(!delivery.dropped);
+        }
+        let apk = parties[0].aggregated_public_key().clone();
+        for party in &mut parties {
+            let signature = party.pick_output().unwrap();
+            assert!(verify_dalek(&apk, &signature, message));
+        }
+    }
+
+    #[test]
+    fn test_run_with_dropped_message() {
+        let mut parties = parties(3, b"simulate");
+        // party 3 never gets the reveal of party 2, so it can't sign and nobody finishes
+        let result = simulate::run_with_faults(&mut parties, |receiver, msg| {
+            match (&msg.body, msg.sender, receiver) {
+                (AggSigMessage::Reveal(_), 2, 3) => Fault::Drop,
+                _ => Fault::Deliver,
+            }
+        });
+        assert_eq!(
+            result,
+            Err(SimulationError::Stalled {
+                unfinished: vec![1, 2, 3]
+            })
+        );
+        assert_eq!(parties[2].current_round(), 2);
+        assert_eq!(parties[0].current_round(), 3);
+    }
+
+    #[test]
+    fn test_run_with_tampered_message() {
+        let mut parties = parties(3, b"simulate");
+        let result = simulate::run_with_faults(&mut parties, |receiver, msg| {
+            if let (AggSigMessage::Reveal(reveal), 1, 2) = (&mut msg.body, msg.sender, receiver) {
+                reveal.blind_factor = &reveal.blind_factor + BigInt::from(1);
+            }
+            Fault::Deliver
+        });
+        assert_eq!(
+            result,
+            Err(SimulationError::Party {
+                party: 2,
+                error: InvalidCommitment { party: 1 }
+            })
+        );
+    }
+
+    #[test]
+    fn test_run_party_mismatch() {
+        let mut parties = parties(3, b"simulate");
+        parties.swap(0, 2);
+        assert_eq!(
+            simulate::run(&mut parties),
+            Err(SimulationError::PartyMismatch)
+        );
+        assert_eq!(
+            simulate::run(&mut parties[..2]),
+            Err(SimulationError::PartyMismatch)
+        );
+    }
+}
@@ -0,0 +1,146 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Aggsig producing Substrate sr25519 signatures
+//!
+//! The `schnorr` rounds over Ristretto with the challenge and nonces of
+//! [schnorrkel](https://github.com/w3f/schnorrkel), in the `substrate` signing context that
+//! Substrate signs and verifies everything in. The transcript of a signature on `message` is
+//! ```text
+//! SigningContext: "" <- "substrate", sign-bytes <- message,
+//! proto-name <- "Schnorr-sig", sign:pk <- A, sign:R <- R, challenge sign:c (64 bytes)
+//! ```
+//! and every signer draws its nonce from the transcript up to `sign:pk` (with its own key) rekeyed
+//! with its secret key, as schnorrkel's `witness_scalar`. Signatures are encoded as `R || s` with
+//! the top bit of `s` set, schnorrkel's marker, so an aggregated `Signature` verifies with
+//! `sp_core::sr25519::Pair::verify` under the aggregated public key.
+//!
+//! The keys are made by `KeyPair::from_seed` like the other schemes, not schnorrkel's mini secret
+//! key expansion, only the aggregated key has to be registered on chain.
+
+#[cfg(test)]
+mod test;
+
+use curv::arithmetic::Converter;
+use curv::elliptic::curves::{Point, Ristretto, Scalar};
+use curv::BigInt;
+use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+use protocols::schnorr::{self, scalar_from_bytes_le, scalar_to_bytes_le, Scheme};
+
+pub use protocols::schnorr::{
+    add_signature_parts, create_ephemeral_key_and_commit_rng, get_R_tot, partial_sign,
+    sign_single_rng, verify_commitment, verify_partial_sig, SignFirstMsg, SIGNATURE_SIZE,
+};
+#[cfg(feature = "std")]
+pub use protocols::schnorr::{create_ephemeral_key_and_commit, sign_single};
+
+pub const SIGNING_CONTEXT: &[u8] = b"substrate";
+
+// set in the last byte of every schnorrkel signature
+const SCHNORRKEL_MARKER: u8 = 0x80;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Sr25519;
+
+impl Scheme for Sr25519 {
+    type Curve = Ristretto;
+
+    const NAME: &'static [u8] = b"sr25519";
+
+    fn challenge(
+        R: &Point<Ristretto>,
+        public_key: &Point<Ristretto>,
+        message: &[u8],
+    ) -> Scalar<Ristretto> {
+        let mut transcript = signing_transcript(public_key, message);
+        transcript.append_message(b"sign:R", &R.to_bytes(true));
+        let mut k = [0u8; 64];
+        transcript.challenge_bytes(b"sign:c", &mut k);
+        wide_scalar(k)
+    }
+
+    fn nonce(
+        secret_key: &Scalar<Ristretto>,
+        public_key: &Point<Ristretto>,
+        message: &[u8],
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> Scalar<Ristretto> {
+        let mut transcript_rng = signing_transcript(public_key, message)
+            .build_rng()
+            .rekey_with_witness_bytes(b"signing", &secret_key.to_bytes())
+            .finalize(rng);
+        let mut r = [0u8; 64];
+        transcript_rng.fill_bytes(&mut r);
+        wide_scalar(r)
+    }
+
+    fn encode_point(point: &Point<Ristretto>) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&point.to_bytes(true));
+        bytes
+    }
+
+    fn decode_point(bytes: &[u8; 32]) -> Option<Point<Ristretto>> {
+        Point::from_bytes(bytes).ok()
+    }
+
+    /// Little endian with the marker bit set, `s` is reduced so the bit is otherwise clear.
+    fn encode_scalar(scalar: &Scalar<Ristretto>) -> [u8; 32] {
+        let mut bytes = scalar_to_bytes_le(scalar);
+        bytes[31] |= SCHNORRKEL_MARKER;
+        bytes
+    }
+
+    /// `None` without the marker bit, the ed25519 signatures schnorrkel rejects.
+    fn decode_scalar(bytes: &[u8; 32]) -> Option<Scalar<Ristretto>> {
+        if bytes[31] & SCHNORRKEL_MARKER == 0 {
+            return None;
+        }
+        let mut bytes = *bytes;
+        bytes[31] &= !SCHNORRKEL_MARKER;
+        scalar_from_bytes_le(&bytes)
+    }
+}
+
+pub type KeyPair = schnorr::KeyPair<Sr25519>;
+pub type KeyAgg = schnorr::KeyAgg<Sr25519>;
+pub type EphemeralKey = schnorr::EphemeralKey<Sr25519>;
+pub type SignSecondMsg = schnorr::SignSecondMsg<Sr25519>;
+pub type PartialSignature = schnorr::PartialSignature<Sr25519>;
+pub type Signature = schnorr::Signature<Sr25519>;
+
+// schnorrkel's `signing_context(SIGNING_CONTEXT).bytes(message)` after the protocol name and key
+fn signing_transcript(public_key: &Point<Ristretto>, message: &[u8]) -> Transcript {
+    let mut transcript = Transcript::new(b"SigningContext");
+    transcript.append_message(b"", SIGNING_CONTEXT);
+    transcript.append_message(b"sign-bytes", message);
+    transcript.append_message(b"proto-name", b"Schnorr-sig");
+    transcript.append_message(b"sign:pk", &public_key.to_bytes(true));
+    transcript
+}
+
+// `Scalar::from_bytes_mod_order_wide` of 64 little endian bytes
+fn wide_scalar(mut bytes: [u8; 64]) -> Scalar<Ristretto> {
+    // reverse because BigInt uses BigEndian.
+    bytes.reverse();
+    let scalar = Scalar::from_bigint(&BigInt::from_bytes(&bytes));
+    bytes.zeroize();
+    scalar
+}
@@ -0,0 +1,108 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use itertools::MultiUnzip;
+    use rand::Rng;
+
+    use protocols::schnorr::Scheme;
+    use protocols::sr25519::{self, KeyAgg, KeyPair, Signature, Sr25519};
+    use protocols::tests::deterministic_fast_rand;
+    use VerifyError;
+
+    #[test]
+    fn test_multiparty_signing() {
+        let mut rng = deterministic_fast_rand("test_sr25519_multiparty_signing", None);
+        let message = b"validator heartbeat";
+        let keys: Vec<_> = (0..3).map(|_| KeyPair::create_rng(&mut rng)).collect();
+        let pks: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        let key_aggs: Vec<_> = (0..3)
+            .map(|i| KeyAgg::key_aggregation_n(&pks, i).unwrap())
+            .collect();
+        let apk = key_aggs[0].apk.clone();
+
+        let (ephemeral_keys, first_msgs, second_msgs): (Vec<_>, Vec<_>, Vec<_>) = keys
+            .iter()
+            .map(|k| sr25519::create_ephemeral_key_and_commit_rng(k, message, &mut rng))
+            .multiunzip();
+        for (first_msg, second_msg) in first_msgs.iter().zip(&second_msgs) {
+            assert!(sr25519::verify_commitment(first_msg, second_msg).is_ok());
+        }
+        let Rs: Vec<_> = second_msgs.iter().map(|msg| msg.R.clone()).collect();
+        let R_tot = sr25519::get_R_tot(&Rs).unwrap();
+        let partial_sigs: Vec<_> = ephemeral_keys
+            .into_iter()
+            .zip(&keys)
+            .zip(&key_aggs)
+            .map(|((eph, k), key_agg)| {
+                sr25519::partial_sign(eph, k, &key_agg.hash, &R_tot, &apk, message)
+            })
+            .collect();
+        for (i, sig) in partial_sigs.iter().enumerate() {
+            assert!(sr25519::verify_partial_sig(
+                sig,
+                message,
+                &key_aggs[i].hash,
+                &Rs[i],
+                &pks[i],
+                &apk
+            )
+            .is_ok());
+        }
+        let signature = sr25519::add_signature_parts(&partial_sigs).unwrap();
+        assert!(signature.verify(message, &apk).is_ok());
+
+        let bytes = signature.to_bytes();
+        assert_eq!(bytes[63] & 0x80, 0x80);
+        assert_eq!(Signature::from_bytes(&bytes).unwrap(), signature);
+        let apk_bytes = Sr25519::encode_point(&apk);
+        assert!(Signature::verify_bytes(&bytes, message, &apk_bytes).is_ok());
+        assert_eq!(
+            Signature::verify_bytes(&bytes, b"another message", &apk_bytes),
+            Err(VerifyError::InvalidSignature)
+        );
+        // an unmarked, ed25519 style, signature
+        let mut unmarked = bytes;
+        unmarked[63] &= 0x7f;
+        assert_eq!(
+            Signature::from_bytes(&unmarked),
+            Err(VerifyError::NonCanonicalS)
+        );
+    }
+
+    #[test]
+    fn test_challenge_and_nonce() {
+        let mut rng = deterministic_fast_rand("test_sr25519_challenge_and_nonce", None);
+        let message: [u8; 32] = rng.gen();
+        let keys = KeyPair::create_rng(&mut rng);
+        let signature = sr25519::sign_single_rng(&message, &keys, &mut rng);
+        assert!(signature.verify(&message, &keys.public_key).is_ok());
+        // the challenge binds the key, R and the message
+        let k = Sr25519::challenge(&signature.R, &keys.public_key, &message);
+        assert_ne!(
+            k,
+            Sr25519::challenge(&keys.public_key, &signature.R, &message)
+        );
+        assert_ne!(k, Sr25519::challenge(&signature.R, &keys.public_key, b""));
+        // fresh randomness gives fresh nonces for the same message
+        let (first, _, _) = sr25519::create_ephemeral_key_and_commit_rng(&keys, &message, &mut rng);
+        let (second, _, _) =
+            sr25519::create_ephemeral_key_and_commit_rng(&keys, &message, &mut rng);
+        assert_ne!(first.R, second.R);
+    }
+}
@@ -10,7 +10,7 @@
     version 3 of the License, or (at your option) any later version.
     @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-eddsa/blob/master/LICENSE>
 */
-use Error::{self, InvalidKey, InvalidSS};
+use Error::{self, InvalidKey, InvalidSS, RoundMismatch};
 
 use curv::arithmetic::traits::*;
 use curv::cryptographic_primitives::commitments::hash_commitment::HashCommitment;
@@ -20,8 +20,9 @@ use curv::cryptographic_primitives::secret_sharing::feldman_vss::{SecretShares,
 use curv::elliptic::curves::{Ed25519, Point, Scalar};
 use curv::BigInt;
 use protocols::{ExpandedKeyPair, Signature};
-use rand::{thread_rng, Rng};
+use rand::{thread_rng, CryptoRng, Rng, RngCore};
 use sha2::{digest::Digest, Sha512};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 const SECURITY: usize = 256;
 
@@ -64,10 +65,56 @@ pub struct LocalSig {
     k: Scalar<Ed25519>,
 }
 
+impl Zeroize for Keys {
+    fn zeroize(&mut self) {
+        self.keypair.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Keys {}
+
+impl Zeroize for SharedKeys {
+    fn zeroize(&mut self) {
+        self.x_i = Scalar::zero();
+        self.prefix = Scalar::zero();
+    }
+}
+
+impl ZeroizeOnDrop for SharedKeys {}
+
+impl Zeroize for EphemeralKey {
+    fn zeroize(&mut self) {
+        self.r_i = Scalar::zero();
+    }
+}
+
+impl ZeroizeOnDrop for EphemeralKey {}
+
+impl Zeroize for EphemeralSharedKeys {
+    fn zeroize(&mut self) {
+        self.r_i = Scalar::zero();
+    }
+}
+
+impl ZeroizeOnDrop for EphemeralSharedKeys {}
+
+impl Zeroize for LocalSig {
+    fn zeroize(&mut self) {
+        self.gamma_i = Scalar::zero();
+        self.k = Scalar::zero();
+    }
+}
+
+impl ZeroizeOnDrop for LocalSig {}
+
 impl Keys {
     pub fn phase1_create(party_index: u16) -> Keys {
+        Self::phase1_create_rng(party_index, &mut thread_rng())
+    }
+
+    pub fn phase1_create_rng(party_index: u16, rng: &mut (impl CryptoRng + RngCore)) -> Keys {
         Keys {
-            keypair: ExpandedKeyPair::create(),
+            keypair: ExpandedKeyPair::create_rng(rng),
             party_index,
         }
     }
@@ -83,7 +130,10 @@ impl Keys {
         self.phase1_broadcast_rng(&mut thread_rng())
     }
 
-    fn phase1_broadcast_rng(&self, rng: &mut impl Rng) -> (KeyGenBroadcastMessage1, BigInt) {
+    pub fn phase1_broadcast_rng(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> (KeyGenBroadcastMessage1, BigInt) {
         let blind_factor: [u8; SECURITY / 8] = rng.gen();
         let blind_factor = BigInt::from_bytes(&blind_factor);
         let com = HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
@@ -103,21 +153,12 @@ impl Keys {
         parties: &[u16],
     ) -> Result<(VerifiableSS<Ed25519>, SecretShares<Ed25519>), Error> {
         // test length:
-        assert_eq!(blind_vec.len(), usize::from(params.share_count));
-        assert_eq!(bc1_vec.len(), usize::from(params.share_count));
-        assert_eq!(y_vec.len(), usize::from(params.share_count));
+        let n = usize::from(params.share_count);
+        if blind_vec.len() != n || bc1_vec.len() != n || y_vec.len() != n {
+            return Err(RoundMismatch);
+        }
         // test decommitments
-        let correct_key_correct_decom_all = y_vec
-            .iter()
-            .zip(blind_vec.iter())
-            .zip(bc1_vec.iter())
-            .all(|((y, blind), comm)| {
-                HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
-                    &y.y_coord().unwrap(),
-                    blind,
-                ) == comm.com
-            });
-        if !correct_key_correct_decom_all {
+        if !identify_invalid_decommitments(y_vec, blind_vec, bc1_vec)?.is_empty() {
             return Err(InvalidKey);
         }
         Ok(VerifiableSS::share_at_indices(
@@ -136,23 +177,17 @@ impl Keys {
         vss_scheme_vec: &[VerifiableSS<Ed25519>],
         index: u16,
     ) -> Result<SharedKeys, Error> {
-        assert_eq!(y_vec.len(), usize::from(params.share_count));
-        assert_eq!(secret_shares_vec.len(), usize::from(params.share_count));
-        assert_eq!(vss_scheme_vec.len(), usize::from(params.share_count));
+        let n = usize::from(params.share_count);
+        if y_vec.len() != n || secret_shares_vec.len() != n || vss_scheme_vec.len() != n {
+            return Err(RoundMismatch);
+        }
 
-        let correct_ss_verify = vss_scheme_vec
-            .iter()
-            .zip(secret_shares_vec.iter())
-            .zip(y_vec.iter())
-            .all(|((vss_scheme, secret_share), y)| {
-                vss_scheme.validate_share(secret_share, index).is_ok()
-                    && &vss_scheme.commitments[0] == y
-            });
-        if !correct_ss_verify {
+        if !identify_invalid_shares(params, y_vec, secret_shares_vec, vss_scheme_vec, index)?
+            .is_empty()
+        {
             return Err(InvalidSS);
         }
-        let first_y = y_vec[0].clone();
-        let y = y_vec[1..].iter().fold(first_y, |acc, y| acc + y);
+        let y = y_vec.iter().fold(Point::zero(), |acc, y| acc + y);
         let x_i = secret_shares_vec
             .iter()
             .fold(Scalar::zero(), |acc, x| acc + x);
@@ -181,11 +216,11 @@ impl EphemeralKey {
         )
     }
 
-    fn ephermeral_key_create_from_deterministic_secret_rng(
+    pub fn ephermeral_key_create_from_deterministic_secret_rng(
         keys: &Keys,
         message: &[u8],
         index: u16,
-        rng: &mut impl Rng,
+        rng: &mut (impl CryptoRng + RngCore),
     ) -> EphemeralKey {
         // here we deviate from the spec, by introducing  non-deterministic element (random number)
         // to the nonce
@@ -207,7 +242,10 @@ impl EphemeralKey {
         self.phase1_broadcast_rng(&mut thread_rng())
     }
 
-    pub fn phase1_broadcast_rng(&self, rng: &mut impl Rng) -> (KeyGenBroadcastMessage1, BigInt) {
+    pub fn phase1_broadcast_rng(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> (KeyGenBroadcastMessage1, BigInt) {
         let blind_factor: [u8; SECURITY / 8] = rng.gen();
         let blind_factor = BigInt::from_bytes(&blind_factor);
         let com = HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
@@ -227,31 +265,14 @@ impl EphemeralKey {
         parties: &[u16],
     ) -> Result<(VerifiableSS<Ed25519>, SecretShares<Ed25519>), Error> {
         // test length:
-        assert!(
-            blind_vec.len() > usize::from(params.threshold)
-                && blind_vec.len() <= usize::from(params.share_count)
-        );
-        assert!(
-            bc1_vec.len() > usize::from(params.threshold)
-                && bc1_vec.len() <= usize::from(params.share_count)
-        );
-        assert!(
-            R_vec.len() > usize::from(params.threshold)
-                && R_vec.len() <= usize::from(params.share_count)
-        );
+        let valid_len = |len: usize| {
+            len > usize::from(params.threshold) && len <= usize::from(params.share_count)
+        };
+        if !valid_len(blind_vec.len()) || !valid_len(bc1_vec.len()) || !valid_len(R_vec.len()) {
+            return Err(RoundMismatch);
+        }
         // test decommitments
-        let correct_key_correct_decom_all = R_vec
-            .iter()
-            .zip(blind_vec.iter())
-            .zip(bc1_vec.iter())
-            .all(|((R, blind), comm)| {
-                HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(
-                    &R.y_coord().unwrap(),
-                    blind,
-                ) == comm.com
-            });
-
-        if !correct_key_correct_decom_all {
+        if !identify_invalid_decommitments(R_vec, blind_vec, bc1_vec)?.is_empty() {
             return Err(InvalidKey);
         }
 
@@ -271,33 +292,23 @@ impl EphemeralKey {
         vss_scheme_vec: &[VerifiableSS<Ed25519>],
         index: u16,
     ) -> Result<EphemeralSharedKeys, Error> {
-        assert!(
-            R_vec.len() > usize::from(params.threshold)
-                && R_vec.len() <= usize::from(params.share_count)
-        );
-        assert!(
-            secret_shares_vec.len() > usize::from(params.threshold)
-                && secret_shares_vec.len() <= usize::from(params.share_count)
-        );
-        assert!(
-            vss_scheme_vec.len() > usize::from(params.threshold)
-                && vss_scheme_vec.len() <= usize::from(params.share_count)
-        );
+        let valid_len = |len: usize| {
+            len > usize::from(params.threshold) && len <= usize::from(params.share_count)
+        };
+        if !valid_len(R_vec.len())
+            || !valid_len(secret_shares_vec.len())
+            || !valid_len(vss_scheme_vec.len())
+        {
+            return Err(RoundMismatch);
+        }
 
-        let correct_ss_verify = vss_scheme_vec
-            .iter()
-            .zip(secret_shares_vec.iter())
-            .zip(R_vec.iter())
-            .all(|((vss_scheme, secret_share), R)| {
-                vss_scheme.validate_share(secret_share, index).is_ok()
-                    && &vss_scheme.commitments[0] == R
-            });
-        if !correct_ss_verify {
+        if !identify_invalid_shares(params, R_vec, secret_shares_vec, vss_scheme_vec, index)?
+            .is_empty()
+        {
             return Err(InvalidSS);
         }
 
-        let R_first = R_vec[0].clone();
-        let R = R_vec[1..].iter().fold(R_first, |acc, x| acc + x);
+        let R = R_vec.iter().fold(Point::zero(), |acc, x| acc + x);
         let r_i = secret_shares_vec
             .iter()
             .fold(Scalar::zero(), |acc, x| acc + x);
@@ -321,23 +332,78 @@ impl LocalSig {
     }
 
     // section 4.2 step 3
-    #[allow(unused_doc_comments)]
     pub fn verify_local_sigs(
         gamma_vec: &[LocalSig],
         parties_index_vec: &[u16],
         vss_private_keys: &[VerifiableSS<Ed25519>],
         vss_ephemeral_keys: &[VerifiableSS<Ed25519>],
+    ) -> Result<VerifiableSS<Ed25519>, Error> {
+        let vss_sum = Self::sum_vss(
+            gamma_vec,
+            parties_index_vec,
+            vss_private_keys,
+            vss_ephemeral_keys,
+        )?;
+        if Self::invalid_local_sigs(&vss_sum, gamma_vec, parties_index_vec).is_empty() {
+            Ok(vss_sum)
+        } else {
+            Err(InvalidSS)
+        }
+    }
+
+    /// Returns the positions in `gamma_vec` of the local signatures that don't match the
+    /// signer's public verification share, `verify_local_sigs` fails with `InvalidSS` if there
+    /// are any. Those signers have to be removed before signing again.
+    pub fn identify_invalid_local_sigs(
+        gamma_vec: &[LocalSig],
+        parties_index_vec: &[u16],
+        vss_private_keys: &[VerifiableSS<Ed25519>],
+        vss_ephemeral_keys: &[VerifiableSS<Ed25519>],
+    ) -> Result<Vec<usize>, Error> {
+        let vss_sum = Self::sum_vss(
+            gamma_vec,
+            parties_index_vec,
+            vss_private_keys,
+            vss_ephemeral_keys,
+        )?;
+        Ok(Self::invalid_local_sigs(
+            &vss_sum,
+            gamma_vec,
+            parties_index_vec,
+        ))
+    }
+
+    #[allow(unused_doc_comments)]
+    fn sum_vss(
+        gamma_vec: &[LocalSig],
+        parties_index_vec: &[u16],
+        vss_private_keys: &[VerifiableSS<Ed25519>],
+        vss_ephemeral_keys: &[VerifiableSS<Ed25519>],
     ) -> Result<VerifiableSS<Ed25519>, Error> {
         //parties_index_vec is a vector with indices of the parties that are participating and provided gamma_i for this step
         // test that enough parties are in this round
-        assert!(parties_index_vec.len() > usize::from(vss_private_keys[0].parameters.threshold));
+        let (first_private, first_ephemeral) =
+            match (vss_private_keys.first(), vss_ephemeral_keys.first()) {
+                (Some(first_private), Some(first_ephemeral)) => (first_private, first_ephemeral),
+                _ => return Err(RoundMismatch),
+            };
+        let threshold = usize::from(first_private.parameters.threshold);
+        if parties_index_vec.len() <= threshold
+            || gamma_vec.len() != parties_index_vec.len()
+            || vss_private_keys
+                .iter()
+                .chain(vss_ephemeral_keys)
+                .any(|vss| vss.commitments.len() != threshold + 1)
+        {
+            return Err(RoundMismatch);
+        }
 
         // Vec of joint commitments:
         // n' = num of signers, n - num of parties in keygen
         // [com0_eph_0,... ,com0_eph_n', e*com0_kg_0, ..., e*com0_kg_n ;
         // ...  ;
         // comt_eph_0,... ,comt_eph_n', e*comt_kg_0, ..., e*comt_kg_n ]
-        let comm_vec: Vec<_> = (0..usize::from(vss_private_keys[0].parameters.threshold) + 1)
+        let comm_vec: Vec<_> = (0..threshold + 1)
             .map(|i| {
                 let mut key_gen_comm_i_vec: Vec<_> = (0..vss_private_keys.len())
                     .map(|j| &vss_private_keys[j].commitments[i] * &gamma_vec[i].k)
@@ -346,33 +412,36 @@ impl LocalSig {
                     .map(|j| vss_ephemeral_keys[j].commitments[i].clone())
                     .collect();
                 key_gen_comm_i_vec.append(&mut eph_comm_i_vec);
-                let first = key_gen_comm_i_vec[0].clone();
-                key_gen_comm_i_vec[1..].iter().fold(first, |acc, x| acc + x)
+                key_gen_comm_i_vec
+                    .iter()
+                    .fold(Point::zero(), |acc, x| acc + x)
             })
             .collect();
 
-        let vss_sum = VerifiableSS {
-            parameters: vss_ephemeral_keys[0].parameters.clone(),
+        Ok(VerifiableSS {
+            parameters: first_ephemeral.parameters.clone(),
             commitments: comm_vec,
-        };
+        })
+    }
 
+    fn invalid_local_sigs(
+        vss_sum: &VerifiableSS<Ed25519>,
+        gamma_vec: &[LocalSig],
+        parties_index_vec: &[u16],
+    ) -> Vec<usize> {
         let g = Point::generator();
-
-        let correct_ss_verify =
-            gamma_vec
-                .iter()
-                .zip(parties_index_vec.iter())
-                .all(|(gamma, &party_index)| {
-                    let gamma_i_g = &gamma.gamma_i * g;
-                    vss_sum
-                        .validate_share_public(&gamma_i_g, party_index + 1)
-                        .is_ok()
-                });
-
-        match correct_ss_verify {
-            true => Ok(vss_sum),
-            false => Err(InvalidSS),
-        }
+        gamma_vec
+            .iter()
+            .zip(parties_index_vec.iter())
+            .enumerate()
+            .filter(|(_, (gamma, &party_index))| {
+                let gamma_i_g = &gamma.gamma_i * g;
+                vss_sum
+                    .validate_share_public(&gamma_i_g, party_index + 1)
+                    .is_err()
+            })
+            .map(|(i, _)| i)
+            .collect()
     }
 }
 
@@ -381,14 +450,75 @@ pub fn generate(
     local_sig_vec: &[LocalSig],
     parties_index_vec: &[u16],
     R: Point<Ed25519>,
-) -> Signature {
+) -> Result<Signature, Error> {
     let reconstruct_limit = usize::from(vss_sum_local_sigs.parameters.threshold) + 1;
+    if local_sig_vec.len() < reconstruct_limit || parties_index_vec.len() < reconstruct_limit {
+        return Err(RoundMismatch);
+    }
     let gamma_vec: Vec<_> = local_sig_vec[..reconstruct_limit]
         .iter()
         .map(|sig| sig.gamma_i.clone())
         .collect();
     let s = vss_sum_local_sigs.reconstruct(&parties_index_vec[0..reconstruct_limit], &gamma_vec);
-    Signature { s, R }
+    Ok(Signature { s, R })
+}
+
+/// Returns the positions of the parties whose revealed point (`y_i` in key generation, `R_i`
+/// when signing) doesn't open their round 1 commitment, the messages of party `i` are
+/// `points[i]`, `blind_vec[i]` and `bc1_vec[i]`.
+/// The `phase1_verify_com_phase2_distribute` functions fail with `InvalidKey` if there are any.
+pub fn identify_invalid_decommitments(
+    points: &[Point<Ed25519>],
+    blind_vec: &[BigInt],
+    bc1_vec: &[KeyGenBroadcastMessage1],
+) -> Result<Vec<usize>, Error> {
+    if blind_vec.len() != points.len() || bc1_vec.len() != points.len() {
+        return Err(RoundMismatch);
+    }
+    Ok((0..points.len())
+        .filter(|&i| !verify_decommitment(&points[i], &blind_vec[i], &bc1_vec[i]))
+        .collect())
+}
+
+/// Returns the positions of the parties whose VSS isn't consistent: it doesn't have
+/// `threshold + 1` commitments, doesn't commit to their revealed point or doesn't match the
+/// secret share they sent to party `index`. The messages of party `i` are `points[i]`,
+/// `secret_shares_vec[i]` and `vss_scheme_vec[i]`.
+/// The `phase2_verify_vss_construct_keypair` functions fail with `InvalidSS` if there are any.
+pub fn identify_invalid_shares(
+    params: &Parameters,
+    points: &[Point<Ed25519>],
+    secret_shares_vec: &[Scalar<Ed25519>],
+    vss_scheme_vec: &[VerifiableSS<Ed25519>],
+    index: u16,
+) -> Result<Vec<usize>, Error> {
+    if secret_shares_vec.len() != points.len() || vss_scheme_vec.len() != points.len() {
+        return Err(RoundMismatch);
+    }
+    let threshold = usize::from(params.threshold);
+    Ok((0..points.len())
+        .filter(|&i| {
+            let vss_scheme = &vss_scheme_vec[i];
+            vss_scheme.commitments.len() != threshold + 1
+                || vss_scheme.parameters.threshold != params.threshold
+                || vss_scheme.commitments[0] != points[i]
+                || vss_scheme
+                    .validate_share(&secret_shares_vec[i], index)
+                    .is_err()
+        })
+        .collect())
+}
+
+// a zero point has no y coordinate in curv, so it can't match any commitment
+fn verify_decommitment(
+    point: &Point<Ed25519>,
+    blind_factor: &BigInt,
+    bc1: &KeyGenBroadcastMessage1,
+) -> bool {
+    point.y_coord().is_some_and(|y| {
+        HashCommitment::<Sha512>::create_commitment_with_user_defined_randomness(&y, blind_factor)
+            == bc1.com
+    })
 }
 
 mod test;
@@ -13,13 +13,15 @@
 #[cfg(test)]
 mod tests {
     use curv::cryptographic_primitives::secret_sharing::feldman_vss::VerifiableSS;
-    use curv::elliptic::curves::{Ed25519, Point};
+    use curv::elliptic::curves::{Ed25519, Point, Scalar};
+    use curv::BigInt;
     use itertools::{izip, Itertools};
     use protocols::tests::{deterministic_fast_rand, verify_dalek};
     use protocols::thresholdsig::{
         self, EphemeralKey, EphemeralSharedKeys, Keys, LocalSig, Parameters, SharedKeys,
     };
-    use rand::{Rng, RngCore};
+    use rand::{CryptoRng, RngCore};
+    use Error;
 
     #[test]
     fn test_sign_threshold_verify_dalek_n1() {
@@ -102,7 +104,8 @@ mod tests {
                         &partial_sigs,
                         &group_indexs,
                         agg_nonce,
-                    );
+                    )
+                    .unwrap();
                     assert!(verify_dalek(&agg_pubkey, &sig, msg));
                 }
             }
@@ -117,7 +120,7 @@ mod tests {
         }
     }
 
-    fn test_t2_n4_internal(rng: &mut impl Rng) {
+    fn test_t2_n4_internal(rng: &mut (impl CryptoRng + RngCore)) {
         // this test assumes that in keygen we have n=4 parties and in signing we have 4 parties as well.
         let t = 2u16;
         let n = 4u16;
@@ -146,7 +149,8 @@ mod tests {
         assert!(verify_local_sig.is_ok());
         let vss_sum_local_sigs = verify_local_sig.unwrap();
         let signature =
-            thresholdsig::generate(&vss_sum_local_sigs, &local_sig_vec, &parties_index_vec, R);
+            thresholdsig::generate(&vss_sum_local_sigs, &local_sig_vec, &parties_index_vec, R)
+                .unwrap();
         let verify_sig = signature.verify(&message, &Y);
         assert!(verify_sig.is_ok());
     }
@@ -160,7 +164,7 @@ mod tests {
     }
 
     #[allow(unused_doc_comments)]
-    fn test_t2_n5_sign_with_4_internal(rng: &mut impl Rng) {
+    fn test_t2_n5_sign_with_4_internal(rng: &mut (impl CryptoRng + RngCore)) {
         /// this test assumes that in keygen we have n=4 parties and in signing we have 4 parties, indices 0,1,3,4.
         let t = 2;
         let n = 5;
@@ -208,16 +212,121 @@ mod tests {
 
         /// each party / dealer can generate the signature
         let signature =
-            thresholdsig::generate(&vss_sum_local_sigs, &local_sig_vec, &parties_index_vec, R);
+            thresholdsig::generate(&vss_sum_local_sigs, &local_sig_vec, &parties_index_vec, R)
+                .unwrap();
         let verify_sig = signature.verify(&message, &Y);
         assert!(verify_sig.is_ok());
     }
 
+    #[test]
+    fn test_identify_faulty_parties() {
+        let mut rng = deterministic_fast_rand("test_identify_faulty_parties", None);
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let parties: Vec<u16> = (1..=3).collect();
+        let keypairs: Vec<_> = parties
+            .iter()
+            .map(|&i| Keys::phase1_create_rng(i, &mut rng))
+            .collect();
+        let (first_msgs, mut blinds): (Vec<_>, Vec<_>) = keypairs
+            .iter()
+            .map(|keypair| keypair.phase1_broadcast_rng(&mut rng))
+            .unzip();
+        let pubkeys: Vec<_> = keypairs
+            .iter()
+            .map(|k| k.keypair.public_key.clone())
+            .collect();
+
+        // party 1 opens its commitment with the wrong blind factor
+        let blind = blinds[1].clone();
+        blinds[1] = BigInt::from(1);
+        assert_eq!(
+            thresholdsig::identify_invalid_decommitments(&pubkeys, &blinds, &first_msgs),
+            Ok(vec![1])
+        );
+        assert!(keypairs[0]
+            .phase1_verify_com_phase2_distribute(&params, &blinds, &pubkeys, &first_msgs, &parties)
+            .is_err());
+        blinds[1] = blind;
+        assert_eq!(
+            thresholdsig::identify_invalid_decommitments(&pubkeys, &blinds[..2], &first_msgs),
+            Err(Error::RoundMismatch)
+        );
+
+        let (vss_schemes, secret_shares): (Vec<_>, Vec<_>) = keypairs
+            .iter()
+            .map(|keypair| {
+                keypair
+                    .phase1_verify_com_phase2_distribute(
+                        &params,
+                        &blinds,
+                        &pubkeys,
+                        &first_msgs,
+                        &parties,
+                    )
+                    .unwrap()
+            })
+            .unzip();
+        // party 2 sends party 0 the share meant for party 1
+        let mut shares: Vec<Scalar<Ed25519>> =
+            (0..3).map(|j| secret_shares[j][0].clone()).collect();
+        assert_eq!(
+            thresholdsig::identify_invalid_shares(&params, &pubkeys, &shares, &vss_schemes, 1),
+            Ok(vec![])
+        );
+        shares[2] = secret_shares[2][1].clone();
+        assert_eq!(
+            thresholdsig::identify_invalid_shares(&params, &pubkeys, &shares, &vss_schemes, 1),
+            Ok(vec![2])
+        );
+        assert!(keypairs[0]
+            .phase2_verify_vss_construct_keypair(&params, &pubkeys, &shares, &vss_schemes, 1)
+            .is_err());
+
+        // party 1 sends a wrong local signature
+        let (keypairs, shared_keys, _, vss_schemes) = keygen_t_n_parties(1, 3, &parties, &mut rng);
+        let message = b"identifiable abort";
+        let (eph_shared_keys, _, eph_vss_schemes) =
+            eph_keygen_t_n_parties(1, 3, &parties, &keypairs, message, &mut rng);
+        let mut local_sigs: Vec<_> = (0..3)
+            .map(|i| LocalSig::compute(message, &eph_shared_keys[i], &shared_keys[i]))
+            .collect();
+        let parties_index_vec: [u16; 3] = [0, 1, 2];
+        assert_eq!(
+            LocalSig::identify_invalid_local_sigs(
+                &local_sigs,
+                &parties_index_vec,
+                &vss_schemes,
+                &eph_vss_schemes,
+            ),
+            Ok(vec![])
+        );
+        local_sigs[1].gamma_i = &local_sigs[1].gamma_i + Scalar::from(1u16);
+        assert_eq!(
+            LocalSig::identify_invalid_local_sigs(
+                &local_sigs,
+                &parties_index_vec,
+                &vss_schemes,
+                &eph_vss_schemes,
+            ),
+            Ok(vec![1])
+        );
+        assert!(LocalSig::verify_local_sigs(
+            &local_sigs,
+            &parties_index_vec,
+            &vss_schemes,
+            &eph_vss_schemes,
+        )
+        .is_err());
+    }
+
     pub fn keygen_t_n_parties(
         t: u16,
         n: u16,
         parties: &[u16],
-        rng: &mut impl Rng,
+        rng: &mut (impl CryptoRng + RngCore),
     ) -> (
         Vec<Keys>,
         Vec<SharedKeys>,
@@ -229,7 +338,10 @@ mod tests {
             share_count: n,
         };
         assert_eq!(parties.len(), usize::from(n));
-        let keypairs: Vec<_> = parties.iter().copied().map(Keys::phase1_create).collect();
+        let keypairs: Vec<_> = parties
+            .iter()
+            .map(|&i| Keys::phase1_create_rng(i, rng))
+            .collect();
 
         let (first_msgs, first_msg_blinds): (Vec<_>, Vec<_>) = keypairs
             .iter()
@@ -292,7 +404,7 @@ mod tests {
         parties: &[u16],
         keypairs: &[Keys],
         message: &[u8],
-        rng: &mut impl Rng,
+        rng: &mut (impl CryptoRng + RngCore),
     ) -> (
         Vec<EphemeralSharedKeys>,
         Point<Ed25519>,
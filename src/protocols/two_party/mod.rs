@@ -0,0 +1,223 @@
+#![allow(non_snake_case)]
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Two-party signing between a client (e.g. a wallet) and a server, built on MuSig2.
+//!
+//! Key generation is a single exchange of `KeyGenMsg`, the server answers with its first
+//! `NonceMsg`. Since MuSig2 nonces can be created before the message is known, the server
+//! always hands out the nonces for the next signature in advance, which makes signing a single
+//! round trip: the client sends a `SignRequest` and gets back a `SignResponse` holding the
+//! server's partial signature and its next `NonceMsg`.
+//! The server must use every `ServerNonces` only once, `server_sign` consumes them and returns
+//! the next ones, which have to replace the old ones in its storage before the response is sent.
+
+use core::slice;
+
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+#[cfg(feature = "std")]
+use rand::thread_rng;
+use rand::{CryptoRng, RngCore};
+
+use protocols::musig2::{
+    self, PrivatePartialNonces, PublicKeyAgg, PublicPartialNonces, NUMBER_OF_NONCES,
+};
+use protocols::{ExpandedKeyPair, Signature};
+use Error::{self, InvalidKey, InvalidSig};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyGenMsg {
+    pub public_key: Point<Ed25519>,
+}
+
+/// The joint public key, kept by both sides.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JointKey {
+    pub agg_public_key: Point<Ed25519>,
+    pub peer_public_key: Point<Ed25519>,
+    musig_coefficient: Scalar<Ed25519>,
+}
+
+/// The server's nonces for the next signature, sent to the client ahead of signing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NonceMsg {
+    pub R: [Point<Ed25519>; NUMBER_OF_NONCES],
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServerNonces {
+    private: PrivatePartialNonces,
+    public: PublicPartialNonces,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignRequest {
+    pub R: [Point<Ed25519>; NUMBER_OF_NONCES],
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignResponse {
+    pub s: Scalar<Ed25519>,
+    pub next_nonces: NonceMsg,
+}
+
+/// The client's half of the signature, held until the `SignResponse` arrives.
+#[derive(Debug)]
+pub struct ClientSession {
+    partial_sig: musig2::PartialSignature,
+}
+
+impl KeyGenMsg {
+    pub fn new(keys: &ExpandedKeyPair) -> KeyGenMsg {
+        KeyGenMsg {
+            public_key: keys.public_key.clone(),
+        }
+    }
+}
+
+impl JointKey {
+    /// Fails with `InvalidKey` if the peer's key is the identity or the same as ours.
+    pub fn new(keys: &ExpandedKeyPair, peer_msg: &KeyGenMsg) -> Result<JointKey, Error> {
+        let peer_public_key = &peer_msg.public_key;
+        if peer_public_key.is_zero() || *peer_public_key == keys.public_key {
+            return Err(InvalidKey);
+        }
+        let key_agg = PublicKeyAgg::key_aggregation_n(
+            alloc::vec![keys.public_key.clone(), peer_public_key.clone()],
+            &keys.public_key,
+        )
+        .ok_or(InvalidKey)?;
+        Ok(JointKey {
+            agg_public_key: key_agg.agg_public_key,
+            peer_public_key: peer_public_key.clone(),
+            musig_coefficient: key_agg.musig_coefficient,
+        })
+    }
+
+    fn key_agg(&self) -> PublicKeyAgg {
+        PublicKeyAgg {
+            agg_public_key: self.agg_public_key.clone(),
+            musig_coefficient: self.musig_coefficient.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn server_nonces(keys: &ExpandedKeyPair) -> (ServerNonces, NonceMsg) {
+    server_nonces_rng(keys, &mut thread_rng())
+}
+
+/// Creates the nonces for the first signature, the ones after come with every `SignResponse`.
+pub fn server_nonces_rng(
+    keys: &ExpandedKeyPair,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> (ServerNonces, NonceMsg) {
+    let (private, public) = musig2::generate_partial_nonces_rng(keys, None, rng);
+    let nonce_msg = NonceMsg {
+        R: public.R.clone(),
+    };
+    (ServerNonces { private, public }, nonce_msg)
+}
+
+#[cfg(feature = "std")]
+pub fn client_sign(
+    keys: &ExpandedKeyPair,
+    joint_key: &JointKey,
+    server_nonces: &NonceMsg,
+    message: &[u8],
+) -> (ClientSession, SignRequest) {
+    client_sign_rng(keys, joint_key, server_nonces, message, &mut thread_rng())
+}
+
+/// The client's part of signing `message` with the server nonces from the last `SignResponse`
+/// (or key generation). The server only answers one request per `NonceMsg`.
+pub fn client_sign_rng(
+    keys: &ExpandedKeyPair,
+    joint_key: &JointKey,
+    server_nonces: &NonceMsg,
+    message: &[u8],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> (ClientSession, SignRequest) {
+    let (private, public) = musig2::generate_partial_nonces_rng(keys, Some(message), rng);
+    let request = SignRequest {
+        R: public.R.clone(),
+    };
+    let partial_sig = musig2::partial_sign(
+        slice::from_ref(&server_nonces.R),
+        private,
+        public,
+        &joint_key.key_agg(),
+        keys,
+        message,
+    );
+    (ClientSession { partial_sig }, request)
+}
+
+#[cfg(feature = "std")]
+pub fn server_sign(
+    keys: &ExpandedKeyPair,
+    joint_key: &JointKey,
+    nonces: ServerNonces,
+    request: &SignRequest,
+    message: &[u8],
+) -> (ServerNonces, SignResponse) {
+    server_sign_rng(keys, joint_key, nonces, request, message, &mut thread_rng())
+}
+
+/// The server's partial signature over `message`, along with the nonces for the next signature.
+/// The returned `ServerNonces` replace `nonces`.
+pub fn server_sign_rng(
+    keys: &ExpandedKeyPair,
+    joint_key: &JointKey,
+    nonces: ServerNonces,
+    request: &SignRequest,
+    message: &[u8],
+    rng: &mut (impl CryptoRng + RngCore),
+) -> (ServerNonces, SignResponse) {
+    let ServerNonces { private, public } = nonces;
+    let partial_sig = musig2::partial_sign(
+        slice::from_ref(&request.R),
+        private,
+        public,
+        &joint_key.key_agg(),
+        keys,
+        message,
+    );
+    let (next_nonces, next_nonce_msg) = server_nonces_rng(keys, rng);
+    let response = SignResponse {
+        s: partial_sig.my_partial_s,
+        next_nonces: next_nonce_msg,
+    };
+    (next_nonces, response)
+}
+
+/// Completes the signature, fails with `InvalidSig` if the server's partial signature is wrong.
+/// `response.next_nonces` are the server nonces for the next `client_sign`.
+pub fn client_finish(
+    session: ClientSession,
+    joint_key: &JointKey,
+    response: &SignResponse,
+    message: &[u8],
+) -> Result<Signature, Error> {
+    let signature =
+        musig2::aggregate_partial_signatures(&session.partial_sig, slice::from_ref(&response.s));
+    signature
+        .verify(message, &joint_key.agg_public_key)
+        .map_err(|_| InvalidSig)?;
+    Ok(signature)
+}
+
+mod test;
@@ -0,0 +1,101 @@
+/*
+    multi-party-ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of multi-party-ed25519 library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    multi-party-ed25519 is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::{Point, Scalar};
+    use rand::Rng;
+
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+    use protocols::two_party::{self, JointKey, KeyGenMsg};
+    use protocols::ExpandedKeyPair;
+    use Error;
+
+    #[test]
+    fn test_two_party_signing() {
+        let mut rng = deterministic_fast_rand("test_two_party_signing", None);
+        let client_keys = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let server_keys = ExpandedKeyPair::create_from_private_key(rng.gen());
+
+        // keygen handshake
+        let client_msg = KeyGenMsg::new(&client_keys);
+        let server_msg = KeyGenMsg::new(&server_keys);
+        let server_key = JointKey::new(&server_keys, &client_msg).unwrap();
+        let (mut server_nonces, mut nonce_msg) =
+            two_party::server_nonces_rng(&server_keys, &mut rng);
+        let client_key = JointKey::new(&client_keys, &server_msg).unwrap();
+        assert_eq!(client_key.agg_public_key, server_key.agg_public_key);
+
+        for message in [&b""[..], b"first", b"second"] {
+            let (session, request) = two_party::client_sign_rng(
+                &client_keys,
+                &client_key,
+                &nonce_msg,
+                message,
+                &mut rng,
+            );
+            let (next_nonces, response) = two_party::server_sign_rng(
+                &server_keys,
+                &server_key,
+                server_nonces,
+                &request,
+                message,
+                &mut rng,
+            );
+            server_nonces = next_nonces;
+            let signature =
+                two_party::client_finish(session, &client_key, &response, message).unwrap();
+            assert!(verify_dalek(
+                &client_key.agg_public_key,
+                &signature,
+                message
+            ));
+            assert_ne!(response.next_nonces, nonce_msg);
+            nonce_msg = response.next_nonces;
+        }
+
+        // a wrong server partial signature is caught by the client
+        let (session, request) =
+            two_party::client_sign_rng(&client_keys, &client_key, &nonce_msg, b"message", &mut rng);
+        let (_, mut response) = two_party::server_sign_rng(
+            &server_keys,
+            &server_key,
+            server_nonces,
+            &request,
+            b"message",
+            &mut rng,
+        );
+        response.s = &response.s + Scalar::from(1);
+        assert_eq!(
+            two_party::client_finish(session, &client_key, &response, b"message"),
+            Err(Error::InvalidSig)
+        );
+    }
+
+    #[test]
+    fn test_two_party_keygen_reject_bad_key() {
+        let mut rng = deterministic_fast_rand("test_two_party_keygen_reject_bad_key", None);
+        let keys = ExpandedKeyPair::create_from_private_key(rng.gen());
+        assert_eq!(
+            JointKey::new(&keys, &KeyGenMsg::new(&keys)),
+            Err(Error::InvalidKey)
+        );
+        let identity = KeyGenMsg {
+            public_key: Point::zero(),
+        };
+        assert_eq!(JointKey::new(&keys, &identity), Err(Error::InvalidKey));
+    }
+}
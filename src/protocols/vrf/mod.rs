@@ -0,0 +1,244 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! ECVRF-EDWARDS25519-SHA512-TAI of draft-irtf-cfrg-vrf (published as RFC 9381)
+//!
+//! `prove` makes the proof `pi` for the input `alpha` with the same `ExpandedKeyPair` that signs,
+//! `verify` checks it under the Ed25519 public key and returns the 64 byte output `beta`, which
+//! nobody can predict without the private key and which is unique for the key and `alpha`
+//! (e.g. for leader election with the validators' signing keys).
+//! The VRF hashes are domain separated from Ed25519 signatures, so one key can do both.
+//!
+//! `H = encode_to_curve(Y, alpha)` (try-and-increment), `Gamma = x * H`,
+//! `c = H(Y, H, Gamma, k * G, k * H)` truncated to 16 bytes and `s = k + c * x`,
+//! `pi = Gamma || c || s` and `beta = H(8 * Gamma)`.
+
+use curv::arithmetic::Converter;
+use curv::elliptic::curves::ed25519::{Ed25519Point, Ed25519Scalar};
+use curv::elliptic::curves::{ECPoint, ECScalar, Ed25519, Point, Scalar};
+use curv::BigInt;
+use sha2::{Digest, Sha512};
+use zeroize::Zeroize;
+
+use protocols::{decode_point, ExpandedKeyPair};
+use Error::{self, InvalidKey, InvalidPoint, InvalidSig};
+
+mod test;
+#[cfg(feature = "std")]
+pub mod threshold;
+
+pub const PROOF_SIZE: usize = 80;
+pub const OUTPUT_SIZE: usize = 64;
+
+const SUITE: u8 = 0x03;
+const ENCODE_TO_CURVE_FRONT: u8 = 0x01;
+const PROOF_TO_HASH_FRONT: u8 = 0x03;
+const CHALLENGE_FRONT: u8 = 0x02;
+const BACK: u8 = 0x00;
+const CHALLENGE_SIZE: usize = 16;
+const COFACTOR: u64 = 8;
+
+/// The proof `pi` of a VRF output.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Proof {
+    pub Gamma: Point<Ed25519>,
+    /// The challenge, less than `2^128`.
+    pub c: Scalar<Ed25519>,
+    pub s: Scalar<Ed25519>,
+}
+
+impl Proof {
+    /// The output `beta` of the proof, only meaningful once the proof was verified.
+    pub fn output(&self) -> [u8; OUTPUT_SIZE] {
+        proof_to_hash(&self.Gamma)
+    }
+
+    /// The encoding `Gamma || c || s`.
+    pub fn to_bytes(&self) -> [u8; PROOF_SIZE] {
+        let mut bytes = [0u8; PROOF_SIZE];
+        bytes[..32].copy_from_slice(&self.Gamma.to_bytes(true));
+        let mut c = self.c.to_bigint().to_bytes();
+        c.reverse();
+        bytes[32..32 + c.len()].copy_from_slice(&c);
+        bytes[48..].copy_from_slice(&self.s.to_bytes());
+        bytes
+    }
+
+    /// Fails with `InvalidPoint` if `Gamma` isn't the canonical encoding of a point of the prime
+    /// order group and with `InvalidSig` for any other encoding error, including `s >= L`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Proof, Error> {
+        if bytes.len() != PROOF_SIZE {
+            return Err(InvalidSig);
+        }
+        let Gamma = decode_point(&bytes[..32]).ok_or(InvalidPoint)?;
+        let c = from_le_bytes(&bytes[32..48]);
+        let s = from_le_bytes(&bytes[48..]);
+        if s >= *Scalar::<Ed25519>::group_order() {
+            return Err(InvalidSig);
+        }
+        Ok(Proof {
+            Gamma,
+            c: Scalar::from_bigint(&c),
+            s: Scalar::from_bigint(&s),
+        })
+    }
+}
+
+/// The proof for `alpha`, its output is `proof.output()`.
+/// Fails with `InvalidPoint` if `alpha` can't be hashed to the curve, which happens with a
+/// probability of about `2^-256`.
+pub fn prove(keys: &ExpandedKeyPair, alpha: &[u8]) -> Result<Proof, Error> {
+    let H = encode_to_curve(&keys.public_key, alpha)?;
+    let k = nonce(keys, &H);
+    Ok(prove_dleq(
+        &keys.expanded_private_key.private_key,
+        k,
+        &keys.public_key,
+        &H,
+    ))
+}
+
+/// Verifies `proof` for `alpha` under `public_key` and returns its output `beta`.
+/// Fails with `InvalidKey` for the identity as the public key and with `InvalidSig` if the
+/// proof doesn't verify.
+pub fn verify(
+    public_key: &Point<Ed25519>,
+    alpha: &[u8],
+    proof: &Proof,
+) -> Result<[u8; OUTPUT_SIZE], Error> {
+    if public_key.is_zero() {
+        return Err(InvalidKey);
+    }
+    let H = encode_to_curve(public_key, alpha).map_err(|_| InvalidSig)?;
+    if verify_dleq(public_key, &H, proof) {
+        Ok(proof.output())
+    } else {
+        Err(InvalidSig)
+    }
+}
+
+// `Gamma = x * H` and the proof that `log_G(Y) = log_H(Gamma)` with the nonce `k`
+fn prove_dleq(
+    x: &Scalar<Ed25519>,
+    k: Scalar<Ed25519>,
+    Y: &Point<Ed25519>,
+    H: &Point<Ed25519>,
+) -> Proof {
+    let Gamma = H * x;
+    let c = challenge(Y, H, &Gamma, &(Point::generator() * &k), &(H * &k));
+    let s = k + &c * x;
+    Proof { Gamma, c, s }
+}
+
+fn verify_dleq(Y: &Point<Ed25519>, H: &Point<Ed25519>, proof: &Proof) -> bool {
+    let U = Point::generator() * &proof.s - Y * &proof.c;
+    let V = H * &proof.s - &proof.Gamma * &proof.c;
+    challenge(Y, H, &proof.Gamma, &U, &V) == proof.c
+}
+
+fn proof_to_hash(Gamma: &Point<Ed25519>) -> [u8; OUTPUT_SIZE] {
+    let cofactor_Gamma = Gamma * Scalar::from(COFACTOR);
+    let mut beta = [0u8; OUTPUT_SIZE];
+    beta.copy_from_slice(
+        &Sha512::new()
+            .chain([SUITE, PROOF_TO_HASH_FRONT])
+            .chain(&*cofactor_Gamma.to_bytes(true))
+            .chain([BACK])
+            .finalize(),
+    );
+    beta
+}
+
+/// `proof` decoded with `Proof::from_bytes` and verified.
+pub fn verify_bytes(
+    public_key: &Point<Ed25519>,
+    alpha: &[u8],
+    proof: &[u8],
+) -> Result<[u8; OUTPUT_SIZE], Error> {
+    verify(public_key, alpha, &Proof::from_bytes(proof)?)
+}
+
+// try-and-increment: the first of `H(suite || 1 || Y || alpha || ctr || 0)` for ctr = 0, 1, ...
+// that is the encoding of a point whose multiple by the cofactor isn't the identity
+fn encode_to_curve(public_key: &Point<Ed25519>, alpha: &[u8]) -> Result<Point<Ed25519>, Error> {
+    let prefix = Sha512::new()
+        .chain([SUITE, ENCODE_TO_CURVE_FRONT])
+        .chain(&*public_key.to_bytes(true))
+        .chain(alpha);
+    let cofactor = Ed25519Scalar::from_bigint(&BigInt::from(COFACTOR));
+    for ctr in 0..=255u8 {
+        let hash = prefix.clone().chain([ctr, BACK]).finalize();
+        // any point of the curve, the multiplication by the cofactor maps it to the prime order group
+        let point = match Ed25519Point::deserialize(&hash[..32]) {
+            Ok(point) => point.scalar_mul(&cofactor),
+            Err(_) => continue,
+        };
+        if let Ok(H) = Point::from_raw(point) {
+            if !H.is_zero() {
+                return Ok(H);
+            }
+        }
+    }
+    Err(InvalidPoint)
+}
+
+// as the RFC8032 nonce: `H(prefix || H)` with the second half of the hashed seed as the prefix
+fn nonce(keys: &ExpandedKeyPair, H: &Point<Ed25519>) -> Scalar<Ed25519> {
+    let mut prefix = [0u8; 32];
+    match keys.to_seed_bytes() {
+        Some(mut seed) => {
+            let mut h = Sha512::new().chain(seed).finalize();
+            prefix.copy_from_slice(&h[32..]);
+            h.as_mut_slice().zeroize();
+            seed.zeroize();
+        }
+        // a valid proof as well, but not the one of the RFC, the output is the same
+        None => prefix.copy_from_slice(&keys.expanded_private_key.prefix.to_bytes()),
+    }
+    let mut k = Sha512::new()
+        .chain(prefix)
+        .chain(&*H.to_bytes(true))
+        .finalize();
+    prefix.zeroize();
+    let scalar = Scalar::from_bigint(&from_le_bytes(&k));
+    k.as_mut_slice().zeroize();
+    scalar
+}
+
+fn challenge(
+    public_key: &Point<Ed25519>,
+    H: &Point<Ed25519>,
+    Gamma: &Point<Ed25519>,
+    U: &Point<Ed25519>,
+    V: &Point<Ed25519>,
+) -> Scalar<Ed25519> {
+    let c = [public_key, H, Gamma, U, V]
+        .iter()
+        .fold(
+            Sha512::new().chain([SUITE, CHALLENGE_FRONT]),
+            |hasher, point| hasher.chain(&*point.to_bytes(true)),
+        )
+        .chain([BACK])
+        .finalize();
+    Scalar::from_bigint(&from_le_bytes(&c[..CHALLENGE_SIZE]))
+}
+
+fn from_le_bytes(bytes: &[u8]) -> BigInt {
+    let mut bytes = bytes.to_vec();
+    bytes.reverse();
+    BigInt::from_bytes(&bytes)
+}
@@ -0,0 +1,199 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use hex::decode;
+    #[cfg(feature = "std")]
+    use rand::Rng;
+
+    use protocols::aggsig;
+    #[cfg(feature = "std")]
+    use protocols::keygen::{self, test::tests::keygen_t_n_parties, Parameters};
+    #[cfg(feature = "std")]
+    use protocols::tests::deterministic_fast_rand;
+    use protocols::tests::verify_dalek;
+    #[cfg(feature = "std")]
+    use protocols::vrf::threshold;
+    use protocols::vrf::{self, Proof};
+    use protocols::ExpandedKeyPair;
+    use Error;
+
+    fn keys(secret: &str) -> ExpandedKeyPair {
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&decode(secret).unwrap());
+        ExpandedKeyPair::create_from_private_key(seed)
+    }
+
+    #[test]
+    fn test_vrf_rfc9381_vectors() {
+        // examples 16 to 18 of RFC 9381, the RFC8032 test keys 1 to 3
+        let vectors = [
+            (
+                "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+                "",
+                "8657106690b5526245a92b003bb079ccd1a92130477671f6fc01ad16f26f723f26f8a57ccaed74ee1b190bed1f479d9727d2d0f9b005a6e456a35d4fb0daab1268a1b0db10836d9826a528ca76567805",
+                "90cf1df3b703cce59e2a35b925d411164068269d7b2d29f3301c03dd757876ff66b71dda49d2de59d03450451af026798e8f81cd2e333de5cdf4f3e140fdd8ae",
+            ),
+            (
+                "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+                "72",
+                "f3141cd382dc42909d19ec5110469e4feae18300e94f304590abdced48aed5933bf0864a62558b3ed7f2fea45c92a465301b3bbf5e3e54ddf2d935be3b67926da3ef39226bbc355bdc9850112c8f4b02",
+                "eb4440665d3891d668e7e0fcaf587f1b4bd7fbfe99d0eb2211ccec90496310eb5e33821bc613efb94db5e5b54c70a848a0bef4553a41befc57663b56373a5031",
+            ),
+            (
+                "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+                "af82",
+                "9bc0f79119cc5604bf02d23b4caede71393cedfbb191434dd016d30177ccbf8096bb474e53895c362d8628ee9f9ea3c0e52c7a5c691b6c18c9979866568add7a2d41b00b05081ed0f58ee5e31b3a970e",
+                "645427e5d00c62a23fb703732fa5d892940935942101e456ecca7bb217c61c452118fec1219202a0edcf038bb6373241578be7217ba85a2687f7a0310b2df19f",
+            ),
+        ];
+        for (secret, alpha, pi, beta) in vectors.iter() {
+            let keys = keys(secret);
+            let alpha = decode(alpha).unwrap();
+            let proof = vrf::prove(&keys, &alpha).unwrap();
+            assert_eq!(hex::encode(&proof.to_bytes()[..]), *pi);
+            assert_eq!(hex::encode(&proof.output()[..]), *beta);
+            let output = vrf::verify_bytes(&keys.public_key, &alpha, &decode(pi).unwrap()).unwrap();
+            assert_eq!(hex::encode(&output[..]), *beta);
+        }
+    }
+
+    #[test]
+    fn test_vrf_reject_invalid_proofs() {
+        let keys = keys("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60");
+        let other = ExpandedKeyPair::create();
+        let proof = vrf::prove(&keys, b"slot 1").unwrap();
+        assert!(vrf::verify(&keys.public_key, b"slot 1", &proof).is_ok());
+        assert_eq!(
+            vrf::verify(&keys.public_key, b"slot 2", &proof),
+            Err(Error::InvalidSig)
+        );
+        assert_eq!(
+            vrf::verify(&other.public_key, b"slot 1", &proof),
+            Err(Error::InvalidSig)
+        );
+        // another key's Gamma with the same challenge and response
+        let forged = Proof {
+            Gamma: vrf::prove(&other, b"slot 1").unwrap().Gamma,
+            ..proof.clone()
+        };
+        assert_eq!(
+            vrf::verify(&keys.public_key, b"slot 1", &forged),
+            Err(Error::InvalidSig)
+        );
+        let mut bytes = proof.to_bytes();
+        bytes[79] |= 0xf0;
+        assert_eq!(Proof::from_bytes(&bytes).err(), Some(Error::InvalidSig));
+        assert_eq!(
+            Proof::from_bytes(&bytes[..79]).err(),
+            Some(Error::InvalidSig)
+        );
+    }
+
+    #[test]
+    fn test_vrf_with_signing_key() {
+        let keys = ExpandedKeyPair::create();
+        let message = b"block 7";
+        let proof = vrf::prove(&keys, message).unwrap();
+        let output = vrf::verify(&keys.public_key, message, &proof).unwrap();
+        // unique for the key and input
+        assert_eq!(vrf::prove(&keys, message).unwrap().output(), output);
+        assert_ne!(vrf::prove(&keys, b"block 8").unwrap().output(), output);
+        let signature = aggsig::sign_single(message, &keys);
+        assert!(verify_dalek(&keys.public_key, &signature, message));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_threshold_vrf() {
+        let mut rng = deterministic_fast_rand("test_threshold_vrf", None);
+        let params = Parameters {
+            threshold: 2,
+            share_count: 5,
+        };
+        let shared_keys = keygen_t_n_parties(&params, &mut rng);
+        let vss = &shared_keys[0].vss;
+        let alpha = b"epoch 3";
+        let partials: Vec<_> = shared_keys
+            .iter()
+            .map(|keys| threshold::partial_evaluate(keys, alpha).unwrap())
+            .collect();
+        for partial in &partials {
+            threshold::verify_partial_evaluation(vss, alpha, partial).unwrap();
+        }
+        let proof = threshold::combine(vss, alpha, &partials[..3]).unwrap();
+        let output = threshold::verify(vss, alpha, &proof).unwrap();
+        // any other quorum gives the same output
+        let other = threshold::combine(vss, alpha, &partials[2..]).unwrap();
+        assert_eq!(other.output(), output);
+        assert_eq!(
+            threshold::combine(vss, b"epoch 4", &partials[..3]).err(),
+            Some(Error::InvalidProof { party: 1 })
+        );
+
+        assert_eq!(
+            threshold::combine(vss, alpha, &partials[..2]).err(),
+            Some(Error::RoundMismatch)
+        );
+        let duplicated = [
+            partials[0].clone(),
+            partials[1].clone(),
+            partials[1].clone(),
+        ];
+        assert_eq!(
+            threshold::combine(vss, alpha, &duplicated).err(),
+            Some(Error::RoundMismatch)
+        );
+        let mut forged = partials[..3].to_vec();
+        forged[1].proof.Gamma = forged[0].proof.Gamma.clone();
+        assert_eq!(
+            threshold::combine(vss, alpha, &forged).err(),
+            Some(Error::InvalidProof { party: 2 })
+        );
+        let mut tampered = proof.clone();
+        tampered.Gamma = partials[0].proof.Gamma.clone();
+        assert_eq!(
+            threshold::verify(vss, alpha, &tampered),
+            Err(Error::InvalidSig)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_threshold_vrf_matches_dealt_key() {
+        let mut rng = deterministic_fast_rand("test_threshold_vrf_matches_dealt_key", None);
+        let keypair = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let params = Parameters {
+            threshold: 1,
+            share_count: 3,
+        };
+        let shared_keys = keygen::deal_rng(&params, &keypair, &mut rng).unwrap();
+        let alpha = b"round 42";
+        let partials: Vec<_> = shared_keys[1..]
+            .iter()
+            .map(|keys| threshold::partial_evaluate(keys, alpha).unwrap())
+            .collect();
+        let proof = threshold::combine(&shared_keys[0].vss, alpha, &partials).unwrap();
+        let expected = vrf::prove(&keypair, alpha).unwrap();
+        assert_eq!(proof.Gamma, expected.Gamma);
+        assert_eq!(
+            threshold::verify(&shared_keys[0].vss, alpha, &proof).unwrap()[..],
+            vrf::verify(&keypair.public_key, alpha, &expected).unwrap()[..]
+        );
+    }
+}
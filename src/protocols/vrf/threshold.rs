@@ -0,0 +1,150 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Threshold VRF with the shares of `keygen`, e.g. for a randomness beacon run by the signing
+//! committee.
+//!
+//! Every party evaluates `Gamma_i = x_i * H` on `H = encode_to_curve(y, alpha)` and proves with a
+//! DLEQ proof against its verification share `x_i * G` that it used its share. Any
+//! `threshold + 1` valid evaluations interpolate to `Gamma = x * H`, so the output is the ECVRF
+//! output of the joint key `y` (the one `vrf::prove` would give the dealt key) whichever parties
+//! took part. The combined proof is the evaluations themselves, anyone with the public
+//! verification vector of the keygen can check it; it isn't an RFC 9381 proof.
+
+use curv::cryptographic_primitives::hashing::DigestExt;
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use sha2::{Digest, Sha512};
+
+use super::{encode_to_curve, proof_to_hash, prove_dleq, verify_dleq, Proof, OUTPUT_SIZE};
+use protocols::keygen::{lagrange_coefficient, SharedKeys, VerificationVector};
+use Error::{self, InvalidKey, InvalidProof, InvalidSig, RoundMismatch};
+
+/// `proof.Gamma` is `x_i * H`, `proof` proves it against the verification share of `party_index`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PartialEvaluation {
+    pub party_index: u16,
+    pub proof: Proof,
+}
+
+/// `Gamma = x * H` with the evaluations it was interpolated from.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CombinedProof {
+    pub Gamma: Point<Ed25519>,
+    pub partials: Vec<PartialEvaluation>,
+}
+
+impl CombinedProof {
+    /// The output `beta`, only meaningful once the proof was verified.
+    pub fn output(&self) -> [u8; OUTPUT_SIZE] {
+        proof_to_hash(&self.Gamma)
+    }
+}
+
+/// Our evaluation for `alpha`, to be broadcast. The nonce is derived from our prefix and `H`, so
+/// evaluating the same input twice gives the same proof.
+pub fn partial_evaluate(keys: &SharedKeys, alpha: &[u8]) -> Result<PartialEvaluation, Error> {
+    let H = encode_to_curve(&keys.y, alpha)?;
+    let k = Sha512::new()
+        .chain([14])
+        .chain_scalar(&keys.prefix)
+        .chain(keys.party_index.to_be_bytes())
+        .chain(&*H.to_bytes(true))
+        .result_scalar();
+    let Y_i = Point::generator() * &keys.x_i;
+    Ok(PartialEvaluation {
+        party_index: keys.party_index,
+        proof: prove_dleq(&keys.x_i, k, &Y_i, &H),
+    })
+}
+
+/// Fails with `InvalidProof { party }` if the evaluation of `party` doesn't verify against its
+/// verification share in `vss`.
+pub fn verify_partial_evaluation(
+    vss: &VerificationVector,
+    alpha: &[u8],
+    partial: &PartialEvaluation,
+) -> Result<(), Error> {
+    let y = vss.commitments.first().ok_or(InvalidKey)?;
+    let H = encode_to_curve(y, alpha)?;
+    verify_partial(vss, &H, partial)
+}
+
+/// Verifies every evaluation and interpolates them. Fails with `RoundMismatch` for fewer than
+/// `threshold + 1` evaluations or a party index that is zero or given twice.
+pub fn combine(
+    vss: &VerificationVector,
+    alpha: &[u8],
+    partials: &[PartialEvaluation],
+) -> Result<CombinedProof, Error> {
+    let Gamma = interpolate(vss, alpha, partials)?;
+    Ok(CombinedProof {
+        Gamma,
+        partials: partials.to_vec(),
+    })
+}
+
+/// Verifies `proof` for `alpha` under the keygen with the verification vector `vss` and returns
+/// its output `beta`; fails like `combine` and with `InvalidSig` if `Gamma` doesn't match.
+pub fn verify(
+    vss: &VerificationVector,
+    alpha: &[u8],
+    proof: &CombinedProof,
+) -> Result<[u8; OUTPUT_SIZE], Error> {
+    let Gamma = interpolate(vss, alpha, &proof.partials)?;
+    if Gamma != proof.Gamma {
+        return Err(InvalidSig);
+    }
+    Ok(proof.output())
+}
+
+fn interpolate(
+    vss: &VerificationVector,
+    alpha: &[u8],
+    partials: &[PartialEvaluation],
+) -> Result<Point<Ed25519>, Error> {
+    let y = vss.commitments.first().ok_or(InvalidKey)?;
+    let mut indices: Vec<_> = partials.iter().map(|partial| partial.party_index).collect();
+    indices.sort_unstable();
+    indices.dedup();
+    if indices.len() != partials.len()
+        || indices.len() < vss.commitments.len()
+        || indices.contains(&0)
+    {
+        return Err(RoundMismatch);
+    }
+    let H = encode_to_curve(y, alpha)?;
+    partials.iter().try_fold(Point::zero(), |Gamma, partial| {
+        verify_partial(vss, &H, partial)?;
+        let lambda: Scalar<Ed25519> = lagrange_coefficient(partial.party_index, &indices);
+        Ok(Gamma + &partial.proof.Gamma * lambda)
+    })
+}
+
+fn verify_partial(
+    vss: &VerificationVector,
+    H: &Point<Ed25519>,
+    partial: &PartialEvaluation,
+) -> Result<(), Error> {
+    let Y_i = vss.get_point_commitment(partial.party_index);
+    if partial.party_index != 0 && verify_dleq(&Y_i, H, &partial.proof) {
+        Ok(())
+    } else {
+        Err(InvalidProof {
+            party: partial.party_index,
+        })
+    }
+}
@@ -0,0 +1,158 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Versioned envelopes for the protocol messages
+//!
+//! An `Envelope` tags a message with `WIRE_VERSION` and with its type, e.g.
+//! `{"version":2,"message":{"type":"AggSigFirst","payload":{...}}}` in JSON.
+//! The version is checked before the payload is parsed, so a message from an incompatible crate
+//! version fails to deserialize instead of being read as a message of a different shape.
+//! `WIRE_VERSION` has to be bumped whenever the encoding of any of the messages changes.
+
+use core::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use protocols::aggsig::{SignFirstMsg, SignSecondMsg};
+#[cfg(feature = "std")]
+use protocols::frost::{self, SigningCommitment};
+#[cfg(feature = "std")]
+use protocols::keygen::{KeyGenBroadcastMessage1, KeyGenMessage2};
+use protocols::musig2::{self, PublicPartialNonces};
+#[cfg(feature = "std")]
+use protocols::refresh::{RefreshBroadcastMessage1, RefreshMessage2};
+use protocols::Signature;
+
+/// Version of the encoding of all messages in `Message`.
+/// 2: the aggsig round 1 commitment is to the compressed `R` instead of its y coordinate, see
+/// `DomainConfig::legacy_commitment`.
+pub const WIRE_VERSION: u16 = 2;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload")]
+pub enum Message {
+    AggSigFirst(SignFirstMsg),
+    AggSigSecond(SignSecondMsg),
+    AggSigPartialSignature(Signature),
+    Musig2Nonces(PublicPartialNonces),
+    Musig2PartialSignature(musig2::PartialSignature),
+    #[cfg(feature = "std")]
+    KeyGenBroadcast1(KeyGenBroadcastMessage1),
+    #[cfg(feature = "std")]
+    KeyGenMessage2(KeyGenMessage2),
+    #[cfg(feature = "std")]
+    RefreshBroadcast1(RefreshBroadcastMessage1),
+    #[cfg(feature = "std")]
+    RefreshMessage2(RefreshMessage2),
+    #[cfg(feature = "std")]
+    FrostCommitment(SigningCommitment),
+    #[cfg(feature = "std")]
+    FrostPartialSignature(frost::PartialSignature),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Envelope {
+    // always WIRE_VERSION, deserialization rejects anything else
+    version: u16,
+    pub message: Message,
+}
+
+impl Envelope {
+    pub fn new(message: Message) -> Envelope {
+        Envelope {
+            version: WIRE_VERSION,
+            message,
+        }
+    }
+
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn into_message(self) -> Message {
+        self.message
+    }
+}
+
+impl From<Message> for Envelope {
+    fn from(message: Message) -> Envelope {
+        Envelope::new(message)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum Field {
+    Version,
+    Message,
+}
+
+struct EnvelopeVisitor;
+
+fn check_version<E: de::Error>(version: u16) -> Result<(), E> {
+    if version == WIRE_VERSION {
+        Ok(())
+    } else {
+        Err(E::custom(format_args!(
+            "unsupported wire version {}, expected {}",
+            version, WIRE_VERSION
+        )))
+    }
+}
+
+impl<'de> Visitor<'de> for EnvelopeVisitor {
+    type Value = Envelope;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a versioned protocol message")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Envelope, A::Error> {
+        let version = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        check_version(version)?;
+        let message = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok(Envelope { version, message })
+    }
+
+    // the version has to be the first field, so the message is only parsed once it's known to be compatible
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Envelope, A::Error> {
+        match map.next_key()? {
+            Some(Field::Version) => {}
+            _ => return Err(de::Error::missing_field("version")),
+        }
+        let version = map.next_value()?;
+        check_version(version)?;
+        match map.next_key()? {
+            Some(Field::Message) => {}
+            _ => return Err(de::Error::missing_field("message")),
+        }
+        let message = map.next_value()?;
+        Ok(Envelope { version, message })
+    }
+}
+
+impl<'de> Deserialize<'de> for Envelope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Envelope, D::Error> {
+        deserializer.deserialize_struct("Envelope", &["version", "message"], EnvelopeVisitor)
+    }
+}
+
+mod test;
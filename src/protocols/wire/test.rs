@@ -0,0 +1,68 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+#[cfg(test)]
+mod tests {
+    use protocols::aggsig;
+    use protocols::tests::deterministic_fast_rand;
+    use protocols::wire::{Envelope, Message, WIRE_VERSION};
+    use protocols::ExpandedKeyPair;
+    use rand::Rng;
+    use serde_json;
+
+    #[test]
+    fn test_envelope_roundtrip() {
+        let mut rng = deterministic_fast_rand("test_envelope_roundtrip", None);
+        let keys = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let (_, sign_first_msg, sign_second_msg) =
+            aggsig::create_ephemeral_key_and_commit_rng(&keys, b"wire", &mut rng).unwrap();
+
+        for message in [
+            Message::AggSigFirst(sign_first_msg),
+            Message::AggSigSecond(sign_second_msg),
+        ] {
+            let envelope = Envelope::new(message);
+            let json = serde_json::to_string(&envelope).unwrap();
+            let decoded: Envelope = serde_json::from_str(&json).unwrap();
+            assert_eq!(decoded.version(), WIRE_VERSION);
+            assert_eq!(decoded, envelope);
+        }
+    }
+
+    #[test]
+    fn test_envelope_reject_other_version() {
+        let mut rng = deterministic_fast_rand("test_envelope_reject_other_version", None);
+        let keys = ExpandedKeyPair::create_from_private_key(rng.gen());
+        let (_, sign_first_msg, _) =
+            aggsig::create_ephemeral_key_and_commit_rng(&keys, b"wire", &mut rng).unwrap();
+        let json = serde_json::to_string(&Envelope::new(Message::AggSigFirst(sign_first_msg)))
+            .unwrap()
+            .replacen("\"version\":2", "\"version\":1", 1);
+        // a peer committing to the y coordinate of `R` is told apart by the version
+        let err = serde_json::from_str::<Envelope>(&json).unwrap_err();
+        assert!(err.to_string().contains("unsupported wire version 1"));
+
+        // a future message type is rejected by the version, not by the unknown payload
+        let json = r#"{"version":3,"message":{"type":"AggSigThird","payload":[]}}"#;
+        let err = serde_json::from_str::<Envelope>(json).unwrap_err();
+        assert!(err.to_string().contains("unsupported wire version 3"));
+
+        // unversioned messages are rejected
+        let json = r#"{"message":{"type":"AggSigThird","payload":[]}}"#;
+        assert!(serde_json::from_str::<Envelope>(json).is_err());
+    }
+}
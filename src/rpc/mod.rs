@@ -0,0 +1,810 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! A JSON-RPC 2.0 co-signer: key management and the aggsig rounds as methods, so an
+//! orchestrator in any language can drive a co-signer.
+//!
+//! `CoSigner::handle` answers a request (or a batch) given as JSON text and is transport
+//! independent, the `multi-party-eddsa-rpc` binary serves it over HTTP and WebSocket. The
+//! methods, with the params and result types of each:
+//!
+//! | method | params | result |
+//! |---|---|---|
+//! | `keygen` | none | `KeyInfo` |
+//! | `import_key` | `ImportKeyParams` | `KeyInfo` |
+//! | `list_keys` | none | `[KeyInfo]` |
+//! | `delete_key` | `KeyIdParams` | `true` |
+//! | `aggregate_keys` | `AggregateKeysParams` | `AggregateKeysResult` |
+//! | `sign_round1` | `SignRound1Params` | `SignRound1Result` |
+//! | `sign_round2` | `SignRound2Params` | `SignRound2Result` |
+//! | `sign_round3` | `SignRound3Params` | `SignRound3Result` |
+//! | `abort_session` | `SessionParams` | `true` |
+//! | `aggregate_signatures` | `AggregateSignaturesParams` | `SignatureResult` |
+//! | `verify` | `VerifyParams` | `VerifyResult` |
+//!
+//! Keys, commitments, reveals and signatures are in the serde encoding of the crate's types,
+//! messages are hex. The commitments and reveals of all signers are passed in the order of the
+//! public keys, ours included. A session ends with `sign_round3` or the first error in it, its
+//! nonce is never used twice. It's dropped if it isn't over within `SESSION_TIMEOUT` of
+//! `sign_round1`, which fails with `TOO_MANY_SESSIONS` while `MAX_SESSIONS` are open (see
+//! `CoSigner::with_session_limits`).
+//!
+//! Anyone who can send requests to `handle` can sign with the keys, a transport has to
+//! authenticate them first.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use curv::elliptic::curves::{Ed25519, Point};
+use rand::{thread_rng, Rng};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{self, Value};
+
+use protocols::aggsig::{self, EphemeralKey, KeyAgg, KeySet, SignFirstMsg, SignSecondMsg};
+use protocols::{ExpandedKeyPair, Signature};
+use Error::{self, InvalidCom, InvalidCommitment, InvalidKey, RoundMismatch};
+
+pub const JSONRPC_VERSION: &str = "2.0";
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// A protocol step failed, `data` is the `Error`.
+pub const PROTOCOL_ERROR: i64 = -32000;
+pub const UNKNOWN_KEY: i64 = -32001;
+pub const UNKNOWN_SESSION: i64 = -32002;
+pub const TOO_MANY_SESSIONS: i64 = -32003;
+
+pub const MAX_SESSIONS: usize = 1024;
+pub const SESSION_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    /// `None` for a notification, which gets no response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+    pub id: Value,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: &str) -> RpcError {
+        RpcError {
+            code,
+            message: String::from(message),
+            data: None,
+        }
+    }
+
+    fn protocol(error: Error) -> RpcError {
+        RpcError {
+            code: PROTOCOL_ERROR,
+            message: error.to_string(),
+            data: Some(Value::String(format!("{:?}", error))),
+        }
+    }
+}
+
+/// A key of the co-signer, `key_id` is the hex of the public key.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyInfo {
+    pub key_id: String,
+    pub public_key: Point<Ed25519>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImportKeyParams {
+    /// Hex of a 32 byte Ed25519 private key.
+    pub private_key: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyIdParams {
+    pub key_id: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AggregateKeysParams {
+    pub public_keys: Vec<Point<Ed25519>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AggregateKeysResult {
+    pub aggregated_public_key: Point<Ed25519>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignRound1Params {
+    pub key_id: String,
+    /// The public keys of all signers, ours included.
+    pub public_keys: Vec<Point<Ed25519>>,
+    /// Hex of the message.
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignRound1Result {
+    pub session_id: String,
+    pub aggregated_public_key: Point<Ed25519>,
+    pub commitment: SignFirstMsg,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignRound2Params {
+    pub session_id: String,
+    pub commitments: Vec<SignFirstMsg>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignRound2Result {
+    pub reveal: SignSecondMsg,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignRound3Params {
+    pub session_id: String,
+    pub reveals: Vec<SignSecondMsg>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignRound3Result {
+    pub partial_signature: Signature,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionParams {
+    pub session_id: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AggregateSignaturesParams {
+    pub partial_signatures: Vec<Signature>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignatureResult {
+    pub signature: Signature,
+    /// Hex of the 64 byte RFC 8032 encoding.
+    pub signature_bytes: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VerifyParams {
+    pub public_key: Point<Ed25519>,
+    /// Hex of the message.
+    pub message: String,
+    pub signature: Signature,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VerifyResult {
+    pub valid: bool,
+}
+
+struct Session {
+    started: Instant,
+    key_id: String,
+    key_agg: KeyAgg,
+    party_index: usize,
+    parties: usize,
+    message: Vec<u8>,
+    ephemeral_key: EphemeralKey,
+    commitment: SignFirstMsg,
+    reveal: SignSecondMsg,
+    // set by round 2
+    commitments: Option<Vec<SignFirstMsg>>,
+}
+
+/// The keys and open signing sessions of one co-signer.
+pub struct CoSigner {
+    keys: HashMap<String, ExpandedKeyPair>,
+    sessions: HashMap<String, Session>,
+    max_sessions: usize,
+    session_timeout: Duration,
+}
+
+impl Default for CoSigner {
+    fn default() -> CoSigner {
+        CoSigner::new()
+    }
+}
+
+impl CoSigner {
+    pub fn new() -> CoSigner {
+        CoSigner::with_session_limits(MAX_SESSIONS, SESSION_TIMEOUT)
+    }
+
+    /// A co-signer with at most `max_sessions` open sessions, each dropped `session_timeout`
+    /// after its `sign_round1`.
+    pub fn with_session_limits(max_sessions: usize, session_timeout: Duration) -> CoSigner {
+        CoSigner {
+            keys: HashMap::new(),
+            sessions: HashMap::new(),
+            max_sessions,
+            session_timeout,
+        }
+    }
+
+    /// Adds `keys` as if imported, returns its key id.
+    pub fn add_key(&mut self, keys: ExpandedKeyPair) -> String {
+        let key_id = hex::encode(&keys.public_key.to_bytes(true)[..]);
+        self.keys.insert(key_id.clone(), keys);
+        key_id
+    }
+
+    /// The response to a JSON-RPC request or batch, `None` if it only held notifications.
+    pub fn handle(&mut self, request: &str) -> Option<String> {
+        let response = match serde_json::from_str(request) {
+            Ok(Value::Array(batch)) if !batch.is_empty() => {
+                let responses: Vec<_> = batch
+                    .into_iter()
+                    .filter_map(|request| self.handle_value(request))
+                    .collect();
+                if responses.is_empty() {
+                    return None;
+                }
+                serde_json::to_value(responses)
+            }
+            Ok(Value::Array(_)) => serde_json::to_value(error_response(
+                Value::Null,
+                RpcError::new(INVALID_REQUEST, "empty batch"),
+            )),
+            Ok(request) => serde_json::to_value(self.handle_value(request)?),
+            Err(_) => serde_json::to_value(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, "parse error"),
+            )),
+        };
+        Some(response.expect("responses encode as JSON").to_string())
+    }
+
+    fn handle_value(&mut self, request: Value) -> Option<Response> {
+        let request: Request = match serde_json::from_value(request) {
+            Ok(request) => request,
+            Err(_) => {
+                return Some(error_response(
+                    Value::Null,
+                    RpcError::new(INVALID_REQUEST, "invalid request"),
+                ))
+            }
+        };
+        let result = if request.jsonrpc == JSONRPC_VERSION {
+            self.call(&request.method, request.params)
+        } else {
+            Err(RpcError::new(INVALID_REQUEST, "jsonrpc has to be \"2.0\""))
+        };
+        let id = request.id?;
+        Some(match result {
+            Ok(result) => Response {
+                jsonrpc: String::from(JSONRPC_VERSION),
+                result: Some(result),
+                error: None,
+                id,
+            },
+            Err(error) => error_response(id, error),
+        })
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let session_timeout = self.session_timeout;
+        self.sessions
+            .retain(|_, session| session.started.elapsed() < session_timeout);
+        match method {
+            "keygen" => {
+                let key_id = self.add_key(ExpandedKeyPair::create());
+                to_result(self.key_info(&key_id))
+            }
+            "import_key" => {
+                let params: ImportKeyParams = parse_params(params)?;
+                let mut private_key = [0u8; 32];
+                match hex::decode(&params.private_key) {
+                    Ok(ref bytes) if bytes.len() == 32 => private_key.copy_from_slice(bytes),
+                    _ => return Err(invalid_params("private_key has to be 32 bytes of hex")),
+                }
+                let key_id = self.add_key(ExpandedKeyPair::create_from_private_key(private_key));
+                to_result(self.key_info(&key_id))
+            }
+            "list_keys" => {
+                let mut key_ids: Vec<_> = self.keys.keys().cloned().collect();
+                key_ids.sort();
+                let keys: Vec<_> = key_ids.iter().map(|key_id| self.key_info(key_id)).collect();
+                to_result(keys)
+            }
+            "delete_key" => {
+                let params: KeyIdParams = parse_params(params)?;
+                self.keys.remove(&params.key_id).ok_or_else(unknown_key)?;
+                self.sessions
+                    .retain(|_, session| session.key_id != params.key_id);
+                Ok(Value::Bool(true))
+            }
+            "aggregate_keys" => {
+                let params: AggregateKeysParams = parse_params(params)?;
+                to_result(AggregateKeysResult {
+                    aggregated_public_key: aggregate_keys(&params.public_keys, 0)?.apk,
+                })
+            }
+            "sign_round1" => {
+                let params: SignRound1Params = parse_params(params)?;
+                to_result(self.sign_round1(params)?)
+            }
+            "sign_round2" => {
+                let SignRound2Params {
+                    session_id,
+                    commitments,
+                } = parse_params(params)?;
+                let reveal = self.in_session(&session_id, |session| {
+                    if commitments.len() != session.parties {
+                        return Err(RoundMismatch);
+                    }
+                    if session.commitments.is_some() {
+                        return Err(RoundMismatch);
+                    }
+                    if commitments[session.party_index] != session.commitment {
+                        return Err(InvalidCom);
+                    }
+                    session.commitments = Some(commitments);
+                    Ok(session.reveal.clone())
+                })?;
+                to_result(SignRound2Result { reveal })
+            }
+            "sign_round3" => {
+                let params: SignRound3Params = parse_params(params)?;
+                let session = self
+                    .sessions
+                    .remove(&params.session_id)
+                    .ok_or_else(unknown_session)?;
+                let partial_signature = self
+                    .sign_round3(session, &params.reveals)
+                    .map_err(RpcError::protocol)?;
+                to_result(SignRound3Result { partial_signature })
+            }
+            "abort_session" => {
+                let params: SessionParams = parse_params(params)?;
+                self.sessions
+                    .remove(&params.session_id)
+                    .ok_or_else(unknown_session)?;
+                Ok(Value::Bool(true))
+            }
+            "aggregate_signatures" => {
+                let params: AggregateSignaturesParams = parse_params(params)?;
+                let signature = aggsig::add_signature_parts(&params.partial_signatures)
+                    .map_err(RpcError::protocol)?;
+                to_result(SignatureResult {
+                    signature_bytes: hex::encode(&signature.to_bytes()[..]),
+                    signature,
+                })
+            }
+            "verify" => {
+                let params: VerifyParams = parse_params(params)?;
+                let message = decode_message(&params.message)?;
+                to_result(VerifyResult {
+                    valid: params
+                        .signature
+                        .verify(&message, &params.public_key)
+                        .is_ok(),
+                })
+            }
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, "method not found")),
+        }
+    }
+
+    fn key_info(&self, key_id: &str) -> KeyInfo {
+        KeyInfo {
+            key_id: String::from(key_id),
+            public_key: self.keys[key_id].public_key.clone(),
+        }
+    }
+
+    fn sign_round1(&mut self, params: SignRound1Params) -> Result<SignRound1Result, RpcError> {
+        let message = decode_message(&params.message)?;
+        let keys = self.keys.get(&params.key_id).ok_or_else(unknown_key)?;
+        let party_index = params
+            .public_keys
+            .iter()
+            .position(|public_key| *public_key == keys.public_key)
+            .ok_or_else(|| RpcError::protocol(InvalidKey))?;
+        let key_agg = aggregate_keys(&params.public_keys, party_index)?;
+        if self.sessions.len() >= self.max_sessions {
+            return Err(RpcError::new(TOO_MANY_SESSIONS, "too many open sessions"));
+        }
+        let (ephemeral_key, commitment, reveal) =
+            aggsig::create_ephemeral_key_and_commit(keys, &message).map_err(RpcError::protocol)?;
+        let session_id = hex::encode(thread_rng().gen::<[u8; 16]>());
+        let result = SignRound1Result {
+            session_id: session_id.clone(),
+            aggregated_public_key: key_agg.apk.clone(),
+            commitment: commitment.clone(),
+        };
+        self.sessions.insert(
+            session_id,
+            Session {
+                started: Instant::now(),
+                key_id: params.key_id,
+                key_agg,
+                party_index,
+                parties: params.public_keys.len(),
+                message,
+                ephemeral_key,
+                commitment,
+                reveal,
+                commitments: None,
+            },
+        );
+        Ok(result)
+    }
+
+    fn sign_round3(&self, session: Session, reveals: &[SignSecondMsg]) -> Result<Signature, Error> {
+        let commitments = session.commitments.ok_or(RoundMismatch)?;
+        if reveals.len() != commitments.len() {
+            return Err(RoundMismatch);
+        }
+        for (j, (commitment, reveal)) in commitments.iter().zip(reveals).enumerate() {
+            let party = u16::try_from(j + 1).map_err(|_| RoundMismatch)?;
+            aggsig::verify_commitment(commitment, reveal)
+                .map_err(|_| InvalidCommitment { party })?;
+        }
+        let Rs: Vec<_> = reveals.iter().map(|reveal| &reveal.R).collect();
+        let R_tot = aggsig::get_R_tot(Rs).ok_or(RoundMismatch)?;
+        let keys = self.keys.get(&session.key_id).ok_or(InvalidKey)?;
+        Ok(aggsig::partial_sign(
+            session.ephemeral_key,
+            keys,
+            &session.key_agg.hash,
+            &R_tot,
+            &session.key_agg.apk,
+            &session.message,
+        ))
+    }
+
+    // runs `f` on a session, which is aborted if `f` fails
+    fn in_session<T>(
+        &mut self,
+        session_id: &str,
+        f: impl FnOnce(&mut Session) -> Result<T, Error>,
+    ) -> Result<T, RpcError> {
+        let session = self
+            .sessions
+            .get_mut(session_id)
+            .ok_or_else(unknown_session)?;
+        f(session).map_err(|error| {
+            self.sessions.remove(session_id);
+            RpcError::protocol(error)
+        })
+    }
+}
+
+// the keys in the given order, checked by `KeySetBuilder::build`
+fn aggregate_keys(public_keys: &[Point<Ed25519>], party_index: usize) -> Result<KeyAgg, RpcError> {
+    let key_set = KeySet::builder()
+        .extend(public_keys.iter().cloned())
+        .build()
+        .map_err(RpcError::protocol)?;
+    Ok(key_set.aggregate().key_agg(party_index))
+}
+
+fn error_response(id: Value, error: RpcError) -> Response {
+    Response {
+        jsonrpc: String::from(JSONRPC_VERSION),
+        result: None,
+        error: Some(error),
+        id,
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| invalid_params(&e.to_string()))
+}
+
+fn to_result<T: Serialize>(result: T) -> Result<Value, RpcError> {
+    Ok(serde_json::to_value(result).expect("results encode as JSON"))
+}
+
+fn decode_message(message: &str) -> Result<Vec<u8>, RpcError> {
+    hex::decode(message).map_err(|_| invalid_params("message has to be hex"))
+}
+
+fn invalid_params(message: &str) -> RpcError {
+    RpcError::new(INVALID_PARAMS, message)
+}
+
+fn unknown_key() -> RpcError {
+    RpcError::new(UNKNOWN_KEY, "unknown key")
+}
+
+fn unknown_session() -> RpcError {
+    RpcError::new(UNKNOWN_SESSION, "unknown session")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(cosigner: &mut CoSigner, method: &str, params: Value) -> Result<Value, RpcError> {
+        let request = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 7});
+        let response: Response =
+            serde_json::from_str(&cosigner.handle(&request.to_string()).unwrap()).unwrap();
+        assert_eq!(response.id, json!(7));
+        match response {
+            Response {
+                result: Some(result),
+                error: None,
+                ..
+            } => Ok(result),
+            Response {
+                error: Some(error), ..
+            } => Err(error),
+            _ => panic!("a response without result or error"),
+        }
+    }
+
+    #[test]
+    fn test_signing_session() {
+        let mut cosigners: Vec<_> = (0..3).map(|_| CoSigner::new()).collect();
+        let keys: Vec<KeyInfo> = cosigners
+            .iter_mut()
+            .map(|cosigner| serde_json::from_value(call(cosigner, "keygen", Value::Null).unwrap()))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let public_keys: Vec<_> = keys.iter().map(|key| key.public_key.clone()).collect();
+        let aggregated: AggregateKeysResult = serde_json::from_value(
+            call(
+                &mut cosigners[0],
+                "aggregate_keys",
+                json!({ "public_keys": public_keys }),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let message = hex::encode(b"signed over JSON-RPC");
+
+        let round1: Vec<SignRound1Result> = cosigners
+            .iter_mut()
+            .zip(&keys)
+            .map(|(cosigner, key)| {
+                let params = json!({
+                    "key_id": key.key_id,
+                    "public_keys": public_keys,
+                    "message": message,
+                });
+                serde_json::from_value(call(cosigner, "sign_round1", params).unwrap()).unwrap()
+            })
+            .collect();
+        assert!(round1
+            .iter()
+            .all(|round1| round1.aggregated_public_key == aggregated.aggregated_public_key));
+        let commitments: Vec<_> = round1.iter().map(|r| r.commitment.clone()).collect();
+        let reveals: Vec<_> = cosigners
+            .iter_mut()
+            .zip(&round1)
+            .map(|(cosigner, round1)| {
+                let params = json!({"session_id": round1.session_id, "commitments": commitments});
+                let result: SignRound2Result =
+                    serde_json::from_value(call(cosigner, "sign_round2", params).unwrap()).unwrap();
+                result.reveal
+            })
+            .collect();
+        let partial_signatures: Vec<_> = cosigners
+            .iter_mut()
+            .zip(&round1)
+            .map(|(cosigner, round1)| {
+                let params = json!({"session_id": round1.session_id, "reveals": reveals});
+                let result: SignRound3Result =
+                    serde_json::from_value(call(cosigner, "sign_round3", params).unwrap()).unwrap();
+                result.partial_signature
+            })
+            .collect();
+        // the session is over, its nonce is gone
+        let params = json!({"session_id": round1[0].session_id, "reveals": reveals});
+        assert_eq!(
+            call(&mut cosigners[0], "sign_round3", params)
+                .unwrap_err()
+                .code,
+            UNKNOWN_SESSION
+        );
+
+        let signature: SignatureResult = serde_json::from_value(
+            call(
+                &mut cosigners[1],
+                "aggregate_signatures",
+                json!({ "partial_signatures": partial_signatures }),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            signature.signature_bytes,
+            hex::encode(&signature.signature.to_bytes()[..])
+        );
+        let params = json!({
+            "public_key": aggregated.aggregated_public_key,
+            "message": message,
+            "signature": signature.signature,
+        });
+        assert_eq!(
+            call(&mut cosigners[2], "verify", params).unwrap(),
+            json!({"valid": true})
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut cosigner = CoSigner::new();
+        let key_id = cosigner.add_key(ExpandedKeyPair::create());
+        let other = ExpandedKeyPair::create().public_key;
+        let public_key = cosigner.keys[&key_id].public_key.clone();
+
+        let error = |response: Option<String>| -> RpcError {
+            let response: Response = serde_json::from_str(&response.unwrap()).unwrap();
+            response.error.unwrap()
+        };
+        assert_eq!(error(cosigner.handle("{")).code, PARSE_ERROR);
+        assert_eq!(error(cosigner.handle("[]")).code, INVALID_REQUEST);
+        assert_eq!(
+            error(cosigner.handle(r#"{"jsonrpc":"1.0","method":"keygen","id":1}"#)).code,
+            INVALID_REQUEST
+        );
+        assert_eq!(
+            call(&mut cosigner, "sign", Value::Null).unwrap_err().code,
+            METHOD_NOT_FOUND
+        );
+        assert_eq!(
+            call(&mut cosigner, "import_key", json!({"private_key": "00"}))
+                .unwrap_err()
+                .code,
+            INVALID_PARAMS
+        );
+        // notifications get no response, also in a batch
+        assert_eq!(
+            cosigner.handle(r#"{"jsonrpc":"2.0","method":"list_keys"}"#),
+            None
+        );
+        let batch = cosigner
+            .handle(
+                r#"[{"jsonrpc":"2.0","method":"list_keys","id":1},
+                    {"jsonrpc":"2.0","method":"keygen"}]"#,
+            )
+            .unwrap();
+        let batch: Vec<Response> = serde_json::from_str(&batch).unwrap();
+        assert_eq!(batch.len(), 1);
+        assert_eq!(
+            call(&mut cosigner, "list_keys", Value::Null)
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let params = json!({"key_id": "00", "public_keys": [other], "message": ""});
+        assert_eq!(
+            call(&mut cosigner, "sign_round1", params).unwrap_err().code,
+            UNKNOWN_KEY
+        );
+        // a signer set without our key
+        let params = json!({"key_id": key_id, "public_keys": [other], "message": ""});
+        assert_eq!(
+            call(&mut cosigner, "sign_round1", params).unwrap_err(),
+            RpcError::protocol(InvalidKey)
+        );
+        // a key given twice
+        let params = json!({"public_keys": [other, other]});
+        assert_eq!(
+            call(&mut cosigner, "aggregate_keys", params).unwrap_err(),
+            RpcError::protocol(InvalidKey)
+        );
+        let params =
+            json!({"key_id": key_id, "public_keys": [public_key, public_key], "message": ""});
+        assert_eq!(
+            call(&mut cosigner, "sign_round1", params).unwrap_err(),
+            RpcError::protocol(InvalidKey)
+        );
+        assert_eq!(
+            call(
+                &mut cosigner,
+                "aggregate_keys",
+                json!({ "public_keys": [] })
+            )
+            .unwrap_err(),
+            RpcError::protocol(InvalidKey)
+        );
+        let params = json!({"key_id": key_id, "public_keys": [other, public_key], "message": ""});
+        let round1: SignRound1Result =
+            serde_json::from_value(call(&mut cosigner, "sign_round1", params).unwrap()).unwrap();
+        // a commitment too few aborts the session
+        let params = json!({"session_id": round1.session_id, "commitments": [round1.commitment]});
+        assert_eq!(
+            call(&mut cosigner, "sign_round2", params.clone()).unwrap_err(),
+            RpcError::protocol(RoundMismatch)
+        );
+        assert_eq!(
+            call(&mut cosigner, "sign_round2", params).unwrap_err().code,
+            UNKNOWN_SESSION
+        );
+        assert_eq!(
+            call(&mut cosigner, "delete_key", json!({ "key_id": key_id })).unwrap(),
+            json!(true)
+        );
+        assert_eq!(
+            call(&mut cosigner, "delete_key", json!({ "key_id": key_id }))
+                .unwrap_err()
+                .code,
+            UNKNOWN_KEY
+        );
+    }
+
+    #[test]
+    fn test_session_limits() {
+        let round1 = |cosigner: &mut CoSigner, key_id: &str, public_key: &Point<Ed25519>| {
+            let params = json!({"key_id": key_id, "public_keys": [public_key], "message": ""});
+            call(cosigner, "sign_round1", params)
+        };
+        let keys = ExpandedKeyPair::create();
+        let public_key = keys.public_key.clone();
+
+        let mut cosigner = CoSigner::with_session_limits(1, SESSION_TIMEOUT);
+        let key_id = cosigner.add_key(keys.clone());
+        let session: SignRound1Result =
+            serde_json::from_value(round1(&mut cosigner, &key_id, &public_key).unwrap()).unwrap();
+        assert_eq!(
+            round1(&mut cosigner, &key_id, &public_key)
+                .unwrap_err()
+                .code,
+            TOO_MANY_SESSIONS
+        );
+        let params = json!({ "session_id": session.session_id });
+        call(&mut cosigner, "abort_session", params).unwrap();
+        assert!(round1(&mut cosigner, &key_id, &public_key).is_ok());
+
+        // expired before round 2
+        let mut cosigner = CoSigner::with_session_limits(1, Duration::from_secs(0));
+        let key_id = cosigner.add_key(keys);
+        let session: SignRound1Result =
+            serde_json::from_value(round1(&mut cosigner, &key_id, &public_key).unwrap()).unwrap();
+        let params = json!({"session_id": session.session_id, "commitments": [session.commitment]});
+        assert_eq!(
+            call(&mut cosigner, "sign_round2", params).unwrap_err().code,
+            UNKNOWN_SESSION
+        );
+        assert!(round1(&mut cosigner, &key_id, &public_key).is_ok());
+    }
+}
@@ -0,0 +1,414 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Malicious aggsig parties, to test that a coordinator or transport detects them and aborts.
+//!
+//! A `MaliciousParty` runs `AggSigStateMachine` underneath and tampers with what it sends
+//! according to its `Behavior`, with `Behavior::Honest` for the other parties of the session, so
+//! all of them fit in one `protocols::simulate::run`. The cheating party stops once it has sent
+//! its partial signature, everyone else is expected to abort:
+//!
+//! * `WrongOpening` and `BiasedNonce` with `InvalidCommitment { party }`,
+//! * `RogueKey` with `InvalidPartialSignature { party }`, or with `InvalidProof { party }` at
+//!   `AggregatedKey::new_with_pop` for `rogue_proof_of_possession`,
+//! * `EquivocateR` with `RoundMismatch`, or with `EchoError::Inconsistent` under an
+//!   `EchoBroadcast` if the transport delivers its messages as broadcasts.
+
+use curv::elliptic::curves::{Ed25519, Point};
+use curv::BigInt;
+
+use protocols::aggsig::{
+    self, AggSigMessage, AggSigStateMachine, ProofOfPossession, SignFirstMsg, SignSecondMsg,
+};
+use protocols::rounds::{Msg, StateMachine};
+use protocols::{ExpandedKeyPair, Signature};
+use Error::{self, RoundMismatch};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Behavior {
+    Honest,
+    /// Reveals its `R` with a blind factor that doesn't open its commitment.
+    WrongOpening,
+    /// Holds its reveal back until it has the `R` of every other party, then reveals the `R`
+    /// that makes `R_tot` its own committed nonce, which doesn't open its commitment.
+    BiasedNonce,
+    /// Announces `rogue_public_key` as its public key, but signs with its own key.
+    RogueKey,
+    /// Commits to and reveals one `R` to the parties with odd indices and another one to those
+    /// with even indices, as a party broadcasting over point-to-point links can. Its messages
+    /// of these rounds are addressed to each party (`receiver: Some(j)`).
+    EquivocateR,
+}
+
+/// The key a rogue-key attacker announces: its own public key minus the other parties' keys, so
+/// that the plain sum of `public_keys` with it in slot `party_index` is its own public key.
+pub fn rogue_public_key(
+    keys: &ExpandedKeyPair,
+    public_keys: &[Point<Ed25519>],
+    party_index: u16,
+) -> Point<Ed25519> {
+    public_keys
+        .iter()
+        .enumerate()
+        .filter(|(j, _)| *j + 1 != usize::from(party_index))
+        .fold(keys.public_key.clone(), |rogue, (_, pk)| rogue - pk)
+}
+
+/// A proof of possession of `rogue_public_key` made with the attacker's own key, which doesn't
+/// verify.
+pub fn rogue_proof_of_possession(
+    keys: &ExpandedKeyPair,
+    public_keys: &[Point<Ed25519>],
+    party_index: u16,
+) -> ProofOfPossession {
+    ProofOfPossession {
+        public_key: rogue_public_key(keys, public_keys, party_index),
+        signature: ProofOfPossession::prove(keys).signature,
+    }
+}
+
+pub struct MaliciousParty {
+    inner: AggSigStateMachine,
+    behavior: Behavior,
+    queue: Vec<Msg<AggSigMessage>>,
+    // the `R` the other parties revealed, for `BiasedNonce`
+    Rs: Vec<Option<Point<Ed25519>>>,
+    held_reveal: Option<SignSecondMsg>,
+    // the commitment and reveal `EquivocateR` sends to the parties with even indices
+    second_nonce: Option<(SignFirstMsg, SignSecondMsg)>,
+}
+
+impl MaliciousParty {
+    /// `AggSigStateMachine::new` for a party with `behavior`. With `Behavior::RogueKey`,
+    /// `public_keys[party_index - 1]` is the announced `rogue_public_key` the other parties were
+    /// given, not `keys.public_key`.
+    pub fn new(
+        keys: ExpandedKeyPair,
+        public_keys: &[Point<Ed25519>],
+        party_index: u16,
+        message: &[u8],
+        behavior: Behavior,
+    ) -> Result<MaliciousParty, Error> {
+        let mut own_public_keys = public_keys.to_vec();
+        if behavior == Behavior::RogueKey {
+            let slot = own_public_keys
+                .get_mut(usize::from(party_index).wrapping_sub(1))
+                .ok_or(RoundMismatch)?;
+            *slot = keys.public_key.clone();
+        }
+        Ok(MaliciousParty {
+            inner: AggSigStateMachine::new(keys, &own_public_keys, party_index, message)?,
+            behavior,
+            queue: Vec::new(),
+            Rs: vec![None; public_keys.len()],
+            held_reveal: None,
+            second_nonce: None,
+        })
+    }
+
+    pub fn behavior(&self) -> Behavior {
+        self.behavior
+    }
+
+    // a cheating party is done once it has sent its partial signature
+    fn stopped(&self) -> bool {
+        self.behavior != Behavior::Honest && self.inner.current_round() >= 3
+    }
+
+    fn send(&mut self, receiver: Option<u16>, body: AggSigMessage) {
+        self.queue.push(Msg {
+            sender: self.party_ind(),
+            receiver,
+            body,
+        });
+    }
+
+    // every other party gets `odd` or `even` by the parity of its index
+    fn equivocate(&mut self, odd: AggSigMessage, even: AggSigMessage) {
+        let own = self.party_ind();
+        for j in (1..=self.parties()).filter(|j| *j != own) {
+            let body = if j % 2 == 1 {
+                odd.clone()
+            } else {
+                even.clone()
+            };
+            self.send(Some(j), body);
+        }
+    }
+
+    // moves the messages of the honest state machine to our queue, tampered with
+    fn forward_queue(&mut self) -> Result<(), Error> {
+        let msgs: Vec<_> = self.inner.message_queue().drain(..).collect();
+        for msg in msgs {
+            match (self.behavior, msg.body) {
+                (Behavior::WrongOpening, AggSigMessage::Reveal(mut reveal)) => {
+                    reveal.blind_factor = &reveal.blind_factor + BigInt::from(1);
+                    self.send(None, AggSigMessage::Reveal(reveal));
+                }
+                (Behavior::BiasedNonce, AggSigMessage::Reveal(reveal)) => {
+                    self.held_reveal = Some(reveal);
+                    self.release_biased_reveal();
+                }
+                (Behavior::EquivocateR, AggSigMessage::Commitment(commitment)) => {
+                    let (_, second_commitment, second_reveal) =
+                        aggsig::create_ephemeral_key_and_commit(&ExpandedKeyPair::create(), b"")?;
+                    self.second_nonce = Some((second_commitment.clone(), second_reveal));
+                    self.equivocate(
+                        AggSigMessage::Commitment(commitment),
+                        AggSigMessage::Commitment(second_commitment),
+                    );
+                }
+                (Behavior::EquivocateR, AggSigMessage::Reveal(reveal)) => {
+                    let (_, second_reveal) = self.second_nonce.clone().ok_or(RoundMismatch)?;
+                    self.equivocate(
+                        AggSigMessage::Reveal(reveal),
+                        AggSigMessage::Reveal(second_reveal),
+                    );
+                }
+                (_, body) => self.send(msg.receiver, body),
+            }
+        }
+        Ok(())
+    }
+
+    fn release_biased_reveal(&mut self) {
+        let own = usize::from(self.party_ind() - 1);
+        let others: Option<Vec<_>> = self
+            .Rs
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != own)
+            .map(|(_, R)| R.clone())
+            .collect();
+        if let Some(others) = others {
+            if let Some(mut reveal) = self.held_reveal.take() {
+                reveal.R = others.iter().fold(reveal.R, |R, other| R - other);
+                self.send(None, AggSigMessage::Reveal(reveal));
+            }
+        }
+    }
+}
+
+impl StateMachine for MaliciousParty {
+    type MessageBody = AggSigMessage;
+    type Output = Signature;
+    type Err = Error;
+
+    fn handle_incoming(&mut self, msg: Msg<AggSigMessage>) -> Result<(), Error> {
+        let sender = msg.sender;
+        let R = match &msg.body {
+            AggSigMessage::Reveal(reveal) => Some(reveal.R.clone()),
+            _ => None,
+        };
+        self.inner.handle_incoming(msg)?;
+        // the state machine accepted it, so `sender` is another party of the session
+        if R.is_some() {
+            self.Rs[usize::from(sender - 1)] = R;
+        }
+        if self.behavior == Behavior::BiasedNonce {
+            self.release_biased_reveal();
+        }
+        Ok(())
+    }
+
+    fn message_queue(&mut self) -> &mut Vec<Msg<AggSigMessage>> {
+        &mut self.queue
+    }
+
+    fn wants_to_proceed(&self) -> bool {
+        !self.stopped() && self.inner.wants_to_proceed()
+    }
+
+    fn proceed(&mut self) -> Result<(), Error> {
+        if self.stopped() {
+            return Err(RoundMismatch);
+        }
+        self.inner.proceed()?;
+        self.forward_queue()
+    }
+
+    fn current_round(&self) -> u16 {
+        self.inner.current_round()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.stopped() || self.inner.is_finished()
+    }
+
+    fn pick_output(&mut self) -> Option<Signature> {
+        self.inner.pick_output()
+    }
+
+    fn party_ind(&self) -> u16 {
+        self.inner.party_ind()
+    }
+
+    fn parties(&self) -> u16 {
+        self.inner.parties()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use curv::elliptic::curves::{Ed25519, Point};
+    use rand::Rng;
+
+    use super::{rogue_proof_of_possession, rogue_public_key, Behavior, MaliciousParty};
+    use protocols::aggsig::{self, AggSigMessage, AggregatedKey, ProofOfPossession, SignSecondMsg};
+    use protocols::rounds::echo::{EchoBroadcast, EchoError, EchoMessage};
+    use protocols::rounds::{Msg, StateMachine};
+    use protocols::simulate::{self, Fault, SimulationError};
+    use protocols::tests::deterministic_fast_rand;
+    use protocols::ExpandedKeyPair;
+    use Error;
+
+    const MESSAGE: &[u8] = b"malicious";
+
+    // three parties, the last one with `behavior`
+    fn session(behavior: Behavior) -> (Vec<ExpandedKeyPair>, Vec<Point<Ed25519>>) {
+        let mut rng = deterministic_fast_rand("test_malicious_party", None);
+        let keys: Vec<_> = (0..3)
+            .map(|_| ExpandedKeyPair::create_from_private_key(rng.gen()))
+            .collect();
+        let mut public_keys: Vec<_> = keys.iter().map(|k| k.public_key.clone()).collect();
+        if behavior == Behavior::RogueKey {
+            public_keys[2] = rogue_public_key(&keys[2], &public_keys, 3);
+        }
+        (keys, public_keys)
+    }
+
+    fn parties(behavior: Behavior) -> Vec<MaliciousParty> {
+        let (keys, public_keys) = session(behavior);
+        keys.into_iter()
+            .zip(1..)
+            .map(|(keys, i)| {
+                let behavior = if i == 3 { behavior } else { Behavior::Honest };
+                MaliciousParty::new(keys, &public_keys, i, MESSAGE, behavior).unwrap()
+            })
+            .collect()
+    }
+
+    fn run(behavior: Behavior) -> Result<(), SimulationError<Error>> {
+        simulate::run(&mut parties(behavior)).map(|_| ())
+    }
+
+    #[test]
+    fn test_honest_parties_sign() {
+        let mut parties = parties(Behavior::Honest);
+        assert!(simulate::run(&mut parties).is_ok());
+        let apk = parties[0].inner.aggregated_public_key().clone();
+        for party in &mut parties {
+            assert!(party.pick_output().unwrap().verify(MESSAGE, &apk).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_detect_cheating_openings() {
+        let aborted = Err(SimulationError::Party {
+            party: 1,
+            error: Error::InvalidCommitment { party: 3 },
+        });
+        assert_eq!(run(Behavior::WrongOpening), aborted);
+        assert_eq!(run(Behavior::BiasedNonce), aborted);
+
+        // the biased reveal makes R_tot the cheater's own committed nonce
+        let (mut commitment, mut reveals) = (None, vec![None; 3]);
+        let _ = simulate::run_with_faults(&mut parties(Behavior::BiasedNonce), |receiver, msg| {
+            match &msg.body {
+                AggSigMessage::Commitment(c) if msg.sender == 3 => commitment = Some(c.clone()),
+                AggSigMessage::Reveal(reveal) if receiver == 1 || msg.sender == 1 => {
+                    reveals[usize::from(msg.sender - 1)] = Some(reveal.clone())
+                }
+                _ => (),
+            }
+            Fault::Deliver
+        });
+        let reveals: Vec<_> = reveals.into_iter().map(Option::unwrap).collect();
+        let R_tot = SignSecondMsg {
+            R: reveals
+                .iter()
+                .fold(Point::zero(), |sum, reveal| sum + &reveal.R),
+            blind_factor: reveals[2].blind_factor.clone(),
+        };
+        assert!(aggsig::verify_commitment(&commitment.unwrap(), &R_tot).is_ok());
+    }
+
+    #[test]
+    fn test_detect_rogue_key() {
+        let (keys, public_keys) = session(Behavior::RogueKey);
+        // the plain sum of the keys is the attacker's key, the aggregated key isn't
+        let sum = public_keys.iter().fold(Point::zero(), |sum, pk| sum + pk);
+        assert_eq!(sum, keys[2].public_key);
+        assert_ne!(AggregatedKey::new(&public_keys).apk, sum);
+        assert_eq!(
+            run(Behavior::RogueKey),
+            Err(SimulationError::Party {
+                party: 1,
+                error: Error::InvalidPartialSignature { party: 3 },
+            })
+        );
+
+        let mut proofs: Vec<_> = keys[..2].iter().map(ProofOfPossession::prove).collect();
+        proofs.push(rogue_proof_of_possession(&keys[2], &public_keys, 3));
+        assert_eq!(
+            AggregatedKey::new_with_pop(&proofs).err(),
+            Some(Error::InvalidProof { party: 3 })
+        );
+    }
+
+    #[test]
+    fn test_detect_equivocation() {
+        // the honest parties don't accept point-to-point messages in broadcast rounds
+        assert_eq!(
+            run(Behavior::EquivocateR),
+            Err(SimulationError::Party {
+                party: 1,
+                error: Error::RoundMismatch,
+            })
+        );
+
+        // relayed as broadcasts the openings are valid, but parties 1 and 2 got different `R`
+        let relay = |_: u16, msg: &mut Msg<_>| {
+            msg.receiver = None;
+            Fault::Deliver
+        };
+        let mut split = parties(Behavior::EquivocateR);
+        let result = simulate::run_with_faults(&mut split, relay);
+        assert!(matches!(
+            result,
+            Err(SimulationError::Party {
+                error: Error::InvalidPartialSignature { .. },
+                ..
+            })
+        ));
+        let mut echoed: Vec<_> = parties(Behavior::EquivocateR)
+            .into_iter()
+            .map(EchoBroadcast::new)
+            .collect();
+        let relay = |_: u16, msg: &mut Msg<EchoMessage<AggSigMessage>>| {
+            msg.receiver = None;
+            Fault::Deliver
+        };
+        assert_eq!(
+            simulate::run_with_faults(&mut echoed, relay).map(|_| ()),
+            Err(SimulationError::Party {
+                party: 1,
+                error: EchoError::Inconsistent { round: 1, party: 2 },
+            })
+        );
+    }
+}
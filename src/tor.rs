@@ -0,0 +1,215 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! Tor onion service keys: the `hs_ed25519_secret_key` and `hs_ed25519_public_key` files of a v3
+//! onion service directory and its `.onion` address.
+//!
+//! Tor keeps the identity key expanded, the secret key file is a 32 byte header then the
+//! private scalar and the nonce prefix of an `ExpandedKeyPair`. To hold an onion service key
+//! multi-party, read the existing key with `ExpandedKeyPair::from_tor_secret_key` and split it
+//! with `keygen::deal`, the shares sign under the same public key and so the same address.
+//! The scalar is written reduced mod `l`, which Tor signs with like the clamped one it makes.
+
+use alloc::string::String;
+
+use curv::elliptic::curves::{Ed25519, Point, Scalar};
+use sha3::{Digest, Sha3_256};
+
+use base32;
+use protocols::{decode_point, ExpandedKeyPair, ExpandedPrivateKey};
+use Error::{self, InvalidKey};
+
+pub const SECRET_KEY_FILE_SIZE: usize = 96;
+pub const PUBLIC_KEY_FILE_SIZE: usize = 64;
+
+const SECRET_KEY_HEADER: &[u8; 32] = b"== ed25519v1-secret: type0 ==\0\0\0";
+const PUBLIC_KEY_HEADER: &[u8; 32] = b"== ed25519v1-public: type0 ==\0\0\0";
+const ONION_VERSION: u8 = 3;
+const ONION_SUFFIX: &str = ".onion";
+
+impl ExpandedKeyPair {
+    /// The contents of `hs_ed25519_secret_key`.
+    pub fn to_tor_secret_key(&self) -> [u8; SECRET_KEY_FILE_SIZE] {
+        let mut file = [0u8; SECRET_KEY_FILE_SIZE];
+        file[..32].copy_from_slice(SECRET_KEY_HEADER);
+        file[32..64].copy_from_slice(&self.expanded_private_key.private_key.to_bytes());
+        file[64..].copy_from_slice(&self.expanded_private_key.prefix.to_bytes());
+        file
+    }
+
+    /// Reads `hs_ed25519_secret_key`, fails with `InvalidKey` for another header or size.
+    /// The pair has no seed, Tor doesn't keep it.
+    pub fn from_tor_secret_key(file: &[u8]) -> Result<ExpandedKeyPair, Error> {
+        if file.len() != SECRET_KEY_FILE_SIZE || file[..32] != SECRET_KEY_HEADER[..] {
+            return Err(InvalidKey);
+        }
+        let private_key = read_scalar_le(&file[32..64]);
+        if private_key.is_zero() {
+            return Err(InvalidKey);
+        }
+        Ok(ExpandedKeyPair::from_expanded_private_key(
+            ExpandedPrivateKey {
+                prefix: read_scalar_le(&file[64..]),
+                private_key,
+            },
+        ))
+    }
+}
+
+/// The contents of `hs_ed25519_public_key`.
+pub fn public_key_to_tor(public_key: &Point<Ed25519>) -> [u8; PUBLIC_KEY_FILE_SIZE] {
+    let mut file = [0u8; PUBLIC_KEY_FILE_SIZE];
+    file[..32].copy_from_slice(PUBLIC_KEY_HEADER);
+    file[32..].copy_from_slice(&public_key.to_bytes(true));
+    file
+}
+
+/// Reads `hs_ed25519_public_key`, fails with `InvalidKey` for another header or size or a key
+/// that isn't a canonically encoded point.
+pub fn public_key_from_tor(file: &[u8]) -> Result<Point<Ed25519>, Error> {
+    if file.len() != PUBLIC_KEY_FILE_SIZE || file[..32] != PUBLIC_KEY_HEADER[..] {
+        return Err(InvalidKey);
+    }
+    decode_point(&file[32..]).ok_or(InvalidKey)
+}
+
+/// The v3 onion address of the service with `public_key`, as in the `hostname` file.
+pub fn onion_address(public_key: &Point<Ed25519>) -> String {
+    let public_key = public_key.to_bytes(true);
+    let mut address = [0u8; 35];
+    address[..32].copy_from_slice(&public_key);
+    address[32..34].copy_from_slice(&onion_checksum(&public_key));
+    address[34] = ONION_VERSION;
+    let mut address = base32::encode(&address).to_ascii_lowercase();
+    address.push_str(ONION_SUFFIX);
+    address
+}
+
+/// The public key of a v3 onion address (with or without `.onion`), fails with `InvalidKey`
+/// for another version, a bad checksum or a key that isn't a canonically encoded point.
+pub fn public_key_from_onion_address(address: &str) -> Result<Point<Ed25519>, Error> {
+    let address = address.strip_suffix(ONION_SUFFIX).unwrap_or(address);
+    if address.bytes().any(|c| c.is_ascii_uppercase()) {
+        return Err(InvalidKey);
+    }
+    let bytes = base32::decode(&address.to_ascii_uppercase()).ok_or(InvalidKey)?;
+    if bytes.len() != 35
+        || bytes[34] != ONION_VERSION
+        || bytes[32..34] != onion_checksum(&bytes[..32])
+    {
+        return Err(InvalidKey);
+    }
+    decode_point(&bytes[..32]).ok_or(InvalidKey)
+}
+
+// the first two bytes of SHA3-256(".onion checksum" || public key || version)
+fn onion_checksum(public_key: &[u8]) -> [u8; 2] {
+    let hash = Sha3_256::new()
+        .chain(b".onion checksum")
+        .chain(public_key)
+        .chain([ONION_VERSION])
+        .finalize();
+    [hash[0], hash[1]]
+}
+
+// curv's Ed25519 scalars are little endian and reduced mod `l` on decoding
+fn read_scalar_le(bytes: &[u8]) -> Scalar<Ed25519> {
+    Scalar::from_bytes(bytes).expect("the scalar is 32 bytes long, so can't fail")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocols::aggsig;
+    use protocols::keygen::{self, Parameters};
+    use protocols::tests::{deterministic_fast_rand, verify_dalek};
+
+    #[test]
+    fn test_tor_keys() {
+        let mut rng = deterministic_fast_rand("test_tor_keys", None);
+        let keypair = ExpandedKeyPair::create_rng(&mut rng);
+        let file = keypair.to_tor_secret_key();
+        assert_eq!(&file[..29], b"== ed25519v1-secret: type0 ==");
+        let read = ExpandedKeyPair::from_tor_secret_key(&file).unwrap();
+        assert_eq!(read.public_key, keypair.public_key);
+        assert_eq!(read.to_seed_bytes(), None);
+        assert_eq!(read.to_tor_secret_key()[..], file[..]);
+        // it signs like the pair it came from
+        let signature = aggsig::sign_single(b"onion", &read);
+        assert!(verify_dalek(&keypair.public_key, &signature, b"onion"));
+
+        let public_file = public_key_to_tor(&keypair.public_key);
+        assert_eq!(
+            public_key_from_tor(&public_file).unwrap(),
+            keypair.public_key
+        );
+        assert_eq!(
+            ExpandedKeyPair::from_tor_secret_key(&public_file).err(),
+            Some(InvalidKey)
+        );
+        assert_eq!(public_key_from_tor(&file[..64]), Err(InvalidKey));
+        let mut zero = file;
+        zero[32..64].copy_from_slice(&[0; 32]);
+        assert_eq!(
+            ExpandedKeyPair::from_tor_secret_key(&zero).err(),
+            Some(InvalidKey)
+        );
+
+        // the shares of a dealt onion key sign under its address
+        let shares = keygen::deal_rng(
+            &Parameters {
+                threshold: 1,
+                share_count: 3,
+            },
+            &read,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(shares.iter().all(|share| share.y == keypair.public_key));
+    }
+
+    #[test]
+    fn test_onion_address() {
+        // from rend-spec-v3 and torproject.org
+        for address in &[
+            "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion",
+            "2gzyxa5ihm7nsggfxnu52rck2vv4rvmdlkiu3zzui5du4xyclen53wid.onion",
+        ] {
+            let public_key = public_key_from_onion_address(address).unwrap();
+            assert_eq!(onion_address(&public_key), *address);
+        }
+        let mut rng = deterministic_fast_rand("test_onion_address", None);
+        let public_key = ExpandedKeyPair::create_rng(&mut rng).public_key;
+        let address = onion_address(&public_key);
+        assert_eq!(address.len(), 62);
+        assert_eq!(
+            public_key_from_onion_address(address.trim_end_matches(".onion")).unwrap(),
+            public_key
+        );
+        assert_eq!(
+            public_key_from_onion_address(&address.to_uppercase()),
+            Err(InvalidKey)
+        );
+        // another version byte, the checksum doesn't match either
+        let mut v4 = base32::decode(&address[..56].to_uppercase()).unwrap();
+        v4[34] = 4;
+        assert_eq!(
+            public_key_from_onion_address(&base32::encode(&v4).to_lowercase()),
+            Err(InvalidKey)
+        );
+    }
+}
@@ -0,0 +1,150 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! `wasm_bindgen` wrappers for aggsig, so a browser can act as one of the co-signers.
+//!
+//! Every key, message and signature crosses the boundary as a JSON string of the Rust type's
+//! serde encoding, so it can be forwarded to the other parties as is.
+//! The flow mirrors `protocols::aggsig`:
+//! `keygen` and `key_aggregation`, then round 1 `create_ephemeral_key_and_commit` (broadcast
+//! `sign_first_msg`), round 2 broadcast `sign_second_msg` and check everyone else's with
+//! `verify_commitment`, round 3 `partial_sign` and finally `add_signature_parts` and `verify`.
+//! The ephemeral key returned in round 1 is secret and must only be used for one signature.
+//!
+//! Build with `cargo rustc --lib --release --target wasm32-unknown-unknown --features wasm
+//! --crate-type cdylib` and run `wasm-bindgen` on the output.
+
+use curv::elliptic::curves::{Ed25519, Point};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json;
+use wasm_bindgen::prelude::*;
+
+use protocols::aggsig::{self, EphemeralKey, KeyAgg, SignFirstMsg, SignSecondMsg};
+use protocols::{ExpandedKeyPair, Signature};
+
+#[derive(Serialize, Deserialize)]
+struct EphemeralKeyAndCommitment {
+    ephemeral_key: EphemeralKey,
+    sign_first_msg: SignFirstMsg,
+    sign_second_msg: SignSecondMsg,
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, JsValue> {
+    serde_json::from_str(json).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, JsValue> {
+    serde_json::to_string(value).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Returns a new `ExpandedKeyPair`, this JSON holds the private key.
+#[wasm_bindgen]
+pub fn keygen() -> Result<String, JsValue> {
+    to_json(&ExpandedKeyPair::create())
+}
+
+/// `secret` is a 32 byte Ed25519 private key.
+#[wasm_bindgen]
+pub fn keygen_from_private_key(secret: &[u8]) -> Result<String, JsValue> {
+    if secret.len() != 32 {
+        return Err(JsValue::from_str("private key has to be 32 bytes long"));
+    }
+    let mut private_key = [0u8; 32];
+    private_key.copy_from_slice(secret);
+    to_json(&ExpandedKeyPair::create_from_private_key(private_key))
+}
+
+#[wasm_bindgen]
+pub fn public_key(keys: &str) -> Result<String, JsValue> {
+    let keys: ExpandedKeyPair = from_json(keys)?;
+    to_json(&keys.public_key)
+}
+
+/// `public_keys` is a JSON array of the public keys of all signers, in the same order for everyone.
+#[wasm_bindgen]
+pub fn key_aggregation(public_keys: &str, party_index: usize) -> Result<String, JsValue> {
+    let public_keys: Vec<Point<Ed25519>> = from_json(public_keys)?;
+    if party_index >= public_keys.len() {
+        return Err(JsValue::from_str("party index out of range"));
+    }
+    let key_agg = KeyAgg::key_aggregation_n_checked(&public_keys, party_index)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    to_json(&key_agg)
+}
+
+/// Round 1, returns `{ephemeral_key, sign_first_msg, sign_second_msg}`.
+#[wasm_bindgen]
+pub fn create_ephemeral_key_and_commit(keys: &str, message: &[u8]) -> Result<String, JsValue> {
+    let keys: ExpandedKeyPair = from_json(keys)?;
+    let (ephemeral_key, sign_first_msg, sign_second_msg) =
+        aggsig::create_ephemeral_key_and_commit(&keys, message)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    to_json(&EphemeralKeyAndCommitment {
+        ephemeral_key,
+        sign_first_msg,
+        sign_second_msg,
+    })
+}
+
+/// Round 2, checks that another party's revealed `R` matches its round 1 commitment.
+#[wasm_bindgen]
+pub fn verify_commitment(sign_first_msg: &str, sign_second_msg: &str) -> Result<bool, JsValue> {
+    let sign_first_msg: SignFirstMsg = from_json(sign_first_msg)?;
+    let sign_second_msg: SignSecondMsg = from_json(sign_second_msg)?;
+    Ok(aggsig::verify_commitment(&sign_first_msg, &sign_second_msg).is_ok())
+}
+
+/// Round 3, `Rs` is a JSON array of the `R` of every signer (including ours).
+#[wasm_bindgen]
+pub fn partial_sign(
+    ephemeral_key: &str,
+    keys: &str,
+    key_agg: &str,
+    Rs: &str,
+    message: &[u8],
+) -> Result<String, JsValue> {
+    let ephemeral_key: EphemeralKey = from_json(ephemeral_key)?;
+    let keys: ExpandedKeyPair = from_json(keys)?;
+    let key_agg: KeyAgg = from_json(key_agg)?;
+    let Rs: Vec<Point<Ed25519>> = from_json(Rs)?;
+    let R_tot = aggsig::get_R_tot(&Rs).ok_or_else(|| JsValue::from_str("no R given"))?;
+    to_json(&aggsig::partial_sign(
+        ephemeral_key,
+        &keys,
+        &key_agg.hash,
+        &R_tot,
+        &key_agg.apk,
+        message,
+    ))
+}
+
+/// `partial_sigs` is a JSON array of the partial signatures of all signers.
+#[wasm_bindgen]
+pub fn add_signature_parts(partial_sigs: &str) -> Result<String, JsValue> {
+    let partial_sigs: Vec<Signature> = from_json(partial_sigs)?;
+    let signature = aggsig::add_signature_parts(&partial_sigs)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    to_json(&signature)
+}
+
+#[wasm_bindgen]
+pub fn verify(signature: &str, message: &[u8], public_key: &str) -> Result<bool, JsValue> {
+    let signature: Signature = from_json(signature)?;
+    let public_key: Point<Ed25519> = from_json(public_key)?;
+    Ok(signature.verify(message, &public_key).is_ok())
+}
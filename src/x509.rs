@@ -0,0 +1,587 @@
+#![allow(non_snake_case)]
+/*
+    Multisig ed25519
+
+    Copyright 2018 by Kzen Networks
+
+    This file is part of Multisig Schnorr library
+    (https://github.com/KZen-networks/multisig-schnorr)
+
+    Multisig Schnorr is free software: you can redistribute
+    it and/or modify it under the terms of the GNU General Public
+    License as published by the Free Software Foundation, either
+    version 3 of the License, or (at your option) any later version.
+
+    @license GPL-3.0+ <https://github.com/KZen-networks/multi-party-ed25519/blob/master/LICENSE>
+*/
+
+//! X.509 certificates (RFC 5280) signed with Ed25519 (RFC 8410), so the key of an internal
+//! CA can be an aggregated key held by its co-signers.
+//!
+//! `CertificateParams` are encoded to the DER `TBSCertificate` the CA signs, `sign` runs the
+//! signing protocol on it and returns the DER certificate. `RemoteKeyPair` is the CA key with
+//! the methods of rcgen's `RemoteKeyPair` trait (the raw 32 byte public key and a 64 byte
+//! signature of a message), so it can back rcgen's certificate generation with `PKCS_ED25519`
+//! as the algorithm.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use curv::elliptic::curves::{Ed25519, Point};
+use pkcs8_der::der::asn1::{
+    AnyRef, BitStringRef, GeneralizedTime, Ia5StringRef, OctetStringRef, PrintableStringRef,
+    UIntRef, UtcTime, Utf8StringRef,
+};
+use pkcs8_der::der::{Decode, Encode, Reader, SliceReader, Tag, TagNumber, Tagged};
+use pkcs8_der::ObjectIdentifier;
+use rand::{thread_rng, CryptoRng, Rng, RngCore};
+use sha2::{Digest, Sha256};
+
+use pkcs8::{decode_pem, encode_pem, public_key_from_der, public_key_to_der};
+use protocols::Signature;
+use Error::{self, InvalidKey, InvalidMessage, InvalidSig};
+
+const ED25519_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+const SUBJECT_KEY_IDENTIFIER: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.14");
+const KEY_USAGE: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.15");
+const SUBJECT_ALT_NAME: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.17");
+const BASIC_CONSTRAINTS: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.19");
+const AUTHORITY_KEY_IDENTIFIER: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.35");
+const EXTENDED_KEY_USAGE: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.37");
+const CERTIFICATE_LABEL: &str = "CERTIFICATE";
+// 9999-12-31T23:59:59Z, "no well-defined expiration date" in RFC 5280
+const NO_EXPIRATION: u64 = 253_402_300_799;
+
+/// The attributes of a distinguished name, countries are PrintableStrings and the others
+/// UTF8Strings.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DnType {
+    CommonName,
+    Country,
+    Locality,
+    StateOrProvince,
+    Organization,
+    OrganizationalUnit,
+}
+
+impl DnType {
+    fn oid(self) -> ObjectIdentifier {
+        ObjectIdentifier::new_unwrap(match self {
+            DnType::CommonName => "2.5.4.3",
+            DnType::Country => "2.5.4.6",
+            DnType::Locality => "2.5.4.7",
+            DnType::StateOrProvince => "2.5.4.8",
+            DnType::Organization => "2.5.4.10",
+            DnType::OrganizationalUnit => "2.5.4.11",
+        })
+    }
+}
+
+/// A name with one attribute per relative distinguished name, in order.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct DistinguishedName(pub Vec<(DnType, String)>);
+
+impl DistinguishedName {
+    pub fn new() -> DistinguishedName {
+        DistinguishedName::default()
+    }
+
+    pub fn push(&mut self, attribute: DnType, value: &str) {
+        self.0.push((attribute, String::from(value)));
+    }
+
+    fn to_der(&self) -> Result<Vec<u8>, Error> {
+        let mut rdns = Vec::new();
+        for (attribute, value) in &self.0 {
+            let value = match attribute {
+                DnType::Country => PrintableStringRef::new(value).and_then(|s| s.to_vec()),
+                _ => Utf8StringRef::new(value).and_then(|s| s.to_vec()),
+            }
+            .map_err(|_| InvalidMessage)?;
+            let attribute = sequence(&[&encode(attribute.oid())?, &value])?;
+            rdns.extend_from_slice(&tlv(Tag::Set, &attribute)?);
+        }
+        tlv(Tag::Sequence, &rdns)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyUsage {
+    DigitalSignature,
+    ContentCommitment,
+    KeyEncipherment,
+    DataEncipherment,
+    KeyAgreement,
+    KeyCertSign,
+    CrlSign,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExtendedKeyUsage {
+    ServerAuth,
+    ClientAuth,
+    CodeSigning,
+}
+
+impl ExtendedKeyUsage {
+    fn oid(self) -> ObjectIdentifier {
+        ObjectIdentifier::new_unwrap(match self {
+            ExtendedKeyUsage::ServerAuth => "1.3.6.1.5.5.7.3.1",
+            ExtendedKeyUsage::ClientAuth => "1.3.6.1.5.5.7.3.2",
+            ExtendedKeyUsage::CodeSigning => "1.3.6.1.5.5.7.3.3",
+        })
+    }
+}
+
+/// The fields of a certificate, times are seconds since the epoch.
+/// Every certificate has subject and authority key identifiers (the first 160 bits of the
+/// SHA-256 of the key, RFC 7093), the other extensions are only added if they are set.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CertificateParams {
+    /// Big endian, positive and at most 20 bytes long.
+    pub serial_number: Vec<u8>,
+    pub subject: DistinguishedName,
+    pub not_before: u64,
+    pub not_after: u64,
+    /// A CA with an optional path length constraint, in a critical basic constraints extension.
+    pub is_ca: Option<Option<u8>>,
+    /// A critical key usage extension.
+    pub key_usages: Vec<KeyUsage>,
+    pub extended_key_usages: Vec<ExtendedKeyUsage>,
+    /// The DNS names of the subject alternative name extension.
+    pub dns_names: Vec<String>,
+}
+
+impl CertificateParams {
+    pub fn new(subject: DistinguishedName) -> CertificateParams {
+        CertificateParams::new_rng(subject, &mut thread_rng())
+    }
+
+    /// An end entity certificate of `subject` valid from the epoch without an expiration date,
+    /// `rng` is used for the 16 byte serial number.
+    pub fn new_rng(
+        subject: DistinguishedName,
+        rng: &mut (impl CryptoRng + RngCore),
+    ) -> CertificateParams {
+        let mut serial_number: [u8; 16] = rng.gen();
+        // positive and nonzero
+        serial_number[0] = (serial_number[0] & 0x7f) | 0x40;
+        CertificateParams {
+            serial_number: Vec::from(&serial_number[..]),
+            subject,
+            not_before: 0,
+            not_after: NO_EXPIRATION,
+            is_ca: None,
+            key_usages: Vec::new(),
+            extended_key_usages: Vec::new(),
+            dns_names: Vec::new(),
+        }
+    }
+
+    /// The DER `TBSCertificate` of `subject_key` issued by `issuer` with `issuer_key`.
+    /// Fails with `InvalidMessage` for a serial number that isn't positive or longer than 20
+    /// bytes, a country that isn't a PrintableString, a DNS name that isn't ASCII or a time
+    /// after 9999.
+    pub fn tbs_der(
+        &self,
+        subject_key: &Point<Ed25519>,
+        issuer: &DistinguishedName,
+        issuer_key: &Point<Ed25519>,
+    ) -> Result<Vec<u8>, Error> {
+        if self.serial_number.len() > 20 || self.serial_number.iter().all(|b| *b == 0) {
+            return Err(InvalidMessage);
+        }
+        let serial_number = UIntRef::new(&self.serial_number)
+            .and_then(|n| n.to_vec())
+            .map_err(|_| InvalidMessage)?;
+        let validity = sequence(&[&time(self.not_before)?, &time(self.not_after)?])?;
+        let extensions = tlv(
+            context(3),
+            &tlv(Tag::Sequence, &self.extensions(subject_key, issuer_key)?)?,
+        )?;
+        sequence(&[
+            // version 3
+            &tlv(context(0), &encode(2u8)?)?,
+            &serial_number,
+            &algorithm()?,
+            &issuer.to_der()?,
+            &validity,
+            &self.subject.to_der()?,
+            &public_key_to_der(subject_key)?,
+            &extensions,
+        ])
+    }
+
+    /// The DER certificate of `subject_key` issued by `issuer` with `issuer_key`, `sign` runs
+    /// the signing protocol on the `tbs_der`. Fails like `tbs_der` and with `InvalidSig` if the
+    /// signature of `sign` doesn't verify.
+    pub fn sign(
+        &self,
+        subject_key: &Point<Ed25519>,
+        issuer: &DistinguishedName,
+        issuer_key: &Point<Ed25519>,
+        sign: impl FnOnce(&[u8]) -> Result<Signature, Error>,
+    ) -> Result<Vec<u8>, Error> {
+        let tbs = self.tbs_der(subject_key, issuer, issuer_key)?;
+        let signature = sign(&tbs)?;
+        signature.verify(&tbs, issuer_key).map_err(|_| InvalidSig)?;
+        let signature = BitStringRef::new(0, &signature.to_bytes())
+            .and_then(|s| s.to_vec())
+            .map_err(|_| InvalidSig)?;
+        sequence(&[&tbs, &algorithm()?, &signature])
+    }
+
+    /// `sign` of a certificate issued by its own subject, e.g. a CA's root certificate.
+    pub fn self_signed(
+        &self,
+        key: &Point<Ed25519>,
+        sign: impl FnOnce(&[u8]) -> Result<Signature, Error>,
+    ) -> Result<Vec<u8>, Error> {
+        self.sign(key, &self.subject, key, sign)
+    }
+
+    fn extensions(
+        &self,
+        subject_key: &Point<Ed25519>,
+        issuer_key: &Point<Ed25519>,
+    ) -> Result<Vec<u8>, Error> {
+        let mut extensions = Vec::new();
+        if let Some(path_len) = self.is_ca {
+            let mut constraints = encode(true)?;
+            if let Some(path_len) = path_len {
+                constraints.extend_from_slice(&encode(path_len)?);
+            }
+            extensions.extend_from_slice(&extension(
+                BASIC_CONSTRAINTS,
+                true,
+                &tlv(Tag::Sequence, &constraints)?,
+            )?);
+        }
+        if !self.key_usages.is_empty() {
+            let bits = self
+                .key_usages
+                .iter()
+                .fold(0u8, |bits, usage| bits | (0x80 >> *usage as u8));
+            // DER drops the trailing zero bits
+            let usage = BitStringRef::new(bits.trailing_zeros() as u8, &[bits])
+                .and_then(|s| s.to_vec())
+                .map_err(|_| InvalidMessage)?;
+            extensions.extend_from_slice(&extension(KEY_USAGE, true, &usage)?);
+        }
+        if !self.extended_key_usages.is_empty() {
+            let mut usages = Vec::new();
+            for usage in &self.extended_key_usages {
+                usages.extend_from_slice(&encode(usage.oid())?);
+            }
+            extensions.extend_from_slice(&extension(
+                EXTENDED_KEY_USAGE,
+                false,
+                &tlv(Tag::Sequence, &usages)?,
+            )?);
+        }
+        if !self.dns_names.is_empty() {
+            let mut names = Vec::new();
+            for name in &self.dns_names {
+                let name = Ia5StringRef::new(name).map_err(|_| InvalidMessage)?;
+                // dNSName [2] IMPLICIT IA5String
+                names.extend_from_slice(&tlv(
+                    Tag::ContextSpecific {
+                        constructed: false,
+                        number: TagNumber::N2,
+                    },
+                    name.as_bytes(),
+                )?);
+            }
+            extensions.extend_from_slice(&extension(
+                SUBJECT_ALT_NAME,
+                false,
+                &tlv(Tag::Sequence, &names)?,
+            )?);
+        }
+        extensions.extend_from_slice(&extension(
+            SUBJECT_KEY_IDENTIFIER,
+            false,
+            &encode(
+                OctetStringRef::new(&key_identifier(subject_key)).map_err(|_| InvalidMessage)?,
+            )?,
+        )?);
+        // keyIdentifier [0] IMPLICIT OCTET STRING
+        let authority_key_identifier = tlv(
+            Tag::ContextSpecific {
+                constructed: false,
+                number: TagNumber::N0,
+            },
+            &key_identifier(issuer_key),
+        )?;
+        extensions.extend_from_slice(&extension(
+            AUTHORITY_KEY_IDENTIFIER,
+            false,
+            &tlv(Tag::Sequence, &authority_key_identifier)?,
+        )?);
+        Ok(extensions)
+    }
+}
+
+/// A CA key whose signatures come from a multi-party signing session, with the methods of
+/// rcgen's `RemoteKeyPair`.
+pub struct RemoteKeyPair<F: Fn(&[u8]) -> Result<Signature, Error>> {
+    public_key: Point<Ed25519>,
+    public_key_bytes: [u8; 32],
+    sign: F,
+}
+
+impl<F: Fn(&[u8]) -> Result<Signature, Error>> RemoteKeyPair<F> {
+    pub fn new(public_key: Point<Ed25519>, sign: F) -> RemoteKeyPair<F> {
+        let mut public_key_bytes = [0u8; 32];
+        public_key_bytes.copy_from_slice(&public_key.to_bytes(true));
+        RemoteKeyPair {
+            public_key,
+            public_key_bytes,
+            sign,
+        }
+    }
+
+    /// The 32 byte public key.
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key_bytes
+    }
+
+    /// The 64 byte signature of `message`, fails with `InvalidSig` if the signature of the
+    /// session doesn't verify.
+    pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        let signature = (self.sign)(message)?;
+        signature
+            .verify(message, &self.public_key)
+            .map_err(|_| InvalidSig)?;
+        Ok(Vec::from(&signature.to_bytes()[..]))
+    }
+
+    /// `CertificateParams::sign` with this key as the issuer key.
+    pub fn issue(
+        &self,
+        params: &CertificateParams,
+        subject_key: &Point<Ed25519>,
+        issuer: &DistinguishedName,
+    ) -> Result<Vec<u8>, Error> {
+        params.sign(subject_key, issuer, &self.public_key, &self.sign)
+    }
+}
+
+/// Checks the signature of a DER certificate under `issuer_key` and returns its subject key.
+/// Fails with `InvalidKey` for anything but an Ed25519 certificate of an Ed25519 key and with
+/// `InvalidSig` if the signature doesn't verify.
+pub fn verify_certificate(
+    der: &[u8],
+    issuer_key: &Point<Ed25519>,
+) -> Result<Point<Ed25519>, Error> {
+    let certificate = AnyRef::from_der(der).map_err(|_| InvalidKey)?;
+    if certificate.tag() != Tag::Sequence {
+        return Err(InvalidKey);
+    }
+    let mut reader = SliceReader::new(certificate.value()).map_err(|_| InvalidKey)?;
+    let tbs: AnyRef = reader.decode().map_err(|_| InvalidKey)?;
+    let signature_algorithm: AnyRef = reader.decode().map_err(|_| InvalidKey)?;
+    let signature: BitStringRef = reader.decode().map_err(|_| InvalidKey)?;
+    if !reader.is_finished()
+        || tbs.tag() != Tag::Sequence
+        || signature_algorithm.to_vec().ok() != Some(algorithm()?)
+    {
+        return Err(InvalidKey);
+    }
+    let subject_key = subject_public_key(tbs.value())?;
+    let signature = signature
+        .as_bytes()
+        .ok_or(InvalidSig)
+        .and_then(|bytes| Signature::from_bytes(bytes).map_err(|_| InvalidSig))?;
+    let tbs = tbs.to_vec().map_err(|_| InvalidKey)?;
+    signature.verify(&tbs, issuer_key).map_err(|_| InvalidSig)?;
+    Ok(subject_key)
+}
+
+pub fn certificate_to_pem(der: &[u8]) -> String {
+    encode_pem(CERTIFICATE_LABEL, der)
+}
+
+pub fn certificate_from_pem(pem: &str) -> Result<Vec<u8>, Error> {
+    decode_pem(CERTIFICATE_LABEL, pem)
+}
+
+// the key of the SubjectPublicKeyInfo, the 7th field of the TBSCertificate
+fn subject_public_key(tbs: &[u8]) -> Result<Point<Ed25519>, Error> {
+    let mut reader = SliceReader::new(tbs).map_err(|_| InvalidKey)?;
+    for _ in 0..6 {
+        let _: AnyRef = reader.decode().map_err(|_| InvalidKey)?;
+    }
+    let spki: AnyRef = reader.decode().map_err(|_| InvalidKey)?;
+    public_key_from_der(&spki.to_vec().map_err(|_| InvalidKey)?)
+}
+
+fn algorithm() -> Result<Vec<u8>, Error> {
+    sequence(&[&encode(ED25519_OID)?])
+}
+
+// the first 160 bits of the SHA-256 of the key, method 1 of RFC 7093
+fn key_identifier(public_key: &Point<Ed25519>) -> [u8; 20] {
+    let mut identifier = [0u8; 20];
+    identifier.copy_from_slice(&Sha256::digest(&public_key.to_bytes(true))[..20]);
+    identifier
+}
+
+// UTCTime until 2049, GeneralizedTime after as RFC 5280 requires
+fn time(seconds: u64) -> Result<Vec<u8>, Error> {
+    let since_epoch = Duration::from_secs(seconds);
+    UtcTime::from_unix_duration(since_epoch)
+        .and_then(|time| time.to_vec())
+        .or_else(|_| {
+            GeneralizedTime::from_unix_duration(since_epoch).and_then(|time| time.to_vec())
+        })
+        .map_err(|_| InvalidMessage)
+}
+
+fn extension(oid: ObjectIdentifier, critical: bool, value: &[u8]) -> Result<Vec<u8>, Error> {
+    let value = encode(OctetStringRef::new(value).map_err(|_| InvalidMessage)?)?;
+    if critical {
+        sequence(&[&encode(oid)?, &encode(true)?, &value])
+    } else {
+        // DER leaves out the default `critical` of false
+        sequence(&[&encode(oid)?, &value])
+    }
+}
+
+fn context(number: u8) -> Tag {
+    Tag::ContextSpecific {
+        constructed: true,
+        number: TagNumber::new(number),
+    }
+}
+
+fn sequence(fields: &[&[u8]]) -> Result<Vec<u8>, Error> {
+    tlv(Tag::Sequence, &fields.concat())
+}
+
+fn tlv(tag: Tag, value: &[u8]) -> Result<Vec<u8>, Error> {
+    AnyRef::new(tag, value)
+        .and_then(|any| any.to_vec())
+        .map_err(|_| InvalidMessage)
+}
+
+fn encode(value: impl Encode) -> Result<Vec<u8>, Error> {
+    value.to_vec().map_err(|_| InvalidMessage)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use local;
+    use protocols::aggsig::{self, AggregatedKey};
+    use protocols::tests::deterministic_fast_rand;
+    use protocols::ExpandedKeyPair;
+
+    fn name(common_name: &str) -> DistinguishedName {
+        let mut name = DistinguishedName::new();
+        name.push(DnType::Country, "CH");
+        name.push(DnType::Organization, "Example");
+        name.push(DnType::CommonName, common_name);
+        name
+    }
+
+    #[test]
+    fn test_x509_aggregated_ca() {
+        let mut rng = deterministic_fast_rand("test_x509_aggregated_ca", None);
+        let session_rng = RefCell::new(deterministic_fast_rand("test_x509_session", None));
+        let signers = [
+            ExpandedKeyPair::create_rng(&mut rng),
+            ExpandedKeyPair::create_rng(&mut rng),
+        ];
+        let agg_key =
+            AggregatedKey::new(&[signers[0].public_key.clone(), signers[1].public_key.clone()]);
+        let ca = RemoteKeyPair::new(agg_key.apk.clone(), |message: &[u8]| {
+            local::sign_rng(message, &signers, &agg_key, &mut *session_rng.borrow_mut())
+        });
+        assert_eq!(ca.public_key(), &*agg_key.apk.to_bytes(true));
+
+        let ca_name = name("Internal CA");
+        let mut root = CertificateParams::new_rng(ca_name.clone(), &mut rng);
+        root.is_ca = Some(Some(0));
+        root.key_usages = alloc::vec![KeyUsage::KeyCertSign, KeyUsage::CrlSign];
+        let root_der = ca.issue(&root, &agg_key.apk, &ca_name).unwrap();
+        assert_eq!(
+            verify_certificate(&root_der, &agg_key.apk).unwrap(),
+            agg_key.apk
+        );
+        // keyCertSign and cRLSign, with one unused bit
+        let key_usage = [0x04, 0x04, 0x03, 0x02, 0x01, 0x06];
+        assert!(root_der.windows(6).any(|bytes| bytes == key_usage));
+
+        let server = ExpandedKeyPair::create_rng(&mut rng).public_key;
+        let mut leaf = CertificateParams::new_rng(name("internal.example"), &mut rng);
+        leaf.not_before = 1_700_000_000;
+        // GeneralizedTime in 2100
+        leaf.not_after = 4_102_444_800;
+        leaf.key_usages = alloc::vec![KeyUsage::DigitalSignature];
+        leaf.extended_key_usages = alloc::vec![ExtendedKeyUsage::ServerAuth];
+        leaf.dns_names = alloc::vec![String::from("internal.example")];
+        let leaf_der = ca.issue(&leaf, &server, &ca_name).unwrap();
+        assert_eq!(verify_certificate(&leaf_der, &agg_key.apk).unwrap(), server);
+        assert_eq!(verify_certificate(&leaf_der, &server), Err(InvalidSig));
+        assert!(leaf_der
+            .windows(17)
+            .any(|bytes| bytes == b"\x18\x0f21000101000000Z"));
+
+        let pem = certificate_to_pem(&leaf_der);
+        assert!(pem.starts_with("-----BEGIN CERTIFICATE-----\n"));
+        assert_eq!(certificate_from_pem(&pem).unwrap(), leaf_der);
+    }
+
+    #[test]
+    fn test_x509_reject_invalid() {
+        let mut rng = deterministic_fast_rand("test_x509_reject_invalid", None);
+        let keypair = ExpandedKeyPair::create_rng(&mut rng);
+        let other = ExpandedKeyPair::create_rng(&mut rng);
+        let sign = |message: &[u8]| Ok(aggsig::sign_single(message, &keypair));
+        let mut params = CertificateParams::new_rng(name("root"), &mut rng);
+        let der = params.self_signed(&keypair.public_key, sign).unwrap();
+        assert_eq!(
+            verify_certificate(&der, &keypair.public_key).unwrap(),
+            keypair.public_key
+        );
+        assert_eq!(
+            verify_certificate(&der[..der.len() - 1], &keypair.public_key),
+            Err(InvalidKey)
+        );
+        let mut tampered = der.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert_eq!(
+            verify_certificate(&tampered, &keypair.public_key),
+            Err(InvalidSig)
+        );
+
+        // a session signing with another key
+        assert_eq!(
+            params.self_signed(&keypair.public_key, |message| Ok(aggsig::sign_single(
+                message, &other
+            ))),
+            Err(InvalidSig)
+        );
+        let wrong_ca = RemoteKeyPair::new(keypair.public_key.clone(), |message: &[u8]| {
+            Ok(aggsig::sign_single(message, &other))
+        });
+        assert_eq!(wrong_ca.sign(b"tbs"), Err(InvalidSig));
+
+        params.serial_number = alloc::vec![0; 4];
+        assert_eq!(
+            params.tbs_der(&keypair.public_key, &name("root"), &keypair.public_key),
+            Err(InvalidMessage)
+        );
+        params.serial_number = alloc::vec![1];
+        params.subject.push(DnType::Country, "Ü");
+        assert_eq!(
+            params.tbs_der(&keypair.public_key, &name("root"), &keypair.public_key),
+            Err(InvalidMessage)
+        );
+    }
+}